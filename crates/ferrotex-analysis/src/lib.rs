@@ -1,5 +1,9 @@
 use serde::{Deserialize, Serialize};
 
+pub mod macros;
+
+use macros::MacroDefinition;
+
 /// An abstract value representing a set of possible concrete TeX values.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AbstractValue {
@@ -30,6 +34,17 @@ pub struct AbstractState {
     pub registers: std::collections::HashMap<String, AbstractValue>,
 }
 
+/// One macro expansion performed by [`AbstractMachine::step`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Expansion {
+    /// The expanded control sequence.
+    pub name: String,
+    /// The arguments it read, the default included.
+    pub arguments: Vec<Vec<AbstractValue>>,
+    /// The tokens that replaced it and its arguments on the input.
+    pub replacement: Vec<AbstractValue>,
+}
+
 /// A simplified abstract machine for analyzing TeX macro behavior.
 pub struct AbstractMachine {
     pub state: AbstractState,
@@ -37,6 +52,14 @@ pub struct AbstractMachine {
     pub max_depth: usize,
     /// Stack of currently expanding control sequences to detect cycles.
     pub call_stack: Vec<String>,
+    /// Macros expanded with their real definitions, by name.
+    pub macros: std::collections::HashMap<String, MacroDefinition>,
+    /// Number of macros expanded so far.
+    pub expansions: usize,
+    /// Expansions after which a macro is assumed to recurse forever.
+    pub max_expansions: usize,
+    /// The expansion the last [`step`](Self::step) performed, if it expanded a macro.
+    pub last_expansion: Option<Expansion>,
 }
 
 impl Default for AbstractMachine {
@@ -52,18 +75,49 @@ impl AbstractMachine {
             expansion_depth: 0,
             max_depth: 1000,
             call_stack: Vec::new(),
+            macros: std::collections::HashMap::new(),
+            expansions: 0,
+            max_expansions: 10_000,
+            last_expansion: None,
         }
     }
 
+    /// Adds a macro definition, replacing any of the same name.
+    pub fn define(&mut self, definition: MacroDefinition) {
+        self.macros.insert(definition.name.clone(), definition);
+    }
+
+    /// Puts `tokens` in front of the input, to be read first to last.
+    pub fn push_input(&mut self, tokens: &[AbstractValue]) {
+        self.state.input_stack.extend(tokens.iter().rev().cloned());
+    }
+
     /// Steps the abstract machine one abstract instruction.
     pub fn step(&mut self) -> Option<AbstractValue> {
         if self.expansion_depth > self.max_depth {
             return Some(AbstractValue::AnalysisError("Maximum recursion depth exceeded".to_string()));
         }
 
+        self.last_expansion = None;
         // Pop the next token from input
         if let Some(token) = self.state.input_stack.pop() {
             match &token {
+                AbstractValue::ControlSequence(name) if self.macros.contains_key(name) => {
+                    if self.expansions >= self.max_expansions {
+                        return Some(AbstractValue::AnalysisError(format!(
+                            "{} expansions without finishing; {} may expand to itself forever",
+                            self.max_expansions, name
+                        )));
+                    }
+                    self.expansions += 1;
+                    match self.expand_macro(name) {
+                        Ok(expansion) => {
+                            self.last_expansion = Some(expansion);
+                            Some(token)
+                        }
+                        Err(message) => Some(AbstractValue::AnalysisError(message)),
+                    }
+                }
                 AbstractValue::ControlSequence(name) => {
                     if self.call_stack.contains(name) {
                         return Some(AbstractValue::AnalysisError(format!("Infinite recursion detected in control sequence: {}", name)));
@@ -91,6 +145,17 @@ impl AbstractMachine {
         }
     }
 
+    /// Reads the arguments of the macro `name` from the input and replaces them by its body.
+    fn expand_macro(&mut self, name: &str) -> Result<Expansion, String> {
+        let definition = &self.macros[name];
+        let input: Vec<AbstractValue> = self.state.input_stack.iter().rev().cloned().collect();
+        let (arguments, used) = macros::read_arguments(definition, &input)?;
+        let replacement = macros::substitute(definition, &arguments);
+        self.state.input_stack.truncate(self.state.input_stack.len() - used);
+        self.push_input(&replacement);
+        Ok(Expansion { name: name.to_string(), arguments, replacement })
+    }
+
     fn execute_control_sequence(&mut self, name: &str) {
         match name {
             "\\def" | "\\newcommand" => {
@@ -191,6 +256,37 @@ mod tests {
         assert_eq!(result, Some(AbstractValue::Token("a".to_string())));
     }
 
+    #[test]
+    fn test_macro_expansion() {
+        let mut machine = AbstractMachine::new();
+        for definition in macros::harvest(&macros::tokenize("\\newcommand{\\pair}[2]{(#1,\\sq{#2})}\\def\\sq#1{#1^2}")) {
+            machine.define(definition);
+        }
+        machine.push_input(&macros::tokenize("\\pair a{b}"));
+        let mut expanded = Vec::new();
+        let mut output = Vec::new();
+        while let Some(token) = machine.step() {
+            match machine.last_expansion.take() {
+                Some(expansion) => expanded.push(expansion.name),
+                None => output.push(token),
+            }
+        }
+        assert_eq!(expanded, vec!["\\pair", "\\sq"]);
+        assert_eq!(macros::render(&output), "(a,b^2)");
+    }
+
+    #[test]
+    fn test_recursive_macro() {
+        let mut machine = AbstractMachine::new();
+        machine.max_expansions = 10;
+        for definition in macros::harvest(&macros::tokenize("\\def\\loop{x\\loop}")) {
+            machine.define(definition);
+        }
+        machine.push_input(&macros::tokenize("\\loop"));
+        let error = std::iter::from_fn(|| machine.step()).find(|token| matches!(token, AbstractValue::AnalysisError(_)));
+        assert!(matches!(error, Some(AbstractValue::AnalysisError(message)) if message.contains("\\loop may expand to itself")));
+    }
+
     #[test]
     fn test_abstract_empty_stack() {
         let mut machine = AbstractMachine::new();
@@ -0,0 +1,473 @@
+//! Macro definitions for the [`AbstractMachine`](crate::AbstractMachine): tokenizing TeX
+//! source, harvesting `\newcommand` and `\def` definitions, and reading macro arguments.
+
+use crate::AbstractValue;
+use serde::{Deserialize, Serialize};
+
+/// A macro the document defines.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MacroDefinition {
+    /// The control sequence, with its backslash.
+    pub name: String,
+    /// What the macro reads before its replacement: `#1#2` for `\newcommand{\foo}[2]`, or the
+    /// delimited parameters of `\def\foo#1.#2\end`.
+    pub parameter_text: Vec<AbstractValue>,
+    /// The default of the first argument, which is then optional and given in brackets.
+    pub default: Option<Vec<AbstractValue>>,
+    /// The replacement text, with `#1`..`#9` standing for the arguments.
+    pub body: Vec<AbstractValue>,
+}
+
+/// Commands defining a macro like `\newcommand{\foo}[2][x]{body}`.
+const NEWCOMMANDS: &[&str] = &["\\newcommand", "\\renewcommand", "\\providecommand", "\\DeclareRobustCommand"];
+
+/// Commands defining a macro like `\def\foo#1#2{body}`.
+const DEFS: &[&str] = &["\\def", "\\gdef", "\\edef", "\\xdef"];
+
+/// Splits TeX source into tokens: control sequences, `#`, braces and single characters.
+///
+/// Comments are dropped, spaces after control words skipped and runs of white space
+/// collapsed into one space, or into `\par` if they span an empty line.
+pub fn tokenize(text: &str) -> Vec<AbstractValue> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let mut name = String::from("\\");
+                while let Some(&next) = chars.peek()
+                    && (next.is_ascii_alphabetic() || next == '@')
+                {
+                    name.push(next);
+                    chars.next();
+                }
+                if name.len() > 1 {
+                    while chars.next_if(|next| *next == ' ' || *next == '\t').is_some() {}
+                    // The end of the line is skipped too, unless an empty line follows.
+                    let mut next_line = chars.clone();
+                    if next_line.next_if_eq(&'\n').is_some() {
+                        while next_line.next_if(|next| *next == ' ' || *next == '\t').is_some() {}
+                        if next_line.peek() != Some(&'\n') {
+                            chars = next_line;
+                        }
+                    }
+                } else if let Some(symbol) = chars.next() {
+                    name.push(symbol);
+                }
+                tokens.push(AbstractValue::ControlSequence(name));
+            }
+            '%' => {
+                while chars.next_if(|next| *next != '\n').is_some() {}
+                chars.next();
+                while chars.next_if(|next| *next == ' ' || *next == '\t').is_some() {}
+            }
+            c if c.is_whitespace() => {
+                let mut newlines = usize::from(c == '\n');
+                while let Some(next) = chars.next_if(|next| next.is_whitespace()) {
+                    newlines += usize::from(next == '\n');
+                }
+                tokens.push(if newlines > 1 {
+                    AbstractValue::ControlSequence("\\par".to_string())
+                } else {
+                    AbstractValue::Token(" ".to_string())
+                });
+            }
+            c => tokens.push(AbstractValue::Token(c.to_string())),
+        }
+    }
+    tokens
+}
+
+/// Renders tokens back to TeX source, separating control words from following letters.
+pub fn render(tokens: &[AbstractValue]) -> String {
+    let mut text = String::new();
+    let mut after_control_word = false;
+    for token in tokens {
+        let piece = match token {
+            AbstractValue::ControlSequence(name) => name.clone(),
+            AbstractValue::Token(text) => text.clone(),
+            AbstractValue::Group => "{…}".to_string(),
+            AbstractValue::Any => "…".to_string(),
+            AbstractValue::Dimension => "<dimen>".to_string(),
+            AbstractValue::Integer => "<number>".to_string(),
+            AbstractValue::Bottom => String::new(),
+            AbstractValue::AnalysisError(message) => format!("<error: {}>", message),
+        };
+        if after_control_word && piece.starts_with(|c: char| c.is_ascii_alphabetic() || c == '@') {
+            text.push(' ');
+        }
+        after_control_word = matches!(token, AbstractValue::ControlSequence(name) if is_control_word(name));
+        text.push_str(&piece);
+    }
+    text
+}
+
+/// The macros defined in `tokens`, in order.
+///
+/// `\newcommand` and its variants, `\def` and its variants (but `\edef` bodies are not
+/// expanded), `\DeclareMathOperator` and `\let` of a macro defined before are understood.
+pub fn harvest(tokens: &[AbstractValue]) -> Vec<MacroDefinition> {
+    let mut definitions: Vec<MacroDefinition> = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let AbstractValue::ControlSequence(command) = &tokens[i] else {
+            i += 1;
+            continue;
+        };
+        let mut cursor = Cursor { tokens, position: i + 1 };
+        let definition = if NEWCOMMANDS.contains(&command.as_str()) {
+            cursor.newcommand()
+        } else if DEFS.contains(&command.as_str()) {
+            cursor.def()
+        } else if command == "\\DeclareMathOperator" {
+            cursor.math_operator()
+        } else if command == "\\let" {
+            cursor.let_(&definitions)
+        } else {
+            None
+        };
+        match definition {
+            Some(definition) => {
+                // `\providecommand` keeps an earlier definition.
+                let provides = command == "\\providecommand";
+                match definitions.iter_mut().find(|known| known.name == definition.name) {
+                    Some(_) if provides => {}
+                    Some(known) => *known = definition,
+                    None => definitions.push(definition),
+                }
+                i = cursor.position;
+            }
+            None => i += 1,
+        }
+    }
+    definitions
+}
+
+/// Reads definitions from a token slice.
+struct Cursor<'a> {
+    tokens: &'a [AbstractValue],
+    position: usize,
+}
+
+impl Cursor<'_> {
+    fn peek(&self) -> Option<&AbstractValue> {
+        self.tokens.get(self.position)
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(is_space) {
+            self.position += 1;
+        }
+    }
+
+    fn eat(&mut self, text: &str) -> bool {
+        self.skip_spaces();
+        let eaten = self.peek().is_some_and(|token| is_char(token, text));
+        self.position += usize::from(eaten);
+        eaten
+    }
+
+    /// A balanced `{...}` group without its braces.
+    fn group(&mut self) -> Option<Vec<AbstractValue>> {
+        self.skip_spaces();
+        let (group, end) = read_group(self.tokens, self.position)?;
+        self.position = end;
+        Some(group)
+    }
+
+    /// `[...]` without its brackets, if the next token opens one.
+    fn optional(&mut self) -> Option<Vec<AbstractValue>> {
+        self.skip_spaces();
+        let (text, end) = read_optional(self.tokens, self.position)?;
+        self.position = end;
+        Some(text)
+    }
+
+    /// `\foo` or `{\foo}`.
+    fn name(&mut self) -> Option<String> {
+        self.skip_spaces();
+        let tokens = match self.peek()? {
+            AbstractValue::ControlSequence(_) => {
+                self.position += 1;
+                vec![self.tokens[self.position - 1].clone()]
+            }
+            _ => self.group()?,
+        };
+        match tokens.as_slice() {
+            [AbstractValue::ControlSequence(name)] => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    /// `\newcommand*{\foo}[2][default]{body}`, after `\newcommand`.
+    fn newcommand(&mut self) -> Option<MacroDefinition> {
+        self.eat("*");
+        let name = self.name()?;
+        let parameters = match self.optional() {
+            Some(count) => render(&count).trim().parse::<usize>().ok().filter(|count| *count <= 9)?,
+            None => 0,
+        };
+        let default = if parameters > 0 { self.optional() } else { None };
+        let body = self.group()?;
+        let parameter_text = (1..=parameters)
+            .flat_map(|i| [AbstractValue::Token("#".to_string()), AbstractValue::Token(i.to_string())])
+            .collect();
+        Some(MacroDefinition { name, parameter_text, default, body })
+    }
+
+    /// `\def\foo#1.#2\end{body}`, after `\def`.
+    fn def(&mut self) -> Option<MacroDefinition> {
+        let Some(AbstractValue::ControlSequence(name)) = self.peek().cloned() else { return None };
+        self.position += 1;
+        let start = self.position;
+        while !is_char(self.peek()?, "{") {
+            self.position += 1;
+        }
+        let parameter_text = self.tokens[start..self.position].to_vec();
+        let body = self.group()?;
+        Some(MacroDefinition { name, parameter_text, default: None, body })
+    }
+
+    /// `\DeclareMathOperator*{\Tr}{Tr}`, after `\DeclareMathOperator`.
+    fn math_operator(&mut self) -> Option<MacroDefinition> {
+        let star = self.eat("*");
+        let name = self.name()?;
+        let text = self.group()?;
+        let mut body = vec![AbstractValue::ControlSequence("\\operatorname".to_string())];
+        if star {
+            body.push(AbstractValue::Token("*".to_string()));
+        }
+        body.push(AbstractValue::Token("{".to_string()));
+        body.extend(text);
+        body.push(AbstractValue::Token("}".to_string()));
+        Some(MacroDefinition { name, parameter_text: Vec::new(), default: None, body })
+    }
+
+    /// `\let\foo\bar` or `\let\foo=\bar`, after `\let`, if `\bar` is in `known`.
+    fn let_(&mut self, known: &[MacroDefinition]) -> Option<MacroDefinition> {
+        let name = self.name()?;
+        self.eat("=");
+        let target = self.name()?;
+        let definition = known.iter().find(|definition| definition.name == target)?;
+        Some(MacroDefinition { name, ..definition.clone() })
+    }
+}
+
+/// Reads the arguments of `definition` from `input`, which holds the tokens following the
+/// macro in reading order, and returns them with the number of tokens they took.
+pub fn read_arguments(
+    definition: &MacroDefinition,
+    input: &[AbstractValue],
+) -> Result<(Vec<Vec<AbstractValue>>, usize), String> {
+    let mut arguments = Vec::new();
+    let mut position = 0;
+    let mut parameters = definition.parameter_text.as_slice();
+    if let Some(default) = &definition.default {
+        // The optional argument replaces the first parameter.
+        parameters = parameters.get(2..).unwrap_or_default();
+        let mut start = position;
+        while input.get(start).is_some_and(is_space) {
+            start += 1;
+        }
+        match read_optional(input, start) {
+            Some((argument, end)) => {
+                arguments.push(argument);
+                position = end;
+            }
+            None => arguments.push(default.clone()),
+        }
+    }
+
+    while let Some(token) = parameters.first() {
+        if !is_char(token, "#") {
+            // Text the definition requires before the next parameter.
+            if input.get(position) != Some(token) {
+                return Err(format!("Use of {} doesn't match its definition", definition.name));
+            }
+            position += 1;
+            parameters = &parameters[1..];
+            continue;
+        }
+        parameters = parameters.get(2..).unwrap_or_default();
+        let delimiter_len = parameters.iter().position(|token| is_char(token, "#")).unwrap_or(parameters.len());
+        let delimiter = &parameters[..delimiter_len];
+        if delimiter.is_empty() {
+            while input.get(position).is_some_and(is_space) {
+                position += 1;
+            }
+            let token = input.get(position).ok_or_else(|| format!("Missing argument for {}", definition.name))?;
+            if is_char(token, "{") {
+                let (group, end) = read_group(input, position).ok_or_else(|| format!("Runaway argument of {}", definition.name))?;
+                arguments.push(group);
+                position = end;
+            } else if is_char(token, "}") {
+                return Err(format!("Argument of {} has an extra }}", definition.name));
+            } else {
+                arguments.push(vec![token.clone()]);
+                position += 1;
+            }
+            continue;
+        }
+
+        // Delimited: everything up to the delimiter, outside braces.
+        let start = position;
+        let mut depth = 0usize;
+        loop {
+            if depth == 0 && input[position..].starts_with(delimiter) {
+                break;
+            }
+            let token = input.get(position).ok_or_else(|| format!("Runaway argument of {}", definition.name))?;
+            if is_char(token, "{") {
+                depth += 1;
+            } else if is_char(token, "}") {
+                depth = depth.checked_sub(1).ok_or_else(|| format!("Argument of {} has an extra }}", definition.name))?;
+            }
+            position += 1;
+        }
+        let mut argument = &input[start..position];
+        // TeX removes the braces around an argument that is one group.
+        if read_group(argument, 0).is_some_and(|(_, end)| end == argument.len()) {
+            argument = &argument[1..argument.len() - 1];
+        }
+        arguments.push(argument.to_vec());
+        position += delimiter.len();
+        parameters = &parameters[delimiter_len..];
+    }
+    Ok((arguments, position))
+}
+
+/// The body of `definition` with `#1`..`#9` replaced by `arguments` and `##` by `#`.
+pub fn substitute(definition: &MacroDefinition, arguments: &[Vec<AbstractValue>]) -> Vec<AbstractValue> {
+    let mut result = Vec::new();
+    let mut tokens = definition.body.iter();
+    while let Some(token) = tokens.next() {
+        if !is_char(token, "#") {
+            result.push(token.clone());
+            continue;
+        }
+        match tokens.next() {
+            Some(AbstractValue::Token(digit)) if digit.len() == 1 && digit != "#" => {
+                let index = digit.parse::<usize>().unwrap_or(0);
+                result.extend(index.checked_sub(1).and_then(|index| arguments.get(index)).into_iter().flatten().cloned());
+            }
+            Some(next) => result.push(next.clone()),
+            None => result.push(token.clone()),
+        }
+    }
+    result
+}
+
+/// The tokens of the group opening at `start`, without braces, and the index after it.
+fn read_group(tokens: &[AbstractValue], start: usize) -> Option<(Vec<AbstractValue>, usize)> {
+    if !is_char(tokens.get(start)?, "{") {
+        return None;
+    }
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        if is_char(token, "{") {
+            depth += 1;
+        } else if is_char(token, "}") {
+            depth -= 1;
+            if depth == 0 {
+                return Some((tokens[start + 1..i].to_vec(), i + 1));
+            }
+        }
+    }
+    None
+}
+
+/// The tokens of the `[...]` opening at `start`, without brackets, and the index after it.
+fn read_optional(tokens: &[AbstractValue], start: usize) -> Option<(Vec<AbstractValue>, usize)> {
+    if !is_char(tokens.get(start)?, "[") {
+        return None;
+    }
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate().skip(start + 1) {
+        if is_char(token, "{") {
+            depth += 1;
+        } else if is_char(token, "}") {
+            depth = depth.saturating_sub(1);
+        } else if depth == 0 && is_char(token, "]") {
+            return Some((tokens[start + 1..i].to_vec(), i + 1));
+        }
+    }
+    None
+}
+
+/// Whether `name` is a control word like `\foo`, rather than a control symbol like `\$`.
+fn is_control_word(name: &str) -> bool {
+    name.len() > 1 && name[1..].chars().all(|c| c.is_ascii_alphabetic() || c == '@')
+}
+
+fn is_char(token: &AbstractValue, text: &str) -> bool {
+    matches!(token, AbstractValue::Token(token) if token == text)
+}
+
+fn is_space(token: &AbstractValue) -> bool {
+    is_char(token, " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cs(name: &str) -> AbstractValue {
+        AbstractValue::ControlSequence(name.to_string())
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("\\foo  bar% comment\n  \\$x\n\ny");
+        assert_eq!(
+            tokens,
+            vec![
+                cs("\\foo"),
+                AbstractValue::Token("b".to_string()),
+                AbstractValue::Token("a".to_string()),
+                AbstractValue::Token("r".to_string()),
+                cs("\\$"),
+                AbstractValue::Token("x".to_string()),
+                cs("\\par"),
+                AbstractValue::Token("y".to_string()),
+            ]
+        );
+        assert_eq!(render(&tokens), "\\foo bar\\$x\\par y");
+    }
+
+    #[test]
+    fn test_harvest() {
+        let definitions = harvest(&tokenize(
+            "\\newcommand{\\vect}[1]{\\mathbf{#1}}\n\\newcommand*\\R{\\mathbb{R}}\n\\providecommand{\\R}{X}\n\\renewcommand{\\norm}[2][2]{\\|#2\\|_{#1}}\n\\def\\pair(#1,#2){#1 and #2}\n\\DeclareMathOperator{\\Tr}{Tr}\n\\let\\vec=\\vect",
+        ));
+        let names: Vec<&str> = definitions.iter().map(|definition| definition.name.as_str()).collect();
+        assert_eq!(names, vec!["\\vect", "\\R", "\\norm", "\\pair", "\\Tr", "\\vec"]);
+        assert_eq!(render(&definitions[1].body), "\\mathbb{R}");
+        assert_eq!(definitions[2].default, Some(tokenize("2")));
+        assert_eq!(render(&definitions[3].parameter_text), "(#1,#2)");
+        assert_eq!(render(&definitions[4].body), "\\operatorname{Tr}");
+        assert_eq!(definitions[5].body, definitions[0].body);
+    }
+
+    #[test]
+    fn test_read_arguments() {
+        let definitions = harvest(&tokenize("\\newcommand{\\norm}[2][2]{\\|#2\\|_{#1}}\\def\\pair(#1,#2){#1 and #2}"));
+        let (arguments, used) = read_arguments(&definitions[0], &tokenize(" {x}rest")).unwrap();
+        assert_eq!(arguments, vec![tokenize("2"), tokenize("x")]);
+        assert_eq!(used, 4);
+        assert_eq!(render(&substitute(&definitions[0], &arguments)), "\\|x\\|_{2}");
+
+        let (arguments, _) = read_arguments(&definitions[0], &tokenize("[\\infty]y")).unwrap();
+        assert_eq!(render(&substitute(&definitions[0], &arguments)), "\\|y\\|_{\\infty}");
+
+        let (arguments, used) = read_arguments(&definitions[1], &tokenize("({a,b},c)!")).unwrap();
+        assert_eq!(arguments, vec![tokenize("a,b"), tokenize("c")]);
+        assert_eq!(used, 9);
+
+        assert_eq!(
+            read_arguments(&definitions[1], &tokenize("[a,b]")),
+            Err("Use of \\pair doesn't match its definition".to_string())
+        );
+        assert_eq!(read_arguments(&definitions[0], &[]), Err("Missing argument for \\norm".to_string()));
+        assert_eq!(read_arguments(&definitions[0], &tokenize("{x")), Err("Runaway argument of \\norm".to_string()));
+    }
+}
@@ -1,7 +1,14 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs;
 use sha2::{Sha256, Digest};
-use crate::{Artifact, ArtifactId};
+use crate::{Artifact, ArtifactId, ArtifactSpec};
+
+/// Fingerprint of artifacts that do not exist (yet).
+pub const MISSING: &str = "MISSING";
+
+pub(crate) fn sha256(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
 
 #[derive(Debug, Clone)]
 pub struct FileArtifact {
@@ -24,18 +31,121 @@ impl Artifact for FileArtifact {
 
     fn fingerprint(&self) -> String {
         match fs::read(&self.path) {
-            Ok(bytes) => {
-                let mut hasher = Sha256::new();
-                hasher.update(&bytes);
-                hex::encode(hasher.finalize())
+            Ok(bytes) => sha256(&bytes),
+            Err(_) => MISSING.to_string(), // Or handle error gracefully
+        }
+    }
+
+    fn path(&self) -> Option<PathBuf> {
+        Some(self.path.clone())
+    }
+
+    fn spec(&self) -> ArtifactSpec {
+        ArtifactSpec::File { id: self.id(), path: self.path.clone() }
+    }
+}
+
+/// Content that only exists in memory, e.g. a generated preamble snippet or the flags
+/// injected for a traced build.
+///
+/// Its ID is `memory:<name>`, so the name must be unique within a graph.
+#[derive(Debug, Clone)]
+pub struct MemoryArtifact {
+    pub name: String,
+    pub content: Vec<u8>,
+}
+
+impl MemoryArtifact {
+    pub fn new(name: &str, content: impl Into<Vec<u8>>) -> Self {
+        Self { name: name.to_string(), content: content.into() }
+    }
+}
+
+impl Artifact for MemoryArtifact {
+    fn id(&self) -> ArtifactId {
+        ArtifactId(format!("memory:{}", self.name))
+    }
+
+    fn fingerprint(&self) -> String {
+        sha256(&self.content)
+    }
+
+    fn path(&self) -> Option<PathBuf> {
+        None
+    }
+
+    fn spec(&self) -> ArtifactSpec {
+        let content = String::from_utf8_lossy(&self.content).into_owned();
+        ArtifactSpec::Memory { id: self.id(), name: self.name.clone(), content }
+    }
+}
+
+/// A directory and everything below it, e.g. a font directory or the output directory.
+///
+/// The fingerprint covers the relative path and content of every file, so renaming,
+/// adding or editing a file changes it; empty subdirectories and timestamps do not.
+#[derive(Debug, Clone)]
+pub struct DirArtifact {
+    pub path: PathBuf,
+}
+
+impl DirArtifact {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Paths of the files below the directory, relative to it and sorted.
+    pub fn files(&self) -> Vec<PathBuf> {
+        fn walk(dir: &Path, root: &Path, files: &mut Vec<PathBuf>) {
+            let Ok(entries) = fs::read_dir(dir) else {
+                return;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    walk(&path, root, files);
+                } else if let Ok(relative) = path.strip_prefix(root) {
+                    files.push(relative.to_path_buf());
+                }
             }
-            Err(_) => "MISSING".to_string(), // Or handle error gracefully
         }
+        let mut files = Vec::new();
+        walk(&self.path, &self.path, &mut files);
+        files.sort();
+        files
+    }
+}
+
+impl Artifact for DirArtifact {
+    fn id(&self) -> ArtifactId {
+        // Same scheme as `FileArtifact`, so transforms can refer to a directory by path.
+        let abs_path = fs::canonicalize(&self.path).unwrap_or(self.path.clone());
+        ArtifactId(abs_path.to_string_lossy().to_string())
+    }
+
+    fn fingerprint(&self) -> String {
+        if !self.path.is_dir() {
+            return MISSING.to_string();
+        }
+        let mut hasher = Sha256::new();
+        for file in self.files() {
+            // `/` separators keep the fingerprint identical across platforms.
+            let name: Vec<String> = file.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect();
+            hasher.update(name.join("/").as_bytes());
+            hasher.update([0]);
+            hasher.update(FileArtifact::new(self.path.join(&file)).fingerprint().as_bytes());
+            hasher.update(b"\n");
+        }
+        hex::encode(hasher.finalize())
     }
 
     fn path(&self) -> Option<PathBuf> {
         Some(self.path.clone())
     }
+
+    fn spec(&self) -> ArtifactSpec {
+        ArtifactSpec::Directory { id: self.id(), path: self.path.clone() }
+    }
 }
 #[cfg(test)]
 mod tests {
@@ -65,6 +175,40 @@ mod tests {
         let _ = fs::remove_dir_all(temp_dir);
     }
 
+    #[test]
+    fn test_memory_artifact() {
+        let trace = MemoryArtifact::new("trace-flags", "\\tracingall");
+        assert_eq!(trace.id().0, "memory:trace-flags");
+        assert_eq!(trace.fingerprint(), MemoryArtifact::new("other", "\\tracingall").fingerprint());
+        assert_ne!(trace.fingerprint(), MemoryArtifact::new("trace-flags", "").fingerprint());
+        assert!(trace.path().is_none());
+    }
+
+    #[test]
+    fn test_dir_artifact_fingerprint() {
+        let dir = std::env::current_dir().unwrap().join("target").join("test_dir_artifact");
+        let _ = fs::remove_dir_all(&dir);
+        let artifact = DirArtifact::new(dir.clone());
+        assert_eq!(artifact.fingerprint(), MISSING);
+
+        fs::create_dir_all(dir.join("type1")).unwrap();
+        fs::write(dir.join("font.otf"), "glyphs").unwrap();
+        fs::write(dir.join("type1").join("font.pfb"), "outlines").unwrap();
+        let fp1 = artifact.fingerprint();
+        assert_eq!(artifact.files(), [PathBuf::from("font.otf"), Path::new("type1").join("font.pfb")]);
+
+        fs::create_dir_all(dir.join("empty")).unwrap();
+        assert_eq!(artifact.fingerprint(), fp1);
+
+        fs::write(dir.join("type1").join("font.pfb"), "new outlines").unwrap();
+        let fp2 = artifact.fingerprint();
+        assert_ne!(fp1, fp2);
+
+        fs::rename(dir.join("font.otf"), dir.join("renamed.otf")).unwrap();
+        assert_ne!(artifact.fingerprint(), fp2);
+        let _ = fs::remove_dir_all(dir);
+    }
+
     #[test]
     fn test_file_artifact_id_and_path() {
         let path = PathBuf::from("test.tex");
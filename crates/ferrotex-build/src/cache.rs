@@ -0,0 +1,121 @@
+use crate::{ArtifactId, BuildGraph, Transform};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Fingerprints recorded for one transform after it last succeeded.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// Artifact ID -> fingerprint of each input, taken before the transform ran.
+    pub inputs: BTreeMap<String, String>,
+    /// Artifact ID -> fingerprint of each output, taken after the transform ran.
+    pub outputs: BTreeMap<String, String>,
+}
+
+/// Content-addressed record of previous transform runs.
+///
+/// A transform whose inputs and outputs still have the fingerprints recorded here is
+/// up to date and is not executed again. The cache is stored as JSON next to the
+/// lockfile (see [`BuildCache::path_for_lockfile`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildCache {
+    pub version: String,
+    /// Transform key (see [`transform_key`]) -> fingerprints of its last successful run.
+    pub entries: HashMap<String, CacheEntry>,
+}
+
+impl Default for BuildCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BuildCache {
+    pub fn new() -> Self {
+        Self {
+            version: "0.20.0".to_string(),
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Returns the cache file belonging to a lockfile, e.g. `main.cache` for `main.lock`.
+    pub fn path_for_lockfile(lockfile: &Path) -> PathBuf {
+        lockfile.with_extension("cache")
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let cache: Self = serde_json::from_str(&content)?;
+        Ok(cache)
+    }
+
+    /// Loads the cache at `path`, starting empty if it is missing or unreadable.
+    pub fn load_or_default(path: &Path) -> Self {
+        Self::load(path).unwrap_or_default()
+    }
+}
+
+/// Identifies a transform across runs by its description, outputs and command.
+///
+/// The command (program, arguments, working directory and container image) is hashed in,
+/// so changing e.g. the profile, SyncTeX or shell escape reruns the transform even though
+/// its inputs are unchanged.
+pub fn transform_key(transform: &dyn Transform) -> String {
+    let mut outputs: Vec<String> = transform.outputs().into_iter().map(|id| id.0).collect();
+    outputs.sort();
+    let key = format!("{} -> {}", transform.description(), outputs.join(", "));
+    match transform.command() {
+        Some(command) => {
+            let command = serde_json::to_vec(&command).unwrap_or_default();
+            format!("{} [{}]", key, &crate::artifacts::sha256(&command)[..16])
+        }
+        None => key,
+    }
+}
+
+/// Hits and misses of one execution.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    /// Transforms skipped because they were up to date.
+    pub hits: usize,
+    /// Transforms executed while a cache was in use.
+    pub misses: usize,
+}
+
+impl BuildGraph {
+    /// Fingerprints the given artifacts, or `None` if one of them is not part of the graph.
+    pub(crate) fn fingerprints(&self, ids: &HashSet<ArtifactId>) -> Option<BTreeMap<String, String>> {
+        ids.iter()
+            .map(|id| Some((id.0.clone(), self.artifacts.get(id)?.fingerprint())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_cache_roundtrip() {
+        let mut cache = BuildCache::new();
+        let mut entry = CacheEntry::default();
+        entry.inputs.insert("chapter.tex".to_string(), "abc".to_string());
+        cache.entries.insert("pdflatex -> main.pdf".to_string(), entry.clone());
+
+        let dir = std::env::current_dir().unwrap().join("target").join("test_cache");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = BuildCache::path_for_lockfile(&dir.join("main.lock"));
+        assert_eq!(path, dir.join("main.cache"));
+
+        cache.save(&path).unwrap();
+        assert_eq!(BuildCache::load(&path).unwrap().entries["pdflatex -> main.pdf"], entry);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(BuildCache::load_or_default(&path).entries.is_empty());
+    }
+}
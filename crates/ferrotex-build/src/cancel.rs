@@ -0,0 +1,157 @@
+use crate::{LogSink, TransformError};
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// How often a running program is checked for exit and cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Tells running transforms to stop.
+///
+/// Clones share the same flag, so a token handed to a Ctrl-C handler or a server's
+/// cancel command stops every transform it was passed to. A token derived with
+/// [`CancellationToken::with_timeout`] additionally expires on its own.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<(Instant, Duration)>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether [`CancellationToken::cancel`] was called on this token or one of its clones.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// A token cancelled together with this one, which also expires `timeout` from now.
+    ///
+    /// An earlier deadline of this token is kept.
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        let deadline = (Instant::now() + timeout, timeout);
+        Self {
+            cancelled: self.cancelled.clone(),
+            deadline: match self.deadline {
+                Some(current) if current.0 <= deadline.0 => Some(current),
+                _ => Some(deadline),
+            },
+        }
+    }
+
+    /// Returns the error a transform should stop with, if any.
+    pub fn check(&self) -> Result<(), TransformError> {
+        if self.is_cancelled() {
+            return Err(TransformError::Cancelled);
+        }
+        match self.deadline {
+            Some((deadline, timeout)) if Instant::now() >= deadline => Err(TransformError::TimedOut { timeout }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Runs `cmd` to completion like [`Command::output`], killing it once `cancel` fires.
+///
+/// Each line the program prints is passed to `log` as it arrives. The program runs in a
+/// process group of its own, so that the programs it starts (e.g. the engine passes of
+/// latexmk) are killed with it.
+pub fn output(cmd: &mut Command, cancel: &CancellationToken, log: &LogSink) -> Result<Output, TransformError> {
+    cancel.check()?;
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(cmd, 0);
+    let mut child = cmd.spawn().map_err(|e| format!("{}: {}", program, e))?;
+
+    // Drain both pipes while waiting, or a chatty program blocks on a full pipe.
+    let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+    std::thread::scope(|scope| {
+        let stdout = stdout.map(|out| scope.spawn(|| read_lines(out, log)));
+        let stderr = stderr.map(|err| scope.spawn(|| read_lines(err, log)));
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) => {}
+                Err(e) => return Err(format!("{}: {}", program, e).into()),
+            }
+            if let Err(e) = cancel.check() {
+                kill(&mut child);
+                return Err(e);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        };
+        let join = |reader: Option<std::thread::ScopedJoinHandle<Vec<u8>>>| {
+            reader.map(|r| r.join().unwrap_or_default()).unwrap_or_default()
+        };
+        Ok(Output { status, stdout: join(stdout), stderr: join(stderr) })
+    })
+}
+
+fn read_lines(stream: impl Read, log: &LogSink) -> Vec<u8> {
+    let mut reader = BufReader::new(stream);
+    let mut buffer = Vec::new();
+    loop {
+        let start = buffer.len();
+        match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) | Err(_) => break,
+            // Programs may print in the system encoding rather than UTF-8.
+            Ok(_) => log(&String::from_utf8_lossy(&buffer[start..])),
+        }
+    }
+    buffer
+}
+
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    // SAFETY: `kill` has no memory-safety preconditions; a negative pid addresses the group.
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeout_keeps_earliest_deadline() {
+        let token = CancellationToken::new();
+        assert!(token.check().is_ok());
+        let expired = token.with_timeout(Duration::ZERO).with_timeout(Duration::from_secs(60));
+        assert_eq!(expired.check(), Err(TransformError::TimedOut { timeout: Duration::ZERO }));
+
+        token.cancel();
+        assert!(expired.is_cancelled());
+        assert_eq!(expired.check(), Err(TransformError::Cancelled));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_streams_lines_and_times_out() {
+        let token = CancellationToken::new().with_timeout(Duration::from_millis(100));
+        let start = Instant::now();
+        let result = output(Command::new("sh").args(["-c", "sleep 10"]), &token, &|_| {});
+        assert_eq!(result, Err(TransformError::TimedOut { timeout: Duration::from_millis(100) }));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let lines = std::sync::Mutex::new(Vec::new());
+        let log = |line: &str| lines.lock().unwrap().push(line.to_string());
+        let output = output(Command::new("sh").args(["-c", "echo one; echo two >&2"]), &CancellationToken::new(), &log).unwrap();
+        assert_eq!(output.stdout, b"one\n");
+        assert_eq!(output.stderr, b"two\n");
+        let mut lines = lines.into_inner().unwrap();
+        lines.sort();
+        assert_eq!(lines, ["one\n", "two\n"]);
+    }
+}
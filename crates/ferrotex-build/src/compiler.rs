@@ -1,13 +1,34 @@
-use crate::{ArtifactId, Transform};
+use crate::container::{Container, Mount};
+use crate::{ArtifactId, PostProcessStep, RemoteAsset, BuildProfile, CancellationToken, CommandSpec, FileRecorder, LogSink, SandboxPolicy, Transform, TransformError};
+use ferrotex_log::ir::{EventPayload, LogEvent};
 use std::collections::HashSet;
 use std::path::PathBuf;
-use std::process::Command;
+use std::process::{Command, Output};
+
+/// Number of extra engine passes allowed by default when the log asks for a rerun.
+pub const DEFAULT_MAX_RERUNS: usize = 3;
 
 /// A Compiler holds the configuration for executing an external TeX engine.
+#[derive(Debug, Clone)]
 pub struct Compiler {
     pub engine: String, // e.g., "pdflatex", "xelatex", "tectonic"
     pub output_dir: PathBuf,
     pub extra_args: Vec<String>,
+    /// Extra passes allowed when the log reports changed cross-references.
+    pub max_reruns: usize,
+    pub profile: BuildProfile,
+    /// Applied to the engine and the auxiliary tools.
+    pub sandbox: SandboxPolicy,
+    /// Runs the engine and the auxiliary tools in a container instead of on the host.
+    pub container: Option<Container>,
+    /// Writes gzipped SyncTeX data (`<stem>.synctex.gz`); `None` follows the profile.
+    pub synctex: Option<bool>,
+    /// Run in order on the PDF, for profiles that write one.
+    pub post_process: Vec<PostProcessStep>,
+    /// Fetched next to the root document before the engine runs.
+    pub assets: Vec<RemoteAsset>,
+    /// Keeps verified downloads of [`Compiler::assets`] across builds.
+    pub asset_cache: PathBuf,
 }
 
 impl Compiler {
@@ -16,6 +37,14 @@ impl Compiler {
             engine: engine.to_string(),
             output_dir,
             extra_args: Vec::new(),
+            max_reruns: DEFAULT_MAX_RERUNS,
+            profile: BuildProfile::default(),
+            sandbox: SandboxPolicy::default(),
+            container: None,
+            synctex: None,
+            post_process: Vec::new(),
+            assets: Vec::new(),
+            asset_cache: crate::remote::default_cache_dir(),
         }
     }
 
@@ -23,6 +52,48 @@ impl Compiler {
         self.extra_args = args;
         self
     }
+
+    pub fn with_max_reruns(mut self, max_reruns: usize) -> Self {
+        self.max_reruns = max_reruns;
+        self
+    }
+
+    pub fn with_profile(mut self, profile: BuildProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    pub fn with_sandbox(mut self, sandbox: SandboxPolicy) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    pub fn with_container(mut self, container: Container) -> Self {
+        self.container = Some(container);
+        self
+    }
+
+    /// Writes SyncTeX data, or not, whatever the profile. Editors want it even for draft
+    /// builds, so that forward and inverse search follow the latest edits.
+    pub fn with_synctex(mut self, synctex: bool) -> Self {
+        self.synctex = Some(synctex);
+        self
+    }
+
+    pub fn with_post_process(mut self, steps: Vec<PostProcessStep>) -> Self {
+        self.post_process = steps;
+        self
+    }
+
+    pub fn with_assets(mut self, assets: Vec<RemoteAsset>) -> Self {
+        self.assets = assets;
+        self
+    }
+
+    /// Whether the engine is run with `-synctex=1`.
+    pub fn writes_synctex(&self) -> bool {
+        self.synctex.unwrap_or(self.profile.synctex())
+    }
 }
 
 /// ShellTransform executes an external shell command as a build step.
@@ -33,6 +104,9 @@ pub struct ShellTransform {
     command: String,
     args: Vec<String>,
     working_dir: Option<PathBuf>,
+    sandbox: Option<SandboxPolicy>,
+    recorder: Option<PathBuf>,
+    container: Option<Container>,
 }
 
 impl ShellTransform {
@@ -50,6 +124,9 @@ impl ShellTransform {
             command: command.to_string(),
             args,
             working_dir: None,
+            sandbox: None,
+            recorder: None,
+            container: None,
         }
     }
 
@@ -57,6 +134,73 @@ impl ShellTransform {
         self.working_dir = Some(dir);
         self
     }
+
+    /// Runs the command under `policy`. Without a sandbox the environment is inherited as is.
+    pub fn with_sandbox(mut self, policy: SandboxPolicy) -> Self {
+        self.sandbox = Some(policy);
+        self
+    }
+
+    /// Checks the accesses listed in the `.fls` file at `path` against the sandbox after
+    /// each run. The command must write it, e.g. a TeX engine run with `-recorder`.
+    pub fn with_recorder(mut self, path: PathBuf) -> Self {
+        self.recorder = Some(path);
+        self
+    }
+
+    /// Runs the command in `container`, which can only see the declared inputs and the
+    /// directories of the declared outputs.
+    pub fn with_container(mut self, container: Container) -> Self {
+        self.container = Some(container);
+        self
+    }
+
+    /// Runs the command like [`crate::cancel::output`], stopping its container if it is
+    /// cancelled or times out.
+    pub(crate) fn run(&self, cancel: &CancellationToken, log: &LogSink) -> Result<Output, TransformError> {
+        let name = Container::unique_name();
+        let result = crate::cancel::output(&mut self.process(&name)?, cancel, log);
+        if let (Err(TransformError::Cancelled | TransformError::TimedOut { .. }), Some(container)) = (&result, &self.container) {
+            let _ = container.kill(&name).output();
+        }
+        result
+    }
+
+    /// Builds the process to run, inside the container called `name` if one is set.
+    fn process(&self, name: &str) -> Result<Command, String> {
+        let env: Vec<(&str, &str)> = self.sandbox.iter().flat_map(|policy| policy.env()).collect();
+        let Some(container) = &self.container else {
+            let mut cmd = Command::new(&self.command);
+            cmd.args(&self.args).envs(env.iter().copied());
+            if let Some(ref dir) = self.working_dir {
+                cmd.current_dir(dir);
+            }
+            return Ok(cmd);
+        };
+        let dir = match &self.working_dir {
+            Some(dir) => dir.clone(),
+            None => std::env::current_dir().map_err(|e| e.to_string())?,
+        };
+        let mounts = Mount::for_artifacts(&self.input_ids, &self.output_ids);
+        Ok(container.command(name, &self.command, &self.args, &dir, &mounts, &env))
+    }
+
+    fn check_sandbox(&self) -> Result<(), TransformError> {
+        let (Some(policy), Some(path)) = (&self.sandbox, &self.recorder) else {
+            return Ok(());
+        };
+        let recorder = FileRecorder::load(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let root = match &self.working_dir {
+            Some(dir) => dir.clone(),
+            None => std::env::current_dir().map_err(|e| e.to_string())?,
+        };
+        let violations = policy.check(&recorder, &root, &self.input_ids, &self.output_ids);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(TransformError::Sandbox { violations })
+        }
+    }
 }
 
 impl Transform for ShellTransform {
@@ -72,20 +216,24 @@ impl Transform for ShellTransform {
         self.output_ids.clone()
     }
 
-    fn execute(&self) -> Result<(), String> {
-        let mut cmd = Command::new(&self.command);
-        cmd.args(&self.args);
-        
-        if let Some(ref dir) = self.working_dir {
-            cmd.current_dir(dir);
-        }
+    fn command(&self) -> Option<CommandSpec> {
+        Some(CommandSpec {
+            program: self.command.clone(),
+            args: self.args.clone(),
+            working_dir: self.working_dir.clone(),
+            image: self.container.as_ref().map(|container| format!("{}:{}", container.runtime, container.image)),
+        })
+    }
 
-        let output = cmd.output().map_err(|e| e.to_string())?;
+    fn execute(&self, cancel: &CancellationToken, log: &LogSink) -> Result<(), TransformError> {
+        let output = self.run(cancel, log)?;
+        // A violation is reported even if the command failed afterwards.
+        self.check_sandbox()?;
         
         if output.status.success() {
             Ok(())
         } else {
-            Err(String::from_utf8_lossy(&output.stderr).to_string())
+            Err(String::from_utf8_lossy(&output.stderr).to_string().into())
         }
     }
 }
@@ -130,10 +278,271 @@ impl Transform for PdfLatexTransform {
     fn outputs(&self) -> HashSet<ArtifactId> {
         self.inner.outputs()
     }
-    fn execute(&self) -> Result<(), String> {
-        self.inner.execute()
+    fn command(&self) -> Option<CommandSpec> {
+        self.inner.command()
+    }
+    fn execute(&self, cancel: &CancellationToken, log: &LogSink) -> Result<(), TransformError> {
+        self.inner.execute(cancel, log)
+    }
+}
+/// Returns true if the log asks for another engine pass, e.g.
+/// "Label(s) may have changed. Rerun to get cross-references right." or
+/// "Package biblatex Warning: Please rerun LaTeX."
+pub fn needs_rerun(events: &[LogEvent]) -> bool {
+    events.iter().any(|event| matches!(event.payload, EventPayload::RerunRequired { .. }))
+}
+
+/// A TeX engine pass that repeats itself until cross-references settle.
+///
+/// After each run the log is parsed and the engine runs again while it reports a
+/// rerun warning, at most `max_reruns` extra times.
+pub struct LatexTransform {
+    inner: ShellTransform,
+    log_path: PathBuf,
+    max_reruns: usize,
+}
+
+impl LatexTransform {
+    /// Wraps the engine invocation `inner`, which writes its log to `log_path`.
+    pub fn new(inner: ShellTransform, log_path: PathBuf) -> Self {
+        Self {
+            inner,
+            log_path,
+            max_reruns: DEFAULT_MAX_RERUNS,
+        }
+    }
+
+    pub fn with_max_reruns(mut self, max_reruns: usize) -> Self {
+        self.max_reruns = max_reruns;
+        self
+    }
+
+    fn read_log(&self) -> Vec<LogEvent> {
+        // Logs are not necessarily UTF-8 (e.g. latin-1 file names).
+        match std::fs::read(&self.log_path) {
+            Ok(bytes) => ferrotex_log::LogParser::new().parse(&String::from_utf8_lossy(&bytes)),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+impl Transform for LatexTransform {
+    fn description(&self) -> String {
+        self.inner.description()
+    }
+    fn inputs(&self) -> HashSet<ArtifactId> {
+        self.inner.inputs()
+    }
+    fn outputs(&self) -> HashSet<ArtifactId> {
+        self.inner.outputs()
+    }
+    fn command(&self) -> Option<CommandSpec> {
+        self.inner.command()
+    }
+    fn execute(&self, cancel: &CancellationToken, log: &LogSink) -> Result<(), TransformError> {
+        for _ in 0..=self.max_reruns {
+            let result = self.inner.execute(cancel, log);
+            let events = self.read_log();
+            if let Err(e) = result {
+                // The engine prints its errors on stdout; the log has them in order.
+                let first_error = ferrotex_log::correlate(&events).into_iter().find_map(|event| match event.payload {
+                    EventPayload::Error { message, file: Some(file), line: Some(line), .. } => {
+                        Some(format!("{}:{}: {}", file.trim_start_matches("./"), line, message))
+                    }
+                    EventPayload::Error { message, .. } => Some(message),
+                    _ => None,
+                });
+                return Err(match (e, first_error) {
+                    (TransformError::Failed { .. }, Some(message)) => message.into(),
+                    (e, _) => e,
+                });
+            }
+            if !needs_rerun(&events) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The program that turns citations into a `.bbl` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BibliographyTool {
+    /// Used with biblatex; reads the `.bcf` file.
+    Biber,
+    /// Used with `\bibliography`; reads the `.aux` file.
+    Bibtex,
+}
+
+impl BibliographyTool {
+    pub fn command(self) -> &'static str {
+        match self {
+            BibliographyTool::Biber => "biber",
+            BibliographyTool::Bibtex => "bibtex",
+        }
+    }
+
+    /// Interprets an exit code. BibTeX exits with 1 when it only printed warnings.
+    pub fn succeeded(self, code: Option<i32>) -> bool {
+        match self {
+            BibliographyTool::Biber => code == Some(0),
+            BibliographyTool::Bibtex => matches!(code, Some(0 | 1)),
+        }
+    }
+}
+
+/// Runs biber or BibTeX on a job, e.g. `biber build/main`.
+pub struct BibliographyTransform {
+    tool: BibliographyTool,
+    inner: ShellTransform,
+}
+
+impl BibliographyTransform {
+    /// `job` is the output path of the document without extension; the tool runs in
+    /// `working_dir`, where the document's bibliography paths are relative to.
+    pub fn new(
+        tool: BibliographyTool,
+        input_ids: HashSet<ArtifactId>,
+        output_ids: HashSet<ArtifactId>,
+        job: PathBuf,
+        working_dir: PathBuf,
+    ) -> Self {
+        let args = vec![job.to_string_lossy().into_owned()];
+        let inner = ShellTransform::new(tool.command(), input_ids, output_ids, tool.command(), args)
+            .with_working_dir(working_dir);
+        Self { tool, inner }
+    }
+
+    /// Runs the tool under `policy`; only shell escape and output paths are restricted.
+    pub fn with_sandbox(mut self, policy: SandboxPolicy) -> Self {
+        self.inner = self.inner.with_sandbox(policy);
+        self
+    }
+
+    /// Runs the tool in `container` (see [`ShellTransform::with_container`]).
+    pub fn with_container(mut self, container: Container) -> Self {
+        self.inner = self.inner.with_container(container);
+        self
+    }
+}
+
+impl Transform for BibliographyTransform {
+    fn description(&self) -> String {
+        self.inner.description()
+    }
+    fn inputs(&self) -> HashSet<ArtifactId> {
+        self.inner.inputs()
+    }
+    fn outputs(&self) -> HashSet<ArtifactId> {
+        self.inner.outputs()
+    }
+    fn command(&self) -> Option<CommandSpec> {
+        self.inner.command()
+    }
+    fn execute(&self, cancel: &CancellationToken, log: &LogSink) -> Result<(), TransformError> {
+        let output = self.inner.run(cancel, log)?;
+        if self.tool.succeeded(output.status.code()) {
+            return Ok(());
+        }
+        // Both tools report problems on stdout: biber as `ERROR - ...`, BibTeX after `---`.
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let errors: Vec<&str> = stdout
+            .lines()
+            .filter(|line| line.contains("ERROR") || line.contains("error"))
+            .collect();
+        if errors.is_empty() {
+            Err(format!("{} exited with {}", self.tool.command(), output.status).into())
+        } else {
+            Err(errors.join("\n").into())
+        }
+    }
+}
+
+/// Programs that sort index and glossary entries for the next engine pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexTool {
+    /// `.idx` -> `.ind` with makeindex.
+    Makeindex,
+    /// `.idx` -> `.ind` with xindy, for `\usepackage[xindy]{imakeidx}` and friends.
+    Xindy,
+    /// `.glo` -> `.gls` (and acronym lists) with the glossaries package's helper, which
+    /// picks makeindex or xindy from the `.aux` file.
+    Makeglossaries,
+}
+
+impl IndexTool {
+    pub fn command(self) -> &'static str {
+        match self {
+            IndexTool::Makeindex => "makeindex",
+            IndexTool::Xindy => "texindy",
+            IndexTool::Makeglossaries => "makeglossaries",
+        }
+    }
+
+    /// Arguments for processing `job`, the output path of the document without extension.
+    pub fn args(self, job: &std::path::Path) -> Vec<String> {
+        let with = |extension: &str| job.with_extension(extension).to_string_lossy().into_owned();
+        match self {
+            IndexTool::Makeindex | IndexTool::Xindy => vec!["-o".to_string(), with("ind"), with("idx")],
+            IndexTool::Makeglossaries => {
+                let dir = job.parent().unwrap_or(std::path::Path::new(".")).to_string_lossy().into_owned();
+                let name = job.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                vec!["-d".to_string(), dir, name]
+            }
+        }
+    }
+}
+
+/// Runs an index or glossary tool on a job, e.g. `makeindex -o build/main.ind build/main.idx`.
+pub struct IndexTransform {
+    inner: ShellTransform,
+}
+
+impl IndexTransform {
+    /// `job` is the output path of the document without extension; the tool runs in `working_dir`.
+    pub fn new(
+        tool: IndexTool,
+        input_ids: HashSet<ArtifactId>,
+        output_ids: HashSet<ArtifactId>,
+        job: PathBuf,
+        working_dir: PathBuf,
+    ) -> Self {
+        let inner = ShellTransform::new(tool.command(), input_ids, output_ids, tool.command(), tool.args(&job))
+            .with_working_dir(working_dir);
+        Self { inner }
+    }
+
+    /// Runs the tool under `policy`; only shell escape and output paths are restricted.
+    pub fn with_sandbox(mut self, policy: SandboxPolicy) -> Self {
+        self.inner = self.inner.with_sandbox(policy);
+        self
+    }
+
+    /// Runs the tool in `container` (see [`ShellTransform::with_container`]).
+    pub fn with_container(mut self, container: Container) -> Self {
+        self.inner = self.inner.with_container(container);
+        self
+    }
+}
+
+impl Transform for IndexTransform {
+    fn description(&self) -> String {
+        self.inner.description()
+    }
+    fn inputs(&self) -> HashSet<ArtifactId> {
+        self.inner.inputs()
+    }
+    fn outputs(&self) -> HashSet<ArtifactId> {
+        self.inner.outputs()
+    }
+    fn command(&self) -> Option<CommandSpec> {
+        self.inner.command()
+    }
+    fn execute(&self, cancel: &CancellationToken, log: &LogSink) -> Result<(), TransformError> {
+        self.inner.execute(cancel, log)
     }
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -167,7 +576,7 @@ mod tests {
         );
         
         assert_eq!(transform.description(), "test echo");
-        assert!(transform.execute().is_ok());
+        assert!(transform.execute(&CancellationToken::new(), &|_| {}).is_ok());
     }
 
     #[test]
@@ -179,7 +588,7 @@ mod tests {
             "false",
             vec![],
         );
-        assert!(transform.execute().is_err());
+        assert!(transform.execute(&CancellationToken::new(), &|_| {}).is_err());
     }
 
     #[test]
@@ -191,7 +600,7 @@ mod tests {
             "pwd",
             vec![],
         ).with_working_dir(std::env::current_dir().unwrap());
-        assert!(transform.execute().is_ok());
+        assert!(transform.execute(&CancellationToken::new(), &|_| {}).is_ok());
     }
 
     #[test]
@@ -205,7 +614,7 @@ mod tests {
         assert_eq!(transform.description(), "pdflatex compilation");
         assert_eq!(transform.outputs().len(), 1);
         // This exercises the trait delegation
-        let _ = transform.execute();
+        let _ = transform.execute(&CancellationToken::new(), &|_| {});
     }
 
     #[test]
@@ -217,7 +626,7 @@ mod tests {
             "/non/existent/command/at/all",
             vec![],
         );
-        assert!(transform.execute().is_err());
+        assert!(transform.execute(&CancellationToken::new(), &|_| {}).is_err());
     }
 
     #[test]
@@ -229,6 +638,99 @@ mod tests {
             "echo",
             vec!["ok".to_string()],
         );
-        assert!(transform.execute().is_ok());
+        assert!(transform.execute(&CancellationToken::new(), &|_| {}).is_ok());
+    }
+
+    fn warning(message: &str) -> LogEvent {
+        ferrotex_log::LogParser::new().parse(&format!("{}\n", message)).remove(0)
+    }
+
+    #[test]
+    fn test_needs_rerun() {
+        assert!(needs_rerun(&[warning("LaTeX Warning: Label(s) may have changed. Rerun to get cross-references right.")]));
+        assert!(needs_rerun(&[warning("Package biblatex Warning: Please rerun LaTeX.")]));
+        assert!(needs_rerun(&[warning("Package rerunfilecheck Warning: File `main.out' has changed.")]));
+        assert!(!needs_rerun(&[warning("LaTeX Warning: There were undefined references.")]));
+        assert!(!needs_rerun(&[]));
+    }
+
+    #[test]
+    fn test_latex_transform_reruns_until_settled() {
+        let dir = std::env::current_dir().unwrap().join("target").join("test_latex_rerun");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        // Asks for a rerun on the first two passes.
+        let script = "echo run >> passes; if [ $(wc -l < passes) -lt 3 ]; then \
+            echo 'LaTeX Warning: Label(s) may have changed. Rerun to get cross-references right.' > main.log; \
+            else echo 'Output written on main.pdf' > main.log; fi";
+        let engine = || {
+            ShellTransform::new("engine", HashSet::new(), HashSet::new(), "sh", vec!["-c".to_string(), script.to_string()])
+                .with_working_dir(dir.clone())
+        };
+        let passes = || std::fs::read_to_string(dir.join("passes")).unwrap().lines().count();
+
+        assert!(LatexTransform::new(engine(), dir.join("main.log")).execute(&CancellationToken::new(), &|_| {}).is_ok());
+        assert_eq!(passes(), 3);
+
+        std::fs::remove_file(dir.join("passes")).unwrap();
+        assert!(LatexTransform::new(engine(), dir.join("main.log")).with_max_reruns(1).execute(&CancellationToken::new(), &|_| {}).is_ok());
+        assert_eq!(passes(), 2);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_latex_transform_reports_log_error() {
+        let dir = std::env::current_dir().unwrap().join("target").join("test_latex_error");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = "printf '(./main.tex\\n! Undefined control sequence.\\nl.3 \\\\foo\\n' > main.log; exit 1";
+        let engine = ShellTransform::new("engine", HashSet::new(), HashSet::new(), "sh", vec!["-c".to_string(), script.to_string()])
+            .with_working_dir(dir.clone());
+        let error = LatexTransform::new(engine, dir.join("main.log")).execute(&CancellationToken::new(), &|_| {}).unwrap_err();
+        assert_eq!(error.to_string(), "main.tex:3: Undefined control sequence.");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_shell_transform_sandbox() {
+        let dir = std::env::current_dir().unwrap().join("target").join("test_sandbox");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.tex"), "").unwrap();
+        std::fs::write(dir.join("private.tex"), "").unwrap();
+        let dir = dir.canonicalize().unwrap();
+        let inputs = HashSet::from([ArtifactId(dir.join("main.tex").to_string_lossy().into_owned())]);
+        // Stands in for an engine run with `-recorder`; fails unless shell escape is disabled.
+        let engine = |reads: &str| {
+            let script = format!("test \"$shell_escape\" = f && printf 'PWD {}\\nINPUT main.tex\\n{}' > main.fls", dir.display(), reads);
+            ShellTransform::new("engine", inputs.clone(), HashSet::new(), "sh", vec!["-c".to_string(), script])
+                .with_working_dir(dir.clone())
+                .with_sandbox(SandboxPolicy::default())
+                .with_recorder(dir.join("main.fls"))
+        };
+
+        assert!(engine("").execute(&CancellationToken::new(), &|_| {}).is_ok());
+        let Err(TransformError::Sandbox { violations }) = engine("INPUT private.tex\\n").execute(&CancellationToken::new(), &|_| {}) else {
+            panic!("expected a sandbox violation");
+        };
+        assert_eq!(violations, vec![crate::SandboxViolation { access: crate::Access::Read, path: dir.join("private.tex") }]);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_bibliography_tool_exit_codes() {
+        assert!(BibliographyTool::Bibtex.succeeded(Some(1)));
+        assert!(!BibliographyTool::Bibtex.succeeded(Some(2)));
+        assert!(!BibliographyTool::Biber.succeeded(Some(1)));
+        assert!(!BibliographyTool::Biber.succeeded(None));
+        assert_eq!(BibliographyTool::Biber.command(), "biber");
+    }
+
+    #[test]
+    fn test_index_tool_args() {
+        let job = PathBuf::from("build/main");
+        assert_eq!(IndexTool::Makeindex.args(&job), ["-o", "build/main.ind", "build/main.idx"]);
+        assert_eq!(IndexTool::Xindy.command(), "texindy");
+        assert_eq!(IndexTool::Makeglossaries.args(&job), ["-d", "build", "main"]);
     }
 }
@@ -0,0 +1,171 @@
+use crate::ArtifactId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// TeX Live image used when none is configured. Historic images are frozen, so builds
+/// keep getting the same distribution.
+pub const DEFAULT_IMAGE: &str = "docker.io/texlive/texlive:TL2024-historic";
+
+/// The program that runs containers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerRuntime {
+    Docker,
+    Podman,
+}
+
+impl ContainerRuntime {
+    pub fn command(self) -> &'static str {
+        match self {
+            ContainerRuntime::Docker => "docker",
+            ContainerRuntime::Podman => "podman",
+        }
+    }
+}
+
+impl fmt::Display for ContainerRuntime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.command())
+    }
+}
+
+impl FromStr for ContainerRuntime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "docker" => Ok(ContainerRuntime::Docker),
+            "podman" => Ok(ContainerRuntime::Podman),
+            _ => Err(format!("unknown container runtime '{}' (expected docker or podman)", s)),
+        }
+    }
+}
+
+/// A host path made visible inside the container, at the same path.
+///
+/// Keeping paths identical means arguments, `.fls` files and logs need no translation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Mount {
+    pub path: PathBuf,
+    pub read_only: bool,
+}
+
+impl Mount {
+    /// Mounts the declared inputs read-only and the directories of the declared outputs
+    /// read-write. Inputs that do not exist yet are skipped.
+    pub fn for_artifacts(inputs: &HashSet<ArtifactId>, outputs: &HashSet<ArtifactId>) -> Vec<Mount> {
+        let mut output_dirs: Vec<PathBuf> =
+            outputs.iter().filter_map(|id| Path::new(&id.0).parent().map(Path::to_path_buf)).collect();
+        output_dirs.sort();
+        output_dirs.dedup();
+        let mut input_files: Vec<PathBuf> = inputs
+            .iter()
+            .map(|id| PathBuf::from(&id.0))
+            .filter(|path| path.exists() && !output_dirs.iter().any(|dir| path.starts_with(dir)))
+            .collect();
+        input_files.sort();
+        input_files
+            .into_iter()
+            .map(|path| Mount { path, read_only: true })
+            .chain(output_dirs.into_iter().map(|path| Mount { path, read_only: false }))
+            .collect()
+    }
+}
+
+/// Runs build programs in a throwaway container from a pinned TeX image.
+///
+/// The container has no network access and sees only the mounted paths, so a build
+/// cannot depend on anything else on the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Container {
+    pub runtime: ContainerRuntime,
+    pub image: String,
+}
+
+impl Container {
+    pub fn new(runtime: ContainerRuntime, image: &str) -> Self {
+        Self {
+            runtime,
+            image: image.to_string(),
+        }
+    }
+
+    /// Returns a container name no other build uses, for [`Container::command`].
+    pub fn unique_name() -> String {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        format!("ferrotex-{}-{}", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Returns the command running `program` in `working_dir` inside a new container called `name`.
+    pub fn command(&self, name: &str, program: &str, args: &[String], working_dir: &Path, mounts: &[Mount], env: &[(&str, &str)]) -> Command {
+        let mut cmd = Command::new(self.runtime.command());
+        cmd.args(["run", "--rm", "--network=none"]);
+        cmd.arg(format!("--name={}", name));
+        // Files written to the mounts should belong to the user, not to root.
+        #[cfg(unix)]
+        if let Ok(meta) = std::fs::metadata(working_dir) {
+            use std::os::unix::fs::MetadataExt;
+            cmd.arg(format!("--user={}:{}", meta.uid(), meta.gid()));
+        }
+        for mount in mounts {
+            let mode = if mount.read_only { "ro" } else { "rw" };
+            cmd.arg(format!("--volume={}:{}:{}", mount.path.display(), mount.path.display(), mode));
+        }
+        for (key, value) in env {
+            cmd.arg(format!("--env={}={}", key, value));
+        }
+        cmd.arg(format!("--workdir={}", working_dir.display()));
+        cmd.arg(&self.image).arg(program).args(args);
+        cmd
+    }
+
+    /// Returns the command stopping the container `name`.
+    ///
+    /// Killing the runtime's client, e.g. when a build is cancelled, leaves the container
+    /// running; this stops it, and `--rm` removes it.
+    pub fn kill(&self, name: &str) -> Command {
+        let mut cmd = Command::new(self.runtime.command());
+        cmd.args(["kill", name]);
+        cmd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_container_command() {
+        let dir = std::env::current_dir().unwrap().join("target").join("test_container");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.tex"), "").unwrap();
+        let id = |path: PathBuf| ArtifactId(path.to_string_lossy().into_owned());
+        let inputs = HashSet::from([id(dir.join("main.tex")), id(dir.join("build/main.aux")), id(dir.join("missing.png"))]);
+        let outputs = HashSet::from([id(dir.join("build/main.aux")), id(dir.join("build/main.pdf"))]);
+
+        let mounts = Mount::for_artifacts(&inputs, &outputs);
+        assert_eq!(mounts, vec![
+            Mount { path: dir.join("main.tex"), read_only: true },
+            Mount { path: dir.join("build"), read_only: false },
+        ]);
+
+        let container = Container::new("podman".parse().unwrap(), DEFAULT_IMAGE);
+        let name = Container::unique_name();
+        assert_ne!(name, Container::unique_name());
+        let cmd = container.command(&name, "pdflatex", &["main.tex".to_string()], &dir, &mounts, &[("shell_escape", "f")]);
+        assert_eq!(cmd.get_program(), "podman");
+        let args: Vec<String> = cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect();
+        assert_eq!(args[..4], ["run", "--rm", "--network=none", &format!("--name={}", name)]);
+        let kill: Vec<String> = container.kill(&name).get_args().map(|a| a.to_string_lossy().into_owned()).collect();
+        assert_eq!(kill, ["kill", name.as_str()]);
+        assert!(args.contains(&format!("--volume={}:{}:ro", dir.join("main.tex").display(), dir.join("main.tex").display())));
+        assert!(args.contains(&"--env=shell_escape=f".to_string()));
+        assert_eq!(args[args.len() - 3..], [DEFAULT_IMAGE, "pdflatex", "main.tex"]);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,857 @@
+use crate::cache::{transform_key, BuildCache, CacheEntry, CacheStats};
+use crate::{ArtifactId, BuildGraph, CancellationToken, Lockfile, TransformError};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// What happened to a transform during [`BuildGraph::execute`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum TransformOutcome {
+    /// The transform ran and succeeded.
+    Succeeded,
+    /// The transform was up to date according to the build cache and did not run.
+    Cached,
+    /// The transform ran and returned an error.
+    Failed { error: TransformError },
+    /// The transform did not run because `dependency` (a transform index) failed or was skipped.
+    Skipped { dependency: usize },
+    /// The build was cancelled before the transform finished.
+    Cancelled,
+}
+
+impl TransformOutcome {
+    /// Returns true if the outputs of the transform are up to date.
+    pub fn is_success(&self) -> bool {
+        matches!(self, TransformOutcome::Succeeded | TransformOutcome::Cached)
+    }
+}
+
+/// The result of one transform.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransformReport {
+    /// Position of the transform in the graph, in the order it was added.
+    pub index: usize,
+    pub description: String,
+    pub outcome: TransformOutcome,
+    /// Wall-clock time spent in `Transform::execute`; zero for skipped transforms.
+    pub duration: Duration,
+}
+
+/// Progress of a build, sent while [`Executor::run`] runs (see [`Executor::with_events`]).
+///
+/// Every transform gets exactly one [`BuildEvent::TransformFinished`], whether it ran or
+/// not; only transforms that actually run are started and log output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum BuildEvent {
+    /// The transform at `index` started running.
+    TransformStarted { index: usize, description: String },
+    /// The outcome of the transform at `index` is known.
+    TransformFinished { index: usize, description: String, outcome: TransformOutcome, duration: Duration },
+    /// A line printed by a program the transform at `index` runs.
+    LogChunk { index: usize, text: String },
+    /// Every transform finished; sent last.
+    BuildFinished { success: bool, cancelled: bool },
+}
+
+/// The result of executing a build graph.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildReport {
+    /// One entry per transform, in graph order.
+    pub transforms: Vec<TransformReport>,
+    /// Indices of the transforms that were executed, in the order they started.
+    pub execution_order: Vec<usize>,
+    /// Cache hits and misses; all zero when no cache was used.
+    pub cache: CacheStats,
+}
+
+impl BuildReport {
+    /// Returns true if every transform succeeded or was up to date.
+    pub fn success(&self) -> bool {
+        self.transforms.iter().all(|t| t.outcome.is_success())
+    }
+
+    /// Returns the transforms that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &TransformReport> {
+        self.transforms.iter().filter(|t| matches!(t.outcome, TransformOutcome::Failed { .. }))
+    }
+
+    /// Returns true if the build was cancelled before every transform finished.
+    pub fn cancelled(&self) -> bool {
+        self.transforms.iter().any(|t| t.outcome == TransformOutcome::Cancelled)
+    }
+}
+
+/// Why a transform would run (see [`Executor::execute_dry_run`]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "camelCase")]
+pub enum RunReason {
+    /// No cache is used, or it has no entry for the transform.
+    NotCached,
+    /// An input or output is not an artifact of the graph, so the transform cannot be cached.
+    Uncacheable,
+    /// These inputs changed since the last run, or were added or removed.
+    InputsChanged { artifacts: Vec<ArtifactId> },
+    /// These outputs changed since the last run, e.g. a deleted or edited PDF.
+    OutputsChanged { artifacts: Vec<ArtifactId> },
+    /// The transform at `dependency` runs first and may change the inputs.
+    DependencyRuns { dependency: usize },
+}
+
+/// What [`Executor::run`] would do with one transform.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DryRunEntry {
+    pub index: usize,
+    pub description: String,
+    /// Empty if the transform is a cache hit.
+    pub reasons: Vec<RunReason>,
+}
+
+impl DryRunEntry {
+    pub fn would_run(&self) -> bool {
+        !self.reasons.is_empty()
+    }
+}
+
+/// The result of [`Executor::execute_dry_run`], one entry per transform in graph order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DryRunReport {
+    pub transforms: Vec<DryRunEntry>,
+}
+
+impl DryRunReport {
+    /// The transforms that would run, in graph order.
+    pub fn would_run(&self) -> impl Iterator<Item = &DryRunEntry> {
+        self.transforms.iter().filter(|entry| entry.would_run())
+    }
+}
+
+impl BuildGraph {
+    /// Runs all transforms, using one worker per available CPU.
+    ///
+    /// See [`Executor::run`].
+    pub fn execute(&self) -> Result<BuildReport, String> {
+        Executor::new(self).run()
+    }
+
+    /// Runs all transforms with at most `jobs` running at once.
+    pub fn execute_with_jobs(&self, jobs: usize) -> Result<BuildReport, String> {
+        Executor::new(self).with_jobs(jobs).run()
+    }
+
+    /// For each transform, the transforms that consume one of its outputs.
+    pub(crate) fn dependents(&self) -> Vec<Vec<usize>> {
+        let mut producers: HashMap<ArtifactId, Vec<usize>> = HashMap::new();
+        for (index, transform) in self.transforms.iter().enumerate() {
+            for output in transform.outputs() {
+                producers.entry(output).or_default().push(index);
+            }
+        }
+        let mut dependents = vec![Vec::new(); self.transforms.len()];
+        for (index, transform) in self.transforms.iter().enumerate() {
+            let mut dependencies: Vec<usize> =
+                transform.inputs().iter().filter_map(|input| producers.get(input)).flatten().copied().collect();
+            dependencies.sort_unstable();
+            dependencies.dedup();
+            for dependency in dependencies {
+                dependents[dependency].push(index);
+            }
+        }
+        dependents
+    }
+}
+
+/// Schedules the transforms of a [`BuildGraph`].
+pub struct Executor<'a> {
+    graph: &'a BuildGraph,
+    jobs: usize,
+    cache: Option<&'a mut BuildCache>,
+    locked: Option<(&'a Lockfile, &'a Path)>,
+    cancel: CancellationToken,
+    timeout: Option<Duration>,
+    events: Option<mpsc::Sender<BuildEvent>>,
+}
+
+/// What a worker reports back for one transform.
+enum WorkerResult {
+    Cached,
+    Ran(Result<(), TransformError>, Duration, Option<CacheEntry>),
+}
+
+impl<'a> Executor<'a> {
+    /// Creates an executor using one worker per available CPU and no cache.
+    pub fn new(graph: &'a BuildGraph) -> Self {
+        let jobs = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        Self { graph, jobs, cache: None, locked: None, cancel: CancellationToken::new(), timeout: None, events: None }
+    }
+
+    /// Limits the number of transforms running at once.
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Skips transforms that are up to date in `cache`, and records the transforms that ran.
+    ///
+    /// Only transforms whose inputs and outputs are all artifacts of the graph can be
+    /// cached; the others always run.
+    pub fn with_cache(mut self, cache: &'a mut BuildCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Strict mode: refuses to run unless every file in `lockfile` still has its locked hash.
+    ///
+    /// Relative lockfile paths are resolved against `root` (see [`Lockfile::verify`]).
+    pub fn with_lockfile(mut self, lockfile: &'a Lockfile, root: &'a Path) -> Self {
+        self.locked = Some((lockfile, root));
+        self
+    }
+
+    /// Stops the build once `cancel` fires: running transforms are asked to stop and no
+    /// further transform starts.
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Stops each transform that runs longer than `timeout`; it then fails with
+    /// [`TransformError::TimedOut`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sends a [`BuildEvent`] to `events` for each step of the build, in the order they
+    /// happen. Sending stops silently if the receiver is dropped.
+    pub fn with_events(mut self, events: mpsc::Sender<BuildEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Runs all transforms in dependency order.
+    ///
+    /// A transform depends on every transform producing one of its inputs. Transforms
+    /// become ready once all their dependencies succeeded; a failure marks everything
+    /// downstream as skipped while independent branches keep running. After cancellation,
+    /// every transform that did not finish is reported as [`TransformOutcome::Cancelled`].
+    ///
+    /// Returns an error without running anything if the transforms form a cycle, or in
+    /// strict mode if a locked file changed.
+    pub fn run(mut self) -> Result<BuildReport, String> {
+        let graph = self.graph;
+        let dependents = graph.dependents();
+        let mut pending: Vec<usize> = vec![0; graph.transforms.len()];
+        for targets in &dependents {
+            for &target in targets {
+                pending[target] += 1;
+            }
+        }
+        topological_order(&dependents, &pending, |index| graph.transforms[index].description())?;
+        if let Some((lockfile, root)) = self.locked {
+            let violations = lockfile.verify(root);
+            if !violations.is_empty() {
+                let details: Vec<String> = violations.iter().map(ToString::to_string).collect();
+                return Err(format!("Sources differ from the lockfile: {}", details.join("; ")));
+            }
+        }
+
+        let mut outcomes: Vec<Option<(TransformOutcome, Duration)>> = vec![None; graph.transforms.len()];
+        let mut ready: VecDeque<usize> = (0..pending.len()).filter(|&i| pending[i] == 0).collect();
+        let mut started = Vec::new();
+        let mut stats = CacheStats::default();
+        let use_cache = self.cache.is_some();
+        let events = self.events.as_ref();
+        let finished = |index: usize, outcome: &(TransformOutcome, Duration)| {
+            if let Some(events) = events {
+                let _ = events.send(BuildEvent::TransformFinished {
+                    index,
+                    description: graph.transforms[index].description(),
+                    outcome: outcome.0.clone(),
+                    duration: outcome.1,
+                });
+            }
+        };
+
+        std::thread::scope(|scope| {
+            let (tx, rx) = mpsc::channel::<(usize, WorkerResult)>();
+            let mut running = 0;
+            loop {
+                while running < self.jobs && !self.cancel.is_cancelled() {
+                    let Some(index) = ready.pop_front() else {
+                        break;
+                    };
+                    let transform = &graph.transforms[index];
+                    let key = transform_key(transform.as_ref());
+                    let previous = self.cache.as_ref().and_then(|cache| cache.entries.get(&key).cloned());
+                    let cancel = match self.timeout {
+                        Some(timeout) => self.cancel.with_timeout(timeout),
+                        None => self.cancel.clone(),
+                    };
+                    let tx = tx.clone();
+                    started.push(index);
+                    running += 1;
+                    scope.spawn(move || {
+                        let inputs = graph.fingerprints(&transform.inputs());
+                        if let (Some(previous), Some(inputs)) = (&previous, &inputs) {
+                            let up_to_date = previous.inputs == *inputs
+                                && graph.fingerprints(&transform.outputs()).is_some_and(|outputs| outputs == previous.outputs);
+                            if up_to_date {
+                                let _ = tx.send((index, WorkerResult::Cached));
+                                return;
+                            }
+                        }
+                        let send = |event: BuildEvent| {
+                            if let Some(events) = events {
+                                let _ = events.send(event);
+                            }
+                        };
+                        send(BuildEvent::TransformStarted { index, description: transform.description() });
+                        let log = |line: &str| send(BuildEvent::LogChunk { index, text: line.to_string() });
+                        let start = Instant::now();
+                        let result = transform.execute(&cancel, &log);
+                        let duration = start.elapsed();
+                        let entry = match (&result, use_cache, inputs) {
+                            (Ok(()), true, Some(inputs)) => graph
+                                .fingerprints(&transform.outputs())
+                                .map(|outputs| CacheEntry { inputs, outputs }),
+                            _ => None,
+                        };
+                        let _ = tx.send((index, WorkerResult::Ran(result, duration, entry)));
+                    });
+                }
+                if running == 0 {
+                    break;
+                }
+                let Ok((index, result)) = rx.recv() else {
+                    break;
+                };
+                running -= 1;
+                let (outcome, duration) = match result {
+                    WorkerResult::Cached => {
+                        stats.hits += 1;
+                        (TransformOutcome::Cached, Duration::ZERO)
+                    }
+                    WorkerResult::Ran(result, duration, entry) => {
+                        if use_cache {
+                            stats.misses += 1;
+                        }
+                        if let (Some(cache), Some(entry)) = (self.cache.as_deref_mut(), entry) {
+                            cache.entries.insert(transform_key(graph.transforms[index].as_ref()), entry);
+                        }
+                        match result {
+                            Ok(()) => (TransformOutcome::Succeeded, duration),
+                            Err(TransformError::Cancelled) => (TransformOutcome::Cancelled, duration),
+                            Err(error) => (TransformOutcome::Failed { error }, duration),
+                        }
+                    }
+                };
+                let (success, cancelled) = (outcome.is_success(), outcome == TransformOutcome::Cancelled);
+                let outcome = (outcome, duration);
+                finished(index, &outcome);
+                outcomes[index] = Some(outcome);
+                if success {
+                    for &dependent in &dependents[index] {
+                        pending[dependent] -= 1;
+                        if pending[dependent] == 0 && outcomes[dependent].is_none() {
+                            ready.push_back(dependent);
+                        }
+                    }
+                } else if !cancelled {
+                    // Dependents of a cancelled transform are reported as cancelled too.
+                    for skipped in skip_dependents(index, &dependents, &mut outcomes) {
+                        finished(skipped, outcomes[skipped].as_ref().expect("just skipped"));
+                    }
+                }
+            }
+        });
+
+        let transforms = outcomes
+            .into_iter()
+            .enumerate()
+            .map(|(index, outcome)| {
+                // Only a cancelled build leaves transforms neither run nor skipped.
+                let (outcome, duration) = outcome.unwrap_or_else(|| {
+                    let cancelled = (TransformOutcome::Cancelled, Duration::ZERO);
+                    finished(index, &cancelled);
+                    cancelled
+                });
+                TransformReport { index, description: graph.transforms[index].description(), outcome, duration }
+            })
+            .collect::<Vec<TransformReport>>();
+        let execution_order =
+            started.into_iter().filter(|&index| transforms[index].outcome != TransformOutcome::Cached).collect();
+        let report = BuildReport { transforms, execution_order, cache: stats };
+        if let Some(events) = events {
+            let _ = events.send(BuildEvent::BuildFinished { success: report.success(), cancelled: report.cancelled() });
+        }
+        Ok(report)
+    }
+
+    /// Reports which transforms [`Executor::run`] would execute and why, without running
+    /// anything, to explain slow or surprising rebuilds.
+    ///
+    /// A transform is a cache hit if its inputs and outputs still have the fingerprints of
+    /// its last run. Otherwise every reason that applies is listed, with the changed artifacts.
+    /// A transform downstream of one that runs is reported as running too, since its
+    /// inputs are only known once its dependencies finished.
+    pub fn execute_dry_run(&self) -> Result<DryRunReport, String> {
+        let graph = self.graph;
+        let dependents = graph.dependents();
+        let mut dependencies = vec![Vec::new(); graph.transforms.len()];
+        for (index, targets) in dependents.iter().enumerate() {
+            for &target in targets {
+                dependencies[target].push(index);
+            }
+        }
+        let pending: Vec<usize> = dependencies.iter().map(Vec::len).collect();
+        let order = topological_order(&dependents, &pending, |index| graph.transforms[index].description())?;
+
+        let mut reasons: Vec<Vec<RunReason>> = vec![Vec::new(); graph.transforms.len()];
+        for index in order {
+            let transform = &graph.transforms[index];
+            let mut found = Vec::new();
+            let previous = self.cache.as_ref().and_then(|cache| cache.entries.get(&transform_key(transform.as_ref())));
+            match (graph.fingerprints(&transform.inputs()), graph.fingerprints(&transform.outputs())) {
+                (Some(inputs), Some(outputs)) => match previous {
+                    None => found.push(RunReason::NotCached),
+                    Some(previous) => {
+                        let inputs = changed(&previous.inputs, &inputs);
+                        if !inputs.is_empty() {
+                            found.push(RunReason::InputsChanged { artifacts: inputs });
+                        }
+                        let outputs = changed(&previous.outputs, &outputs);
+                        if !outputs.is_empty() {
+                            found.push(RunReason::OutputsChanged { artifacts: outputs });
+                        }
+                    }
+                },
+                _ => found.push(RunReason::Uncacheable),
+            }
+            for &dependency in &dependencies[index] {
+                if !reasons[dependency].is_empty() {
+                    found.push(RunReason::DependencyRuns { dependency });
+                }
+            }
+            reasons[index] = found;
+        }
+
+        let transforms = reasons
+            .into_iter()
+            .enumerate()
+            .map(|(index, reasons)| DryRunEntry { index, description: graph.transforms[index].description(), reasons })
+            .collect();
+        Ok(DryRunReport { transforms })
+    }
+}
+
+/// The artifacts whose fingerprint differs between two runs, including added and removed ones.
+fn changed(previous: &BTreeMap<String, String>, current: &BTreeMap<String, String>) -> Vec<ArtifactId> {
+    let mut ids: Vec<&String> = previous.keys().chain(current.keys()).collect();
+    ids.sort();
+    ids.dedup();
+    ids.into_iter().filter(|id| previous.get(*id) != current.get(*id)).map(|id| ArtifactId(id.clone())).collect()
+}
+
+/// Orders the transforms with Kahn's algorithm, or fails if they form a cycle.
+fn topological_order(dependents: &[Vec<usize>], pending: &[usize], description: impl Fn(usize) -> String) -> Result<Vec<usize>, String> {
+    let mut pending = pending.to_vec();
+    let mut queue: Vec<usize> = (0..pending.len()).filter(|&i| pending[i] == 0).collect();
+    let mut ordered = Vec::new();
+    while let Some(index) = queue.pop() {
+        ordered.push(index);
+        for &dependent in &dependents[index] {
+            pending[dependent] -= 1;
+            if pending[dependent] == 0 {
+                queue.push(dependent);
+            }
+        }
+    }
+    if ordered.len() == pending.len() {
+        return Ok(ordered);
+    }
+    let index = pending.iter().position(|&p| p > 0).unwrap_or_default();
+    Err(format!("Cycle detected involving transform '{}'", description(index)))
+}
+
+/// Marks every transform downstream of `failed` as skipped, and returns them.
+fn skip_dependents(
+    failed: usize,
+    dependents: &[Vec<usize>],
+    outcomes: &mut [Option<(TransformOutcome, Duration)>],
+) -> Vec<usize> {
+    let mut skipped = Vec::new();
+    let mut stack = vec![failed];
+    while let Some(index) = stack.pop() {
+        for &dependent in &dependents[index] {
+            if outcomes[dependent].is_none() {
+                outcomes[dependent] = Some((TransformOutcome::Skipped { dependency: index }, Duration::ZERO));
+                skipped.push(dependent);
+                stack.push(dependent);
+            }
+        }
+    }
+    skipped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Artifact, CommandSpec, LogSink, Transform};
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier};
+
+    fn ids(names: &[&str]) -> HashSet<ArtifactId> {
+        names.iter().map(|n| ArtifactId(n.to_string())).collect()
+    }
+
+    struct Step {
+        name: &'static str,
+        inputs: HashSet<ArtifactId>,
+        outputs: HashSet<ArtifactId>,
+        fail: bool,
+        barrier: Option<Arc<Barrier>>,
+        runs: Arc<AtomicUsize>,
+        /// Content changed by each run, standing in for the files a real transform writes.
+        writes: Option<Arc<AtomicUsize>>,
+        /// How long the step runs, standing in for a slow program.
+        sleep: Duration,
+        /// Cancelled when the step starts, like a user pressing Ctrl-C.
+        cancels: Option<CancellationToken>,
+        /// Arguments of the program the step stands for.
+        args: Vec<String>,
+    }
+
+    impl Step {
+        fn new(name: &'static str, inputs: &[&str], outputs: &[&str]) -> Self {
+            Self {
+                name,
+                inputs: ids(inputs),
+                outputs: ids(outputs),
+                fail: false,
+                barrier: None,
+                runs: Arc::new(AtomicUsize::new(0)),
+                writes: None,
+                sleep: Duration::ZERO,
+                cancels: None,
+                args: Vec::new(),
+            }
+        }
+    }
+
+    impl Transform for Step {
+        fn description(&self) -> String { self.name.to_string() }
+        fn inputs(&self) -> HashSet<ArtifactId> { self.inputs.clone() }
+        fn outputs(&self) -> HashSet<ArtifactId> { self.outputs.clone() }
+        fn command(&self) -> Option<CommandSpec> {
+            Some(CommandSpec { program: self.name.to_string(), args: self.args.clone(), working_dir: None, image: None })
+        }
+        fn execute(&self, cancel: &CancellationToken, log: &LogSink) -> Result<(), TransformError> {
+            self.runs.fetch_add(1, Ordering::SeqCst);
+            log(&format!("running {}\n", self.name));
+            if let Some(writes) = &self.writes {
+                writes.fetch_add(1, Ordering::SeqCst);
+            }
+            if let Some(barrier) = &self.barrier {
+                barrier.wait();
+            }
+            if let Some(build) = &self.cancels {
+                build.cancel();
+            }
+            let start = Instant::now();
+            while start.elapsed() < self.sleep {
+                cancel.check()?;
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            if self.fail { Err(format!("{} failed", self.name).into()) } else { Ok(()) }
+        }
+    }
+
+    #[test]
+    fn test_execute_topological_order() {
+        let mut graph = BuildGraph::new();
+        // Added out of order on purpose: pdf needs aux, bbl needs aux.
+        graph.add_transform(Box::new(Step::new("pdflatex final", &["tex", "bbl"], &["pdf"])));
+        graph.add_transform(Box::new(Step::new("biber", &["aux"], &["bbl"])));
+        graph.add_transform(Box::new(Step::new("pdflatex draft", &["tex"], &["aux"])));
+
+        let report = graph.execute_with_jobs(4).unwrap();
+        assert!(report.success());
+        assert_eq!(report.execution_order, vec![2, 1, 0]);
+        assert_eq!(report.transforms[0].description, "pdflatex final");
+    }
+
+    #[test]
+    fn test_execute_runs_independent_transforms_in_parallel() {
+        // Both figures wait on the barrier, so this deadlocks unless they run together.
+        let barrier = Arc::new(Barrier::new(2));
+        let mut graph = BuildGraph::new();
+        for (name, output) in [("figure a", "a.pdf"), ("figure b", "b.pdf")] {
+            let mut step = Step::new(name, &["data"], &[output]);
+            step.barrier = Some(barrier.clone());
+            graph.add_transform(Box::new(step));
+        }
+        graph.add_transform(Box::new(Step::new("pdflatex", &["a.pdf", "b.pdf"], &["pdf"])));
+
+        let report = graph.execute_with_jobs(2).unwrap();
+        assert!(report.success());
+        assert_eq!(report.execution_order[2], 2);
+    }
+
+    #[test]
+    fn test_execute_propagates_failures() {
+        let mut graph = BuildGraph::new();
+        let mut broken = Step::new("broken figure", &["data"], &["fig.pdf"]);
+        broken.fail = true;
+        graph.add_transform(Box::new(broken));
+        let downstream = Step::new("pdflatex", &["tex", "fig.pdf"], &["pdf"]);
+        let downstream_runs = downstream.runs.clone();
+        graph.add_transform(Box::new(downstream));
+        graph.add_transform(Box::new(Step::new("compress", &["pdf"], &["small.pdf"])));
+        graph.add_transform(Box::new(Step::new("index", &["idx"], &["ind"])));
+
+        let report = graph.execute_with_jobs(1).unwrap();
+        assert!(!report.success());
+        assert_eq!(report.transforms[0].outcome, TransformOutcome::Failed { error: "broken figure failed".to_string().into() });
+        assert_eq!(report.transforms[1].outcome, TransformOutcome::Skipped { dependency: 0 });
+        assert_eq!(report.transforms[2].outcome, TransformOutcome::Skipped { dependency: 1 });
+        assert_eq!(report.transforms[3].outcome, TransformOutcome::Succeeded);
+        assert_eq!(report.failures().count(), 1);
+        assert_eq!(downstream_runs.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_execute_rejects_cycles() {
+        let mut graph = BuildGraph::new();
+        let first = Step::new("first", &["b"], &["a"]);
+        let runs = first.runs.clone();
+        graph.add_transform(Box::new(first));
+        graph.add_transform(Box::new(Step::new("second", &["a"], &["b"])));
+
+        assert!(graph.execute().unwrap_err().contains("Cycle detected"));
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_execute_strict_lockfile() {
+        let dir = std::env::current_dir().unwrap().join("target").join("test_executor_lockfile");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.tex"), "locked").unwrap();
+        let mut lockfile = Lockfile::new();
+        lockfile.entries.insert("main.tex".to_string(), crate::FileArtifact::new(dir.join("main.tex")).fingerprint());
+
+        let mut graph = BuildGraph::new();
+        let step = Step::new("pdflatex", &["main.tex"], &["main.pdf"]);
+        let runs = step.runs.clone();
+        graph.add_transform(Box::new(step));
+        assert!(Executor::new(&graph).with_lockfile(&lockfile, &dir).run().unwrap().success());
+
+        std::fs::write(dir.join("main.tex"), "edited").unwrap();
+        let error = Executor::new(&graph).with_lockfile(&lockfile, &dir).run().unwrap_err();
+        assert!(error.contains("main.tex has hash"), "{}", error);
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_execute_cancellation() {
+        let cancel = CancellationToken::new();
+        let mut graph = BuildGraph::new();
+        let mut figure = Step::new("figure", &["data"], &["fig.pdf"]);
+        figure.cancels = Some(cancel.clone());
+        figure.sleep = Duration::from_secs(10);
+        graph.add_transform(Box::new(figure));
+        let downstream = Step::new("pdflatex", &["fig.pdf"], &["pdf"]);
+        let downstream_runs = downstream.runs.clone();
+        graph.add_transform(Box::new(downstream));
+
+        let start = Instant::now();
+        let report = Executor::new(&graph).with_jobs(1).with_cancellation(cancel).run().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(report.cancelled());
+        assert_eq!(report.transforms[0].outcome, TransformOutcome::Cancelled);
+        assert_eq!(report.transforms[1].outcome, TransformOutcome::Cancelled);
+        assert_eq!(downstream_runs.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_execute_timeout() {
+        let mut graph = BuildGraph::new();
+        let mut slow = Step::new("slow figure", &["data"], &["fig.pdf"]);
+        slow.sleep = Duration::from_secs(10);
+        graph.add_transform(Box::new(slow));
+        graph.add_transform(Box::new(Step::new("pdflatex", &["fig.pdf"], &["pdf"])));
+        graph.add_transform(Box::new(Step::new("index", &["idx"], &["ind"])));
+
+        let timeout = Duration::from_millis(50);
+        let report = Executor::new(&graph).with_timeout(timeout).run().unwrap();
+        assert_eq!(report.transforms[0].outcome, TransformOutcome::Failed { error: TransformError::TimedOut { timeout } });
+        assert_eq!(report.transforms[1].outcome, TransformOutcome::Skipped { dependency: 0 });
+        assert_eq!(report.transforms[2].outcome, TransformOutcome::Succeeded);
+        assert!(!report.cancelled());
+    }
+
+    #[test]
+    fn test_execute_events() {
+        let mut graph = BuildGraph::new();
+        let mut broken = Step::new("broken figure", &["data"], &["fig.pdf"]);
+        broken.fail = true;
+        graph.add_transform(Box::new(broken));
+        graph.add_transform(Box::new(Step::new("pdflatex", &["tex", "fig.pdf"], &["pdf"])));
+
+        let (tx, rx) = mpsc::channel();
+        Executor::new(&graph).with_events(tx).run().unwrap();
+        // Durations vary between runs.
+        let events: Vec<BuildEvent> = rx
+            .into_iter()
+            .map(|event| match event {
+                BuildEvent::TransformFinished { index, description, outcome, .. } => {
+                    BuildEvent::TransformFinished { index, description, outcome, duration: Duration::ZERO }
+                }
+                event => event,
+            })
+            .collect();
+        let error = TransformError::from("broken figure failed".to_string());
+        assert_eq!(events, vec![
+            BuildEvent::TransformStarted { index: 0, description: "broken figure".to_string() },
+            BuildEvent::LogChunk { index: 0, text: "running broken figure\n".to_string() },
+            BuildEvent::TransformFinished {
+                index: 0,
+                description: "broken figure".to_string(),
+                outcome: TransformOutcome::Failed { error },
+                duration: Duration::ZERO,
+            },
+            BuildEvent::TransformFinished {
+                index: 1,
+                description: "pdflatex".to_string(),
+                outcome: TransformOutcome::Skipped { dependency: 0 },
+                duration: Duration::ZERO,
+            },
+            BuildEvent::BuildFinished { success: false, cancelled: false },
+        ]);
+        let json = serde_json::to_value(&events[4]).unwrap();
+        assert_eq!(json["event"], "buildFinished");
+    }
+
+    /// An artifact whose content is a counter.
+    struct Counter(ArtifactId, Arc<AtomicUsize>);
+
+    impl Artifact for Counter {
+        fn id(&self) -> ArtifactId { self.0.clone() }
+        fn fingerprint(&self) -> String { self.1.load(Ordering::SeqCst).to_string() }
+        fn path(&self) -> Option<PathBuf> { None }
+    }
+
+    #[test]
+    fn test_execute_with_cache() {
+        let mut graph = BuildGraph::new();
+        let content: Vec<Arc<AtomicUsize>> = (0..5).map(|_| Arc::new(AtomicUsize::new(0))).collect();
+        for (name, counter) in ["ch1.tex", "ch2.tex", "ch1.aux", "ch2.aux", "main.pdf"].iter().zip(&content) {
+            graph.add_artifact(Box::new(Counter(ArtifactId(name.to_string()), counter.clone())));
+        }
+        let mut steps = vec![
+            Step::new("chapter 1", &["ch1.tex"], &["ch1.aux"]),
+            Step::new("chapter 2", &["ch2.tex"], &["ch2.aux"]),
+            Step::new("link", &["ch1.aux", "ch2.aux"], &["main.pdf"]),
+            Step::new("unregistered", &["figure.svg"], &["figure.pdf"]),
+        ];
+        for (step, output) in steps.iter_mut().zip([2, 3, 4]) {
+            step.writes = Some(content[output].clone());
+        }
+        let runs: Vec<_> = steps.iter().map(|s| s.runs.clone()).collect();
+        for step in steps {
+            graph.add_transform(Box::new(step));
+        }
+        let run_counts = || runs.iter().map(|r| r.load(Ordering::SeqCst)).collect::<Vec<_>>();
+        let mut cache = BuildCache::new();
+
+        let report = Executor::new(&graph).with_cache(&mut cache).run().unwrap();
+        assert_eq!(report.cache, CacheStats { hits: 0, misses: 4 });
+        assert_eq!(cache.entries.len(), 3, "only transforms of registered artifacts are cached");
+
+        let report = Executor::new(&graph).with_cache(&mut cache).run().unwrap();
+        assert!(report.success());
+        assert_eq!(report.cache, CacheStats { hits: 3, misses: 1 });
+        assert_eq!(report.transforms[0].outcome, TransformOutcome::Cached);
+        assert_eq!(report.execution_order, vec![3]);
+        assert_eq!(run_counts(), vec![1, 1, 1, 2]);
+
+        // Editing chapter 2 reruns it and, since its output changes, the final link step.
+        content[1].fetch_add(1, Ordering::SeqCst);
+        let report = Executor::new(&graph).with_cache(&mut cache).run().unwrap();
+        assert_eq!(report.cache, CacheStats { hits: 1, misses: 3 });
+        assert_eq!(run_counts(), vec![1, 2, 2, 3]);
+
+        // A changed output (e.g. a deleted PDF) is rebuilt even though the inputs match.
+        content[4].store(42, Ordering::SeqCst);
+        Executor::new(&graph).with_cache(&mut cache).run().unwrap();
+        assert_eq!(run_counts(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_changed_args_miss_cache() {
+        let source = Arc::new(AtomicUsize::new(0));
+        let pdf = Arc::new(AtomicUsize::new(0));
+        let runs = Arc::new(AtomicUsize::new(0));
+        let graph = |args: &[&str]| {
+            let mut graph = BuildGraph::new();
+            graph.add_artifact(Box::new(Counter(ArtifactId("main.tex".to_string()), source.clone())));
+            graph.add_artifact(Box::new(Counter(ArtifactId("main.pdf".to_string()), pdf.clone())));
+            let mut step = Step::new("pdflatex", &["main.tex"], &["main.pdf"]);
+            step.args = args.iter().map(|arg| arg.to_string()).collect();
+            step.writes = Some(pdf.clone());
+            step.runs = runs.clone();
+            graph.add_transform(Box::new(step));
+            graph
+        };
+        let mut cache = BuildCache::new();
+
+        Executor::new(&graph(&["main.tex"])).with_cache(&mut cache).run().unwrap();
+        let report = Executor::new(&graph(&["main.tex"])).with_cache(&mut cache).run().unwrap();
+        assert_eq!(report.cache, CacheStats { hits: 1, misses: 0 });
+
+        // Same inputs, but e.g. `-synctex=1` added: the previous PDF does not match.
+        let report = Executor::new(&graph(&["-synctex=1", "main.tex"])).with_cache(&mut cache).run().unwrap();
+        assert_eq!(report.cache, CacheStats { hits: 0, misses: 1 });
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_execute_dry_run() {
+        let mut graph = BuildGraph::new();
+        let content: Vec<Arc<AtomicUsize>> = (0..4).map(|_| Arc::new(AtomicUsize::new(0))).collect();
+        for (name, counter) in ["ch1.tex", "ch2.tex", "ch2.aux", "main.pdf"].iter().zip(&content) {
+            graph.add_artifact(Box::new(Counter(ArtifactId(name.to_string()), counter.clone())));
+        }
+        let link = Step::new("link", &["ch1.tex", "ch2.aux"], &["main.pdf"]);
+        let runs = link.runs.clone();
+        graph.add_transform(Box::new(link));
+        graph.add_transform(Box::new(Step::new("chapter 2", &["ch2.tex"], &["ch2.aux"])));
+        graph.add_transform(Box::new(Step::new("figure", &["figure.svg"], &["figure.pdf"])));
+        let mut cache = BuildCache::new();
+
+        let report = Executor::new(&graph).with_cache(&mut cache).execute_dry_run().unwrap();
+        assert_eq!(report.transforms[1].reasons, vec![RunReason::NotCached]);
+        assert_eq!(report.transforms[2].reasons, vec![RunReason::Uncacheable]);
+        Executor::new(&graph).with_cache(&mut cache).run().unwrap();
+        let report = Executor::new(&graph).with_cache(&mut cache).execute_dry_run().unwrap();
+        assert_eq!(report.would_run().map(|entry| entry.index).collect::<Vec<_>>(), vec![2]);
+
+        content[1].fetch_add(1, Ordering::SeqCst);
+        content[3].fetch_add(1, Ordering::SeqCst);
+        let report = Executor::new(&graph).with_cache(&mut cache).execute_dry_run().unwrap();
+        assert_eq!(report.transforms[1].reasons, vec![RunReason::InputsChanged { artifacts: vec![ArtifactId("ch2.tex".to_string())] }]);
+        assert_eq!(report.transforms[0].reasons, vec![
+            RunReason::OutputsChanged { artifacts: vec![ArtifactId("main.pdf".to_string())] },
+            RunReason::DependencyRuns { dependency: 1 },
+        ]);
+        assert_eq!(runs.load(Ordering::SeqCst), 1, "a dry run executes nothing");
+        let json = serde_json::to_value(&report.transforms[0].reasons[1]).unwrap();
+        assert_eq!(json, serde_json::json!({ "reason": "dependencyRuns", "dependency": 1 }));
+    }
+}
@@ -1,11 +1,15 @@
 use std::path::PathBuf;
-use std::collections::{HashSet, HashMap};
+use std::collections::{BTreeMap, HashSet, HashMap};
 use serde::{Serialize, Deserialize};
+use ferrotex_package::{DistributionPackage, PackageIndex};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Lockfile {
     pub version: String,
     pub entries: HashMap<String, String>, // path -> sha256 hash
+    /// File name of each `.sty`/`.cls` read from the TeX distribution -> its package.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub packages: BTreeMap<String, DistributionPackage>,
 }
 
 impl Lockfile {
@@ -13,6 +17,7 @@ impl Lockfile {
         Self {
             version: "0.20.0".to_string(),
             entries: HashMap::new(),
+            packages: BTreeMap::new(),
         }
     }
 
@@ -27,13 +32,130 @@ impl Lockfile {
         let lock: Self = serde_json::from_str(&content)?;
         Ok(lock)
     }
+
+    /// Compares every locked file with its current content, sorted by path.
+    ///
+    /// Relative paths are resolved against `root`. Returns an empty list when the sources
+    /// are exactly those the lockfile was written for.
+    pub fn verify(&self, root: &std::path::Path) -> Vec<LockViolation> {
+        let mut paths: Vec<&String> = self.entries.keys().collect();
+        paths.sort();
+        paths
+            .into_iter()
+            .filter_map(|path| {
+                let expected = &self.entries[path];
+                let file = root.join(path);
+                if !file.is_file() {
+                    return Some(LockViolation::Missing { path: path.clone() });
+                }
+                let actual = FileArtifact::new(file).fingerprint();
+                (actual != *expected).then(|| LockViolation::Mismatch {
+                    path: path.clone(),
+                    expected: expected.clone(),
+                    actual,
+                })
+            })
+            .collect()
+    }
+
+    /// Locks the distribution package of every package and class read during a build.
+    ///
+    /// Files the index does not know, such as packages shipped with the document, are
+    /// left out; their content is locked through [`Lockfile::entries`] instead.
+    pub fn record_packages(&mut self, recorder: &FileRecorder, index: &PackageIndex) {
+        for file in recorder.packages() {
+            if let Some(package) = index.package_for_file(&file) {
+                self.packages.insert(file, package.clone());
+            }
+        }
+    }
+
+    /// Compares the locked packages with the distribution described by `index`.
+    pub fn verify_packages(&self, index: &PackageIndex) -> Vec<LockViolation> {
+        self.packages
+            .iter()
+            .filter_map(|(file, expected)| {
+                let actual = index.package_for_file(file);
+                (actual != Some(expected)).then(|| LockViolation::Package {
+                    file: file.clone(),
+                    expected: expected.clone(),
+                    actual: actual.cloned(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// A locked file that no longer matches the lockfile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LockViolation {
+    /// The file does not exist.
+    Missing { path: String },
+    /// The file exists but its SHA-256 hash differs from the locked one.
+    Mismatch { path: String, expected: String, actual: String },
+    /// The distribution installs `file` from another package or revision, or not at all.
+    Package { file: String, expected: DistributionPackage, actual: Option<DistributionPackage> },
+}
+
+impl LockViolation {
+    /// The path as written in the lockfile.
+    pub fn path(&self) -> &str {
+        match self {
+            LockViolation::Missing { path } | LockViolation::Mismatch { path, .. } => path,
+            LockViolation::Package { file, .. } => file,
+        }
+    }
+}
+
+impl std::fmt::Display for LockViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LockViolation::Missing { path } => write!(f, "{} is missing", path),
+            LockViolation::Mismatch { path, expected, actual } => {
+                write!(f, "{} has hash {} but {} is locked", path, actual, expected)
+            }
+            LockViolation::Package { file, expected, actual: Some(actual) } => write!(
+                f,
+                "{} comes from {} r{} but {} r{} is locked",
+                file, actual.name, actual.revision, expected.name, expected.revision
+            ),
+            LockViolation::Package { file, expected, actual: None } => {
+                write!(f, "{} is not installed but {} r{} is locked", file, expected.name, expected.revision)
+            }
+        }
+    }
 }
 
 pub mod artifacts;
+pub mod cache;
+pub mod cancel;
 pub mod compiler;
+pub mod container;
+pub mod executor;
+pub mod planner;
+pub mod postprocess;
+pub mod profile;
+pub mod project;
+pub mod recorder;
+pub mod remote;
+pub mod sandbox;
+pub mod spec;
+pub mod watcher;
 
-pub use artifacts::FileArtifact;
+pub use artifacts::{DirArtifact, FileArtifact, MemoryArtifact};
 pub use compiler::{Compiler, ShellTransform, PdfLatexTransform};
+pub use cache::{BuildCache, CacheStats};
+pub use cancel::CancellationToken;
+pub use container::{Container, ContainerRuntime};
+pub use executor::{BuildEvent, BuildReport, DryRunEntry, DryRunReport, Executor, RunReason, TransformOutcome, TransformReport};
+pub use planner::{Planner, ProjectSources};
+pub use postprocess::{PostProcessStep, PostProcessTransform};
+pub use profile::BuildProfile;
+pub use project::{ProjectManifest, ProjectTarget};
+pub use recorder::FileRecorder;
+pub use remote::{FetchTransform, RemoteArtifact, RemoteAsset};
+pub use sandbox::{Access, SandboxPolicy, SandboxViolation};
+pub use spec::{ArtifactSpec, CommandSpec, GraphSpec, TransformSpec};
 
 /// Represents a unique identifier for an artifact (content-addressed or path-based).
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -41,7 +163,7 @@ pub struct ArtifactId(pub String);
 
 /// An Artifact is a concrete input or output of the build process.
 /// Examples: Source File, PDF, Log File, Object File.
-pub trait Artifact {
+pub trait Artifact: Send + Sync {
     /// Returns the unique ID of this artifact.
     fn id(&self) -> ArtifactId;
     
@@ -51,11 +173,19 @@ pub trait Artifact {
     
     /// Returns the path to the artifact on disk, if applicable.
     fn path(&self) -> Option<PathBuf>;
+
+    /// Describes the artifact for [`BuildGraph::to_json`].
+    fn spec(&self) -> ArtifactSpec {
+        ArtifactSpec::Other { id: self.id(), path: self.path() }
+    }
 }
 
 /// A Transform turns a set of Input Artifacts into Output Artifacts.
 /// Examples: "Run pdflatex", "Copy file".
-pub trait Transform {
+///
+/// Transforms must be `Send + Sync` because [`BuildGraph::execute`] runs independent
+/// ones on separate threads.
+pub trait Transform: Send + Sync {
     /// Returns the name/description of this description.
     fn description(&self) -> String;
     
@@ -64,10 +194,55 @@ pub trait Transform {
     
     /// Returns the set of output Artifact IDs this transform produces.
     fn outputs(&self) -> HashSet<ArtifactId>;
+
+    /// The external program the transform runs, if any, for [`BuildGraph::to_json`].
+    fn command(&self) -> Option<CommandSpec> {
+        None
+    }
     
     /// Executes the transform implementation.
-    /// Returns true if successful.
-    fn execute(&self) -> Result<(), String>;
+    ///
+    /// Long-running transforms should stop with [`TransformError::Cancelled`] or
+    /// [`TransformError::TimedOut`] soon after `cancel` fires (see [`CancellationToken::check`]).
+    /// Output lines of the programs run are passed to `log` as they are printed.
+    fn execute(&self, cancel: &CancellationToken, log: &LogSink) -> Result<(), TransformError>;
+}
+
+/// Receives the output of a transform, one line (with its line break) at a time.
+pub type LogSink<'a> = dyn Fn(&str) + Send + Sync + 'a;
+
+/// Why a transform failed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum TransformError {
+    /// The program failed or could not be started.
+    Failed { message: String },
+    /// The program accessed files its [`SandboxPolicy`] does not allow.
+    Sandbox { violations: Vec<SandboxViolation> },
+    /// The build was cancelled while the transform ran.
+    Cancelled,
+    /// The transform ran longer than its timeout.
+    TimedOut { timeout: std::time::Duration },
+}
+
+impl From<String> for TransformError {
+    fn from(message: String) -> Self {
+        TransformError::Failed { message }
+    }
+}
+
+impl std::fmt::Display for TransformError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransformError::Failed { message } => f.write_str(message),
+            TransformError::Sandbox { violations } => {
+                let details: Vec<String> = violations.iter().map(ToString::to_string).collect();
+                write!(f, "sandbox violation: {}", details.join("; "))
+            }
+            TransformError::Cancelled => f.write_str("cancelled"),
+            TransformError::TimedOut { timeout } => write!(f, "timed out after {:?}", timeout),
+        }
+    }
 }
 
 /// The Build Graph represents the DAG of all transforms and artifacts.
@@ -99,6 +274,11 @@ impl BuildGraph {
     pub fn add_transform(&mut self, transform: Box<dyn Transform>) {
         self.transforms.push(transform);
     }
+
+    /// The artifacts of the graph, in no particular order.
+    pub fn artifacts(&self) -> impl Iterator<Item = &dyn Artifact> {
+        self.artifacts.values().map(|artifact| artifact.as_ref())
+    }
     
     /// Validates that the graph is a DAG (no cycles) and fully connected.
     pub fn validate(&self) -> Result<(), String> {
@@ -181,6 +361,53 @@ mod tests {
         let _ = std::fs::remove_file(temp_file);
     }
 
+    #[test]
+    fn test_lockfile_verify() {
+        let dir = std::env::current_dir().unwrap().join("target").join("test_lock_verify");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.tex"), "hello").unwrap();
+        std::fs::write(dir.join("chapter.tex"), "edited").unwrap();
+
+        let mut lock = Lockfile::new();
+        let hash = |path: &str| FileArtifact::new(dir.join(path)).fingerprint();
+        lock.entries.insert("main.tex".to_string(), hash("main.tex"));
+        lock.entries.insert("chapter.tex".to_string(), "0000".to_string());
+        lock.entries.insert("gone.sty".to_string(), "1111".to_string());
+        assert_eq!(lock.verify(&dir), vec![
+            LockViolation::Mismatch { path: "chapter.tex".to_string(), expected: "0000".to_string(), actual: hash("chapter.tex") },
+            LockViolation::Missing { path: "gone.sty".to_string() },
+        ]);
+
+        lock.entries.retain(|path, _| path == "main.tex");
+        assert!(lock.verify(&dir).is_empty());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_lockfile_packages() {
+        let package = |name: &str, revision: &str| DistributionPackage { name: name.to_string(), revision: revision.to_string() };
+        let mut index = PackageIndex::new();
+        index.files.insert("amsmath.sty".to_string(), package("amsmath", "63514"));
+        index.files.insert("article.cls".to_string(), package("latex", "65161"));
+        let recorder = FileRecorder::parse("INPUT /texmf/amsmath.sty\nINPUT /texmf/article.cls\nINPUT mymacros.sty\n");
+
+        let mut lock = Lockfile::new();
+        lock.record_packages(&recorder, &index);
+        assert_eq!(lock.packages.keys().collect::<Vec<_>>(), ["amsmath.sty", "article.cls"]);
+        assert!(lock.verify_packages(&index).is_empty());
+
+        // Another machine with an older amsmath and no article.cls.
+        index.files.insert("amsmath.sty".to_string(), package("amsmath", "60000"));
+        index.files.remove("article.cls");
+        let violations = lock.verify_packages(&index);
+        assert_eq!(violations[0].to_string(), "amsmath.sty comes from amsmath r60000 but amsmath r63514 is locked");
+        assert_eq!(violations[1].to_string(), "article.cls is not installed but latex r65161 is locked");
+
+        // Lockfiles written before packages were recorded still load.
+        let old: Lockfile = serde_json::from_str(r#"{"version": "0.20.0", "entries": {}}"#).unwrap();
+        assert!(old.packages.is_empty());
+    }
+
     struct MockArtifact(ArtifactId);
     impl Artifact for MockArtifact {
         fn id(&self) -> ArtifactId { self.0.clone() }
@@ -196,7 +423,7 @@ mod tests {
         fn description(&self) -> String { "mock".to_string() }
         fn inputs(&self) -> HashSet<ArtifactId> { self.inputs.clone() }
         fn outputs(&self) -> HashSet<ArtifactId> { self.outputs.clone() }
-        fn execute(&self) -> Result<(), String> { Ok(()) }
+        fn execute(&self, _cancel: &CancellationToken, _log: &LogSink) -> Result<(), TransformError> { Ok(()) }
     }
 
     #[test]
@@ -0,0 +1,523 @@
+use crate::compiler::{BibliographyTool, BibliographyTransform, IndexTool, IndexTransform, LatexTransform};
+use crate::{Artifact, ArtifactId, BuildGraph, Compiler, FetchTransform, FileArtifact, PostProcessTransform, RemoteArtifact, ShellTransform, Transform};
+use ferrotex_syntax::{SyntaxKind, SyntaxNode, SyntaxToken};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Extensions tried, in order, for `\includegraphics` paths without one.
+const GRAPHICS_EXTENSIONS: &[&str] = &["pdf", "png", "jpg", "jpeg", "eps"];
+
+/// The files a LaTeX project consists of, found by following `\input` and `\include`
+/// from the root document.
+///
+/// Paths are resolved against the directory of the root document, like TeX does.
+/// Files that do not exist are left out; the engine reports them when it runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectSources {
+    /// The canonical path of the root document.
+    pub root: PathBuf,
+    /// Every `.tex` file read by the root document, the root first.
+    pub tex_files: Vec<PathBuf>,
+    pub bibliographies: Vec<PathBuf>,
+    pub graphics: Vec<PathBuf>,
+    /// Packages and classes shipped with the project rather than the TeX distribution.
+    pub packages: Vec<PathBuf>,
+    /// The bibliography is managed by biblatex and processed by biber rather than BibTeX.
+    pub biblatex: bool,
+    /// The document calls `\makeindex`.
+    pub makeindex: bool,
+    /// The index is sorted with xindy rather than makeindex (`\usepackage[xindy]{imakeidx}`).
+    pub xindy: bool,
+    /// The document uses the glossaries package.
+    pub glossaries: bool,
+}
+
+impl ProjectSources {
+    /// Parses the root document and everything it includes.
+    pub fn discover(root: &Path) -> anyhow::Result<Self> {
+        let root = std::fs::canonicalize(root)?;
+        let base_dir = root.parent().map(Path::to_path_buf).unwrap_or_default();
+        let mut sources = Self { root: root.clone(), ..Self::default() };
+        let mut queue = vec![root];
+        let mut seen = HashSet::new();
+        while let Some(file) = queue.pop() {
+            if !seen.insert(file.clone()) {
+                continue;
+            }
+            let text = std::fs::read_to_string(&file)?;
+            sources.tex_files.push(file);
+            let included = sources.scan(&ferrotex_syntax::parse(&text).syntax(), &base_dir);
+            // Visit includes in document order.
+            queue.extend(included.into_iter().rev());
+        }
+        Ok(sources)
+    }
+
+    /// Records the references of one file and returns the `.tex` files it includes.
+    fn scan(&mut self, root: &SyntaxNode, base_dir: &Path) -> Vec<PathBuf> {
+        let mut included = Vec::new();
+        for element in root.descendants_with_tokens() {
+            if let Some(token) = element.as_token().filter(|t| t.kind() == SyntaxKind::Command) {
+                match token.text() {
+                    "\\includegraphics" => {
+                        if let Some(path) = argument(token).and_then(|p| resolve_graphic(base_dir, &p)) {
+                            push_unique(&mut self.graphics, path);
+                        }
+                    }
+                    "\\usepackage" | "\\RequirePackage" => {
+                        let packages = argument(token).unwrap_or_default();
+                        for path in packages.split(',').filter_map(|name| resolve(base_dir, name, "sty")) {
+                            push_unique(&mut self.packages, path);
+                        }
+                        let uses = |name: &str| packages.split(',').any(|p| p.trim() == name);
+                        self.biblatex |= uses("biblatex");
+                        self.glossaries |= uses("glossaries") || uses("glossaries-extra");
+                        let options = option(token).unwrap_or_default();
+                        self.xindy |= uses("imakeidx") && options.split(',').any(|o| o.trim().starts_with("xindy"));
+                    }
+                    "\\documentclass" => {
+                        if let Some(path) = argument(token).and_then(|name| resolve(base_dir, &name, "cls")) {
+                            push_unique(&mut self.packages, path);
+                        }
+                    }
+                    "\\makeindex" => self.makeindex = true,
+                    _ => {}
+                }
+                continue;
+            }
+            let Some(node) = element.as_node() else {
+                continue;
+            };
+            match node.kind() {
+                SyntaxKind::Include => {
+                    if let Some(path) = group_argument(node).and_then(|p| resolve(base_dir, &p, "tex")) {
+                        included.push(path);
+                    }
+                }
+                SyntaxKind::Bibliography => {
+                    if node.first_token().is_some_and(|t| t.text() == "\\addbibresource") {
+                        self.biblatex = true;
+                    }
+                    for path in group_argument(node).iter().flat_map(|paths| paths.split(',')) {
+                        if let Some(path) = resolve(base_dir, path, "bib") {
+                            push_unique(&mut self.bibliographies, path);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        included
+    }
+}
+
+/// Plans the build of a LaTeX project as a [`BuildGraph`].
+///
+/// The graph runs the engine once to produce the auxiliary files, then biber or
+/// BibTeX, makeindex or xindy and makeglossaries as the document requires, and a final engine pass that
+/// writes the PDF. Without auxiliary tools a single pass does both. The pass that
+/// writes the PDF repeats while the log asks for a rerun, up to
+/// [`Compiler::max_reruns`] times.
+///
+/// The [`BuildProfile`](crate::BuildProfile) of the compiler can drop the auxiliary tools and the reruns;
+/// a draft build is then a single pass that writes no PDF.
+///
+/// Every program runs under the [`SandboxPolicy`](crate::SandboxPolicy) of the compiler. The
+/// engine may only read the discovered sources and write to the output directory. With a
+/// [`Container`](crate::Container), only those files are mounted in the first place.
+pub struct Planner {
+    pub(crate) compiler: Compiler,
+}
+
+impl Planner {
+    /// Creates a planner for `compiler`; a relative output directory is taken relative
+    /// to the directory of the root document.
+    pub fn new(compiler: Compiler) -> Self {
+        Self { compiler }
+    }
+
+    /// Returns the directory the engine writes to when building `root`.
+    pub fn output_dir(&self, root: &Path) -> PathBuf {
+        root.parent().unwrap_or(Path::new("")).join(&self.compiler.output_dir)
+    }
+
+    /// Discovers the sources of `root` and plans their build.
+    ///
+    /// Creates the output directory, which the engine does not do itself.
+    pub fn plan(&self, root: &Path) -> anyhow::Result<BuildGraph> {
+        let sources = ProjectSources::discover(root)?;
+        let output_dir = self.output_dir(&sources.root);
+        std::fs::create_dir_all(&output_dir)?;
+        // `\include` writes a `.aux` per chapter, in the same relative directory.
+        let base_dir = sources.root.parent().unwrap_or(Path::new(""));
+        for file in &sources.tex_files {
+            if let Some(parent) = file.strip_prefix(base_dir).ok().and_then(Path::parent) {
+                std::fs::create_dir_all(output_dir.join(parent))?;
+            }
+        }
+        Ok(self.plan_sources(&sources))
+    }
+
+    /// Plans the build of already discovered sources without touching the disk.
+    pub fn plan_sources(&self, sources: &ProjectSources) -> BuildGraph {
+        let root = &sources.root;
+        let base_dir = root.parent().unwrap_or(Path::new("")).to_path_buf();
+        let output_dir = self.output_dir(root);
+        let stem = root.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        let output = |extension: &str| output_dir.join(format!("{}.{}", stem, extension));
+        let profile = self.compiler.profile;
+        let sandbox = self.compiler.sandbox;
+
+        let mut graph = BuildGraph::new();
+        // Remote assets are fetched first and read by every engine pass.
+        let mut fetches: Vec<Box<dyn Transform>> = Vec::new();
+        let mut asset_ids = HashSet::new();
+        for asset in &self.compiler.assets {
+            graph.add_artifact(Box::new(RemoteArtifact { url: asset.url.clone(), sha256: asset.sha256.clone() }));
+            let target = base_dir.join(&asset.path);
+            let file = FileArtifact::new(target.clone());
+            asset_ids.insert(file.id());
+            graph.add_artifact(Box::new(file));
+            fetches.push(Box::new(FetchTransform::new(asset.clone(), target, self.compiler.asset_cache.clone())));
+        }
+        let mut add = |path: PathBuf| {
+            let artifact = FileArtifact::new(path);
+            let id = artifact.id();
+            graph.add_artifact(Box::new(artifact));
+            id
+        };
+
+        let mut sources_ids: HashSet<ArtifactId> = sources
+            .tex_files
+            .iter()
+            .chain(&sources.graphics)
+            .chain(&sources.packages)
+            .map(|p| add(p.clone()))
+            .collect();
+        sources_ids.extend(asset_ids);
+        let bib_ids: HashSet<ArtifactId> = sources.bibliographies.iter().map(|p| add(p.clone())).collect();
+        let aux = add(output("aux"));
+        let pdf = profile.writes_pdf().then(|| add(output("pdf")));
+        // Written by the pass producing the PDF, for forward and inverse search.
+        let synctex = self.compiler.writes_synctex().then(|| add(output("synctex.gz")));
+
+        // Auxiliary tools run in the source directory on `<output dir>/<stem>`, so they
+        // find the bibliography files where the document names them.
+        let job = output_dir.join(&stem);
+        let mut tools: Vec<Box<dyn Transform>> = Vec::new();
+        let mut first_outputs = HashSet::from([aux.clone()]);
+        let mut final_inputs = sources_ids.clone();
+        final_inputs.insert(aux.clone());
+        if profile.auxiliary_tools() && !sources.bibliographies.is_empty() {
+            let bbl = add(output("bbl"));
+            let mut inputs = bib_ids;
+            let tool = if sources.biblatex {
+                let bcf = add(output("bcf"));
+                first_outputs.insert(bcf.clone());
+                inputs.insert(bcf);
+                BibliographyTool::Biber
+            } else {
+                inputs.insert(aux.clone());
+                BibliographyTool::Bibtex
+            };
+            final_inputs.insert(bbl.clone());
+            let outputs = HashSet::from([bbl]);
+            let mut transform = BibliographyTransform::new(tool, inputs, outputs, job.clone(), base_dir.clone()).with_sandbox(sandbox);
+            if let Some(container) = &self.compiler.container {
+                transform = transform.with_container(container.clone());
+            }
+            tools.push(Box::new(transform));
+        }
+        let mut index_step = |tool: IndexTool, from: &str, to: &str| {
+            let (input, output) = (add(output(from)), add(output(to)));
+            first_outputs.insert(input.clone());
+            final_inputs.insert(output.clone());
+            let (inputs, outputs) = (HashSet::from([input]), HashSet::from([output]));
+            let mut transform = IndexTransform::new(tool, inputs, outputs, job.clone(), base_dir.clone()).with_sandbox(sandbox);
+            if let Some(container) = &self.compiler.container {
+                transform = transform.with_container(container.clone());
+            }
+            tools.push(Box::new(transform));
+        };
+        if profile.auxiliary_tools() && sources.makeindex {
+            index_step(if sources.xindy { IndexTool::Xindy } else { IndexTool::Makeindex }, "idx", "ind");
+        }
+        if profile.auxiliary_tools() && sources.glossaries {
+            index_step(IndexTool::Makeglossaries, "glo", "gls");
+        }
+
+        let log = output("log");
+        let fls = output("fls");
+        let engine = |description: String, inputs, outputs, max_reruns| {
+            // `-recorder` lists the files read in `<stem>.fls`, for the lockfile.
+            let mut args = vec![
+                "-interaction=nonstopmode".to_string(),
+                "-recorder".to_string(),
+                format!("-output-directory={}", output_dir.display()),
+            ];
+            args.extend(profile.engine_args(&self.compiler.engine));
+            if self.compiler.writes_synctex() {
+                args.push("-synctex=1".to_string());
+            }
+            args.extend(sandbox.engine_args());
+            args.extend(self.compiler.extra_args.iter().cloned());
+            args.push(root.to_string_lossy().into_owned());
+            let mut run = ShellTransform::new(&description, inputs, outputs, &self.compiler.engine, args)
+                .with_working_dir(base_dir.clone())
+                .with_sandbox(sandbox)
+                .with_recorder(fls.clone());
+            if let Some(container) = &self.compiler.container {
+                run = run.with_container(container.clone());
+            }
+            LatexTransform::new(run, log.clone()).with_max_reruns(max_reruns)
+        };
+        let max_reruns = profile.max_reruns().unwrap_or(self.compiler.max_reruns);
+        let engine_pdf = pdf.clone();
+        for fetch in fetches {
+            graph.add_transform(fetch);
+        }
+        if tools.is_empty() {
+            first_outputs.extend(pdf);
+            first_outputs.extend(synctex);
+            let only = engine(self.compiler.engine.clone(), sources_ids, first_outputs, max_reruns);
+            graph.add_transform(Box::new(only));
+        } else {
+            // The auxiliary tools run next anyway, so the first pass never repeats.
+            let first = engine(format!("{} (first pass)", self.compiler.engine), sources_ids, first_outputs, 0);
+            graph.add_transform(Box::new(first));
+            for tool in tools {
+                graph.add_transform(tool);
+            }
+            let outputs = pdf.into_iter().chain(synctex).collect();
+            let last = engine(format!("{} (final pass)", self.compiler.engine), final_inputs, outputs, max_reruns);
+            graph.add_transform(Box::new(last));
+        }
+
+        if let Some(mut current) = engine_pdf {
+            let mut current_path = output("pdf");
+            for step in &self.compiler.post_process {
+                let extension = if step.writes_pdf() { "pdf" } else { "txt" };
+                let target = output(&format!("{}.{}", step.name(), extension));
+                let artifact = FileArtifact::new(target.clone());
+                let id = artifact.id();
+                graph.add_artifact(Box::new(artifact));
+                let input = (current.clone(), current_path.as_path());
+                let transform = PostProcessTransform::new(step.clone(), input, (id.clone(), &target), base_dir.clone());
+                graph.add_transform(Box::new(transform));
+                if step.writes_pdf() {
+                    (current, current_path) = (id, target);
+                }
+            }
+        }
+        graph
+    }
+}
+
+/// Returns the `{...}` argument following a command, skipping a `*` and one `[...]` option.
+fn argument(command: &SyntaxToken) -> Option<String> {
+    let mut next = command.next_sibling_or_token();
+    let mut in_option = false;
+    while let Some(element) = next {
+        match element.kind() {
+            SyntaxKind::Group if !in_option => return element.into_node().and_then(|group| group_text(&group)),
+            SyntaxKind::LBracket if !in_option => in_option = true,
+            SyntaxKind::RBracket if in_option => in_option = false,
+            _ if in_option => {}
+            SyntaxKind::Text if element.as_token().is_some_and(|t| t.text() == "*") => {}
+            _ => return None,
+        }
+        next = element.next_sibling_or_token();
+    }
+    None
+}
+
+/// Returns the `[...]` option directly following a command.
+fn option(command: &SyntaxToken) -> Option<String> {
+    let mut next = command.next_sibling_or_token();
+    let mut text: Option<String> = None;
+    while let Some(element) = next {
+        match (element.kind(), &mut text) {
+            (SyntaxKind::LBracket, None) => text = Some(String::new()),
+            (SyntaxKind::RBracket, Some(_)) => return text,
+            (_, Some(text)) => text.push_str(&element.to_string()),
+            (SyntaxKind::Text, None) if element.as_token().is_some_and(|t| t.text() == "*") => {}
+            _ => return None,
+        }
+        next = element.next_sibling_or_token();
+    }
+    None
+}
+
+/// Returns the argument of an `Include` or `Bibliography` node.
+fn group_argument(node: &SyntaxNode) -> Option<String> {
+    node.children().find(|n| n.kind() == SyntaxKind::Group).and_then(|group| group_text(&group))
+}
+
+fn group_text(group: &SyntaxNode) -> Option<String> {
+    let text = group.text().to_string();
+    let inner = text.strip_prefix('{')?;
+    let inner = inner.strip_suffix('}').unwrap_or(inner).trim();
+    (!inner.is_empty()).then(|| inner.to_string())
+}
+
+/// Resolves `path` against `base_dir`, appending `extension` if the path has none.
+fn resolve(base_dir: &Path, path: &str, extension: &str) -> Option<PathBuf> {
+    let path = Path::new(path.trim());
+    let path = if path.extension().is_some() { path.to_path_buf() } else { path.with_extension(extension) };
+    let path = base_dir.join(path);
+    path.is_file().then_some(path)
+}
+
+fn resolve_graphic(base_dir: &Path, path: &str) -> Option<PathBuf> {
+    let path = base_dir.join(path.trim());
+    if path.extension().is_some() && path.is_file() {
+        return Some(path);
+    }
+    GRAPHICS_EXTENSIONS.iter().map(|ext| path.with_extension(ext)).find(|p| p.is_file())
+}
+
+fn push_unique(paths: &mut Vec<PathBuf>, path: PathBuf) {
+    if !paths.contains(&path) {
+        paths.push(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BuildProfile, PostProcessStep, RemoteAsset};
+    use std::fs;
+
+    fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::current_dir().unwrap().join("target").join(name);
+        let _ = fs::remove_dir_all(&dir);
+        for (path, content) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        fs::canonicalize(dir).unwrap()
+    }
+
+    #[test]
+    fn test_discover_sources() {
+        let dir = project("test_planner_discover", &[
+            ("main.tex", "\\documentclass{thesis}\n\\usepackage{macros,amsmath}\n\\usepackage[style=apa]{biblatex}\n\\usepackage[xindy]{imakeidx}\n\\usepackage{glossaries}\n\\addbibresource{refs.bib}\n\\makeindex\n\\input{chapters/intro}\n\\include{missing}\n"),
+            ("chapters/intro.tex", "\\includegraphics[width=3cm]{figures/plot}\n\\input{chapters/intro}\n"),
+            ("refs.bib", "@book{a, title={A}}"),
+            ("thesis.cls", ""),
+            ("macros.sty", ""),
+            ("figures/plot.png", ""),
+        ]);
+
+        let sources = ProjectSources::discover(&dir.join("main.tex")).unwrap();
+        assert_eq!(sources.tex_files, vec![dir.join("main.tex"), dir.join("chapters/intro.tex")]);
+        assert_eq!(sources.bibliographies, vec![dir.join("refs.bib")]);
+        assert_eq!(sources.graphics, vec![dir.join("figures/plot.png")]);
+        assert_eq!(sources.packages, vec![dir.join("thesis.cls"), dir.join("macros.sty")]);
+        assert!(sources.biblatex);
+        assert!(sources.makeindex);
+        assert!(sources.xindy);
+        assert!(sources.glossaries);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_plan_wires_auxiliary_tools() {
+        let root = PathBuf::from("/project/main.tex");
+        let sources = ProjectSources {
+            root: root.clone(),
+            tex_files: vec![root.clone()],
+            bibliographies: vec![PathBuf::from("/project/refs.bib")],
+            makeindex: true,
+            glossaries: true,
+            ..ProjectSources::default()
+        };
+        let planner = Planner::new(Compiler::new("pdflatex", PathBuf::from("build")));
+        let graph = planner.plan_sources(&sources);
+        assert!(graph.validate().is_ok());
+
+        let descriptions: Vec<String> = graph.transforms.iter().map(|t| t.description()).collect();
+        assert_eq!(descriptions, ["pdflatex (first pass)", "bibtex", "makeindex", "makeglossaries", "pdflatex (final pass)"]);
+        let id = |path: &str| ArtifactId(path.to_string());
+        assert!(graph.transforms[1].inputs().contains(&id("/project/build/main.aux")));
+        assert!(graph.transforms[1].inputs().contains(&id("/project/refs.bib")));
+        assert_eq!(graph.transforms[2].outputs(), HashSet::from([id("/project/build/main.ind")]));
+        assert!(graph.transforms[3].inputs().contains(&id("/project/build/main.glo")));
+        let last = &graph.transforms[4];
+        assert!(last.inputs().contains(&id("/project/build/main.bbl")));
+        assert!(last.inputs().contains(&id("/project/build/main.gls")));
+        assert_eq!(last.outputs(), HashSet::from([id("/project/build/main.pdf"), id("/project/build/main.synctex.gz")]));
+        assert!(last.command().unwrap().args.contains(&"-synctex=1".to_string()));
+    }
+
+    #[test]
+    fn test_plan_single_pass() {
+        let root = PathBuf::from("/project/main.tex");
+        let sources = ProjectSources { root: root.clone(), tex_files: vec![root], ..ProjectSources::default() };
+        let graph = Planner::new(Compiler::new("xelatex", PathBuf::from("out"))).plan_sources(&sources);
+        assert_eq!(graph.transforms.len(), 1);
+        assert_eq!(graph.transforms[0].description(), "xelatex");
+        assert!(graph.transforms[0].outputs().contains(&ArtifactId("/project/out/main.pdf".to_string())));
+    }
+
+    #[test]
+    fn test_plan_post_processing() {
+        let root = PathBuf::from("/project/main.tex");
+        let sources = ProjectSources { root: root.clone(), tex_files: vec![root], ..ProjectSources::default() };
+        let steps = vec![PostProcessStep::Compress, PostProcessStep::ValidatePdfA { flavour: None }];
+        let compiler = Compiler::new("pdflatex", PathBuf::from("build")).with_post_process(steps.clone());
+        let graph = Planner::new(compiler).plan_sources(&sources);
+        assert!(graph.validate().is_ok());
+        let descriptions: Vec<String> = graph.transforms.iter().map(|t| t.description()).collect();
+        assert_eq!(descriptions, ["pdflatex", "qpdf", "verapdf"]);
+        let id = |path: &str| ArtifactId(path.to_string());
+        assert_eq!(graph.transforms[1].inputs(), HashSet::from([id("/project/build/main.pdf")]));
+        assert_eq!(graph.transforms[2].inputs(), HashSet::from([id("/project/build/main.compressed.pdf")]));
+        assert_eq!(graph.transforms[2].outputs(), HashSet::from([id("/project/build/main.pdfa.txt")]));
+
+        // Draft builds write no PDF to process.
+        let compiler = Compiler::new("pdflatex", PathBuf::from("build")).with_profile(BuildProfile::Draft).with_post_process(steps);
+        assert_eq!(Planner::new(compiler).plan_sources(&sources).transforms.len(), 1);
+    }
+
+    #[test]
+    fn test_plan_remote_assets() {
+        let root = PathBuf::from("/project/main.tex");
+        let sources = ProjectSources { root: root.clone(), tex_files: vec![root], ..ProjectSources::default() };
+        let asset = RemoteAsset {
+            url: "https://example.org/acmart.cls".to_string(),
+            sha256: "0".repeat(64),
+            path: PathBuf::from("acmart.cls"),
+        };
+        let compiler = Compiler::new("pdflatex", PathBuf::from("build")).with_assets(vec![asset]);
+        let graph = Planner::new(compiler).plan_sources(&sources);
+        assert!(graph.validate().is_ok());
+        assert_eq!(graph.transforms[0].description(), "fetch https://example.org/acmart.cls");
+        assert!(graph.transforms[1].inputs().contains(&ArtifactId("/project/acmart.cls".to_string())));
+        assert!(graph.artifacts.contains_key(&ArtifactId("remote:https://example.org/acmart.cls".to_string())));
+    }
+
+    #[test]
+    fn test_plan_draft_profile() {
+        let root = PathBuf::from("/project/main.tex");
+        let sources = ProjectSources {
+            root: root.clone(),
+            tex_files: vec![root],
+            bibliographies: vec![PathBuf::from("/project/refs.bib")],
+            makeindex: true,
+            ..ProjectSources::default()
+        };
+        let compiler = Compiler::new("pdflatex", PathBuf::from("build")).with_profile(BuildProfile::Draft);
+        let graph = Planner::new(compiler).plan_sources(&sources);
+        assert_eq!(graph.transforms.len(), 1);
+        assert_eq!(graph.transforms[0].description(), "pdflatex");
+        assert_eq!(graph.transforms[0].outputs(), HashSet::from([ArtifactId("/project/build/main.aux".to_string())]));
+
+        // Editors keep SyncTeX on for draft builds.
+        let compiler = Compiler::new("pdflatex", PathBuf::from("build")).with_profile(BuildProfile::Draft).with_synctex(true);
+        let graph = Planner::new(compiler).plan_sources(&sources);
+        assert!(graph.transforms[0].outputs().contains(&ArtifactId("/project/build/main.synctex.gz".to_string())));
+        assert!(graph.transforms[0].command().unwrap().args.contains(&"-synctex=1".to_string()));
+    }
+}
@@ -0,0 +1,160 @@
+use crate::{ArtifactId, CancellationToken, CommandSpec, LogSink, ShellTransform, Transform, TransformError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// A step run on the PDF after the engine wrote it, declared per project target (see
+/// [`crate::ProjectTarget::post_process`]).
+///
+/// Steps changing the PDF write a new file, `<stem>.<name>.pdf`, which the next step
+/// reads; the engine's PDF is left untouched. Reports go to `<stem>.<name>.txt`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum PostProcessStep {
+    /// Sets the document information dictionary with exiftool.
+    #[serde(rename_all = "camelCase")]
+    Metadata {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        author: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        subject: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        keywords: Option<String>,
+    },
+    /// Recompresses streams and packs objects with qpdf. Lossless, so PDF/A conformance
+    /// is kept.
+    Compress,
+    /// Validates PDF/A conformance with veraPDF, e.g. flavour `2b`; without one, the
+    /// flavour claimed in the PDF's metadata is checked.
+    ValidatePdfA {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        flavour: Option<String>,
+    },
+}
+
+impl PostProcessStep {
+    /// Names the file the step writes.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PostProcessStep::Metadata { .. } => "metadata",
+            PostProcessStep::Compress => "compressed",
+            PostProcessStep::ValidatePdfA { .. } => "pdfa",
+        }
+    }
+
+    pub fn program(&self) -> &'static str {
+        match self {
+            PostProcessStep::Metadata { .. } => "exiftool",
+            PostProcessStep::Compress => "qpdf",
+            PostProcessStep::ValidatePdfA { .. } => "verapdf",
+        }
+    }
+
+    /// Whether the step writes a PDF, rather than a report.
+    pub fn writes_pdf(&self) -> bool {
+        !matches!(self, PostProcessStep::ValidatePdfA { .. })
+    }
+
+    /// Arguments processing `input` into `output`. veraPDF prints its report instead.
+    pub fn args(&self, input: &Path, output: &Path) -> Vec<String> {
+        let (input, output) = (input.to_string_lossy().into_owned(), output.to_string_lossy().into_owned());
+        match self {
+            PostProcessStep::Metadata { title, author, subject, keywords } => {
+                let fields = [("Title", title), ("Author", author), ("Subject", subject), ("Keywords", keywords)];
+                let mut args: Vec<String> = fields
+                    .into_iter()
+                    .filter_map(|(tag, value)| value.as_ref().map(|value| format!("-{}={}", tag, value)))
+                    .collect();
+                args.extend(["-o".to_string(), output, input]);
+                args
+            }
+            PostProcessStep::Compress => vec![
+                "--compress-streams=y".to_string(),
+                "--object-streams=generate".to_string(),
+                input,
+                output,
+            ],
+            PostProcessStep::ValidatePdfA { flavour } => {
+                let mut args = vec!["--format".to_string(), "text".to_string()];
+                if let Some(flavour) = flavour {
+                    args.extend(["--flavour".to_string(), flavour.clone()]);
+                }
+                args.push(input);
+                args
+            }
+        }
+    }
+}
+
+/// Runs a [`PostProcessStep`] on a PDF.
+///
+/// The tools come with few TeX distributions, so the step runs on the host even when
+/// the engine runs in a container.
+pub struct PostProcessTransform {
+    step: PostProcessStep,
+    inner: ShellTransform,
+    output: PathBuf,
+}
+
+impl PostProcessTransform {
+    /// Processes the PDF at `input` into `output` (a PDF or a report, see
+    /// [`PostProcessStep::writes_pdf`]), running in `working_dir`.
+    pub fn new(step: PostProcessStep, input: (ArtifactId, &Path), output: (ArtifactId, &Path), working_dir: PathBuf) -> Self {
+        let args = step.args(input.1, output.1);
+        let inner = ShellTransform::new(step.program(), HashSet::from([input.0]), HashSet::from([output.0]), step.program(), args)
+            .with_working_dir(working_dir);
+        Self { step, inner, output: output.1.to_path_buf() }
+    }
+}
+
+impl Transform for PostProcessTransform {
+    fn description(&self) -> String {
+        self.inner.description()
+    }
+    fn inputs(&self) -> HashSet<ArtifactId> {
+        self.inner.inputs()
+    }
+    fn outputs(&self) -> HashSet<ArtifactId> {
+        self.inner.outputs()
+    }
+    fn command(&self) -> Option<CommandSpec> {
+        self.inner.command()
+    }
+    fn execute(&self, cancel: &CancellationToken, log: &LogSink) -> Result<(), TransformError> {
+        if self.step.writes_pdf() {
+            return self.inner.execute(cancel, log);
+        }
+        let output = self.inner.run(cancel, log)?;
+        std::fs::write(&self.output, &output.stdout).map_err(|e| format!("{}: {}", self.output.display(), e))?;
+        // `FAIL <file> <flavour>` for each document that does not conform.
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if stdout.lines().any(|line| line.starts_with("FAIL")) {
+            return Err(format!("not PDF/A compliant, see {}", self.output.display()).into());
+        }
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).to_string().into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_post_process_steps() {
+        let steps: Vec<PostProcessStep> = serde_json::from_str(
+            r#"[{"kind": "metadata", "title": "On Lattices", "author": "A. Author"}, {"kind": "compress"}, {"kind": "validatePdfA", "flavour": "2b"}]"#,
+        )
+        .unwrap();
+        let (input, output) = (Path::new("build/main.pdf"), Path::new("build/main.metadata.pdf"));
+        assert_eq!(steps[0].args(input, output), ["-Title=On Lattices", "-Author=A. Author", "-o", "build/main.metadata.pdf", "build/main.pdf"]);
+        assert_eq!(steps[1].program(), "qpdf");
+        assert_eq!(steps[2].args(input, Path::new("build/main.pdfa.txt")), ["--format", "text", "--flavour", "2b", "build/main.pdf"]);
+        assert!(!steps[2].writes_pdf());
+    }
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A named set of build options, chosen per build.
+///
+/// `draft` checks a document quickly: one engine pass without auxiliary tools, and no
+/// PDF written (`-draftmode`). `final` runs every pass the document needs and writes
+/// SyncTeX data for the viewer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildProfile {
+    Draft,
+    /// Unknown profile names map here.
+    #[default]
+    #[serde(other)]
+    Final,
+}
+
+impl BuildProfile {
+    pub const ALL: [BuildProfile; 2] = [BuildProfile::Draft, BuildProfile::Final];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BuildProfile::Draft => "draft",
+            BuildProfile::Final => "final",
+        }
+    }
+
+    /// Options passed to `engine` on every pass. SyncTeX is requested separately, as it
+    /// can be turned on for any profile (see [`crate::Compiler::writes_synctex`]).
+    pub fn engine_args(self, engine: &str) -> Vec<String> {
+        match self {
+            // XeTeX has no draft mode; `-no-pdf` skips the equivalent xdvipdfmx step.
+            BuildProfile::Draft if engine == "xelatex" => vec!["-no-pdf".to_string()],
+            BuildProfile::Draft => vec!["-draftmode".to_string()],
+            BuildProfile::Final => Vec::new(),
+        }
+    }
+
+    /// Whether the engine writes the PDF.
+    pub fn writes_pdf(self) -> bool {
+        self == BuildProfile::Final
+    }
+
+    /// Whether SyncTeX data is written next to the PDF, unless the compiler says otherwise.
+    pub fn synctex(self) -> bool {
+        self == BuildProfile::Final
+    }
+
+    /// Whether biber/BibTeX, the index and glossary tools run.
+    pub fn auxiliary_tools(self) -> bool {
+        self == BuildProfile::Final
+    }
+
+    /// Extra engine passes allowed, or `None` to keep the compiler's limit.
+    pub fn max_reruns(self) -> Option<usize> {
+        match self {
+            BuildProfile::Draft => Some(0),
+            BuildProfile::Final => None,
+        }
+    }
+}
+
+impl fmt::Display for BuildProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for BuildProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BuildProfile::ALL
+            .into_iter()
+            .find(|profile| profile.name() == s)
+            .ok_or_else(|| format!("unknown build profile '{}' (expected draft or final)", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_names() {
+        assert_eq!("draft".parse::<BuildProfile>(), Ok(BuildProfile::Draft));
+        assert_eq!(BuildProfile::Final.to_string(), "final");
+        assert!("fast".parse::<BuildProfile>().is_err());
+        // Settings fall back to the default instead of failing.
+        assert_eq!(serde_json::from_str::<BuildProfile>("\"fast\"").unwrap(), BuildProfile::Final);
+    }
+
+    #[test]
+    fn test_draft_engine_args() {
+        assert_eq!(BuildProfile::Draft.engine_args("pdflatex"), ["-draftmode"]);
+        assert_eq!(BuildProfile::Draft.engine_args("xelatex"), ["-no-pdf"]);
+        assert!(BuildProfile::Final.engine_args("lualatex").is_empty());
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{ArtifactId, BuildGraph, BuildProfile, CancellationToken, CommandSpec, LogSink, Planner, PostProcessStep, RemoteAsset, Transform, TransformError};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+/// File name of the project manifest looked for by the CLI.
+pub const MANIFEST_FILE: &str = "ferrotex-project.json";
+
+/// Several root documents built together, e.g. a paper, its supplementary material and
+/// the slides of the talk:
+///
+/// ```json
+/// {
+///   "targets": [
+///     { "name": "paper", "root": "paper/main.tex" },
+///     { "name": "supplement", "root": "paper/supplement.tex" },
+///     { "name": "slides", "root": "talk/slides.tex", "engine": "lualatex", "profile": "final",
+///       "postProcess": [{ "kind": "compress" }, { "kind": "validatePdfA", "flavour": "2b" }],
+///       "assets": [{ "url": "https://example.org/beamerthemeLab.sty", "sha256": "2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae", "path": "beamerthemeLab.sty" }] }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectManifest {
+    pub targets: Vec<ProjectTarget>,
+}
+
+/// One root document of a [`ProjectManifest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectTarget {
+    /// Prefixes the transforms of the target in reports, e.g. `slides: lualatex`.
+    pub name: String,
+    /// Relative to the manifest.
+    pub root: PathBuf,
+    /// Overrides the engine of the compiler.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engine: Option<String>,
+    /// Overrides the profile of the compiler.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<BuildProfile>,
+    /// Steps run on the PDF after the engine, in order (see [`PostProcessStep`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_process: Vec<PostProcessStep>,
+    /// Files fetched next to the root document before the engine runs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assets: Vec<RemoteAsset>,
+}
+
+impl ProjectManifest {
+    /// Reads a manifest and resolves the target roots against its directory.
+    ///
+    /// Fails if an asset path is absolute or contains `..`: assets are written next to the
+    /// root document, and a cloned project must not be able to overwrite files elsewhere.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let mut manifest: Self = serde_json::from_str(&content)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for target in &mut manifest.targets {
+            target.root = dir.join(&target.root);
+            let escaping = target.assets.iter().find(|asset| {
+                asset.path.is_absolute() || asset.path.components().any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+            });
+            if let Some(asset) = escaping {
+                anyhow::bail!(
+                    "Asset path '{}' of target '{}' must be relative and stay inside the project",
+                    asset.path.display(),
+                    target.name
+                );
+            }
+        }
+        let mut names = HashSet::new();
+        if let Some(target) = manifest.targets.iter().find(|t| !names.insert(&t.name)) {
+            anyhow::bail!("Target '{}' is listed twice in {}", target.name, path.display());
+        }
+        Ok(manifest)
+    }
+}
+
+impl Planner {
+    /// Plans every target of `manifest` into one graph.
+    ///
+    /// Files shared by several targets, such as common chapters or the bibliography, are
+    /// a single artifact of the graph, so a build cache fingerprints them once and an edit
+    /// reruns exactly the targets that read them. Independent targets build in parallel.
+    /// Fails if two targets would write the same files.
+    pub fn plan_project(&self, manifest: &ProjectManifest) -> anyhow::Result<BuildGraph> {
+        let mut graph = BuildGraph::new();
+        for target in &manifest.targets {
+            let mut compiler = self.compiler.clone();
+            if let Some(engine) = &target.engine {
+                compiler.engine = engine.clone();
+            }
+            if let Some(profile) = target.profile {
+                compiler.profile = profile;
+            }
+            if !target.post_process.is_empty() {
+                compiler.post_process = target.post_process.clone();
+            }
+            compiler.assets.extend(target.assets.iter().cloned());
+            let target_graph = Planner::new(compiler).plan(&target.root)?;
+            graph.artifacts.extend(target_graph.artifacts);
+            for transform in target_graph.transforms {
+                graph.add_transform(Box::new(TargetTransform { target: target.name.clone(), inner: transform }));
+            }
+        }
+
+        let mut producers: HashMap<ArtifactId, String> = HashMap::new();
+        for transform in &graph.transforms {
+            for output in transform.outputs() {
+                if let Some(other) = producers.insert(output.clone(), transform.description()) {
+                    anyhow::bail!("'{}' and '{}' both write {}", other, transform.description(), output.0);
+                }
+            }
+        }
+        Ok(graph)
+    }
+}
+
+/// A transform of one target of a project, labelled with the target's name.
+struct TargetTransform {
+    target: String,
+    inner: Box<dyn Transform>,
+}
+
+impl Transform for TargetTransform {
+    fn description(&self) -> String {
+        format!("{}: {}", self.target, self.inner.description())
+    }
+    fn inputs(&self) -> HashSet<ArtifactId> {
+        self.inner.inputs()
+    }
+    fn outputs(&self) -> HashSet<ArtifactId> {
+        self.inner.outputs()
+    }
+    fn command(&self) -> Option<CommandSpec> {
+        self.inner.command()
+    }
+    fn execute(&self, cancel: &CancellationToken, log: &LogSink) -> Result<(), TransformError> {
+        self.inner.execute(cancel, log)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Compiler;
+    use std::fs;
+
+    #[test]
+    fn test_plan_project_shares_inputs() {
+        let dir = std::env::current_dir().unwrap().join("target").join("test_project_manifest");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("chapters")).unwrap();
+        fs::write(dir.join("chapters/results.tex"), "Results.").unwrap();
+        fs::write(dir.join("paper.tex"), "\\input{chapters/results}").unwrap();
+        fs::write(dir.join("slides.tex"), "\\input{chapters/results}").unwrap();
+        let manifest_path = dir.join(MANIFEST_FILE);
+        fs::write(&manifest_path, r#"{"targets": [
+            {"name": "paper", "root": "paper.tex"},
+            {"name": "slides", "root": "slides.tex", "engine": "lualatex"}
+        ]}"#).unwrap();
+
+        let manifest = ProjectManifest::load(&manifest_path).unwrap();
+        assert_eq!(manifest.targets[1].root, dir.join("slides.tex"));
+        let graph = Planner::new(Compiler::new("pdflatex", PathBuf::from("build"))).plan_project(&manifest).unwrap();
+        let descriptions: Vec<String> = graph.transforms.iter().map(|t| t.description()).collect();
+        assert_eq!(descriptions, ["paper: pdflatex", "slides: lualatex"]);
+        let results = ArtifactId(fs::canonicalize(dir.join("chapters/results.tex")).unwrap().to_string_lossy().into_owned());
+        assert!(graph.transforms.iter().all(|t| t.inputs().contains(&results)));
+        // Two roots, one shared chapter, and an `.aux`, a PDF and SyncTeX data per target.
+        assert_eq!(graph.artifacts.len(), 9);
+
+        // Both targets would write build/paper.pdf.
+        let twice = ProjectManifest {
+            targets: vec![manifest.targets[0].clone(), ProjectTarget { name: "draft".to_string(), ..manifest.targets[0].clone() }],
+        };
+        let error = Planner::new(Compiler::new("pdflatex", PathBuf::from("build"))).plan_project(&twice).err().unwrap();
+        assert!(error.to_string().contains("both write"), "{}", error);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_load_rejects_escaping_asset_paths() {
+        let dir = std::env::current_dir().unwrap().join("target").join("test_project_asset_paths");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let manifest_path = dir.join(MANIFEST_FILE);
+        let with_asset = |path: &str| {
+            format!(
+                r#"{{"targets": [{{"name": "paper", "root": "paper.tex",
+                    "assets": [{{"url": "https://example.org/x", "sha256": "00", "path": {:?}}}]}}]}}"#,
+                path
+            )
+        };
+
+        fs::write(&manifest_path, with_asset("styles/lab.sty")).unwrap();
+        assert!(ProjectManifest::load(&manifest_path).is_ok());
+        for path in ["/home/user/.bashrc", "../../.bashrc", "styles/../../x.sty"] {
+            fs::write(&manifest_path, with_asset(path)).unwrap();
+            let error = ProjectManifest::load(&manifest_path).err().unwrap();
+            assert!(error.to_string().contains("must be relative"), "{}: {}", path, error);
+        }
+        let _ = fs::remove_dir_all(dir);
+    }
+}
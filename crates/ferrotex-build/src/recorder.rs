@@ -0,0 +1,81 @@
+use std::path::{Path, PathBuf};
+
+/// The files read and written by an engine run, as listed in the `.fls` file that
+/// `-recorder` makes TeX write next to the log.
+///
+/// Each line is `PWD <dir>`, `INPUT <file>` or `OUTPUT <file>`; relative paths are
+/// relative to the `PWD` line. Files appear once per time they were opened.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileRecorder {
+    /// The working directory of the engine.
+    pub pwd: Option<PathBuf>,
+    /// Files read, in the order they were first opened.
+    pub inputs: Vec<PathBuf>,
+    /// Files written, in the order they were first opened.
+    pub outputs: Vec<PathBuf>,
+}
+
+impl FileRecorder {
+    pub fn parse(content: &str) -> Self {
+        let mut recorder = Self::default();
+        for line in content.lines() {
+            let Some((kind, path)) = line.split_once(' ') else {
+                continue;
+            };
+            let path = PathBuf::from(path.trim_end());
+            let files = match kind {
+                "PWD" => {
+                    recorder.pwd = Some(path);
+                    continue;
+                }
+                "INPUT" => &mut recorder.inputs,
+                "OUTPUT" => &mut recorder.outputs,
+                _ => continue,
+            };
+            if !files.contains(&path) {
+                files.push(path);
+            }
+        }
+        recorder
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    /// File names of the packages and classes read, e.g. `amsmath.sty`, sorted.
+    pub fn packages(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .inputs
+            .iter()
+            .filter(|p| p.extension().is_some_and(|e| e == "sty" || e == "cls"))
+            .filter_map(|p| Some(p.file_name()?.to_string_lossy().into_owned()))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_recorder() {
+        let recorder = FileRecorder::parse(
+            "PWD /home/me/thesis\n\
+             INPUT /usr/share/texlive/texmf-dist/web2c/texmf.cnf\n\
+             INPUT main.tex\n\
+             OUTPUT build/main.log\n\
+             INPUT /usr/share/texlive/texmf-dist/tex/latex/base/article.cls\n\
+             INPUT /usr/share/texlive/texmf-dist/tex/latex/amsmath/amsmath.sty\n\
+             INPUT /usr/share/texlive/texmf-dist/tex/latex/amsmath/amsmath.sty\n\
+             INPUT mymacros.sty\n",
+        );
+        assert_eq!(recorder.pwd, Some(PathBuf::from("/home/me/thesis")));
+        assert_eq!(recorder.inputs.len(), 5);
+        assert_eq!(recorder.outputs, vec![PathBuf::from("build/main.log")]);
+        assert_eq!(recorder.packages(), ["amsmath.sty", "article.cls", "mymacros.sty"]);
+    }
+}
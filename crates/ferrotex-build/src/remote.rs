@@ -0,0 +1,164 @@
+use crate::{Artifact, ArtifactId, ArtifactSpec, CancellationToken, LogSink, Transform, TransformError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// A file the document needs but the project does not ship, such as a publisher's class
+/// or a shared figure, fetched from `url` before the engine runs.
+///
+/// The download must match `sha256`, so builds get the exact file they were written
+/// against.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteAsset {
+    pub url: String,
+    /// Lowercase hex SHA-256 of the content.
+    pub sha256: String,
+    /// Where the document expects the file, relative to the root document.
+    pub path: PathBuf,
+}
+
+/// The content behind a URL, identified by its expected hash.
+///
+/// Its ID is `remote:<url>` and its fingerprint the expected hash, so a build cache
+/// only refetches when the declaration changes.
+#[derive(Debug, Clone)]
+pub struct RemoteArtifact {
+    pub url: String,
+    pub sha256: String,
+}
+
+impl Artifact for RemoteArtifact {
+    fn id(&self) -> ArtifactId {
+        ArtifactId(format!("remote:{}", self.url))
+    }
+
+    fn fingerprint(&self) -> String {
+        self.sha256.clone()
+    }
+
+    fn path(&self) -> Option<PathBuf> {
+        None
+    }
+
+    fn spec(&self) -> ArtifactSpec {
+        ArtifactSpec::Remote { id: self.id(), url: self.url.clone(), sha256: self.sha256.clone() }
+    }
+}
+
+/// Directory keeping verified downloads by hash, shared by every project of the user:
+/// `$XDG_CACHE_HOME/ferrotex/assets`, or `~/.cache/ferrotex/assets`.
+pub fn default_cache_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(std::env::temp_dir);
+    base.join("ferrotex").join("assets")
+}
+
+/// Places a [`RemoteAsset`] at `target`, from the download cache or from its URL.
+pub struct FetchTransform {
+    asset: RemoteAsset,
+    target: PathBuf,
+    cache_dir: PathBuf,
+}
+
+impl FetchTransform {
+    pub fn new(asset: RemoteAsset, target: PathBuf, cache_dir: PathBuf) -> Self {
+        Self { asset, target, cache_dir }
+    }
+
+    fn download(&self, cancel: &CancellationToken) -> Result<Vec<u8>, TransformError> {
+        let error = |e: &dyn std::fmt::Display| TransformError::from(format!("{}: {}", self.asset.url, e));
+        let response = reqwest::blocking::get(&self.asset.url).and_then(|r| r.error_for_status()).map_err(|e| error(&e))?;
+        let mut content = Vec::new();
+        let mut reader = response;
+        let mut chunk = [0; 64 * 1024];
+        loop {
+            cancel.check()?;
+            match reader.read(&mut chunk).map_err(|e| error(&e))? {
+                0 => return Ok(content),
+                n => content.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+}
+
+impl Transform for FetchTransform {
+    fn description(&self) -> String {
+        format!("fetch {}", self.asset.url)
+    }
+
+    fn inputs(&self) -> HashSet<ArtifactId> {
+        HashSet::from([RemoteArtifact { url: self.asset.url.clone(), sha256: self.asset.sha256.clone() }.id()])
+    }
+
+    fn outputs(&self) -> HashSet<ArtifactId> {
+        HashSet::from([crate::FileArtifact::new(self.target.clone()).id()])
+    }
+
+    fn execute(&self, cancel: &CancellationToken, log: &LogSink) -> Result<(), TransformError> {
+        let expected = self.asset.sha256.to_ascii_lowercase();
+        if std::fs::read(&self.target).is_ok_and(|content| sha256(&content) == expected) {
+            return Ok(());
+        }
+        let cached = self.cache_dir.join(&expected);
+        let content = match std::fs::read(&cached) {
+            Ok(content) if sha256(&content) == expected => content,
+            _ => {
+                log(&format!("Downloading {}\n", self.asset.url));
+                let content = self.download(cancel)?;
+                let actual = sha256(&content);
+                if actual != expected {
+                    return Err(format!("{}: expected SHA-256 {} but downloaded {}", self.asset.url, expected, actual).into());
+                }
+                // A failed cache write only costs a download next time.
+                let _ = std::fs::create_dir_all(&self.cache_dir).and_then(|_| std::fs::write(&cached, &content));
+                content
+            }
+        };
+        write(&self.target, &content).map_err(|e| format!("{}: {}", self.target.display(), e).into())
+    }
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+fn write(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fetch_verifies_hash() {
+        let dir = std::env::current_dir().unwrap().join("target").join("test_remote");
+        let _ = std::fs::remove_dir_all(&dir);
+        let cache_dir = dir.join("cache");
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        let content = b"\\ProvidesClass{acmart}";
+        let hash = sha256(content);
+        // Served from the cache; the URL is never contacted.
+        std::fs::write(cache_dir.join(&hash), content).unwrap();
+        let asset = RemoteAsset { url: "https://example.invalid/acmart.cls".to_string(), sha256: hash, path: "acmart.cls".into() };
+
+        let fetch = FetchTransform::new(asset.clone(), dir.join("acmart.cls"), cache_dir.clone());
+        fetch.execute(&CancellationToken::new(), &|_| {}).unwrap();
+        assert_eq!(std::fs::read(dir.join("acmart.cls")).unwrap(), content);
+        assert_eq!(fetch.inputs(), HashSet::from([ArtifactId("remote:https://example.invalid/acmart.cls".to_string())]));
+
+        // A tampered cache entry is not trusted, and the download fails.
+        std::fs::write(cache_dir.join(&asset.sha256), "tampered").unwrap();
+        let fetch = FetchTransform::new(asset, dir.join("other/acmart.cls"), cache_dir);
+        let error = fetch.execute(&CancellationToken::new(), &|_| {}).unwrap_err();
+        assert!(error.to_string().starts_with("https://example.invalid/acmart.cls: "), "{}", error);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
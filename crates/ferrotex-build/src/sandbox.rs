@@ -0,0 +1,186 @@
+use crate::recorder::FileRecorder;
+use crate::ArtifactId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Restrictions for the external programs run by a build.
+///
+/// Shell escape and file paths are restricted through the kpathsea configuration
+/// (`shell_escape`, `openin_any`, `openout_any`), which every TeX Live program honours.
+/// Reads and writes of project files are then checked against the declared inputs and
+/// outputs of the transform, using the `.fls` file written by engines run with `-recorder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SandboxPolicy {
+    /// Allow `\write18` and `\input|"command"`.
+    pub shell_escape: bool,
+    /// Refuse reads by absolute or `..` paths leaving the working and output directories
+    /// (kpathsea's paranoid `openin_any`), and reject reads of project files that are not
+    /// declared inputs.
+    ///
+    /// This is not a full read sandbox: files kpathsea finds on its search paths, such as
+    /// the TeX distribution or directories added to `TEXINPUTS`, can still be read, and
+    /// programs outside TeX Live (biber, Tectonic) ignore `openin_any`.
+    pub restrict_reads: bool,
+    /// Reject writes outside the directories of the declared outputs.
+    pub restrict_writes: bool,
+}
+
+impl Default for SandboxPolicy {
+    fn default() -> Self {
+        Self {
+            shell_escape: false,
+            restrict_reads: true,
+            restrict_writes: true,
+        }
+    }
+}
+
+impl SandboxPolicy {
+    /// A policy that allows everything, shell escape included.
+    pub fn unrestricted() -> Self {
+        Self {
+            shell_escape: true,
+            restrict_reads: false,
+            restrict_writes: false,
+        }
+    }
+
+    pub fn with_shell_escape(mut self, shell_escape: bool) -> Self {
+        self.shell_escape = shell_escape;
+        self
+    }
+
+    /// Options for a TeX engine.
+    pub fn engine_args(&self) -> Vec<String> {
+        let flag = if self.shell_escape { "-shell-escape" } else { "-no-shell-escape" };
+        vec![flag.to_string()]
+    }
+
+    /// The kpathsea variables enforcing the policy, for any TeX Live program (BibTeX,
+    /// makeindex, ...), not only the engines.
+    pub fn env(&self) -> [(&'static str, &'static str); 3] {
+        [
+            ("shell_escape", if self.shell_escape { "t" } else { "f" }),
+            ("openin_any", if self.restrict_reads { "p" } else { "a" }),
+            // `p` ("paranoid") refuses dot files and paths outside the working and output directories.
+            ("openout_any", if self.restrict_writes { "p" } else { "a" }),
+        ]
+    }
+
+    /// Compares the files a program accessed with the declared inputs and outputs.
+    ///
+    /// Relative recorded paths are resolved against the recorder's `PWD`, or `root`.
+    /// Files written by the run itself, or lying in the directory of a declared output,
+    /// may be read back (e.g. the `.aux` and `.toc` of the previous pass).
+    pub fn check(
+        &self,
+        recorder: &FileRecorder,
+        root: &Path,
+        inputs: &HashSet<ArtifactId>,
+        outputs: &HashSet<ArtifactId>,
+    ) -> Vec<SandboxViolation> {
+        let base = recorder.pwd.as_deref().unwrap_or(root);
+        let resolve = |path: &Path| {
+            let path = base.join(path);
+            std::fs::canonicalize(&path).unwrap_or(path)
+        };
+        let declared = |ids: &HashSet<ArtifactId>| -> HashSet<PathBuf> {
+            ids.iter().map(|id| resolve(Path::new(&id.0))).collect()
+        };
+        let (inputs, outputs) = (declared(inputs), declared(outputs));
+        let output_dirs: HashSet<&Path> = outputs.iter().filter_map(|p| p.parent()).collect();
+        let in_output_dir = |path: &Path| output_dirs.iter().any(|dir| path.starts_with(dir));
+        let root = resolve(root);
+
+        let mut violations = Vec::new();
+        let written: Vec<PathBuf> = recorder.outputs.iter().map(|p| resolve(p)).collect();
+        if self.restrict_writes {
+            for path in &written {
+                if !outputs.contains(path) && !in_output_dir(path) {
+                    violations.push(SandboxViolation { access: Access::Write, path: path.clone() });
+                }
+            }
+        }
+        if self.restrict_reads {
+            for path in recorder.inputs.iter().map(|p| resolve(p)) {
+                let allowed = !path.starts_with(&root)
+                    || inputs.contains(&path)
+                    || written.contains(&path)
+                    || in_output_dir(&path);
+                if !allowed {
+                    violations.push(SandboxViolation { access: Access::Read, path });
+                }
+            }
+        }
+        violations
+    }
+}
+
+/// How a file was accessed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// A file access the [`SandboxPolicy`] does not allow.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxViolation {
+    pub access: Access,
+    pub path: PathBuf,
+}
+
+impl fmt::Display for SandboxViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.access {
+            Access::Read => write!(f, "read undeclared input {}", self.path.display()),
+            Access::Write => write!(f, "wrote outside the declared outputs: {}", self.path.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_recorded_accesses() {
+        let ids = |paths: &[&str]| paths.iter().map(|p| ArtifactId(p.to_string())).collect::<HashSet<_>>();
+        let inputs = ids(&["/project/main.tex", "/project/chapters/intro.tex"]);
+        let outputs = ids(&["/project/build/main.aux", "/project/build/main.pdf"]);
+        let recorder = FileRecorder::parse(
+            "PWD /project\n\
+             INPUT /usr/share/texlive/texmf-dist/tex/latex/base/article.cls\n\
+             INPUT main.tex\n\
+             INPUT chapters/intro.tex\n\
+             INPUT build/main.toc\n\
+             INPUT secret/notes.tex\n\
+             OUTPUT build/main.log\n\
+             OUTPUT main.out\n\
+             INPUT main.out\n",
+        );
+
+        let violations = SandboxPolicy::default().check(&recorder, Path::new("/project"), &inputs, &outputs);
+        assert_eq!(violations, vec![
+            SandboxViolation { access: Access::Write, path: PathBuf::from("/project/main.out") },
+            SandboxViolation { access: Access::Read, path: PathBuf::from("/project/secret/notes.tex") },
+        ]);
+        assert_eq!(violations[1].to_string(), "read undeclared input /project/secret/notes.tex");
+        assert!(SandboxPolicy::unrestricted().check(&recorder, Path::new("/project"), &inputs, &outputs).is_empty());
+    }
+
+    #[test]
+    fn test_engine_args() {
+        assert_eq!(SandboxPolicy::default().engine_args(), ["-no-shell-escape"]);
+        assert_eq!(SandboxPolicy::default().with_shell_escape(true).engine_args(), ["-shell-escape"]);
+    }
+
+    #[test]
+    fn test_env() {
+        assert_eq!(SandboxPolicy::default().env(), [("shell_escape", "f"), ("openin_any", "p"), ("openout_any", "p")]);
+        assert_eq!(SandboxPolicy::unrestricted().env(), [("shell_escape", "t"), ("openin_any", "a"), ("openout_any", "a")]);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{ArtifactId, BuildGraph, DirArtifact, FileArtifact, MemoryArtifact, RemoteArtifact, ShellTransform};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// A serializable description of a [`BuildGraph`], for tools that inspect or replay
+/// build plans (see [`BuildGraph::to_json`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphSpec {
+    /// Sorted by ID.
+    pub artifacts: Vec<ArtifactSpec>,
+    /// In graph order; [`crate::TransformReport::index`] refers to positions in this list.
+    pub transforms: Vec<TransformSpec>,
+}
+
+/// What an artifact is, as returned by [`crate::Artifact::spec`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ArtifactSpec {
+    File { id: ArtifactId, path: PathBuf },
+    Directory { id: ArtifactId, path: PathBuf },
+    /// The content is stored as text; generated snippets are TeX source.
+    Memory { id: ArtifactId, name: String, content: String },
+    /// Fetched from `url`; the content must have the given SHA-256.
+    Remote { id: ArtifactId, url: String, sha256: String },
+    /// An artifact type that cannot be restored, described for inspection only.
+    Other { id: ArtifactId, path: Option<PathBuf> },
+}
+
+impl ArtifactSpec {
+    pub fn id(&self) -> &ArtifactId {
+        match self {
+            ArtifactSpec::File { id, .. }
+            | ArtifactSpec::Directory { id, .. }
+            | ArtifactSpec::Memory { id, .. }
+            | ArtifactSpec::Remote { id, .. }
+            | ArtifactSpec::Other { id, .. } => id,
+        }
+    }
+}
+
+/// A transform and the program it runs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransformSpec {
+    pub description: String,
+    /// Sorted.
+    pub inputs: Vec<ArtifactId>,
+    /// Sorted.
+    pub outputs: Vec<ArtifactId>,
+    /// `None` for transforms that do not run an external program.
+    pub command: Option<CommandSpec>,
+}
+
+/// An external program run by a transform (see [`crate::Transform::command`]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandSpec {
+    pub program: String,
+    pub args: Vec<String>,
+    pub working_dir: Option<PathBuf>,
+    /// Image of the container the program runs in, if not on the host.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+}
+
+impl BuildGraph {
+    /// Describes the artifacts and transforms of the graph.
+    pub fn spec(&self) -> GraphSpec {
+        let mut artifacts: Vec<ArtifactSpec> = self.artifacts.values().map(|artifact| artifact.spec()).collect();
+        artifacts.sort_by(|a, b| a.id().0.cmp(&b.id().0));
+        let sorted = |ids: std::collections::HashSet<ArtifactId>| {
+            let mut ids: Vec<ArtifactId> = ids.into_iter().collect();
+            ids.sort_by(|a, b| a.0.cmp(&b.0));
+            ids
+        };
+        let transforms = self
+            .transforms
+            .iter()
+            .map(|transform| TransformSpec {
+                description: transform.description(),
+                inputs: sorted(transform.inputs()),
+                outputs: sorted(transform.outputs()),
+                command: transform.command(),
+            })
+            .collect();
+        GraphSpec { artifacts, transforms }
+    }
+
+    /// Serializes [`BuildGraph::spec`] as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.spec()).expect("graph specs only contain strings and lists")
+    }
+
+    /// Restores a graph written by [`BuildGraph::to_json`].
+    ///
+    /// Restored transforms run their command once, as a plain [`ShellTransform`]: rerun
+    /// loops, sandboxes and containers of the original transforms are not part of the
+    /// description. Fails on artifacts or transforms that cannot be restored.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let spec: GraphSpec = serde_json::from_str(json)?;
+        let mut graph = BuildGraph::new();
+        for artifact in spec.artifacts {
+            match artifact {
+                ArtifactSpec::File { path, .. } => graph.add_artifact(Box::new(FileArtifact::new(path))),
+                ArtifactSpec::Directory { path, .. } => graph.add_artifact(Box::new(DirArtifact::new(path))),
+                ArtifactSpec::Memory { name, content, .. } => {
+                    graph.add_artifact(Box::new(MemoryArtifact::new(&name, content)))
+                }
+                ArtifactSpec::Remote { url, sha256, .. } => graph.add_artifact(Box::new(RemoteArtifact { url, sha256 })),
+                ArtifactSpec::Other { id, .. } => anyhow::bail!("artifact {} cannot be restored", id.0),
+            }
+        }
+        for transform in spec.transforms {
+            let Some(command) = transform.command else {
+                anyhow::bail!("transform '{}' runs no command and cannot be restored", transform.description);
+            };
+            let mut shell = ShellTransform::new(
+                &transform.description,
+                transform.inputs.into_iter().collect(),
+                transform.outputs.into_iter().collect(),
+                &command.program,
+                command.args,
+            );
+            if let Some(dir) = command.working_dir {
+                shell = shell.with_working_dir(dir);
+            }
+            graph.add_transform(Box::new(shell));
+        }
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Artifact, Transform};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_graph_json_roundtrip() {
+        let ids = |names: &[&str]| names.iter().map(|n| ArtifactId(n.to_string())).collect::<HashSet<_>>();
+        let mut graph = BuildGraph::new();
+        graph.add_artifact(Box::new(FileArtifact::new(PathBuf::from("/project/main.tex"))));
+        graph.add_artifact(Box::new(MemoryArtifact::new("preamble", "\\usepackage{amsmath}")));
+        graph.add_transform(Box::new(
+            ShellTransform::new(
+                "pdflatex main.tex",
+                ids(&["/project/main.tex", "memory:preamble"]),
+                ids(&["/project/build/main.pdf"]),
+                "pdflatex",
+                vec!["main.tex".to_string()],
+            )
+            .with_working_dir(PathBuf::from("/project")),
+        ));
+
+        let spec = graph.spec();
+        assert_eq!(spec.artifacts[0], ArtifactSpec::File {
+            id: ArtifactId("/project/main.tex".to_string()),
+            path: PathBuf::from("/project/main.tex"),
+        });
+        assert_eq!(spec.transforms[0].inputs, vec![
+            ArtifactId("/project/main.tex".to_string()),
+            ArtifactId("memory:preamble".to_string()),
+        ]);
+        let json = graph.to_json();
+        assert!(json.contains("\"kind\": \"memory\""), "{}", json);
+
+        let restored = BuildGraph::from_json(&json).unwrap();
+        assert_eq!(restored.spec(), spec);
+        assert_eq!(restored.artifacts[&ArtifactId("memory:preamble".to_string())].fingerprint(),
+            MemoryArtifact::new("preamble", "\\usepackage{amsmath}").fingerprint());
+    }
+
+    #[test]
+    fn test_graph_json_rejects_opaque_transforms() {
+        struct Copy;
+        impl Transform for Copy {
+            fn description(&self) -> String { "copy".to_string() }
+            fn inputs(&self) -> HashSet<ArtifactId> { HashSet::new() }
+            fn outputs(&self) -> HashSet<ArtifactId> { HashSet::new() }
+            fn execute(&self, _: &crate::CancellationToken, _: &crate::LogSink) -> Result<(), crate::TransformError> { Ok(()) }
+        }
+        let mut graph = BuildGraph::new();
+        graph.add_transform(Box::new(Copy));
+        assert_eq!(graph.spec().transforms[0].command, None);
+        let error = BuildGraph::from_json(&graph.to_json()).err().unwrap();
+        assert_eq!(error.to_string(), "transform 'copy' runs no command and cannot be restored");
+    }
+}
@@ -0,0 +1,285 @@
+use crate::{ArtifactId, ArtifactSpec, BuildCache, BuildGraph, BuildReport, CancellationToken, Executor};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// How long to wait for further changes after the first one, so that saving several
+/// files, or an editor writing through a temporary file, starts a single build.
+pub const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// How often [`GraphWatcher::wait`] checks for cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A source of the graph: an artifact no transform writes.
+struct Source {
+    id: ArtifactId,
+    path: PathBuf,
+    directory: bool,
+}
+
+/// Watches the source artifacts of a [`BuildGraph`] for changes.
+///
+/// Artifacts written by a transform are not watched, so a build does not trigger
+/// itself. Files are watched through their directory, which keeps working when an
+/// editor saves by replacing the file.
+pub struct GraphWatcher {
+    _watcher: notify::RecommendedWatcher,
+    changes: mpsc::Receiver<notify::Result<notify::Event>>,
+    sources: Vec<Source>,
+}
+
+impl GraphWatcher {
+    pub fn new(graph: &BuildGraph) -> anyhow::Result<Self> {
+        let outputs: HashSet<ArtifactId> = graph.transforms.iter().flat_map(|transform| transform.outputs()).collect();
+        let sources: Vec<Source> = graph
+            .artifacts
+            .values()
+            .filter(|artifact| !outputs.contains(&artifact.id()))
+            .filter_map(|artifact| {
+                let path = artifact.path()?;
+                let directory = matches!(artifact.spec(), ArtifactSpec::Directory { .. });
+                Some(Source { id: artifact.id(), path: canonical(&path), directory })
+            })
+            .collect();
+
+        let (tx, changes) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        let mut watched = BTreeSet::new();
+        for source in &sources {
+            let (path, mode) = match source.path.parent() {
+                _ if source.directory => (source.path.as_path(), RecursiveMode::Recursive),
+                Some(parent) => (parent, RecursiveMode::NonRecursive),
+                None => continue,
+            };
+            // A source in a directory that does not exist yet is not watched; it is picked up
+            // when a `.tex` source changes and [`watch`] re-plans the graph.
+            if watched.insert(path.to_path_buf()) && path.exists() {
+                watcher.watch(path, mode)?;
+            }
+        }
+        Ok(Self { _watcher: watcher, changes, sources })
+    }
+
+    /// Blocks until sources change and returns them, or `None` once `cancel` fires.
+    ///
+    /// Changes arriving within [`DEBOUNCE`] of each other are returned together.
+    pub fn wait(&self, cancel: &CancellationToken) -> Option<HashSet<ArtifactId>> {
+        let mut changed = HashSet::new();
+        loop {
+            if cancel.is_cancelled() {
+                return None;
+            }
+            let timeout = if changed.is_empty() { POLL_INTERVAL } else { DEBOUNCE };
+            match self.changes.recv_timeout(timeout) {
+                Ok(Ok(event)) => changed.extend(self.changed_sources(&event)),
+                // Errors such as a full inotify queue only lose events; keep watching.
+                Ok(Err(_)) => {}
+                Err(mpsc::RecvTimeoutError::Timeout) if changed.is_empty() => {}
+                Err(mpsc::RecvTimeoutError::Timeout) => return Some(changed),
+                Err(mpsc::RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+    }
+
+    fn changed_sources(&self, event: &notify::Event) -> Vec<ArtifactId> {
+        if matches!(event.kind, EventKind::Access(_)) {
+            return Vec::new();
+        }
+        let paths: Vec<PathBuf> = event.paths.iter().map(|path| canonical(path)).collect();
+        self.sources
+            .iter()
+            .filter(|source| {
+                paths.iter().any(|path| *path == source.path || (source.directory && path.starts_with(&source.path)))
+            })
+            .map(|source| source.id.clone())
+            .collect()
+    }
+}
+
+/// Resolves symbolic links, like the planner does for artifact IDs. A deleted file is
+/// resolved through its directory.
+fn canonical(path: &Path) -> PathBuf {
+    if let Ok(path) = std::fs::canonicalize(path) {
+        return path;
+    }
+    match (path.parent().and_then(|parent| std::fs::canonicalize(parent).ok()), path.file_name()) {
+        (Some(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+impl BuildGraph {
+    /// The transforms that read one of `changed`, directly or through the outputs of
+    /// another affected transform, in graph order.
+    pub fn affected(&self, changed: &HashSet<ArtifactId>) -> Vec<usize> {
+        let dependents = self.dependents();
+        let mut affected = vec![false; self.transforms.len()];
+        let mut stack: Vec<usize> = (0..self.transforms.len())
+            .filter(|&index| !self.transforms[index].inputs().is_disjoint(changed))
+            .collect();
+        while let Some(index) = stack.pop() {
+            if !std::mem::replace(&mut affected[index], true) {
+                stack.extend(&dependents[index]);
+            }
+        }
+        (0..affected.len()).filter(|&index| affected[index]).collect()
+    }
+}
+
+/// Builds the graph returned by `plan`, then rebuilds it whenever its sources change, until
+/// `cancel` fires.
+///
+/// A changed `.tex` source may include other files than before, so the graph is planned
+/// again first; if that fails, the previous graph is rebuilt. `on_build` receives the graph,
+/// the indices of the transforms affected by the change (all of them for the first build)
+/// and the report. Unaffected transforms are cache hits, so only the affected ones run
+/// again; transforms that cannot be cached always run.
+pub fn watch(
+    mut plan: impl FnMut() -> anyhow::Result<BuildGraph>,
+    cache: &mut BuildCache,
+    cancel: &CancellationToken,
+    mut on_build: impl FnMut(&BuildGraph, &[usize], Result<BuildReport, String>),
+) -> anyhow::Result<()> {
+    let mut graph = plan()?;
+    let mut watcher = GraphWatcher::new(&graph)?;
+    let all: Vec<usize> = (0..graph.transforms.len()).collect();
+    on_build(&graph, &all, Executor::new(&graph).with_cache(cache).with_cancellation(cancel.clone()).run());
+    while let Some(mut changed) = watcher.wait(cancel) {
+        if changed.iter().any(|id| Path::new(&id.0).extension().is_some_and(|ext| ext == "tex"))
+            && let Ok(replanned) = plan()
+        {
+            // New artifacts, e.g. a newly included chapter, count as changed.
+            changed.extend(replanned.artifacts.keys().filter(|id| !graph.artifacts.contains_key(id)).cloned());
+            watcher = GraphWatcher::new(&replanned)?;
+            graph = replanned;
+        }
+        let affected = graph.affected(&changed);
+        if affected.is_empty() {
+            continue;
+        }
+        on_build(&graph, &affected, Executor::new(&graph).with_cache(cache).with_cancellation(cancel.clone()).run());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Artifact, FileArtifact, LogSink, Transform, TransformError};
+    use std::fs;
+
+    /// Copies a file.
+    struct Copy {
+        from: PathBuf,
+        to: PathBuf,
+    }
+
+    impl Transform for Copy {
+        fn description(&self) -> String {
+            format!("copy {}", self.from.display())
+        }
+        fn inputs(&self) -> HashSet<ArtifactId> {
+            HashSet::from([FileArtifact::new(self.from.clone()).id()])
+        }
+        fn outputs(&self) -> HashSet<ArtifactId> {
+            HashSet::from([FileArtifact::new(self.to.clone()).id()])
+        }
+        fn execute(&self, _: &CancellationToken, _: &LogSink) -> Result<(), TransformError> {
+            fs::copy(&self.from, &self.to).map(|_| ()).map_err(|e| e.to_string().into())
+        }
+    }
+
+    #[test]
+    fn test_watch_rebuilds_affected_transforms() {
+        let dir = canonical(&std::env::current_dir().unwrap().join("target").join("test_watcher"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name);
+        fs::write(path("a.tex"), "a").unwrap();
+        fs::write(path("b.tex"), "b").unwrap();
+        let mut graph = BuildGraph::new();
+        for name in ["a.tex", "b.tex", "a.out", "b.out", "ab.out"] {
+            graph.add_artifact(Box::new(FileArtifact::new(path(name))));
+        }
+        graph.add_transform(Box::new(Copy { from: path("a.tex"), to: path("a.out") }));
+        graph.add_transform(Box::new(Copy { from: path("b.tex"), to: path("b.out") }));
+        graph.add_transform(Box::new(Copy { from: path("a.out"), to: path("ab.out") }));
+        assert_eq!(graph.affected(&HashSet::from([FileArtifact::new(path("a.tex")).id()])), vec![0, 2]);
+        let mut graph = Some(graph);
+
+        let cancel = CancellationToken::new();
+        let (tx, builds) = mpsc::channel();
+        std::thread::scope(|scope| {
+            let watching = scope.spawn(|| {
+                let mut cache = BuildCache::new();
+                // Re-planning after the edit fails, so the first graph is rebuilt.
+                let plan = || graph.take().ok_or_else(|| anyhow::anyhow!("planned once"));
+                watch(plan, &mut cache, &cancel, |_, affected, report| {
+                    let _ = tx.send((affected.to_vec(), report.unwrap().execution_order));
+                })
+            });
+            let first = builds.recv_timeout(Duration::from_secs(10)).unwrap();
+            assert_eq!(first.0, vec![0, 1, 2]);
+
+            fs::write(path("a.tex"), "edited").unwrap();
+            let second = builds.recv_timeout(Duration::from_secs(10));
+            cancel.cancel();
+            watching.join().unwrap().unwrap();
+            let (affected, mut ran) = second.unwrap();
+            ran.sort();
+            assert_eq!((affected, ran), (vec![0, 2], vec![0, 2]));
+        });
+        assert_eq!(fs::read_to_string(path("ab.out")).unwrap(), "edited");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_watch_replans_when_tex_changes() {
+        let dir = canonical(&std::env::current_dir().unwrap().join("target").join("test_watcher_replan"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name);
+        fs::write(path("main.tex"), "main").unwrap();
+        fs::create_dir_all(path("chapters")).unwrap();
+        fs::write(path("chapters/b.tex"), "b").unwrap();
+        // `main.tex` includes `chapters/b.tex` once it mentions it.
+        let plan = || {
+            let mut graph = BuildGraph::new();
+            graph.add_artifact(Box::new(FileArtifact::new(path("main.tex"))));
+            graph.add_artifact(Box::new(FileArtifact::new(path("main.out"))));
+            graph.add_transform(Box::new(Copy { from: path("main.tex"), to: path("main.out") }));
+            if fs::read_to_string(path("main.tex"))?.contains("chapters/b") {
+                graph.add_artifact(Box::new(FileArtifact::new(path("chapters/b.tex"))));
+                graph.add_artifact(Box::new(FileArtifact::new(path("b.out"))));
+                graph.add_transform(Box::new(Copy { from: path("chapters/b.tex"), to: path("b.out") }));
+            }
+            Ok(graph)
+        };
+
+        let cancel = CancellationToken::new();
+        let (tx, builds) = mpsc::channel();
+        std::thread::scope(|scope| {
+            let watching = scope.spawn(|| {
+                let mut cache = BuildCache::new();
+                watch(plan, &mut cache, &cancel, |graph, affected, _| {
+                    let _ = tx.send((graph.transforms.len(), affected.to_vec()));
+                })
+            });
+            assert_eq!(builds.recv_timeout(Duration::from_secs(10)).unwrap(), (1, vec![0]));
+
+            fs::write(path("main.tex"), "main chapters/b").unwrap();
+            let replanned = builds.recv_timeout(Duration::from_secs(10));
+            // The new source is watched after the re-plan.
+            fs::write(path("chapters/b.tex"), "edited").unwrap();
+            let edited = builds.recv_timeout(Duration::from_secs(10));
+            cancel.cancel();
+            watching.join().unwrap().unwrap();
+            assert_eq!(replanned.unwrap(), (2, vec![0, 1]));
+            assert_eq!(edited.unwrap(), (2, vec![1]));
+        });
+        assert_eq!(fs::read_to_string(path("b.out")).unwrap(), "edited");
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,212 @@
+//! `ferrotex bib`: formatting, validation and pruning of `.bib` files.
+
+use crate::output::Summary;
+use anyhow::Context;
+use clap::Subcommand;
+use ferrotex_syntax::TextRange;
+use ferrotexd::position::LineIndex;
+use ferrotexd::workspace::Workspace;
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::Url;
+
+/// `ferrotex bib` subcommands.
+#[derive(Subcommand)]
+pub enum BibCommand {
+    /// Format `.bib` files in place: one field per line, canonical field order, aligned `=`.
+    Fmt {
+        /// Files to format, or `-` for standard input.
+        #[arg(value_name = "FILE", required = true)]
+        paths: Vec<PathBuf>,
+        /// Change nothing: print a diff of every file that is not formatted and fail if any.
+        #[arg(long)]
+        check: bool,
+        /// Spaces per indentation level.
+        #[arg(long, default_value_t = 4)]
+        indent_size: usize,
+        /// Sort entries by citation key.
+        #[arg(long)]
+        sort_entries: bool,
+    },
+    /// Report entries missing required fields and citation keys defined more than once.
+    Check {
+        /// Files to check.
+        #[arg(value_name = "FILE", required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// List the entries of a document's bibliographies that it does not cite.
+    Prune {
+        /// Path to the root .tex file.
+        #[arg(long, value_name = "FILE")]
+        root: PathBuf,
+        /// Remove the uncited entries from the `.bib` files.
+        #[arg(long)]
+        write: bool,
+    },
+}
+
+pub fn run(command: &BibCommand) -> anyhow::Result<Summary> {
+    match command {
+        BibCommand::Fmt { paths, check, indent_size, sort_entries } => {
+            let options = ferrotexd::fmt::FormatOptions {
+                tab_size: *indent_size,
+                insert_spaces: true,
+                trim_trailing_whitespace: true,
+                insert_final_newline: true,
+                sort_bib_entries: *sort_entries,
+                ..Default::default()
+            };
+            for path in paths {
+                anyhow::ensure!(
+                    crate::is_stdin(path) || path.extension().is_some_and(|extension| extension == "bib"),
+                    "{} is not a .bib file",
+                    path.display()
+                );
+            }
+            crate::format_files(paths, Some(Path::new("<stdin>.bib")), *check, &options)
+        }
+        BibCommand::Check { paths } => {
+            let problems = check(paths)?;
+            if problems > 0 {
+                eprintln!("{} problem{} found", problems, if problems == 1 { "" } else { "s" });
+            }
+            Ok(Summary::errors(problems))
+        }
+        // Uncited entries left in place are warnings.
+        BibCommand::Prune { root, write } => {
+            let uncited = prune(root, *write)?;
+            Ok(if *write { Summary::default() } else { Summary::warnings(uncited) })
+        }
+    }
+}
+
+/// Prints the problems of `paths`, duplicate keys counting across all of them, and
+/// returns their number.
+fn check(paths: &[PathBuf]) -> anyhow::Result<usize> {
+    let workspace = Workspace::new();
+    let mut problems = Vec::new();
+    let mut files = Vec::new();
+    for path in paths {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let uri = file_uri(path)?;
+        workspace.update_bib(&uri, &text);
+        let bib = ferrotex_syntax::bibtex::parse_bibtex(&text);
+        for (range, message) in ferrotexd::diagnostics::bib::check_required_fields(&bib) {
+            problems.push((uri.clone(), range, message));
+        }
+        files.push((uri, path.clone(), LineIndex::new(&text)));
+    }
+    problems.extend(workspace.validate_bib_duplicates());
+    problems.sort_by(|a, b| (&a.0, a.1.start()).cmp(&(&b.0, b.1.start())));
+
+    for (uri, range, message) in &problems {
+        if let Some((_, path, line_index)) = files.iter().find(|(u, _, _)| u == uri) {
+            let start = line_index.position(range.start());
+            println!("{}:{}:{}: {}", path.display(), start.line + 1, start.character + 1, message);
+        }
+    }
+    Ok(problems.len())
+}
+
+/// Lists, or with `write` removes, the entries the document rooted at `root` does not cite,
+/// and returns their number.
+fn prune(root: &Path, write: bool) -> anyhow::Result<usize> {
+    anyhow::ensure!(root.is_file(), "{} does not exist", root.display());
+    let workspace = Workspace::new();
+    workspace.load_tree(&file_uri(root)?);
+    let missing = workspace.validate_bibliographies();
+    if let Some((_, _, message)) = missing.first() {
+        // Entries cited from a missing file would look uncited.
+        anyhow::bail!("{}; not pruning", message);
+    }
+
+    let uncited = workspace.uncited_entries();
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let mut by_file: Vec<(Url, Vec<TextRange>)> = Vec::new();
+    for (uri, entry) in &uncited {
+        let path = uri.to_file_path().map_err(|_| anyhow::anyhow!("Invalid file URI {}", uri))?;
+        let text = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let line = LineIndex::new(&text).position(entry.range.start()).line + 1;
+        let name = path.strip_prefix(&cwd).unwrap_or(&path).display().to_string();
+        println!("{}:{}: {}{}", name, line, entry.key, if write { " (removed)" } else { "" });
+        match by_file.iter_mut().find(|(u, _)| u == uri) {
+            Some((_, ranges)) => ranges.push(entry.range),
+            None => by_file.push((uri.clone(), vec![entry.range])),
+        }
+    }
+
+    if write {
+        for (uri, ranges) in &by_file {
+            let path = uri.to_file_path().map_err(|_| anyhow::anyhow!("Invalid file URI {}", uri))?;
+            let text = std::fs::read_to_string(&path)?;
+            std::fs::write(&path, remove_ranges(&text, ranges)).with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+    }
+    eprintln!("{} uncited entr{}", uncited.len(), if uncited.len() == 1 { "y" } else { "ies" });
+    Ok(uncited.len())
+}
+
+/// Removes the entries at `ranges` from `text` with the whitespace after them, or before
+/// the last one, so that the blank lines between the remaining entries are kept.
+fn remove_ranges(text: &str, ranges: &[TextRange]) -> String {
+    let mut ranges = ranges.to_vec();
+    ranges.sort_by_key(|range| std::cmp::Reverse(range.start()));
+    let mut text = text.to_string();
+    for range in ranges {
+        let start = usize::from(range.start());
+        let mut end = usize::from(range.end()).min(text.len());
+        end += text[end..].len() - text[end..].trim_start().len();
+        let start = if end == text.len() { text[..start].trim_end().len() } else { start };
+        text.replace_range(start..end, if end == text.len() && start > 0 { "\n" } else { "" });
+    }
+    text
+}
+
+fn file_uri(path: &Path) -> anyhow::Result<Url> {
+    let absolute = std::path::absolute(path)?;
+    Url::from_file_path(&absolute).map_err(|_| anyhow::anyhow!("Invalid path {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range_of(text: &str, entry: &str) -> TextRange {
+        let start = text.find(entry).unwrap();
+        TextRange::new((start as u32).into(), ((start + entry.len()) as u32).into())
+    }
+
+    #[test]
+    fn test_remove_ranges() {
+        let text = "@book{a, title={A}}\n\n@book{b, title={B}}\n\n@book{c, title={C}}\n";
+        let remove = |keys: &[&str]| {
+            let ranges: Vec<TextRange> =
+                keys.iter().map(|key| range_of(text, &format!("@book{{{}, title={{{}}}}}", key, key.to_uppercase()))).collect();
+            remove_ranges(text, &ranges)
+        };
+        assert_eq!(remove(&["a"]), "@book{b, title={B}}\n\n@book{c, title={C}}\n");
+        assert_eq!(remove(&["b"]), "@book{a, title={A}}\n\n@book{c, title={C}}\n");
+        assert_eq!(remove(&["c"]), "@book{a, title={A}}\n\n@book{b, title={B}}\n");
+        assert_eq!(remove(&["a", "c"]), "@book{b, title={B}}\n");
+    }
+
+    #[test]
+    fn test_prune_keeps_crossref_parents() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("main.tex");
+        let bib = dir.path().join("refs.bib");
+        std::fs::write(&root, "\\cite{paper}\\bibliography{refs}").unwrap();
+        std::fs::write(
+            &bib,
+            "@inproceedings{paper, title={P}, crossref={proc}}\n\n@proceedings{proc, title={Proc}}\n\n@book{unused, title={U}}\n",
+        )
+        .unwrap();
+
+        assert_eq!(prune(&root, false).unwrap(), 1);
+        assert_eq!(prune(&root, true).unwrap(), 1);
+        assert_eq!(
+            std::fs::read_to_string(&bib).unwrap(),
+            "@inproceedings{paper, title={P}, crossref={proc}}\n\n@proceedings{proc, title={Proc}}\n"
+        );
+        assert_eq!(prune(&root, false).unwrap(), 0);
+    }
+}
@@ -0,0 +1,75 @@
+//! `ferrotex check`: syntax and cross-reference validation of a document without compiling it.
+
+use anyhow::Context;
+use ferrotex_syntax::TextRange;
+use ferrotexd::position::LineIndex;
+use ferrotexd::workspace::Workspace;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::Url;
+
+/// A problem found in a file of the document.
+struct Finding {
+    uri: Url,
+    range: TextRange,
+    message: String,
+}
+
+/// Checks the document rooted at `root` and its include tree, printing one
+/// `file:line:column: message` line per problem. Returns the number of problems.
+pub fn run(root: &Path) -> anyhow::Result<usize> {
+    let path = std::path::absolute(root).with_context(|| format!("Invalid path {}", root.display()))?;
+    anyhow::ensure!(path.is_file(), "{} does not exist", root.display());
+    let root_uri = Url::from_file_path(&path).map_err(|_| anyhow::anyhow!("Invalid path {}", root.display()))?;
+
+    let workspace = Workspace::new();
+    let files = workspace.load_tree(&root_uri);
+
+    let mut findings = Vec::new();
+    let mut texts = HashMap::new();
+    for uri in &files {
+        let text = read(uri)?;
+        for error in ferrotex_syntax::parse(&text).errors {
+            findings.push(Finding { uri: uri.clone(), range: error.range, message: error.message });
+        }
+        texts.insert(uri.clone(), text);
+    }
+    let validations = [
+        workspace.validate_includes(&root_uri),
+        workspace.detect_cycles(),
+        workspace.validate_bibliographies(),
+        workspace.validate_bib_duplicates(),
+        workspace.validate_citations(),
+        workspace.validate_labels(),
+    ];
+    for (uri, range, message) in validations.into_iter().flatten() {
+        findings.push(Finding { uri, range, message });
+    }
+    findings.sort_by(|a, b| (&a.uri, a.range.start(), &a.message).cmp(&(&b.uri, b.range.start(), &b.message)));
+
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let mut line_indices = HashMap::new();
+    for finding in &findings {
+        if !texts.contains_key(&finding.uri) {
+            // Bibliographies are only read when a finding points into them.
+            texts.insert(finding.uri.clone(), read(&finding.uri)?);
+        }
+        let line_index = line_indices
+            .entry(finding.uri.clone())
+            .or_insert_with(|| LineIndex::new(&texts[&finding.uri]));
+        let start = line_index.position(finding.range.start());
+        println!("{}:{}:{}: {}", display_path(&finding.uri, &cwd).display(), start.line + 1, start.character + 1, finding.message);
+    }
+    Ok(findings.len())
+}
+
+pub fn read(uri: &Url) -> anyhow::Result<String> {
+    let path = uri.to_file_path().map_err(|_| anyhow::anyhow!("Invalid file URI {}", uri))?;
+    std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// The path of `uri`, relative to `cwd` if it is below it.
+pub fn display_path(uri: &Url, cwd: &Path) -> PathBuf {
+    let path = uri.to_file_path().unwrap_or_else(|_| PathBuf::from(uri.as_str()));
+    path.strip_prefix(cwd).map(Path::to_path_buf).unwrap_or(path)
+}
@@ -0,0 +1,66 @@
+//! `ferrotex parse` on TeX sources: dumps of the concrete syntax tree.
+
+use crate::output::Format;
+use ferrotex_syntax::{SyntaxNode, TextRange};
+use rowan::NodeOrToken;
+use serde_json::json;
+use std::fmt::Write;
+
+/// File extensions parsed as TeX sources rather than logs.
+pub const TEX_EXTENSIONS: &[&str] = &["tex", "sty", "cls", "ltx", "dtx"];
+
+/// Parses `text` and renders its syntax tree and syntax errors in `format`, `tree` or
+/// `json`.
+pub fn dump(text: &str, format: Format) -> anyhow::Result<String> {
+    let parse = ferrotex_syntax::parse(text);
+    let root = parse.syntax();
+    match format {
+        Format::Tree => {
+            let mut out = format!("{:#?}", root);
+            if !parse.errors.is_empty() {
+                out.push_str("errors:\n");
+                for error in &parse.errors {
+                    let _ = writeln!(out, "  {:?}: {}", error.range, error.message);
+                }
+            }
+            Ok(out)
+        }
+        Format::Json => {
+            let errors: Vec<_> = parse
+                .errors
+                .iter()
+                .map(|error| json!({ "message": error.message, "range": range(error.range) }))
+                .collect();
+            let mut out = serde_json::to_string_pretty(&json!({ "root": node(&root), "errors": errors }))?;
+            out.push('\n');
+            Ok(out)
+        }
+        Format::Ndjson | Format::Sarif | Format::Human => {
+            anyhow::bail!("--format {} only applies to logs", format!("{:?}", format).to_lowercase())
+        }
+    }
+}
+
+fn node(node: &SyntaxNode) -> serde_json::Value {
+    let children: Vec<_> = node
+        .children_with_tokens()
+        .map(|child| match child {
+            NodeOrToken::Node(child) => self::node(&child),
+            NodeOrToken::Token(token) => json!({
+                "kind": format!("{:?}", token.kind()),
+                "range": range(token.text_range()),
+                "text": token.text(),
+            }),
+        })
+        .collect();
+    json!({
+        "kind": format!("{:?}", node.kind()),
+        "range": range(node.text_range()),
+        "children": children,
+    })
+}
+
+/// A range as `[start, end]` byte offsets.
+fn range(range: TextRange) -> [u32; 2] {
+    [range.start().into(), range.end().into()]
+}
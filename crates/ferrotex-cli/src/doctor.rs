@@ -0,0 +1,273 @@
+//! `ferrotex doctor`: checks the TeX installation and FerroTeX's caches, with fixes.
+
+use crate::output::Summary;
+use ferrotex_core::package_manager::PackageManager;
+use ferrotex_package::PackageIndex;
+use std::path::Path;
+use std::process::Command;
+
+/// The engines `ferrotex build --engine` runs, with the TeX Live package installing them;
+/// the first is the default.
+const ENGINES: &[(&str, &str)] = &[("pdflatex", "latex-bin"), ("xelatex", "xetex"), ("lualatex", "latex-bin")];
+
+/// Tools of the distribution the build planner and `build` use, with what needs them.
+const TOOLS: &[(&str, &str)] = &[
+    ("latexmk", "building with a latexmkrc"),
+    ("biber", "biblatex bibliographies"),
+    ("bibtex", "BibTeX bibliographies"),
+    ("makeindex", "indexes"),
+    ("synctex", "forward and inverse search from the command line"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// The result of one check, with how to fix it if it did not pass.
+struct Check {
+    status: Status,
+    name: String,
+    detail: String,
+    fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self { status: Status::Ok, name: name.into(), detail: detail.into(), fix: None }
+    }
+
+    fn problem(status: Status, name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { status, name: name.into(), detail: detail.into(), fix: Some(fix.into()) }
+    }
+}
+
+/// Runs every check, prints them with their fixes and counts the failed ones.
+pub fn run() -> Summary {
+    println!(
+        "ferrotex {} on {} {}\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let manager = PackageManager::new();
+    let install = |package: &str| match manager.backend_name() {
+        "miktex" => format!("Install it with `mpm --install={}`", package),
+        _ => format!("Install it with `tlmgr install {}`", package),
+    };
+
+    let engines = ENGINES
+        .iter()
+        .enumerate()
+        .map(|(i, (engine, package))| match which::which(engine) {
+            Ok(path) => {
+                let version = version(&path).unwrap_or_else(|| "unknown version".to_string());
+                Check::ok(*engine, format!("{} ({})", version, path.display()))
+            }
+            // Only the default engine is needed by every command that builds.
+            Err(_) => Check::problem(if i == 0 { Status::Error } else { Status::Warning }, *engine, "not found", install(package)),
+        })
+        .collect();
+    let tools = TOOLS
+        .iter()
+        .map(|(tool, purpose)| match which::which(tool) {
+            Ok(path) => Check::ok(*tool, path.display().to_string()),
+            Err(_) => Check::problem(Status::Warning, *tool, format!("not found, needed for {}", purpose), install(tool)),
+        })
+        .collect();
+
+    let package_manager = if manager.is_available() {
+        Check::ok("package manager", manager.backend_name())
+    } else {
+        Check::problem(
+            Status::Warning,
+            "package manager",
+            "neither tlmgr nor mpm found; `ferrotex pkg install` and missing-package fixes are unavailable",
+            "Add the bin directory of TeX Live or MiKTeX to PATH",
+        )
+    };
+
+    let sections = [
+        ("TeX distribution", vec![distribution()]),
+        ("Engines", engines),
+        ("Tools", tools),
+        ("Packages", vec![package_manager, package_index()]),
+        ("Caches", caches()),
+        ("SyncTeX", vec![synctex()]),
+    ];
+
+    let mut errors = 0;
+    let mut warnings = 0;
+    for (title, checks) in &sections {
+        println!("{}", title);
+        for check in checks {
+            let mark = match check.status {
+                Status::Ok => "ok",
+                Status::Warning => "warning",
+                Status::Error => "error",
+            };
+            println!("  [{}] {}: {}", mark, check.name, check.detail);
+            if let Some(fix) = &check.fix {
+                println!("      fix: {}", fix);
+            }
+            match check.status {
+                Status::Error => errors += 1,
+                Status::Warning => warnings += 1,
+                Status::Ok => {}
+            }
+        }
+        println!();
+    }
+    println!(
+        "{} error{}, {} warning{}",
+        errors,
+        if errors == 1 { "" } else { "s" },
+        warnings,
+        if warnings == 1 { "" } else { "s" }
+    );
+    Summary { errors, warnings, ..Summary::default() }
+}
+
+/// Whether `kpsewhich` finds the distribution's files, and which distribution it is.
+fn distribution() -> Check {
+    let name = "distribution";
+    let fix = "Install TeX Live (https://tug.org/texlive/) or MiKTeX (https://miktex.org/) and add its bin directory to PATH";
+    let Ok(kpsewhich) = which::which("kpsewhich") else {
+        return Check::problem(Status::Error, name, "no TeX distribution found (kpsewhich is not in PATH)", fix);
+    };
+    let Some(article) = output(&kpsewhich, &["article.cls"]).filter(|path| !path.is_empty()) else {
+        return Check::problem(
+            Status::Error,
+            name,
+            "kpsewhich cannot find article.cls",
+            "Install the LaTeX base packages (`tlmgr install latex`), or run `mktexlsr` to refresh the file database",
+        );
+    };
+    let texmf = output(&kpsewhich, &["-var-value", "TEXMFDIST"]).unwrap_or_default();
+    // `kpsewhich --version` names the distribution, e.g. `kpathsea version 6.3.5 (TeX Live 2023)`.
+    let release = version(&kpsewhich)
+        .and_then(|line| Some(line[line.find('(')? + 1..line.rfind(')')?].to_string()))
+        .unwrap_or_else(|| "unknown release".to_string());
+    let root = if texmf.is_empty() { article } else { texmf };
+    Check::ok(name, format!("{} ({})", release, root))
+}
+
+/// Whether the package index FerroTeX completes `\usepackage` and commands from is cached.
+fn package_index() -> Check {
+    let name = "package index";
+    let Some(path) = PackageIndex::cache_path() else {
+        return Check::problem(Status::Warning, name, "no cache directory", "Set $XDG_CACHE_HOME or $HOME");
+    };
+    if !path.exists() {
+        return Check::problem(
+            Status::Warning,
+            name,
+            format!("not built yet ({})", path.display()),
+            "Open a TeX file with the FerroTeX language server running; it scans the distribution once",
+        );
+    }
+    match PackageIndex::load_from_path(&path) {
+        Some(index) if index.packages.is_empty() => Check::problem(
+            Status::Warning,
+            name,
+            format!("{} is empty; the distribution was not found when it was scanned", path.display()),
+            format!("Delete {} once the distribution is installed; it is rebuilt on the next run", path.display()),
+        ),
+        Some(index) => Check::ok(
+            name,
+            format!("{} packages, {} files ({})", index.packages.len(), index.files.len(), path.display()),
+        ),
+        None => Check::problem(
+            Status::Error,
+            name,
+            format!("{} is unreadable", path.display()),
+            format!("Delete {}; it is rebuilt on the next run", path.display()),
+        ),
+    }
+}
+
+/// Whether the cache directories are writable, and how much they hold.
+fn caches() -> Vec<Check> {
+    let mut checks = Vec::new();
+    match dirs::cache_dir().map(|dir| dir.join("ferrotex")) {
+        Some(dir) => {
+            checks.push(writable("cache directory", &dir));
+            let workspaces = dir.join("workspaces");
+            if workspaces.is_dir() {
+                let (files, bytes) = usage(&workspaces);
+                checks.push(Check::ok("workspace index", format!("{} projects, {}", files, size(bytes))));
+            }
+        }
+        None => checks.push(Check::problem(Status::Error, "cache directory", "unknown", "Set $XDG_CACHE_HOME or $HOME")),
+    }
+    let assets = ferrotex_build::remote::default_cache_dir();
+    if assets.is_dir() {
+        let (files, bytes) = usage(&assets);
+        checks.push(Check::ok("remote assets", format!("{} files, {} ({})", files, size(bytes), assets.display())));
+    }
+    checks
+}
+
+/// Whether files can be created in `dir`, creating it if needed.
+fn writable(name: &str, dir: &Path) -> Check {
+    let probe = dir.join(format!(".doctor-{}", std::process::id()));
+    let result = std::fs::create_dir_all(dir).and_then(|_| std::fs::write(&probe, b"")).and_then(|_| std::fs::remove_file(&probe));
+    match result {
+        Ok(()) => Check::ok(name, dir.display().to_string()),
+        Err(error) => Check::problem(
+            Status::Error,
+            name,
+            format!("{} is not writable: {}", dir.display(), error),
+            format!("Fix the permissions of {}, or delete it", dir.display()),
+        ),
+    }
+}
+
+/// Whether the default engine writes SyncTeX data, which forward and inverse search need.
+fn synctex() -> Check {
+    let (default, _) = ENGINES[0];
+    let Ok(engine) = which::which(default) else {
+        return Check::problem(Status::Warning, "engine support", "not checked, pdflatex not found", "Install pdflatex first");
+    };
+    let help = output(&engine, &["-help"]).unwrap_or_default();
+    if help.contains("-synctex") {
+        Check::ok("engine support", format!("{} accepts -synctex", default))
+    } else {
+        Check::problem(
+            Status::Warning,
+            "engine support",
+            format!("{} does not list -synctex in its options", default),
+            "Update the TeX distribution; SyncTeX is part of every engine since TeX Live 2008",
+        )
+    }
+}
+
+/// The first line `program --version` prints.
+fn version(program: &Path) -> Option<String> {
+    output(program, &["--version"]).and_then(|text| text.lines().next().map(str::to_string))
+}
+
+/// The trimmed standard output of `program args`, if it succeeded.
+fn output(program: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The number of files below `dir` and their total size in bytes.
+fn usage(dir: &Path) -> (usize, u64) {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .fold((0, 0), |(files, bytes), entry| (files + 1, bytes + entry.metadata().map(|m| m.len()).unwrap_or(0)))
+}
+
+fn size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+    }
+}
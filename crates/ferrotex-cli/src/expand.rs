@@ -0,0 +1,103 @@
+//! `ferrotex expand`: step-by-step expansion of the macros a document defines.
+
+use crate::check::read;
+use crate::output::Summary;
+use anyhow::Context;
+use ferrotex_analysis::macros::{self, MacroDefinition};
+use ferrotex_analysis::{AbstractMachine, AbstractValue};
+use ferrotexd::workspace::Workspace;
+use std::path::Path;
+use tower_lsp::lsp_types::Url;
+
+/// Characters of the text shown after each step; longer texts are cut.
+const MAX_TEXT: usize = 200;
+
+/// Expands `command` with the macros defined in `root` and the files it includes, printing
+/// every expansion with its arguments and the text it leads to.
+///
+/// Control sequences the document does not define, such as primitives and package macros,
+/// are left unexpanded and listed at the end. An expansion failing like TeX would, e.g. on
+/// a missing argument, or not finishing within `max_steps` expansions is an error.
+pub fn run(root: &Path, command: &str, max_steps: usize) -> anyhow::Result<Summary> {
+    let path = std::path::absolute(root).with_context(|| format!("Invalid path {}", root.display()))?;
+    anyhow::ensure!(path.is_file(), "{} does not exist", root.display());
+    let root_uri = Url::from_file_path(&path).map_err(|_| anyhow::anyhow!("Invalid path {}", root.display()))?;
+
+    let workspace = Workspace::new();
+    let files = workspace.load_tree(&root_uri);
+    let mut machine = AbstractMachine::new();
+    machine.max_expansions = max_steps;
+    let mut definitions: Vec<MacroDefinition> = Vec::new();
+    for uri in &files {
+        // Later definitions, e.g. from `\renewcommand`, replace earlier ones.
+        for definition in macros::harvest(&macros::tokenize(&read(uri)?)) {
+            definitions.retain(|known| known.name != definition.name);
+            definitions.push(definition);
+        }
+    }
+    println!(
+        "{} macro{} defined in {} file{}",
+        definitions.len(),
+        if definitions.len() == 1 { "" } else { "s" },
+        files.len(),
+        if files.len() == 1 { "" } else { "s" }
+    );
+    for definition in definitions {
+        machine.define(definition);
+    }
+
+    let input = macros::tokenize(command.trim());
+    println!("\n{}", macros::render(&input));
+    machine.push_input(&input);
+    let mut output = Vec::new();
+    let mut step = 0;
+    while let Some(token) = machine.step() {
+        if let AbstractValue::AnalysisError(message) = token {
+            eprintln!("Expansion stopped after {} step{}: {}", step, if step == 1 { "" } else { "s" }, message);
+            return Ok(Summary::errors(1));
+        }
+        let Some(expansion) = machine.last_expansion.take() else {
+            output.push(token);
+            continue;
+        };
+        step += 1;
+        let arguments: Vec<String> = expansion
+            .arguments
+            .iter()
+            .enumerate()
+            .map(|(i, argument)| format!("  #{} = {}", i + 1, macros::render(argument)))
+            .collect();
+        println!("{:>3}. {}{}", step, expansion.name, arguments.join(","));
+        let mut text = output.clone();
+        text.extend(machine.state.input_stack.iter().rev().cloned());
+        let text = macros::render(&text);
+        match text.char_indices().nth(MAX_TEXT) {
+            Some((cut, _)) => println!("     {}…", &text[..cut]),
+            None => println!("     {}", text),
+        }
+    }
+
+    if step == 0 {
+        let name = input.iter().find_map(|token| match token {
+            AbstractValue::ControlSequence(name) => Some(name.as_str()),
+            _ => None,
+        });
+        match name {
+            Some(name) => anyhow::bail!("{} is not defined in {} or the files it includes", name, root.display()),
+            None => anyhow::bail!("`{}` has no macro to expand", command),
+        }
+    }
+    println!("\nResult: {}", macros::render(&output));
+    let mut unexpanded: Vec<&str> = Vec::new();
+    for token in &output {
+        if let AbstractValue::ControlSequence(name) = token {
+            if !unexpanded.contains(&name.as_str()) {
+                unexpanded.push(name);
+            }
+        }
+    }
+    if !unexpanded.is_empty() {
+        println!("Left to TeX (primitives or package macros): {}", unexpanded.join(", "));
+    }
+    Ok(Summary::default())
+}
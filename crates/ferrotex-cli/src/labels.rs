@@ -0,0 +1,103 @@
+//! `ferrotex labels`: label definitions and references across a document's include tree.
+
+use crate::check::{display_path, read};
+use anyhow::Context;
+use ferrotex_syntax::TextRange;
+use ferrotexd::position::LineIndex;
+use ferrotexd::workspace::{LabelUsage, Workspace};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::Url;
+
+/// Which labels `ferrotex labels` reports; all of them if none is set.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Filter {
+    /// Labels defined but never referenced.
+    pub unused: bool,
+    /// References to labels that are not defined.
+    pub undefined: bool,
+    /// Labels defined more than once.
+    pub duplicates: bool,
+}
+
+impl Filter {
+    fn is_empty(&self) -> bool {
+        !(self.unused || self.undefined || self.duplicates)
+    }
+}
+
+/// Prints the labels of the document rooted at `root` as `file:line:column: ...` lines
+/// and returns the number of problems the filter selected.
+///
+/// Without a filter, every definition and reference is listed, label by label, and no
+/// problem is counted. With one, the selected definitions or references are listed in
+/// file order.
+pub fn run(root: &Path, filter: Filter) -> anyhow::Result<usize> {
+    let path = std::path::absolute(root).with_context(|| format!("Invalid path {}", root.display()))?;
+    anyhow::ensure!(path.is_file(), "{} does not exist", root.display());
+    let root_uri = Url::from_file_path(&path).map_err(|_| anyhow::anyhow!("Invalid path {}", root.display()))?;
+
+    let workspace = Workspace::new();
+    workspace.load_tree(&root_uri);
+    let usages = workspace.label_usages();
+    let mut printer = Printer { cwd: std::env::current_dir().unwrap_or_default(), line_indices: HashMap::new() };
+
+    if filter.is_empty() {
+        for usage in &usages {
+            let references = usage.references.len();
+            for (uri, range) in &usage.definitions {
+                let count = format!("{} reference{}", references, if references == 1 { "" } else { "s" });
+                printer.print(uri, *range, &format!("label '{}' ({})", usage.name, count))?;
+            }
+            for (uri, range) in &usage.references {
+                printer.print(uri, *range, &format!("reference to '{}'", usage.name))?;
+            }
+        }
+        return Ok(0);
+    }
+
+    let mut problems = Vec::new();
+    for usage in &usages {
+        problems.extend(select(usage, filter));
+    }
+    problems.sort_by(|a, b| (&a.0, a.1.start(), &a.2).cmp(&(&b.0, b.1.start(), &b.2)));
+    for (uri, range, message) in &problems {
+        printer.print(uri, *range, message)?;
+    }
+    Ok(problems.len())
+}
+
+/// The problems of `usage` that `filter` selects.
+fn select(usage: &LabelUsage, filter: Filter) -> Vec<(Url, TextRange, String)> {
+    let mut problems = Vec::new();
+    let mut report = |places: &[(Url, TextRange)], message: String| {
+        problems.extend(places.iter().map(|(uri, range)| (uri.clone(), *range, message.clone())));
+    };
+    if filter.unused && usage.references.is_empty() {
+        report(&usage.definitions, format!("Unused label: '{}'", usage.name));
+    }
+    if filter.undefined && usage.definitions.is_empty() {
+        report(&usage.references, format!("Undefined reference: '{}'", usage.name));
+    }
+    if filter.duplicates && usage.definitions.len() > 1 {
+        report(&usage.definitions, format!("Duplicate label definition: '{}'", usage.name));
+    }
+    problems
+}
+
+/// Prints locations relative to the working directory, reading each file once.
+struct Printer {
+    cwd: PathBuf,
+    line_indices: HashMap<Url, LineIndex>,
+}
+
+impl Printer {
+    fn print(&mut self, uri: &Url, range: TextRange, message: &str) -> anyhow::Result<()> {
+        if !self.line_indices.contains_key(uri) {
+            self.line_indices.insert(uri.clone(), LineIndex::new(&read(uri)?));
+        }
+        let start = self.line_indices[uri].position(range.start());
+        println!("{}:{}:{}: {}", display_path(uri, &self.cwd).display(), start.line + 1, start.character + 1, message);
+        Ok(())
+    }
+}
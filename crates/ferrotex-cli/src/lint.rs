@@ -0,0 +1,224 @@
+//! `ferrotex lint`: the language server's style rules, run over `.tex` files on disk.
+
+use anyhow::Context;
+use ferrotexd::diagnostics::lint::{self, LintConfig};
+use ferrotexd::position::LineIndex;
+use serde::Deserialize;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::{NumberOrString, Range};
+
+/// The configuration file, looked up from the linted path upwards.
+pub const CONFIG_FILE: &str = ".ferrotexlint.toml";
+
+/// The contents of a [`CONFIG_FILE`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintFile {
+    /// Ids of the rules that do not run (see `ferrotex lint --list-rules`).
+    pub disabled: Vec<String>,
+    /// Glob patterns of files and directories not to lint, relative to the directory of
+    /// the configuration file.
+    pub exclude: Vec<String>,
+}
+
+impl LintFile {
+    /// Reads the configuration at `path`.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    /// Finds the [`CONFIG_FILE`] in `start` or the nearest directory above it.
+    pub fn find(start: &Path) -> Option<PathBuf> {
+        start.ancestors().map(|dir| dir.join(CONFIG_FILE)).find(|path| path.is_file())
+    }
+}
+
+/// How findings are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// `file:line:column: message [rule]` lines.
+    Human,
+    /// A JSON array of findings.
+    Json,
+    /// A SARIF 2.1 log, for code scanning services.
+    Sarif,
+}
+
+/// A rule violation in a file.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub path: PathBuf,
+    pub rule: String,
+    pub message: String,
+    /// Zero-based, in UTF-16 code units like LSP positions.
+    pub range: Range,
+}
+
+/// Lints the `.tex` files in `paths`, searching directories recursively, and prints the
+/// findings in `format`. Returns the number of findings.
+///
+/// `-` is standard input, reported as `stdin_filename`. The configuration is `config`,
+/// or else the [`CONFIG_FILE`] nearest to the first path.
+pub fn run(
+    paths: &[PathBuf],
+    stdin_filename: Option<&Path>,
+    config: Option<&Path>,
+    format: Format,
+) -> anyhow::Result<usize> {
+    let config_path = match config {
+        Some(path) => Some(path.to_path_buf()),
+        None => paths
+            .first()
+            .and_then(|path| LintFile::find(&std::path::absolute(crate::input_path(path, stdin_filename)).ok()?)),
+    };
+    let config = config_path.as_deref().map(LintFile::load).transpose()?.unwrap_or_default();
+    for id in &config.disabled {
+        if !lint::RULES.iter().any(|rule| rule.id == id) {
+            eprintln!("warning: unknown lint rule `{}` in {}", id, CONFIG_FILE);
+        }
+    }
+    let base = config_path.as_deref().and_then(Path::parent).map(Path::to_path_buf).unwrap_or_default();
+    let exclude = config
+        .exclude
+        .iter()
+        .map(|pattern| glob::Pattern::new(pattern).with_context(|| format!("Invalid exclude pattern `{}`", pattern)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let is_excluded = |path: &Path| {
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let relative = path.strip_prefix(&base).unwrap_or(&path);
+        exclude.iter().any(|pattern| pattern.matches_path(relative))
+    };
+
+    let lint_config = LintConfig { disabled: config.disabled.iter().cloned().collect() };
+    let mut findings = Vec::new();
+    for file in tex_files(paths, &is_excluded) {
+        let text = crate::read_input(&file)?;
+        let name = PathBuf::from(crate::input_name(&file, stdin_filename));
+        let line_index = LineIndex::new(&text);
+        let root = ferrotex_syntax::parse(&text).syntax();
+        for diagnostic in lint::check_lint(&root, &line_index, &lint_config) {
+            let rule = match diagnostic.code {
+                Some(NumberOrString::String(rule)) => rule,
+                _ => continue,
+            };
+            findings.push(Finding { path: name.clone(), rule, message: diagnostic.message, range: diagnostic.range });
+        }
+    }
+
+    match format {
+        Format::Human => {
+            for finding in &findings {
+                let start = finding.range.start;
+                println!(
+                    "{}:{}:{}: {} [{}]",
+                    finding.path.display(),
+                    start.line + 1,
+                    start.character + 1,
+                    finding.message,
+                    finding.rule
+                );
+            }
+        }
+        Format::Json => println!("{}", serde_json::to_string_pretty(&to_json(&findings))?),
+        Format::Sarif => println!("{}", serde_json::to_string_pretty(&to_sarif(&findings))?),
+    }
+    Ok(findings.len())
+}
+
+/// The `.tex` files among `paths` and in the directories among them, skipping hidden
+/// directories and the excluded paths, in a stable order.
+fn tex_files(paths: &[PathBuf], is_excluded: &dyn Fn(&Path) -> bool) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        let entries = walkdir::WalkDir::new(path)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| {
+                let hidden = entry.depth() > 0 && entry.file_name().to_string_lossy().starts_with('.');
+                !hidden && !is_excluded(entry.path())
+            })
+            .filter_map(Result::ok);
+        for entry in entries {
+            if entry.file_type().is_file() && entry.path().extension().is_some_and(|extension| extension == "tex") {
+                files.push(entry.into_path());
+            }
+        }
+    }
+    files.retain(|file| !is_excluded(file));
+    files
+}
+
+/// One-based line and column of a position.
+fn line_column(position: tower_lsp::lsp_types::Position) -> (u32, u32) {
+    (position.line + 1, position.character + 1)
+}
+
+fn to_json(findings: &[Finding]) -> serde_json::Value {
+    findings
+        .iter()
+        .map(|finding| {
+            let (line, column) = line_column(finding.range.start);
+            let (end_line, end_column) = line_column(finding.range.end);
+            json!({
+                "file": finding.path.to_string_lossy(),
+                "rule": finding.rule,
+                "message": finding.message,
+                "line": line,
+                "column": column,
+                "endLine": end_line,
+                "endColumn": end_column,
+            })
+        })
+        .collect()
+}
+
+fn to_sarif(findings: &[Finding]) -> serde_json::Value {
+    let results: Vec<_> = findings
+        .iter()
+        .map(|finding| {
+            let (line, column) = line_column(finding.range.start);
+            let (end_line, end_column) = line_column(finding.range.end);
+            json!({
+                "ruleId": finding.rule,
+                "level": "note",
+                "message": { "text": finding.message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": finding.path.to_string_lossy().replace('\\', "/") },
+                        "region": {
+                            "startLine": line,
+                            "startColumn": column,
+                            "endLine": end_line,
+                            "endColumn": end_column,
+                        },
+                    }
+                }],
+            })
+        })
+        .collect();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": ferrotex_log::export::sarif::SARIF_VERSION,
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "FerroTeX",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/jxoesneon/FerroTeX",
+                    "rules": lint::RULES
+                        .iter()
+                        .map(|rule| json!({ "id": rule.id, "shortDescription": { "text": rule.description } }))
+                        .collect::<Vec<_>>(),
+                }
+            },
+            "columnKind": "utf16CodeUnits",
+            "results": results,
+        }]
+    })
+}
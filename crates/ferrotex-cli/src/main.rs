@@ -1,11 +1,25 @@
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use ferrotex_log::LogParser;
 use notify::{EventKind, RecursiveMode, Watcher};
 use std::fs::{self, File};
 use std::io::{Read, Seek, SeekFrom};
+use output::Summary;
 use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::sync::mpsc::channel;
 
+mod bib;
+mod check;
+mod cst;
+mod doctor;
+mod expand;
+mod labels;
+mod lint;
+mod new;
+mod output;
+mod pkg;
+mod tui;
+
 /// The main CLI argument parser.
 #[derive(Parser)]
 #[command(name = "ferrotex")]
@@ -15,33 +29,283 @@ struct Cli {
     /// The subcommand to execute.
     #[command(subcommand)]
     command: Commands,
+    /// Print the counts of errors, warnings and lints as a last line of standard output.
+    #[arg(long, global = true, value_name = "FORMAT")]
+    summary: Option<output::SummaryFormat>,
+    /// Exit with 2 if there are warnings but no errors.
+    #[arg(long, global = true)]
+    strict: bool,
 }
 
 /// Available CLI subcommands.
 #[derive(Subcommand)]
 enum Commands {
-    /// Parse a TeX log file and emit JSON IR.
+    /// Parse a TeX log file and emit JSON IR, or dump the syntax tree of a TeX source.
+    ///
+    /// `.tex`, `.sty`, `.cls`, `.ltx` and `.dtx` files are parsed as TeX: the concrete
+    /// syntax tree is printed with the kind and byte range of every node and token,
+    /// followed by the syntax errors. Other files are parsed as logs.
     Parse {
-        /// Path to the .log file.
+        /// Path to the .log file or TeX source, or `-` for standard input.
         #[arg(value_name = "FILE")]
         path: PathBuf,
+        /// File name of standard input: its extension decides whether it is a TeX source.
+        #[arg(long, value_name = "NAME")]
+        stdin_filename: Option<PathBuf>,
+        /// Output format; `tree` (the default for TeX sources) only applies to them,
+        /// `ndjson`, `sarif` and `human` only to logs (which default to `json`).
+        #[arg(long)]
+        format: Option<output::Format>,
+        /// Fail if the log has an event this severe or worse.
+        #[arg(long, value_name = "SEVERITY")]
+        fail_on: Option<output::FailOn>,
+        /// Column the engine wrapped the log at (`0` if it did not).
+        #[arg(long, default_value_t = ferrotex_log::DEFAULT_MAX_PRINT_LINE)]
+        max_print_line: usize,
+        /// Drop the events scored below this confidence (0 to 1).
+        #[arg(long, default_value_t = 0.0)]
+        confidence_threshold: f64,
     },
-    /// Watch a TeX log file for changes and stream events.
+    /// Watch a TeX log file for changes and stream events, or with `--tui` rebuild a
+    /// document on every change in a terminal dashboard.
     Watch {
-        /// Path to the .log file.
+        /// Path to the .log file, or with `--tui` to the root .tex file.
         #[arg(value_name = "FILE")]
         path: PathBuf,
+        /// Build the document, rebuild it when its files change and show its errors and
+        /// warnings in a terminal dashboard.
+        #[arg(long, conflicts_with_all = ["format", "fail_on"])]
+        tui: bool,
+        /// TeX engine run with `--tui`: `pdflatex`, `xelatex` or `lualatex`.
+        #[arg(short, long, default_value = "pdflatex", requires = "tui")]
+        engine: String,
+        /// Output directory of the `--tui` builds (defaults to current directory).
+        #[arg(short, long, default_value = ".", requires = "tui")]
+        output_dir: PathBuf,
+        /// Output format: `ndjson` or `human`.
+        #[arg(long, default_value = "ndjson")]
+        format: output::Format,
+        /// Stop and fail at the first event this severe or worse.
+        #[arg(long, value_name = "SEVERITY")]
+        fail_on: Option<output::FailOn>,
+        /// Column the engine wrapped the log at (`0` if it did not).
+        #[arg(long, default_value_t = ferrotex_log::DEFAULT_MAX_PRINT_LINE)]
+        max_print_line: usize,
     },
     /// Start the Debug Adapter Protocol (DAP) server.
     Debug,
-    /// Build a TeX document using pdflatex.
+    /// Build a TeX document, or every document of a project, with pdflatex or `--engine`.
     Build {
-        /// Path to the .tex file to compile.
+        /// Path to the .tex file to compile, or to a project manifest (`ferrotex-project.json`).
+        #[arg(value_name = "FILE")]
+        path: PathBuf,
+        /// Output directory (defaults to current directory). For a project, relative to
+        /// each root document.
+        #[arg(short, long, default_value = ".")]
+        output_dir: PathBuf,
+        /// Build profile: `draft` (one pass, no PDF, no bibliography) or `final`.
+        #[arg(short, long, default_value = "final")]
+        profile: ferrotex_build::BuildProfile,
+        /// TeX engine run on the document: `pdflatex`, `xelatex` or `lualatex`.
+        #[arg(short, long, default_value = "pdflatex")]
+        engine: String,
+        /// How progress is reported: `human` lines, or `json`, one build event per line
+        /// on stdout with the other messages on stderr.
+        #[arg(long, value_enum, default_value_t = Progress::Human)]
+        progress: Progress,
+        /// Strict mode: refuse to build if a file or package differs from this lockfile.
+        #[arg(long, value_name = "LOCKFILE")]
+        locked: Option<PathBuf>,
+        /// After a successful build, lock the sources and distribution packages it read.
+        #[arg(long, value_name = "LOCKFILE")]
+        write_lock: Option<PathBuf>,
+        /// Allow `\write18` (disabled by default).
+        #[arg(long)]
+        shell_escape: bool,
+        /// Run the engine and tools in a `docker` or `podman` container, for hermetic builds.
+        #[arg(long, value_name = "RUNTIME")]
+        container: Option<ferrotex_build::ContainerRuntime>,
+        /// TeX image used with `--container`.
+        #[arg(long, default_value = ferrotex_build::container::DEFAULT_IMAGE, requires = "container")]
+        image: String,
+        /// Stop any step (engine pass, biber, ...) still running after this many seconds.
+        #[arg(long, value_name = "SECONDS")]
+        timeout: Option<u64>,
+        /// Write SyncTeX data (`true` or `false`) regardless of the profile.
+        #[arg(long, value_name = "BOOL")]
+        synctex: Option<bool>,
+        /// List the steps that would run and why, without building.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print the files a TeX document depends on, or its planned build graph.
+    ///
+    /// The dependency graph follows includes, bibliographies and graphics from the root.
+    /// `.tex` and `.bib` files next to the root that it does not reach are listed as
+    /// orphans.
+    Deps {
+        /// Path to the .tex file, or to a project manifest with `--format plan`.
         #[arg(value_name = "FILE")]
         path: PathBuf,
-        /// Output directory (defaults to current directory).
+        /// What to print.
+        #[arg(long, default_value = "json")]
+        format: DepsFormat,
+        /// Output directory for `--format plan` (defaults to current directory).
         #[arg(short, long, default_value = ".")]
         output_dir: PathBuf,
+        /// Build profile to plan for with `--format plan`.
+        #[arg(short, long, default_value = "final")]
+        profile: ferrotex_build::BuildProfile,
+    },
+    /// Format LaTeX and BibTeX files in place with the language server's formatter.
+    ///
+    /// Trailing whitespace is removed and every file ends with a newline.
+    Fmt {
+        /// Files to format; `.bib` files are formatted as bibliographies. `-` formats
+        /// standard input to standard output.
+        #[arg(value_name = "FILE", required = true)]
+        paths: Vec<PathBuf>,
+        /// File name of standard input: `.bib` formats it as a bibliography.
+        #[arg(long, value_name = "NAME")]
+        stdin_filename: Option<PathBuf>,
+        /// Change nothing: print a diff of every file that is not formatted and fail if any.
+        #[arg(long)]
+        check: bool,
+        /// Spaces per indentation level.
+        #[arg(long, default_value_t = 4)]
+        indent_size: usize,
+        /// Indent with tabs instead of spaces.
+        #[arg(long)]
+        tabs: bool,
+        /// Break prose lines longer than this column.
+        #[arg(long, value_name = "COLUMN")]
+        wrap_column: Option<usize>,
+        /// Sort `.bib` entries by citation key.
+        #[arg(long)]
+        sort_bib_entries: bool,
+    },
+    /// Check a document and everything it includes without compiling it.
+    ///
+    /// Reports syntax errors, missing included files and bibliographies, inclusion
+    /// cycles, duplicate labels and citation keys, and undefined references and
+    /// citations. Fails if there are any.
+    Check {
+        /// Path to the root .tex file.
+        #[arg(value_name = "FILE")]
+        path: PathBuf,
+    },
+    /// Check the TeX distribution, engines, tools, package manager, caches and SyncTeX
+    /// support, printing a fix for each problem.
+    ///
+    /// Fails if a check finds an error; include its output in bug reports.
+    Doctor,
+    /// Expand a command step by step with the macros a document defines, to debug them
+    /// without compiling.
+    Expand {
+        /// The root .tex file; its included files are read too.
+        #[arg(value_name = "FILE")]
+        path: PathBuf,
+        /// The text to expand, e.g. `'\mymacro{x}'`.
+        #[arg(short, long)]
+        command: String,
+        /// Expansions after which a macro is assumed to recurse forever.
+        #[arg(long, default_value_t = 200)]
+        max_steps: usize,
+    },
+    /// List the label definitions and references of a document and the files it includes.
+    ///
+    /// The filters select problems instead and fail if there are any.
+    Labels {
+        /// Path to the root .tex file.
+        #[arg(value_name = "FILE")]
+        path: PathBuf,
+        /// Labels that are never referenced.
+        #[arg(long)]
+        unused: bool,
+        /// References to labels that are not defined.
+        #[arg(long)]
+        undefined: bool,
+        /// Labels defined more than once.
+        #[arg(long)]
+        duplicates: bool,
+    },
+    /// Count the words of a document and of the files it includes, per file and section.
+    ///
+    /// Only running text counts: commands, comments, math, verbatim text and the
+    /// preamble do not.
+    Words {
+        /// Path to the root .tex file.
+        #[arg(value_name = "FILE")]
+        path: PathBuf,
+        /// Print the counts as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Remove the auxiliary files a build leaves next to root documents.
+    ///
+    /// Removes `<stem>.<ext>` next to each root document and the auxiliary files in its
+    /// `build` directory.
+    Clean {
+        /// Root documents, or directories whose `.tex` files with a `\documentclass` are
+        /// root documents.
+        #[arg(value_name = "PATH", default_value = ".")]
+        paths: Vec<PathBuf>,
+        /// Also remove the output PDF, package cache directories such as `_minted-<stem>`
+        /// and the `build` directory if it ends up empty.
+        #[arg(long)]
+        deep: bool,
+        /// Extensions to remove, comma-separated (defaults to the language server's list).
+        #[arg(long, value_delimiter = ',', value_name = "EXT")]
+        extensions: Vec<String>,
+    },
+    /// Format, check and prune BibTeX files.
+    Bib {
+        #[command(subcommand)]
+        command: bib::BibCommand,
+    },
+    /// Create a project from a template: `main.tex`, `refs.bib`, `.gitignore` and a
+    /// project manifest.
+    New {
+        /// Directory to create; its name is the project name.
+        #[arg(value_name = "NAME")]
+        path: PathBuf,
+        /// `article`, `beamer`, `thesis`, `letter`, or the name of a user template.
+        #[arg(long, default_value = "article")]
+        template: String,
+        /// Directory of user templates, one subdirectory per template (defaults to
+        /// `~/.config/ferrotex/templates`).
+        #[arg(long, value_name = "DIR")]
+        template_dir: Option<PathBuf>,
+    },
+    /// Search, install and look up the packages of the TeX distribution.
+    ///
+    /// Resolves missing-package errors from the terminal: `pkg which` names the package
+    /// defining a command, `pkg install` installs it with tlmgr or mpm.
+    Pkg {
+        #[command(subcommand)]
+        command: pkg::PkgCommand,
+    },
+    /// Check LaTeX files against the language server's lint rules.
+    ///
+    /// Rules are disabled and paths excluded in a `.ferrotexlint.toml` next to the files
+    /// or in a directory above them. Fails if there are findings.
+    Lint {
+        /// Files and directories to lint; directories are searched for `.tex` files. `-`
+        /// lints standard input.
+        #[arg(value_name = "PATH", default_value = ".")]
+        paths: Vec<PathBuf>,
+        /// File name of standard input, used in findings and to find the configuration.
+        #[arg(long, value_name = "NAME")]
+        stdin_filename: Option<PathBuf>,
+        /// How findings are printed.
+        #[arg(long, default_value = "human")]
+        format: lint::Format,
+        /// Configuration file to use instead of the nearest `.ferrotexlint.toml`.
+        #[arg(long, value_name = "FILE")]
+        config: Option<PathBuf>,
+        /// Print the rules and exit.
+        #[arg(long)]
+        list_rules: bool,
     },
     /// Verify the current source files against ferrotex.lock.
     Verify {
@@ -51,18 +315,113 @@ enum Commands {
     },
 }
 
-fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+/// What `ferrotex deps` prints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum DepsFormat {
+    /// The dependency graph as Graphviz DOT.
+    Dot,
+    /// The dependency graph as JSON, with the orphaned files.
+    Json,
+    /// The planned build graph as JSON.
+    Plan,
+}
+
+/// How `ferrotex build` reports progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Progress {
+    Human,
+    Json,
+}
+
+fn main() -> ExitCode {
+    let usage_error = |error: clap::Error| {
+        let _ = error.print();
+        ExitCode::from(if error.use_stderr() { output::EXIT_USAGE } else { 0 })
+    };
+    let matches = match Cli::command().try_get_matches() {
+        Ok(matches) => matches,
+        Err(error) => return usage_error(error),
+    };
+    let cli = match Cli::from_arg_matches(&matches) {
+        Ok(cli) => cli,
+        Err(error) => return usage_error(error),
+    };
+
+    let (summary, code, error) = match run(&cli.command) {
+        Ok(summary) => (summary, summary.exit_code(cli.strict), None),
+        Err(error) => {
+            eprintln!("Error: {:?}", error);
+            (Summary::default(), output::EXIT_FAILURE, Some(error.to_string()))
+        }
+    };
+    if let Some(output::SummaryFormat::Json) = cli.summary {
+        let command = matches.subcommand_name().unwrap_or_default();
+        println!("{}", summary.to_json(command, code, error.as_deref()));
+    }
+    ExitCode::from(code)
+}
 
-    match &cli.command {
-        Commands::Parse { path } => {
-            let content = fs::read_to_string(path)?;
-            let parser = LogParser::new();
+/// Runs `command` and returns what it found.
+fn run(command: &Commands) -> anyhow::Result<Summary> {
+    match command {
+        Commands::Parse { path, stdin_filename, format, fail_on, max_print_line, confidence_threshold } => {
+            let content = read_input(path)?;
+            let is_tex = input_path(path, stdin_filename.as_deref())
+                .extension()
+                .is_some_and(|extension| cst::TEX_EXTENSIONS.iter().any(|tex| extension == *tex));
+            if is_tex {
+                anyhow::ensure!(fail_on.is_none(), "--fail-on only applies to logs");
+                print!("{}", cst::dump(&content, format.unwrap_or(output::Format::Tree))?);
+                return Ok(Summary::default());
+            }
+            let parser = LogParser::new()
+                .with_max_print_line(*max_print_line)
+                .with_min_confidence(ferrotex_log::ir::Confidence(*confidence_threshold));
             let events = parser.parse(&content);
-            println!("{}", serde_json::to_string_pretty(&events)?);
+            let findings = ferrotex_log::export::findings(&events);
+            match format.unwrap_or(output::Format::Json) {
+                output::Format::Tree => anyhow::bail!("--format tree only applies to TeX sources"),
+                output::Format::Json => println!("{}", serde_json::to_string_pretty(&events)?),
+                output::Format::Ndjson => {
+                    for event in &events {
+                        println!("{}", serde_json::to_string(event)?);
+                    }
+                }
+                output::Format::Sarif => {
+                    let sarif = ferrotex_log::export::sarif::to_sarif(&events);
+                    println!("{}", serde_json::to_string_pretty(&sarif)?);
+                }
+                output::Format::Human => {
+                    let color = output::use_color();
+                    for finding in &findings {
+                        println!("{}", output::human(finding, color));
+                    }
+                    eprintln!("{}", output::summary(&findings));
+                }
+            }
+            let mut summary = Summary::of_findings(&findings);
+            if let Some(fail_on) = fail_on {
+                if fail_on.is_met_by(&events) {
+                    eprintln!(
+                        "{} has events of severity {} or worse",
+                        input_name(path, stdin_filename.as_deref()),
+                        fail_on.name()
+                    );
+                    summary.failed = true;
+                }
+            }
+            Ok(summary)
         }
-        Commands::Watch { path } => {
-            watch_log(path)?;
+        Commands::Watch { path, tui: true, engine, output_dir, max_print_line, .. } => {
+            let compiler = ferrotex_build::Compiler::new(engine, resolve_output_dir(path, output_dir)?);
+            tui::run(path, compiler, *max_print_line)
+        }
+        Commands::Watch { path, format, fail_on, max_print_line, .. } => {
+            anyhow::ensure!(
+                matches!(format, output::Format::Ndjson | output::Format::Human),
+                "watch prints ndjson or human output"
+            );
+            watch_log(path, *format, *fail_on, *max_print_line)
         }
         Commands::Debug => {
             #[cfg(feature = "tectonic-engine")]
@@ -73,88 +432,663 @@ fn main() -> anyhow::Result<()> {
             {
                 ferrotex_dap::run_mock_session()?;
             }
+            Ok(Summary::default())
+        }
+        Commands::Build {
+            path,
+            output_dir,
+            profile,
+            engine,
+            progress,
+            locked,
+            write_lock,
+            shell_escape,
+            container,
+            image,
+            timeout,
+            synctex,
+            dry_run,
+        } => {
+            let mut compiler = ferrotex_build::Compiler::new(engine, resolve_output_dir(path, output_dir)?)
+                .with_profile(*profile)
+                .with_sandbox(ferrotex_build::SandboxPolicy::default().with_shell_escape(*shell_escape));
+            if let Some(runtime) = container {
+                compiler = compiler.with_container(ferrotex_build::Container::new(*runtime, image));
+            }
+            if let Some(synctex) = synctex {
+                compiler = compiler.with_synctex(*synctex);
+            }
+            let timeout = timeout.map(std::time::Duration::from_secs);
+            build_tex(path, compiler, *progress, locked.as_deref(), write_lock.as_deref(), timeout, *dry_run)
+        }
+        Commands::Deps { path, format: DepsFormat::Dot, .. } => {
+            let (graph, orphans) = dependency_graph(path)?;
+            print!("{}", graph.to_dot());
+            for orphan in &orphans {
+                eprintln!("warning: {} is not reachable from {}", orphan.display(), path.display());
+            }
+            Ok(Summary::warnings(orphans.len()))
         }
-        Commands::Build { path, output_dir } => {
-            build_tex(path, output_dir)?;
+        Commands::Deps { path, format: DepsFormat::Json, .. } => {
+            let (graph, orphans) = dependency_graph(path)?;
+            let mut json = serde_json::to_value(&graph)?;
+            json["orphans"] = serde_json::to_value(&orphans)?;
+            println!("{}", serde_json::to_string_pretty(&json)?);
+            Ok(Summary::warnings(orphans.len()))
         }
-        Commands::Verify { path } => {
-            verify_lock(path)?;
+        Commands::Deps { path, format: DepsFormat::Plan, output_dir, profile } => {
+            let compiler = ferrotex_build::Compiler::new("pdflatex", resolve_output_dir(path, output_dir)?).with_profile(*profile);
+            let graph = if is_manifest(path) {
+                let manifest = ferrotex_build::ProjectManifest::load(path)?;
+                ferrotex_build::Planner::new(compiler).plan_project(&manifest)?
+            } else {
+                let sources = ferrotex_build::ProjectSources::discover(path)?;
+                ferrotex_build::Planner::new(compiler).plan_sources(&sources)
+            };
+            println!("{}", graph.to_json());
+            Ok(Summary::default())
         }
+        Commands::Fmt { paths, stdin_filename, check, indent_size, tabs, wrap_column, sort_bib_entries } => {
+            let options = ferrotexd::fmt::FormatOptions {
+                tab_size: *indent_size,
+                insert_spaces: !tabs,
+                trim_trailing_whitespace: true,
+                insert_final_newline: true,
+                wrap_column: *wrap_column,
+                sort_bib_entries: *sort_bib_entries,
+                ..Default::default()
+            };
+            format_files(paths, stdin_filename.as_deref(), *check, &options)
+        }
+        Commands::Check { path } => {
+            let problems = check::run(path)?;
+            if problems > 0 {
+                eprintln!("{} problem{} found", problems, if problems == 1 { "" } else { "s" });
+            }
+            Ok(Summary::errors(problems))
+        }
+        Commands::Doctor => Ok(doctor::run()),
+        Commands::Expand { path, command, max_steps } => expand::run(path, command, *max_steps),
+        Commands::Labels { path, unused, undefined, duplicates } => {
+            let filter = labels::Filter { unused: *unused, undefined: *undefined, duplicates: *duplicates };
+            let problems = labels::run(path, filter)?;
+            if problems > 0 {
+                eprintln!("{} problem{} found", problems, if problems == 1 { "" } else { "s" });
+            }
+            Ok(Summary::errors(problems))
+        }
+        Commands::Words { path, json } => {
+            print_word_counts(path, *json)?;
+            Ok(Summary::default())
+        }
+        Commands::Clean { paths, deep, extensions } => {
+            clean(paths, *deep, extensions)?;
+            Ok(Summary::default())
+        }
+        Commands::Bib { command } => bib::run(command),
+        Commands::New { path, template, template_dir } => {
+            let template_dir = template_dir.clone().or_else(new::user_template_dir);
+            for file in new::create(path, template, template_dir.as_deref())? {
+                println!("Created {}", file.display());
+            }
+            Ok(Summary::default())
+        }
+        Commands::Pkg { command } => pkg::run(command),
+        Commands::Lint { paths, stdin_filename, format, config, list_rules } => {
+            if *list_rules {
+                for rule in ferrotexd::diagnostics::lint::RULES {
+                    println!("{:<24} {}", rule.id, rule.description);
+                }
+                return Ok(Summary::default());
+            }
+            let findings = lint::run(paths, stdin_filename.as_deref(), config.as_deref(), *format)?;
+            if findings > 0 {
+                eprintln!("{} lint finding{}", findings, if findings == 1 { "" } else { "s" });
+            }
+            Ok(Summary::lints(findings))
+        }
+        Commands::Verify { path } => verify_lock(path),
     }
-    Ok(())
 }
 
-fn build_tex(tex_path: &Path, output_dir: &Path) -> anyhow::Result<()> {
-    use ferrotex_build::{ArtifactId, PdfLatexTransform, Transform};
+/// Formats `paths` in place, or with `check` prints a diff of those not formatted and
+/// counts them as lints.
+///
+/// `-` is standard input, named `stdin_filename`; formatted, it is printed rather than
+/// written back, so it cannot be combined with other paths.
+fn format_files(
+    paths: &[PathBuf],
+    stdin_filename: Option<&Path>,
+    check: bool,
+    options: &ferrotexd::fmt::FormatOptions,
+) -> anyhow::Result<Summary> {
+    use anyhow::Context;
 
-    let input_id = ArtifactId(tex_path.to_string_lossy().to_string());
-    let output_id = ArtifactId(
-        tex_path
-            .with_extension("pdf")
-            .file_name()
-            .unwrap()
-            .to_string_lossy()
-            .to_string(),
-    );
+    let from_stdin = paths.iter().any(|path| is_stdin(path));
+    anyhow::ensure!(check || !from_stdin || paths.len() == 1, "`-` cannot be formatted with other files");
+    let mut unformatted = 0;
+    for path in paths {
+        let text = read_input(path)?;
+        let bib = input_path(path, stdin_filename).extension().is_some_and(|extension| extension == "bib");
+        let formatted = ferrotexd::fmt::format_text(&text, bib, options);
+        if is_stdin(path) && !check {
+            print!("{}", formatted);
+            continue;
+        }
+        if formatted == text {
+            continue;
+        }
+        let name = input_name(path, stdin_filename);
+        if check {
+            unformatted += 1;
+            let diff = similar::TextDiff::from_lines(&text, &formatted);
+            print!("{}", diff.unified_diff().header(&name, &format!("{} (formatted)", name)));
+        } else {
+            fs::write(path, formatted).with_context(|| format!("Failed to write {}", name))?;
+            println!("Formatted {}", name);
+        }
+    }
+    if unformatted > 0 {
+        eprintln!("{} of {} files are not formatted", unformatted, paths.len());
+    }
+    Ok(Summary::lints(unformatted))
+}
 
-    let transform = PdfLatexTransform::new(
-        input_id,
-        output_id,
-        tex_path.to_path_buf(),
-        output_dir.to_path_buf(),
-    );
+/// Whether `path` is `-`, standard input.
+fn is_stdin(path: &Path) -> bool {
+    path == Path::new("-")
+}
 
-    println!("Running: {}", transform.description());
-    match transform.execute() {
-        Ok(()) => println!("Build successful!"),
-        Err(e) => eprintln!("Build failed: {}", e),
+/// Reads `path`, or standard input if it is `-`.
+fn read_input(path: &Path) -> anyhow::Result<String> {
+    use anyhow::Context;
+
+    if is_stdin(path) {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text).context("Failed to read standard input")?;
+        Ok(text)
+    } else {
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
     }
+}
 
-    Ok(())
+/// The path standing for `path`: `stdin_filename` for standard input.
+fn input_path<'a>(path: &'a Path, stdin_filename: Option<&'a Path>) -> &'a Path {
+    match stdin_filename {
+        Some(name) if is_stdin(path) => name,
+        _ => path,
+    }
+}
+
+/// The name of `path` in messages: `stdin_filename` or `<stdin>` for standard input.
+fn input_name(path: &Path, stdin_filename: Option<&Path>) -> String {
+    if is_stdin(path) && stdin_filename.is_none() {
+        "<stdin>".to_string()
+    } else {
+        input_path(path, stdin_filename).display().to_string()
+    }
 }
 
-fn verify_lock(lock_path: &Path) -> anyhow::Result<()> {
-    use ferrotex_build::Lockfile;
-    use sha2::{Sha256, Digest};
+/// Reads the document rooted at `path` and returns its dependency graph, with the `.tex`
+/// and `.bib` files in its directory that the graph does not reach.
+///
+/// Orphans are also added to the graph, as nodes without edges.
+fn dependency_graph(path: &Path) -> anyhow::Result<(ferrotexd::dependency_graph::DependencyGraph, Vec<PathBuf>)> {
+    use ferrotexd::dependency_graph::NodeKind;
 
-    let lockfile = Lockfile::load(lock_path)?;
-    println!("🔍 Verifying build against lockfile: {}", lock_path.display());
+    anyhow::ensure!(path.is_file(), "{} does not exist", path.display());
+    let path = std::path::absolute(path)?;
+    let root = tower_lsp::lsp_types::Url::from_file_path(&path)
+        .map_err(|_| anyhow::anyhow!("Invalid path {}", path.display()))?;
+    let workspace = ferrotexd::workspace::Workspace::new();
+    workspace.load_tree(&root);
+    let mut graph = workspace.dependency_graph(Some(&root));
 
-    let mut all_match = true;
-    for (path_str, expected_hash) in &lockfile.entries {
-        let path = Path::new(path_str);
-        if !path.exists() {
-            println!("❌ Missing file: {}", path_str);
-            all_match = false;
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let mut orphans = Vec::new();
+    let mut files = ferrotexd::workspace::collect_project_files(path.parent().unwrap_or(Path::new(".")));
+    files.sort();
+    for file in files {
+        let Ok(uri) = tower_lsp::lsp_types::Url::from_file_path(&file) else { continue };
+        if graph.nodes.iter().any(|node| node.uri == uri) {
             continue;
         }
+        let bib = file.extension().is_some_and(|extension| extension == "bib");
+        graph.add_node(&uri, if bib { NodeKind::Bibliography } else { NodeKind::Tex }, true);
+        orphans.push(file.strip_prefix(&cwd).map(Path::to_path_buf).unwrap_or(file));
+    }
+    Ok((graph, orphans))
+}
+
+/// Prints the word counts of the document rooted at `path`, per file in include order
+/// and per section.
+fn print_word_counts(path: &Path, json: bool) -> anyhow::Result<()> {
+    use ferrotexd::word_count::Counts;
 
-        let data = fs::read(path)?;
-        let mut hasher = Sha256::new();
-        hasher.update(&data);
-        let actual_hash = hex::encode(hasher.finalize());
+    anyhow::ensure!(path.is_file(), "{} does not exist", path.display());
+    let path = std::path::absolute(path)?;
+    let root = tower_lsp::lsp_types::Url::from_file_path(&path)
+        .map_err(|_| anyhow::anyhow!("Invalid path {}", path.display()))?;
+    let workspace = ferrotexd::workspace::Workspace::new();
+    let cwd = std::env::current_dir().unwrap_or_default();
 
-        if actual_hash == *expected_hash {
-            println!("✅ OK: {}", path_str);
+    let mut total = Counts::default();
+    let mut files = Vec::new();
+    for uri in workspace.load_tree(&root) {
+        let Ok(file) = uri.to_file_path() else { continue };
+        let text = fs::read_to_string(&file)?;
+        let count = ferrotexd::word_count::count_words(&ferrotex_syntax::parse(&text).syntax());
+        let line_index = ferrotexd::position::LineIndex::new(&text);
+        total.words += count.total.words;
+        total.characters += count.total.characters;
+
+        let name = file.strip_prefix(&cwd).unwrap_or(&file).display().to_string();
+        if json {
+            let sections: Vec<_> = count
+                .sections
+                .iter()
+                .map(|section| {
+                    let mut value = serde_json::to_value(section).unwrap_or_default();
+                    value["line"] = (line_index.position(section.offset).line + 1).into();
+                    value
+                })
+                .collect();
+            files.push(serde_json::json!({
+                "file": name,
+                "words": count.total.words,
+                "characters": count.total.characters,
+                "sections": sections,
+            }));
         } else {
-            println!("❌ MISMATCH: {}", path_str);
-            println!("   Expected: {}", expected_hash);
-            println!("   Actual:   {}", actual_hash);
-            all_match = false;
+            println!("{}: {} words, {} characters", name, count.total.words, count.total.characters);
+            for section in &count.sections {
+                println!(
+                    "  {}:{} {} \"{}\": {} words, {} characters",
+                    name,
+                    line_index.position(section.offset).line + 1,
+                    section.command,
+                    section.title,
+                    section.counts.words,
+                    section.counts.characters
+                );
+            }
+        }
+    }
+    if json {
+        let summary = serde_json::json!({ "words": total.words, "characters": total.characters, "files": files });
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+    } else {
+        println!("Total: {} words, {} characters", total.words, total.characters);
+    }
+    Ok(())
+}
+
+/// Cleans the root documents among `paths` and in the directories among them, printing
+/// the removed paths.
+fn clean(paths: &[PathBuf], deep: bool, extensions: &[String]) -> anyhow::Result<()> {
+    use anyhow::Context;
+
+    let extensions: Vec<String> = if extensions.is_empty() {
+        ferrotexd::build::clean::DEFAULT_EXTENSIONS.iter().map(|extension| extension.to_string()).collect()
+    } else {
+        extensions.to_vec()
+    };
+    let mut roots = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            anyhow::ensure!(path.is_file(), "{} does not exist", path.display());
+            roots.push(path.clone());
+            continue;
+        }
+        let entries = fs::read_dir(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut found: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|file| file.extension().is_some_and(|extension| extension == "tex"))
+            .filter(|file| fs::read_to_string(file).is_ok_and(|text| text.contains("\\documentclass")))
+            .collect();
+        found.sort();
+        roots.extend(found);
+    }
+
+    let mut count = 0;
+    for root in &roots {
+        let removed = ferrotexd::build::clean::clean(root, &extensions, deep)
+            .with_context(|| format!("Failed to clean {}", root.display()))?;
+        for path in &removed {
+            println!("Removed {}", path.display());
+        }
+        count += removed.len();
+    }
+    eprintln!("Removed {} file(s) for {} root document(s)", count, roots.len());
+    Ok(())
+}
+
+/// Whether `path` is a project manifest rather than a TeX document.
+fn is_manifest(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "json")
+}
+
+/// The output directory for building `path`: relative to the working directory for a
+/// document, relative to each root document for a project.
+fn resolve_output_dir(path: &Path, output_dir: &Path) -> std::io::Result<PathBuf> {
+    if is_manifest(path) {
+        Ok(output_dir.to_path_buf())
+    } else {
+        std::path::absolute(output_dir)
+    }
+}
+
+/// Builds `path`, a document or a project manifest, with `compiler` (see [`resolve_output_dir`]).
+///
+/// Projects keep a build cache next to their manifest, so unchanged targets are not
+/// rebuilt. Ctrl-C stops the running steps and fails the build, as does a failing step,
+/// so the process exits non-zero. With `dry_run`, only explains what would run.
+fn build_tex(
+    path: &Path,
+    compiler: ferrotex_build::Compiler,
+    progress: Progress,
+    locked: Option<&Path>,
+    write_lock: Option<&Path>,
+    timeout: Option<std::time::Duration>,
+    dry_run: bool,
+) -> anyhow::Result<Summary> {
+    use ferrotex_build::{BuildCache, CancellationToken, Executor, Lockfile, Planner, ProjectManifest};
+
+    let profile = compiler.profile;
+    let planner = Planner::new(compiler);
+    let (graph, roots, cache_path) = if is_manifest(path) {
+        let manifest = ProjectManifest::load(path)?;
+        let roots = manifest.targets.iter().map(|target| target.root.clone()).collect();
+        (planner.plan_project(&manifest)?, roots, Some(path.with_extension("cache")))
+    } else {
+        (planner.plan(path)?, vec![path.to_path_buf()], None)
+    };
+    let mut cache = cache_path.as_deref().map(BuildCache::load_or_default);
+
+    if dry_run {
+        let mut executor = Executor::new(&graph);
+        if let Some(cache) = &mut cache {
+            executor = executor.with_cache(cache);
+        }
+        let report = executor.execute_dry_run().map_err(anyhow::Error::msg)?;
+        print_dry_run(&report);
+        return Ok(Summary::default());
+    }
+    // With JSON progress, stdout only carries the events.
+    let say = |message: String| match progress {
+        Progress::Human => println!("{}", message),
+        Progress::Json => eprintln!("{}", message),
+    };
+    say(format!("Building {} ({} profile)", path.display(), profile));
+    let lockfile = locked.map(Lockfile::load).transpose()?;
+    let cancel = CancellationToken::new();
+    let handler = cancel.clone();
+    ctrlc::set_handler(move || handler.cancel())?;
+    let mut executor = Executor::new(&graph).with_cancellation(cancel);
+    if let Some(cache) = &mut cache {
+        executor = executor.with_cache(cache);
+    }
+    if let Some(timeout) = timeout {
+        executor = executor.with_timeout(timeout);
+    }
+    if let Some(lockfile) = &lockfile {
+        if !lockfile.packages.is_empty() {
+            let violations = lockfile.verify_packages(&package_index());
+            if let Some(violation) = violations.first() {
+                eprintln!("Distribution differs from the lockfile: {}", violation);
+                return Ok(Summary::errors(violations.len()));
+            }
+        }
+        // Lockfile paths are relative to the working directory, as for `ferrotex verify`.
+        executor = executor.with_lockfile(lockfile, Path::new(""));
+    }
+    let (events, received) = std::sync::mpsc::channel();
+    let printer = std::thread::spawn(move || match progress {
+        Progress::Human => received.into_iter().for_each(print_event),
+        Progress::Json => {
+            for event in received {
+                if let Ok(line) = serde_json::to_string(&event) {
+                    println!("{}", line);
+                }
+            }
+        }
+    });
+    let report = executor.with_events(events).run();
+    let _ = printer.join();
+    let report = report.map_err(anyhow::Error::msg)?;
+    if let (Some(cache), Some(cache_path)) = (&cache, &cache_path) {
+        cache.save(cache_path)?;
+    }
+    let mut summary = Summary::of_findings(&ferrotex_log::export::findings(&engine_log_events(&graph)));
+    if report.cancelled() {
+        eprintln!("Build cancelled");
+        return Ok(Summary { failed: true, ..summary });
+    }
+    if !report.success() {
+        suggest_installs(&graph);
+        eprintln!("Build failed");
+        summary.failed = true;
+        return Ok(summary);
+    }
+    say("Build successful!".to_string());
+    print_run_summaries(&graph, say);
+    if let Some(lock_path) = write_lock {
+        write_lockfile(&planner, &roots, lock_path)?;
+        say(format!("🔐 Saved lockfile to: {}", lock_path.display()));
+    }
+
+    Ok(summary)
+}
+
+/// Suggests installing the packages and classes the engine logs of `graph` report missing.
+fn suggest_installs(graph: &ferrotex_build::BuildGraph) {
+    use ferrotex_log::ir::{EventPayload, MissingFileKind};
+
+    let mut missing = std::collections::BTreeSet::new();
+    for event in engine_log_events(graph) {
+        if let EventPayload::MissingFile { name, kind: MissingFileKind::Package | MissingFileKind::Class } = event.payload {
+            missing.insert(name);
+        }
+    }
+    for name in missing {
+        let package = ferrotex_core::package_manager::PackageManager::package_for_file(&name);
+        eprintln!("hint: `{}` is not installed; try `tlmgr install {}`", name, package);
+    }
+}
+
+/// Reports the size of the documents the engine logs of `graph` say were written.
+fn print_run_summaries(graph: &ferrotex_build::BuildGraph, say: impl Fn(String)) {
+    use ferrotex_log::ir::EventPayload;
+
+    for event in engine_log_events(graph) {
+        let EventPayload::RunSummary(stats) = event.payload else { continue };
+        if let (Some(output), Some(pages), Some(bytes)) = (stats.output, stats.pages, stats.bytes) {
+            say(format!("Output written on {} ({} pages, {} bytes)", output, pages, bytes));
+        }
+    }
+}
+
+/// Parses the engine logs among the artifacts of `graph` that exist on disk.
+fn engine_log_events(graph: &ferrotex_build::BuildGraph) -> Vec<ferrotex_log::ir::LogEvent> {
+    graph
+        .artifacts()
+        .filter_map(|artifact| artifact.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "log"))
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .flat_map(|log| LogParser::new().parse(&log))
+        .collect()
+}
+
+/// Reports the progress of a build on the console.
+fn print_event(event: ferrotex_build::BuildEvent) {
+    use ferrotex_build::{BuildEvent, TransformOutcome};
+
+    match event {
+        BuildEvent::TransformStarted { description, .. } => println!("Running: {}", description),
+        BuildEvent::TransformFinished { description, outcome, duration, .. } => match outcome {
+            TransformOutcome::Succeeded => println!("Ran: {} ({:.1}s)", description, duration.as_secs_f64()),
+            TransformOutcome::Cached => println!("Up to date: {}", description),
+            TransformOutcome::Failed { error } => eprintln!("Failed: {}: {}", description, error),
+            TransformOutcome::Skipped { .. } => eprintln!("Skipped: {}", description),
+            TransformOutcome::Cancelled => eprintln!("Cancelled: {}", description),
+        },
+        // Engine output is kept in the log files next to the PDF.
+        BuildEvent::LogChunk { .. } | BuildEvent::BuildFinished { .. } => {}
+    }
+}
+
+/// Explains each step of a dry run on the console.
+fn print_dry_run(report: &ferrotex_build::DryRunReport) {
+    use ferrotex_build::RunReason;
+
+    let list = |ids: &[ferrotex_build::ArtifactId]| ids.iter().map(|id| id.0.as_str()).collect::<Vec<_>>().join(", ");
+    for entry in &report.transforms {
+        if !entry.would_run() {
+            println!("Up to date: {}", entry.description);
+            continue;
         }
+        let reasons: Vec<String> = entry
+            .reasons
+            .iter()
+            .map(|reason| match reason {
+                RunReason::NotCached => "no previous run recorded".to_string(),
+                RunReason::Uncacheable => "cannot be cached".to_string(),
+                RunReason::InputsChanged { artifacts } => format!("changed inputs: {}", list(artifacts)),
+                RunReason::OutputsChanged { artifacts } => format!("changed outputs: {}", list(artifacts)),
+                RunReason::DependencyRuns { dependency } => {
+                    format!("runs after {}", report.transforms[*dependency].description)
+                }
+            })
+            .collect();
+        println!("Would run: {} ({})", entry.description, reasons.join("; "));
     }
+}
 
-    if all_match {
+/// Locks the sources of `roots` and the packages listed in the `.fls` of their last build.
+fn write_lockfile(planner: &ferrotex_build::Planner, roots: &[PathBuf], lock_path: &Path) -> anyhow::Result<()> {
+    use ferrotex_build::{Artifact, FileArtifact, FileRecorder, Lockfile, ProjectSources};
+
+    let cwd = std::env::current_dir()?.canonicalize()?;
+    let mut lockfile = Lockfile::new();
+    let index = package_index();
+    for root in roots {
+        let sources = ProjectSources::discover(root)?;
+        for file in sources.tex_files.iter().chain(&sources.bibliographies).chain(&sources.graphics).chain(&sources.packages) {
+            // Relative paths keep the lockfile valid in other checkouts of the project.
+            let path = file.strip_prefix(&cwd).unwrap_or(file);
+            lockfile.entries.insert(path.to_string_lossy().into_owned(), FileArtifact::new(file.clone()).fingerprint());
+        }
+        let stem = sources.root.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        let fls = planner.output_dir(&sources.root).join(format!("{}.fls", stem));
+        match FileRecorder::load(&fls) {
+            Ok(recorder) => lockfile.record_packages(&recorder, &index),
+            Err(e) => eprintln!("⚠️ No package versions recorded, cannot read {}: {}", fls.display(), e),
+        }
+    }
+    lockfile.save(lock_path)
+}
+
+/// The package index of the local TeX distribution, rescanned if the cache has no file list.
+fn package_index() -> ferrotex_package::PackageIndex {
+    use ferrotex_package::{scanner::PackageScanner, PackageIndex};
+
+    PackageIndex::load_from_cache()
+        .filter(|index| !index.files.is_empty())
+        .unwrap_or_else(|| PackageScanner::new().scan())
+}
+
+fn verify_lock(lock_path: &Path) -> anyhow::Result<Summary> {
+    use ferrotex_build::{LockViolation, Lockfile};
+
+    let lockfile = Lockfile::load(lock_path)?;
+    println!("🔍 Verifying build against lockfile: {}", lock_path.display());
+
+    let mut violations = lockfile.verify(Path::new(""));
+    if !lockfile.packages.is_empty() {
+        violations.extend(lockfile.verify_packages(&package_index()));
+    }
+    let mut paths: Vec<&String> = lockfile.entries.keys().collect();
+    paths.sort();
+    for path_str in paths {
+        match violations.iter().find(|v| v.path() == path_str) {
+            None => println!("✅ OK: {}", path_str),
+            Some(LockViolation::Missing { .. }) => println!("❌ Missing file: {}", path_str),
+            Some(LockViolation::Mismatch { expected, actual, .. }) => {
+                println!("❌ MISMATCH: {}", path_str);
+                println!("   Expected: {}", expected);
+                println!("   Actual:   {}", actual);
+            }
+            Some(violation) => println!("❌ {}", violation),
+        }
+    }
+    for (file, package) in &lockfile.packages {
+        match violations.iter().find(|v| v.path() == file) {
+            None => println!("✅ OK: {} ({} r{})", file, package.name, package.revision),
+            Some(violation) => println!("❌ {}", violation),
+        }
+    }
+
+    if violations.is_empty() {
         println!("\n✨ Build is verified and reproducible!");
     } else {
         println!("\n⚠️ Build integrity verification failed!");
-        std::process::exit(1);
     }
 
-    Ok(())
+    Ok(Summary::errors(violations.len()))
+}
+
+/// Prints the events of a watched log as they arrive, in `ndjson` or `human` format.
+struct WatchPrinter {
+    format: output::Format,
+    fail_on: Option<output::FailOn>,
+    color: bool,
+    /// Every event so far; human output locates findings with the files opened before.
+    events: Vec<ferrotex_log::ir::LogEvent>,
+    printed: usize,
+}
+
+impl WatchPrinter {
+    /// Prints `events`, stopping at the first one `fail_on` is met by; returns whether it did.
+    fn print(&mut self, events: Vec<ferrotex_log::ir::LogEvent>) -> anyhow::Result<bool> {
+        let mut failed = false;
+        for event in events {
+            failed = self.fail_on.is_some_and(|fail_on| fail_on.is_met_by(std::slice::from_ref(&event)));
+            if self.format == output::Format::Ndjson {
+                println!("{}", serde_json::to_string(&event)?);
+            }
+            self.events.push(event);
+            if failed {
+                break;
+            }
+        }
+        if self.format == output::Format::Human {
+            let findings = ferrotex_log::export::findings(&self.events);
+            for finding in findings.iter().skip(self.printed) {
+                println!("{}", output::human(finding, self.color));
+            }
+            self.printed = self.printed.max(findings.len());
+        }
+        if let Some(fail_on) = self.fail_on.filter(|_| failed) {
+            eprintln!("Stopped at an event of severity {} or worse", fail_on.name());
+        }
+        Ok(failed)
+    }
+
+    /// The errors and warnings printed, failed if watching stopped at `fail_on`.
+    fn summary(&self, failed: bool) -> Summary {
+        Summary { failed, ..Summary::of_findings(&ferrotex_log::export::findings(&self.events)) }
+    }
+
+    /// Forgets the events of a truncated log.
+    fn reset(&mut self) {
+        self.events.clear();
+        self.printed = 0;
+    }
 }
 
-/// Watches a log file for changes and prints new events as JSON.
+/// Watches a log file for changes and prints new events as JSON lines, or its errors and
+/// warnings as `file:line: severity: message` lines.
 ///
 /// This function tails the file, similar to `tail -f`, but parses the content
 /// using `LogParser` to emit structured events.
@@ -162,8 +1096,15 @@ fn verify_lock(lock_path: &Path) -> anyhow::Result<()> {
 /// # Arguments
 ///
 /// * `path` - The path to the log file to watch.
-fn watch_log(path: &Path) -> anyhow::Result<()> {
-    let mut parser = LogParser::new();
+/// * `fail_on` - Stop at the first event this severe or worse, failing.
+fn watch_log(
+    path: &Path,
+    format: output::Format,
+    fail_on: Option<output::FailOn>,
+    max_print_line: usize,
+) -> anyhow::Result<Summary> {
+    let mut printer = WatchPrinter { format, fail_on, color: output::use_color(), events: Vec::new(), printed: 0 };
+    let mut parser = LogParser::new().with_max_print_line(max_print_line);
     let mut file = File::open(path)?;
     let mut pos = 0;
 
@@ -174,9 +1115,8 @@ fn watch_log(path: &Path) -> anyhow::Result<()> {
         let mut buffer = String::new();
         file.read_to_string(&mut buffer)?;
         pos = len;
-        let events = parser.update(&buffer);
-        for event in events {
-            println!("{}", serde_json::to_string(&event)?);
+        if printer.print(parser.update(&buffer))? {
+            return Ok(printer.summary(true));
         }
     }
 
@@ -200,23 +1140,22 @@ fn watch_log(path: &Path) -> anyhow::Result<()> {
                         // Since we seeked to `pos`, it reads from `pos` to end.
                         // Note: This assumes valid UTF-8 appending.
                         file.read_to_string(&mut buffer)?;
-                        let events = parser.update(&buffer);
-                        for event in events {
-                            println!("{}", serde_json::to_string(&event)?);
+                        if printer.print(parser.update(&buffer))? {
+                            return Ok(printer.summary(true));
                         }
                         pos = current_len;
                     } else if current_len < pos {
                         // File truncated? Reset.
                         eprintln!("File truncated, resetting parser.");
-                        parser = LogParser::new();
+                        parser = LogParser::new().with_max_print_line(max_print_line);
+                        printer.reset();
                         file.seek(SeekFrom::Start(0))?;
                         // Read everything again
                         let mut buffer = String::new();
                         file.read_to_string(&mut buffer)?;
                         pos = file.metadata()?.len();
-                        let events = parser.update(&buffer);
-                        for event in events {
-                            println!("{}", serde_json::to_string(&event)?);
+                        if printer.print(parser.update(&buffer))? {
+                            return Ok(printer.summary(true));
                         }
                     }
                 }
@@ -225,5 +1164,5 @@ fn watch_log(path: &Path) -> anyhow::Result<()> {
         }
     }
 
-    Ok(())
+    Ok(printer.summary(false))
 }
@@ -0,0 +1,205 @@
+//! `ferrotex new`: project skeletons from templates.
+
+use anyhow::Context;
+use ferrotex_build::project::{ProjectManifest, ProjectTarget, MANIFEST_FILE};
+use std::path::{Path, PathBuf};
+
+/// The templates built into the binary: their name and files, as `(path, contents)`.
+pub const TEMPLATES: &[(&str, &[(&str, &str)])] = &[
+    (
+        "article",
+        &[
+            ("main.tex", include_str!("../templates/article/main.tex")),
+            ("refs.bib", include_str!("../templates/article/refs.bib")),
+        ],
+    ),
+    (
+        "beamer",
+        &[
+            ("main.tex", include_str!("../templates/beamer/main.tex")),
+            ("refs.bib", include_str!("../templates/beamer/refs.bib")),
+        ],
+    ),
+    (
+        "thesis",
+        &[
+            ("main.tex", include_str!("../templates/thesis/main.tex")),
+            ("refs.bib", include_str!("../templates/thesis/refs.bib")),
+        ],
+    ),
+    ("letter", &[("main.tex", include_str!("../templates/letter/main.tex"))]),
+];
+
+/// Replaced by the project name in template files, escaped for TeX in `.tex` files.
+const NAME_PLACEHOLDER: &str = "{{name}}";
+
+/// The directory of user templates: `~/.config/ferrotex/templates` on Linux.
+pub fn user_template_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("ferrotex").join("templates"))
+}
+
+/// Creates the project `path` from `template` and returns the files written.
+///
+/// A directory `<template_dir>/<template>` replaces the built-in template of that name,
+/// or adds one: all its files are copied. The project gets a `.gitignore` of the
+/// auxiliary files and a project manifest building `main.tex` unless the template has
+/// its own.
+pub fn create(path: &Path, template: &str, template_dir: Option<&Path>) -> anyhow::Result<Vec<PathBuf>> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .with_context(|| format!("Invalid project path {}", path.display()))?;
+    let mut files = match template_dir.map(|dir| dir.join(template)).filter(|dir| dir.is_dir()) {
+        Some(dir) => read_template_dir(&dir)?,
+        None => {
+            let Some((_, files)) = TEMPLATES.iter().find(|(known, _)| *known == template) else {
+                let known: Vec<&str> = TEMPLATES.iter().map(|(known, _)| *known).collect();
+                anyhow::bail!("Unknown template `{}` (expected {} or a user template)", template, known.join(", "));
+            };
+            files.iter().map(|(file, text)| (PathBuf::from(file), text.as_bytes().to_vec())).collect()
+        }
+    };
+
+    if !files.iter().any(|(file, _)| file == Path::new(".gitignore")) {
+        files.push((PathBuf::from(".gitignore"), gitignore().into_bytes()));
+    }
+    if !files.iter().any(|(file, _)| file == Path::new(MANIFEST_FILE)) {
+        files.push((PathBuf::from(MANIFEST_FILE), manifest(&name)?.into_bytes()));
+    }
+
+    if path.exists() {
+        let empty = path.read_dir().map(|mut entries| entries.next().is_none()).unwrap_or(false);
+        anyhow::ensure!(empty, "{} already exists and is not an empty directory", path.display());
+    }
+    let mut written = Vec::new();
+    for (file, contents) in files {
+        let target = path.join(&file);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let contents = match String::from_utf8(contents) {
+            Ok(text) => substitute(&text, &name, is_tex(&file)).into_bytes(),
+            Err(error) => error.into_bytes(),
+        };
+        std::fs::write(&target, contents).with_context(|| format!("Failed to write {}", target.display()))?;
+        written.push(target);
+    }
+    Ok(written)
+}
+
+/// The files below `dir`, relative to it, in a stable order.
+fn read_template_dir(dir: &Path) -> anyhow::Result<Vec<(PathBuf, Vec<u8>)>> {
+    let mut files = Vec::new();
+    for entry in walkdir::WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            let contents =
+                std::fs::read(entry.path()).with_context(|| format!("Failed to read {}", entry.path().display()))?;
+            files.push((entry.path().strip_prefix(dir)?.to_path_buf(), contents));
+        }
+    }
+    anyhow::ensure!(!files.is_empty(), "Template {} is empty", dir.display());
+    Ok(files)
+}
+
+/// Ignores the auxiliary files `ferrotex clean` removes, the output and the build directory.
+fn gitignore() -> String {
+    let mut text = String::from("# LaTeX auxiliary files\n");
+    for extension in ferrotexd::build::clean::DEFAULT_EXTENSIONS {
+        text.push_str(&format!("*.{}\n", extension));
+    }
+    text.push_str("\n# Output\n*.pdf\nbuild/\n");
+    for directory in ferrotexd::build::clean::CACHE_DIRECTORIES {
+        text.push_str(&format!("{}/\n", directory));
+    }
+    text.push_str("_minted-*/\n");
+    text
+}
+
+/// A project manifest with `main.tex` as its only target.
+fn manifest(name: &str) -> anyhow::Result<String> {
+    let manifest = ProjectManifest {
+        targets: vec![ProjectTarget {
+            name: name.to_string(),
+            root: PathBuf::from("main.tex"),
+            engine: None,
+            profile: None,
+            post_process: Vec::new(),
+            assets: Vec::new(),
+        }],
+    };
+    Ok(serde_json::to_string_pretty(&manifest)? + "\n")
+}
+
+fn is_tex(file: &Path) -> bool {
+    file.extension().is_some_and(|extension| crate::cst::TEX_EXTENSIONS.iter().any(|tex| extension == *tex))
+}
+
+/// Replaces [`NAME_PLACEHOLDER`] in `text` by `name`, escaping TeX special characters if
+/// `tex`.
+fn substitute(text: &str, name: &str, tex: bool) -> String {
+    if !tex {
+        return text.replace(NAME_PLACEHOLDER, name);
+    }
+    let mut escaped = String::new();
+    for c in name.chars() {
+        match c {
+            '_' | '&' | '%' | '#' | '$' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            _ => escaped.push(c),
+        }
+    }
+    text.replace(NAME_PLACEHOLDER, &escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_from_each_template() {
+        let dir = tempfile::tempdir().unwrap();
+        for (template, template_files) in TEMPLATES {
+            let path = dir.path().join(format!("{}_project", template));
+            let written = create(&path, template, None).unwrap();
+            assert_eq!(written.len(), template_files.len() + 2, "{}", template);
+
+            let main = std::fs::read_to_string(path.join("main.tex")).unwrap();
+            assert!(!main.contains(NAME_PLACEHOLDER), "{}", template);
+            assert_eq!(path.join("refs.bib").is_file(), template_files.iter().any(|(file, _)| *file == "refs.bib"));
+            assert!(std::fs::read_to_string(path.join(".gitignore")).unwrap().contains("*.aux"));
+            let manifest = ProjectManifest::load(&path.join(MANIFEST_FILE)).unwrap();
+            assert_eq!(manifest.targets[0].name, format!("{}_project", template));
+            assert_eq!(manifest.targets[0].root, path.join("main.tex"));
+        }
+        assert!(create(&dir.path().join("other"), "poster", None).is_err());
+        // The directory is not empty anymore.
+        assert!(create(&dir.path().join("article_project"), "article", None).is_err());
+    }
+
+    #[test]
+    fn test_user_template_overrides_builtin() {
+        let dir = tempfile::tempdir().unwrap();
+        let templates = dir.path().join("templates");
+        std::fs::create_dir_all(templates.join("article/figures")).unwrap();
+        std::fs::write(templates.join("article/main.tex"), "\\title{{{name}}}\n").unwrap();
+        std::fs::write(templates.join("article/figures/logo.txt"), "{{name}}").unwrap();
+
+        let path = dir.path().join("my_paper");
+        create(&path, "article", Some(&templates)).unwrap();
+        assert_eq!(std::fs::read_to_string(path.join("main.tex")).unwrap(), "\\title{my\\_paper}\n");
+        assert_eq!(std::fs::read_to_string(path.join("figures/logo.txt")).unwrap(), "my_paper");
+        assert!(!path.join("refs.bib").exists());
+        assert!(path.join(".gitignore").is_file());
+        assert!(path.join(MANIFEST_FILE).is_file());
+
+        // Other templates still come from the binary.
+        create(&dir.path().join("talk"), "beamer", Some(&templates)).unwrap();
+        assert!(dir.path().join("talk/refs.bib").is_file());
+    }
+}
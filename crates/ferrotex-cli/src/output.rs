@@ -0,0 +1,197 @@
+//! Output formats of `ferrotex parse` and `ferrotex watch`, and the summary and exit code
+//! of every command.
+
+use ferrotex_log::export::Finding;
+use ferrotex_log::ir::{EventSeverity, LogEvent};
+use std::io::IsTerminal;
+
+/// How `ferrotex parse` and `ferrotex watch` print their results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// An indented `Kind@start..end` outline of the syntax tree (TeX sources only).
+    Tree,
+    /// A JSON document: the syntax tree of a TeX source, the event array of a log.
+    Json,
+    /// One JSON event per line (logs only).
+    Ndjson,
+    /// A SARIF 2.1 log of the errors and warnings (logs only, not with `watch`).
+    Sarif,
+    /// `file:line: severity: message` lines, colored on terminals (logs only).
+    Human,
+}
+
+/// The least severe events that make `--fail-on` fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum FailOn {
+    Error,
+    Warning,
+    #[value(name = "badbox")]
+    BadBox,
+}
+
+impl FailOn {
+    /// The value of the flag.
+    pub fn name(self) -> &'static str {
+        match self {
+            FailOn::Error => "error",
+            FailOn::Warning => "warning",
+            FailOn::BadBox => "badbox",
+        }
+    }
+
+    pub fn severity(self) -> EventSeverity {
+        match self {
+            FailOn::Error => EventSeverity::Error,
+            FailOn::Warning => EventSeverity::Warning,
+            FailOn::BadBox => EventSeverity::BadBox,
+        }
+    }
+
+    /// Whether `events` contain one at or above the threshold.
+    pub fn is_met_by(self, events: &[LogEvent]) -> bool {
+        events.iter().any(|event| event.payload.severity() >= self.severity())
+    }
+}
+
+/// Whether human output is colored: on terminals, unless `NO_COLOR` is set.
+pub fn use_color() -> bool {
+    std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+/// Renders a finding as `file:line: severity: message [rule]`.
+///
+/// Findings without a file show `<log>`; with `color`, the severity is colored with ANSI
+/// escapes and the location is bold.
+pub fn human(finding: &Finding, color: bool) -> String {
+    let mut location = finding.file.clone().unwrap_or_else(|| "<log>".to_string());
+    if let Some(line) = finding.start_line {
+        location = format!("{}:{}", location, line);
+    }
+    let (label, code) = match finding.severity {
+        EventSeverity::Error => ("error", "1;31"),
+        EventSeverity::Warning => ("warning", "1;33"),
+        _ => ("note", "1;36"),
+    };
+    if color {
+        format!("\x1b[1m{}:\x1b[0m \x1b[{}m{}:\x1b[0m {} [{}]", location, code, label, finding.message, finding.rule)
+    } else {
+        format!("{}: {}: {} [{}]", location, label, finding.message, finding.rule)
+    }
+}
+
+/// Exit code of a command that found errors, or whose check failed.
+pub const EXIT_ERRORS: u8 = 1;
+/// Exit code of a command that found warnings, with `--strict`.
+pub const EXIT_WARNINGS: u8 = 2;
+/// Exit code of a command that could not run: unreadable input, a missing tool, ...
+pub const EXIT_FAILURE: u8 = 11;
+/// Exit code for invalid arguments.
+pub const EXIT_USAGE: u8 = 12;
+
+/// How `--summary` prints the counts of a command after its output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SummaryFormat {
+    /// One JSON object on the last line of standard output.
+    Json,
+}
+
+/// What a command found, which decides its exit code.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub errors: usize,
+    pub warnings: usize,
+    /// Style findings: lint findings and unformatted files.
+    pub lints: usize,
+    /// The command failed its check beyond the counts, e.g. a build step failed or
+    /// `--fail-on` was met.
+    pub failed: bool,
+}
+
+impl Summary {
+    pub fn errors(errors: usize) -> Self {
+        Self { errors, ..Self::default() }
+    }
+
+    pub fn warnings(warnings: usize) -> Self {
+        Self { warnings, ..Self::default() }
+    }
+
+    pub fn lints(lints: usize) -> Self {
+        Self { lints, ..Self::default() }
+    }
+
+    /// The errors and warnings among `findings`; notes are not counted.
+    pub fn of_findings(findings: &[Finding]) -> Self {
+        let count = |severity: EventSeverity| findings.iter().filter(|finding| finding.severity == severity).count();
+        Self { errors: count(EventSeverity::Error), warnings: count(EventSeverity::Warning), ..Self::default() }
+    }
+
+    /// `0`, [`EXIT_ERRORS`] if there are errors or lints or the check failed, or
+    /// [`EXIT_WARNINGS`] if there are warnings and `strict`.
+    pub fn exit_code(self, strict: bool) -> u8 {
+        if self.failed || self.errors > 0 || self.lints > 0 {
+            EXIT_ERRORS
+        } else if strict && self.warnings > 0 {
+            EXIT_WARNINGS
+        } else {
+            0
+        }
+    }
+
+    /// The `--summary json` line of `command`, with the error that stopped it if any.
+    pub fn to_json(self, command: &str, exit_code: u8, error: Option<&str>) -> serde_json::Value {
+        let mut json = serde_json::json!({
+            "command": command,
+            "errors": self.errors,
+            "warnings": self.warnings,
+            "lints": self.lints,
+            "exitCode": exit_code,
+        });
+        if let Some(error) = error {
+            json["error"] = serde_json::Value::from(error);
+        }
+        json
+    }
+}
+
+/// A `2 errors, 1 warning, 3 notes` summary of `findings`.
+pub fn summary(findings: &[Finding]) -> String {
+    let count = |severity: EventSeverity| findings.iter().filter(|finding| finding.severity == severity).count();
+    let notes = findings.iter().filter(|finding| finding.severity < EventSeverity::Warning).count();
+    let plural = |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+    format!(
+        "{}, {}, {}",
+        plural(count(EventSeverity::Error), "error"),
+        plural(count(EventSeverity::Warning), "warning"),
+        plural(notes, "note")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code() {
+        assert_eq!(Summary::default().exit_code(true), 0);
+        assert_eq!(Summary::errors(1).exit_code(false), EXIT_ERRORS);
+        assert_eq!(Summary { errors: 1, warnings: 2, ..Summary::default() }.exit_code(true), EXIT_ERRORS);
+        assert_eq!(Summary::lints(3).exit_code(false), EXIT_ERRORS);
+        assert_eq!(Summary::warnings(2).exit_code(false), 0);
+        assert_eq!(Summary::warnings(2).exit_code(true), EXIT_WARNINGS);
+        assert_eq!(Summary { failed: true, ..Summary::default() }.exit_code(false), EXIT_ERRORS);
+        assert_eq!(Summary { failed: true, warnings: 1, ..Summary::default() }.exit_code(true), EXIT_ERRORS);
+    }
+
+    #[test]
+    fn test_to_json() {
+        let summary = Summary { errors: 1, warnings: 2, lints: 3, failed: false };
+        assert_eq!(
+            summary.to_json("lint", 1, None).to_string(),
+            r#"{"command":"lint","errors":1,"exitCode":1,"lints":3,"warnings":2}"#
+        );
+        let json = Summary::default().to_json("parse", EXIT_FAILURE, Some("No such file"));
+        assert_eq!(json["exitCode"], 11);
+        assert_eq!(json["error"], "No such file");
+    }
+}
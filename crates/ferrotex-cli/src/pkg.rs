@@ -0,0 +1,175 @@
+//! `ferrotex pkg`: finding and installing the packages of the TeX distribution.
+
+use crate::output::Summary;
+use clap::Subcommand;
+use ferrotex_core::package_manager::ctan_db::CTAN_DB;
+use ferrotex_core::package_manager::{InstallState, PackageManager};
+use ferrotex_package::PackageIndex;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// `ferrotex pkg` subcommands.
+#[derive(Subcommand)]
+pub enum PkgCommand {
+    /// List the distribution packages whose name or files contain a query.
+    Search {
+        /// Part of a package or file name, e.g. `tikz` or `.cls`; case-insensitive.
+        query: String,
+        /// Also search the files of the package manager's repository (`tlmgr search --global`).
+        #[arg(long)]
+        remote: bool,
+    },
+    /// Install packages with tlmgr or mpm.
+    Install {
+        /// Package names, or files such as `tikz.sty`, which install the package providing them.
+        #[arg(value_name = "NAME", required = true)]
+        names: Vec<String>,
+    },
+    /// Name the installed packages that define a command or environment.
+    Which {
+        /// A command, with or without its backslash, or an environment name.
+        command: String,
+    },
+}
+
+/// Runs `command`; a search or lookup without result and a failed installation are errors.
+pub fn run(command: &PkgCommand) -> anyhow::Result<Summary> {
+    match command {
+        PkgCommand::Search { query, remote } => search(query, *remote),
+        PkgCommand::Install { names } => install(names),
+        PkgCommand::Which { command } => which(command),
+    }
+}
+
+/// Prints the packages of the package index and the CTAN database matching `query`, one
+/// per line with the matching files, then with `remote` the repository's matching files.
+fn search(query: &str, remote: bool) -> anyhow::Result<Summary> {
+    let lowercase = query.to_lowercase();
+    let matches = |name: &str| name.to_lowercase().contains(&lowercase);
+    let index = crate::package_index();
+
+    // Distribution package -> whether it is installed, and its matching files.
+    let mut found: BTreeMap<String, (bool, BTreeSet<String>)> = BTreeMap::new();
+    let mut add = |package: &str, file: &str, installed: bool| {
+        if matches(package) || matches(file) {
+            let (is_installed, files) = found.entry(package.to_string()).or_default();
+            *is_installed |= installed;
+            if matches(file) {
+                files.insert(file.to_string());
+            }
+        }
+    };
+    for (file, package) in &index.files {
+        add(&package.name, file, true);
+    }
+    for name in index.packages.keys() {
+        let file = format!("{}.sty", name);
+        add(&distribution_package(&index, &file), &file, true);
+    }
+    for (file, package) in CTAN_DB.all_mappings() {
+        add(package, file, false);
+    }
+
+    for (package, (installed, files)) in &found {
+        let files: Vec<&str> = files.iter().map(String::as_str).collect();
+        println!(
+            "{}{}{}",
+            package,
+            if *installed { " [installed]" } else { "" },
+            if files.is_empty() { String::new() } else { format!(": {}", files.join(", ")) }
+        );
+    }
+
+    let mut remote_results = Vec::new();
+    if remote {
+        let manager = PackageManager::new();
+        anyhow::ensure!(manager.is_available(), "No TeX package manager (tlmgr or mpm) found");
+        remote_results = manager.search(query)?;
+        if !remote_results.is_empty() {
+            println!("\nIn the {} repository:", manager.backend_name());
+            for result in &remote_results {
+                println!("  {}", result);
+            }
+        }
+    }
+    if found.is_empty() && remote_results.is_empty() {
+        eprintln!("No package matches `{}`", query);
+        return Ok(Summary::errors(1));
+    }
+    Ok(Summary::default())
+}
+
+/// Installs the packages named by `names`, mapping file names to the distribution package
+/// providing them, and counts the failed installations.
+fn install(names: &[String]) -> anyhow::Result<Summary> {
+    let manager = PackageManager::new();
+    anyhow::ensure!(manager.is_available(), "No TeX package manager (tlmgr or mpm) found");
+    // Scanning the distribution is slow, so files not in the cached index are looked up in
+    // the CTAN database only.
+    let index = PackageIndex::load_from_cache().unwrap_or_default();
+
+    let mut packages: Vec<String> = Vec::new();
+    for name in names {
+        let package = if name.contains('.') { distribution_package(&index, name) } else { name.clone() };
+        if package != *name {
+            eprintln!("{} is provided by {}", name, package);
+        }
+        if !packages.contains(&package) {
+            packages.push(package);
+        }
+    }
+
+    let mut failed = 0;
+    for package in &packages {
+        let status = manager.install(package)?;
+        match status.state {
+            InstallState::Complete => println!("Installed {}", package),
+            _ => {
+                failed += 1;
+                let message = status.message.unwrap_or_default();
+                eprintln!("Failed to install {}: {}", package, message.trim());
+            }
+        }
+    }
+    if failed > 0 {
+        eprintln!("{} of {} packages failed to install", failed, packages.len());
+    }
+    Ok(Summary::errors(failed))
+}
+
+/// Prints the installed `.sty` files defining `command` with `\newcommand` or
+/// `\newenvironment`, with the distribution package providing each.
+fn which(command: &str) -> anyhow::Result<Summary> {
+    let index = crate::package_index();
+    anyhow::ensure!(!index.packages.is_empty(), "No TeX distribution found to index");
+    let name = command.trim_start_matches('\\');
+    let is_command = command.starts_with('\\');
+
+    let mut found: Vec<(String, String)> = Vec::new();
+    for (package, metadata) in &index.packages {
+        if metadata.commands.iter().any(|defined| defined == name) {
+            found.push((package.clone(), format!("command \\{}", name)));
+        }
+        if !is_command && metadata.environments.iter().any(|defined| defined == name) {
+            found.push((package.clone(), format!("environment {}", name)));
+        }
+    }
+    found.sort();
+    if found.is_empty() {
+        eprintln!("No installed package defines `{}`", command);
+        return Ok(Summary::errors(1));
+    }
+    for (package, definition) in found {
+        let file = format!("{}.sty", package);
+        println!("{} ({}, package {})", file, definition, distribution_package(&index, &file));
+    }
+    Ok(Summary::default())
+}
+
+/// The distribution package providing `file`: from the index, the CTAN database, or else
+/// the file's stem.
+fn distribution_package(index: &PackageIndex, file: &str) -> String {
+    match index.package_for_file(file) {
+        Some(package) => package.name.clone(),
+        None => PackageManager::package_for_file(file),
+    }
+}
@@ -0,0 +1,541 @@
+//! `ferrotex watch --tui`: a terminal dashboard that rebuilds a document when its files
+//! change and lists the errors and warnings of the engine while it prints them.
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Color, ContentStyle, PrintStyledContent, StyledContent};
+use crossterm::terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crate::output::Summary;
+use crossterm::{cursor, execute, queue};
+use ferrotex_build::{BuildEvent, CancellationToken, Compiler, Executor, Planner, TransformOutcome};
+use ferrotex_log::export::Finding;
+use ferrotex_log::ir::{EventSeverity, LogEvent};
+use ferrotex_log::LogParser;
+use notify::{RecursiveMode, Watcher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+/// Extensions of the files whose changes trigger a rebuild.
+const SOURCE_EXTENSIONS: &[&str] = &["tex", "ltx", "bib", "sty", "cls", "bst", "png", "jpg", "jpeg", "eps"];
+
+/// How long the sources must stay unchanged before a rebuild, so that an editor saving
+/// several files starts one build.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Lines of source shown before and after the line of the selected problem.
+const CONTEXT_LINES: usize = 3;
+
+/// What the build thread and the file watcher report to the dashboard.
+enum Message {
+    /// A source file changed.
+    Changed,
+    Build(BuildEvent),
+    /// The build ended: the events of the engine logs it wrote, or why it could not start.
+    Finished(Result<Vec<LogEvent>, String>),
+}
+
+enum Status {
+    Building { step: String, started: Instant },
+    Succeeded(Duration),
+    Failed(String),
+    Cancelled,
+}
+
+/// The state shown on screen.
+struct Dashboard {
+    root: PathBuf,
+    engine: String,
+    max_print_line: usize,
+    /// The include tree of the root, relative to its directory.
+    files: Vec<PathBuf>,
+    status: Status,
+    /// Whether the step running is an engine pass, whose output is parsed.
+    engine_step: bool,
+    parser: LogParser,
+    events: Vec<LogEvent>,
+    findings: Vec<Finding>,
+    selected: usize,
+    /// Shown in the footer until the next key.
+    notice: Option<String>,
+}
+
+/// Runs the dashboard for the document `root` until `q` is pressed, and returns the
+/// problems shown last.
+pub fn run(root: &Path, compiler: Compiler, max_print_line: usize) -> anyhow::Result<Summary> {
+    anyhow::ensure!(root.is_file(), "{} does not exist", root.display());
+    let root = std::path::absolute(root)?;
+    let dir = root.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    let (tx, rx) = channel();
+    let watcher_tx = tx.clone();
+    let output_dir = compiler.output_dir.clone();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+        let Ok(event) = result else { return };
+        if event.kind.is_access() {
+            return;
+        }
+        // Files the engine writes to a separate output directory must not start a build.
+        let is_output = |path: &Path| path.starts_with(&output_dir) && !dir.starts_with(&output_dir);
+        if event.paths.iter().any(|path| is_source(path) && !is_output(path)) {
+            let _ = watcher_tx.send(Message::Changed);
+        }
+    })?;
+    watcher.watch(root.parent().unwrap_or(Path::new("/")), RecursiveMode::Recursive)?;
+
+    let mut dashboard = Dashboard {
+        root: root.clone(),
+        engine: compiler.engine.clone(),
+        max_print_line,
+        files: Vec::new(),
+        status: Status::Cancelled,
+        engine_step: false,
+        parser: LogParser::new().with_max_print_line(max_print_line),
+        events: Vec::new(),
+        findings: Vec::new(),
+        selected: 0,
+        notice: None,
+    };
+    dashboard.refresh_files();
+
+    let mut screen = Screen::enter()?;
+    let result = event_loop(&mut screen, &mut dashboard, &compiler, &tx, &rx);
+    drop(screen);
+    result?;
+    let failed = matches!(dashboard.status, Status::Failed(_));
+    Ok(Summary { failed, ..Summary::of_findings(&dashboard.findings) })
+}
+
+fn event_loop(
+    screen: &mut Screen,
+    dashboard: &mut Dashboard,
+    compiler: &Compiler,
+    tx: &Sender<Message>,
+    rx: &Receiver<Message>,
+) -> anyhow::Result<()> {
+    let mut build = Some(start_build(&dashboard.root, compiler, tx));
+    dashboard.status = Status::Building { step: "planning".to_string(), started: Instant::now() };
+    let mut changed_at: Option<Instant> = None;
+    loop {
+        screen.draw(dashboard)?;
+
+        while let Ok(message) = rx.try_recv() {
+            match message {
+                Message::Changed => {
+                    changed_at = Some(Instant::now());
+                    // The running build reads stale sources.
+                    if let Some(cancel) = &build {
+                        cancel.cancel();
+                    }
+                }
+                Message::Build(event) => dashboard.on_build_event(event),
+                Message::Finished(result) => {
+                    build = None;
+                    dashboard.on_finished(result);
+                }
+            }
+        }
+
+        if event::poll(Duration::from_millis(100))? {
+            match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => {
+                    dashboard.notice = None;
+                    match key_action(key) {
+                        Action::Quit => break,
+                        Action::Select(delta) => dashboard.select(delta),
+                        Action::Rebuild => {
+                            changed_at = Some(Instant::now().checked_sub(DEBOUNCE).unwrap_or_else(Instant::now));
+                            if let Some(cancel) = &build {
+                                cancel.cancel();
+                            }
+                        }
+                        Action::Open => {
+                            if let Err(error) = screen.suspend(|| dashboard.open_selected()) {
+                                dashboard.notice = Some(error.to_string());
+                            }
+                        }
+                        Action::None => {}
+                    }
+                }
+                Event::Resize(..) => screen.clear()?,
+                _ => {}
+            }
+        }
+
+        if build.is_none() && changed_at.is_some_and(|at| at.elapsed() >= DEBOUNCE) {
+            changed_at = None;
+            dashboard.refresh_files();
+            dashboard.status = Status::Building { step: "planning".to_string(), started: Instant::now() };
+            build = Some(start_build(&dashboard.root, compiler, tx));
+        }
+    }
+    if let Some(cancel) = build {
+        cancel.cancel();
+    }
+    Ok(())
+}
+
+/// Plans and runs a build of `root` on another thread, reporting its progress to `tx`.
+fn start_build(root: &Path, compiler: &Compiler, tx: &Sender<Message>) -> CancellationToken {
+    let cancel = CancellationToken::new();
+    let (root, compiler, tx, token) = (root.to_path_buf(), compiler.clone(), tx.clone(), cancel.clone());
+    std::thread::spawn(move || {
+        let graph = match Planner::new(compiler).plan(&root) {
+            Ok(graph) => graph,
+            Err(error) => {
+                let _ = tx.send(Message::Finished(Err(error.to_string())));
+                return;
+            }
+        };
+        let (events, received) = channel();
+        let forward = tx.clone();
+        let forwarder = std::thread::spawn(move || {
+            for event in received {
+                if forward.send(Message::Build(event)).is_err() {
+                    break;
+                }
+            }
+        });
+        let _ = Executor::new(&graph).with_cancellation(token).with_events(events).run();
+        let _ = forwarder.join();
+        let _ = tx.send(Message::Finished(Ok(crate::engine_log_events(&graph))));
+    });
+    cancel
+}
+
+fn is_source(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| SOURCE_EXTENSIONS.iter().any(|source| extension == *source))
+}
+
+enum Action {
+    None,
+    Quit,
+    Select(isize),
+    Rebuild,
+    Open,
+}
+
+fn key_action(key: KeyEvent) -> Action {
+    match key.code {
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Action::Quit,
+        KeyCode::Char('q') | KeyCode::Esc => Action::Quit,
+        KeyCode::Down | KeyCode::Char('j') | KeyCode::Char('n') | KeyCode::Tab => Action::Select(1),
+        KeyCode::Up | KeyCode::Char('k') | KeyCode::Char('p') | KeyCode::BackTab => Action::Select(-1),
+        KeyCode::Home | KeyCode::Char('g') => Action::Select(isize::MIN),
+        KeyCode::End | KeyCode::Char('G') => Action::Select(isize::MAX),
+        KeyCode::Char('r') => Action::Rebuild,
+        KeyCode::Enter | KeyCode::Char('o') => Action::Open,
+        _ => Action::None,
+    }
+}
+
+impl Dashboard {
+    fn dir(&self) -> &Path {
+        self.root.parent().unwrap_or(Path::new(""))
+    }
+
+    fn refresh_files(&mut self) {
+        let Ok(root) = tower_lsp::lsp_types::Url::from_file_path(&self.root) else { return };
+        let workspace = ferrotexd::workspace::Workspace::new();
+        let dir = self.dir().to_path_buf();
+        self.files = workspace
+            .load_tree(&root)
+            .iter()
+            .filter_map(|uri| uri.to_file_path().ok())
+            .map(|path| path.strip_prefix(&dir).map(Path::to_path_buf).unwrap_or(path))
+            .collect();
+    }
+
+    fn on_build_event(&mut self, event: BuildEvent) {
+        match event {
+            BuildEvent::TransformStarted { description, .. } => {
+                self.engine_step = description.contains(&self.engine);
+                if self.engine_step {
+                    // Every pass prints the whole document again.
+                    self.parser = LogParser::new().with_max_print_line(self.max_print_line);
+                    self.events.clear();
+                    self.set_findings(Vec::new());
+                }
+                let started = match self.status {
+                    Status::Building { started, .. } => started,
+                    _ => Instant::now(),
+                };
+                self.status = Status::Building { step: description, started };
+            }
+            BuildEvent::LogChunk { text, .. } if self.engine_step => {
+                let events = self.parser.update(&text);
+                if !events.is_empty() {
+                    self.events.extend(events);
+                    self.set_findings(ferrotex_log::export::findings(&self.events));
+                }
+            }
+            BuildEvent::TransformFinished { outcome: TransformOutcome::Failed { error }, .. } => {
+                self.status = Status::Failed(error.to_string());
+            }
+            BuildEvent::BuildFinished { success, cancelled } => {
+                self.status = match (&self.status, success, cancelled) {
+                    (_, _, true) => Status::Cancelled,
+                    (Status::Building { started, .. }, true, _) => Status::Succeeded(started.elapsed()),
+                    (Status::Failed(_), _, _) => return,
+                    _ => Status::Failed("build failed".to_string()),
+                };
+            }
+            BuildEvent::LogChunk { .. } | BuildEvent::TransformFinished { .. } => {}
+        }
+    }
+
+    fn on_finished(&mut self, result: Result<Vec<LogEvent>, String>) {
+        match result {
+            // The log files are complete, unlike what the engine printed when cancelled.
+            Ok(events) if !events.is_empty() && !matches!(self.status, Status::Cancelled) => {
+                self.set_findings(ferrotex_log::export::findings(&events));
+            }
+            Ok(_) => {}
+            Err(error) => self.status = Status::Failed(error),
+        }
+        if matches!(self.status, Status::Building { .. }) {
+            self.status = Status::Cancelled;
+        }
+    }
+
+    /// Replaces the findings, keeping the selection on the same problem if it is still there.
+    fn set_findings(&mut self, findings: Vec<Finding>) {
+        let selected = self.findings.get(self.selected).cloned();
+        self.findings = findings;
+        self.selected = selected
+            .and_then(|selected| self.findings.iter().position(|finding| *finding == selected))
+            .unwrap_or(self.selected)
+            .min(self.findings.len().saturating_sub(1));
+    }
+
+    fn select(&mut self, delta: isize) {
+        let last = self.findings.len().saturating_sub(1) as isize;
+        self.selected = (self.selected as isize).saturating_add(delta).clamp(0, last) as usize;
+    }
+
+    /// The file of `finding`, if it names one.
+    fn path_of(&self, finding: &Finding) -> Option<PathBuf> {
+        finding.file.as_ref().map(|file| self.dir().join(file))
+    }
+
+    /// Opens the selected problem in `$VISUAL` or `$EDITOR`, as `editor +line file`.
+    fn open_selected(&self) -> anyhow::Result<()> {
+        let finding = self.findings.get(self.selected).ok_or_else(|| anyhow::anyhow!("No problem selected"))?;
+        let path = self.path_of(finding).ok_or_else(|| anyhow::anyhow!("The problem has no file"))?;
+        let editor = std::env::var("VISUAL")
+            .or_else(|_| std::env::var("EDITOR"))
+            .map_err(|_| anyhow::anyhow!("Set $EDITOR to open files"))?;
+        let mut words = editor.split_whitespace();
+        let program = words.next().ok_or_else(|| anyhow::anyhow!("$EDITOR is empty"))?;
+        let mut command = std::process::Command::new(program);
+        command.args(words);
+        if let Some(line) = finding.start_line {
+            command.arg(format!("+{}", line));
+        }
+        let status = command.arg(&path).status()?;
+        anyhow::ensure!(status.success(), "{} exited with {}", program, status);
+        Ok(())
+    }
+
+    /// The screen contents: one list of styled spans per row, each row `width` wide.
+    fn render(&self, width: usize, height: usize) -> Vec<Vec<Span>> {
+        let mut rows = Vec::with_capacity(height);
+        rows.push(vec![Span::new(self.header(), width, Style::Header)]);
+        let body = height.saturating_sub(2);
+        // Narrow terminals only show the problems.
+        let files_width = if width >= 60 { (width / 4).clamp(16, 32) } else { 0 };
+        let right = width.saturating_sub(files_width + usize::from(files_width > 0));
+        let list_height = (body / 2).max(body.min(3));
+        let left_rows = self.file_rows(body);
+        let mut right_rows = self.problem_rows(list_height);
+        right_rows.extend(self.preview_rows(body.saturating_sub(list_height)));
+        for y in 0..body {
+            let mut row = Vec::new();
+            if files_width > 0 {
+                let (text, style) = left_rows.get(y).cloned().unwrap_or_default();
+                row.push(Span::new(text, files_width, style));
+                row.push(Span::new("│".to_string(), 1, Style::Border));
+            }
+            let (text, style) = right_rows.get(y).cloned().unwrap_or_default();
+            row.push(Span::new(text, right, style));
+            rows.push(row);
+        }
+        let footer = self.notice.clone().unwrap_or_else(|| {
+            " q quit  ↑/↓ select problem  Enter open in $EDITOR  r rebuild".to_string()
+        });
+        rows.push(vec![Span::new(footer, width, Style::Footer)]);
+        rows.truncate(height);
+        rows
+    }
+
+    fn header(&self) -> String {
+        let name = self.root.file_name().unwrap_or_default().to_string_lossy();
+        let status = match &self.status {
+            Status::Building { step, started } => format!("building: {} ({}s)", step, started.elapsed().as_secs()),
+            Status::Succeeded(duration) => format!("built in {:.1}s", duration.as_secs_f64()),
+            Status::Failed(error) => format!("failed: {}", error),
+            Status::Cancelled => "cancelled".to_string(),
+        };
+        format!(" ferrotex watch  {}  {}  |  {}", name, status, crate::output::summary(&self.findings))
+    }
+
+    fn file_rows(&self, height: usize) -> Vec<(String, Style)> {
+        let mut rows = vec![(" Files".to_string(), Style::Title)];
+        for file in &self.files {
+            let path = self.dir().join(file);
+            let count = |severity: EventSeverity| {
+                self.findings
+                    .iter()
+                    .filter(|finding| finding.severity == severity && self.path_of(finding).as_ref() == Some(&path))
+                    .count()
+            };
+            let (errors, warnings) = (count(EventSeverity::Error), count(EventSeverity::Warning));
+            let mut text = format!(" {}", file.display());
+            if errors > 0 {
+                text.push_str(&format!(" {}E", errors));
+            }
+            if warnings > 0 {
+                text.push_str(&format!(" {}W", warnings));
+            }
+            let style = if errors > 0 {
+                Style::Error
+            } else if warnings > 0 {
+                Style::Warning
+            } else {
+                Style::Plain
+            };
+            rows.push((text, style));
+        }
+        rows.truncate(height);
+        rows
+    }
+
+    fn problem_rows(&self, height: usize) -> Vec<(String, Style)> {
+        let mut rows = vec![(" Problems".to_string(), Style::Title)];
+        if self.findings.is_empty() {
+            rows.push((" none".to_string(), Style::Plain));
+        }
+        let visible = height.saturating_sub(1).max(1);
+        // Keep the selection in view.
+        let first = self.selected.saturating_sub(visible - 1);
+        for (index, finding) in self.findings.iter().enumerate().skip(first).take(visible) {
+            let marker = if index == self.selected { ">" } else { " " };
+            let style = match (index == self.selected, finding.severity) {
+                (true, _) => Style::Selected,
+                (false, EventSeverity::Error) => Style::Error,
+                (false, EventSeverity::Warning) => Style::Warning,
+                _ => Style::Plain,
+            };
+            rows.push((format!("{}{}", marker, crate::output::human(finding, false)), style));
+        }
+        rows.resize(height, Default::default());
+        rows
+    }
+
+    /// The lines of source around the selected problem.
+    fn preview_rows(&self, height: usize) -> Vec<(String, Style)> {
+        let Some(finding) = self.findings.get(self.selected) else { return Vec::new() };
+        let (Some(path), Some(line)) = (self.path_of(finding), finding.start_line) else { return Vec::new() };
+        let Ok(text) = std::fs::read_to_string(&path) else { return Vec::new() };
+        let line = line as usize;
+        let mut rows = vec![(format!("─ {}:{} ", finding.file.as_deref().unwrap_or_default(), line), Style::Border)];
+        let first = line.saturating_sub(CONTEXT_LINES).max(1);
+        for (number, content) in text.lines().enumerate().map(|(i, content)| (i + 1, content)).skip(first - 1) {
+            if number > line + CONTEXT_LINES || rows.len() >= height {
+                break;
+            }
+            let style = if number == line { Style::Selected } else { Style::Plain };
+            rows.push((format!("{:>5} │ {}", number, content.replace('\t', "    ")), style));
+        }
+        rows
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+enum Style {
+    #[default]
+    Plain,
+    Header,
+    Footer,
+    Title,
+    Border,
+    Error,
+    Warning,
+    Selected,
+}
+
+impl Style {
+    fn content_style(self) -> ContentStyle {
+        let style = ContentStyle::new();
+        match self {
+            Style::Plain => style,
+            Style::Header | Style::Footer => ContentStyle { attributes: Attribute::Reverse.into(), ..style },
+            Style::Title => ContentStyle { attributes: Attribute::Bold.into(), ..style },
+            Style::Border => ContentStyle { foreground_color: Some(Color::DarkGrey), ..style },
+            Style::Error => ContentStyle { foreground_color: Some(Color::Red), ..style },
+            Style::Warning => ContentStyle { foreground_color: Some(Color::Yellow), ..style },
+            Style::Selected => ContentStyle { attributes: Attribute::Reverse.into(), ..style },
+        }
+    }
+}
+
+/// Text padded or cut to a width, in one style.
+struct Span {
+    text: String,
+    style: Style,
+}
+
+impl Span {
+    fn new(text: String, width: usize, style: Style) -> Self {
+        let mut text: String = text.chars().filter(|c| !c.is_control()).take(width).collect();
+        let len = text.chars().count();
+        text.extend(std::iter::repeat_n(' ', width - len));
+        Self { text, style }
+    }
+}
+
+/// The terminal in raw mode on the alternate screen, restored when dropped.
+struct Screen {
+    out: std::io::Stdout,
+}
+
+impl Screen {
+    fn enter() -> std::io::Result<Self> {
+        let mut out = std::io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(out, EnterAlternateScreen, cursor::Hide, terminal::Clear(ClearType::All))?;
+        Ok(Self { out })
+    }
+
+    fn draw(&mut self, dashboard: &Dashboard) -> std::io::Result<()> {
+        let (width, height) = terminal::size()?;
+        for (y, row) in dashboard.render(width as usize, height as usize).into_iter().enumerate() {
+            queue!(self.out, cursor::MoveTo(0, y as u16))?;
+            for span in row {
+                queue!(self.out, PrintStyledContent(StyledContent::new(span.style.content_style(), span.text)))?;
+            }
+        }
+        self.out.flush()
+    }
+
+    fn clear(&mut self) -> std::io::Result<()> {
+        execute!(self.out, terminal::Clear(ClearType::All))
+    }
+
+    /// Gives the terminal back to run `f`, e.g. an editor.
+    fn suspend<T>(&mut self, f: impl FnOnce() -> T) -> std::io::Result<T> {
+        execute!(self.out, LeaveAlternateScreen, cursor::Show)?;
+        terminal::disable_raw_mode()?;
+        let result = f();
+        terminal::enable_raw_mode()?;
+        execute!(self.out, EnterAlternateScreen, cursor::Hide, terminal::Clear(ClearType::All))?;
+        Ok(result)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(self.out, cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
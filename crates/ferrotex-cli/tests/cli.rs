@@ -0,0 +1,50 @@
+//! Exit codes and `--summary json` output of the CLI, as documented in `docs/spec/cli.md`.
+
+use assert_cmd::Command;
+
+fn ferrotex() -> Command {
+    Command::cargo_bin("ferrotex-cli").unwrap()
+}
+
+/// The JSON object on the last line of standard output.
+fn summary_line(stdout: &[u8]) -> serde_json::Value {
+    let stdout = String::from_utf8_lossy(stdout);
+    let last = stdout.lines().last().expect("no output");
+    serde_json::from_str(last).unwrap_or_else(|e| panic!("last line is not JSON ({}): {}", e, last))
+}
+
+#[test]
+fn test_invalid_arguments_exit_12() {
+    ferrotex().args(["parse", "--no-such-flag"]).assert().code(12);
+    ferrotex().arg("no-such-command").assert().code(12);
+}
+
+#[test]
+fn test_unreadable_input_exits_11_with_summary() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("missing.log");
+    let output = ferrotex().arg("parse").arg(&missing).args(["--summary", "json"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(11));
+    let summary = summary_line(&output.stdout);
+    assert_eq!(summary["command"], "parse");
+    assert_eq!(summary["exitCode"], 11);
+    assert!(summary["error"].is_string());
+}
+
+#[test]
+fn test_summary_is_the_last_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("main.log");
+    std::fs::write(&log, "LaTeX Warning: Reference `fig' on page 1 undefined on input line 3.\n").unwrap();
+
+    let output = ferrotex().arg("--summary").arg("json").arg("parse").arg(&log).args(["--format", "human"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let summary = summary_line(&output.stdout);
+    assert_eq!(summary["warnings"], 1);
+    assert_eq!(summary["exitCode"], 0);
+    assert!(String::from_utf8_lossy(&output.stdout).lines().count() > 1);
+
+    let output = ferrotex().arg("parse").arg(&log).args(["--format", "human", "--strict", "--summary", "json"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(summary_line(&output.stdout)["exitCode"], 2);
+}
@@ -447,7 +447,23 @@ impl PackageManager {
     pub fn is_available(&self) -> bool {
         self.backend.name() != "none"
     }
+
+    /// Returns the name of the active backend (`tlmgr`, `miktex` or `none`).
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.name()
+    }
     
+    /// Returns the distribution package providing `filename` (e.g. `pgf` for `tikz.sty`),
+    /// guessing the file's stem for files not in the CTAN database.
+    pub fn package_for_file(filename: &str) -> String {
+        match ctan_db::CTAN_DB.lookup(filename) {
+            Some(package) => package.to_string(),
+            None => Path::new(filename)
+                .file_stem()
+                .map_or_else(|| filename.to_string(), |stem| stem.to_string_lossy().into_owned()),
+        }
+    }
+
     /// Returns a link to the package documentation on CTAN, if available.
     pub fn get_ctan_link(filename: &str) -> Option<String> {
         ctan_db::CTAN_DB.lookup(filename).map(|pkg| format!("https://ctan.org/pkg/{}", pkg))
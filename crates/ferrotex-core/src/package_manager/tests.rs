@@ -54,6 +54,12 @@ fn test_ctan_lookup_geometry() {
     assert_eq!(link2, Some("https://ctan.org/pkg/geometry".to_string()));
 }
 
+#[test]
+fn test_package_for_file() {
+    assert_eq!(PackageManager::package_for_file("tikz.sty"), "pgf");
+    assert_eq!(PackageManager::package_for_file("acmart.cls"), "acmart");
+}
+
 #[test]
 fn test_ctan_lookup_nonexistent() {
     let link = CTAN_DB.lookup("nonexistent.sty");
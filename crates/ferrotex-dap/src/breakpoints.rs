@@ -0,0 +1,258 @@
+//! Breakpoints set by the client, stored per source file and handed to the engine shim.
+
+use anyhow::Result;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Prefix of the empty files an instrumented line `\input`s, e.g. `ferrotex-breakpoint-3.tex`
+/// for the breakpoint with id 3.
+const MARKER_PREFIX: &str = "ferrotex-breakpoint-";
+
+/// Where an engine can stop for a breakpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    /// When the engine opens the file; breakpoints are moved to its first line.
+    File,
+    /// Before the engine reads the line; breakpoints on blank lines move to the next line
+    /// with text.
+    Line,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    /// Kept while the breakpoint stays on the same line, so files instrumented before the
+    /// client re-sent its breakpoints still stop at it.
+    pub id: i64,
+    /// 1-based line the engine stops at.
+    pub line: usize,
+    pub verified: bool,
+    /// Why the breakpoint is not verified, or where it stops if not at its line.
+    pub message: Option<String>,
+}
+
+/// The breakpoints of every source, replaced source by source as `setBreakpoints` requests arrive.
+#[derive(Debug, Clone, Default)]
+pub struct Breakpoints {
+    sources: HashMap<PathBuf, Vec<Breakpoint>>,
+    next_id: i64,
+}
+
+impl Breakpoints {
+    /// Replaces the breakpoints of the request's source and returns the `setBreakpoints`
+    /// response body, with one breakpoint per requested one, in order.
+    pub fn set(&mut self, args: &Value, granularity: Granularity) -> Result<Value> {
+        let path = args["source"]["path"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing 'source.path' in setBreakpoints args"))?;
+        let path = PathBuf::from(path);
+        // `lines` is the deprecated form of `breakpoints`.
+        let requested: Vec<usize> = match args["breakpoints"].as_array() {
+            Some(breakpoints) => breakpoints.iter().map(|b| b["line"].as_u64().unwrap_or(0) as usize).collect(),
+            None => args["lines"]
+                .as_array()
+                .map(|lines| lines.iter().map(|line| line.as_u64().unwrap_or(0) as usize).collect())
+                .unwrap_or_default(),
+        };
+
+        let text = std::fs::read(&path).ok();
+        let previous = self.sources.remove(&path).unwrap_or_default();
+        let mut breakpoints: Vec<Breakpoint> = Vec::new();
+        for line in requested {
+            let (line, verified, message) = match (&text, granularity) {
+                (None, _) => (line, false, Some(format!("{} does not exist", path.display()))),
+                (Some(_), Granularity::File) => (1, true, Some("Stops when the engine opens this file".to_string())),
+                (Some(text), Granularity::Line) => match next_line_with_text(text, line) {
+                    Some(line) => (line, true, None),
+                    None => (line, false, Some(format!("No line with text from line {}", line))),
+                },
+            };
+            let known = previous.iter().chain(&breakpoints).find(|b| verified && b.verified && b.line == line);
+            let id = match known {
+                Some(known) => known.id,
+                None => {
+                    self.next_id += 1;
+                    self.next_id
+                }
+            };
+            breakpoints.push(Breakpoint { id, line, verified, message });
+        }
+
+        let body: Vec<Value> = breakpoints
+            .iter()
+            .map(|b| {
+                let mut breakpoint = json!({
+                    "id": b.id,
+                    "verified": b.verified,
+                    "line": b.line,
+                    "source": { "path": path },
+                });
+                if let Some(message) = &b.message {
+                    breakpoint["message"] = json!(message);
+                }
+                breakpoint
+            })
+            .collect();
+        if !breakpoints.is_empty() {
+            self.sources.insert(path, breakpoints);
+        }
+        Ok(json!({ "breakpoints": body }))
+    }
+
+    /// The sources with at least one verified breakpoint.
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.sources
+            .iter()
+            .filter(|(_, breakpoints)| breakpoints.iter().any(|b| b.verified))
+            .map(|(path, _)| path.as_path())
+    }
+
+    /// The breakpoints of the file the engine opens as `name`, which is absolute or relative
+    /// to the document's directory.
+    pub fn for_file(&self, name: &str) -> Option<(&Path, &[Breakpoint])> {
+        let name = Path::new(name.trim_start_matches("./"));
+        self.sources
+            .iter()
+            .find(|(path, _)| path.ends_with(name))
+            .map(|(path, breakpoints)| (path.as_path(), breakpoints.as_slice()))
+    }
+
+    /// The source and breakpoint the marker file `name` stands for, if it still exists.
+    pub fn hit(&self, name: &str) -> Option<(&Path, &Breakpoint)> {
+        let id = marker_id(name)?;
+        self.sources.iter().find_map(|(path, breakpoints)| {
+            breakpoints.iter().find(|b| b.verified && b.id == id).map(|b| (path.as_path(), b))
+        })
+    }
+
+    /// The text of the file the engine opens as `name` with an `\input` of an empty marker
+    /// file at the start of every line with a breakpoint, or `None` if it has none.
+    ///
+    /// The engine stops when it opens a marker (see [`Breakpoints::hit`]). Line numbers are
+    /// unchanged, but a line continuing the arguments of a macro on the previous line may read
+    /// differently.
+    pub fn instrument(&self, name: &str, text: &[u8]) -> Option<Vec<u8>> {
+        let (_, breakpoints) = self.for_file(name)?;
+        let mut markers: HashMap<usize, i64> = HashMap::new();
+        for b in breakpoints.iter().filter(|b| b.verified) {
+            markers.insert(b.line, b.id);
+        }
+        if markers.is_empty() {
+            return None;
+        }
+        let mut instrumented = Vec::with_capacity(text.len() + 32 * markers.len());
+        for (i, line) in text.split_inclusive(|&b| b == b'\n').enumerate() {
+            if let Some(id) = markers.get(&(i + 1)) {
+                // The space ends the file name, and is consumed with it.
+                instrumented.extend_from_slice(format!("\\input {}{}.tex ", MARKER_PREFIX, id).as_bytes());
+            }
+            instrumented.extend_from_slice(line);
+        }
+        Some(instrumented)
+    }
+}
+
+/// The breakpoint id of the marker file `name`, if it is one.
+pub fn marker_id(name: &str) -> Option<i64> {
+    name.strip_prefix(MARKER_PREFIX)?.strip_suffix(".tex")?.parse().ok()
+}
+
+/// The first line from `line` on that is not blank; a marker on a blank line would turn the
+/// paragraph break into a space.
+fn next_line_with_text(text: &[u8], line: usize) -> Option<usize> {
+    if line == 0 {
+        return None;
+    }
+    text.split(|&b| b == b'\n')
+        .enumerate()
+        .skip(line - 1)
+        .find(|(_, text)| !text.iter().all(u8::is_ascii_whitespace))
+        .map(|(i, _)| i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(dir: &Path) -> PathBuf {
+        let path = dir.join("chapter.tex");
+        std::fs::write(&path, "\\section{One}\n\nText.\n\\input{two}\n").unwrap();
+        path
+    }
+
+    fn request(path: &Path, lines: &[usize]) -> Value {
+        let breakpoints: Vec<Value> = lines.iter().map(|line| json!({ "line": line })).collect();
+        json!({ "source": { "path": path }, "breakpoints": breakpoints })
+    }
+
+    #[test]
+    fn test_set_verifies_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = source(dir.path());
+        let mut table = Breakpoints::default();
+
+        let body = table.set(&request(&path, &[1, 2, 9]), Granularity::Line).unwrap();
+        let breakpoints = body["breakpoints"].as_array().unwrap();
+        assert_eq!(breakpoints.len(), 3);
+        assert_eq!(breakpoints[0]["line"], 1);
+        assert_eq!(breakpoints[0]["verified"], true);
+        // Moved off the blank line.
+        assert_eq!(breakpoints[1]["line"], 3);
+        assert_eq!(breakpoints[1]["verified"], true);
+        assert_eq!(breakpoints[2]["verified"], false);
+        assert!(breakpoints[2]["message"].as_str().unwrap().contains("line 9"));
+
+        let missing = dir.path().join("missing.tex");
+        let body = table.set(&request(&missing, &[1]), Granularity::Line).unwrap();
+        assert_eq!(body["breakpoints"][0]["verified"], false);
+        assert!(table.set(&json!({ "breakpoints": [] }), Granularity::Line).is_err());
+    }
+
+    #[test]
+    fn test_set_file_granularity() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = source(dir.path());
+        let mut table = Breakpoints::default();
+
+        let body = table.set(&json!({ "source": { "path": path }, "lines": [3] }), Granularity::File).unwrap();
+        assert_eq!(body["breakpoints"][0]["line"], 1);
+        assert_eq!(body["breakpoints"][0]["verified"], true);
+        assert_eq!(table.files().collect::<Vec<_>>(), vec![path.as_path()]);
+    }
+
+    #[test]
+    fn test_ids_are_kept_per_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = source(dir.path());
+        let mut table = Breakpoints::default();
+
+        let first = table.set(&request(&path, &[1, 3]), Granularity::Line).unwrap();
+        let second = table.set(&request(&path, &[3, 4]), Granularity::Line).unwrap();
+        assert_eq!(second["breakpoints"][0]["id"], first["breakpoints"][1]["id"]);
+        assert_ne!(second["breakpoints"][1]["id"], first["breakpoints"][0]["id"]);
+
+        table.set(&request(&path, &[]), Granularity::Line).unwrap();
+        assert!(table.for_file("chapter.tex").is_none());
+    }
+
+    #[test]
+    fn test_instrument_and_hit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = source(dir.path());
+        let mut table = Breakpoints::default();
+        let body = table.set(&request(&path, &[3]), Granularity::Line).unwrap();
+        let id = body["breakpoints"][0]["id"].as_i64().unwrap();
+
+        let text = std::fs::read(&path).unwrap();
+        let instrumented = String::from_utf8(table.instrument("./chapter.tex", &text).unwrap()).unwrap();
+        let marker = format!("ferrotex-breakpoint-{}.tex", id);
+        assert_eq!(instrumented, format!("\\section{{One}}\n\n\\input {} Text.\n\\input{{two}}\n", marker));
+        assert!(table.instrument("other.tex", &text).is_none());
+
+        let (source, breakpoint) = table.hit(&marker).unwrap();
+        assert_eq!(source, path.as_path());
+        assert_eq!(breakpoint.line, 3);
+        assert!(table.hit("ferrotex-breakpoint-999.tex").is_none());
+        assert_eq!(marker_id("chapter.tex"), None);
+    }
+}
@@ -1,8 +1,12 @@
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
+use std::path::{Path, PathBuf};
 
+pub mod breakpoints;
 pub mod shim;
 
+/// The thread `stopped` events name; TeX engines run a single one.
+pub const THREAD_ID: i64 = 1;
 
 /// Represents a raw DAP message (Request, Response, or Event).
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,10 +61,153 @@ pub trait DebugAdapter {
     /// Called when the client requests 'variables'.
     fn variables(&mut self, args: serde_json::Value) -> Result<serde_json::Value>;
 
+    /// Called when the client sends 'setBreakpoints' with every breakpoint of one source.
+    /// Should return them in order, verified where the engine can stop.
+    fn set_breakpoints(&mut self, args: serde_json::Value) -> Result<serde_json::Value> {
+        let breakpoints: Vec<serde_json::Value> = args["breakpoints"]
+            .as_array()
+            .map(|breakpoints| {
+                breakpoints
+                    .iter()
+                    .map(|b| serde_json::json!({ "verified": false, "line": b["line"], "message": "This engine cannot stop at breakpoints" }))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(serde_json::json!({ "breakpoints": breakpoints }))
+    }
+
+    /// Called when the client requests 'threads'.
+    fn threads(&mut self) -> Result<serde_json::Value> {
+        Ok(serde_json::json!({ "threads": [ { "id": THREAD_ID, "name": "TeX" } ] }))
+    }
+
+    /// Called when the client requests 'stackTrace' for the stopped thread.
+    fn stack_trace(&mut self, _args: serde_json::Value) -> Result<serde_json::Value> {
+        Ok(serde_json::json!({ "stackFrames": [], "totalFrames": 0 }))
+    }
+
+    /// Called when the client requests 'source' for a file it cannot open itself.
+    fn source(&mut self, args: serde_json::Value) -> Result<serde_json::Value> {
+        source_content(&args)
+    }
+
+    /// Called when the client requests 'evaluate', from the debug console, a watch or a hover.
+    fn evaluate(&mut self, _args: serde_json::Value) -> Result<serde_json::Value> {
+        anyhow::bail!("This engine cannot evaluate expressions")
+    }
+
+    /// Called when the client requests 'setVariable' to change a variable of a scope.
+    fn set_variable(&mut self, _args: serde_json::Value) -> Result<serde_json::Value> {
+        anyhow::bail!("This engine cannot change variables")
+    }
+
     /// Called to disconnect/terminate the session.
     fn disconnect(&mut self) -> Result<()>;
 }
 
+/// The 'stackTrace' response body for the engine's input stack, given outermost first, as
+/// frames innermost first and paged by `startFrame` and `levels`.
+///
+/// Frame ids count from the outermost file, so they stay the same while the engine reads
+/// nested files. Names the engine opened are resolved against `root`, the document's
+/// directory; files not found there, such as packages from a bundle, have no path.
+pub fn stack_trace_body(frames: &[shim::Frame], root: Option<&Path>, args: &serde_json::Value) -> serde_json::Value {
+    let start = args["startFrame"].as_u64().unwrap_or(0) as usize;
+    let levels = match args["levels"].as_u64() {
+        Some(0) | None => usize::MAX,
+        Some(levels) => levels as usize,
+    };
+    let stack_frames: Vec<serde_json::Value> = frames
+        .iter()
+        .enumerate()
+        .rev()
+        .skip(start)
+        .take(levels)
+        .map(|(i, frame)| {
+            let path = match root {
+                Some(root) => root.join(&frame.name),
+                None => PathBuf::from(&frame.name),
+            };
+            let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_else(|| frame.name.clone());
+            let mut source = serde_json::json!({ "name": name });
+            if path.is_file() {
+                source["path"] = serde_json::json!(path);
+            } else {
+                source["presentationHint"] = serde_json::json!("deemphasize");
+            }
+            serde_json::json!({
+                "id": i + 1,
+                "name": name,
+                "source": source,
+                // 0 tells the client the position is unknown.
+                "line": frame.line.unwrap_or(0),
+                "column": if frame.line.is_some() { 1 } else { 0 },
+            })
+        })
+        .collect();
+    serde_json::json!({ "stackFrames": stack_frames, "totalFrames": frames.len() })
+}
+
+/// The 'evaluate' response body for a register or macro, such as `\count0` or `\foo`, from
+/// the values the engine's assignment tracing reported so far.
+///
+/// Hovers may give a name without its backslash, and `\count 0` names `\count0`. A name not
+/// assigned since the engine started is an error, which clients show as no hover.
+pub fn evaluate_shadow(vars: &std::collections::HashMap<String, String>, args: &serde_json::Value) -> Result<serde_json::Value> {
+    let expression = args["expression"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("Missing 'expression' in evaluate args"))?;
+    let name: String = expression.split_whitespace().collect();
+    anyhow::ensure!(!name.is_empty(), "Nothing to evaluate");
+    let name = if name.starts_with('\\') { name } else { format!("\\{}", name) };
+    let value = vars
+        .get(&name)
+        .ok_or_else(|| anyhow::anyhow!("{} has not been assigned since the engine started", name))?;
+    let kind = if value.contains("macro:") { "macro" } else { "register" };
+    Ok(serde_json::json!({ "result": value, "type": kind, "variablesReference": 0 }))
+}
+
+/// The TeX assignment a 'setVariable' request on the Registers scope (`variablesReference` 1)
+/// asks for, e.g. `\count0=42\relax `, and the response body.
+///
+/// Only registers listed in the scope can be set, to a number, dimension or glue such as
+/// `1.5em plus 2pt`, so no other TeX reaches the engine; the `\relax` ends the value. The assignment is local to the
+/// group the engine is in when it reads it.
+pub fn register_assignment(
+    vars: &std::collections::HashMap<String, String>,
+    args: &serde_json::Value,
+) -> Result<(String, serde_json::Value)> {
+    anyhow::ensure!(args["variablesReference"].as_i64() == Some(1), "Only the Registers scope can be changed");
+    let name = args["name"].as_str().ok_or_else(|| anyhow::anyhow!("Missing 'name' in setVariable args"))?;
+    let value = args["value"].as_str().ok_or_else(|| anyhow::anyhow!("Missing 'value' in setVariable args"))?.trim();
+    let register = name
+        .strip_prefix('\\')
+        .map(|rest| rest.trim_end_matches(|c: char| c.is_ascii_digit()))
+        .is_some_and(|letters| !letters.is_empty() && letters.chars().all(|c| c.is_ascii_alphabetic() || c == '@'));
+    anyhow::ensure!(register, "{} is not a register", name);
+    // Primitives such as `\input` or `\def` look like registers by name; only assignments
+    // the engine reported are known to be registers.
+    let current = vars.get(name).ok_or_else(|| anyhow::anyhow!("{} is not in the Registers scope", name))?;
+    anyhow::ensure!(!current.contains("macro:"), "{} is a macro; only registers can be set", name);
+    anyhow::ensure!(
+        !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, ' ' | '.' | ',' | '-' | '+')),
+        "{} is not a number, dimension or glue",
+        value
+    );
+    let body = serde_json::json!({ "value": value, "type": "register", "variablesReference": 0 });
+    Ok((format!("{}={}\\relax ", name, value), body))
+}
+
+/// The 'source' response body for the request's `source.path`; stack frames only name files
+/// by path, so there are no source references to resolve.
+pub fn source_content(args: &serde_json::Value) -> Result<serde_json::Value> {
+    let path = args["source"]["path"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("Source not available (reference {})", args["sourceReference"]))?;
+    let content = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path, e))?;
+    Ok(serde_json::json!({ "content": content, "mimeType": "text/x-tex" }))
+}
+
 /// A generic session handler that wraps a specific Adapter implementation
 /// and handles the raw protocol loop (reading stdin, writing stdout).
 pub struct DebugSession<A: DebugAdapter> {
@@ -83,8 +230,6 @@ impl<A: DebugAdapter> DebugSession<A> {
 
     /// Internal logic for the DAP session, allowing mocking of I/O.
     pub fn run_session(&mut self, reader: &mut impl std::io::BufRead, stdout: &mut impl std::io::Write) -> Result<()> {
-        use std::io::Read;
-        
         loop {
             // 1. Read Headers (Content-Length)
             let mut content_length = 0;
@@ -102,10 +247,10 @@ impl<A: DebugAdapter> DebugSession<A> {
                     break;
                 }
                 
-                if line.to_lowercase().starts_with("content-length: ") {
-                    if let Ok(len) = line["content-length: ".len()..].parse::<usize>() {
-                        content_length = len;
-                    }
+                if line.to_lowercase().starts_with("content-length: ")
+                    && let Ok(len) = line["content-length: ".len()..].parse::<usize>()
+                {
+                    content_length = len;
                 }
             }
             
@@ -120,10 +265,10 @@ impl<A: DebugAdapter> DebugSession<A> {
             let message_str = String::from_utf8_lossy(&buffer);
 
             // 3. Parse & Dispatch
-            if let Ok(msg) = serde_json::from_str::<ProtocolMessage>(&message_str) {
-                if let ProtocolMessage::Request { seq, command, arguments } = msg {
-                    self.handle_request(seq, &command, arguments, stdout)?;
-                }
+            if let Ok(msg) = serde_json::from_str::<ProtocolMessage>(&message_str)
+                && let ProtocolMessage::Request { seq, command, arguments } = msg
+            {
+                self.handle_request(seq, &command, arguments, stdout)?;
             }
         }
     }
@@ -139,6 +284,12 @@ impl<A: DebugAdapter> DebugSession<A> {
             "stepIn" => self.adapter.step_in().map(|_| serde_json::Value::Null),
             "scopes" => self.adapter.scopes(args),
             "variables" => self.adapter.variables(args),
+            "setBreakpoints" => self.adapter.set_breakpoints(args),
+            "threads" => self.adapter.threads(),
+            "stackTrace" => self.adapter.stack_trace(args),
+            "source" => self.adapter.source(args),
+            "evaluate" => self.adapter.evaluate(args),
+            "setVariable" => self.adapter.set_variable(args),
             _ => Ok(serde_json::json!({})),
         };
         
@@ -171,7 +322,9 @@ impl<A: DebugAdapter> DebugSession<A> {
 }
 
     #[cfg(feature = "tectonic-engine")]
-    use crate::shim::{EngineCommand, EngineEvent};
+    use crate::breakpoints::{Breakpoints, Granularity};
+    #[cfg(feature = "tectonic-engine")]
+    use crate::shim::{EngineCommand, EngineEvent, Frame};
     #[cfg(feature = "tectonic-engine")]
     use std::sync::{Arc, Mutex};
     #[cfg(feature = "tectonic-engine")]
@@ -183,6 +336,12 @@ impl<A: DebugAdapter> DebugSession<A> {
     pub struct TectonicAdapter {
         shim_tx: Option<Sender<EngineCommand>>,
         shadow_vars: Arc<Mutex<HashMap<String, String>>>,
+        /// Sent to the shim on launch and whenever the client changes them.
+        breakpoints: Breakpoints,
+        /// The engine's input stack when it last stopped, outermost first.
+        frames: Arc<Mutex<Vec<Frame>>>,
+        /// The directory of the launched document, which the engine opens files relative to.
+        root: Option<PathBuf>,
     }
 
     #[cfg(feature = "tectonic-engine")]
@@ -191,6 +350,9 @@ impl<A: DebugAdapter> DebugSession<A> {
             Self {
                 shim_tx: None,
                 shadow_vars: Arc::new(Mutex::new(HashMap::new())),
+                breakpoints: Breakpoints::default(),
+                frames: Arc::new(Mutex::new(Vec::new())),
+                root: None,
             }
         }
     }
@@ -202,17 +364,22 @@ impl<A: DebugAdapter> DebugSession<A> {
                 "supportsConfigurationDoneRequest": true,
                 "supportsVariableType": true,
                 "supportsVariablePaging": false,
+                "supportsEvaluateForHovers": true,
+                "supportsSetVariable": true,
             }))
         }
 
         fn launch(&mut self, args: serde_json::Value) -> Result<()> {
             use crate::shim::TectonicShim;
             let program = args["program"].as_str().ok_or_else(|| anyhow::anyhow!("Missing 'program' in launch args"))?;
-            let shim = TectonicShim::new(std::path::PathBuf::from(program));
+            let shim = TectonicShim::new(PathBuf::from(program));
+            self.root = Path::new(program).parent().map(Path::to_path_buf);
             let (tx, rx) = shim.spawn();
+            tx.send(EngineCommand::SetBreakpoints(self.breakpoints.clone()))?;
             self.shim_tx = Some(tx);
             
             let vars = self.shadow_vars.clone();
+            let frames = self.frames.clone();
             // Thread to handle events from the engine
             std::thread::spawn(move || {
                 let mut stdout = std::io::stdout();
@@ -225,7 +392,7 @@ impl<A: DebugAdapter> DebugSession<A> {
                                 "event": "stopped",
                                 "body": {
                                     "reason": reason,
-                                    "threadId": 1,
+                                    "threadId": THREAD_ID,
                                     "allThreadsStopped": true,
                                     "text": location
                                 }
@@ -244,6 +411,9 @@ impl<A: DebugAdapter> DebugSession<A> {
                             let mut v = vars.lock().unwrap();
                             *v = new_vars;
                         }
+                        EngineEvent::StackUpdated(new_frames) => {
+                            *frames.lock().unwrap() = new_frames;
+                        }
                         EngineEvent::Terminated => {
                             let msg = serde_json::json!({ "type": "event", "event": "terminated" });
                             send_raw_dap(&msg, &mut stdout).unwrap();
@@ -293,6 +463,30 @@ impl<A: DebugAdapter> DebugSession<A> {
             Ok(serde_json::json!({ "variables": dap_vars }))
         }
 
+        fn set_breakpoints(&mut self, args: serde_json::Value) -> Result<serde_json::Value> {
+            let body = self.breakpoints.set(&args, Granularity::Line)?;
+            if let Some(tx) = &self.shim_tx {
+                tx.send(EngineCommand::SetBreakpoints(self.breakpoints.clone()))?;
+            }
+            Ok(body)
+        }
+
+        fn stack_trace(&mut self, args: serde_json::Value) -> Result<serde_json::Value> {
+            let frames = self.frames.lock().unwrap();
+            Ok(stack_trace_body(&frames, self.root.as_deref(), &args))
+        }
+
+        fn evaluate(&mut self, args: serde_json::Value) -> Result<serde_json::Value> {
+            evaluate_shadow(&self.shadow_vars.lock().unwrap(), &args)
+        }
+
+        fn set_variable(&mut self, args: serde_json::Value) -> Result<serde_json::Value> {
+            let (assignment, body) = register_assignment(&self.shadow_vars.lock().unwrap(), &args)?;
+            let tx = self.shim_tx.as_ref().ok_or_else(|| anyhow::anyhow!("The engine is not running"))?;
+            tx.send(EngineCommand::Inject(assignment))?;
+            Ok(body)
+        }
+
         fn disconnect(&mut self) -> Result<()> {
             if let Some(tx) = &self.shim_tx {
                 let _ = tx.send(EngineCommand::Terminate);
@@ -318,6 +512,9 @@ pub fn run_mock_session() -> Result<()> {
 
 struct MockAdapter {
     shim_tx: Option<std::sync::mpsc::Sender<crate::shim::EngineCommand>>,
+    breakpoints: crate::breakpoints::Breakpoints,
+    /// The launched document, the only frame the mock engine reports.
+    program: Option<String>,
 }
 
 impl DebugAdapter for MockAdapter {
@@ -329,10 +526,12 @@ impl DebugAdapter for MockAdapter {
         }))
     }
 
-    fn launch(&mut self, _args: serde_json::Value) -> Result<()> {
+    fn launch(&mut self, args: serde_json::Value) -> Result<()> {
         use crate::shim::Shim;
+        self.program = args["program"].as_str().map(str::to_string);
         let shim = crate::shim::MockShim;
         let (tx, _rx) = shim.spawn();
+        tx.send(crate::shim::EngineCommand::SetBreakpoints(self.breakpoints.clone()))?;
         self.shim_tx = Some(tx);
         Ok(())
     }
@@ -358,6 +557,19 @@ impl DebugAdapter for MockAdapter {
     fn variables(&mut self, _args: serde_json::Value) -> Result<serde_json::Value> {
          Ok(serde_json::json!({ "variables": [ { "name": "dummy", "value": "0", "variablesReference": 0 } ] }))
     }
+    fn set_breakpoints(&mut self, args: serde_json::Value) -> Result<serde_json::Value> {
+        // The mock engine has no lines; it only reaches files.
+        let body = self.breakpoints.set(&args, crate::breakpoints::Granularity::File)?;
+        if let Some(tx) = &self.shim_tx {
+            tx.send(crate::shim::EngineCommand::SetBreakpoints(self.breakpoints.clone()))?;
+        }
+        Ok(body)
+    }
+    fn stack_trace(&mut self, args: serde_json::Value) -> Result<serde_json::Value> {
+        let frames: Vec<crate::shim::Frame> =
+            self.program.iter().map(|program| crate::shim::Frame { name: program.clone(), line: None }).collect();
+        Ok(stack_trace_body(&frames, None, &args))
+    }
     fn disconnect(&mut self) -> Result<()> { 
          if let Some(tx) = &self.shim_tx {
             let _ = tx.send(crate::shim::EngineCommand::Terminate);
@@ -367,7 +579,7 @@ impl DebugAdapter for MockAdapter {
 }
 
 pub fn run_mock_session_with_io(reader: &mut impl std::io::BufRead, writer: &mut impl std::io::Write) -> Result<()> {
-    let adapter = MockAdapter { shim_tx: None };
+    let adapter = MockAdapter { shim_tx: None, breakpoints: Default::default(), program: None };
     let mut session = DebugSession::new(adapter);
     session.run_session(reader, writer)?;
     Ok(())
@@ -497,6 +709,146 @@ mod tests {
         assert!(out_str.contains("\"command\":\"disconnect\""));
     }
 
+    #[test]
+    fn test_set_breakpoints() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/src/lib.rs");
+        let commands = vec![
+            json!({"type":"request","seq":1,"command":"setBreakpoints","arguments":{"source":{"path":source},"breakpoints":[{"line":4}]}}),
+            json!({"type":"request","seq":2,"command":"setBreakpoints","arguments":{"breakpoints":[{"line":4}]}}),
+        ];
+        let mut input_data = String::new();
+        for cmd in commands {
+            let body = cmd.to_string();
+            input_data.push_str(&format!("Content-Length: {}\r\n\r\n{}", body.len(), body));
+        }
+        let mut reader = std::io::Cursor::new(input_data);
+        let mut stdout = Vec::new();
+
+        run_mock_session_with_io(&mut reader, &mut stdout).unwrap();
+
+        let out_str = String::from_utf8(stdout).unwrap();
+        assert!(out_str.contains("\"verified\":true"));
+        assert!(out_str.contains("\"line\":1"));
+        assert!(out_str.contains("Missing 'source.path'"));
+
+        // Adapters without breakpoints report them unverified.
+        let mut session = DebugSession::new(SimpleAdapter);
+        let mut stdout = Vec::new();
+        let args = json!({"source":{"path":source},"breakpoints":[{"line":4}]});
+        session.handle_request(1, "setBreakpoints", Some(args), &mut stdout).unwrap();
+        let out_str = String::from_utf8(stdout).unwrap();
+        assert!(out_str.contains("\"verified\":false"));
+    }
+
+    #[test]
+    fn test_stack_trace_body() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/src");
+        let frames = vec![
+            shim::Frame { name: "lib.rs".to_string(), line: None },
+            shim::Frame { name: "article.cls".to_string(), line: None },
+            shim::Frame { name: "./shim.rs".to_string(), line: Some(7) },
+        ];
+
+        let body = stack_trace_body(&frames, Some(Path::new(dir)), &json!({}));
+        assert_eq!(body["totalFrames"], 3);
+        let stack_frames = body["stackFrames"].as_array().unwrap();
+        assert_eq!(stack_frames[0]["id"], 3);
+        assert_eq!(stack_frames[0]["name"], "shim.rs");
+        assert_eq!(stack_frames[0]["line"], 7);
+        assert!(stack_frames[0]["source"]["path"].as_str().unwrap().ends_with("shim.rs"));
+        assert_eq!(stack_frames[1]["source"]["presentationHint"], "deemphasize");
+        assert_eq!(stack_frames[2]["line"], 0);
+
+        let body = stack_trace_body(&frames, Some(Path::new(dir)), &json!({ "startFrame": 1, "levels": 1 }));
+        let stack_frames = body["stackFrames"].as_array().unwrap();
+        assert_eq!(stack_frames.len(), 1);
+        assert_eq!(stack_frames[0]["name"], "article.cls");
+    }
+
+    #[test]
+    fn test_threads_stack_trace_and_source() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/src/lib.rs");
+        let commands = vec![
+            json!({"type":"request","seq":1,"command":"launch","arguments":{"program":source}}),
+            json!({"type":"request","seq":2,"command":"threads"}),
+            json!({"type":"request","seq":3,"command":"stackTrace","arguments":{"threadId":1}}),
+            json!({"type":"request","seq":4,"command":"source","arguments":{"source":{"path":source},"sourceReference":0}}),
+            json!({"type":"request","seq":5,"command":"source","arguments":{"sourceReference":3}}),
+            json!({"type":"request","seq":6,"command":"disconnect"}),
+        ];
+        let mut input_data = String::new();
+        for cmd in commands {
+            let body = cmd.to_string();
+            input_data.push_str(&format!("Content-Length: {}\r\n\r\n{}", body.len(), body));
+        }
+        let mut reader = std::io::Cursor::new(input_data);
+        let mut stdout = Vec::new();
+
+        run_mock_session_with_io(&mut reader, &mut stdout).unwrap();
+
+        let out_str = String::from_utf8(stdout).unwrap();
+        assert!(out_str.contains("\"threads\":[{\"id\":1,\"name\":\"TeX\"}]"));
+        assert!(out_str.contains("\"totalFrames\":1"));
+        assert!(out_str.contains("\"name\":\"lib.rs\""));
+        assert!(out_str.contains("\"mimeType\":\"text/x-tex\""));
+        assert!(out_str.contains("Source not available (reference 3)"));
+    }
+
+    #[test]
+    fn test_evaluate_shadow() {
+        let mut vars = std::collections::HashMap::new();
+        vars.insert("\\count0".to_string(), "42".to_string());
+        vars.insert("\\title".to_string(), "macro:->Notes".to_string());
+
+        let body = evaluate_shadow(&vars, &json!({ "expression": "\\count0", "context": "repl" })).unwrap();
+        assert_eq!(body["result"], "42");
+        assert_eq!(body["type"], "register");
+        let body = evaluate_shadow(&vars, &json!({ "expression": "\\count 0" })).unwrap();
+        assert_eq!(body["result"], "42");
+        let body = evaluate_shadow(&vars, &json!({ "expression": "title", "context": "hover" })).unwrap();
+        assert_eq!(body["result"], "macro:->Notes");
+        assert_eq!(body["type"], "macro");
+
+        let error = evaluate_shadow(&vars, &json!({ "expression": "\\count1" })).unwrap_err();
+        assert!(error.to_string().contains("\\count1 has not been assigned"));
+        assert!(evaluate_shadow(&vars, &json!({ "expression": " " })).is_err());
+        assert!(evaluate_shadow(&vars, &json!({})).is_err());
+
+        let mut session = DebugSession::new(SimpleAdapter);
+        let mut stdout = Vec::new();
+        session.handle_request(1, "evaluate", Some(json!({ "expression": "\\count0" })), &mut stdout).unwrap();
+        let out_str = String::from_utf8(stdout).unwrap();
+        assert!(out_str.contains("\"success\":false"));
+        assert!(out_str.contains("cannot evaluate"));
+    }
+
+    #[test]
+    fn test_register_assignment() {
+        let mut vars = std::collections::HashMap::new();
+        vars.insert("\\count0".to_string(), "1".to_string());
+        vars.insert("\\parskip".to_string(), "0.0pt plus 1.0pt".to_string());
+        vars.insert("\\c@section".to_string(), "2".to_string());
+        vars.insert("\\title".to_string(), "macro:->Notes".to_string());
+        let set = |name: &str, value: &str| {
+            register_assignment(&vars, &json!({ "variablesReference": 1, "name": name, "value": value }))
+        };
+
+        let (assignment, body) = set("\\count0", "42").unwrap();
+        assert_eq!(assignment, "\\count0=42\\relax ");
+        assert_eq!(body["value"], "42");
+        let (assignment, _) = set("\\parskip", " 1.5em plus 2pt ").unwrap();
+        assert_eq!(assignment, "\\parskip=1.5em plus 2pt\\relax ");
+        assert!(set("\\c@section", "-3").is_ok());
+
+        assert!(set("\\title", "1").unwrap_err().to_string().contains("is a macro"));
+        assert!(set("\\input", "secret").unwrap_err().to_string().contains("not in the Registers scope"));
+        assert!(set("\\end", "1").is_err());
+        assert!(set("count0", "1").is_err());
+        assert!(set("\\count0", "1\\relax\\input{secret}").is_err());
+        assert!(set("\\count0", "").is_err());
+        assert!(register_assignment(&vars, &json!({ "variablesReference": 2, "name": "\\count0", "value": "1" })).is_err());
+    }
+
     #[test]
     fn test_session_eof() {
         let mut session = DebugSession::new(SimpleAdapter);
@@ -1,4 +1,5 @@
 use std::sync::mpsc::{Sender, Receiver};
+use crate::breakpoints::Breakpoints;
 
 #[derive(Debug, Clone)]
 pub enum EngineEvent {
@@ -10,6 +11,67 @@ pub enum EngineEvent {
     Output(String),
     /// Variables have been updated.
     VariablesUpdated(std::collections::HashMap<String, String>),
+    /// The files the engine is reading, outermost first; sent before it stops.
+    StackUpdated(Vec<Frame>),
+}
+
+/// The register or macro and its value in a `\tracingassigns` line, such as
+/// `{changing \count0=10}` followed by `{into \count0=11}`.
+///
+/// A `changing` line carries the value before the assignment and is followed by an `into`
+/// line with the new one, so the last line parsed always holds the current value.
+pub fn parse_assignment(line: &str) -> Option<(String, String)> {
+    let inner = line.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let inner = inner.strip_prefix("globally ").unwrap_or(inner);
+    let inner = ["changing ", "into ", "reassigning "].iter().find_map(|prefix| inner.strip_prefix(prefix))?;
+    let (var, val) = inner.split_once('=')?;
+    Some((var.to_string(), val.to_string()))
+}
+
+/// A file the engine is reading.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// The name the engine opened it as, or the path of the breakpoint source it stopped in.
+    pub name: String,
+    /// The 1-based line it stopped at, if known.
+    pub line: Option<usize>,
+}
+
+/// The input stack, inferred from the files the engine opens and the breakpoints it reaches.
+///
+/// Engines do not report closing a file, so files it finished reading stay on the stack until
+/// it stops in a file below them.
+#[derive(Debug, Clone, Default)]
+pub struct FileStack {
+    frames: Vec<Frame>,
+}
+
+impl FileStack {
+    /// Pushes the file the engine opened as `name`.
+    pub fn open(&mut self, name: &str) {
+        self.frames.push(Frame { name: name.to_string(), line: None });
+    }
+
+    /// Records that the engine reached `line` of `source`, which closes the files opened after
+    /// it; a source not on the stack is pushed.
+    pub fn reach(&mut self, source: &std::path::Path, line: usize) {
+        let position = self
+            .frames
+            .iter()
+            .rposition(|frame| source.ends_with(frame.name.trim_start_matches("./")));
+        match position {
+            Some(i) => {
+                self.frames.truncate(i + 1);
+                self.frames[i].line = Some(line);
+            }
+            None => self.frames.push(Frame { name: source.display().to_string(), line: Some(line) }),
+        }
+    }
+
+    /// The files, outermost first.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -18,6 +80,12 @@ pub enum EngineCommand {
     Step,
     Pause,
     Terminate,
+    /// Replaces the breakpoints; files the engine already opened keep the line breakpoints
+    /// they had, unless those were removed.
+    SetBreakpoints(Breakpoints),
+    /// TeX source, such as `\count0=42\relax `, for the engine to read when it resumes:
+    /// before the breakpoint line or file it stopped at, or the next file it reads from disk.
+    Inject(String),
 }
 
 /// A shim wraps a TeX engine (real or mock) and provides channel-based control.
@@ -36,14 +104,27 @@ impl Shim for MockShim {
         
         std::thread::spawn(move || {
             let mut steps = 0;
+            // Files with breakpoints the mock engine has yet to reach, last first; it reaches one per chunk.
+            let mut pending: Vec<std::path::PathBuf> = Vec::new();
             loop {
                 // Wait for command
                 match cmd_rx.recv() {
+                    Ok(EngineCommand::SetBreakpoints(breakpoints)) => {
+                        pending = breakpoints.files().map(|path| path.to_path_buf()).collect();
+                        pending.sort_by(|a, b| b.cmp(a));
+                    }
                     Ok(EngineCommand::Continue) => {
                         // Simulate running for a bit then stopping
                         std::thread::sleep(std::time::Duration::from_millis(100));
                         let _ = event_tx.send(EngineEvent::Output(format!("Processing chunk {}\n", steps)));
                         steps += 1;
+                        if let Some(file) = pending.pop() {
+                            let _ = event_tx.send(EngineEvent::Stopped {
+                                reason: "breakpoint".to_string(),
+                                location: file.display().to_string(),
+                            });
+                            continue;
+                        }
                         if steps > 5 {
                             let _ = event_tx.send(EngineEvent::Terminated);
                             break;
@@ -58,6 +139,9 @@ impl Shim for MockShim {
                             location: format!("line {}", steps) 
                         });
                     }
+                    Ok(EngineCommand::Inject(source)) => {
+                        let _ = event_tx.send(EngineEvent::Output(format!("Injected {}\n", source)));
+                    }
                     Ok(EngineCommand::Terminate) => break,
                     _ => break,
                 }
@@ -74,7 +158,9 @@ mod stepping_io {
     use std::collections::HashMap;
     use tectonic_io_base::{IoProvider, OpenResult, InputHandle, OutputHandle, IoStatus};
     use std::sync::{Arc, Mutex, Condvar};
-    use crate::shim::{EngineEvent};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use crate::breakpoints::{self, Breakpoints};
+    use crate::shim::{EngineEvent, FileStack};
 
     pub struct SteppingIoProvider<T: IoProvider> {
         inner: T,
@@ -85,6 +171,14 @@ mod stepping_io {
         hashes: Arc<Mutex<HashMap<String, String>>>,
         /// Name of the primary file to inject traces into
         primary_file: Option<String>,
+        /// Breakpoints, instrumented into the files with line breakpoints as they are opened
+        breakpoints: Arc<Mutex<Breakpoints>>,
+        /// Whether to stop at every file, after a step, rather than at breakpoints only
+        stepping: Arc<AtomicBool>,
+        /// Files opened so far, reported as stack frames when stopping
+        stack: FileStack,
+        /// Source from `setVariable` waiting for the engine to read it
+        injections: Arc<Mutex<Vec<String>>>,
     }
 
     impl<T: IoProvider> SteppingIoProvider<T> {
@@ -94,15 +188,23 @@ mod stepping_io {
             control: Arc<(Mutex<bool>, Condvar)>,
             hashes: Arc<Mutex<HashMap<String, String>>>,
             primary_file: Option<String>,
+            breakpoints: Arc<Mutex<Breakpoints>>,
+            stepping: Arc<AtomicBool>,
+            injections: Arc<Mutex<Vec<String>>>,
         ) -> Self {
-            Self { inner, event_tx, control, hashes, primary_file }
+            Self { inner, event_tx, control, hashes, primary_file, breakpoints, stepping, stack: FileStack::default(), injections }
+        }
+
+        fn take_injections(&self) -> String {
+            self.injections.lock().unwrap().drain(..).collect()
         }
 
-        fn wait_for_continue(&self, name: &str) {
-            // 1. Notify DAP that we stopped on a file
+        fn wait_for_continue(&self, reason: &str, location: &str) {
+            // 1. Notify DAP that we stopped on a file or breakpoint, and where
+            let _ = self.event_tx.send(EngineEvent::StackUpdated(self.stack.frames().to_vec()));
             let _ = self.event_tx.send(EngineEvent::Stopped {
-                reason: "file_access".to_string(),
-                location: name.to_string(),
+                reason: reason.to_string(),
+                location: location.to_string(),
             });
 
             // 2. Block until control set to true
@@ -118,28 +220,65 @@ mod stepping_io {
 
     impl<T: IoProvider> IoProvider for SteppingIoProvider<T> {
         fn open_input(&mut self, name: &str) -> OpenResult<InputHandle> {
+            // An instrumented line reached its breakpoint; markers of removed breakpoints are
+            // read as empty files without stopping.
+            if breakpoints::marker_id(name).is_some() {
+                let hit = self.breakpoints.lock().unwrap().hit(name).map(|(source, b)| (source.to_path_buf(), b.line));
+                if let Some((source, line)) = hit {
+                    self.stack.reach(&source, line);
+                    self.wait_for_continue("breakpoint", &format!("{}:{}", source.display(), line));
+                }
+                // Assignments made while stopped run before the breakpoint line
+                return Ok(InputHandle::new_memory_backed(self.take_injections().into_bytes()));
+            }
+
             // Only stop on "interesting" files (not core formats)
             if name.ends_with(".tex") || name.ends_with(".sty") || name.ends_with(".cls") {
                 let _ = self.event_tx.send(EngineEvent::Output(format!("📖 Opening: {}\n", name)));
+                self.stack.open(name);
                 
                 // Track hash
+                let mut contents = None;
+                let mut original = None;
                 if let Ok(data) = std::fs::read(name) {
                     let mut hasher = Sha256::new();
                     hasher.update(&data);
                     let hash = hex::encode(hasher.finalize());
                     self.hashes.lock().unwrap().insert(name.to_string(), hash);
 
+                    let instrumented = self.breakpoints.lock().unwrap().instrument(name, &data);
                     // If this is the primary file, inject tracing flags
                     if self.primary_file.as_deref() == Some(name) {
                         let mut augmented = b"\\tracingassigns=1\\tracingonline=1\\tracingmacros=1\n".to_vec();
-                        augmented.extend_from_slice(&data);
-                        
-                        self.wait_for_continue(name);
-                        return Ok(InputHandle::new_memory_backed(augmented));
+                        augmented.extend_from_slice(instrumented.as_deref().unwrap_or(&data));
+                        contents = Some(augmented);
+                    } else {
+                        contents = instrumented;
                     }
+                    original = Some(data);
                 }
                 
-                self.wait_for_continue(name);
+                if self.stepping.load(Ordering::SeqCst) {
+                    self.wait_for_continue("file_access", name);
+                }
+
+                // Assignments made while stopped run before the file's first line, on the same
+                // line so the file's line numbers are unchanged; files from the bundle keep them
+                // for the next file read from disk
+                let injected = self.take_injections();
+                if !injected.is_empty() {
+                    match contents.take().or(original) {
+                        Some(text) => {
+                            let mut with_injections = injected.into_bytes();
+                            with_injections.extend_from_slice(&text);
+                            contents = Some(with_injections);
+                        }
+                        None => self.injections.lock().unwrap().push(injected),
+                    }
+                }
+                if let Some(contents) = contents {
+                    return Ok(InputHandle::new_memory_backed(contents));
+                }
             }
             self.inner.open_input(name)
         }
@@ -174,6 +313,7 @@ impl Shim for TectonicShim {
         use tectonic_status_base::{StatusBackend, MessageKind};
         use tectonic_io_base::IoStack;
         use std::sync::{Arc, Mutex, Condvar};
+        use std::sync::atomic::{AtomicBool, Ordering};
         
         let (cmd_tx, cmd_rx) = std::sync::mpsc::channel();
         let (event_tx, event_rx) = std::sync::mpsc::channel();
@@ -187,6 +327,11 @@ impl Shim for TectonicShim {
         let hashes = Arc::new(Mutex::new(std::collections::HashMap::new()));
         let hashes_clone = hashes.clone();
 
+        // Breakpoints and run mode, updated by the command thread
+        let breakpoints = Arc::new(Mutex::new(Breakpoints::default()));
+        let stepping = Arc::new(AtomicBool::new(true));
+        let injections = Arc::new(Mutex::new(Vec::new()));
+
         std::thread::spawn(move || {
             // Custom StatusBackend that forwards messages to DAP events
             struct EventStatusBackend {
@@ -207,15 +352,12 @@ impl Shim for TectonicShim {
                         format!("{} {}\n", prefix, args)
                     };
 
-                    // Shadow state parsing: Look for patterns like "{changing \count0=10}"
+                    // Shadow state parsing: Look for patterns like "{into \count0=10}"
                     // Note: Tectonic trace output usually goes through the status backend
                     let msg_str = format!("{}", args);
-                    if msg_str.starts_with("{changing ") && msg_str.ends_with('}') {
-                        let inner = &msg_str[10..msg_str.len()-1];
-                        if let Some((var, val)) = inner.split_once('=') {
-                            self.shadow_vars.insert(var.to_string(), val.to_string());
-                            let _ = self.tx.send(EngineEvent::VariablesUpdated(self.shadow_vars.clone()));
-                        }
+                    if let Some((var, val)) = crate::shim::parse_assignment(&msg_str) {
+                        self.shadow_vars.insert(var, val);
+                        let _ = self.tx.send(EngineEvent::VariablesUpdated(self.shadow_vars.clone()));
                     }
 
                     let _ = self.tx.send(EngineEvent::Output(msg));
@@ -233,11 +375,17 @@ impl Shim for TectonicShim {
                 shadow_vars: std::collections::HashMap::new(),
             };
             
-            // Wait for initial launch command
-            if let Ok(cmd) = cmd_rx.recv() {
+            // Wait for initial launch command, taking the breakpoints set before it
+            let mut launch = cmd_rx.recv();
+            while let Ok(EngineCommand::SetBreakpoints(set)) = launch {
+                *breakpoints.lock().unwrap() = set;
+                launch = cmd_rx.recv();
+            }
+            if let Ok(cmd) = launch {
                 if !matches!(cmd, EngineCommand::Continue | EngineCommand::Step) {
                     return;
                 }
+                stepping.store(matches!(cmd, EngineCommand::Step), Ordering::SeqCst);
                 
                 let _ = event_tx.send(EngineEvent::Output("🚀 Starting Tectonic Stepping Engine...\n".to_string()));
                 
@@ -278,6 +426,9 @@ impl Shim for TectonicShim {
                     control_clone.clone(),
                     hashes_clone.clone(),
                     Some(tex_name.clone()),
+                    breakpoints.clone(),
+                    stepping.clone(),
+                    injections.clone(),
                 );
                 
                 let mut builder = ProcessingSessionBuilder::new_with_security(tectonic::SecuritySettings::new(tectonic::SecurityStance::DisableInsecures));
@@ -301,10 +452,20 @@ impl Shim for TectonicShim {
                 // Thread to handle DAP commands and unblock I/O
                 let control_for_cmds = control_clone.clone();
                 let event_tx_for_cmds = event_tx.clone();
+                let breakpoints_for_cmds = breakpoints.clone();
+                let stepping_for_cmds = stepping.clone();
+                let injections_for_cmds = injections.clone();
                 std::thread::spawn(move || {
                     while let Ok(cmd) = cmd_rx.recv() {
                         match cmd {
+                            EngineCommand::SetBreakpoints(set) => {
+                                *breakpoints_for_cmds.lock().unwrap() = set;
+                            }
+                            EngineCommand::Inject(source) => {
+                                injections_for_cmds.lock().unwrap().push(source);
+                            }
                             EngineCommand::Continue | EngineCommand::Step => {
+                                stepping_for_cmds.store(matches!(cmd, EngineCommand::Step), Ordering::SeqCst);
                                 let (lock, cvar) = &*control_for_cmds;
                                 let mut started = lock.lock().unwrap();
                                 *started = true;
@@ -391,6 +552,78 @@ mod tests {
         }
     }
         
+    #[test]
+    fn test_mock_shim_breakpoints() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shim.rs");
+        let mut breakpoints = Breakpoints::default();
+        let args = serde_json::json!({ "source": { "path": source }, "breakpoints": [{ "line": 10 }] });
+        breakpoints.set(&args, crate::breakpoints::Granularity::File).unwrap();
+
+        let shim = MockShim;
+        let (tx, rx) = shim.spawn();
+        tx.send(EngineCommand::SetBreakpoints(breakpoints)).unwrap();
+        tx.send(EngineCommand::Continue).unwrap();
+        assert!(matches!(rx.recv().unwrap(), EngineEvent::Output(_)));
+        match rx.recv().unwrap() {
+            EngineEvent::Stopped { reason, location } => {
+                assert_eq!(reason, "breakpoint");
+                assert_eq!(location, source);
+            }
+            _ => panic!("Expected stopped event"),
+        }
+
+        // Reached once; the next chunk runs on.
+        tx.send(EngineCommand::Continue).unwrap();
+        assert!(matches!(rx.recv().unwrap(), EngineEvent::Output(_)));
+        tx.send(EngineCommand::Terminate).unwrap();
+        assert!(rx.recv().is_err());
+    }
+
+    #[test]
+    fn test_parse_assignment() {
+        assert_eq!(parse_assignment("{changing \\count0=1}"), Some(("\\count0".to_string(), "1".to_string())));
+        assert_eq!(parse_assignment("{into \\count0=2}"), Some(("\\count0".to_string(), "2".to_string())));
+        assert_eq!(
+            parse_assignment("{globally changing \\foo=macro:->a=b}"),
+            Some(("\\foo".to_string(), "macro:->a=b".to_string()))
+        );
+        assert_eq!(parse_assignment("{reassigning \\parindent=15.0pt}").unwrap().1, "15.0pt");
+        assert_eq!(parse_assignment("{vertical mode: \\par}"), None);
+        assert_eq!(parse_assignment("changing \\count0=1"), None);
+    }
+
+    #[test]
+    fn test_file_stack() {
+        let mut stack = FileStack::default();
+        stack.open("main.tex");
+        stack.open("./chapter.tex");
+        stack.open("figure.tex");
+        stack.reach(std::path::Path::new("/doc/chapter.tex"), 12);
+        assert_eq!(
+            stack.frames(),
+            &[
+                Frame { name: "main.tex".to_string(), line: None },
+                Frame { name: "./chapter.tex".to_string(), line: Some(12) },
+            ]
+        );
+
+        stack.reach(std::path::Path::new("/doc/appendix.tex"), 3);
+        assert_eq!(stack.frames().len(), 3);
+        assert_eq!(stack.frames()[2], Frame { name: "/doc/appendix.tex".to_string(), line: Some(3) });
+    }
+
+    #[test]
+    fn test_mock_shim_inject() {
+        let shim = MockShim;
+        let (tx, rx) = shim.spawn();
+        tx.send(EngineCommand::Inject("\\count0=42\\relax ".to_string())).unwrap();
+        match rx.recv().unwrap() {
+            EngineEvent::Output(s) => assert!(s.contains("Injected \\count0=42")),
+            _ => panic!("Expected output event"),
+        }
+        tx.send(EngineCommand::Terminate).unwrap();
+    }
+
     #[test]
     fn test_mock_shim_terminate() {
         let shim = MockShim;
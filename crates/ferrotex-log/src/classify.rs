@@ -0,0 +1,175 @@
+use crate::ir::EventPayload;
+
+/// Well-known warnings as `(id, package, text the message contains)`.
+///
+/// The ids are stable: projects refer to them to suppress warnings.
+const BUILTIN_RULES: &[(&str, &str, &str)] = &[
+    ("latex/undefined-reference", "latex", "Reference `"),
+    ("latex/undefined-citation", "latex", "Citation `"),
+    ("latex/undefined-references", "latex", "There were undefined references"),
+    ("latex/multiply-defined-label", "latex", "multiply defined"),
+    ("latex/multiply-defined-labels", "latex", "There were multiply-defined labels"),
+    ("latex/labels-changed", "latex", "Label(s) may have changed"),
+    ("latex/float-too-large", "latex", "Float too large for page"),
+    ("latex/float-specifier-changed", "latex", "float specifier changed"),
+    ("latex/marginpar-moved", "latex", "Marginpar on page"),
+    ("latex/unused-global-options", "latex", "Unused global option(s)"),
+    ("latex/empty-bibliography", "latex", "Empty `thebibliography' environment"),
+    ("latex/font-size-unavailable", "latex", "' in size <"),
+    ("latex/font-shape-undefined", "latex", "Font shape `"),
+    ("latex/font-shapes-substituted", "latex", "Some font shapes were not available"),
+    ("latex/font-size-substitutions", "latex", "Size substitutions with differences"),
+    ("hyperref/token-not-allowed", "hyperref", "Token not allowed in a PDF string"),
+    ("hyperref/option-already-used", "hyperref", "has already been used"),
+    ("hyperref/bookmark-levels", "hyperref", "between bookmark levels is greater"),
+    ("hyperref/empty-anchor", "hyperref", "Ignoring empty anchor"),
+    ("hyperref/empty-link-target", "hyperref", "Suppressing link with empty target"),
+    ("hyperref/composite-letter", "hyperref", "Composite letter"),
+    ("hyperref/draft-mode", "hyperref", "Draft mode on"),
+    ("natbib/undefined-citation", "natbib", "Citation `"),
+    ("natbib/undefined-citations", "natbib", "There were undefined citations"),
+    ("biblatex/rerun-biber", "biblatex", "Please (re)run Biber"),
+    ("biblatex/rerun-latex", "biblatex", "Please rerun LaTeX"),
+    ("biblatex/entry-not-found", "biblatex", "could not be found"),
+    ("rerunfilecheck/file-changed", "rerunfilecheck", "has changed"),
+    ("fancyhdr/headheight-too-small", "fancyhdr", "\\headheight is too small"),
+    ("geometry/over-specification", "geometry", "Over-specification in"),
+    ("caption/unknown-class", "caption", "Unknown document class"),
+    ("babel/no-hyphenation-patterns", "babel", "No hyphenation patterns were preloaded"),
+];
+
+/// A well-known warning of a package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WarningRule {
+    /// Stable id, `<package>/<name>`, e.g. `hyperref/token-not-allowed`.
+    pub id: String,
+    /// The package printing the warning, as returned by [`package_of`].
+    pub package: String,
+    /// Text the message contains.
+    pub pattern: String,
+}
+
+impl WarningRule {
+    /// Creates a rule matching the warnings of `package` that contain `pattern`.
+    pub fn new(id: impl Into<String>, package: impl Into<String>, pattern: impl Into<String>) -> Self {
+        Self { id: id.into(), package: package.into(), pattern: pattern.into() }
+    }
+}
+
+/// The package and rule of a warning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WarningClass<'a> {
+    /// The package that printed the warning, if the message names one.
+    pub package: Option<&'a str>,
+    /// The id of the first rule the warning matches.
+    pub rule: Option<&'a str>,
+}
+
+impl WarningClass<'_> {
+    /// Whether `selector`, a rule id or a package name, selects the warning. Projects
+    /// list selectors to suppress single warnings or all of a package.
+    pub fn is_selected_by(&self, selector: &str) -> bool {
+        self.rule == Some(selector) || self.package == Some(selector)
+    }
+}
+
+/// Tags warnings with the package that printed them and the rule they match.
+///
+/// The default classifier knows the common warnings of LaTeX, hyperref, natbib,
+/// biblatex and a few other packages; [`with_rule`](Self::with_rule) adds more.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WarningClassifier {
+    rules: Vec<WarningRule>,
+}
+
+impl Default for WarningClassifier {
+    fn default() -> Self {
+        Self {
+            rules: BUILTIN_RULES
+                .iter()
+                .map(|&(id, package, pattern)| WarningRule::new(id, package, pattern))
+                .collect(),
+        }
+    }
+}
+
+impl WarningClassifier {
+    /// A classifier without rules, which only tags packages.
+    pub fn empty() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// Adds a rule, tried before the ones already known.
+    pub fn with_rule(mut self, rule: WarningRule) -> Self {
+        self.rules.insert(0, rule);
+        self
+    }
+
+    /// The rules, in the order they are tried.
+    pub fn rules(&self) -> &[WarningRule] {
+        &self.rules
+    }
+
+    /// Classifies a warning message, e.g. `Package hyperref Warning: Token not allowed
+    /// in a PDF string (Unicode):`.
+    pub fn classify<'a>(&'a self, message: &'a str) -> WarningClass<'a> {
+        let package = package_of(message);
+        let rule = package.and_then(|package| {
+            self.rules
+                .iter()
+                .find(|rule| rule.package == package && message.contains(&rule.pattern))
+                .map(|rule| rule.id.as_str())
+        });
+        WarningClass { package, rule }
+    }
+
+    /// Classifies the warning an event reports, or returns `None` for other events.
+    pub fn classify_event<'a>(&'a self, payload: &'a EventPayload) -> Option<WarningClass<'a>> {
+        payload.warning_message().map(|message| self.classify(message))
+    }
+}
+
+/// The package that printed a warning: `hyperref` for `Package hyperref Warning: ...`,
+/// the class for `Class beamer Warning: ...` and `latex` for LaTeX's own warnings.
+pub fn package_of(message: &str) -> Option<&str> {
+    if message.starts_with("LaTeX Warning:") || message.starts_with("LaTeX Font Warning:") {
+        return Some("latex");
+    }
+    let rest = message.strip_prefix("Package ").or_else(|| message.strip_prefix("Class "))?;
+    let (name, _) = rest.split_once(" Warning:")?;
+    (!name.is_empty() && !name.contains(char::is_whitespace)).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_of() {
+        assert_eq!(package_of("Package hyperref Warning: Draft mode on."), Some("hyperref"));
+        assert_eq!(package_of("Class beamer Warning: Ignoring option."), Some("beamer"));
+        assert_eq!(package_of("LaTeX Warning: There were undefined references."), Some("latex"));
+        assert_eq!(package_of("LaTeX Font Warning: Some font shapes were not available."), Some("latex"));
+        assert_eq!(package_of("Overfull \\hbox (1.0pt too wide) in paragraph at lines 3--4"), None);
+    }
+
+    #[test]
+    fn test_classify() {
+        let classifier = WarningClassifier::default();
+        let class = classifier.classify("Package hyperref Warning: Token not allowed in a PDF string (Unicode):");
+        assert_eq!(class, WarningClass { package: Some("hyperref"), rule: Some("hyperref/token-not-allowed") });
+        assert!(class.is_selected_by("hyperref") && class.is_selected_by("hyperref/token-not-allowed"));
+        assert!(!class.is_selected_by("hyperref/draft-mode"));
+
+        // The same text means different things to different packages.
+        let class = classifier.classify("Package natbib Warning: Citation `knuth' on page 1 undefined on input line 3.");
+        assert_eq!(class.rule, Some("natbib/undefined-citation"));
+        let class = classifier.classify("LaTeX Font Warning: Font shape `OT1/cmr/m/n' in size <5.5> not available");
+        assert_eq!(class.rule, Some("latex/font-size-unavailable"));
+
+        let class = classifier.classify("Package foo Warning: Something odd.");
+        assert_eq!(class, WarningClass { package: Some("foo"), rule: None });
+        let classifier = classifier.with_rule(WarningRule::new("foo/odd", "foo", "odd"));
+        assert_eq!(classifier.classify("Package foo Warning: Something odd.").rule, Some("foo/odd"));
+    }
+}
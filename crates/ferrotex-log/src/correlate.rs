@@ -0,0 +1,126 @@
+use crate::ir::{EventPayload, LogEvent, Span};
+
+/// Consolidates each error with its location into a single [`EventPayload::Error`].
+///
+/// TeX prints an error as a `!` line, followed by a `l.<n>` reference when the error
+/// came from a source line; the parser reports them as separate `ErrorStart` and
+/// `ErrorLineRef` events. This pass replaces the pair with one `Error` event spanning
+/// both, attributed to the file on top of the file stack when the error was printed.
+/// All other events are kept as they are, including the `FileEnter`/`FileExit` events
+/// the file stack is built from.
+///
+/// Logs written with `-file-line-error` already carry `Error` events with the file and
+/// line TeX reported; the `l.<n>` reference only adds its excerpt to them.
+///
+/// The pass runs on complete event lists: an error still waiting for its line
+/// reference at the end of `events` is emitted without a line.
+pub fn correlate(events: &[LogEvent]) -> Vec<LogEvent> {
+    let mut result = Vec::with_capacity(events.len());
+    let mut stack: Vec<&str> = Vec::new();
+    // Index in `result` of the last error, until its `l.<n>` reference arrives.
+    let mut pending: Option<usize> = None;
+
+    for event in events {
+        match &event.payload {
+            EventPayload::ErrorStart { message, .. } => {
+                result.push(LogEvent {
+                    span: event.span,
+                    confidence: event.confidence,
+                    payload: EventPayload::Error {
+                        message: message.clone(),
+                        file: stack.last().map(|path| path.to_string()),
+                        line: None,
+                        excerpt: None,
+                    },
+                });
+                pending = Some(result.len() - 1);
+                continue;
+            }
+            EventPayload::Error { .. } => {
+                result.push(event.clone());
+                pending = Some(result.len() - 1);
+                continue;
+            }
+            EventPayload::ErrorLineRef { line, source_excerpt } => {
+                if let Some(index) = pending.take() {
+                    let error = &mut result[index];
+                    error.span = Span::new(error.span.start, event.span.end);
+                    if let EventPayload::Error { line: l, excerpt, .. } = &mut error.payload {
+                        l.get_or_insert(*line);
+                        *excerpt = source_excerpt.clone();
+                    }
+                    continue;
+                }
+            }
+            // Context lines and details of the error sit between it and its line reference.
+            EventPayload::ErrorContextLine { .. } | EventPayload::MissingFile { .. } => {}
+            EventPayload::FileEnter { path } => {
+                stack.push(path);
+                pending = None;
+            }
+            EventPayload::FileExit => {
+                stack.pop();
+                pending = None;
+            }
+            _ => pending = None,
+        }
+        result.push(event.clone());
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LogParser;
+
+    #[test]
+    fn test_correlate_errors() {
+        let log = "(./main.tex\n(./chapters/intro.tex\n! Undefined control sequence.\nl.7 \\foo\n)\n! Emergency stop.\n)\n";
+        let events = correlate(&LogParser::new().parse(log));
+        let errors: Vec<&EventPayload> = events
+            .iter()
+            .map(|event| &event.payload)
+            .filter(|payload| matches!(payload, EventPayload::Error { .. }))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                &EventPayload::Error {
+                    message: "Undefined control sequence.".to_string(),
+                    file: Some("./chapters/intro.tex".to_string()),
+                    line: Some(7),
+                    excerpt: Some("\\foo".to_string()),
+                },
+                &EventPayload::Error {
+                    message: "Emergency stop.".to_string(),
+                    file: Some("./main.tex".to_string()),
+                    line: None,
+                    excerpt: None,
+                },
+            ]
+        );
+        assert!(!events.iter().any(|event| matches!(
+            event.payload,
+            EventPayload::ErrorStart { .. } | EventPayload::ErrorLineRef { .. }
+        )));
+        assert_eq!(&log[events[2].span.start..events[2].span.end].trim_end(), &"! Undefined control sequence.\nl.7 \\foo");
+    }
+
+    #[test]
+    fn test_correlate_file_line_errors() {
+        let log = "(./main.tex\n./main.tex:12: Undefined control sequence.\nl.12 \\foo\n)\n";
+        let events = correlate(&LogParser::new().parse(log));
+        assert_eq!(
+            events[1].payload,
+            EventPayload::Error {
+                message: "Undefined control sequence.".to_string(),
+                file: Some("./main.tex".to_string()),
+                line: Some(12),
+                excerpt: Some("\\foo".to_string()),
+            }
+        );
+        assert_eq!(events.len(), 3);
+    }
+}
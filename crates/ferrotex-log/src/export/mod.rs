@@ -0,0 +1,101 @@
+/// [SARIF](https://sarifweb.azurewebsites.net/) 2.1, read by code scanning services.
+pub mod sarif;
+
+use crate::ir::{EventPayload, EventSeverity, LogEvent};
+
+/// The ids of the kinds of [`Finding`], with their descriptions. They match the
+/// diagnostic codes of the language server.
+pub const RULES: &[(&str, &str)] = &[
+    ("error", "TeX error"),
+    ("missing-file", "File not found"),
+    ("warning", "LaTeX or package warning"),
+    ("undefined-reference", "Undefined reference"),
+    ("undefined-citation", "Undefined citation"),
+    ("rerun-required", "Another engine pass is needed"),
+    ("font-issue", "Font shape not available"),
+    ("bad-box", "Overfull or underfull box"),
+];
+
+/// A problem reported by a log, with the place in the sources it refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// One of the [`RULES`].
+    pub rule: &'static str,
+    pub severity: EventSeverity,
+    pub message: String,
+    /// The `.tex` file, as found in the log without a leading `./`: relative to the
+    /// directory the engine ran in.
+    pub file: Option<String>,
+    pub start_line: Option<u32>,
+    /// The last line of a bad box's paragraph.
+    pub end_line: Option<u32>,
+}
+
+/// The errors and warnings of a log, in order.
+///
+/// Errors are [correlated](crate::correlate) with their file and line first. Like the
+/// language server, findings are located in the innermost `.tex` file open when they
+/// were printed, with the line the message reports; findings printed outside any `.tex`
+/// file have no location.
+pub fn findings(events: &[LogEvent]) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = Vec::new();
+    let mut stack: Vec<String> = Vec::new();
+
+    for event in crate::correlate(events) {
+        let severity = event.payload.severity();
+        let (rule, message, file, start_line, end_line) = match event.payload {
+            EventPayload::FileEnter { path } => {
+                stack.push(path);
+                continue;
+            }
+            EventPayload::FileExit => {
+                stack.pop();
+                continue;
+            }
+            EventPayload::MissingFile { .. } => {
+                // Follows the error reporting it.
+                if let Some(error) = findings.last_mut().filter(|finding| finding.rule == "error") {
+                    error.rule = "missing-file";
+                }
+                continue;
+            }
+            EventPayload::Error { message, file, line, .. } => match file.filter(|file| is_tex(file)) {
+                Some(file) => ("error", message, Some(file), line, None),
+                None => ("error", message, innermost_tex(&stack), None, None),
+            },
+            EventPayload::Warning { message } => {
+                let line = input_line(&message);
+                ("warning", message, innermost_tex(&stack), line, None)
+            }
+            EventPayload::RerunRequired { reason } => ("rerun-required", reason, innermost_tex(&stack), None, None),
+            EventPayload::UndefinedReference { message, line, .. } => {
+                ("undefined-reference", message, innermost_tex(&stack), line, None)
+            }
+            EventPayload::UndefinedCitation { message, line, .. } => {
+                ("undefined-citation", message, innermost_tex(&stack), line, None)
+            }
+            EventPayload::FontIssue { message, line, .. } => ("font-issue", message, innermost_tex(&stack), line, None),
+            EventPayload::BadBox { message, start_line, end_line, .. } => {
+                ("bad-box", message, innermost_tex(&stack), start_line, end_line)
+            }
+            _ => continue,
+        };
+        let file = file.map(|file| file.trim_start_matches("./").to_string());
+        findings.push(Finding { rule, severity, message, file, start_line, end_line });
+    }
+    findings
+}
+
+fn is_tex(path: &str) -> bool {
+    path.ends_with(".tex")
+}
+
+fn innermost_tex(stack: &[String]) -> Option<String> {
+    stack.iter().rev().find(|path| is_tex(path)).cloned()
+}
+
+/// The line of a `... on input line 12.` warning.
+fn input_line(message: &str) -> Option<u32> {
+    let (_, rest) = message.split_once("on input line ")?;
+    rest.split(|c: char| !c.is_ascii_digit()).next()?.parse().ok()
+}
@@ -0,0 +1,101 @@
+use super::{RULES, findings};
+use crate::ir::{EventSeverity, LogEvent};
+use serde_json::{Value, json};
+
+/// The SARIF version written by [`to_sarif`].
+pub const SARIF_VERSION: &str = "2.1.0";
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Converts log events into a SARIF log with one run.
+///
+/// Results are the [`findings`] of the events, located in the file and line they
+/// report; results printed outside any `.tex` file have no location.
+pub fn to_sarif(events: &[LogEvent]) -> Value {
+    let mut results: Vec<Value> = Vec::new();
+    for finding in findings(events) {
+        let mut result = json!({
+            "ruleId": finding.rule,
+            "level": level(finding.severity),
+            "message": { "text": finding.message },
+        });
+        if let Some(file) = finding.file {
+            let mut location = json!({ "artifactLocation": { "uri": file } });
+            if let Some(start_line) = finding.start_line {
+                location["region"] = json!({ "startLine": start_line });
+                if let Some(end_line) = finding.end_line {
+                    location["region"]["endLine"] = json!(end_line);
+                }
+            }
+            result["locations"] = json!([{ "physicalLocation": location }]);
+        }
+        results.push(result);
+    }
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": SARIF_VERSION,
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "FerroTeX",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/jxoesneon/FerroTeX",
+                    "rules": RULES
+                        .iter()
+                        .map(|(id, description)| json!({ "id": id, "shortDescription": { "text": description } }))
+                        .collect::<Vec<_>>(),
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+/// The SARIF level of a result: bad boxes are notes.
+fn level(severity: EventSeverity) -> &'static str {
+    match severity {
+        EventSeverity::Error => "error",
+        EventSeverity::Warning => "warning",
+        _ => "note",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LogParser;
+
+    #[test]
+    fn test_to_sarif() {
+        let log = "(./main.tex (/usr/share/texmf/tex/latex/base/article.cls)\n(./chapters/intro.tex\nOverfull \\hbox (1.0pt too wide) in paragraph at lines 3--4\n)\n! LaTeX Error: File `foo.sty' not found.\nl.5 \\usepackage{foo}\n\nLaTeX Warning: Label `a' multiply defined on input line 9.\n)\n";
+        let sarif = to_sarif(&LogParser::new().parse(log));
+        assert_eq!(sarif["version"], SARIF_VERSION);
+        let results = sarif["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+
+        assert_eq!(results[0]["ruleId"], "bad-box");
+        assert_eq!(results[0]["level"], "note");
+        let location = &results[0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "chapters/intro.tex");
+        assert_eq!(location["region"], json!({ "startLine": 3, "endLine": 4 }));
+
+        assert_eq!(results[1]["ruleId"], "missing-file");
+        assert_eq!(results[1]["level"], "error");
+        assert_eq!(results[1]["message"]["text"], "LaTeX Error: File `foo.sty' not found.");
+        let location = &results[1]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "main.tex");
+        assert_eq!(location["region"]["startLine"], 5);
+
+        assert_eq!(results[2]["ruleId"], "warning");
+        assert_eq!(results[2]["locations"][0]["physicalLocation"]["region"]["startLine"], 9);
+    }
+
+    #[test]
+    fn test_to_sarif_without_location() {
+        let sarif = to_sarif(&LogParser::new().parse("(/usr/share/texmf/tex/latex/base/article.cls\n! Emergency stop.\n)\n"));
+        let result = &sarif["runs"][0]["results"][0];
+        assert_eq!(result["ruleId"], "error");
+        assert!(result.get("locations").is_none());
+    }
+}
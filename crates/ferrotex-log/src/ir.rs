@@ -17,6 +17,22 @@ impl Span {
 }
 
 /// A confidence score for a parsed event, ranging from 0.0 to 1.0.
+///
+/// Events recognized by their exact syntax score 1.0. The parser lowers the score
+/// where it guesses:
+///
+/// - `FileEnter`: 1.0 for a file name with the extension of a file TeX reads, 0.8 for
+///   another absolute or `./` path and 0.6 for other text in parentheses that looks like
+///   a path; times 0.8 if the path continues on a line TeX did not wrap.
+/// - `FileExit`: the score of the `FileEnter` it closes, halved if a parenthesis of the
+///   text is still open on the line, which the `)` may close instead.
+/// - `Info` for a `)` without an open file: 0.5.
+/// - Any event read across a line wrapped at `max_print_line`: times 0.9, since a line
+///   may reach that length by chance.
+///
+/// Scores are rounded to two decimals.
+/// [`LogParser::with_min_confidence`](crate::LogParser::with_min_confidence) drops the
+/// events below a threshold.
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct Confidence(pub f64);
 
@@ -53,6 +69,10 @@ pub enum EventPayload {
     ErrorStart {
         /// The error message content.
         message: String,
+        /// The context TeX printed after the message, verbatim: pairs of lines split
+        /// where TeX stopped reading, ending with the `l.<n>` line and the rest of it.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        context: Vec<String>,
     },
     /// A reference to a line number in the source file (e.g., `l.10`).
     ErrorLineRef {
@@ -61,6 +81,20 @@ pub enum EventPayload {
         /// Context text following the line number.
         source_excerpt: Option<String>,
     },
+    /// An error together with its location, produced by [`crate::correlate`] from an
+    /// `ErrorStart` and the `ErrorLineRef` that follows it, or by the parser for
+    /// `file:line: message` errors (`-file-line-error`).
+    Error {
+        /// The error message content.
+        message: String,
+        /// The file reported with the error, or else the file on top of the file
+        /// stack when it was printed.
+        file: Option<String>,
+        /// The line number from the `l.<n>` reference, if TeX printed one.
+        line: Option<u32>,
+        /// Source text following the line number.
+        excerpt: Option<String>,
+    },
     /// A context line provided by LaTeX after an error.
     ErrorContextLine {
         /// The content of the context line.
@@ -71,6 +105,84 @@ pub enum EventPayload {
         /// The warning message content.
         message: String,
     },
+    /// An overfull or underfull box, e.g.
+    /// `Overfull \hbox (12.3pt too wide) in paragraph at lines 45--47`.
+    BadBox {
+        /// The full message.
+        message: String,
+        /// Whether the box is overfull or underfull.
+        kind: BadBoxKind,
+        /// Whether the box is an `\hbox` or a `\vbox`.
+        direction: BoxDirection,
+        /// How far an overfull box sticks out, in points.
+        overflow_pt: Option<f64>,
+        /// The badness of an underfull box, up to 10000.
+        badness: Option<u32>,
+        /// First source line of the box.
+        start_line: Option<u32>,
+        /// Last source line of the box; equals `start_line` for a single line.
+        end_line: Option<u32>,
+    },
+    /// A file the engine could not find, reported along with the error, e.g.
+    /// ``LaTeX Error: File `foo.sty' not found`` or ``I can't find file `bar.tex'``.
+    MissingFile {
+        /// The file name as TeX printed it.
+        name: String,
+        /// What the file is, from its extension.
+        kind: MissingFileKind,
+    },
+    /// An undefined `\ref`, e.g.
+    /// ``LaTeX Warning: Reference `fig:plot' on page 3 undefined on input line 12.``
+    UndefinedReference {
+        /// The full warning message.
+        message: String,
+        /// The label referenced.
+        key: String,
+        /// The page the reference is on.
+        page: Option<u32>,
+        /// The source line of the reference.
+        line: Option<u32>,
+    },
+    /// An undefined `\cite`, e.g.
+    /// ``LaTeX Warning: Citation `knuth84' on page 1 undefined on input line 5.``
+    UndefinedCitation {
+        /// The full warning message.
+        message: String,
+        /// The citation key.
+        key: String,
+        /// The page the citation is on.
+        page: Option<u32>,
+        /// The source line of the citation.
+        line: Option<u32>,
+    },
+    /// A warning asking for another engine pass, e.g.
+    /// `LaTeX Warning: Label(s) may have changed. Rerun to get cross-references right.`
+    /// or `Package biblatex Warning: Please rerun LaTeX.`
+    RerunRequired {
+        /// The full warning message.
+        reason: String,
+    },
+    /// A page shipped out to the output file, e.g. `[12]`. Messages following it are
+    /// printed while typesetting later pages.
+    PageShipout {
+        /// The page number (`\count0`) TeX printed.
+        number: u32,
+    },
+    /// A `LaTeX Font Warning`, such as a font shape that is not available and the one
+    /// used instead.
+    FontIssue {
+        /// The full warning message, its continuation lines joined.
+        message: String,
+        /// The shape asked for, e.g. `OT1/cmr/bx/sc`.
+        requested: Option<String>,
+        /// The shape used instead, e.g. `OT1/cmr/bx/n`.
+        substituted: Option<String>,
+        /// The source line the font was selected on.
+        line: Option<u32>,
+    },
+    /// The statistics TeX prints when a run ends: the output written and how much of
+    /// its memory and the PDF's capacity was used.
+    RunSummary(RunStatistics),
     /// General informational message.
     Info {
         /// The message content.
@@ -92,6 +204,116 @@ pub enum EventPayload {
     },
 }
 
+impl EventPayload {
+    /// The message of a warning, including the typed ones such as bad boxes and
+    /// undefined references.
+    pub fn warning_message(&self) -> Option<&str> {
+        match self {
+            EventPayload::Warning { message }
+            | EventPayload::BadBox { message, .. }
+            | EventPayload::UndefinedReference { message, .. }
+            | EventPayload::UndefinedCitation { message, .. }
+            | EventPayload::FontIssue { message, .. }
+            | EventPayload::RerunRequired { reason: message } => Some(message),
+            _ => None,
+        }
+    }
+
+    /// How much the event matters to someone reading the log.
+    ///
+    /// The lines printed with an error and the missing file it reports count as
+    /// errors, so filtering at `Error` keeps everything [`correlate`](crate::correlate)
+    /// merges. File boundaries are `Debug`.
+    pub fn severity(&self) -> EventSeverity {
+        match self {
+            EventPayload::ErrorStart { .. }
+            | EventPayload::ErrorLineRef { .. }
+            | EventPayload::ErrorContextLine { .. }
+            | EventPayload::Error { .. }
+            | EventPayload::MissingFile { .. } => EventSeverity::Error,
+            EventPayload::Warning { .. }
+            | EventPayload::UndefinedReference { .. }
+            | EventPayload::UndefinedCitation { .. }
+            | EventPayload::RerunRequired { .. }
+            | EventPayload::FontIssue { .. } => EventSeverity::Warning,
+            EventPayload::BadBox { .. } => EventSeverity::BadBox,
+            EventPayload::PageShipout { .. }
+            | EventPayload::RunSummary(_)
+            | EventPayload::Info { .. }
+            | EventPayload::OutputArtifact { .. }
+            | EventPayload::BuildSummary { .. } => EventSeverity::Info,
+            EventPayload::FileEnter { .. } | EventPayload::FileExit => EventSeverity::Debug,
+        }
+    }
+}
+
+/// The severity of a [`LogEvent`], from least to most severe.
+///
+/// Unlike the [`Severity`] of a [`Diagnostic`], it tells bad boxes apart from other
+/// warnings, and keeps the bookkeeping events no diagnostic is made from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum EventSeverity {
+    /// Bookkeeping, such as a file being opened or closed.
+    Debug,
+    /// Progress and statistics, such as a page being shipped out.
+    Info,
+    /// An overfull or underfull box.
+    BadBox,
+    /// A warning, such as an undefined reference.
+    Warning,
+    /// An error, or a line printed with one.
+    Error,
+}
+
+/// Whether a box is too full or too loose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BadBoxKind {
+    /// The content does not fit (`Overfull`).
+    Overfull,
+    /// The content is spread too thin (`Underfull`).
+    Underfull,
+}
+
+/// The direction a box is laid out in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BoxDirection {
+    /// An `\hbox`, e.g. a line of a paragraph.
+    Horizontal,
+    /// A `\vbox`, e.g. a page.
+    Vertical,
+}
+
+/// The kind of a missing file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MissingFileKind {
+    /// A package (`.sty`), usually installed from the TeX distribution.
+    Package,
+    /// A document class (`.cls`).
+    Class,
+    /// Any other file, such as an `\input` document or a graphic.
+    Input,
+}
+
+/// Figures from the end of a run. Each is absent when the log does not print it,
+/// e.g. `pdf_objects` for engines writing DVI or XDV.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunStatistics {
+    /// The output file, from `Output written on main.pdf (3 pages, 12345 bytes).`
+    pub output: Option<String>,
+    /// Pages written; `0` for `No pages of output.`
+    pub pages: Option<u32>,
+    /// Size of the output file in bytes.
+    pub bytes: Option<u64>,
+    /// Strings used, from `405 strings out of 476042`.
+    pub strings: Option<u64>,
+    /// Words of main memory used.
+    pub memory_words: Option<u64>,
+    /// Fonts loaded.
+    pub fonts: Option<u32>,
+    /// PDF objects written, from the `PDF statistics:` block.
+    pub pdf_objects: Option<u64>,
+}
+
 /// A standardized diagnostic derived from log events.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Diagnostic {
@@ -8,7 +8,8 @@
 //! (pdfTeX, XeTeX, LuaTeX, etc.) into a stream of typed [`LogEvent`](ir::LogEvent)s.
 //! The parser is designed to handle:
 //!
-//! - **Line wrapping**: TeX logs wrap at 79 characters, splitting paths and messages
+//! - **Line wrapping**: TeX logs wrap at 79 characters (`max_print_line`), splitting
+//!   paths and messages; wrapped lines are joined before classification
 //! - **File stack tracking**: Matching `(file.tex` and `)` pairs for context
 //! - **Error/warning extraction**: Detecting `!` errors, `LaTeX Warning:`, overful boxes
 //! - **Incremental/streaming updates**: Processing logs as they're written
@@ -40,9 +41,34 @@
 //! - [`EventPayload`](ir::EventPayload) - Discriminated union of event types
 //!   - `FileEnter { path }` - Engine opened a file
 //!   - `FileExit` - Engine closed a file
-//!   - `ErrorStart { message }` - Line starting with `!`
+//!   - `ErrorStart { message, context }` - Line starting with `!` and the context after it
 //!   - `Warning { message }` - LaTeX/package warning
+//!   - `BadBox { kind, direction, overflow_pt, badness, .. }` - Overfull/underfull box
+//!   - `UndefinedReference { key, page, line, .. }` - Undefined `\ref` warning
+//!   - `UndefinedCitation { key, page, line, .. }` - Undefined `\cite` warning
+//!   - `PageShipout { number }` - `[12]` marker of a page written to the output
+//!   - `FontIssue { requested, substituted, line, .. }` - `LaTeX Font Warning`
+//!   - `RerunRequired { reason }` - Warning asking for another engine pass
+//!   - `RunSummary(RunStatistics)` - Output size and memory use printed at the end of a run
+//!   - `MissingFile { name, kind }` - File not found, following the error reporting it
 //!   - `ErrorLineRef { line, excerpt }` - `l.123 ...` reference
+//!   - `Error { message, file, line, excerpt }` - An error with its location,
+//!     from a `file:line: message` line (`-file-line-error`) or the [`correlate`] pass
+//!
+//! Tectonic prints its own `error:`, `warning:` and `note:` lines instead of the TeX
+//! terminal output; the parser detects them and reports them in the same IR (see
+//! [`LogFormat`]). So it does with the `.ilg` transcripts of makeindex and xindy.
+//!
+//! [`EventPayload::severity`](ir::EventPayload::severity) ranks each event as an
+//! [`EventSeverity`](ir::EventSeverity), and [`LogParser::with_min_severity`] drops the
+//! events below a threshold.
+//!
+//! [`WarningClassifier`] tags warnings with the package that printed them and maps the
+//! well-known ones to stable rule ids such as `hyperref/token-not-allowed`, which
+//! projects can list to suppress them.
+//!
+//! [`export::sarif::to_sarif`] converts events into a SARIF 2.1 log, so code scanning
+//! services can annotate the lines errors and warnings were reported on.
 //!
 //! ## Schema Versioning
 //!
@@ -106,6 +132,12 @@
 //! # Ok::<(), Box<dyn std::error::Error>>(())
 //! ```
 
+/// Tagging of warnings with their package and a stable rule id.
+pub mod classify;
+/// Consolidation of error events with their location.
+pub mod correlate;
+/// Conversion of events into formats such as SARIF.
+pub mod export;
 /// Typed event Intermediate Representation (IR).
 pub mod ir;
 /// Streaming parser implementation.
@@ -114,7 +146,9 @@ pub mod parser;
 #[cfg(test)]
 mod tests;
 
-pub use parser::LogParser;
+pub use classify::WarningClassifier;
+pub use correlate::correlate;
+pub use parser::{DEFAULT_MAX_PRINT_LINE, LogFormat, LogParser};
 
 /// Schema version for the log event IR.
 ///
@@ -124,4 +158,4 @@ pub use parser::LogParser;
 /// - PATCH: Bug fixes to parsing behavior
 ///
 /// Starting with 1.0.0, backward compatibility is guaranteed within major versions.
-pub const SCHEMA_VERSION: &str = "1.0.0";
+pub const SCHEMA_VERSION: &str = "1.10.0";
@@ -1,15 +1,175 @@
-use crate::ir::{Confidence, EventPayload, LogEvent, Span};
+use crate::ir::{
+    BadBoxKind, BoxDirection, Confidence, EventPayload, EventSeverity, LogEvent, MissingFileKind, RunStatistics,
+    Span,
+};
+
+/// The column TeX wraps log lines at, `max_print_line` in TeX Live's `texmf.cnf`.
+pub const DEFAULT_MAX_PRINT_LINE: usize = 79;
+
+/// The kind of output a [`LogParser`] reads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Decided on the first line that is not blank: Tectonic's start with `note:`,
+    /// `warning:` or `error:`, index transcripts with the banner of makeindex or xindy.
+    #[default]
+    Auto,
+    /// A TeX `.log` file, or the terminal output of a TeX engine or latexmk.
+    TeX,
+    /// The console output of Tectonic, one `error:`, `warning:` or `note:` message per
+    /// line. Its kept `.log` file is in the [`TeX`](LogFormat::TeX) format.
+    Tectonic,
+    /// The transcript (`.ilg`) of makeindex or xindy, which sort the index.
+    Index,
+}
 
 /// A streaming parser for LaTeX logs.
 ///
 /// `LogParser` processes log output incrementally or as a whole, extracting events
 /// such as file entry/exit, warnings, and errors. It maintains a stack of open files
 /// to track the context of messages.
+///
+/// TeX breaks log lines longer than `max_print_line` characters, splitting paths and
+/// messages anywhere. Lines of exactly that length are joined with the next one before
+/// they are classified; spans still refer to the original log.
 pub struct LogParser {
     events: Vec<LogEvent>,
-    file_stack: Vec<String>,
+    /// The open files, with the confidence of their `FileEnter`.
+    file_stack: Vec<(String, Confidence)>,
     buffer: String,
     global_offset: usize,
+    max_print_line: usize,
+    format: LogFormat,
+    min_severity: EventSeverity,
+    min_confidence: Confidence,
+    /// Log offsets of the newlines TeX inserted at `max_print_line`.
+    wraps: Vec<usize>,
+    /// A line wrapped at `max_print_line`, waiting for its continuation.
+    wrapped: Option<LogicalLine>,
+    /// A path that reached the end of its line.
+    pending_path: Option<PendingPath>,
+    /// A font warning, waiting for its `(Font)` continuation lines.
+    font_warning: Option<(Span, String)>,
+    /// The end-of-run statistics read so far, emitted once a line follows them.
+    run_summary: Option<(Span, RunStatistics)>,
+    /// An error, waiting for the context lines TeX prints after it.
+    pending_error: Option<PendingError>,
+    /// The header of a makeindex message, waiting for the message.
+    index_message: Option<(Span, String)>,
+}
+
+/// A line of the log, joined with the lines TeX wrapped it onto.
+#[derive(Debug, Clone)]
+struct LogicalLine {
+    text: String,
+    /// Log offset of the first byte.
+    start: usize,
+    /// Log offset of the end of the last physical line, before its newline.
+    end: usize,
+    /// Positions in `text` where a wrapped line was joined with its continuation.
+    joins: Vec<usize>,
+}
+
+impl LogicalLine {
+    /// Log offset of byte `idx` of the line.
+    fn offset(&self, idx: usize) -> usize {
+        self.start + idx + self.joins.iter().filter(|&&join| join <= idx).count()
+    }
+}
+
+/// A path in parentheses that ran to the end of its line, which may continue on the
+/// next one unless that starts a new message.
+struct PendingPath {
+    path: String,
+    /// From the `(` to the end of the path so far.
+    span: Span,
+    /// The lines the path is on, to scan them as text if it is not a path after all.
+    lines: Vec<LogicalLine>,
+    /// Where the path starts on its first line.
+    first_char: usize,
+}
+
+/// Extensions of the files TeX and LaTeX read and announce in the log.
+const TEX_EXTENSIONS: &[&str] = &[
+    "tex", "ltx", "sty", "cls", "clo", "cfg", "def", "fd", "ldf", "dtx", "aux", "toc", "lof", "lot", "out", "bbl",
+    "ind", "gls", "nav", "snm", "vrb", "lua", "map", "enc", "pfb", "tfm", "vf", "png", "pdf", "jpg", "jpeg", "eps",
+];
+
+/// Confidence of an absolute or `./` path with an unknown extension.
+const EXPLICIT_PATH: Confidence = Confidence(0.8);
+/// Confidence of other text in parentheses accepted as a path, e.g. `subdir/file`.
+const GUESSED_PATH: Confidence = Confidence(0.6);
+/// Factor for a path continued after a line TeX did not wrap at `max_print_line`.
+const CONTINUED_PATH: f64 = 0.8;
+/// Factor for a `)` closing a file while a parenthesis of the text is open on its line.
+const AMBIGUOUS_PAREN: f64 = 0.5;
+/// Factor for an event read across a line wrapped at `max_print_line`.
+const WRAPPED_LINE: f64 = 0.9;
+
+/// Scales `confidence` by `factor`, rounded to two decimals.
+fn scaled(confidence: Confidence, factor: f64) -> Confidence {
+    Confidence((confidence.0 * factor * 100.0).round() / 100.0)
+}
+
+/// At most this many context lines are kept for an error; `\errorcontextlines`
+/// limits them to a dozen or so.
+const MAX_ERROR_CONTEXT: usize = 32;
+
+/// A `!` error whose context is still being read.
+struct PendingError {
+    span: Span,
+    message: String,
+    missing: Option<EventPayload>,
+    context: Vec<String>,
+    /// The `l.<n>` line of the context, reported after the error.
+    line_ref: Option<LogEvent>,
+    /// The length of a top line whose bottom line comes next.
+    top_len: Option<usize>,
+    /// Whether the last top line was the outermost one, `l.<n>` or `<*>`.
+    outermost: bool,
+}
+
+impl PendingError {
+    /// Reads `line` into the context, or returns `false` if it is not part of it.
+    ///
+    /// TeX shows each level of input as two lines: the text read so far, and below
+    /// it, indented to where the first one ends, the text still to be read.
+    fn read_context(&mut self, line: &LogicalLine) -> bool {
+        let text = line.text.as_str();
+        if let Some(top_len) = self.top_len.take() {
+            let indent = text.len() - text.trim_start_matches(' ').len();
+            if indent >= top_len || text.trim().is_empty() {
+                self.context.push(text.to_string());
+                return true;
+            }
+        }
+        // LaTeX errors point to the manual before their context.
+        if self.context.is_empty()
+            && (text.is_empty() || text.starts_with("See the ") || text.starts_with("Type  H <return>"))
+        {
+            return true;
+        }
+        let line_ref = LogParser::parse_line_ref(text);
+        let is_top = line_ref.is_some() || text.starts_with(['<', '\\']) || text.trim() == "...";
+        if !is_top || self.context.len() >= MAX_ERROR_CONTEXT {
+            return false;
+        }
+        self.outermost = line_ref.is_some() || text.starts_with("<*>");
+        if let Some(payload) = line_ref {
+            self.line_ref = Some(LogEvent {
+                span: Span::new(line.start, line.end),
+                confidence: Confidence::default(),
+                payload,
+            });
+        }
+        self.top_len = Some(text.len());
+        self.context.push(text.to_string());
+        true
+    }
+
+    /// Whether the bottom line of the outermost level has been read.
+    fn is_complete(&self) -> bool {
+        self.outermost && self.top_len.is_none()
+    }
 }
 
 impl Default for LogParser {
@@ -27,11 +187,60 @@ impl LogParser {
             file_stack: Vec::new(),
             buffer: String::new(),
             global_offset: 0,
+            max_print_line: DEFAULT_MAX_PRINT_LINE,
+            format: LogFormat::Auto,
+            min_severity: EventSeverity::Debug,
+            min_confidence: Confidence(0.0),
+            wraps: Vec::new(),
+            wrapped: None,
+            pending_path: None,
+            font_warning: None,
+            run_summary: None,
+            pending_error: None,
+            index_message: None,
         }
     }
 
+    /// Sets the column the engine wrapped the log at, for logs written with a custom
+    /// `max_print_line`. `0` turns unwrapping off.
+    pub fn with_max_print_line(mut self, max_print_line: usize) -> Self {
+        self.max_print_line = max_print_line;
+        self
+    }
+
+    /// Sets the kind of output to read instead of detecting it.
+    pub fn with_format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Drops the events less severe than `min_severity`.
+    ///
+    /// File boundaries are [`EventSeverity::Debug`], so [`correlate`](crate::correlate)
+    /// can no longer tell which file an error was printed in once they are dropped.
+    pub fn with_min_severity(mut self, min_severity: EventSeverity) -> Self {
+        self.min_severity = min_severity;
+        self
+    }
+
+    /// Drops the events scored below `min_confidence`; see [`Confidence`] for how
+    /// events are scored.
+    pub fn with_min_confidence(mut self, min_confidence: Confidence) -> Self {
+        self.min_confidence = min_confidence;
+        self
+    }
+
+    /// Whether TeX broke `line` because it reached `max_print_line`.
+    fn is_wrapped(&self, line: &str) -> bool {
+        // pdfTeX counts bytes, XeTeX and LuaTeX characters.
+        self.max_print_line > 0 && (line.len() == self.max_print_line || line.chars().count() == self.max_print_line)
+    }
+
     /// Appends input to the internal buffer and processes available events.
     ///
+    /// Only the lines completed by `input` are read, so feeding a log in small chunks
+    /// costs no more than parsing it at once.
+    ///
     /// # Arguments
     ///
     /// * `input` - A slice of the log file content to append.
@@ -40,8 +249,24 @@ impl LogParser {
     ///
     /// A vector of newly parsed `LogEvent`s.
     pub fn update(&mut self, input: &str) -> Vec<LogEvent> {
-        self.buffer.push_str(input);
-        self.process_buffer()
+        // The buffer only ever holds the start of a line, so lines end in `input`.
+        if let Some(last_nl) = input.rfind('\n') {
+            self.buffer.push_str(&input[..=last_nl]);
+            let complete = std::mem::replace(&mut self.buffer, input[last_nl + 1..].to_string());
+            for line in complete.lines() {
+                self.read_line(line);
+            }
+        } else {
+            self.buffer.push_str(input);
+        }
+        // A path at the end of the last line ends there if the next one starts a message.
+        while self.wrapped.is_none()
+            && Self::starts_event(&self.buffer)
+            && let Some(pending) = self.pending_path.take()
+        {
+            self.finish_path(pending, None);
+        }
+        self.take_events()
     }
 
     /// Consumes the current parser state and processes any remaining buffer as if it were the end of input.
@@ -50,225 +275,512 @@ impl LogParser {
     ///
     /// All remaining parsed `LogEvent`s, including any from the final buffer flush.
     pub fn finish(mut self) -> Vec<LogEvent> {
-        // Ensure any trailing data is processed
         if !self.buffer.is_empty() {
-            if !self.buffer.ends_with('\n') {
-                self.buffer.push(' '); // Ensure any open token is terminated
-                self.buffer.push('\n');
-            }
-            let mut final_events = self.process_buffer();
-            self.events.append(&mut final_events);
+            let mut last = std::mem::take(&mut self.buffer);
+            last.push(' '); // Ensure any open token is terminated
+            self.read_line(&last);
         }
-        std::mem::take(&mut self.events)
+        if let Some(line) = self.wrapped.take() {
+            self.process_line(line);
+        }
+        self.flush_error();
+        while let Some(pending) = self.pending_path.take() {
+            self.finish_path(pending, None);
+        }
+        self.flush_font_warning();
+        Self::flush_run_summary(&mut self.events, &mut self.run_summary);
+        if let Some((span, header)) = self.index_message.take() {
+            self.events.push(LogEvent { span, confidence: Confidence::default(), payload: Self::index_payload(&header, "") });
+        }
+        self.take_events()
     }
 
-    /// Internal method to process the buffer and drain events.
-    fn process_buffer(&mut self) -> Vec<LogEvent> {
-        let new_events = Vec::new();
-        // We need to keep track of how much of buffer we consumed to advance global_offset
-        // and remove consumed part from buffer.
+    /// Drains the events found so far, dropping those below `min_severity` or
+    /// `min_confidence`.
+    fn take_events(&mut self) -> Vec<LogEvent> {
+        let mut events = std::mem::take(&mut self.events);
+        for event in &mut events {
+            // A line of exactly `max_print_line` characters may have ended there by chance.
+            let next_wrap = self.wraps.partition_point(|&wrap| wrap < event.span.start);
+            if self.wraps.get(next_wrap).is_some_and(|&wrap| wrap < event.span.end) {
+                event.confidence = scaled(event.confidence, WRAPPED_LINE);
+            }
+        }
+        events.retain(|event| event.payload.severity() >= self.min_severity && event.confidence >= self.min_confidence);
+        events
+    }
 
-        // This is a naive implementation that still parses line-by-line.
-        // Real streaming needs to handle partial lines carefully.
-        // For Milestone 1, let's process full lines and leave partial lines in buffer.
+    /// Reads one line of the log, without its newline.
+    fn read_line(&mut self, text: &str) {
+        let start = self.global_offset;
+        self.global_offset += text.len() + 1; // +1 for assumed \n
+        if self.format == LogFormat::Auto {
+            if text.trim().is_empty() {
+                return;
+            }
+            self.format = if ["note: ", "warning: ", "error: "].iter().any(|prefix| text.starts_with(prefix)) {
+                LogFormat::Tectonic
+            } else if ["This is makeindex", "xindy", "Opening logfile"].iter().any(|prefix| text.starts_with(prefix)) {
+                LogFormat::Index
+            } else {
+                LogFormat::TeX
+            };
+        }
+        match self.format {
+            LogFormat::Tectonic => return self.read_tectonic_line(text, Span::new(start, start + text.len())),
+            LogFormat::Index => return self.read_index_line(text, Span::new(start, start + text.len())),
+            _ => {}
+        }
 
-        // Find the last newline character to determine the safe processing boundary
-        let process_len = if let Some(last_nl) = self.buffer.rfind('\n') {
-            last_nl + 1 // Include the newline
-        } else {
-            return new_events; // No full lines yet
+        // Unwrap: join each line TeX broke at `max_print_line` with its continuation,
+        // remembering where the newlines were to map positions back to the log.
+        let line = match self.wrapped.take() {
+            Some(mut line) => {
+                line.joins.push(line.text.len());
+                line.text.push_str(text);
+                line.end = start + text.len();
+                line
+            }
+            None => LogicalLine { text: text.to_string(), start, end: start + text.len(), joins: Vec::new() },
         };
+        if self.is_wrapped(text) {
+            self.wraps.push(start + text.len());
+            self.wrapped = Some(line);
+        } else {
+            self.process_line(line);
+        }
+    }
 
-        let chunk = &self.buffer[..process_len];
-        let lines: Vec<&str> = chunk.lines().collect();
+    /// Processes a complete (unwrapped) line, continuing what the previous lines left
+    /// open: a path, an error's context, a font warning or the statistics closing a run.
+    fn process_line(&mut self, line: LogicalLine) {
+        while let Some(pending) = self.pending_path.take() {
+            if !Self::starts_event(&line.text) {
+                return self.continue_path(pending, line);
+            }
+            self.finish_path(pending, None);
+        }
 
-        let peek_line = if process_len < self.buffer.len() {
-            Some(&self.buffer[process_len..])
-        } else {
-            None
-        };
+        // The context of an error is source text, not log output.
+        if let Some(error) = &mut self.pending_error {
+            if error.read_context(&line) {
+                if error.is_complete() {
+                    self.flush_error();
+                }
+                return;
+            }
+            self.flush_error();
+        }
 
-        // ... (Parsing logic adapted from original `parse` method) ...
-        // We will adapt the original loop here, but operating on `lines` derived from `chunk`.
-        // We need to verify how `extract_path_spanning` behaves with `lines`.
-
-        // Existing logic used `input.lines()`.
-
-        // NOTE: The original logic needs significant adaptation to be re-entrant.
-        // For the first step of refactoring, I will paste the original logic body
-        // but wrapped to work on the chunk, and then we will refine it to remove processed bytes.
-
-        let mut line_offsets = Vec::with_capacity(lines.len());
-        let mut current_off = self.global_offset;
-        for line in &lines {
-            line_offsets.push(current_off);
-            current_off += line.len() + 1; // +1 for assumed \n
-        }
-
-        let mut line_idx = 0;
-        let mut char_idx = 0;
-
-        // We need to capture events into `new_events` instead of `self.events` for the return value,
-        // or just append to `self.events` and return a slice/clone.
-        // The original `parse` returned `Vec<LogEvent>`. `update` should probably return new ones.
-        // Let's use `self.events` as history if we want, or just ephemeral.
-        // The roadmap says "Incremental updates without reparsing".
-        // Let's return new events and keep history in `self.events` (or clear it if we don't want history in parser).
-        // Actually, typically a parser might keep history or let the caller handle it.
-        // Let's clear `self.events` at start of `process_buffer` or use a local vec.
-        // But `check_warning` and `extract_path_spanning` might rely on `self` state (file_stack).
-        // `check_warning` pushes to `self.events`.
-
-        let start_event_count = self.events.len();
-
-        while line_idx < lines.len() {
-            // Check if we exhausted current line
-            if char_idx >= lines[line_idx].len() {
-                line_idx += 1;
-                char_idx = 0;
-                continue;
+        // Font warnings continue on `(Font)` lines, which are not files.
+        if let Some((span, message)) = &mut self.font_warning {
+            if let Some(rest) = line.text.strip_prefix("(Font)") {
+                message.push(' ');
+                message.push_str(rest.trim());
+                span.end = line.end;
+                return;
             }
+            self.flush_font_warning();
+        }
+        if line.text.is_empty() {
+            return;
+        }
 
-            let line = lines[line_idx];
-            let abs_line_start = line_offsets[line_idx];
-
-            let remainder = &line[char_idx..];
-            let mut chars = remainder.chars();
-
-            if let Some(c) = chars.next() {
-                let char_len = c.len_utf8();
-                let current_span_start = abs_line_start + char_idx;
-
-                match c {
-                    '(' => {
-                        // Extract path, possibly spanning lines
-                        let (path, consumed_lines, new_char_idx, incomplete) =
-                            Self::extract_path_spanning(
-                                &lines,
-                                line_idx,
-                                char_idx + char_len,
-                                peek_line,
-                            );
-
-                        if incomplete {
-                            // We don't have enough data to finish this path.
-                            break;
-                        }
+        // The statistics closing a run become one `RunSummary`.
+        if self.run_summary.is_none() && Self::starts_run_summary(&line.text) {
+            self.run_summary = Some((Span::new(line.start, line.start), RunStatistics::default()));
+        }
+        if let Some((span, stats)) = &mut self.run_summary {
+            if Self::read_run_statistic(stats, &line.text) {
+                span.end = line.end;
+                return;
+            }
+            Self::flush_run_summary(&mut self.events, &mut self.run_summary);
+        }
 
-                        // VALIDATION: Heuristic to reject non-file text in parentheses
-                        // e.g. "Latexmk: (Info) ..." or "TeX Live (preloaded format=...)"
-                        // A valid TeX path usually:
-                        // - Starts with / or \ or .
-                        // - OR looks like a filename with extension (contains a dot)
-                        // - We accept likely identifiers if they are long enough and don't contain spaces (extract_path_spanning stops at space)
-                        let is_likely_path = path.starts_with('/') 
-                                          || path.starts_with('\\') 
-                                          || path.starts_with('.')
-                                          || (path.contains('.') && !path.ends_with('.'))
-                                          || path.contains('/'); // relative path like "subdir/file"
-                        
-                        // Reject specific false positives seen in logs
-                        let is_blacklisted = path == "Info" 
-                                           || path == "preloaded"
-                                           || path == "TeX"
-                                           || path == "con"; // Windows legacy (unlikely in log but good practice)
-
-                        if !is_likely_path || is_blacklisted {
-                            // Treat '(' as text
-                             char_idx += char_len;
-                             continue;
-                        }
+        // With `-file-line-error`, errors replace the `!` with their location.
+        if let Some(payload) = Self::parse_file_line_error(&line.text) {
+            let span = Span::new(line.start, line.end);
+            let missing = match &payload {
+                EventPayload::Error { message, .. } => Self::parse_missing_file(message),
+                _ => None,
+            };
+            self.events.push(LogEvent { span, confidence: Confidence::default(), payload });
+            Self::push_missing_file(&mut self.events, missing, span);
+            return;
+        }
+
+        if line.text.starts_with("LaTeX Font Warning:") {
+            self.font_warning = Some((Span::new(line.start, line.end), line.text.trim().to_string()));
+            return;
+        }
+
+        self.scan_line(&line, 0);
+    }
+
+    /// Scans `line` from byte `char_idx` for file boundaries, shipouts, errors and
+    /// warnings.
+    fn scan_line(&mut self, line: &LogicalLine, mut char_idx: usize) {
+        let text = line.text.as_str();
+        // Parentheses of the text still open on the line, which a `)` may close instead
+        // of a file.
+        let mut open_parens = 0;
+        while let Some(c) = text[char_idx..].chars().next() {
+            let char_len = c.len_utf8();
+            let current_span_start = line.offset(char_idx);
 
-                        let span_end = if consumed_lines == 0 {
-                            abs_line_start + new_char_idx
-                        } else {
-                            // Calculate end based on new position
-                            let final_line_idx = line_idx + consumed_lines;
-                            if final_line_idx < lines.len() {
-                                line_offsets[final_line_idx] + new_char_idx
-                            } else {
-                                current_off
-                            }
-                        };
-
-                        self.file_stack.push(path.clone());
+            match c {
+                '(' => {
+                    let start = char_idx + char_len;
+                    let Some(len) = text[start..].find(|c: char| c == ')' || c.is_whitespace()) else {
+                        // The path runs to the end of the line; the next line tells whether
+                        // it continues there.
+                        self.pending_path = Some(PendingPath {
+                            path: text[start..].to_string(),
+                            span: Span::new(current_span_start, line.end),
+                            lines: vec![line.clone()],
+                            first_char: start,
+                        });
+                        return;
+                    };
+                    let path = &text[start..start + len];
+                    if Self::is_likely_path(path) {
+                        let confidence = Self::path_confidence(path);
+                        self.file_stack.push((path.to_string(), confidence));
                         self.events.push(LogEvent {
-                            span: Span::new(current_span_start, span_end),
-                            confidence: Confidence::default(),
-                            payload: EventPayload::FileEnter { path },
+                            span: Span::new(current_span_start, line.offset(start + len)),
+                            confidence,
+                            payload: EventPayload::FileEnter { path: path.to_string() },
                         });
-
-                        line_idx += consumed_lines;
-                        char_idx = new_char_idx;
-                        continue;
+                        char_idx = start + len;
+                    } else {
+                        // Treat '(' as text
+                        open_parens += 1;
+                        char_idx += char_len;
                     }
-                    ')' => {
-                        if let Some(_popped) = self.file_stack.pop() {
-                            self.events.push(LogEvent {
-                                span: Span::new(current_span_start, current_span_start + 1),
-                                confidence: Confidence::default(),
-                                payload: EventPayload::FileExit,
-                            });
-                        } else {
-                            self.events.push(LogEvent {
-                                span: Span::new(current_span_start, current_span_start + 1),
-                                confidence: Confidence(0.5),
-                                payload: EventPayload::Info {
-                                    message: "Unmatched closing parenthesis".into(),
-                                },
-                            });
+                }
+                ')' => {
+                    if let Some((_, mut confidence)) = self.file_stack.pop() {
+                        if open_parens > 0 {
+                            open_parens -= 1;
+                            confidence = scaled(confidence, AMBIGUOUS_PAREN);
                         }
-                        char_idx += char_len;
+                        self.events.push(LogEvent {
+                            span: Span::new(current_span_start, current_span_start + 1),
+                            confidence,
+                            payload: EventPayload::FileExit,
+                        });
+                    } else {
+                        self.events.push(LogEvent {
+                            span: Span::new(current_span_start, current_span_start + 1),
+                            confidence: Confidence(0.5),
+                            payload: EventPayload::Info {
+                                message: "Unmatched closing parenthesis".into(),
+                            },
+                        });
                     }
-                    '!' => {
-                        let msg = line[char_idx + char_len..].trim().to_string();
+                    char_idx += char_len;
+                }
+                '[' if !text[..char_idx].ends_with(|c: char| c.is_alphanumeric()) => {
+                    if let Some((number, len)) = Self::parse_shipout(&text[char_idx..]) {
                         self.events.push(LogEvent {
-                            span: Span::new(current_span_start, abs_line_start + line.len()),
+                            span: Span::new(current_span_start, line.offset(char_idx + len)),
                             confidence: Confidence::default(),
-                            payload: EventPayload::ErrorStart { message: msg },
+                            payload: EventPayload::PageShipout { number },
                         });
-                        line_idx += 1;
-                        char_idx = 0;
-                        continue;
-                    }
-                    _ => {
-                        if Self::check_warning(
-                            &mut self.events,
-                            &line[char_idx..],
-                            current_span_start,
-                            abs_line_start + line.len(),
-                        ) {
-                            line_idx += 1;
-                            char_idx = 0;
-                            continue;
-                        }
+                        char_idx += len;
+                    } else {
                         char_idx += char_len;
                     }
                 }
-            } else {
-                line_idx += 1;
-                char_idx = 0;
+                '!' => {
+                    let msg = text[char_idx + char_len..].trim().to_string();
+                    let span = Span::new(current_span_start, line.end);
+                    self.pending_error = Some(PendingError {
+                        span,
+                        missing: Self::parse_missing_file(&msg),
+                        message: msg,
+                        context: Vec::new(),
+                        line_ref: None,
+                        top_len: None,
+                        outermost: false,
+                    });
+                    return;
+                }
+                _ => {
+                    if Self::check_warning(&mut self.events, &text[char_idx..], current_span_start, line.end) {
+                        return;
+                    }
+                    char_idx += char_len;
+                }
             }
         }
+    }
 
-        // Determine how much we actually processed
-        // If we broke early due to incomplete, line_idx tells us where we stopped.
-        // Actually, we need to be precise.
-        // If we finished the loop successfully, we processed `process_len`.
-        // If we broke, we processed up to `line_offsets[line_idx]`.
-        // Wait, if we break, `line_idx` points to the line with `(`, which we haven't consumed.
+    /// Continues a path that reached the end of the previous line on `line`.
+    fn continue_path(&mut self, mut pending: PendingPath, line: LogicalLine) {
+        match line.text.find(|c: char| c == ')' || c.is_whitespace()) {
+            Some(len) => {
+                pending.path.push_str(&line.text[..len]);
+                pending.span.end = line.offset(len);
+                pending.lines.push(line);
+                self.finish_path(pending, Some(len));
+            }
+            None => {
+                pending.path.push_str(&line.text);
+                pending.span.end = line.end;
+                pending.lines.push(line);
+                self.pending_path = Some(pending);
+            }
+        }
+    }
 
-        let consumed_bytes = if line_idx < lines.len() {
-            line_offsets[line_idx] - self.global_offset
+    /// Reports a complete path as a `FileEnter` and scans the rest of its last line from
+    /// `resume`. If it does not look like a path, its lines are scanned as text instead.
+    fn finish_path(&mut self, pending: PendingPath, resume: Option<usize>) {
+        let PendingPath { path, span, lines, first_char } = pending;
+        if Self::is_likely_path(&path) {
+            let mut confidence = Self::path_confidence(&path);
+            if lines.len() > 1 {
+                // Only lines TeX wrapped at `max_print_line` are certain to continue.
+                confidence = scaled(confidence, CONTINUED_PATH);
+            }
+            self.file_stack.push((path.clone(), confidence));
+            self.events.push(LogEvent {
+                span,
+                confidence,
+                payload: EventPayload::FileEnter { path },
+            });
+            if let (Some(resume), Some(last)) = (resume, lines.last()) {
+                self.scan_line(last, resume);
+            }
         } else {
-            process_len
-        };
+            // Treat '(' as text
+            let mut lines = lines.into_iter();
+            if let Some(first) = lines.next() {
+                self.scan_line(&first, first_char);
+            }
+            for line in lines {
+                self.process_line(line);
+            }
+        }
+    }
+
+    /// Emits the pending error with its context, followed by the file it did not find
+    /// and its `l.<n>` line.
+    fn flush_error(&mut self) {
+        if let Some(error) = self.pending_error.take() {
+            self.events.push(LogEvent {
+                span: error.span,
+                confidence: Confidence::default(),
+                payload: EventPayload::ErrorStart { message: error.message, context: error.context },
+            });
+            Self::push_missing_file(&mut self.events, error.missing, error.span);
+            self.events.extend(error.line_ref);
+        }
+    }
+
+    fn flush_font_warning(&mut self) {
+        if let Some((span, message)) = self.font_warning.take() {
+            self.events.push(LogEvent {
+                span,
+                confidence: Confidence::default(),
+                payload: Self::parse_font_issue(message),
+            });
+        }
+    }
+
+    /// Heuristic to reject non-file text in parentheses, e.g. "Latexmk: (Info) ..." or
+    /// "TeX Live (preloaded format=...)".
+    fn is_likely_path(path: &str) -> bool {
+        // A valid TeX path usually:
+        // - Starts with / or \ or .
+        // - OR looks like a filename with extension (contains a dot)
+        // - OR is a relative path like "subdir/file"
+        let is_likely_path = path.starts_with('/')
+            || path.starts_with('\\')
+            || path.starts_with('.')
+            || (path.contains('.') && !path.ends_with('.'))
+            || path.contains('/');
+
+        // Reject specific false positives seen in logs
+        let is_blacklisted = path == "Info"
+            || path == "preloaded"
+            || path == "TeX"
+            || path == "con"; // Windows legacy (unlikely in log but good practice)
+
+        is_likely_path && !is_blacklisted
+    }
 
-        self.global_offset += consumed_bytes;
-        self.buffer.drain(..consumed_bytes);
+    /// How sure a path found by [`is_likely_path`](Self::is_likely_path) is a file:
+    /// certain with the extension of a file TeX reads, less so for other explicit paths,
+    /// and a guess for anything else in parentheses that looks like one.
+    fn path_confidence(path: &str) -> Confidence {
+        let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+        match name.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() && TEX_EXTENSIONS.contains(&extension) => Confidence(1.0),
+            Some(_) if path.starts_with(['/', '\\', '.']) => EXPLICIT_PATH,
+            _ => GUESSED_PATH,
+        }
+    }
+
+    /// Reads one line of Tectonic's console output.
+    ///
+    /// Errors with a `file.tex:12: ` location become `Error` events for that file and
+    /// line. A located warning is enclosed in `FileEnter`/`FileExit` for its file, so it is
+    /// attributed like one in a TeX log. Lines without a prefix, such as TeX's own
+    /// terminal output, are skipped.
+    fn read_tectonic_line(&mut self, line: &str, span: Span) {
+        let event = |payload| LogEvent { span, confidence: Confidence::default(), payload };
+        let line = line.trim_end();
 
-        // Extract new events
-        self.events.split_off(start_event_count)
+        if let Some(rest) = line.strip_prefix("error: ") {
+            let payload = Self::parse_file_line_error(rest).unwrap_or_else(|| EventPayload::Error {
+                message: rest.to_string(),
+                file: None,
+                line: None,
+                excerpt: None,
+            });
+            let missing = match &payload {
+                EventPayload::Error { message, .. } => Self::parse_missing_file(message),
+                _ => None,
+            };
+            self.events.push(event(payload));
+            Self::push_missing_file(&mut self.events, missing, span);
+        } else if let Some(rest) = line.strip_prefix("warning: ") {
+            match Self::parse_file_line_error(rest) {
+                Some(EventPayload::Error { message, file: Some(file), line, .. }) => {
+                    let file_start = span.start + "warning: ".len();
+                    self.events.push(LogEvent {
+                        span: Span::new(file_start, file_start + file.len()),
+                        confidence: Confidence::default(),
+                        payload: EventPayload::FileEnter { path: file },
+                    });
+                    self.events.push(event(Self::classify_warning(message, line)));
+                    self.events.push(LogEvent {
+                        span: Span::new(span.end, span.end),
+                        confidence: Confidence::default(),
+                        payload: EventPayload::FileExit,
+                    });
+                }
+                _ => self.events.push(event(Self::classify_warning(rest.to_string(), None))),
+            }
+        } else if let Some(rest) = line.strip_prefix("note: ") {
+            let payload = if rest.starts_with("Rerunning TeX because") {
+                EventPayload::RerunRequired { reason: rest.to_string() }
+            } else if let Some((path, _)) = rest.strip_prefix("Writing `").and_then(|path| path.split_once('`')) {
+                // `Writing `main.pdf` (41.28 KiB)`
+                EventPayload::OutputArtifact {
+                    path: Some(path.to_string()),
+                    format: path.rsplit_once('.').map(|(_, extension)| extension.to_string()),
+                    role: None,
+                }
+            } else {
+                EventPayload::Info { message: rest.to_string() }
+            };
+            self.events.push(event(payload));
+        }
+    }
+
+    /// Reads one line of a makeindex or xindy transcript.
+    ///
+    /// makeindex prints a header locating each message, e.g. `!! Input index error (file =
+    /// main.idx, line = 3):` or `## Warning (input = main.idx, line = 7; output = main.ind,
+    /// line = 12):`, and the message on the next line after `-- `. Errors become `Error`
+    /// events for the `.idx` or style file and line, warnings `Warning` events. xindy
+    /// prints `ERROR: ` and `WARNING: ` lines.
+    fn read_index_line(&mut self, line: &str, span: Span) {
+        let event = |payload| LogEvent { span, confidence: Confidence::default(), payload };
+        let line = line.trim_end();
+
+        if let Some((mut header_span, header)) = self.index_message.take() {
+            if let Some(message) = line.trim_start().strip_prefix("-- ") {
+                header_span.end = span.end;
+                let payload = Self::index_payload(&header, message);
+                return self.events.push(LogEvent { span: header_span, confidence: Confidence::default(), payload });
+            }
+            self.events.push(LogEvent {
+                span: header_span,
+                confidence: Confidence::default(),
+                payload: Self::index_payload(&header, ""),
+            });
+        }
+
+        if let Some(header) = ["!! ", "** ", "## "].iter().find_map(|prefix| line.strip_prefix(prefix)) {
+            self.index_message = Some((span, header.to_string()));
+        } else if let Some(message) = line.strip_prefix("ERROR: ") {
+            self.events.push(event(EventPayload::Error {
+                message: message.to_string(),
+                file: None,
+                line: None,
+                excerpt: None,
+            }));
+        } else if let Some(message) = line.strip_prefix("WARNING: ") {
+            self.events.push(event(EventPayload::Warning { message: message.to_string() }));
+        } else if let Some(name) = line.strip_prefix("Index file ").and_then(|rest| rest.strip_suffix(" not found.")) {
+            self.events.push(event(EventPayload::Error {
+                message: line.to_string(),
+                file: None,
+                line: None,
+                excerpt: None,
+            }));
+            self.events.push(event(EventPayload::MissingFile { name: name.to_string(), kind: MissingFileKind::Input }));
+        } else if let Some(path) = line.strip_prefix("Output written in ").and_then(|rest| rest.strip_suffix('.')) {
+            self.events.push(event(EventPayload::OutputArtifact {
+                path: Some(path.to_string()),
+                format: path.rsplit_once('.').map(|(_, extension)| extension.to_string()),
+                role: None,
+            }));
+        }
+    }
+
+    /// The event for a makeindex message: `header` is the header without its marker,
+    /// e.g. `Input index error (file = main.idx, line = 3):`.
+    fn index_payload(header: &str, message: &str) -> EventPayload {
+        let header = header.trim_end_matches(':');
+        let (kind, location) = match header.split_once(" (") {
+            Some((kind, location)) => (kind, location.trim_end_matches(')')),
+            None => (header, ""),
+        };
+        if kind == "Warning" {
+            let message = match location {
+                "" => format!("Index warning: {}", message),
+                location => format!("Index warning ({}): {}", location, message),
+            };
+            return EventPayload::Warning { message };
+        }
+        // The location of an error is in the file read: `file = main.idx, line = 3`.
+        let (mut file, mut line) = (None, None);
+        for part in location.split([',', ';']) {
+            match part.trim().split_once(" = ") {
+                Some(("file", name)) => file = Some(name.to_string()),
+                Some(("line", number)) if line.is_none() => line = number.parse().ok(),
+                _ => {}
+            }
+        }
+        EventPayload::Error { message: format!("{}: {}", kind, message), file, line, excerpt: None }
+    }
+
+    /// Classifies the message of a Tectonic warning like the same message in a TeX log,
+    /// taking `line` from its location when the message does not give one.
+    fn classify_warning(message: String, line: Option<u32>) -> EventPayload {
+        let mut payload = if message.starts_with("LaTeX Font Warning:") {
+            Self::parse_font_issue(message)
+        } else if let Some(payload) = Self::parse_undefined(&message).or_else(|| Self::parse_bad_box(&message)) {
+            payload
+        } else if Self::asks_for_rerun(&message) {
+            EventPayload::RerunRequired { reason: message }
+        } else {
+            EventPayload::Warning { message }
+        };
+        if let EventPayload::UndefinedReference { line: known, .. }
+        | EventPayload::UndefinedCitation { line: known, .. }
+        | EventPayload::FontIssue { line: known, .. }
+        | EventPayload::BadBox { start_line: known, .. } = &mut payload
+        {
+            *known = known.or(line);
+        }
+        payload
     }
 
     /// Legacy parse support for backward compatibility.
@@ -289,6 +801,57 @@ impl LogParser {
         events
     }
 
+    fn starts_run_summary(line: &str) -> bool {
+        line.starts_with("Here is how much of TeX's memory you used:")
+            || line.starts_with("Output written on ")
+            || line.starts_with("No pages of output.")
+            || line.starts_with("PDF statistics:")
+    }
+
+    /// Reads one line of the end-of-run statistics into `stats`, or returns `false`
+    /// if the line is not part of them.
+    fn read_run_statistic(stats: &mut RunStatistics, line: &str) -> bool {
+        if let Some(rest) = line.strip_prefix("Output written on ") {
+            // `main.pdf (3 pages, 12345 bytes).`
+            let (output, sizes) = rest.rsplit_once(" (").unwrap_or((rest.trim_end_matches('.'), ""));
+            let mut numbers = sizes.split(|c: char| !c.is_ascii_digit()).filter(|n| !n.is_empty());
+            stats.output = Some(output.to_string());
+            stats.pages = numbers.next().and_then(|n| n.parse().ok());
+            stats.bytes = numbers.next().and_then(|n| n.parse().ok());
+        } else if line.starts_with("No pages of output.") {
+            stats.pages = Some(0);
+        } else if line.starts_with(' ') {
+            // ` 405 strings out of 476042`; other figures are skipped.
+            if let Some((used, what)) = line.trim_start().split_once(' ') {
+                if what.starts_with("strings out of") {
+                    stats.strings = used.parse().ok();
+                } else if what.starts_with("words of memory out of") {
+                    stats.memory_words = used.parse().ok();
+                } else if what.starts_with("PDF objects out of") {
+                    stats.pdf_objects = used.parse().ok();
+                } else if let Some(fonts) = what.strip_prefix("words of font info for ") {
+                    stats.fonts = fonts.split_once(' ').and_then(|(fonts, _)| fonts.parse().ok());
+                }
+            }
+        } else {
+            // Blank lines and the `<cmr10.pfb>` font files embedded belong to the block too.
+            return line.is_empty()
+                || line.starts_with(['<', '{'])
+                || Self::starts_run_summary(line);
+        }
+        true
+    }
+
+    fn flush_run_summary(events: &mut Vec<LogEvent>, run_summary: &mut Option<(Span, RunStatistics)>) {
+        if let Some((span, stats)) = run_summary.take() {
+            events.push(LogEvent {
+                span,
+                confidence: Confidence::default(),
+                payload: EventPayload::RunSummary(stats),
+            });
+        }
+    }
+
     fn check_warning(
         events: &mut Vec<LogEvent>,
         text: &str,
@@ -298,126 +861,252 @@ impl LogParser {
         if (text.starts_with("LaTeX Warning:") || text.starts_with("Package"))
             && text.contains("Warning:")
         {
+            let message = text.trim().to_string();
             events.push(LogEvent {
                 span: Span::new(span_start, span_end),
                 confidence: Confidence::default(),
-                payload: EventPayload::Warning {
-                    message: text.trim().to_string(),
-                },
+                payload: Self::parse_undefined(&message).unwrap_or_else(|| {
+                    if Self::asks_for_rerun(&message) {
+                        EventPayload::RerunRequired { reason: message }
+                    } else {
+                        EventPayload::Warning { message }
+                    }
+                }),
             });
             return true;
         }
-        if text.starts_with("Overfull \\hbox") || text.starts_with("Underfull \\hbox") {
+        if let Some(payload) = Self::parse_bad_box(text.trim()) {
             events.push(LogEvent {
                 span: Span::new(span_start, span_end),
                 confidence: Confidence::default(),
-                payload: EventPayload::Warning {
-                    message: text.trim().to_string(),
-                },
+                payload,
             });
             return true;
         }
-        if let Some(number_part) = text.strip_prefix("l.") {
-            let digits: String = number_part
-                .chars()
-                .take_while(|c| c.is_ascii_digit())
-                .collect();
-            if !digits.is_empty()
-                && let Ok(line_num) = digits.parse::<u32>()
-            {
-                let excerpt = if 2 + digits.len() < text.len() {
-                    Some(text[2 + digits.len()..].trim().to_string())
-                } else {
-                    None
-                };
-                events.push(LogEvent {
-                    span: Span::new(span_start, span_end),
-                    confidence: Confidence::default(),
-                    payload: EventPayload::ErrorLineRef {
-                        line: line_num,
-                        source_excerpt: excerpt,
-                    },
-                });
-                return true;
-            }
+        if let Some(payload) = Self::parse_line_ref(text) {
+            events.push(LogEvent {
+                span: Span::new(span_start, span_end),
+                confidence: Confidence::default(),
+                payload,
+            });
+            return true;
         }
         false
     }
 
-    fn extract_path_spanning(
-        lines: &[&str],
-        start_line_idx: usize,
-        start_char_idx: usize,
-        peek_line: Option<&str>,
-    ) -> (String, usize, usize, bool) {
-        let mut path = String::new();
-        let mut current_line_idx = start_line_idx;
-        let mut current_char_idx = start_char_idx;
-
-        loop {
-
-            let line = lines[current_line_idx];
-            let remainder = &line[current_char_idx..];
-
-            if let Some(end_idx) = remainder.find(|c: char| c == ')' || c.is_whitespace()) {
-                path.push_str(&remainder[..end_idx]);
-                return (
-                    path,
-                    current_line_idx - start_line_idx,
-                    current_char_idx + end_idx,
-                    false,
-                );
-            } else {
-                // Check if we should wrap.
-                let next_line_idx = current_line_idx + 1;
-                if next_line_idx < lines.len() {
-                    let next_line = lines[next_line_idx];
-                    // Guarded joining: don't join if next line looks like a new event
-                    if next_line.starts_with("LaTeX Warning:")
-                        || next_line.starts_with("Package")
-                        || next_line.starts_with("!")
-                        || next_line.starts_with("(")
-                        || next_line.starts_with(")")
-                        || next_line.starts_with("Overfull")
-                        || next_line.starts_with("Underfull")
-                        || next_line.starts_with("LaTeX") // e.g. "LaTeX2e <2020...>"
-                        || next_line.starts_with("Document Class:")
-                        || next_line.starts_with("L3 programming")
-                    {
-                        // Don't join. Assume path ended at newline.
-                        path.push_str(remainder);
-                        return (path, current_line_idx - start_line_idx, line.len(), false);
-                    }
-                } else {
-                    // We are at the last line of the current chunk.
-                    // We check peek_line to decide if we should wrap.
-                    if let Some(next_line) = peek_line
-                        && (next_line.starts_with("LaTeX Warning:")
-                            || next_line.starts_with("Package")
-                            || next_line.starts_with("!")
-                            || next_line.starts_with("(")
-                            || next_line.starts_with(")")
-                            || next_line.starts_with("Overfull")
-                            || next_line.starts_with("Underfull")
-                            || next_line.starts_with("LaTeX")
-                            || next_line.starts_with("Document Class:")
-                            || next_line.starts_with("L3 programming"))
-                    {
-                        // Don't join.
-                        path.push_str(remainder);
-                        return (path, current_line_idx - start_line_idx, line.len(), false);
-                    }
+    /// Parses an `l.<n> <source>` line into an `ErrorLineRef`.
+    fn parse_line_ref(text: &str) -> Option<EventPayload> {
+        let number_part = text.strip_prefix("l.")?;
+        let digits: String = number_part
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        let line_num = digits.parse::<u32>().ok()?;
+        let excerpt = if 2 + digits.len() < text.len() {
+            Some(text[2 + digits.len()..].trim().to_string())
+        } else {
+            None
+        };
+        Some(EventPayload::ErrorLineRef {
+            line: line_num,
+            source_excerpt: excerpt,
+        })
+    }
 
-                    // Otherwise, we can't decide. Incomplete.
-                    return (path, current_line_idx - start_line_idx, 0, true);
-                }
+    /// Parses the start of a shipout marker: `[12]`, `[12.3]` when `\count1` is set, or
+    /// `[1{pdftex.map} <./fig.png>]` when files are read with the page. Returns the page
+    /// number and the length of `[<number>`.
+    fn parse_shipout(text: &str) -> Option<(u32, usize)> {
+        let rest = text.strip_prefix('[')?;
+        let counters = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.').len();
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let terminated = rest[counters..].is_empty() || rest[counters..].starts_with([']', ' ', '{', '<']);
+        if digits == 0 || !terminated {
+            return None;
+        }
+        Some((rest[..digits].parse().ok()?, 1 + counters))
+    }
 
-                path.push_str(remainder);
-                current_line_idx += 1;
-                current_char_idx = 0;
-            }
+    /// Follows an error about a missing file with a [`EventPayload::MissingFile`] event
+    /// spanning the same line.
+    fn push_missing_file(events: &mut Vec<LogEvent>, missing: Option<EventPayload>, span: Span) {
+        if let Some(payload) = missing {
+            events.push(LogEvent { span, confidence: Confidence::default(), payload });
         }
     }
+
+    /// Recognizes ``LaTeX Error: File `foo.sty' not found.`` and
+    /// ``I can't find file `bar.tex'.`` error messages.
+    fn parse_missing_file(message: &str) -> Option<EventPayload> {
+        let name = ["LaTeX Error: File `", "I can't find file `"]
+            .iter()
+            .find_map(|prefix| message.strip_prefix(prefix))?
+            .split_once('\'')?
+            .0;
+        let kind = match name.rsplit_once('.').map(|(_, extension)| extension) {
+            Some("sty") => MissingFileKind::Package,
+            Some("cls") => MissingFileKind::Class,
+            _ => MissingFileKind::Input,
+        };
+        Some(EventPayload::MissingFile { name: name.to_string(), kind })
+    }
+
+    /// Parses `./chapters/intro.tex:7: Undefined control sequence.`, the error format of
+    /// engines run with `-file-line-error`, into an [`EventPayload::Error`].
+    fn parse_file_line_error(line: &str) -> Option<EventPayload> {
+        // Windows paths contain a drive colon, so look for the first `:<n>: `.
+        line.match_indices(':').find_map(|(colon, _)| {
+            let (file, rest) = (&line[..colon], &line[colon + 1..]);
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let message = rest[digits..].strip_prefix(": ")?;
+            let name = file.rsplit(['/', '\\']).next()?;
+            if digits == 0 || file.starts_with(char::is_whitespace) || !name.contains('.') {
+                return None;
+            }
+            Some(EventPayload::Error {
+                message: message.trim().to_string(),
+                file: Some(file.to_string()),
+                line: rest[..digits].parse().ok(),
+                excerpt: None,
+            })
+        })
+    }
+
+    /// Parses a font warning, its `(Font)` continuation lines joined, e.g.
+    /// ``LaTeX Font Warning: Font shape `OT1/cmr/bx/sc' undefined using `OT1/cmr/bx/n'
+    /// instead on input line 12.``
+    fn parse_font_issue(message: String) -> EventPayload {
+        // Shapes are quoted as `T1/lmr/m/scit'; a substitution names the requested one first.
+        let mut shapes = message
+            .split('`')
+            .skip(1)
+            .filter_map(|quoted| quoted.split_once('\'').map(|(shape, _)| shape.to_string()));
+        let (requested, substituted) = if message.contains("Font shape `") {
+            (shapes.next(), shapes.next())
+        } else {
+            (None, None)
+        };
+        let line = message
+            .split_once("on input line ")
+            .and_then(|(_, rest)| rest.chars().take_while(|c| c.is_ascii_digit()).collect::<String>().parse().ok());
+        EventPayload::FontIssue { message, requested, substituted, line }
+    }
+
+    /// Whether a warning asks for another engine pass: LaTeX's and biblatex's mention a
+    /// rerun, rerunfilecheck's name the changed file.
+    fn asks_for_rerun(message: &str) -> bool {
+        message.starts_with("Package rerunfilecheck Warning")
+            || message
+                .split(|c: char| !c.is_alphanumeric())
+                .any(|word| word.eq_ignore_ascii_case("rerun"))
+    }
+
+    /// Parses ``LaTeX Warning: Reference `fig:plot' on page 3 undefined on input line 12.``
+    /// and the `Citation` equivalent, also printed by natbib and biblatex.
+    fn parse_undefined(message: &str) -> Option<EventPayload> {
+        let (_, rest) = message.split_once("Warning: ")?;
+        let (citation, rest) = if let Some(rest) = rest.strip_prefix("Reference ") {
+            (false, rest)
+        } else {
+            (true, rest.strip_prefix("Citation ")?)
+        };
+        // The key is quoted as `key' by LaTeX and 'key' by biblatex.
+        let rest = rest.strip_prefix(['`', '\''])?;
+        let (key, rest) = rest.split_once(['\'', '`'])?;
+        let (before, after) = rest.split_once(" undefined")?;
+        let number_after = |text: &str, prefix: &str| -> Option<u32> {
+            let (_, number) = text.split_once(prefix)?;
+            number
+                .chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect::<String>()
+                .parse()
+                .ok()
+        };
+        let (message, key) = (message.to_string(), key.to_string());
+        let page = number_after(before, "on page ");
+        let line = number_after(after, "on input line ");
+        Some(if citation {
+            EventPayload::UndefinedCitation { message, key, page, line }
+        } else {
+            EventPayload::UndefinedReference { message, key, page, line }
+        })
+    }
+
+    /// Parses `Overfull \hbox (12.3pt too wide) in paragraph at lines 45--47`,
+    /// `Underfull \vbox (badness 10000) detected at line 34` and the like.
+    fn parse_bad_box(text: &str) -> Option<EventPayload> {
+        let (kind, rest) = if let Some(rest) = text.strip_prefix("Overfull ") {
+            (BadBoxKind::Overfull, rest)
+        } else {
+            (BadBoxKind::Underfull, text.strip_prefix("Underfull ")?)
+        };
+        let (direction, rest) = if let Some(rest) = rest.strip_prefix("\\hbox") {
+            (BoxDirection::Horizontal, rest)
+        } else {
+            (BoxDirection::Vertical, rest.strip_prefix("\\vbox")?)
+        };
+
+        // `(12.3pt too wide)`, `(5.0pt too high)` or `(badness 10000)`.
+        let measure = rest
+            .trim_start()
+            .strip_prefix('(')
+            .and_then(|m| m.split_once(')'))
+            .map(|(measure, _)| measure);
+        let overflow_pt = measure
+            .and_then(|m| m.split_once("pt too "))
+            .and_then(|(amount, _)| amount.parse().ok());
+        let badness = measure
+            .and_then(|m| m.strip_prefix("badness "))
+            .and_then(|b| b.parse().ok());
+
+        // `at lines 45--47` or `at line 34`.
+        let (start_line, end_line) = match rest.split_once("at line") {
+            Some((_, lines)) => {
+                let lines = lines.trim_start_matches('s').trim_start();
+                let number = |s: &str| -> Option<u32> {
+                    s.chars()
+                        .take_while(|c| c.is_ascii_digit())
+                        .collect::<String>()
+                        .parse()
+                        .ok()
+                };
+                let start = number(lines);
+                let end = match lines.split_once("--") {
+                    Some((_, end)) => number(end),
+                    None => start,
+                };
+                (start, end)
+            }
+            None => (None, None),
+        };
+
+        Some(EventPayload::BadBox {
+            message: text.to_string(),
+            kind,
+            direction,
+            overflow_pt,
+            badness,
+            start_line,
+            end_line,
+        })
+    }
+
+    /// Whether `line` starts a new message, so a path at the end of the previous line
+    /// is not continued on it.
+    fn starts_event(line: &str) -> bool {
+        line.starts_with("LaTeX Warning:")
+            || line.starts_with("Package")
+            || line.starts_with("!")
+            || line.starts_with("(")
+            || line.starts_with(")")
+            || line.starts_with("Overfull")
+            || line.starts_with("Underfull")
+            || line.starts_with("LaTeX") // e.g. "LaTeX2e <2020...>"
+            || line.starts_with("Document Class:")
+            || line.starts_with("L3 programming")
+            || Self::parse_file_line_error(line).is_some()
+    }
 }
 
 #[cfg(test)]
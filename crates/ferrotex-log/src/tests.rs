@@ -1,6 +1,6 @@
 
-use crate::LogParser;
-use crate::ir::EventPayload;
+use crate::{LogFormat, LogParser};
+use crate::ir::{BadBoxKind, BoxDirection, Confidence, EventPayload, EventSeverity, MissingFileKind, RunStatistics};
 
 #[test]
 fn test_parse_empty_log() {
@@ -29,6 +29,26 @@ LaTeX Warning: Reference `fig:unknown' on page 1 undefined on input line 10.
     let parser = LogParser::new();
     let result = parser.parse(log);
     assert!(!result.is_empty());
+    assert!(matches!(
+        &result[0].payload,
+        EventPayload::UndefinedReference { key, page: Some(1), line: Some(10), .. } if key == "fig:unknown"
+    ));
+}
+
+#[test]
+fn test_parse_undefined_citation() {
+    let log = "Package natbib Warning: Citation `knuth' on page 2 undefined on input line 7.\nLaTeX Warning: Citation 'lamport94' undefined.\nLaTeX Warning: There were undefined references.\n";
+    let result = LogParser::new().parse(log);
+    assert!(matches!(
+        &result[0].payload,
+        EventPayload::UndefinedCitation { key, page: Some(2), line: Some(7), .. } if key == "knuth"
+    ));
+    assert!(matches!(
+        &result[1].payload,
+        EventPayload::UndefinedCitation { key, page: None, line: None, .. } if key == "lamport94"
+    ));
+    assert!(matches!(result[2].payload, EventPayload::Warning { .. }));
+    assert_eq!(result[1].payload.warning_message(), Some("LaTeX Warning: Citation 'lamport94' undefined."));
 }
 
 #[test]
@@ -39,6 +59,24 @@ Overfull \hbox (10.0pt too wide) in paragraph at lines 5--10
     let parser = LogParser::new();
     let result = parser.parse(log);
     assert!(!result.is_empty());
+    if let EventPayload::BadBox {
+        kind,
+        direction,
+        overflow_pt,
+        badness,
+        start_line,
+        end_line,
+        ..
+    } = &result[0].payload
+    {
+        assert_eq!(*kind, BadBoxKind::Overfull);
+        assert_eq!(*direction, BoxDirection::Horizontal);
+        assert_eq!(*overflow_pt, Some(10.0));
+        assert_eq!(*badness, None);
+        assert_eq!((*start_line, *end_line), (Some(5), Some(10)));
+    } else {
+        panic!("Expected BadBox, got {:?}", result[0].payload);
+    }
 }
 
 #[test]
@@ -51,6 +89,355 @@ Underfull \hbox (badness 10000) in paragraph at lines 5--10
     assert!(!result.is_empty());
 }
 
+#[test]
+fn test_parse_underfull_vbox() {
+    let log = "Underfull \\vbox (badness 10000) detected at line 34\n";
+    let result = LogParser::new().parse(log);
+    assert_eq!(
+        result[0].payload,
+        EventPayload::BadBox {
+            message: "Underfull \\vbox (badness 10000) detected at line 34".to_string(),
+            kind: BadBoxKind::Underfull,
+            direction: BoxDirection::Vertical,
+            overflow_pt: None,
+            badness: Some(10000),
+            start_line: Some(34),
+            end_line: Some(34),
+        }
+    );
+}
+
+#[test]
+fn test_parse_file_line_error() {
+    let log = "./chapters/intro.tex:7: LaTeX Error: File `foo.sty' not found.\nC:\\thesis\\main.tex:3: Emergency stop.\nPackage hyperref Warning: Token not allowed in a PDF string: removing `\\x'.\n";
+    let result = LogParser::new().parse(log);
+    assert_eq!(
+        result[0].payload,
+        EventPayload::Error {
+            message: "LaTeX Error: File `foo.sty' not found.".to_string(),
+            file: Some("./chapters/intro.tex".to_string()),
+            line: Some(7),
+            excerpt: None,
+        }
+    );
+    assert!(matches!(result[1].payload, EventPayload::MissingFile { .. }));
+    assert!(matches!(&result[2].payload, EventPayload::Error { file: Some(file), line: Some(3), .. } if file == "C:\\thesis\\main.tex"));
+    assert!(matches!(result[3].payload, EventPayload::Warning { .. }));
+    assert_eq!(result.len(), 4);
+}
+
+#[test]
+fn test_parse_missing_file() {
+    let log = "! LaTeX Error: File `foo.sty' not found.\n! I can't find file `chapters/bar'.\n./main.tex:1: LaTeX Error: File `acmart.cls' not found.\n";
+    let result = LogParser::new().parse(log);
+    let missing: Vec<(&str, MissingFileKind)> = result
+        .iter()
+        .filter_map(|event| match &event.payload {
+            EventPayload::MissingFile { name, kind } => Some((name.as_str(), *kind)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        missing,
+        [
+            ("foo.sty", MissingFileKind::Package),
+            ("chapters/bar", MissingFileKind::Input),
+            ("acmart.cls", MissingFileKind::Class),
+        ]
+    );
+    // Each follows the error reporting it, over the same span.
+    assert!(matches!(result[0].payload, EventPayload::ErrorStart { .. }));
+    assert_eq!(result[0].span, result[1].span);
+    assert_eq!(result.len(), 6);
+}
+
+#[test]
+fn test_parse_rerun_required() {
+    let log = "LaTeX Warning: Label(s) may have changed. Rerun to get cross-references right.\nPackage biblatex Warning: Please (re)run Biber on the file:\nPackage rerunfilecheck Warning: File `main.out' has changed.\nLaTeX Warning: There were undefined references.\n";
+    let result = LogParser::new().parse(log);
+    let reruns: Vec<bool> = result
+        .iter()
+        .map(|event| matches!(event.payload, EventPayload::RerunRequired { .. }))
+        .collect();
+    // biblatex asks for Biber there; `rerun LaTeX afterwards` follows on the next line.
+    assert_eq!(reruns, [true, false, true, false]);
+}
+
+#[test]
+fn test_parse_page_shipout() {
+    let log = "(./main.tex [1{/usr/share/texmf/fonts/map/pdftex.map}] [2 <./fig.png>]\n\nLaTeX Warning: Citation `x' on page 3 undefined on input line 40.\n\n[3.1]\narray[4] [5\n])\n";
+    let result = LogParser::new().parse(log);
+    let pages: Vec<u32> = result
+        .iter()
+        .filter_map(|event| match event.payload {
+            EventPayload::PageShipout { number } => Some(number),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(pages, [1, 2, 3, 5]);
+    let first = result.iter().find(|event| matches!(event.payload, EventPayload::PageShipout { .. })).unwrap();
+    assert_eq!(&log[first.span.start..first.span.end], "[1");
+    // The file stack is not disturbed by the markers.
+    assert!(matches!(result.last().unwrap().payload, EventPayload::FileExit));
+}
+
+#[test]
+fn test_unwrap_long_lines() {
+    let warning = "LaTeX Warning: Reference `sec:an-unusually-long-label-for-the-results' on page 4 undefined on input line 120.";
+    let (head, tail) = warning.split_at(79);
+    let log = format!("(./main.tex\n{}\n{}\n)\n", head, tail);
+    let result = LogParser::new().parse(&log);
+    assert!(matches!(
+        &result[1].payload,
+        EventPayload::UndefinedReference { key, line: Some(120), .. } if key == "sec:an-unusually-long-label-for-the-results"
+    ));
+    // The span covers both lines of the log.
+    assert_eq!(&log[result[1].span.start..result[1].span.end], format!("{}\n{}", head, tail));
+    assert!(matches!(result[2].payload, EventPayload::FileExit));
+
+    // Streaming: the first half waits for its continuation.
+    let mut parser = LogParser::new();
+    assert!(parser.update(&format!("{}\n", head)).is_empty());
+    let events = parser.update(&format!("{}\n", tail));
+    assert!(matches!(events[0].payload, EventPayload::UndefinedReference { .. }));
+
+    let result = LogParser::new().with_max_print_line(0).parse(&log);
+    assert!(!result.iter().any(|event| matches!(event.payload, EventPayload::UndefinedReference { .. })));
+}
+
+#[test]
+fn test_parse_font_issue() {
+    let log = "(./main.tex\nLaTeX Font Warning: Font shape `OT1/cmr/bx/sc' undefined\n(Font)              using `OT1/cmr/bx/n' instead on input line 12.\n\nLaTeX Font Warning: Some font shapes were not available, defaults substituted.\n\n)\n";
+    let result = LogParser::new().parse(log);
+    assert_eq!(
+        result[1].payload,
+        EventPayload::FontIssue {
+            message: "LaTeX Font Warning: Font shape `OT1/cmr/bx/sc' undefined using `OT1/cmr/bx/n' instead on input line 12.".to_string(),
+            requested: Some("OT1/cmr/bx/sc".to_string()),
+            substituted: Some("OT1/cmr/bx/n".to_string()),
+            line: Some(12),
+        }
+    );
+    assert!(matches!(&result[2].payload, EventPayload::FontIssue { requested: None, .. }));
+    // `(Font)` does not close `main.tex`.
+    assert!(matches!(result[3].payload, EventPayload::FileExit));
+    assert_eq!(result.len(), 4);
+}
+
+#[test]
+fn test_parse_run_summary() {
+    let log = "(./main.tex [1] )\nHere is how much of TeX's memory you used:\n 405 strings out of 476042\n 1925355 words of memory out of 5000000\n 476907 words of font info for 46 fonts, out of 8000000 for 9000\n\n</usr/share/texmf/fonts/type1/public/amsfonts/cm/cmr10.pfb>\nOutput written on main.pdf (1 page, 11768 bytes).\nPDF statistics:\n 12 PDF objects out of 1000 (max. 8388607)\n";
+    let result = LogParser::new().parse(log);
+    assert_eq!(
+        result.last().unwrap().payload,
+        EventPayload::RunSummary(RunStatistics {
+            output: Some("main.pdf".to_string()),
+            pages: Some(1),
+            bytes: Some(11768),
+            strings: Some(405),
+            memory_words: Some(1925355),
+            fonts: Some(46),
+            pdf_objects: Some(12),
+        })
+    );
+    let summary = result.last().unwrap().span;
+    assert!(log[summary.start..summary.end].starts_with("Here is how much"));
+    // `(1 page` and `(max.` open no files, so nothing closes `main.tex` a second time.
+    assert_eq!(result.iter().filter(|event| matches!(event.payload, EventPayload::FileExit)).count(), 1);
+
+    // Streaming: the summary is emitted at the line after it.
+    let mut parser = LogParser::new();
+    assert!(parser.update("No pages of output.\n").is_empty());
+    let events = parser.update("Transcript written on main.log.\n");
+    assert!(matches!(events[0].payload, EventPayload::RunSummary(RunStatistics { pages: Some(0), .. })));
+}
+
+#[test]
+fn test_min_severity() {
+    let log = "(./main.tex\nOverfull \\hbox (1.0pt too wide) in paragraph at lines 3--4\n[1]\nLaTeX Warning: Label(s) may have changed. Rerun to get cross-references right.\n! Undefined control sequence.\nl.7 \\foo\n)\n";
+    let all = LogParser::new().parse(log);
+    assert_eq!(all[0].payload.severity(), EventSeverity::Debug);
+    assert_eq!(all[1].payload.severity(), EventSeverity::BadBox);
+    assert_eq!(all[2].payload.severity(), EventSeverity::Info);
+
+    let result = LogParser::new().with_min_severity(EventSeverity::BadBox).parse(log);
+    assert_eq!(
+        result.iter().map(|event| event.payload.severity()).collect::<Vec<_>>(),
+        [EventSeverity::BadBox, EventSeverity::Warning, EventSeverity::Error, EventSeverity::Error]
+    );
+    let result = LogParser::new().with_min_severity(EventSeverity::Error).parse(log);
+    assert_eq!(result.len(), 2);
+}
+
+#[test]
+fn test_parse_tectonic_output() {
+    let log = "note: Running TeX ...\nwarning: ./main.tex:9: LaTeX Warning: Reference `fig:a' on page 1 undefined\nwarning: main.tex:14: Overfull \\hbox (1.0pt too wide) in paragraph\nerror: ./main.tex:3: LaTeX Error: File `foo.sty' not found.\nerror: halted on potentially-recoverable error as specified\nnote: Rerunning TeX because \"main.aux\" changed ...\nnote: Writing `main.pdf` (41.28 KiB)\n";
+    let result = LogParser::new().parse(log);
+    let payloads: Vec<&EventPayload> = result.iter().map(|event| &event.payload).collect();
+    assert_eq!(payloads[0], &EventPayload::Info { message: "Running TeX ...".to_string() });
+    assert_eq!(payloads[1], &EventPayload::FileEnter { path: "./main.tex".to_string() });
+    assert_eq!(&log[result[1].span.start..result[1].span.end], "./main.tex");
+    assert!(matches!(payloads[2], EventPayload::UndefinedReference { key, page: Some(1), line: Some(9), .. } if key == "fig:a"));
+    assert_eq!(payloads[3], &EventPayload::FileExit);
+    assert!(matches!(payloads[5], EventPayload::BadBox { start_line: Some(14), .. }));
+    assert_eq!(
+        payloads[7],
+        &EventPayload::Error {
+            message: "LaTeX Error: File `foo.sty' not found.".to_string(),
+            file: Some("./main.tex".to_string()),
+            line: Some(3),
+            excerpt: None,
+        }
+    );
+    assert!(matches!(payloads[8], EventPayload::MissingFile { kind: MissingFileKind::Package, .. }));
+    assert!(matches!(payloads[9], EventPayload::Error { file: None, line: None, .. }));
+    assert!(matches!(payloads[10], EventPayload::RerunRequired { .. }));
+    assert_eq!(
+        payloads[11],
+        &EventPayload::OutputArtifact { path: Some("main.pdf".to_string()), format: Some("pdf".to_string()), role: None }
+    );
+    assert_eq!(payloads.len(), 12);
+
+    // A TeX log is not mistaken for Tectonic output, nor the other way round when told.
+    let result = LogParser::new().parse("This is pdfTeX, Version 3.141592653\nerror: not a message\n");
+    assert!(result.is_empty());
+    assert!(LogParser::new().with_format(LogFormat::TeX).parse("note: Running TeX ...\n").is_empty());
+}
+
+#[test]
+fn test_parse_index_log() {
+    let ilg = "This is makeindex, version 2.17 [TeX Live 2024] (kpathsea + Thai support).
+Scanning input file main.idx....
+!! Input index error (file = main.idx, line = 3):
+   -- Extra `@' at position 5 of first argument.
+done (4 entries accepted, 1 rejected).
+Sorting entries....done (9 comparisons).
+Generating output file main.ind....
+## Warning (input = main.idx, line = 7; output = main.ind, line = 12):
+   -- Unmatched range opening operator (.
+done (15 lines written, 1 warning).
+Output written in main.ind.
+Transcript written in main.ilg.
+";
+    let result = LogParser::new().parse(ilg);
+    let payloads: Vec<_> = result.iter().map(|event| event.payload.clone()).collect();
+    assert_eq!(
+        payloads,
+        [
+            EventPayload::Error {
+                message: "Input index error: Extra `@' at position 5 of first argument.".to_string(),
+                file: Some("main.idx".to_string()),
+                line: Some(3),
+                excerpt: None,
+            },
+            EventPayload::Warning {
+                message: "Index warning (input = main.idx, line = 7; output = main.ind, line = 12): Unmatched range opening operator (.".to_string(),
+            },
+            EventPayload::OutputArtifact {
+                path: Some("main.ind".to_string()),
+                format: Some("ind".to_string()),
+                role: None,
+            },
+        ]
+    );
+    // The span covers the header and the message.
+    assert_eq!(&ilg[result[0].span.start..result[0].span.end], "!! Input index error (file = main.idx, line = 3):\n   -- Extra `@' at position 5 of first argument.");
+
+    let xindy = "Opening logfile \"main.ilg\" (done)\nReading raw-index \"main.raw\"...\nWARNING: unknown cross-reference-class `see'! (ignored)\nERROR: CHAR: index 0 should be less than the length of the string\n";
+    let result = LogParser::new().parse(xindy);
+    assert_eq!(result.len(), 2);
+    assert!(matches!(&result[0].payload, EventPayload::Warning { message } if message.starts_with("unknown cross-reference-class")));
+    assert!(matches!(result[1].payload, EventPayload::Error { file: None, line: None, .. }));
+}
+
+#[test]
+fn test_streaming_matches_whole_parse() {
+    let log = format!(
+        "{}{}",
+        include_str!("../tests/fixtures/basic.log"),
+        "(./main.tex) (./chapters/\nintro.tex [2]\nLaTeX Font Warning: Font shape `OT1/cmr/bx/sc' undefined\n(Font)              using `OT1/cmr/bx/n' instead on input line 12.\n)"
+    );
+    let whole = LogParser::new().parse(&log);
+    for size in [1, 2, 3, 7, 64] {
+        let mut parser = LogParser::new();
+        let mut events = Vec::new();
+        for chunk in log.as_bytes().chunks(size) {
+            events.extend(parser.update(std::str::from_utf8(chunk).unwrap()));
+        }
+        events.extend(parser.finish());
+        assert_eq!(events, whole, "chunks of {} bytes", size);
+    }
+    assert!(whole.iter().any(|event| event.payload == EventPayload::FileEnter { path: "./chapters/intro.tex".to_string() }));
+}
+
+#[test]
+fn test_parse_error_context() {
+    let log = "(./main.tex\n! Undefined control sequence.\n<argument> \\foo\n                \n\\textbf #1->{\\bfseries #1}\n                         \nl.7 \\textbf{\\foo\n                 } bar (see above)\nThe control sequence at the end of the top line\n)";
+    let events = LogParser::new().parse(log);
+    let payloads: Vec<_> = events.iter().map(|event| &event.payload).collect();
+    assert_eq!(
+        payloads[1],
+        &EventPayload::ErrorStart {
+            message: "Undefined control sequence.".to_string(),
+            context: vec![
+                "<argument> \\foo".to_string(),
+                " ".repeat(16),
+                "\\textbf #1->{\\bfseries #1}".to_string(),
+                " ".repeat(25),
+                "l.7 \\textbf{\\foo".to_string(),
+                "                 } bar (see above)".to_string(),
+            ],
+        }
+    );
+    assert!(matches!(payloads[2], EventPayload::ErrorLineRef { line: 7, .. }));
+    // The parentheses in the source are not file boundaries.
+    assert_eq!(payloads[3], &EventPayload::FileExit);
+    assert_eq!(payloads.len(), 4);
+
+    // LaTeX errors refer to the manual before the context; the help text ends it.
+    let log = "! LaTeX Error: Environment foo undefined.\n\nSee the LaTeX manual or LaTeX Companion for explanation.\nType  H <return>  for immediate help.\n ...\n\nl.5 \\begin{foo}\n               \nYour command was ignored.\n";
+    let events = LogParser::new().parse(log);
+    let EventPayload::ErrorStart { context, .. } = &events[0].payload else {
+        panic!("Expected ErrorStart, got {:?}", events[0].payload);
+    };
+    assert_eq!(context, &[" ...", "", "l.5 \\begin{foo}", "               "]);
+    assert!(matches!(events[1].payload, EventPayload::ErrorLineRef { line: 5, .. }));
+
+    // Without a context, the error is emitted when the next line is read.
+    let events = LogParser::new().parse("! LaTeX Error: File `foo.sty' not found.\n\nType X to quit or <RETURN> to proceed,\n");
+    assert_eq!(events[0].payload, EventPayload::ErrorStart { message: "LaTeX Error: File `foo.sty' not found.".to_string(), context: Vec::new() });
+    assert!(matches!(events[1].payload, EventPayload::MissingFile { .. }));
+}
+
+#[test]
+fn test_confidence() {
+    let log = "(./main.tex (/opt/fig.tikz (chapters/intro (see below)) text)\n";
+    let scores: Vec<_> = LogParser::new().parse(log).iter().map(|event| (event.payload.clone(), event.confidence.0)).collect();
+    assert_eq!(
+        scores,
+        [
+            (EventPayload::FileEnter { path: "./main.tex".to_string() }, 1.0),
+            (EventPayload::FileEnter { path: "/opt/fig.tikz".to_string() }, 0.8),
+            (EventPayload::FileEnter { path: "chapters/intro".to_string() }, 0.6),
+            // `(see below)` is text, so its `)` probably does not close a file.
+            (EventPayload::FileExit, 0.3),
+            (EventPayload::FileExit, 0.8),
+            (EventPayload::FileExit, 1.0),
+        ]
+    );
+
+    // A path joined across a wrapped line, and one continued after a line that is not.
+    let wrapped = format!("({}\n.tex)\n", "a".repeat(78));
+    let events = LogParser::new().parse(&wrapped);
+    assert_eq!(events[0].confidence, Confidence(0.9));
+    let events = LogParser::new().parse("(./chapters/\nintro.tex)\n");
+    assert_eq!(events[0].confidence, Confidence(0.8));
+
+    let events = LogParser::new().with_min_confidence(Confidence(0.7)).parse(log);
+    assert_eq!(events.len(), 4);
+    assert!(events.iter().all(|event| event.confidence >= Confidence(0.7)));
+}
+
 #[test]
 fn test_parse_file_enter_exit() {
     let log = "(./main.tex)";
@@ -81,7 +468,7 @@ l.42 some text
 #[test]
 fn test_incremental_parsing() {
     let mut parser = LogParser::new();
-    let events1 = parser.update("! Error\n");
+    let events1 = parser.update("! Error\nl.1 \\foo\n        \n");
     assert!(!events1.is_empty());
     let final_events = parser.finish();
     assert!(events1.len() + final_events.len() > 0);
@@ -123,7 +510,7 @@ fn test_path_interrupted_by_warning() {
 #[test]
 fn test_path_interrupted_by_error() {
     let mut parser = LogParser::new();
-    let log = "(./some/broken/\n! Undefined control sequence.\n";
+    let log = "(./some/broken/\n! Undefined control sequence.\nl.1 \\foo\n        \n";
     let events = parser.update(log);
     
     if let Some(EventPayload::FileEnter { path }) = events.first().map(|e| &e.payload) {
@@ -18,10 +18,11 @@ fn test_simple_structure() {
     }
 
     // Check Warning
-    if let EventPayload::Warning { message } = &events[1].payload {
+    if let EventPayload::UndefinedReference { message, key, .. } = &events[1].payload {
         assert!(message.contains("Reference `X`"));
+        assert_eq!(key, "X");
     } else {
-        panic!("Expected Warning");
+        panic!("Expected UndefinedReference");
     }
 
     // Check FileExit
@@ -36,7 +37,7 @@ fn test_error_line_ref() {
 
     assert_eq!(events.len(), 2);
 
-    if let EventPayload::ErrorStart { message } = &events[0].payload {
+    if let EventPayload::ErrorStart { message, .. } = &events[0].payload {
         assert_eq!(message, "Undefined control sequence.");
     } else {
         panic!("Expected ErrorStart");
@@ -76,10 +77,10 @@ fn test_streaming_update() {
     // Chunk 3: Finish warning
     events.extend(parser.update("Reference `X` undefined.\n"));
     assert_eq!(events.len(), 2);
-    if let EventPayload::Warning { message } = &events[1].payload {
+    if let EventPayload::UndefinedReference { message, .. } = &events[1].payload {
         assert_eq!(message, "LaTeX Warning: Reference `X` undefined.");
     } else {
-        panic!("Expected Warning");
+        panic!("Expected UndefinedReference");
     }
 
     // Chunk 4: Closing paren
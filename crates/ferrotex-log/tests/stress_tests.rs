@@ -29,10 +29,11 @@ LaTeX Warning: Reference `X` on page 1 undefined on input line 10.
     }
 
     // Check Warning
-    if let EventPayload::Warning { message } = &events[1].payload {
+    if let EventPayload::UndefinedReference { message, key, .. } = &events[1].payload {
         assert!(message.contains("Reference `X`"));
+        assert_eq!(key, "X");
     } else {
-        panic!("Expected Warning");
+        panic!("Expected UndefinedReference");
     }
 
     // Check FileExit
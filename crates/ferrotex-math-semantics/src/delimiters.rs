@@ -60,33 +60,33 @@ pub fn check_delimiters(root: &SyntaxNode) -> Vec<DelimiterError> {
         match ch {
             '(' | '[' | '{' => paren_stack.push((ch, idx)),
             ')' => {
-                if let Some((open, _)) = paren_stack.pop() {
-                    if open != '(' {
-                        errors.push(DelimiterError {
-                            message: format!("Mismatched delimiter: expected closing for '{}', found ')'", open),
-                            offset: idx,
-                        });
-                    }
+                if let Some((open, _)) = paren_stack.pop()
+                    && open != '('
+                {
+                    errors.push(DelimiterError {
+                        message: format!("Mismatched delimiter: expected closing for '{}', found ')'", open),
+                        offset: idx,
+                    });
                 }
             }
             ']' => {
-                if let Some((open, _)) = paren_stack.pop() {
-                    if open != '[' {
-                        errors.push(DelimiterError {
-                            message: format!("Mismatched delimiter: expected closing for '{}', found ']'", open),
-                            offset: idx,
-                        });
-                    }
+                if let Some((open, _)) = paren_stack.pop()
+                    && open != '['
+                {
+                    errors.push(DelimiterError {
+                        message: format!("Mismatched delimiter: expected closing for '{}', found ']'", open),
+                        offset: idx,
+                    });
                 }
             }
             '}' => {
-                if let Some((open, _)) = paren_stack.pop() {
-                    if open != '{' {
-                        errors.push(DelimiterError {
-                            message: format!("Mismatched delimiter: expected closing for '{}', found '}}'", open),
-                            offset: idx,
-                        });
-                    }
+                if let Some((open, _)) = paren_stack.pop()
+                    && open != '{'
+                {
+                    errors.push(DelimiterError {
+                        message: format!("Mismatched delimiter: expected closing for '{}', found '}}'", open),
+                        offset: idx,
+                    });
                 }
             }
             _ => {}
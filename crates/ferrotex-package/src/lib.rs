@@ -2,10 +2,14 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 pub mod scanner;
+pub mod tlpdb;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct PackageIndex {
     pub packages: HashMap<String, PackageMetadata>,
+    /// File name (e.g. `amsopn.sty`) -> the distribution package installing it.
+    #[serde(default)]
+    pub files: HashMap<String, DistributionPackage>,
 }
 
 impl PackageIndex {
@@ -21,6 +25,11 @@ impl PackageIndex {
         self.packages.get(name)
     }
 
+    /// Returns the distribution package that installs `file`, e.g. `amsopn.sty`.
+    pub fn package_for_file(&self, file: &str) -> Option<&DistributionPackage> {
+        self.files.get(file)
+    }
+
     /// Returns the default cache file path: ~/.cache/ferrotex/packages.json
     pub fn cache_path() -> Option<std::path::PathBuf> {
         dirs::cache_dir().map(|p| p.join("ferrotex").join("packages.json"))
@@ -40,7 +49,7 @@ impl PackageIndex {
             std::fs::create_dir_all(parent)?;
         }
         let json = serde_json::to_string_pretty(self)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+            .map_err(std::io::Error::other)?;
         std::fs::write(path, json)?;
         Ok(())
     }
@@ -116,3 +125,12 @@ pub struct PackageMetadata {
     pub commands: Vec<String>,
     pub environments: Vec<String>,
 }
+
+/// A package of the TeX distribution, as installed by its package manager.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DistributionPackage {
+    /// The package name, e.g. `amsmath`.
+    pub name: String,
+    /// The package revision, e.g. `63514`.
+    pub revision: String,
+}
@@ -75,6 +75,12 @@ impl PackageScanner {
                     }
                 }
             }
+            if let Some(db) = crate::tlpdb::locate(root) {
+                match fs::read_to_string(&db) {
+                    Ok(content) => index.files = crate::tlpdb::parse(&content),
+                    Err(e) => log::warn!("Failed to read {:?}: {}", db, e),
+                }
+            }
         } else {
              log::warn!("TeX root not found. Skipping scan.");
         }
@@ -149,6 +155,8 @@ mod tests {
         std::fs::create_dir_all(&temp_dir).unwrap();
         let sty_file = temp_dir.join("testpkg.sty");
         std::fs::write(&sty_file, r"\newcommand{\testcmd}{text}").unwrap();
+        std::fs::create_dir_all(temp_dir.join("tlpkg")).unwrap();
+        std::fs::write(temp_dir.join("tlpkg/texlive.tlpdb"), "name testpkg\nrevision 7\nrunfiles size=1\n texmf-dist/tex/latex/testpkg/testpkg.sty\n").unwrap();
 
         let mut scanner = PackageScanner::new();
         scanner.tex_root = Some(temp_dir.clone());
@@ -156,6 +164,7 @@ mod tests {
         
         assert!(index.get("testpkg").is_some());
         assert!(index.get("testpkg").unwrap().commands.contains(&"testcmd".to_string()));
+        assert_eq!(index.package_for_file("testpkg.sty").map(|p| p.revision.as_str()), Some("7"));
 
         // Cleanup
         let _ = std::fs::remove_dir_all(temp_dir);
@@ -0,0 +1,91 @@
+use crate::DistributionPackage;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Location of the TeX Live package database, relative to `TEXMFROOT`.
+const TLPDB_PATH: &str = "tlpkg/texlive.tlpdb";
+
+/// Finds the TeX Live package database above `dir`, e.g. from `texmf-dist/tex/latex`.
+pub fn locate(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().map(|d| d.join(TLPDB_PATH)).find(|p| p.is_file())
+}
+
+/// Maps every `.sty` and `.cls` run file listed in a `texlive.tlpdb` to its package.
+///
+/// The database is a list of records separated by blank lines. Each record starts with
+/// `name <package>`, has a `revision <n>` line, and lists its files indented by one
+/// space after `runfiles`, `docfiles` or `srcfiles`.
+pub fn parse(content: &str) -> HashMap<String, DistributionPackage> {
+    let mut files = HashMap::new();
+    let mut name: Option<&str> = None;
+    let mut revision: Option<&str> = None;
+    let mut run_files: Vec<&str> = Vec::new();
+    let mut in_run_files = false;
+
+    let mut flush = |name: &mut Option<&str>, revision: &mut Option<&str>, run_files: &mut Vec<&str>| {
+        if let (Some(name), Some(revision)) = (name.take(), revision.take()) {
+            for file in run_files.drain(..) {
+                let package = DistributionPackage { name: name.to_string(), revision: revision.to_string() };
+                files.insert(file.to_string(), package);
+            }
+        }
+        run_files.clear();
+    };
+
+    for line in content.lines() {
+        if let Some(path) = line.strip_prefix(' ') {
+            if in_run_files {
+                // Run files may carry attributes, e.g. ` texmf-dist/fonts/... details="..."`.
+                let path = path.split_whitespace().next().unwrap_or_default();
+                let file = path.rsplit('/').next().unwrap_or(path);
+                if file.ends_with(".sty") || file.ends_with(".cls") {
+                    run_files.push(file);
+                }
+            }
+            continue;
+        }
+        in_run_files = false;
+        match line.split_once(' ') {
+            Some(("name", value)) => {
+                flush(&mut name, &mut revision, &mut run_files);
+                name = Some(value.trim());
+            }
+            Some(("revision", value)) => revision = Some(value.trim()),
+            Some(("runfiles", _)) => in_run_files = true,
+            _ if line.is_empty() => flush(&mut name, &mut revision, &mut run_files),
+            _ => {}
+        }
+    }
+    flush(&mut name, &mut revision, &mut run_files);
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tlpdb() {
+        let content = "\
+name amsmath
+category Package
+revision 63514
+runfiles size=62
+ texmf-dist/tex/latex/amsmath/amsmath.sty
+ texmf-dist/tex/latex/amsmath/amsopn.sty
+ texmf-dist/tex/latex/amsmath/amsldoc.tex
+docfiles size=10
+ texmf-dist/doc/latex/amsmath/sample.sty
+
+name koma-script
+revision 64685
+runfiles size=900
+ texmf-dist/tex/latex/koma-script/scrartcl.cls
+";
+        let files = parse(content);
+        assert_eq!(files.len(), 3);
+        assert_eq!(files["amsopn.sty"], DistributionPackage { name: "amsmath".to_string(), revision: "63514".to_string() });
+        assert_eq!(files["scrartcl.cls"].name, "koma-script");
+        assert!(!files.contains_key("sample.sty"));
+    }
+}
@@ -39,6 +39,7 @@ use std::str::CharIndices;
 
 /// Represents a single BibTeX entry (e.g., `@article{...}`).
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BibEntry {
     /// The type of the entry (e.g., "article", "book").
     pub entry_type: String,
@@ -52,6 +53,7 @@ pub struct BibEntry {
 
 /// Represents a parsed BibTeX file.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BibFile {
     /// The list of entries found in the file.
     pub entries: Vec<BibEntry>,
@@ -74,10 +76,7 @@ pub fn parse_bibtex(input: &str) -> BibFile {
     let mut entries = Vec::new();
     let mut chars = input.char_indices().peekable();
 
-    loop {
-        let Some((start_idx, c)) = chars.next() else {
-            break;
-        };
+    while let Some((start_idx, c)) = chars.next() {
         if c == '@' && let Some(entry) = parse_entry(&mut chars, start_idx, input.len()) {
             entries.push(entry);
         }
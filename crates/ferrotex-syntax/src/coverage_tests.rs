@@ -167,13 +167,11 @@ mod coverage_tests {
 
     #[test]
     fn test_verbatim_like_environments() {
-        // Our parser is naive and parses content of verbatim like normal content
-        // unless we add specific support. This test just ensures it doesn't crash.
+        // Verbatim bodies are kept as raw tokens; content after the environment
+        // is parsed normally. This test just ensures it doesn't crash.
         let input = r"\begin{verbatim} \end{verbatim} $ % & { } ";
         let _res = parse(input);
         // It should try to parse $ % etc as tokens or comments.
-        // It might error on unmatched braces if verbatim contains them?
-        // Actually our environment parser loops until \end
     }
 
     #[test]
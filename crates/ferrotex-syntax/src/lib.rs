@@ -29,6 +29,7 @@
 //! - **[`lexer`]** - Tokenizes LaTeX source into [`SyntaxKind`] tokens
 //! - **[`parser`]** - Builds a CST using recursive descent parsing
 //! - **[`bibtex`]** - Specialized parsing for BibTeX bibliography files
+//! - **[`table`]** - Row and cell structure of `tabular`, `align` and similar environments
 //!
 //! ## Design Principles
 //!
@@ -114,6 +115,11 @@
 //! }
 //! ```
 //!
+//! ## Cargo Features
+//!
+//! - `serde`: derives `Serialize` and `Deserialize` for the [`bibtex`] types, so that
+//!   parsed bibliographies can be cached on disk.
+//!
 //! ## Rowan Integration
 //!
 //! This crate uses the [`rowan`](https://github.com/rust-analyzer/rowan) library,
@@ -129,10 +135,13 @@
 pub mod bibtex;
 pub mod lexer;
 pub mod parser;
+pub mod table;
 
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod coverage_tests;
 #[cfg(test)]
+#[allow(clippy::module_inception, clippy::assertions_on_constants)]
 mod additional_tests;
 
 pub use parser::parse;
@@ -2,6 +2,12 @@ use crate::{SyntaxKind, SyntaxNode, lexer::Lexer};
 use rowan::{GreenNode, GreenNodeBuilder, TextRange, TextSize};
 use std::iter::Peekable;
 
+/// Environments whose bodies are raw text rather than LaTeX.
+///
+/// Their content is kept as flat tokens under the [`SyntaxKind::Environment`] node: braces
+/// do not open groups and only the matching `\end{name}` closes the environment.
+pub const VERBATIM_ENVIRONMENTS: &[&str] = &["verbatim", "verbatim*", "lstlisting", "minted", "comment"];
+
 /// Represents an error encountered during parsing.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct SyntaxError {
@@ -88,7 +94,7 @@ impl<'a> Parser<'a> {
         lexer_clone.next();
         if let Some((SyntaxKind::LBrace, _)) = lexer_clone.next() {
             let mut text = String::new();
-            while let Some((kind, content)) = lexer_clone.next() {
+            for (kind, content) in lexer_clone {
                 match kind {
                     SyntaxKind::RBrace | SyntaxKind::Eof => break,
                     _ => text.push_str(content),
@@ -264,8 +270,23 @@ impl<'a> Parser<'a> {
         self.builder.start_node(SyntaxKind::Section.into());
         self.bump(); // Consume \section
 
-        // Optional: handle * for \section*?
-        // For now, simple \section{...}
+        // Starred form: \section*{...}
+        if self.peek() == SyntaxKind::Text && self.peek_text() == "*" {
+            self.bump();
+        }
+
+        // Optional short title [ ... ]
+        if self.peek() == SyntaxKind::LBracket {
+            self.bump(); // consume [
+            while self.peek() != SyntaxKind::Eof && self.peek() != SyntaxKind::RBracket {
+                self.parse_element();
+            }
+            if self.peek() == SyntaxKind::RBracket {
+                self.bump(); // consume ]
+            } else {
+                self.error("Expected ']'".into());
+            }
+        }
 
         // Expect {Title}
         if self.peek() == SyntaxKind::LBrace {
@@ -279,6 +300,17 @@ impl<'a> Parser<'a> {
         self.builder.finish_node();
     }
 
+    /// Consumes raw tokens up to (not including) `\end{name}` or the end of input.
+    fn parse_verbatim_body(&mut self, name: &str) {
+        while self.peek() != SyntaxKind::Eof {
+            let at_end = self.peek() == SyntaxKind::Command && self.peek_text() == "\\end";
+            if at_end && self.get_group_text_peek() == name {
+                return;
+            }
+            self.bump();
+        }
+    }
+
     fn parse_environment(&mut self) {
         self.builder.start_node(SyntaxKind::Environment.into());
         let begin_name = self.get_group_text_peek();
@@ -291,6 +323,10 @@ impl<'a> Parser<'a> {
             self.error("Expected '{' after \\begin".into());
         }
 
+        if VERBATIM_ENVIRONMENTS.contains(&begin_name.as_str()) {
+            self.parse_verbatim_body(&begin_name);
+        }
+
         // Parse content until \end
         loop {
             match self.peek() {
@@ -316,11 +352,9 @@ impl<'a> Parser<'a> {
                                 self.error("Expected '{' after \\end".into());
                             }
                             break;
-                        } else if *text == "\\begin" {
-                            // Nested environment
-                            self.parse_environment();
                         } else {
-                            self.bump();
+                            // Nested environments, labels, references, includes, ...
+                            self.parse_command_or_environment();
                         }
                     } else {
                         self.bump();
@@ -423,6 +457,20 @@ mod tests {
         assert_eq!(env.kind(), SyntaxKind::Environment);
     }
 
+    #[test]
+    fn test_parse_verbatim_body_is_raw() {
+        let input = "\\begin{verbatim}\nif (x) { \\begin{foo}\n\\end{verbatim}\nafter";
+        let parse = parse(input);
+        assert!(parse.errors.is_empty(), "{:?}", parse.errors);
+        let node = parse.syntax();
+        let env = node.children().next().unwrap();
+        assert_eq!(env.kind(), SyntaxKind::Environment);
+        // Only the name groups of \begin and \end are nodes; the body is flat.
+        assert_eq!(env.children().count(), 2);
+        assert!(env.children().all(|c| c.kind() == SyntaxKind::Group));
+        assert_eq!(node.to_string(), input);
+    }
+
     #[test]
     fn test_nested() {
         let input = r"\begin{a} { \begin{b} \end{b} } \end{a}";
@@ -430,6 +478,38 @@ mod tests {
         assert!(parse.errors.is_empty());
     }
 
+    #[test]
+    fn test_commands_inside_environment() {
+        let root = parse("\\begin{document}\\input{intro}\\label{a}\\ref{a}\\cite{k}\\end{document}").syntax();
+        let env = root.first_child().unwrap();
+        assert_eq!(env.kind(), SyntaxKind::Environment);
+        let kinds: Vec<_> = env.children().map(|node| node.kind()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                SyntaxKind::Group,
+                SyntaxKind::Include,
+                SyntaxKind::LabelDefinition,
+                SyntaxKind::LabelReference,
+                SyntaxKind::Citation,
+                SyntaxKind::Group,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_starred_and_short_title_sections() {
+        let parse = parse("\\begin{document}\\section*{Intro}\\section[Short]{Long title}\\end{document}");
+        assert!(parse.errors.is_empty(), "{:?}", parse.errors);
+        let sections: Vec<_> = parse
+            .syntax()
+            .descendants()
+            .filter(|node| node.kind() == SyntaxKind::Section)
+            .map(|node| node.to_string())
+            .collect();
+        assert_eq!(sections, vec!["\\section*{Intro}", "\\section[Short]{Long title}"]);
+    }
+
     #[test]
     fn test_errors() {
         let input = r"{ \cmd";
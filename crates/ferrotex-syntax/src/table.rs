@@ -0,0 +1,185 @@
+//! Cell structure of alignment environments.
+//!
+//! ## Overview
+//!
+//! Environments such as `tabular`, `align` and `matrix` split their body into rows
+//! with `\\` and into cells with `&`. The parser keeps those bodies flat, so this
+//! module recovers the row/cell structure from an [`SyntaxKind::Environment`] node.
+//!
+//! Only separators at the top level of the environment count: an `&` inside a brace
+//! group or a nested environment belongs to that inner construct, and an escaped `\&`
+//! is a [`SyntaxKind::Command`] token, not a separator.
+//!
+//! ## Examples
+//!
+//! ```
+//! use ferrotex_syntax::{parse, SyntaxKind};
+//! use ferrotex_syntax::table::table_rows;
+//!
+//! let source = r"\begin{tabular}{ll} a & b \\ c & d \end{tabular}";
+//! let root = parse(source).syntax();
+//! let env = root.descendants().find(|n| n.kind() == SyntaxKind::Environment).unwrap();
+//!
+//! let rows = table_rows(&env);
+//! assert_eq!(rows.len(), 2);
+//! assert_eq!(&source[rows[0].cells[1]], " b ");
+//! ```
+
+use crate::{SyntaxKind, SyntaxNode, TextRange, TextSize};
+use rowan::NodeOrToken;
+
+/// Commands that end a row.
+const ROW_TERMINATORS: &[&str] = &["\\\\", "\\cr", "\\tabularnewline"];
+
+/// One row of an alignment environment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableRow {
+    /// The cells between `&` separators, including surrounding whitespace.
+    ///
+    /// A row always has at least one cell.
+    pub cells: Vec<TextRange>,
+    /// The `\\` (or equivalent) ending the row. `None` for the last row of the environment.
+    pub terminator: Option<TextRange>,
+}
+
+impl TableRow {
+    /// Returns the range from the start of the first cell to the end of the last one.
+    pub fn range(&self) -> TextRange {
+        let start = self.cells[0].start();
+        let end = self.cells[self.cells.len() - 1].end();
+        TextRange::new(start, end)
+    }
+}
+
+/// Splits the body of an environment node into rows and cells.
+///
+/// The body starts after `\begin{name}` and any arguments attached to it without
+/// whitespace (such as the column specification of `tabular`), and ends at `\end`.
+/// The row after the final `\\` is always returned, even if it only holds whitespace.
+pub fn table_rows(environment: &SyntaxNode) -> Vec<TableRow> {
+    let mut elements = environment.children_with_tokens().peekable();
+
+    // Skip `\begin`, the name group and directly attached arguments.
+    let mut body_start = environment.text_range().start();
+    let mut seen_begin = false;
+    let mut in_option = false;
+    while let Some(element) = elements.peek() {
+        let kind = element.kind();
+        let attached = match kind {
+            SyntaxKind::Command if !seen_begin && element.as_token().is_some_and(|t| t.text() == "\\begin") => {
+                seen_begin = true;
+                true
+            }
+            _ if in_option => {
+                if kind == SyntaxKind::RBracket {
+                    in_option = false;
+                }
+                true
+            }
+            SyntaxKind::LBracket if seen_begin => {
+                in_option = true;
+                true
+            }
+            SyntaxKind::Group if seen_begin => true,
+            _ => false,
+        };
+        if !attached {
+            break;
+        }
+        body_start = element.text_range().end();
+        elements.next();
+    }
+
+    let mut rows = Vec::new();
+    let mut cells = Vec::new();
+    let mut cell_start = body_start;
+    let mut body_end = environment.text_range().end();
+
+    for element in elements {
+        let NodeOrToken::Token(token) = element else {
+            // Groups and nested environments are opaque.
+            continue;
+        };
+        match token.kind() {
+            SyntaxKind::Command if token.text() == "\\end" => {
+                body_end = token.text_range().start();
+                break;
+            }
+            SyntaxKind::Command if ROW_TERMINATORS.contains(&token.text()) => {
+                cells.push(TextRange::new(cell_start, token.text_range().start()));
+                rows.push(TableRow {
+                    cells: std::mem::take(&mut cells),
+                    terminator: Some(token.text_range()),
+                });
+                cell_start = token.text_range().end();
+            }
+            SyntaxKind::Text => {
+                let base = token.text_range().start();
+                for (idx, _) in token.text().match_indices('&') {
+                    let separator = base + TextSize::from(idx as u32);
+                    cells.push(TextRange::new(cell_start, separator));
+                    cell_start = separator + TextSize::from(1);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    cells.push(TextRange::new(cell_start, body_end.max(cell_start)));
+    rows.push(TableRow { cells, terminator: None });
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn rows_of(source: &str) -> Vec<Vec<String>> {
+        let root = parse(source).syntax();
+        let env = root
+            .descendants()
+            .find(|n| n.kind() == SyntaxKind::Environment)
+            .unwrap();
+        table_rows(&env)
+            .iter()
+            .map(|row| row.cells.iter().map(|c| source[*c].trim().to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_tabular_rows_and_cells() {
+        let source = "\\begin{tabular}{l|r}\n\\hline\na & b \\\\\nc & d\\\\[2pt]\n\\end{tabular}";
+        assert_eq!(
+            rows_of(source),
+            vec![
+                vec!["\\hline\na", "b"],
+                vec!["c", "d"],
+                vec!["[2pt]"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_align_without_arguments() {
+        let source = "\\begin{align}\nx &= 1 \\\\\ny &= 2\n\\end{align}";
+        assert_eq!(rows_of(source), vec![vec!["x", "= 1"], vec!["y", "= 2"]]);
+    }
+
+    #[test]
+    fn test_nested_and_escaped_ampersands_are_not_separators() {
+        let source = r"\begin{tabular}{ll} A \& B & {x & y} \end{tabular}";
+        assert_eq!(rows_of(source), vec![vec![r"A \& B", "{x & y}"]]);
+    }
+
+    #[test]
+    fn test_row_range_and_terminator() {
+        let source = r"\begin{matrix}a & b\\c & d\end{matrix}";
+        let root = parse(source).syntax();
+        let env = root.first_child().unwrap();
+        let rows = table_rows(&env);
+        assert_eq!(&source[rows[0].range()], "a & b");
+        assert_eq!(&source[rows[0].terminator.unwrap()], "\\\\");
+        assert_eq!(rows[1].terminator, None);
+    }
+}
@@ -0,0 +1,263 @@
+use crate::fmt::FormatOptions;
+use crate::position::LineIndex;
+use ferrotex_syntax::TextSize;
+use tower_lsp::lsp_types::{Position, Range, TextEdit};
+
+/// Canonical field order. Fields not listed here keep their relative order after these.
+const FIELD_ORDER: &[&str] = &[
+    "author", "editor", "translator", "title", "subtitle", "booktitle", "journal", "journaltitle",
+    "series", "volume", "number", "pages", "edition", "chapter", "publisher", "organization",
+    "institution", "school", "address", "location", "month", "year", "date", "isbn", "issn", "doi",
+    "eprint", "eprinttype", "url", "urldate", "note", "abstract", "keywords", "file",
+];
+
+/// Entry types whose bodies are not `key, field = value` lists and are copied verbatim.
+const VERBATIM_ENTRY_TYPES: &[&str] = &["string", "preamble", "comment"];
+
+/// A top-level piece of a `.bib` file.
+enum Block<'a> {
+    /// A regular entry that can be re-emitted from its parts.
+    Entry(Entry<'a>),
+    /// Anything else: comments, `@string`, entries we could not split safely.
+    Verbatim(&'a str),
+}
+
+struct Entry<'a> {
+    entry_type: String,
+    key: &'a str,
+    fields: Vec<(String, &'a str)>,
+}
+
+/// Formats a BibTeX file.
+///
+/// Every regular entry is rewritten with one field per line, fields in a canonical order
+/// and `=` signs aligned. Comments, `@string`/`@preamble`/`@comment` blocks and entries
+/// that cannot be split into fields are kept as written. With [`FormatOptions::sort_bib_entries`],
+/// runs of consecutive entries are sorted by key; entries never move across comments.
+///
+/// Returns a single edit replacing the whole document, or nothing if it is already formatted.
+pub fn format_bibtex(text: &str, line_index: &LineIndex, options: &FormatOptions) -> Vec<TextEdit> {
+    let mut blocks = split_blocks(text);
+    if options.sort_bib_entries {
+        sort_entries(&mut blocks);
+    }
+
+    let indent = options.indent(1);
+    let mut output = blocks
+        .iter()
+        .map(|block| match block {
+            Block::Entry(entry) => render_entry(entry, &indent),
+            Block::Verbatim(text) => text.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    if !output.is_empty() {
+        output.push('\n');
+    }
+
+    if output == text {
+        return Vec::new();
+    }
+    vec![TextEdit {
+        range: Range {
+            start: Position { line: 0, character: 0 },
+            end: line_index.position(TextSize::of(text)),
+        },
+        new_text: output,
+    }]
+}
+
+/// Splits the file into entries and the text between them (trimmed, empty gaps dropped).
+fn split_blocks(text: &str) -> Vec<Block<'_>> {
+    let mut blocks = Vec::new();
+    let mut gap_start = 0;
+    let mut pos = 0;
+
+    while let Some(at) = text[pos..].find('@').map(|i| pos + i) {
+        // An `@` not followed by an entry type (e.g. an e-mail address in a comment) is plain text.
+        let entry_type = text[at + 1..].split(['{', '(']).next().unwrap_or("").trim();
+        if entry_type.is_empty() || !entry_type.chars().all(|c| c.is_ascii_alphanumeric()) {
+            pos = at + 1;
+            continue;
+        }
+        let Some(end) = entry_end(text, at) else {
+            // Unclosed entry: keep the rest of the file untouched.
+            break;
+        };
+        push_gap(&mut blocks, &text[gap_start..at]);
+        let raw = &text[at..end];
+        blocks.push(parse_entry(raw).map_or(Block::Verbatim(raw), Block::Entry));
+        gap_start = end;
+        pos = end;
+    }
+
+    push_gap(&mut blocks, &text[gap_start..]);
+    blocks
+}
+
+fn push_gap<'a>(blocks: &mut Vec<Block<'a>>, gap: &'a str) {
+    let gap = gap.trim();
+    if !gap.is_empty() {
+        blocks.push(Block::Verbatim(gap));
+    }
+}
+
+/// Returns the byte offset just past the brace closing the entry that starts at `at`.
+fn entry_end(text: &str, at: usize) -> Option<usize> {
+    let open = at + text[at..].find(['{', '('])?;
+    let (open_char, close_char) = if text[open..].starts_with('{') { ('{', '}') } else { ('(', ')') };
+    let mut depth = 0;
+    for (i, c) in text[open..].char_indices() {
+        if c == open_char || (c == '{' && open_char == '(') {
+            depth += 1;
+        } else if c == close_char || (c == '}' && open_char == '(') {
+            depth -= 1;
+            if depth == 0 {
+                return Some(open + i + c.len_utf8());
+            }
+        }
+    }
+    None
+}
+
+/// Splits a brace-delimited entry into type, key and fields.
+///
+/// Returns `None` for anything the formatter should not touch.
+fn parse_entry(raw: &str) -> Option<Entry<'_>> {
+    let open = raw.find('{')?;
+    let entry_type = raw[1..open].trim().to_lowercase();
+    if entry_type.is_empty()
+        || !entry_type.chars().all(|c| c.is_ascii_alphanumeric())
+        || VERBATIM_ENTRY_TYPES.contains(&entry_type.as_str())
+        || !raw.ends_with('}')
+    {
+        return None;
+    }
+
+    let body = &raw[open + 1..raw.len() - 1];
+    let mut parts = split_top_level(body, ',')?.into_iter().map(str::trim);
+    let key = parts.next().filter(|k| !k.is_empty() && !k.contains(char::is_whitespace))?;
+
+    let mut fields = Vec::new();
+    for part in parts.filter(|p| !p.is_empty()) {
+        let (name, value) = part.split_once('=')?;
+        let name = name.trim();
+        let value = value.trim();
+        if name.is_empty() || value.is_empty() || name.contains(char::is_whitespace) || name.contains(['{', '"', '%']) {
+            return None;
+        }
+        fields.push((name.to_lowercase(), value));
+    }
+
+    Some(Entry { entry_type, key, fields })
+}
+
+/// Splits `text` at `separator` characters outside braces and quotes.
+///
+/// Returns `None` if braces are unbalanced or a `%` appears outside a value,
+/// since BibTeX tools disagree on whether that starts a comment.
+fn split_top_level(text: &str, separator: char) -> Option<Vec<&str>> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut in_quotes = false;
+    let mut start = 0;
+
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.checked_sub(1)?,
+            '"' if depth == 0 => in_quotes = !in_quotes,
+            '%' if depth == 0 && !in_quotes => return None,
+            c if c == separator && depth == 0 && !in_quotes => {
+                parts.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 || in_quotes {
+        return None;
+    }
+    parts.push(&text[start..]);
+    Some(parts)
+}
+
+fn field_rank(name: &str) -> usize {
+    FIELD_ORDER.iter().position(|f| *f == name).unwrap_or(FIELD_ORDER.len())
+}
+
+fn render_entry(entry: &Entry, indent: &str) -> String {
+    if entry.fields.is_empty() {
+        return format!("@{}{{{}}}", entry.entry_type, entry.key);
+    }
+
+    let mut fields: Vec<&(String, &str)> = entry.fields.iter().collect();
+    fields.sort_by_key(|(name, _)| field_rank(name));
+    let width = fields.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0);
+
+    let mut out = format!("@{}{{{},\n", entry.entry_type, entry.key);
+    for (i, (name, value)) in fields.iter().enumerate() {
+        let comma = if i + 1 < fields.len() { "," } else { "" };
+        out.push_str(&format!("{}{:<width$} = {}{}\n", indent, name, value, comma, width = width));
+    }
+    out.push('}');
+    out
+}
+
+/// Sorts each run of consecutive entries by key. Verbatim blocks stay where they are.
+fn sort_entries(blocks: &mut [Block]) {
+    for run in blocks.split_mut(|b| matches!(b, Block::Verbatim(_))) {
+        run.sort_by_key(|b| match b {
+            Block::Entry(entry) => entry.key.to_lowercase(),
+            Block::Verbatim(_) => String::new(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(input: &str, options: &FormatOptions) -> String {
+        match format_bibtex(input, &LineIndex::new(input), options).pop() {
+            Some(edit) => edit.new_text,
+            None => input.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_bib_format_entry() {
+        let input = "@Article{knuth84, year=1984,title = {Literate {P}rogramming},\n      author=\"Donald Knuth\", journal = cj,}";
+        let expected = "@article{knuth84,\n    author  = \"Donald Knuth\",\n    title   = {Literate {P}rogramming},\n    journal = cj,\n    year    = 1984\n}\n";
+        let options = FormatOptions::default();
+        assert_eq!(format(input, &options), expected);
+        assert!(format_bibtex(expected, &LineIndex::new(expected), &options).is_empty(), "Formatting must be idempotent");
+    }
+
+    #[test]
+    fn test_bib_format_keeps_comments_and_strings() {
+        let input = "% My library, mail me@example.com\n@string{ieee = \"IEEE\"}\n\n\n@misc{a, note = ieee # \" Press\", howpublished={x}}";
+        let expected = "% My library, mail me@example.com\n\n@string{ieee = \"IEEE\"}\n\n@misc{a,\n    note         = ieee # \" Press\",\n    howpublished = {x}\n}\n";
+        assert_eq!(format(input, &FormatOptions::default()), expected);
+    }
+
+    #[test]
+    fn test_bib_format_leaves_unsafe_entries() {
+        let input = "@misc{a,\n  title = {Unclosed\n";
+        assert_eq!(format(input, &FormatOptions::default()), "@misc{a,\n  title = {Unclosed\n");
+
+        let input = "@misc{a, % comment\n title = {T}}\n";
+        assert_eq!(format(input, &FormatOptions::default()), input);
+    }
+
+    #[test]
+    fn test_bib_sort_entries() {
+        let input = "@misc{zeta, title={Z}}\n@misc{Alpha, title={A}}\n% keep here\n@misc{beta, title={B}}\n@misc{alpha2, title={A2}}\n";
+        let options = FormatOptions { sort_bib_entries: true, tab_size: 2, ..Default::default() };
+        let expected = "@misc{Alpha,\n  title = {A}\n}\n\n@misc{zeta,\n  title = {Z}\n}\n\n% keep here\n\n@misc{alpha2,\n  title = {A2}\n}\n\n@misc{beta,\n  title = {B}\n}\n";
+        assert_eq!(format(input, &options), expected);
+
+        let unsorted = format(input, &FormatOptions::default());
+        assert!(unsorted.starts_with("@misc{zeta,"));
+    }
+}
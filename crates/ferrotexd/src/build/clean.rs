@@ -0,0 +1,120 @@
+use std::path::{Path, PathBuf};
+
+/// Auxiliary files removed by `ferrotex.clean` unless configured otherwise.
+pub const DEFAULT_EXTENSIONS: &[&str] = &[
+    "aux", "log", "out", "toc", "lof", "lot", "bbl", "bcf", "blg", "run.xml", "fls", "fdb_latexmk",
+    "synctex.gz", "synctex", "nav", "snm", "vrb", "idx", "ilg", "ind", "xdv",
+];
+
+/// Cache directories written next to a document by packages, removed by a deep clean.
+///
+/// `_minted-<stem>` is removed as well.
+pub const CACHE_DIRECTORIES: &[&str] = &["_minted", "svg-inkscape"];
+
+/// Removes the auxiliary files of the root document `root`.
+///
+/// Looks for `<stem>.<ext>` next to the document and in the `build` directory the
+/// engines write to; the latter also loses the files of `\include`d documents.
+/// `deep` also removes the output PDF, the [`CACHE_DIRECTORIES`] and the `build`
+/// directory if nothing is left in it. Returns the removed paths, sorted.
+pub fn clean(root: &Path, extensions: &[String], deep: bool) -> std::io::Result<Vec<PathBuf>> {
+    let (Some(dir), Some(stem)) = (root.parent(), root.file_stem()) else {
+        return Ok(Vec::new());
+    };
+    let stem = stem.to_string_lossy();
+    let out_dir = dir.join("build");
+
+    let mut extensions = extensions.to_vec();
+    if deep {
+        extensions.push("pdf".to_string());
+    }
+    let mut candidates: Vec<PathBuf> = extensions
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", stem, ext.trim_start_matches('.'))))
+        .collect();
+    if let Ok(entries) = std::fs::read_dir(&out_dir) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if extensions.iter().any(|ext| name.ends_with(&format!(".{}", ext.trim_start_matches('.')))) {
+                candidates.push(entry.path());
+            }
+        }
+    }
+
+    let mut removed = Vec::new();
+    for path in candidates {
+        if !path.is_file() {
+            continue;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => removed.push(path),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    if deep {
+        let minted = format!("_minted-{}", stem);
+        for name in CACHE_DIRECTORIES.iter().copied().chain([minted.as_str()]) {
+            let path = dir.join(name);
+            if path.is_dir() {
+                std::fs::remove_dir_all(&path)?;
+                removed.push(path);
+            }
+        }
+        // Fails if something else is left, e.g. files of another document.
+        if std::fs::remove_dir(&out_dir).is_ok() {
+            removed.push(out_dir);
+        }
+    }
+    removed.sort();
+    removed.dedup();
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean() {
+        let dir = tempfile::tempdir().unwrap();
+        let build = dir.path().join("build");
+        std::fs::create_dir(&build).unwrap();
+        for file in ["main.tex", "main.aux", "main.log", "notes.log", "build/main.synctex.gz", "build/chapter.aux", "build/main.pdf"] {
+            std::fs::write(dir.path().join(file), "").unwrap();
+        }
+
+        let extensions: Vec<String> = DEFAULT_EXTENSIONS.iter().map(|e| e.to_string()).collect();
+        let removed = clean(&dir.path().join("main.tex"), &extensions, false).unwrap();
+        let names: Vec<String> = removed
+            .iter()
+            .map(|p| p.strip_prefix(dir.path()).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        assert_eq!(names, ["build/chapter.aux", "build/main.synctex.gz", "main.aux", "main.log"]);
+
+        // Sources, outputs and files of other documents are kept.
+        assert!(dir.path().join("main.tex").exists());
+        assert!(dir.path().join("notes.log").exists());
+        assert!(build.join("main.pdf").exists());
+    }
+
+    #[test]
+    fn test_clean_deep() {
+        let dir = tempfile::tempdir().unwrap();
+        for sub in ["build", "_minted-main", "svg-inkscape", "figures"] {
+            std::fs::create_dir(dir.path().join(sub)).unwrap();
+        }
+        for file in ["main.tex", "main.pdf", "main.aux", "build/main.pdf", "_minted-main/code.pygtex", "figures/plot.pdf"] {
+            std::fs::write(dir.path().join(file), "").unwrap();
+        }
+
+        let extensions = vec!["aux".to_string()];
+        let removed = clean(&dir.path().join("main.tex"), &extensions, true).unwrap();
+        let names: Vec<String> = removed
+            .iter()
+            .map(|p| p.strip_prefix(dir.path()).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        assert_eq!(names, ["_minted-main", "build", "build/main.pdf", "main.aux", "main.pdf", "svg-inkscape"]);
+        assert!(dir.path().join("figures/plot.pdf").exists());
+    }
+}
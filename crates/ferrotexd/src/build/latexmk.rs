@@ -1,14 +1,29 @@
-use super::{BuildEngine, BuildLog, BuildRequest, BuildStatus};
+use super::{run_to_completion, spawn_in_group, BuildEngine, BuildRequest, BuildStatus, LiveLog};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use ferrotex_build::container::{Container, Mount};
+use ferrotex_build::BuildProfile;
 use std::process::Stdio;
-use tokio::io::AsyncBufReadExt;
 use tokio::process::Command;
 
 /// Implementation of `BuildEngine` using the `latexmk` command-line tool.
 ///
 /// Handles spawning `latexmk` with appropriate flags for PDF generation and interaction modes.
-pub struct LatexmkAdapter;
+pub struct LatexmkAdapter {
+    container: Option<Container>,
+}
+
+impl LatexmkAdapter {
+    /// Creates an adapter running latexmk on the host, or in `container`.
+    ///
+    /// latexmk's inputs are not known before it runs, so unlike the planned builds of
+    /// `ferrotex_build` the container sees the whole workspace (or the document's directory),
+    /// read-only, and can only write to the output directory. Cancelling a build stops the
+    /// container.
+    pub fn new(container: Option<Container>) -> Self {
+        Self { container }
+    }
+}
 
 #[async_trait]
 impl BuildEngine for LatexmkAdapter {
@@ -36,12 +51,41 @@ impl BuildEngine for LatexmkAdapter {
         // Ensure out_dir exists
         tokio::fs::create_dir_all(&out_dir).await?;
 
-        // latexmk -pdf -interaction=nonstopmode -halt-on-error -file-line-error -outdir=<dist> <file>
+        // latexmk -pdf <profile args> -interaction=nonstopmode -halt-on-error -file-line-error -outdir=<dist> <file>
+        // `ferrotex_log` reads both `file:line: message` and `! message` / `l.<n>` errors.
+        let mut args: Vec<String> = vec!["-pdf".to_string()];
+        args.extend(profile_args(request.profile).into_iter().map(String::from));
+        args.push(synctex_arg(request.synctex).to_string());
+        args.push("-interaction=nonstopmode".to_string());
+        args.push("-halt-on-error".to_string());
+        args.push("-file-line-error".to_string());
+        args.push(format!("-outdir={}", out_dir.to_string_lossy()));
+        args.push(file_path.to_string_lossy().into_owned());
+
+        let container_name = Container::unique_name();
+        let mut cmd = match &self.container {
+            Some(container) => {
+                let visible = request
+                    .workspace_root
+                    .as_deref()
+                    .filter(|root| file_path.starts_with(root))
+                    .unwrap_or(parent_dir);
+                let mounts = [
+                    Mount { path: visible.to_path_buf(), read_only: true },
+                    Mount { path: out_dir.clone(), read_only: false },
+                ];
+                Command::from(container.command(&container_name, "latexmk", &args, parent_dir, &mounts, &[]))
+            }
+            None => {
+                let mut cmd = Command::new("latexmk");
+                cmd.args(&args);
+                cmd
+            }
+        };
+
         // PATH Augmentation for macOS (MacTeX)
-        let mut cmd = Command::new("latexmk");
-        
         #[cfg(target_os = "macos")]
-        {
+        if self.container.is_none() {
             let current_path = std::env::var("PATH").unwrap_or_default();
             // Common MacTeX path
             let mactex_path = "/Library/TeX/texbin";
@@ -51,73 +95,79 @@ impl BuildEngine for LatexmkAdapter {
             }
         }
 
-        let mut child = cmd
-            .arg("-pdf")
-            .arg("-interaction=nonstopmode")
-            .arg("-halt-on-error")
-            .arg("-file-line-error")
-            .arg(format!("-outdir={}", out_dir.to_string_lossy()))
-            .arg(&file_path)
-            .stdout(Stdio::piped())
+        cmd.stdout(Stdio::piped())
             .stderr(Stdio::piped())
-            .current_dir(parent_dir) // Run in file's directory
-            .spawn()
-            .context("Failed to spawn latexmk. Ensure it is installed and in your PATH (e.g. /Library/TeX/texbin).")?;
-
-        let stdout = child.stdout.take().context("Failed to open stdout")?;
-        let stderr = child.stderr.take().context("Failed to open stderr")?;
+            .current_dir(parent_dir); // Run in file's directory
+        let child = spawn_in_group(&mut cmd).with_context(|| match &self.container {
+            Some(container) => format!("Failed to spawn {}. Ensure it is installed and in your PATH.", container.runtime),
+            None => "Failed to spawn latexmk. Ensure it is installed and in your PATH (e.g. /Library/TeX/texbin).".to_string(),
+        })?;
 
-        // If a callback is provided, we need to stream logs in real-time.
-        // We spawn tasks to read stdout/stderr concurrently.
-        if let Some(callback) = log_callback {
-            let cb_stdout = std::sync::Arc::new(callback);
-            let cb_stderr = cb_stdout.clone();
-
-            let mut stdout_reader = tokio::io::BufReader::new(stdout).lines();
-            let mut stderr_reader = tokio::io::BufReader::new(stderr).lines();
-
-            let stdout_handle = tokio::spawn(async move {
-                while let Ok(Some(line)) = stdout_reader.next_line().await {
-                    cb_stdout(format!("[stdout] {}\n", line));
-                }
-            });
-
-            let stderr_handle = tokio::spawn(async move {
-                while let Ok(Some(line)) = stderr_reader.next_line().await {
-                    cb_stderr(format!("[stderr] {}\n", line));
+        // Output is parsed and streamed to the callback (if any) while the engine runs;
+        // latexmk echoes the TeX log of every pass.
+        let live = LiveLog::default();
+        match run_to_completion(child, request.cancel.as_deref(), Some(live.tee(log_callback))).await? {
+            None => {
+                if let Some(container) = &self.container {
+                    // Killing the runtime's client leaves the container running.
+                    let _ = Command::from(container.kill(&container_name)).output().await;
                 }
-            });
-
-            // Wait for process to finish
-            let status = child.wait().await?;
-            
-            // Wait for IO streams to finish
-            let _ = tokio::join!(stdout_handle, stderr_handle);
-
-            if status.success() {
-                let file_stem = file_path.file_stem().unwrap_or_default();
-                let mut artifact = out_dir.join(file_stem);
-                artifact.set_extension("pdf");
-                Ok(BuildStatus::Success(artifact))
-            } else {
-                 Ok(BuildStatus::Failure(BuildLog {
-                    stdout: "See realtime logs".into(),
-                    stderr: "See realtime logs".into(),
-                }))
+                Ok(BuildStatus::Cancelled)
             }
-        } else {
-             // Buffered mode (same as before)
-             let output = child.wait_with_output().await?;
-             if output.status.success() {
+            Some((status, _)) if status.success() => {
                 let file_stem = file_path.file_stem().unwrap_or_default();
                 let mut artifact = out_dir.join(file_stem);
                 artifact.set_extension("pdf");
                 Ok(BuildStatus::Success(artifact))
-            } else {
-                let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-                let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-                Ok(BuildStatus::Failure(BuildLog { stdout, stderr }))
             }
+            Some(_) => Ok(BuildStatus::Failure(live.finish())),
         }
     }
 }
+
+/// latexmk options for `profile`.
+///
+/// latexmk insists on producing the PDF, so a draft build keeps writing it but runs
+/// a single pass without BibTeX/biber.
+pub fn profile_args(profile: BuildProfile) -> Vec<&'static str> {
+    match profile {
+        BuildProfile::Draft => vec!["-bibtex-", "-e", "$max_repeat=1"],
+        BuildProfile::Final => Vec::new(),
+    }
+}
+
+/// The latexmk option for [`BuildRequest::synctex`]; `1` writes `<stem>.synctex.gz`.
+pub fn synctex_arg(synctex: bool) -> &'static str {
+    if synctex { "-synctex=1" } else { "-synctex=0" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_args() {
+        assert!(profile_args(BuildProfile::Final).is_empty());
+        assert!(profile_args(BuildProfile::Draft).contains(&"-bibtex-"));
+        assert_eq!(synctex_arg(true), "-synctex=1");
+        assert_eq!(super::super::tectonic::profile_args(BuildProfile::Draft), ["--reruns", "0"]);
+    }
+
+    #[test]
+    fn test_live_log_parses_forwarded_lines() {
+        use ferrotex_log::ir::EventPayload;
+
+        let forwarded = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = forwarded.clone();
+        let live = LiveLog::default();
+        let callback = live.tee(Some(Box::new(move |line| sink.lock().unwrap().push(line))));
+        for line in ["(./main.tex\n", "! Undefined control sequence.\n", "l.3 \\foo\n", ")\n"] {
+            callback(format!("[stdout] {}", line));
+        }
+
+        assert_eq!(forwarded.lock().unwrap()[1], "[stdout] ! Undefined control sequence.\n");
+        let events = live.finish();
+        assert!(events.iter().any(|e| matches!(&e.payload, EventPayload::ErrorStart { message, .. } if message == "Undefined control sequence.")));
+        assert!(events.iter().any(|e| matches!(e.payload, EventPayload::ErrorLineRef { line: 3, .. })));
+    }
+}
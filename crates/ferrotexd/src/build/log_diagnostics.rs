@@ -0,0 +1,374 @@
+use ferrotex_core::package_manager::PackageManager;
+use ferrotex_log::WarningClassifier;
+use ferrotex_log::ir::{EventPayload, LogEvent, MissingFileKind};
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Command, Diagnostic, DiagnosticSeverity, NumberOrString, Position,
+    Range, Url,
+};
+
+/// Code of build errors about a missing file; `data` holds its `name` and `kind`.
+pub const MISSING_FILE_CODE: &str = "missing-file";
+/// Code of undefined reference warnings; `data` holds the `key`.
+pub const UNDEFINED_REFERENCE_CODE: &str = "undefined-reference";
+/// Code of undefined citation warnings; `data` holds the `key`.
+pub const UNDEFINED_CITATION_CODE: &str = "undefined-citation";
+
+lazy_static::lazy_static! {
+    /// `Run number 2 of rule 'pdflatex'`, printed by latexmk before every pass.
+    static ref PASS_RE: Regex = Regex::new(r"Run number (\d+) of rule '([^']+)'").unwrap();
+    /// `... on input line 12.` or `... in paragraph at lines 12--14`.
+    static ref WARNING_LINE_RE: Regex = Regex::new(r"(?:on input line|at lines?) (\d+)").unwrap();
+    /// `note: Running TeX ...`, `note: Rerunning TeX because ...`, `note: Running xdvipdfmx ...`.
+    static ref TECTONIC_PASS_RE: Regex = Regex::new(r"^note: (?:Re)?[Rr]unning (\S+)").unwrap();
+}
+
+/// Returns a progress message for a latexmk pass announcement, e.g. `pdflatex (run 2)`.
+pub fn pass_name(line: &str) -> Option<String> {
+    let caps = PASS_RE.captures(line)?;
+    Some(format!("{} (run {})", &caps[2], &caps[1]))
+}
+
+/// Returns a progress message for a tectonic pass announcement, e.g. `TeX`.
+pub fn tectonic_pass_name(line: &str) -> Option<String> {
+    let caps = TECTONIC_PASS_RE.captures(line)?;
+    Some(caps[1].trim_end_matches(['.', ',']).to_string())
+}
+
+/// Converts parsed log events into diagnostics grouped by source file.
+///
+/// Errors are [correlated](ferrotex_log::correlate) with their file and `l.<n>` line;
+/// warnings, bad boxes and font issues are attributed to the file on top of the
+/// engine's file stack when they were printed and take their line from an
+/// `on input line <n>` suffix or the first line of the box. Warnings matching a rule of
+/// [`WarningClassifier`] carry its id as their code. Relative paths are resolved
+/// against `base_dir`, the directory the engine ran in; messages outside any `.tex`
+/// file (e.g. from packages) go to `root`.
+pub fn diagnostics_from_events(events: &[LogEvent], base_dir: &Path, root: &Url) -> HashMap<Url, Vec<Diagnostic>> {
+    let mut result: HashMap<Url, Vec<Diagnostic>> = HashMap::new();
+    let mut stack: Vec<String> = Vec::new();
+    // Index of the last error in `result[uri]`, which a missing file belongs to.
+    let mut last_error: Option<(Url, usize)> = None;
+    let classifier = WarningClassifier::default();
+
+    for event in ferrotex_log::correlate(events) {
+        match event.payload {
+            EventPayload::FileEnter { path } => stack.push(path),
+            EventPayload::FileExit => {
+                stack.pop();
+            }
+            EventPayload::Error { message, file, line, .. } => {
+                // The line of another file, e.g. a package or the `.idx` of the index, is
+                // not a line of `root`; it is named in the message instead.
+                let (uri, message, line) = match file {
+                    Some(file) => match tex_file(&file, base_dir) {
+                        Some(uri) => (uri, message, line),
+                        None => match line {
+                            Some(line) => (root.clone(), format!("{}:{}: {}", file, line, message), None),
+                            None => (root.clone(), format!("{}: {}", file, message), None),
+                        },
+                    },
+                    None => (root.clone(), message, line),
+                };
+                let diagnostics = result.entry(uri.clone()).or_default();
+                diagnostics.push(diagnostic(&message, DiagnosticSeverity::ERROR, line));
+                last_error = Some((uri, diagnostics.len() - 1));
+            }
+            EventPayload::MissingFile { name, kind } => {
+                if let Some(d) = last_error.as_ref().and_then(|(uri, idx)| result.get_mut(uri)?.get_mut(*idx)) {
+                    d.code = Some(NumberOrString::String(MISSING_FILE_CODE.to_string()));
+                    d.data = Some(serde_json::json!({ "name": name, "kind": kind }));
+                }
+            }
+            EventPayload::Warning { message } | EventPayload::RerunRequired { reason: message } => {
+                let uri = current_file(&stack, base_dir).unwrap_or_else(|| root.clone());
+                let line = WARNING_LINE_RE
+                    .captures(&message)
+                    .and_then(|caps| caps[1].parse().ok());
+                result.entry(uri).or_default().push(warning(&classifier, &message, line));
+            }
+            EventPayload::UndefinedReference { message, key, line, .. } => {
+                let uri = current_file(&stack, base_dir).unwrap_or_else(|| root.clone());
+                let d = undefined_key(&message, UNDEFINED_REFERENCE_CODE, &key, line);
+                result.entry(uri).or_default().push(d);
+            }
+            EventPayload::UndefinedCitation { message, key, line, .. } => {
+                let uri = current_file(&stack, base_dir).unwrap_or_else(|| root.clone());
+                let d = undefined_key(&message, UNDEFINED_CITATION_CODE, &key, line);
+                result.entry(uri).or_default().push(d);
+            }
+            EventPayload::FontIssue { message, line, .. } => {
+                let uri = current_file(&stack, base_dir).unwrap_or_else(|| root.clone());
+                result.entry(uri).or_default().push(warning(&classifier, &message, line));
+            }
+            EventPayload::BadBox { message, start_line, .. } => {
+                let uri = current_file(&stack, base_dir).unwrap_or_else(|| root.clone());
+                result
+                    .entry(uri)
+                    .or_default()
+                    .push(diagnostic(&message, DiagnosticSeverity::WARNING, start_line));
+            }
+            _ => {}
+        }
+    }
+
+    result
+}
+
+/// The transcript makeindex or xindy wrote for the root document `root`, in the `build`
+/// directory or next to it.
+pub fn index_log_path(root: &Path) -> Option<PathBuf> {
+    let dir = root.parent()?;
+    let name = Path::new(root.file_stem()?).with_extension("ilg");
+    [dir.join("build").join(&name), dir.join(&name)].into_iter().find(|path| path.is_file())
+}
+
+/// Drops the undefined reference and citation warnings whose key `is_defined` knows.
+///
+/// `is_defined` receives the diagnostic code and the key. Such warnings come from a
+/// build that stopped before resolving them, e.g. a single draft pass, or from sources
+/// edited since.
+pub fn drop_resolved_keys(found: &mut HashMap<Url, Vec<Diagnostic>>, is_defined: impl Fn(&str, &str) -> bool) {
+    for diagnostics in found.values_mut() {
+        diagnostics.retain(|d| {
+            let Some(NumberOrString::String(code)) = &d.code else { return true };
+            if code != UNDEFINED_REFERENCE_CODE && code != UNDEFINED_CITATION_CODE {
+                return true;
+            }
+            let key = d.data.as_ref().and_then(|data| data.get("key")?.as_str());
+            !key.is_some_and(|key| is_defined(code, key))
+        });
+    }
+}
+
+/// Drops the warnings selected by an entry of `suppressed`: a rule id such as
+/// `hyperref/token-not-allowed`, or a package name for all of its warnings.
+pub fn drop_suppressed(events: &mut Vec<LogEvent>, suppressed: &[String]) {
+    if suppressed.is_empty() {
+        return;
+    }
+    let classifier = WarningClassifier::default();
+    events.retain(|event| {
+        !classifier
+            .classify_event(&event.payload)
+            .is_some_and(|class| suppressed.iter().any(|selector| class.is_selected_by(selector)))
+    });
+}
+
+/// Offers to install the distribution package providing a missing package or class.
+pub fn install_actions(diagnostics: &[Diagnostic]) -> Vec<CodeActionOrCommand> {
+    diagnostics
+        .iter()
+        .filter(|d| d.code == Some(NumberOrString::String(MISSING_FILE_CODE.to_string())))
+        .filter_map(|d| {
+            let data = d.data.as_ref()?;
+            let kind: MissingFileKind = serde_json::from_value(data.get("kind")?.clone()).ok()?;
+            if kind == MissingFileKind::Input {
+                return None;
+            }
+            let package = PackageManager::package_for_file(data.get("name")?.as_str()?);
+            let title = format!("Install package '{}'", package);
+            Some(CodeActionOrCommand::CodeAction(CodeAction {
+                title: title.clone(),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![d.clone()]),
+                command: Some(Command {
+                    title,
+                    command: "ferrotex.installPackage".to_string(),
+                    arguments: Some(vec![serde_json::json!(package)]),
+                }),
+                is_preferred: Some(true),
+                ..Default::default()
+            }))
+        })
+        .collect()
+}
+
+/// Returns the innermost `.tex` file on the stack as a URI.
+fn current_file(stack: &[String], base_dir: &Path) -> Option<Url> {
+    let path = stack.iter().rev().find(|p| p.ends_with(".tex"))?;
+    tex_file(path, base_dir)
+}
+
+/// Returns `path` as a URI if it is a `.tex` file.
+fn tex_file(path: &str, base_dir: &Path) -> Option<Url> {
+    if !path.ends_with(".tex") {
+        return None;
+    }
+    Url::from_file_path(base_dir.join(path.trim_start_matches("./"))).ok()
+}
+
+fn diagnostic(message: &str, severity: DiagnosticSeverity, line: Option<u32>) -> Diagnostic {
+    Diagnostic {
+        range: line.map_or_else(Range::default, line_range),
+        severity: Some(severity),
+        source: Some("latex".to_string()),
+        message: message.to_string(),
+        ..Default::default()
+    }
+}
+
+/// A warning, with the id of the rule it matches as its code.
+fn warning(classifier: &WarningClassifier, message: &str, line: Option<u32>) -> Diagnostic {
+    Diagnostic {
+        code: classifier.classify(message).rule.map(|rule| NumberOrString::String(rule.to_string())),
+        ..diagnostic(message, DiagnosticSeverity::WARNING, line)
+    }
+}
+
+/// A warning about an undefined label or citation `key`, tagged with `code`.
+fn undefined_key(message: &str, code: &str, key: &str, line: Option<u32>) -> Diagnostic {
+    Diagnostic {
+        code: Some(NumberOrString::String(code.to_string())),
+        data: Some(serde_json::json!({ "key": key })),
+        ..diagnostic(message, DiagnosticSeverity::WARNING, line)
+    }
+}
+
+/// Covers line `line` (1-based, as TeX reports it) up to a generous end column.
+fn line_range(line: u32) -> Range {
+    let line = line.saturating_sub(1);
+    Range {
+        start: Position { line, character: 0 },
+        end: Position { line, character: u32::MAX },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferrotex_log::LogParser;
+
+    #[test]
+    fn test_pass_name() {
+        assert_eq!(pass_name("Run number 2 of rule 'pdflatex'").as_deref(), Some("pdflatex (run 2)"));
+        assert_eq!(pass_name("Latexmk: All targets are up-to-date"), None);
+    }
+
+    #[test]
+    fn test_tectonic_output() {
+        assert_eq!(tectonic_pass_name("note: Running TeX ...").as_deref(), Some("TeX"));
+        assert_eq!(tectonic_pass_name("note: Rerunning TeX because \"main.aux\" changed ...").as_deref(), Some("TeX"));
+        assert_eq!(tectonic_pass_name("note: Running xdvipdfmx ...").as_deref(), Some("xdvipdfmx"));
+        assert_eq!(tectonic_pass_name("note: downloading foo.sty"), None);
+
+        // Tectonic's console output goes through the log parser like a TeX log.
+        let output = "note: Running TeX ...\nerror: ./chapters/intro.tex:4: Undefined control sequence\nwarning: main.tex:9: LaTeX Warning: Reference `x' on page 1 undefined\nerror: halted on potentially-recoverable error as specified\n";
+        let events = LogParser::new().with_format(ferrotex_log::LogFormat::Tectonic).parse(output);
+        let root = Url::from_file_path("/project/main.tex").unwrap();
+        let diagnostics = diagnostics_from_events(&events, Path::new("/project"), &root);
+
+        let intro = &diagnostics[&Url::from_file_path("/project/chapters/intro.tex").unwrap()];
+        assert_eq!(intro[0].severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(intro[0].message, "Undefined control sequence");
+        assert_eq!(intro[0].range.start.line, 3);
+
+        let main = &diagnostics[&root];
+        assert_eq!(main.len(), 2);
+        assert_eq!(main[0].severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(main[0].range.start.line, 8);
+        assert_eq!(main[1].message, "halted on potentially-recoverable error as specified");
+        assert_eq!(main[1].range, Range::default());
+    }
+
+    #[test]
+    fn test_diagnostics_from_events() {
+        let log = "(./main.tex\n(./chapters/intro.tex\n! Undefined control sequence.\nl.7 \\foo\n)\nLaTeX Warning: Reference `x' on page 1 undefined on input line 12.\nOverfull \\hbox (2.5pt too wide) in paragraph at lines 20--22\n)\n";
+        let events = LogParser::new().parse(log);
+        let base = Path::new("/project");
+        let root = Url::from_file_path("/project/main.tex").unwrap();
+        let diagnostics = diagnostics_from_events(&events, base, &root);
+
+        let intro = Url::from_file_path("/project/chapters/intro.tex").unwrap();
+        let errors = &diagnostics[&intro];
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(errors[0].message, "Undefined control sequence.");
+        assert_eq!(errors[0].range.start.line, 6);
+
+        let warnings = &diagnostics[&root];
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(warnings[0].range.start.line, 11);
+        assert_eq!(warnings[1].range.start.line, 19);
+    }
+
+    #[test]
+    fn test_drop_resolved_keys() {
+        let log = "(./main.tex\nLaTeX Warning: Reference `fig:new' on page 1 undefined on input line 3.\nLaTeX Warning: Citation `knuth84' on page 1 undefined on input line 4.\nLaTeX Warning: Reference `fig:gone' on page 1 undefined on input line 5.\n)\n";
+        let events = LogParser::new().parse(log);
+        let root = Url::from_file_path("/project/main.tex").unwrap();
+        let mut diagnostics = diagnostics_from_events(&events, Path::new("/project"), &root);
+        assert_eq!(diagnostics[&root][1].code, Some(NumberOrString::String(UNDEFINED_CITATION_CODE.to_string())));
+
+        drop_resolved_keys(&mut diagnostics, |code, key| match code {
+            UNDEFINED_REFERENCE_CODE => key == "fig:new",
+            _ => key == "knuth84",
+        });
+        let remaining: Vec<&str> = diagnostics[&root].iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(remaining, ["LaTeX Warning: Reference `fig:gone' on page 1 undefined on input line 5."]);
+    }
+
+    #[test]
+    fn test_suppressed_warnings() {
+        let log = "(./main.tex\nPackage hyperref Warning: Token not allowed in a PDF string (Unicode):\nPackage hyperref Warning: Draft mode on.\nLaTeX Warning: Label `a' multiply defined.\nPackage foo Warning: Something odd.\n)\n";
+        let root = Url::from_file_path("/project/main.tex").unwrap();
+        let codes = |events: &[LogEvent]| -> Vec<Option<NumberOrString>> {
+            diagnostics_from_events(events, Path::new("/project"), &root)[&root].iter().map(|d| d.code.clone()).collect()
+        };
+        let code = |rule: &str| Some(NumberOrString::String(rule.to_string()));
+
+        let mut events = LogParser::new().parse(log);
+        assert_eq!(
+            codes(&events),
+            [code("hyperref/token-not-allowed"), code("hyperref/draft-mode"), code("latex/multiply-defined-label"), None]
+        );
+        drop_suppressed(&mut events, &["hyperref/draft-mode".to_string(), "foo".to_string()]);
+        assert_eq!(codes(&events), [code("hyperref/token-not-allowed"), code("latex/multiply-defined-label")]);
+        drop_suppressed(&mut events, &["hyperref".to_string()]);
+        assert_eq!(codes(&events), [code("latex/multiply-defined-label")]);
+
+    }
+
+    #[test]
+    fn test_index_log_diagnostics() {
+        let ilg = "This is makeindex, version 2.17 [TeX Live 2024].\n!! Input index error (file = main.idx, line = 3):\n   -- Extra `@' at position 5 of first argument.\n## Warning (input = main.idx, line = 7; output = main.ind, line = 12):\n   -- Extra range closing operator ).\n";
+        let root = Url::from_file_path("/project/main.tex").unwrap();
+        let found = diagnostics_from_events(&LogParser::new().parse(ilg), Path::new("/project"), &root);
+        let diagnostics = &found[&root];
+        assert_eq!(diagnostics.len(), 2);
+        // Lines of the `.idx` are not lines of the document.
+        assert_eq!(diagnostics[0].message, "main.idx:3: Input index error: Extra `@' at position 5 of first argument.");
+        assert_eq!(diagnostics[0].range, Range::default());
+        assert_eq!(diagnostics[1].severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(diagnostics[1].range, Range::default());
+
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("main.tex");
+        assert_eq!(index_log_path(&main), None);
+        std::fs::create_dir(dir.path().join("build")).unwrap();
+        std::fs::write(dir.path().join("build/main.ilg"), ilg).unwrap();
+        assert_eq!(index_log_path(&main), Some(dir.path().join("build/main.ilg")));
+    }
+
+    #[test]
+    fn test_missing_package_quick_fix() {
+        let log = "(./main.tex\n! LaTeX Error: File `tikz.sty' not found.\nl.3 \\usepackage\n! I can't find file `intro'.\n)\n";
+        let events = LogParser::new().parse(log);
+        let root = Url::from_file_path("/project/main.tex").unwrap();
+        let diagnostics = diagnostics_from_events(&events, Path::new("/project"), &root);
+        let errors = &diagnostics[&root];
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].code, Some(NumberOrString::String(MISSING_FILE_CODE.to_string())));
+        assert_eq!(errors[0].range.start.line, 2);
+
+        // No package provides an `\input` document.
+        let actions = install_actions(errors);
+        assert_eq!(actions.len(), 1);
+        let CodeActionOrCommand::CodeAction(action) = &actions[0] else { panic!("expected a code action") };
+        assert_eq!(action.title, "Install package 'pgf'");
+        assert_eq!(action.command.as_ref().unwrap().arguments, Some(vec![serde_json::json!("pgf")]));
+    }
+}
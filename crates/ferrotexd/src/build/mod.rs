@@ -2,6 +2,14 @@
 
 use anyhow::Result;
 use async_trait::async_trait;
+use ferrotex_build::BuildProfile;
+use ferrotex_log::ir::LogEvent;
+use ferrotex_log::LogParser;
+use std::process::ExitStatus;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::Notify;
 
 /// Search query parameters for a build request.
 #[derive(Debug, Clone)]
@@ -10,6 +18,12 @@ pub struct BuildRequest {
     pub document_uri: tower_lsp::lsp_types::Url,
     /// The root directory of the workspace (optional).
     pub workspace_root: Option<std::path::PathBuf>,
+    /// Notified to abort the build; the engine then kills its process group.
+    pub cancel: Option<Arc<Notify>>,
+    /// Selects the passes and outputs of the build (see [`BuildProfile`]).
+    pub profile: BuildProfile,
+    /// Writes gzipped SyncTeX data next to the PDF, whatever the profile.
+    pub synctex: bool,
 }
 
 /// Start/End logs from a build execution.
@@ -24,8 +38,22 @@ pub struct BuildLog {
 pub enum BuildStatus {
     /// Build succeeded, producing an artifact at the given path.
     Success(std::path::PathBuf), 
-    /// Build failed, with captured logs.
-    Failure(BuildLog),
+    /// Build failed, with the errors, warnings and files of the engine's log.
+    Failure(Vec<LogEvent>),
+    /// Build was aborted through [`BuildRequest::cancel`] before it finished.
+    Cancelled,
+}
+
+/// Receives engine output lines as they are printed.
+pub type LogCallback = Box<dyn Fn(String) + Send + Sync>;
+
+/// How an engine reports errors, warnings and passes on its console.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The TeX terminal output, as written to the `.log` file (latexmk, pdflatex, ...).
+    TexLog,
+    /// Tectonic's `error: file.tex:12: message` and `note: Running TeX ...` lines.
+    Tectonic,
 }
 
 #[async_trait]
@@ -33,17 +61,185 @@ pub trait BuildEngine: Send + Sync {
     /// uniquely identifies the engine (e.g. "latexmk", "tectonic")
     fn name(&self) -> &str;
 
+    /// The format of the lines passed to the `log_callback` of [`BuildEngine::build`].
+    fn output_format(&self) -> OutputFormat {
+        OutputFormat::TexLog
+    }
+
     /// Execute the build for the given request.
     ///
     /// `log_callback` is an optional function that receives stdout/stderr lines in real-time.
     async fn build(
         &self,
         request: &BuildRequest,
-        log_callback: Option<Box<dyn Fn(String) + Send + Sync>>,
+        log_callback: Option<LogCallback>,
     ) -> Result<BuildStatus>;
 }
 
+/// Parses engine output with [`LogParser`] while it is printed.
+///
+/// Clones share the parser, so the callback returned by [`LiveLog::tee`] can be handed
+/// to [`run_to_completion`] and the events collected once the engine exits.
+#[derive(Clone, Default)]
+pub(crate) struct LiveLog {
+    state: Arc<Mutex<(LogParser, Vec<LogEvent>)>>,
+}
+
+impl LiveLog {
+    /// Returns a callback parsing every line before passing it on to `log_callback`.
+    pub(crate) fn tee(&self, log_callback: Option<LogCallback>) -> LogCallback {
+        let live = self.clone();
+        Box::new(move |line: String| {
+            let text = line
+                .strip_prefix("[stdout] ")
+                .or_else(|| line.strip_prefix("[stderr] "))
+                .unwrap_or(&line);
+            let mut state = live.state.lock().unwrap();
+            let events = state.0.update(text);
+            state.1.extend(events);
+            drop(state);
+            if let Some(callback) = &log_callback {
+                callback(line);
+            }
+        })
+    }
+
+    /// Flushes the parser and returns every event seen.
+    pub(crate) fn finish(&self) -> Vec<LogEvent> {
+        let mut state = self.state.lock().unwrap();
+        let (parser, mut events) = std::mem::take(&mut *state);
+        events.extend(parser.finish());
+        events
+    }
+}
+
+/// Starts the engine in a process group of its own, so that cancelling a build also
+/// stops the passes it spawned (pdflatex, biber, ...).
+pub(crate) fn spawn_in_group(cmd: &mut Command) -> std::io::Result<Child> {
+    #[cfg(unix)]
+    cmd.process_group(0);
+    cmd.kill_on_drop(true).spawn()
+}
+
+/// Waits for `child` to exit, or kills its process group once `cancel` is notified.
+///
+/// Returns `None` if the build was cancelled.
+pub(crate) async fn wait_or_cancel(child: &mut Child, cancel: Option<&Notify>) -> Result<Option<ExitStatus>> {
+    let Some(cancel) = cancel else {
+        return Ok(Some(child.wait().await?));
+    };
+    tokio::select! {
+        status = child.wait() => Ok(Some(status?)),
+        _ = cancel.notified() => {
+            kill_group(child);
+            let _ = child.kill().await;
+            Ok(None)
+        }
+    }
+}
+
+/// Collects the output of an engine started with piped stdout and stderr until it exits.
+///
+/// Each line is also passed to `log_callback` as it arrives, prefixed with `[stdout] ` or
+/// `[stderr] `. Returns `None` if the build was cancelled.
+pub(crate) async fn run_to_completion(
+    mut child: Child,
+    cancel: Option<&Notify>,
+    log_callback: Option<LogCallback>,
+) -> Result<Option<(ExitStatus, BuildLog)>> {
+    let callback = log_callback.map(Arc::new);
+    let stdout = child.stdout.take().map(|out| tokio::spawn(collect_lines(out, "[stdout]", callback.clone())));
+    let stderr = child.stderr.take().map(|err| tokio::spawn(collect_lines(err, "[stderr]", callback)));
+
+    let status = wait_or_cancel(&mut child, cancel).await?;
+
+    let mut log = BuildLog { stdout: String::new(), stderr: String::new() };
+    if let Some(handle) = stdout {
+        log.stdout = handle.await.unwrap_or_default();
+    }
+    if let Some(handle) = stderr {
+        log.stderr = handle.await.unwrap_or_default();
+    }
+    Ok(status.map(|status| (status, log)))
+}
+
+async fn collect_lines(
+    stream: impl AsyncRead + Unpin,
+    prefix: &'static str,
+    callback: Option<Arc<LogCallback>>,
+) -> String {
+    let mut lines = BufReader::new(stream).lines();
+    let mut acc = String::new();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if let Some(callback) = &callback {
+            callback(format!("{} {}\n", prefix, line));
+        }
+        acc.push_str(&line);
+        acc.push('\n');
+    }
+    acc
+}
+
+#[cfg(unix)]
+fn kill_group(child: &Child) {
+    if let Some(pid) = child.id() {
+        // SAFETY: `kill` has no memory-safety preconditions; a negative pid addresses the group.
+        unsafe {
+            libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_group(_child: &Child) {}
+
+pub mod clean;
 pub mod latexmk;
-#[cfg(feature = "use-tectonic")]
+pub mod log_diagnostics;
 pub mod tectonic;
 
+use crate::settings::{BuildEngineKind, BuildSettings};
+
+/// Returns the build engine for the configured engine kind.
+///
+/// `Auto` prefers `latexmk` and falls back to `tectonic` when only the latter is installed,
+/// so that users without a TeX distribution can still build. A configured container
+/// runs `latexmk` from its image, so the host needs neither.
+pub fn engine_for(settings: &BuildSettings) -> Box<dyn BuildEngine> {
+    let tectonic = tectonic::TectonicAdapter::new(settings.tectonic_path.as_deref());
+    let container = settings.container.container();
+    match settings.engine {
+        BuildEngineKind::Tectonic => Box::new(tectonic),
+        BuildEngineKind::Auto
+            if container.is_none() && which::which("latexmk").is_err() && which::which(tectonic.executable()).is_ok() =>
+        {
+            Box::new(tectonic)
+        }
+        _ => Box::new(latexmk::LatexmkAdapter::new(container)),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[tokio::test]
+    async fn test_cancel_kills_process_group() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("grandchild-survived");
+        // The grandchild would create the marker if it outlived the cancellation.
+        let script = format!("(sleep 1; touch '{}') & wait", marker.display());
+        let mut child = spawn_in_group(Command::new("sh").arg("-c").arg(script)).unwrap();
+
+        let cancel = Arc::new(Notify::new());
+        cancel.notify_one();
+        let start = Instant::now();
+        let status = wait_or_cancel(&mut child, Some(&cancel)).await.unwrap();
+        assert!(status.is_none());
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(!marker.exists());
+    }
+}
@@ -1,12 +1,31 @@
-use super::{BuildEngine, BuildLog, BuildRequest, BuildStatus};
+use super::{run_to_completion, spawn_in_group, BuildEngine, BuildRequest, BuildStatus, OutputFormat};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use ferrotex_build::BuildProfile;
 use std::process::Stdio;
-use tokio::io::AsyncBufReadExt;
 use tokio::process::Command;
 
 /// Implementation of `BuildEngine` using the `tectonic` command-line tool.
-pub struct TectonicAdapter;
+///
+/// Tectonic fetches missing packages on demand and reruns TeX by itself, so it works
+/// without a local TeX distribution. Unlike latexmk it does not echo the TeX log:
+/// errors and warnings are printed as `error: file.tex:12: message` (see [`OutputFormat::Tectonic`]).
+pub struct TectonicAdapter {
+    executable: String,
+}
+
+impl TectonicAdapter {
+    /// Creates an adapter running `executable`, or `tectonic` from `PATH` when `None`.
+    pub fn new(executable: Option<&str>) -> Self {
+        Self {
+            executable: executable.filter(|e| !e.is_empty()).unwrap_or("tectonic").to_string(),
+        }
+    }
+
+    pub fn executable(&self) -> &str {
+        &self.executable
+    }
+}
 
 #[async_trait]
 impl BuildEngine for TectonicAdapter {
@@ -14,6 +33,10 @@ impl BuildEngine for TectonicAdapter {
         "tectonic"
     }
 
+    fn output_format(&self) -> OutputFormat {
+        OutputFormat::Tectonic
+    }
+
     async fn build(
         &self,
         request: &BuildRequest,
@@ -27,83 +50,47 @@ impl BuildEngine for TectonicAdapter {
         let parent_dir = file_path
             .parent()
             .unwrap_or_else(|| std::path::Path::new("."));
-        
-        // Tectonic auto-downloads packages, so we just run it on the file.
-        // tectonic -outdir <build> <file>
-        // Note: Tectonic default interface is chatty, we want to capture stdout/stderr.
-        
+
+        // tectonic --outdir <build> --keep-logs <profile args> <file>
+        // The kept .log and .synctex.gz files match what latexmk leaves behind.
         let out_dir = parent_dir.join("build");
         tokio::fs::create_dir_all(&out_dir).await?;
 
-        let mut child = Command::new("tectonic")
-            .arg("-o")
+        let mut cmd = Command::new(&self.executable);
+        cmd.arg("--outdir")
             .arg(&out_dir)
+            .arg("--keep-logs")
+            .args(profile_args(request.profile))
+            .args(request.synctex.then_some("--synctex"))
             .arg(&file_path)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
-            .current_dir(parent_dir)
-            .spawn()
-            .context("Failed to spawn tectonic. Ensure it is installed and in your PATH.")?;
-
-        let stdout = child.stdout.take().context("Failed to open stdout")?;
-        let stderr = child.stderr.take().context("Failed to open stderr")?;
+            .current_dir(parent_dir);
+        let child = spawn_in_group(&mut cmd)
+            .with_context(|| format!("Failed to spawn {}. Ensure it is installed and in your PATH.", self.executable))?;
 
-        if let Some(callback) = log_callback {
-             let cb_stdout = std::sync::Arc::new(callback);
-             let cb_stderr = cb_stdout.clone();
-
-             let mut stdout_reader = tokio::io::BufReader::new(stdout).lines();
-             let mut stderr_reader = tokio::io::BufReader::new(stderr).lines();
-
-             let stdout_handle = tokio::spawn(async move {
-                 let mut acc = String::new();
-                 while let Ok(Some(line)) = stdout_reader.next_line().await {
-                     cb_stdout(format!("[stdout] {}\n", line));
-                     acc.push_str(&line);
-                     acc.push('\n');
-                 }
-                 acc
-             });
-
-             let stderr_handle = tokio::spawn(async move {
-                 let mut acc = String::new();
-                 while let Ok(Some(line)) = stderr_reader.next_line().await {
-                     cb_stderr(format!("[stderr] {}\n", line));
-                     acc.push_str(&line);
-                     acc.push('\n');
-                 }
-                 acc
-             });
-
-             let status = child.wait().await?;
-             let (stdout_res, stderr_res) = tokio::join!(stdout_handle, stderr_handle);
-             let stdout = stdout_res.unwrap_or_default();
-             let stderr = stderr_res.unwrap_or_default();
-
-             if status.success() {
-                 let file_stem = file_path.file_stem().unwrap_or_default();
-                 let mut artifact = out_dir.join(file_stem);
-                 artifact.set_extension("pdf");
-                 Ok(BuildStatus::Success(artifact))
-             } else {
-                 Ok(BuildStatus::Failure(BuildLog {
-                     stdout,
-                     stderr,
-                 }))
-             }
-
-        } else {
-            let output = child.wait_with_output().await?;
-            if output.status.success() {
-                 let file_stem = file_path.file_stem().unwrap_or_default();
-                 let mut artifact = out_dir.join(file_stem);
-                 artifact.set_extension("pdf");
-                 Ok(BuildStatus::Success(artifact))
-            } else {
-                 let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-                 let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-                 Ok(BuildStatus::Failure(BuildLog { stdout, stderr }))
+        match run_to_completion(child, request.cancel.as_deref(), log_callback).await? {
+            None => Ok(BuildStatus::Cancelled),
+            Some((status, _)) if status.success() => {
+                let file_stem = file_path.file_stem().unwrap_or_default();
+                let mut artifact = out_dir.join(file_stem);
+                artifact.set_extension("pdf");
+                Ok(BuildStatus::Success(artifact))
+            }
+            Some(_) => {
+                // Tectonic prints its own messages rather than the TeX log; parse the kept one.
+                let log_path = out_dir.join(file_path.file_stem().unwrap_or_default()).with_extension("log");
+                let content = tokio::fs::read(&log_path).await.unwrap_or_default();
+                Ok(BuildStatus::Failure(ferrotex_log::LogParser::new().parse(&String::from_utf8_lossy(&content))))
             }
         }
     }
 }
+
+/// Tectonic options for `profile`: a draft build runs TeX once.
+pub fn profile_args(profile: BuildProfile) -> Vec<&'static str> {
+    match profile {
+        BuildProfile::Draft => vec!["--reruns", "0"],
+        BuildProfile::Final => Vec::new(),
+    }
+}
@@ -1,7 +1,25 @@
-use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind};
-use std::collections::HashMap;
+use crate::workspace::MacroDef;
+use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind, InsertTextFormat};
+use std::collections::{HashMap, HashSet};
 use ferrotex_package::PackageIndex;
 
+/// Upper bound on the items of one completion response; the list is marked incomplete
+/// beyond it so that the client asks again as the user keeps typing.
+pub const MAX_COMPLETION_ITEMS: usize = 500;
+
+/// Where a completion item comes from, in order of relevance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CompletionSource {
+    /// Defined in the project with `\newcommand` or `\newenvironment`.
+    Document,
+    /// Provided by a package the document loads.
+    LoadedPackage,
+    /// Part of the LaTeX kernel.
+    Standard,
+    /// Provided by an indexed package the document does not load.
+    OtherPackage,
+}
+
 /// Represents the completion data available for a specific LaTeX package.
 #[derive(Debug, Clone)]
 pub struct PackageCompletion {
@@ -89,6 +107,107 @@ pub fn get_package_completions(
     (cmd_items, env_items)
 }
 
+/// Returns (commands, environments) completion items for the LaTeX kernel.
+pub fn get_standard_completions() -> (Vec<CompletionItem>, Vec<CompletionItem>) {
+    let commands = crate::COMMANDS
+        .iter()
+        .map(|cmd| CompletionItem {
+            label: format!("\\{}", cmd),
+            kind: Some(CompletionItemKind::FUNCTION),
+            detail: Some("LaTeX".to_string()),
+            ..Default::default()
+        })
+        .collect();
+    let environments = crate::ENVIRONMENTS
+        .iter()
+        .map(|env| CompletionItem {
+            label: env.to_string(),
+            kind: Some(CompletionItemKind::SNIPPET),
+            detail: Some("LaTeX".to_string()),
+            ..Default::default()
+        })
+        .collect();
+    (commands, environments)
+}
+
+/// Returns (commands, environments) completion items for indexed packages not in `loaded`.
+pub fn get_other_package_completions(
+    loaded: &[String],
+    index: &PackageIndex,
+) -> (Vec<CompletionItem>, Vec<CompletionItem>) {
+    let mut names: Vec<&String> = index.packages.keys().filter(|name| !loaded.contains(name)).collect();
+    names.sort();
+    let mut cmd_items = Vec::new();
+    let mut env_items = Vec::new();
+    for name in names {
+        let data = &index.packages[name];
+        add_items(&mut cmd_items, &mut env_items, name, &data.commands, &data.environments);
+    }
+    (cmd_items, env_items)
+}
+
+/// Returns the word being completed at the end of `line`, including a leading backslash.
+pub fn completion_prefix(line: &str) -> &str {
+    let word_start = line
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_ascii_alphabetic() || matches!(c, '@' | '*'))
+        .last()
+        .map_or(line.len(), |(i, _)| i);
+    match line[..word_start].strip_suffix('\\') {
+        Some(rest) => &line[rest.len()..],
+        None => &line[word_start..],
+    }
+}
+
+/// Filters items by `prefix` and ranks them by source, then by match quality.
+///
+/// An item matches if the prefix is a prefix of its name or, failing that, a
+/// case-insensitive subsequence of it; backslashes are ignored on both sides. Items
+/// with the same label are kept once, from the most relevant source. `sort_text`
+/// encodes the ranking. Returns whether the list was truncated to [`MAX_COMPLETION_ITEMS`].
+pub fn rank_completions(
+    mut items: Vec<(CompletionSource, CompletionItem)>,
+    prefix: &str,
+) -> (Vec<CompletionItem>, bool) {
+    let needle = prefix.trim_start_matches('\\');
+    items.sort_by_key(|(source, _)| *source);
+
+    let mut seen = HashSet::new();
+    let mut ranked: Vec<(String, CompletionItem)> = Vec::new();
+    for (source, mut item) in items {
+        let name = item.filter_text.as_deref().unwrap_or(&item.label).trim_start_matches('\\');
+        let Some(quality) = match_quality(name, needle) else {
+            continue;
+        };
+        if !seen.insert(item.label.clone()) {
+            continue;
+        }
+        let key = format!("{}{}{}", source as u8, quality, item.label.trim_start_matches('\\').to_lowercase());
+        item.sort_text = Some(key.clone());
+        ranked.push((key, item));
+    }
+
+    ranked.sort_by(|a, b| a.0.cmp(&b.0));
+    let truncated = ranked.len() > MAX_COMPLETION_ITEMS;
+    ranked.truncate(MAX_COMPLETION_ITEMS);
+    (ranked.into_iter().map(|(_, item)| item).collect(), truncated)
+}
+
+/// 0 for a prefix match, 1 for a case-insensitive prefix match, 2 for a fuzzy match.
+fn match_quality(name: &str, needle: &str) -> Option<u8> {
+    if name.starts_with(needle) {
+        return Some(0);
+    }
+    let name = name.to_lowercase();
+    let needle = needle.to_lowercase();
+    if name.starts_with(&needle) {
+        return Some(1);
+    }
+    let mut chars = name.chars();
+    needle.chars().all(|c| chars.any(|n| n == c)).then_some(2)
+}
+
 fn add_items(
     cmd_items: &mut Vec<CompletionItem>,
     env_items: &mut Vec<CompletionItem>,
@@ -114,6 +233,63 @@ fn add_items(
     }
 }
 
+/// Returns completion items for commands and environments defined in the project.
+///
+/// Parameters become snippet placeholders; an optional first parameter is rendered in
+/// brackets with its default as placeholder text.
+pub fn get_user_completions(commands: &[MacroDef], environments: &[MacroDef]) -> Vec<CompletionItem> {
+    let mut items = Vec::new();
+    for def in commands {
+        let snippet = format!("\\\\{}{}", escape_snippet(&def.name), parameter_placeholders(def));
+        items.push(CompletionItem {
+            label: format!("\\{}", def.name),
+            kind: Some(CompletionItemKind::FUNCTION),
+            detail: Some(describe("command", def)),
+            insert_text: (def.parameters > 0).then_some(snippet),
+            insert_text_format: (def.parameters > 0).then_some(InsertTextFormat::SNIPPET),
+            ..Default::default()
+        });
+    }
+    for def in environments {
+        let name = escape_snippet(&def.name);
+        items.push(CompletionItem {
+            label: def.name.clone(),
+            kind: Some(CompletionItemKind::SNIPPET),
+            detail: Some(describe("environment", def)),
+            filter_text: Some(format!("\\begin{{{}}}", def.name)),
+            insert_text: Some(format!(
+                "\\\\begin{{{name}}}{}\n\t$0\n\\\\end{{{name}}}",
+                parameter_placeholders(def)
+            )),
+            insert_text_format: Some(InsertTextFormat::SNIPPET),
+            ..Default::default()
+        });
+    }
+    items
+}
+
+fn parameter_placeholders(def: &MacroDef) -> String {
+    (1..=def.parameters)
+        .map(|i| match &def.default {
+            Some(default) if i == 1 => format!("[${{1:{}}}]", escape_snippet(default)),
+            _ => format!("{{${{{}}}}}", i),
+        })
+        .collect()
+}
+
+fn describe(what: &str, def: &MacroDef) -> String {
+    match def.parameters {
+        0 => format!("User-defined {}", what),
+        1 => format!("User-defined {} (1 parameter)", what),
+        n => format!("User-defined {} ({} parameters)", what, n),
+    }
+}
+
+/// Escapes the characters with a meaning in LSP snippets.
+fn escape_snippet(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('$', "\\$").replace('}', "\\}")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -171,4 +347,63 @@ mod tests {
         // but for coverage it doesn't matter.
         assert!(text_count >= 1);
     }
+
+    #[test]
+    fn test_get_user_completions() {
+        let def = |name: &str, parameters, default: Option<&str>| MacroDef {
+            name: name.to_string(),
+            parameters,
+            default: default.map(str::to_string),
+            range: Default::default(),
+        };
+        let items = get_user_completions(
+            &[def("R", 0, None), def("norm", 2, Some("2"))],
+            &[def("proofsketch", 1, None)],
+        );
+
+        assert_eq!(items[0].label, "\\R");
+        assert_eq!(items[0].insert_text, None);
+        assert_eq!(items[1].label, "\\norm");
+        assert_eq!(items[1].insert_text.as_deref(), Some("\\\\norm[${1:2}]{${2}}"));
+        assert_eq!(items[1].detail.as_deref(), Some("User-defined command (2 parameters)"));
+        assert_eq!(items[2].label, "proofsketch");
+        assert_eq!(
+            items[2].insert_text.as_deref(),
+            Some("\\\\begin{proofsketch}{${1}}\n\t$0\n\\\\end{proofsketch}")
+        );
+    }
+
+    #[test]
+    fn test_completion_prefix() {
+        assert_eq!(completion_prefix("text \\sec"), "\\sec");
+        assert_eq!(completion_prefix("\\begin{ite"), "ite");
+        assert_eq!(completion_prefix("\\"), "\\");
+        assert_eq!(completion_prefix("word "), "");
+    }
+
+    #[test]
+    fn test_rank_completions() {
+        let item = |label: &str| CompletionItem { label: label.to_string(), ..Default::default() };
+        let items = vec![
+            (CompletionSource::OtherPackage, item("\\sectionmark")),
+            (CompletionSource::Standard, item("\\section")),
+            (CompletionSource::Standard, item("\\subsection")),
+            (CompletionSource::Standard, item("\\label")),
+            (CompletionSource::LoadedPackage, item("\\Section")),
+            (CompletionSource::Document, item("\\secref")),
+            (CompletionSource::OtherPackage, item("\\section")),
+        ];
+        let (ranked, truncated) = rank_completions(items, "\\sec");
+        let labels: Vec<&str> = ranked.iter().map(|i| i.label.as_str()).collect();
+        assert_eq!(labels, ["\\secref", "\\Section", "\\section", "\\subsection", "\\sectionmark"]);
+        assert!(!truncated);
+        assert!(ranked.windows(2).all(|w| w[0].sort_text < w[1].sort_text));
+
+        let many: Vec<_> = (0..MAX_COMPLETION_ITEMS + 1)
+            .map(|i| (CompletionSource::OtherPackage, item(&format!("\\cmd{}", i))))
+            .collect();
+        let (ranked, truncated) = rank_completions(many, "");
+        assert_eq!(ranked.len(), MAX_COMPLETION_ITEMS);
+        assert!(truncated);
+    }
 }
@@ -0,0 +1,175 @@
+use serde::Serialize;
+use std::fmt::Write;
+use tower_lsp::lsp_types::Url;
+
+/// The kind of file a node of the [`DependencyGraph`] stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    Tex,
+    Bibliography,
+    Graphics,
+}
+
+/// How a file refers to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeKind {
+    /// `\input` or `\include`.
+    Include,
+    /// `\bibliography` or `\addbibresource`.
+    Bibliography,
+    /// `\includegraphics`.
+    Graphics,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphNode {
+    pub uri: Url,
+    pub kind: NodeKind,
+    /// Whether the file is indexed (TeX, BibTeX) or present on disk (graphics).
+    pub exists: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphEdge {
+    pub from: Url,
+    pub to: Url,
+    pub kind: EdgeKind,
+}
+
+/// The files of a project and the references between them, built by
+/// [`crate::workspace::Workspace::dependency_graph`].
+///
+/// Serializes to JSON as `{ "nodes": [...], "edges": [...] }`; [`DependencyGraph::to_dot`]
+/// renders it for Graphviz.
+#[derive(Debug, Default, Serialize)]
+pub struct DependencyGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl DependencyGraph {
+    /// Adds a node unless one for `uri` exists already.
+    pub fn add_node(&mut self, uri: &Url, kind: NodeKind, exists: bool) {
+        if !self.nodes.iter().any(|n| &n.uri == uri) {
+            self.nodes.push(GraphNode { uri: uri.clone(), kind, exists });
+        }
+    }
+
+    pub fn add_edge(&mut self, from: &Url, to: &Url, kind: EdgeKind) {
+        let edge = GraphEdge { from: from.clone(), to: to.clone(), kind };
+        if !self.edges.contains(&edge) {
+            self.edges.push(edge);
+        }
+    }
+
+    /// Renders the graph in the Graphviz DOT language.
+    ///
+    /// Nodes are labelled with their file name; missing files are drawn dashed.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph dependencies {\n    rankdir=LR;\n");
+        for node in &self.nodes {
+            let shape = match node.kind {
+                NodeKind::Tex => "box",
+                NodeKind::Bibliography => "note",
+                NodeKind::Graphics => "component",
+            };
+            let style = if node.exists { "solid" } else { "dashed" };
+            let _ = writeln!(
+                dot,
+                "    \"{}\" [label=\"{}\", shape={}, style={}];",
+                escape(node.uri.as_str()),
+                escape(file_name(&node.uri)),
+                shape,
+                style
+            );
+        }
+        for edge in &self.edges {
+            let _ = writeln!(
+                dot,
+                "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                escape(edge.from.as_str()),
+                escape(edge.to.as_str()),
+                match edge.kind {
+                    EdgeKind::Include => "include",
+                    EdgeKind::Bibliography => "bibliography",
+                    EdgeKind::Graphics => "graphics",
+                }
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn file_name(uri: &Url) -> &str {
+    uri.path_segments().and_then(|mut s| s.next_back()).unwrap_or(uri.as_str())
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::workspace::Workspace;
+    use super::*;
+
+    #[test]
+    fn test_dependency_graph() {
+        let workspace = Workspace::new();
+        let main = Url::parse("file:///project/main.tex").unwrap();
+        let intro = Url::parse("file:///project/chapters/intro.tex").unwrap();
+        let unrelated = Url::parse("file:///project/notes.tex").unwrap();
+        workspace.update(&main, "\\input{chapters/intro}\n\\bibliography{refs}\n\\includegraphics[width=2cm]{logo.png}");
+        workspace.update(&intro, "\\input{missing}");
+        workspace.update(&unrelated, "\\input{main}");
+
+        let graph = workspace.dependency_graph(Some(&main));
+        let nodes: Vec<(&str, NodeKind, bool)> = graph.nodes.iter().map(|n| (n.uri.path(), n.kind, n.exists)).collect();
+        assert_eq!(
+            nodes,
+            [
+                ("/project/main.tex", NodeKind::Tex, true),
+                ("/project/refs.bib", NodeKind::Bibliography, false),
+                ("/project/logo.png", NodeKind::Graphics, false),
+                ("/project/chapters/intro.tex", NodeKind::Tex, true),
+                ("/project/missing.tex", NodeKind::Tex, false),
+            ]
+        );
+        assert_eq!(graph.edges.len(), 4);
+        assert_eq!(graph.edges[0].kind, EdgeKind::Include);
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("\"file:///project/main.tex\" [label=\"main.tex\", shape=box, style=solid];"));
+        assert!(dot.contains("\"file:///project/main.tex\" -> \"file:///project/chapters/intro.tex\" [label=\"include\"];"));
+
+        // Without a root, every indexed file is included, its includes resolved against its
+        // own root document.
+        let graph = workspace.dependency_graph(None);
+        assert_eq!(graph.nodes.len(), 6);
+        assert!(graph.nodes.iter().any(|n| n.uri.path() == "/project/missing.tex"));
+        assert!(!graph.nodes.iter().any(|n| n.uri.path() == "/project/chapters/missing.tex"));
+    }
+
+    #[test]
+    fn test_nested_files_resolve_against_root() {
+        let workspace = Workspace::new();
+        let main = Url::parse("file:///project/main.tex").unwrap();
+        let chapter = Url::parse("file:///project/chapters/a.tex").unwrap();
+        workspace.update(&main, "\\input{chapters/a}");
+        workspace.update(&chapter, "\\bibliography{refs}\n\\includegraphics{figures/plot.png}");
+
+        let graph = workspace.dependency_graph(Some(&main));
+        let edges: Vec<(&str, &str, EdgeKind)> = graph.edges.iter().map(|e| (e.from.path(), e.to.path(), e.kind)).collect();
+        assert_eq!(
+            edges,
+            [
+                ("/project/main.tex", "/project/chapters/a.tex", EdgeKind::Include),
+                ("/project/chapters/a.tex", "/project/refs.bib", EdgeKind::Bibliography),
+                ("/project/chapters/a.tex", "/project/figures/plot.png", EdgeKind::Graphics),
+            ]
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use ferrotex_syntax::TextRange;
+use ferrotex_syntax::bibtex::{BibEntry, BibFile};
+
+/// Fields each entry type requires, after the BibTeX standard styles. `a|b` is satisfied
+/// by either field; biblatex's names (`journaltitle`, `date`, `institution` for theses)
+/// are accepted as alternatives. Types not listed require nothing.
+const REQUIRED_FIELDS: &[(&str, &[&str])] = &[
+    ("article", &["author", "title", "journal|journaltitle", "year|date"]),
+    ("book", &["author|editor", "title", "publisher", "year|date"]),
+    ("booklet", &["title"]),
+    ("inbook", &["author|editor", "title", "chapter|pages", "publisher", "year|date"]),
+    ("incollection", &["author", "title", "booktitle", "publisher", "year|date"]),
+    ("inproceedings", &["author", "title", "booktitle", "year|date"]),
+    ("conference", &["author", "title", "booktitle", "year|date"]),
+    ("manual", &["title"]),
+    ("mastersthesis", &["author", "title", "school|institution", "year|date"]),
+    ("phdthesis", &["author", "title", "school|institution", "year|date"]),
+    ("thesis", &["author", "title", "type", "institution|school", "year|date"]),
+    ("proceedings", &["title", "year|date"]),
+    ("techreport", &["author", "title", "institution", "year|date"]),
+    ("unpublished", &["author", "title", "note"]),
+    ("online", &["title", "url|doi|eprint"]),
+];
+
+/// Returns the required fields `entry` lacks, as written in the table (`year|date`),
+/// or an empty list if its type is unknown.
+pub fn missing_fields(entry: &BibEntry) -> Vec<&'static str> {
+    let Some((_, required)) = REQUIRED_FIELDS.iter().find(|(entry_type, _)| *entry_type == entry.entry_type) else {
+        return Vec::new();
+    };
+    required
+        .iter()
+        .copied()
+        .filter(|alternatives| {
+            !alternatives
+                .split('|')
+                .any(|field| entry.fields.get(field).is_some_and(|value| !value.trim().is_empty()))
+        })
+        .collect()
+}
+
+/// Reports the entries of a `.bib` file that lack required fields, at the entry's range.
+pub fn check_required_fields(bib: &BibFile) -> Vec<(TextRange, String)> {
+    bib.entries
+        .iter()
+        .filter_map(|entry| {
+            let missing = missing_fields(entry);
+            (!missing.is_empty()).then(|| {
+                let missing: Vec<String> = missing.iter().map(|field| field.replace('|', " or ")).collect();
+                (
+                    entry.range,
+                    format!("Entry '{}' (@{}) is missing required fields: {}", entry.key, entry.entry_type, missing.join(", ")),
+                )
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferrotex_syntax::bibtex::parse_bibtex;
+
+    #[test]
+    fn test_check_required_fields() {
+        let bib = parse_bibtex(
+            "@article{complete, author={A}, title={T}, journaltitle={J}, date={2020}}\n\
+             @Book{partial, editor={E}, title={T}, year={}}\n\
+             @misc{anything, note={N}}\n\
+             @string{acm = {ACM}}\n",
+        );
+        let problems = check_required_fields(&bib);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].1, "Entry 'partial' (@book) is missing required fields: publisher, year or date");
+        assert_eq!(problems[0].0, bib.entries[1].range);
+    }
+}
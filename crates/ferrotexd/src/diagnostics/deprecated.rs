@@ -0,0 +1,134 @@
+use DeprecationKind::{Command, Environment, Package, Syntax};
+
+/// The category of an obsolete construct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeprecationKind {
+    /// A package loaded with `\usepackage`.
+    Package,
+    /// A command such as `\bf`.
+    Command,
+    /// An environment such as `eqnarray`.
+    Environment,
+    /// Plain TeX syntax such as `$$ ... $$`.
+    Syntax,
+}
+
+/// An obsolete package, command or environment together with its modern replacement.
+#[derive(Debug)]
+pub struct Deprecation {
+    pub kind: DeprecationKind,
+    /// Name as written in the source (commands include the backslash).
+    pub name: &'static str,
+    /// What to use instead.
+    pub replacement: &'static str,
+}
+
+impl Deprecation {
+    /// Returns the user-facing diagnostic message.
+    pub fn message(&self) -> String {
+        match self.kind {
+            DeprecationKind::Package => format!(
+                "Package '{}' is obsolete. Use {} instead.",
+                self.name, self.replacement
+            ),
+            DeprecationKind::Command => format!(
+                "Command '{}' is deprecated. Use {} instead.",
+                self.name, self.replacement
+            ),
+            DeprecationKind::Environment => format!(
+                "Environment '{}' is deprecated. Use {} instead.",
+                self.name, self.replacement
+            ),
+            DeprecationKind::Syntax => format!(
+                "Plain TeX '{}' is deprecated in LaTeX. Use {} instead.",
+                self.name, self.replacement
+            ),
+        }
+    }
+}
+
+const fn entry(kind: DeprecationKind, name: &'static str, replacement: &'static str) -> Deprecation {
+    Deprecation { kind, name, replacement }
+}
+
+/// Known obsolete constructs. Add new entries here; the scanner picks them up automatically.
+pub const DEPRECATIONS: &[Deprecation] = &[
+    // Packages
+    entry(Package, "a4wide", "'geometry'"),
+    entry(Package, "times", "'newtxtext' and 'newtxmath'"),
+    entry(Package, "mathptm", "'newtxmath'"),
+    entry(Package, "palatino", "'newpxtext' and 'newpxmath'"),
+    entry(Package, "epsfig", "'graphicx'"),
+    entry(Package, "epsf", "'graphicx'"),
+    entry(Package, "psfig", "'graphicx'"),
+    entry(Package, "subfigure", "'subcaption'"),
+    entry(Package, "fancyheadings", "'fancyhdr'"),
+    entry(Package, "doublespace", "'setspace'"),
+    entry(Package, "caption2", "'caption'"),
+    entry(Package, "scrpage2", "'scrlayer-scrpage'"),
+    entry(Package, "glossary", "'glossaries'"),
+    entry(Package, "isolatin1", "'inputenc'"),
+    entry(Package, "t1enc", "'fontenc' with the T1 option"),
+    entry(Package, "ae", "'lmodern'"),
+    // Font switches from LaTeX 2.09
+    entry(Command, "\\bf", "\\textbf{...} or \\bfseries"),
+    entry(Command, "\\it", "\\textit{...} or \\itshape"),
+    entry(Command, "\\sl", "\\textsl{...} or \\slshape"),
+    entry(Command, "\\sc", "\\textsc{...} or \\scshape"),
+    entry(Command, "\\rm", "\\textrm{...} or \\rmfamily"),
+    entry(Command, "\\sf", "\\textsf{...} or \\sffamily"),
+    entry(Command, "\\tt", "\\texttt{...} or \\ttfamily"),
+    entry(Command, "\\cal", "\\mathcal{...}"),
+    // Plain TeX and legacy commands
+    entry(Command, "\\over", "\\frac{...}{...}"),
+    entry(Command, "\\centerline", "\\centering or the center environment"),
+    entry(Command, "\\epsfig", "\\includegraphics"),
+    entry(Command, "\\psfig", "\\includegraphics"),
+    entry(Command, "\\epsfbox", "\\includegraphics"),
+    // Environments
+    entry(Environment, "eqnarray", "'align' from amsmath"),
+    entry(Environment, "eqnarray*", "'align*' from amsmath"),
+    // Syntax
+    entry(Syntax, "$$ ... $$", "\\[ ... \\]"),
+];
+
+/// Looks up a deprecation entry by kind and name.
+pub fn lookup(kind: DeprecationKind, name: &str) -> Option<&'static Deprecation> {
+    DEPRECATIONS.iter().find(|d| d.kind == kind && d.name == name)
+}
+
+/// Resolves a marker recorded by the workspace scanner (e.g. `package:times`, `\bf:group`).
+pub fn lookup_marker(marker: &str) -> Option<&'static Deprecation> {
+    if let Some(pkg) = marker.strip_prefix("package:") {
+        lookup(Package, pkg)
+    } else if let Some(env) = marker.strip_prefix("environment:") {
+        lookup(Environment, env)
+    } else if let Some(cmd) = marker.strip_suffix(":group") {
+        lookup(Command, cmd)
+    } else if marker == "displaymath" {
+        lookup(Syntax, "$$ ... $$")
+    } else {
+        lookup(Command, marker)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_marker() {
+        let d = lookup_marker("package:epsfig").unwrap();
+        assert!(d.message().contains("graphicx"));
+
+        let d = lookup_marker("\\it:group").unwrap();
+        assert!(d.message().contains("\\textit"));
+
+        let d = lookup_marker("environment:eqnarray").unwrap();
+        assert!(d.message().contains("align"));
+
+        let d = lookup_marker("displaymath").unwrap();
+        assert!(d.message().contains("$$"));
+        assert!(lookup_marker("package:geometry").is_none());
+    }
+}
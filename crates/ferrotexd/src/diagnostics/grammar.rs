@@ -0,0 +1,457 @@
+use dashmap::DashMap;
+use ferrotex_syntax::{SyntaxKind, SyntaxNode, SyntaxToken, TextRange, TextSize};
+use crate::position::LineIndex;
+use serde::Deserialize;
+use std::time::Duration;
+use tower_lsp::lsp_types::{
+    CodeActionOrCommand, Diagnostic, DiagnosticSeverity, NumberOrString, Url,
+};
+
+use crate::settings::GrammarSettings;
+
+/// Source attached to every grammar diagnostic; quick fixes are matched on it.
+pub const GRAMMAR_SOURCE: &str = "ferrotex-grammar";
+
+/// Stand-in for math and references, so that LanguageTool sees a complete sentence.
+const PLACEHOLDER: &str = "X";
+
+/// Reference-like commands whose argument is replaced by [`PLACEHOLDER`].
+const REFERENCE_COMMANDS: &[&str] = &[
+    "\\eqref", "\\pageref", "\\autoref", "\\cref", "\\Cref", "\\citep", "\\citet", "\\parencite",
+    "\\textcite", "\\autocite",
+];
+
+/// Commands that end a paragraph.
+const PARAGRAPH_COMMANDS: &[&str] = &["\\par", "\\item", "\\section", "\\subsection", "\\subsubsection", "\\paragraph"];
+
+/// Plain prose extracted from a document, with a map back to the source.
+#[derive(Debug, Default, Clone)]
+pub struct Prose {
+    /// The text submitted to LanguageTool.
+    pub text: String,
+    spans: Vec<Span>,
+}
+
+/// A run of `text` starting at `offset` that was produced from `source`.
+///
+/// Exact spans were copied verbatim, so offsets inside them map one to one.
+/// Other spans (collapsed whitespace, placeholders) map to the whole source range.
+#[derive(Debug, Clone, Copy)]
+struct Span {
+    offset: usize,
+    source: TextRange,
+    exact: bool,
+}
+
+impl Prose {
+    fn push(&mut self, text: &str, source: TextRange, exact: bool) {
+        self.spans.push(Span { offset: self.text.len(), source, exact });
+        self.text.push_str(text);
+    }
+
+    /// `exact` marks a word space whose source is a single space.
+    fn push_space(&mut self, source: TextRange, paragraph: bool, exact: bool) {
+        if self.text.is_empty() || self.text.ends_with("\n\n") {
+            return;
+        }
+        if paragraph {
+            let trimmed = self.text.trim_end_matches(' ').len();
+            self.text.truncate(trimmed);
+            self.push("\n\n", source, false);
+        } else if !self.text.ends_with(' ') {
+            self.push(" ", source, exact);
+        }
+    }
+
+    /// Maps the byte range `start..end` of [`Prose::text`] back to the source document.
+    pub fn source_range(&self, start: usize, end: usize) -> TextRange {
+        let start = self.source_offset(start, false);
+        let end = self.source_offset(end, true).max(start);
+        TextRange::new(start, end)
+    }
+
+    /// Returns `true` if the byte range `start..end` of [`Prose::text`] is a verbatim copy of
+    /// its source range, so that a replacement for the one can replace the other.
+    fn is_verbatim(&self, start: usize, end: usize) -> bool {
+        let first = self.spans.partition_point(|s| s.offset <= start).saturating_sub(1);
+        let last = self.spans.partition_point(|s| s.offset < end).max(first + 1).min(self.spans.len());
+        let covered = &self.spans[first.min(last)..last];
+        !covered.is_empty()
+            && covered.iter().all(|s| s.exact)
+            && covered.windows(2).all(|pair| pair[0].source.end() == pair[1].source.start())
+    }
+
+    fn source_offset(&self, offset: usize, is_end: bool) -> TextSize {
+        // An end offset sitting exactly on a span boundary belongs to the previous span.
+        let index = self
+            .spans
+            .partition_point(|s| if is_end { s.offset < offset } else { s.offset <= offset });
+        let Some(span) = index.checked_sub(1).and_then(|i| self.spans.get(i)) else {
+            return self.spans.first().map(|s| s.source.start()).unwrap_or_default();
+        };
+        if span.exact {
+            let delta = TextSize::from((offset - span.offset) as u32);
+            (span.source.start() + delta).min(span.source.end())
+        } else if is_end {
+            span.source.end()
+        } else {
+            span.source.start()
+        }
+    }
+
+    /// Converts a UTF-16 offset, as reported by LanguageTool, to a byte offset into [`Prose::text`].
+    fn byte_offset(&self, utf16: usize) -> usize {
+        let mut units = 0;
+        for (i, c) in self.text.char_indices() {
+            if units >= utf16 {
+                return i;
+            }
+            units += c.len_utf16();
+        }
+        self.text.len()
+    }
+}
+
+/// Extracts the running text of the document for grammar checking.
+///
+/// Commands are dropped while their prose arguments are kept. Inline math and
+/// references become a placeholder word; display math, verbatim text and
+/// identifier arguments are skipped entirely.
+pub fn extract_prose(root: &SyntaxNode) -> Prose {
+    let mut prose = Prose::default();
+    let mut math_start: Option<TextSize> = None;
+
+    for token in root.descendants_with_tokens().filter_map(|e| e.into_token()) {
+        let range = token.text_range();
+        match token.kind() {
+            SyntaxKind::Dollar => match math_start.take() {
+                Some(start) => prose.push(PLACEHOLDER, TextRange::new(start, range.end()), false),
+                None => math_start = Some(range.start()),
+            },
+            SyntaxKind::Command => match token.text() {
+                "\\(" => math_start = Some(range.start()),
+                "\\)" => {
+                    if let Some(start) = math_start.take() {
+                        prose.push(PLACEHOLDER, TextRange::new(start, range.end()), false);
+                    }
+                }
+                "\\[" => math_start = Some(range.start()),
+                "\\]" => {
+                    math_start = None;
+                    prose.push_space(range, true, false);
+                }
+                _ if math_start.is_some() || in_skipped_region(&token) => {}
+                text if PARAGRAPH_COMMANDS.contains(&text) => prose.push_space(range, true, false),
+                text if REFERENCE_COMMANDS.contains(&text) => prose.push(PLACEHOLDER, range, false),
+                _ if is_reference_node(&token) => {
+                    let source = token.parent().map_or(range, |p| p.text_range());
+                    prose.push(PLACEHOLDER, source, false);
+                }
+                _ => {}
+            },
+            SyntaxKind::RBrace if math_start.is_none() && ends_heading(&token) => prose.push_space(range, true, false),
+            SyntaxKind::Whitespace if math_start.is_none() && !in_skipped_region(&token) => {
+                prose.push_space(range, token.text().matches('\n').count() > 1, token.text() == " ");
+            }
+            SyntaxKind::Text => {
+                if math_start.is_some()
+                    || in_skipped_region(&token)
+                    || super::spell::in_non_prose_argument(&token)
+                    || super::spell::in_command_option(&token)
+                {
+                    continue;
+                }
+                prose.push(token.text(), range, true);
+            }
+            _ => {}
+        }
+    }
+
+    let trimmed = prose.text.trim_end().len();
+    prose.text.truncate(trimmed);
+    prose
+}
+
+fn in_skipped_region(token: &SyntaxToken) -> bool {
+    super::in_verbatim(token) || super::in_environment(token, super::MATH_ENVIRONMENTS)
+}
+
+/// Returns `true` if the token closes the title argument of a sectioning command.
+fn ends_heading(token: &SyntaxToken) -> bool {
+    let Some(group) = token.parent().filter(|g| g.kind() == SyntaxKind::Group) else {
+        return false;
+    };
+    group.parent().is_some_and(|p| p.kind() == SyntaxKind::Section)
+        || group
+            .prev_sibling_or_token()
+            .is_some_and(|p| p.kind() == SyntaxKind::Command && PARAGRAPH_COMMANDS.contains(&p.to_string().as_str()))
+}
+
+fn is_reference_node(token: &SyntaxToken) -> bool {
+    token
+        .parent()
+        .is_some_and(|p| matches!(p.kind(), SyntaxKind::LabelReference | SyntaxKind::Citation))
+}
+
+/// A single problem reported by LanguageTool.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrammarMatch {
+    pub message: String,
+    /// Offset into the checked text, in UTF-16 code units.
+    pub offset: usize,
+    pub length: usize,
+    #[serde(default)]
+    pub replacements: Vec<Replacement>,
+    pub rule: Rule,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Replacement {
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    pub id: String,
+    #[serde(default)]
+    pub issue_type: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckResponse {
+    matches: Vec<GrammarMatch>,
+}
+
+/// LanguageTool state shared by all validations.
+///
+/// One HTTP client serves every request, so connections are reused, and the diagnostics of
+/// the last checked version of each document are kept, so revalidating an unchanged
+/// document (e.g. after a build) does not submit it again.
+#[derive(Debug)]
+pub struct GrammarChecker {
+    client: reqwest::Client,
+    results: DashMap<Url, (i32, Vec<Diagnostic>)>,
+    /// Running check per document; starting another one aborts it.
+    pending: DashMap<Url, tokio::task::JoinHandle<()>>,
+}
+
+impl Default for GrammarChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GrammarChecker {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder().timeout(Duration::from_secs(30)).build().unwrap_or_default();
+        Self { client, results: DashMap::new(), pending: DashMap::new() }
+    }
+
+    /// The diagnostics found in `version` of `uri`, if that version was checked.
+    pub fn cached(&self, uri: &Url, version: i32) -> Option<Vec<Diagnostic>> {
+        self.results.get(uri).filter(|result| result.0 == version).map(|result| result.1.clone())
+    }
+
+    /// Records the diagnostics found in `version` of `uri`.
+    pub fn store(&self, uri: Url, version: i32, diagnostics: Vec<Diagnostic>) {
+        self.results.insert(uri, (version, diagnostics));
+    }
+
+    /// Tracks the running check of `uri`, aborting the one it supersedes.
+    pub fn track(&self, uri: Url, check: tokio::task::JoinHandle<()>) {
+        if let Some(previous) = self.pending.insert(uri, check) {
+            previous.abort();
+        }
+    }
+
+    /// Forgets a document and aborts its check, e.g. when it is closed.
+    pub fn remove(&self, uri: &Url) {
+        if let Some((_, check)) = self.pending.remove(uri) {
+            check.abort();
+        }
+        self.results.remove(uri);
+    }
+
+    /// Forgets every result and aborts every check, e.g. when the settings change.
+    pub fn clear(&self) {
+        for check in self.pending.iter() {
+            check.abort();
+        }
+        self.pending.clear();
+        self.results.clear();
+    }
+
+    /// Submits `prose` to the LanguageTool server configured in `settings`.
+    ///
+    /// `language` is a LanguageTool language code such as `en-US`, or `auto`.
+    pub async fn check(&self, settings: &GrammarSettings, language: &str, prose: &Prose) -> anyhow::Result<Vec<GrammarMatch>> {
+        if prose.text.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut form = vec![("language", language.to_string()), ("text", prose.text.clone())];
+        if !settings.disabled_rules.is_empty() {
+            form.push(("disabledRules", settings.disabled_rules.join(",")));
+        }
+        let response = self
+            .client
+            .post(format!("{}/v2/check", settings.url.trim_end_matches('/')))
+            .form(&form)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<CheckResponse>()
+            .await?;
+        Ok(response.matches)
+    }
+}
+
+/// Converts a `% !TeX spellcheck` / Hunspell language (`en_US`) to a LanguageTool code (`en-US`).
+pub fn languagetool_language(lang: &str) -> String {
+    lang.replace('_', "-")
+}
+
+/// Turns LanguageTool matches into diagnostics on the source document.
+///
+/// Each diagnostic carries the suggested replacements in `data`, which
+/// [`suggestion_actions`] turns into quick fixes. Matches spanning markup, placeholders or
+/// collapsed whitespace get none: the replacement would overwrite source text LanguageTool
+/// never saw.
+pub fn to_diagnostics(prose: &Prose, matches: &[GrammarMatch], line_index: &LineIndex) -> Vec<Diagnostic> {
+    matches
+        .iter()
+        .map(|m| {
+            let start = prose.byte_offset(m.offset);
+            let end = prose.byte_offset(m.offset + m.length);
+            let range = prose.source_range(start, end);
+            let severity = match m.rule.issue_type.as_str() {
+                "style" | "typographical" | "whitespace" => DiagnosticSeverity::HINT,
+                _ => DiagnosticSeverity::INFORMATION,
+            };
+            let replacements: Vec<&str> = if prose.is_verbatim(start, end) {
+                m.replacements.iter().take(5).map(|r| r.value.as_str()).collect()
+            } else {
+                Vec::new()
+            };
+            Diagnostic {
+                range: line_index.range(range),
+                severity: Some(severity),
+                code: Some(NumberOrString::String(m.rule.id.clone())),
+                source: Some(GRAMMAR_SOURCE.to_string()),
+                message: m.message.clone(),
+                data: Some(serde_json::json!(replacements)),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Builds "replace with" quick fixes for the grammar diagnostics in `diagnostics`.
+pub fn suggestion_actions(uri: &Url, diagnostics: &[Diagnostic]) -> Vec<CodeActionOrCommand> {
+    super::replacement_actions(uri, diagnostics, |diag| diag.source.as_deref() == Some(GRAMMAR_SOURCE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferrotex_syntax::parse;
+    use tower_lsp::lsp_types::Position;
+
+    fn prose(input: &str) -> Prose {
+        extract_prose(&parse(input).syntax())
+    }
+
+    fn source_text<'a>(input: &'a str, prose: &Prose, needle: &str) -> &'a str {
+        let start = prose.text.find(needle).unwrap();
+        let range = prose.source_range(start, start + needle.len());
+        &input[range]
+    }
+
+    #[test]
+    fn test_extract_prose_drops_markup() {
+        let input = "\\section{Intro}\nThis is \\emph{very} simple, see \\ref{fig:a} and $x^2$.\n\n% note\nNext   paragraph.";
+        let prose = prose(input);
+        assert_eq!(prose.text, "Intro\n\nThis is very simple, see X and X.\n\nNext paragraph.");
+    }
+
+    #[test]
+    fn test_extract_prose_skips_non_prose() {
+        let input = "\\usepackage{amsmath}\nText \\label{sec:x} here.\n\\begin{equation}a = b\\end{equation}\nEnd.";
+        let prose = prose(input);
+        assert!(!prose.text.contains("amsmath"));
+        assert!(!prose.text.contains("sec:x"));
+        assert!(!prose.text.contains("a = b"));
+        assert!(prose.text.contains("Text here."));
+    }
+
+    #[test]
+    fn test_source_range_mapping() {
+        let input = "We \\textbf{has} a cat, see \\cite{knuth} and $y$.";
+        let prose = prose(input);
+        assert_eq!(source_text(input, &prose, "has"), "has");
+        assert_eq!(source_text(input, &prose, "We has"), "We \\textbf{has");
+        assert_eq!(source_text(input, &prose, "a cat"), "a cat");
+        assert_eq!(source_text(input, &prose, "see X"), "see \\cite{knuth}");
+        assert_eq!(source_text(input, &prose, "and X."), "and $y$.");
+    }
+
+    #[test]
+    fn test_matches_to_diagnostics() {
+        let input = "Caf\u{e9} \\emph{are} open.";
+        let prose = prose(input);
+        let response = r#"{"matches": [{
+            "message": "Possible agreement error.",
+            "offset": 5, "length": 3,
+            "replacements": [{"value": "is"}],
+            "rule": {"id": "AGREEMENT", "issueType": "grammar"}
+        }]}"#;
+        let matches = serde_json::from_str::<CheckResponse>(response).unwrap().matches;
+        let line_index = LineIndex::new(input);
+        let diags = to_diagnostics(&prose, &matches, &line_index);
+        assert_eq!(diags.len(), 1);
+        // Positions are UTF-16 columns: 'é' counts once.
+        let start = input[..input.find("are").unwrap()].encode_utf16().count() as u32;
+        assert_eq!(diags[0].range.start, Position { line: 0, character: start });
+        assert_eq!(diags[0].range.end, Position { line: 0, character: start + 3 });
+        assert_eq!(diags[0].code, Some(NumberOrString::String("AGREEMENT".to_string())));
+
+        let uri = Url::parse("file:///doc.tex").unwrap();
+        let actions = suggestion_actions(&uri, &diags);
+        assert_eq!(actions.len(), 1);
+    }
+
+    #[test]
+    fn test_results_cached_per_version() {
+        let checker = GrammarChecker::new();
+        let uri = Url::parse("file:///doc.tex").unwrap();
+        let found = vec![Diagnostic { message: "Possible agreement error.".to_string(), ..Default::default() }];
+        checker.store(uri.clone(), 2, found.clone());
+        assert_eq!(checker.cached(&uri, 2), Some(found));
+        assert_eq!(checker.cached(&uri, 3), None);
+
+        checker.remove(&uri);
+        assert_eq!(checker.cached(&uri, 2), None);
+    }
+
+    #[test]
+    fn test_replacements_only_for_verbatim_text() {
+        let grammar_match = |offset: usize, length: usize| GrammarMatch {
+            message: "Possible agreement error.".to_string(),
+            offset,
+            length,
+            replacements: vec![Replacement { value: "We have".to_string() }],
+            rule: Rule { id: "AGREEMENT".to_string(), issue_type: "grammar".to_string() },
+        };
+        let suggestions = |input: &str| {
+            let prose = prose(input);
+            let diags = to_diagnostics(&prose, &[grammar_match(0, 6)], &LineIndex::new(input));
+            diags[0].data.clone().unwrap()
+        };
+
+        assert_eq!(suggestions("We has a cat."), serde_json::json!(["We have"]));
+        // "We has" stands for `We \textbf{has`: replacing it would drop the markup.
+        assert_eq!(suggestions("We \\textbf{has} a cat."), serde_json::json!([]));
+        assert_eq!(suggestions("We\nhas a cat."), serde_json::json!([]));
+    }
+}
@@ -0,0 +1,216 @@
+use ferrotex_syntax::{SyntaxKind, SyntaxNode, SyntaxToken, TextRange, TextSize};
+use crate::position::LineIndex;
+use std::collections::HashSet;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+
+/// A single style rule in the spirit of `chktex`.
+///
+/// Every rule carries a stable `id` that is reported as the diagnostic code,
+/// so it can be disabled through [`LintConfig`].
+pub struct LintRule {
+    /// Stable identifier, e.g. `"ellipsis"`.
+    pub id: &'static str,
+    /// Short human-readable summary of what the rule checks.
+    pub description: &'static str,
+    check: fn(&SyntaxNode) -> Vec<(TextRange, String)>,
+}
+
+/// The built-in rule set.
+pub const RULES: &[LintRule] = &[
+    LintRule {
+        id: "ellipsis",
+        description: "Literal `...` should be written as `\\dots`",
+        check: check_ellipsis,
+    },
+    LintRule {
+        id: "footnote-space",
+        description: "No space is allowed before `\\footnote`",
+        check: check_footnote_space,
+    },
+    LintRule {
+        id: "ref-tie",
+        description: "References should be preceded by a non-breaking space `~`",
+        check: check_ref_tie,
+    },
+    LintRule {
+        id: "straight-quotes",
+        description: "Use ``` `` ``` and `''` instead of `\"`",
+        check: check_straight_quotes,
+    },
+];
+
+/// Commands that should be tied to the preceding word with `~`.
+const REF_COMMANDS: &[&str] = &["\\ref", "\\eqref", "\\pageref", "\\autoref", "\\cref", "\\Cref"];
+
+/// Controls which lint rules are active.
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    /// IDs of rules that must not report anything.
+    pub disabled: HashSet<String>,
+}
+
+impl LintConfig {
+    /// Returns `true` if the rule with the given id should run.
+    pub fn is_enabled(&self, id: &str) -> bool {
+        !self.disabled.contains(id)
+    }
+}
+
+/// Runs all enabled lint rules over the document.
+pub fn check_lint(root: &SyntaxNode, line_index: &LineIndex, config: &LintConfig) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for rule in RULES.iter().filter(|r| config.is_enabled(r.id)) {
+        for (range, message) in (rule.check)(root) {
+            diagnostics.push(Diagnostic {
+                range: line_index.range(range),
+                severity: Some(DiagnosticSeverity::INFORMATION),
+                code: Some(NumberOrString::String(rule.id.to_string())),
+                source: Some("ferrotex-lint".to_string()),
+                message,
+                ..Default::default()
+            });
+        }
+    }
+
+    diagnostics
+}
+
+/// Iterates over all tokens that are not inside a verbatim-like environment.
+fn lintable_tokens(root: &SyntaxNode) -> impl Iterator<Item = SyntaxToken> {
+    root.descendants_with_tokens()
+        .filter_map(|e| e.into_token())
+        .filter(|t| !super::in_verbatim(t))
+}
+
+fn check_ellipsis(root: &SyntaxNode) -> Vec<(TextRange, String)> {
+    let mut results = Vec::new();
+    for token in lintable_tokens(root).filter(|t| t.kind() == SyntaxKind::Text) {
+        let text = token.text();
+        let mut search_from = 0;
+        while let Some(idx) = text[search_from..].find("...") {
+            let start = search_from + idx;
+            let offset = token.text_range().start() + TextSize::from(start as u32);
+            results.push((
+                TextRange::at(offset, TextSize::from(3)),
+                "Use `\\dots` instead of `...`".to_string(),
+            ));
+            search_from = start + 3;
+        }
+    }
+    results
+}
+
+fn check_footnote_space(root: &SyntaxNode) -> Vec<(TextRange, String)> {
+    lintable_tokens(root)
+        .filter(|t| t.kind() == SyntaxKind::Command && t.text() == "\\footnote")
+        .filter_map(|t| {
+            let prev = t.prev_token()?;
+            if prev.kind() != SyntaxKind::Whitespace || prev.text().contains('\n') {
+                return None;
+            }
+            Some((prev.text_range(), "Delete this space to keep the footnote mark attached".to_string()))
+        })
+        .collect()
+}
+
+fn check_ref_tie(root: &SyntaxNode) -> Vec<(TextRange, String)> {
+    lintable_tokens(root)
+        .filter(|t| t.kind() == SyntaxKind::Command && REF_COMMANDS.contains(&t.text()))
+        .filter_map(|t| {
+            let ws = t.prev_token()?;
+            if ws.kind() != SyntaxKind::Whitespace || ws.text().contains("\n\n") {
+                return None;
+            }
+            // Only a word directly before the reference needs a tie.
+            if ws.prev_token()?.kind() != SyntaxKind::Text {
+                return None;
+            }
+            Some((
+                ws.text_range(),
+                format!("Use a non-breaking space `~` before `{}`", t.text()),
+            ))
+        })
+        .collect()
+}
+
+fn check_straight_quotes(root: &SyntaxNode) -> Vec<(TextRange, String)> {
+    let mut results = Vec::new();
+    for token in lintable_tokens(root).filter(|t| t.kind() == SyntaxKind::Text) {
+        for (idx, _) in token.text().match_indices('"') {
+            let offset = token.text_range().start() + TextSize::from(idx as u32);
+            results.push((
+                TextRange::at(offset, TextSize::from(1)),
+                "Use ``` `` ``` or `''` instead of `\"`".to_string(),
+            ));
+        }
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferrotex_syntax::parse;
+
+    fn lint(input: &str, config: &LintConfig) -> Vec<Diagnostic> {
+        let parsed = parse(input);
+        let line_index = LineIndex::new(input);
+        check_lint(&parsed.syntax(), &line_index, config)
+    }
+
+    fn codes(diags: &[Diagnostic]) -> Vec<String> {
+        diags
+            .iter()
+            .filter_map(|d| match &d.code {
+                Some(NumberOrString::String(s)) => Some(s.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_ellipsis() {
+        let diags = lint("Wait for it...", &LintConfig::default());
+        assert_eq!(codes(&diags), vec!["ellipsis"]);
+        assert_eq!(diags[0].range.start.character, 11);
+    }
+
+    #[test]
+    fn test_lint_footnote_space() {
+        let diags = lint(r"Text \footnote{Note}", &LintConfig::default());
+        assert_eq!(codes(&diags), vec!["footnote-space"]);
+
+        let diags = lint(r"Text\footnote{Note}", &LintConfig::default());
+        assert!(diags.is_empty());
+    }
+
+    #[test]
+    fn test_lint_ref_tie() {
+        let diags = lint(r"see Figure \ref{fig:a}", &LintConfig::default());
+        assert_eq!(codes(&diags), vec!["ref-tie"]);
+
+        let diags = lint(r"see Figure~\ref{fig:a}", &LintConfig::default());
+        assert!(diags.is_empty());
+    }
+
+    #[test]
+    fn test_lint_straight_quotes() {
+        let diags = lint(r#"He said "hi"."#, &LintConfig::default());
+        assert_eq!(codes(&diags), vec!["straight-quotes", "straight-quotes"]);
+    }
+
+    #[test]
+    fn test_lint_skips_verbatim_and_comments() {
+        let input = "% etc...\n\\begin{verbatim}\nprint(\"a\") ...\n\\end{verbatim}";
+        assert!(lint(input, &LintConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_lint_disabled_rule() {
+        let config = LintConfig {
+            disabled: ["ellipsis".to_string()].into_iter().collect(),
+        };
+        assert!(lint("and so on...", &config).is_empty());
+    }
+}
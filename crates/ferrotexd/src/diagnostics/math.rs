@@ -3,19 +3,16 @@ use ferrotex_math_semantics::analysis::infer_shape;
 use ferrotex_math_semantics::delimiters::check_delimiters;
 use ferrotex_math_semantics::Shape;
 use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Range, Position};
-use line_index::LineIndex;
+use crate::position::LineIndex;
 
 pub fn check_math(root: &SyntaxNode, line_index: &LineIndex) -> Vec<Diagnostic> {
     let mut diagnostics = Vec::new();
 
     // 1. Check delimiter balance
     for error in check_delimiters(root) {
-        let offset = rowan::TextSize::try_from(error.offset as u32).unwrap_or_default();
-        let pos = line_index.line_col(offset);
-        let lsp_range = Range {
-            start: Position { line: pos.line, character: pos.col },
-            end: Position { line: pos.line, character: pos.col + 1 },
-        };
+        let offset = rowan::TextSize::from(error.offset as u32);
+        let start = line_index.position(offset);
+        let lsp_range = Range { start, end: Position { character: start.character + 1, ..start } };
         diagnostics.push(Diagnostic {
             range: lsp_range,
             severity: Some(DiagnosticSeverity::WARNING),
@@ -48,14 +45,7 @@ pub fn check_math(root: &SyntaxNode, line_index: &LineIndex) -> Vec<Diagnostic>
             if is_matrix {
                 let shape = infer_shape(&node);
                 if let Shape::Invalid(msg) = shape {
-                     let range = node.text_range();
-                     let start = line_index.line_col(range.start());
-                     let end = line_index.line_col(range.end());
-                     
-                     let lsp_range = Range {
-                         start: Position { line: start.line, character: start.col },
-                         end: Position { line: end.line, character: end.col },
-                     };
+                     let lsp_range = line_index.range(node.text_range());
                      
                      diagnostics.push(Diagnostic {
                          range: lsp_range,
@@ -80,7 +70,7 @@ pub fn check_math(root: &SyntaxNode, line_index: &LineIndex) -> Vec<Diagnostic>
 mod tests {
     use super::*;
     use ferrotex_syntax::parse;
-    use line_index::LineIndex;
+    use crate::position::LineIndex;
 
     #[test]
     fn test_check_math_no_matrix() {
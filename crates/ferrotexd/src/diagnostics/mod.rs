@@ -1,2 +1,75 @@
+pub mod bib;
+pub mod deprecated;
 pub mod error_index;
+pub mod grammar;
+pub mod lint;
 pub mod math;
+pub mod spell;
+
+use ferrotex_syntax::{SyntaxKind, SyntaxToken};
+use std::collections::HashMap;
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Diagnostic, TextEdit, Url, WorkspaceEdit,
+};
+
+/// Environments whose bodies are raw text and must not be analysed as LaTeX prose.
+pub use ferrotex_syntax::parser::VERBATIM_ENVIRONMENTS;
+
+/// Environments whose bodies are typeset in math mode.
+pub const MATH_ENVIRONMENTS: &[&str] = &[
+    "equation", "equation*", "align", "align*", "gather", "gather*", "multline", "multline*",
+    "flalign", "flalign*", "alignat", "alignat*", "eqnarray", "eqnarray*", "math", "displaymath",
+];
+
+/// Returns `true` if the token lives inside a verbatim-like environment.
+pub fn in_verbatim(token: &SyntaxToken) -> bool {
+    in_environment(token, VERBATIM_ENVIRONMENTS)
+}
+
+/// Returns `true` if any enclosing environment of the token has one of the given names.
+pub fn in_environment(token: &SyntaxToken, names: &[&str]) -> bool {
+    token.parent_ancestors().any(|node| {
+        node.kind() == SyntaxKind::Environment
+            && crate::workspace::extract_group_text(&node)
+                .is_some_and(|name| names.contains(&name.as_str()))
+    })
+}
+
+/// Builds "replace with" quick fixes for the diagnostics accepted by `filter`.
+///
+/// Replacement suggestions are read from the diagnostic's `data`, which must be
+/// an array of strings; the first one is marked as preferred.
+pub fn replacement_actions(
+    uri: &Url,
+    diagnostics: &[Diagnostic],
+    filter: impl Fn(&Diagnostic) -> bool,
+) -> Vec<CodeActionOrCommand> {
+    let mut actions = Vec::new();
+
+    for diag in diagnostics.iter().filter(|d| filter(d)) {
+        let suggestions = diag
+            .data
+            .as_ref()
+            .and_then(|d| d.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        for (i, suggestion) in suggestions.into_iter().enumerate() {
+            let mut changes = HashMap::new();
+            changes.insert(
+                uri.clone(),
+                vec![TextEdit { range: diag.range, new_text: suggestion.to_string() }],
+            );
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title: format!("Replace with '{}'", suggestion),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diag.clone()]),
+                edit: Some(WorkspaceEdit { changes: Some(changes), ..Default::default() }),
+                is_preferred: Some(i == 0),
+                ..Default::default()
+            }));
+        }
+    }
+
+    actions
+}
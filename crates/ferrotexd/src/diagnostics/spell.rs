@@ -0,0 +1,577 @@
+use ferrotex_syntax::{SyntaxKind, SyntaxNode, SyntaxToken, TextRange, TextSize};
+use crate::position::LineIndex;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tower_lsp::lsp_types::{
+    CodeActionOrCommand, Diagnostic, DiagnosticSeverity, NumberOrString, Url,
+};
+
+/// Language used when neither a magic comment nor a setting selects one.
+pub const DEFAULT_LANGUAGE: &str = "en_US";
+
+/// Diagnostic code attached to every spelling diagnostic.
+pub const SPELLING_CODE: &str = "spelling";
+
+/// Commands whose arguments are identifiers, paths or options rather than prose.
+const NON_PROSE_COMMANDS: &[&str] = &[
+    "\\begin", "\\end", "\\usepackage", "\\RequirePackage", "\\documentclass", "\\includegraphics",
+    "\\graphicspath", "\\url", "\\eqref", "\\pageref", "\\autoref", "\\cref", "\\Cref",
+    "\\citep", "\\citet", "\\parencite", "\\textcite", "\\autocite", "\\nocite", "\\bibliographystyle",
+    "\\newcommand", "\\renewcommand", "\\providecommand", "\\newenvironment", "\\renewenvironment",
+    "\\definecolor", "\\color", "\\hypersetup", "\\setlength", "\\addtolength",
+    "\\vspace", "\\hspace", "\\pagestyle", "\\thispagestyle", "\\pagenumbering", "\\geometry",
+    "\\usetikzlibrary", "\\setcounter", "\\addtocounter", "\\input", "\\include", "\\verb",
+];
+
+/// Commands whose first argument is an identifier but whose second argument is prose.
+const FIRST_ARGUMENT_ONLY: &[&str] = &["\\href", "\\textcolor"];
+
+/// Node kinds whose group argument is never prose.
+const NON_PROSE_NODES: &[SyntaxKind] = &[
+    SyntaxKind::LabelDefinition,
+    SyntaxKind::LabelReference,
+    SyntaxKind::Citation,
+    SyntaxKind::Include,
+    SyntaxKind::Bibliography,
+];
+
+/// Hunspell dictionaries are stored under these directories on most systems.
+const DICTIONARY_DIRS: &[&str] = &[
+    "/usr/share/hunspell",
+    "/usr/share/myspell",
+    "/usr/share/myspell/dicts",
+    "/usr/local/share/hunspell",
+    "/Library/Spelling",
+];
+
+lazy_static::lazy_static! {
+    static ref DICTIONARIES: Mutex<HashMap<String, Option<Arc<Dictionary>>>> = Mutex::new(HashMap::new());
+    static ref SPELLCHECK_COMMENT: Regex = Regex::new(r"(?mi)^%\s*!TeX\s+spellcheck\s*=\s*(\S+)").unwrap();
+}
+
+/// A word list loaded from a Hunspell-style `.dic`/`.aff` pair.
+///
+/// Only prefix and suffix rules are expanded; compounding and the more exotic
+/// affix options are ignored, which is enough for prose checking.
+#[derive(Debug, Default)]
+pub struct Dictionary {
+    words: HashSet<String>,
+}
+
+#[derive(Debug)]
+struct AffixRule {
+    strip: String,
+    add: String,
+    condition: Vec<CharClass>,
+}
+
+#[derive(Debug)]
+enum CharClass {
+    Any,
+    OneOf(Vec<char>),
+    NoneOf(Vec<char>),
+    Exact(char),
+}
+
+impl CharClass {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            CharClass::Any => true,
+            CharClass::OneOf(set) => set.contains(&c),
+            CharClass::NoneOf(set) => !set.contains(&c),
+            CharClass::Exact(e) => *e == c,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Affixes {
+    prefixes: HashMap<String, Vec<AffixRule>>,
+    suffixes: HashMap<String, Vec<AffixRule>>,
+    flag_mode: FlagMode,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+enum FlagMode {
+    #[default]
+    Char,
+    Long,
+    Num,
+}
+
+impl Dictionary {
+    /// Builds a dictionary from the contents of a `.dic` file and, optionally, its `.aff` file.
+    pub fn from_hunspell(dic: &str, aff: Option<&str>) -> Self {
+        let affixes = aff.map(parse_affixes).unwrap_or_default();
+        let mut words = HashSet::new();
+
+        for (i, line) in dic.lines().enumerate() {
+            let line = line.trim();
+            // The first line holds the approximate word count.
+            if line.is_empty() || (i == 0 && line.chars().all(|c| c.is_ascii_digit())) {
+                continue;
+            }
+            let entry = line.split(['\t', ' ']).next().unwrap_or(line);
+            let (word, flags) = match entry.split_once('/') {
+                Some((w, f)) => (w, f),
+                None => (entry, ""),
+            };
+
+            for flag in split_flags(flags, affixes.flag_mode) {
+                for rule in affixes.suffixes.get(&flag).into_iter().flatten() {
+                    if let Some(form) = apply_suffix(word, rule) {
+                        words.insert(form);
+                    }
+                }
+                for rule in affixes.prefixes.get(&flag).into_iter().flatten() {
+                    if let Some(form) = apply_prefix(word, rule) {
+                        words.insert(form);
+                    }
+                }
+            }
+            words.insert(word.to_string());
+        }
+
+        Self { words }
+    }
+
+    /// Loads the dictionary for `lang` (e.g. `en_US`) from the standard Hunspell locations.
+    ///
+    /// Directories listed in the `DICPATH` environment variable are searched first.
+    pub fn load(lang: &str) -> Option<Self> {
+        let mut dirs: Vec<PathBuf> = std::env::var("DICPATH")
+            .map(|v| std::env::split_paths(&v).collect())
+            .unwrap_or_default();
+        dirs.extend(DICTIONARY_DIRS.iter().map(PathBuf::from));
+        if let Some(home) = dirs::home_dir() {
+            dirs.push(home.join("Library/Spelling"));
+        }
+        if let Some(data) = dirs::data_dir() {
+            dirs.push(data.join("hunspell"));
+        }
+
+        dirs.into_iter().find_map(|dir| {
+            let dic = std::fs::read_to_string(dir.join(format!("{}.dic", lang))).ok()?;
+            let aff = std::fs::read_to_string(dir.join(format!("{}.aff", lang))).ok();
+            Some(Self::from_hunspell(&dic, aff.as_deref()))
+        })
+    }
+
+    /// Returns `true` if the word is known, accepting capitalised forms of lowercase entries.
+    pub fn contains(&self, word: &str) -> bool {
+        if self.words.contains(word) {
+            return true;
+        }
+        let lower = word.to_lowercase();
+        if self.words.contains(&lower) {
+            return true;
+        }
+        // "PARIS" is fine when the dictionary knows "Paris", "paris" is not.
+        if word == lower {
+            return false;
+        }
+        let mut chars = lower.chars();
+        let capitalised = match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+            None => return false,
+        };
+        self.words.contains(&capitalised)
+    }
+
+    /// Returns up to `limit` known words within edit distance one of `word`.
+    pub fn suggest(&self, word: &str, limit: usize) -> Vec<String> {
+        let chars: Vec<char> = word.chars().collect();
+        let lower = word.to_lowercase();
+        let mut alphabet: Vec<char> = ('a'..='z').collect();
+        for c in lower.chars() {
+            if !alphabet.contains(&c) {
+                alphabet.push(c);
+            }
+        }
+
+        let mut candidates = Vec::new();
+        for i in 0..=chars.len() {
+            if i < chars.len() {
+                // Deletion
+                candidates.push(splice(&chars, i, 1, &[]));
+                // Substitution
+                for &c in &alphabet {
+                    candidates.push(splice(&chars, i, 1, &[c]));
+                }
+            }
+            if i + 1 < chars.len() {
+                // Transposition
+                candidates.push(splice(&chars, i, 2, &[chars[i + 1], chars[i]]));
+            }
+            // Insertion
+            for &c in &alphabet {
+                candidates.push(splice(&chars, i, 0, &[c]));
+            }
+        }
+
+        let mut seen = HashSet::new();
+        candidates
+            .into_iter()
+            .filter(|c| c != word && self.contains(c) && seen.insert(c.clone()))
+            .take(limit)
+            .collect()
+    }
+}
+
+fn splice(chars: &[char], at: usize, remove: usize, insert: &[char]) -> String {
+    chars[..at]
+        .iter()
+        .chain(insert)
+        .chain(&chars[at + remove..])
+        .collect()
+}
+
+fn split_flags(flags: &str, mode: FlagMode) -> Vec<String> {
+    match mode {
+        FlagMode::Char => flags.chars().map(|c| c.to_string()).collect(),
+        FlagMode::Long => flags
+            .chars()
+            .collect::<Vec<_>>()
+            .chunks(2)
+            .map(|c| c.iter().collect())
+            .collect(),
+        FlagMode::Num => flags.split(',').map(|f| f.trim().to_string()).collect(),
+    }
+}
+
+fn parse_affixes(aff: &str) -> Affixes {
+    let mut affixes = Affixes::default();
+
+    for line in aff.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            ["FLAG", "long", ..] => affixes.flag_mode = FlagMode::Long,
+            ["FLAG", "num", ..] => affixes.flag_mode = FlagMode::Num,
+            [kind @ ("PFX" | "SFX"), flag, strip, add, rest @ ..] => {
+                // Header lines look like `SFX D Y 4`; rule lines have a condition.
+                if matches!(*add, s if s.parse::<usize>().is_ok()) && rest.is_empty() {
+                    continue;
+                }
+                let add = add.split('/').next().unwrap_or("");
+                let rule = AffixRule {
+                    strip: if *strip == "0" { String::new() } else { strip.to_string() },
+                    add: if add == "0" { String::new() } else { add.to_string() },
+                    condition: parse_condition(rest.first().copied().unwrap_or(".")),
+                };
+                let table = if *kind == "PFX" { &mut affixes.prefixes } else { &mut affixes.suffixes };
+                table.entry(flag.to_string()).or_default().push(rule);
+            }
+            _ => {}
+        }
+    }
+
+    affixes
+}
+
+fn parse_condition(cond: &str) -> Vec<CharClass> {
+    let mut classes = Vec::new();
+    let mut chars = cond.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => classes.push(CharClass::Any),
+            '[' => {
+                let mut set = Vec::new();
+                let mut negated = false;
+                for (i, n) in chars.by_ref().enumerate() {
+                    match n {
+                        ']' => break,
+                        '^' if i == 0 => negated = true,
+                        _ => set.push(n),
+                    }
+                }
+                classes.push(if negated { CharClass::NoneOf(set) } else { CharClass::OneOf(set) });
+            }
+            _ => classes.push(CharClass::Exact(c)),
+        }
+    }
+    classes
+}
+
+fn apply_suffix(word: &str, rule: &AffixRule) -> Option<String> {
+    let chars: Vec<char> = word.chars().collect();
+    if chars.len() < rule.condition.len() {
+        return None;
+    }
+    let tail = &chars[chars.len() - rule.condition.len()..];
+    if !tail.iter().zip(&rule.condition).all(|(c, cls)| cls.matches(*c)) {
+        return None;
+    }
+    let stem = word.strip_suffix(rule.strip.as_str())?;
+    Some(format!("{}{}", stem, rule.add))
+}
+
+fn apply_prefix(word: &str, rule: &AffixRule) -> Option<String> {
+    let chars: Vec<char> = word.chars().collect();
+    if chars.len() < rule.condition.len() {
+        return None;
+    }
+    if !chars.iter().zip(&rule.condition).all(|(c, cls)| cls.matches(*c)) {
+        return None;
+    }
+    let stem = word.strip_prefix(rule.strip.as_str())?;
+    Some(format!("{}{}", rule.add, stem))
+}
+
+/// Returns the (cached) dictionary for `lang`, or `None` if it is not installed.
+///
+/// Reading and expanding a Hunspell dictionary takes a while, so the first load runs on
+/// the blocking pool, without holding the cache lock.
+pub async fn dictionary(lang: &str) -> Option<Arc<Dictionary>> {
+    let cached = DICTIONARIES.lock().unwrap().get(lang).cloned();
+    if let Some(dictionary) = cached {
+        return dictionary;
+    }
+    let name = lang.to_string();
+    let loaded = tokio::task::spawn_blocking(move || Dictionary::load(&name).map(Arc::new)).await.ok().flatten();
+    DICTIONARIES.lock().unwrap().entry(lang.to_string()).or_insert(loaded).clone()
+}
+
+/// Reads the `% !TeX spellcheck = <lang>` magic comment from the head of the document.
+pub fn spellcheck_language(text: &str) -> Option<String> {
+    let head = if text.len() > 1024 {
+        let mut end = 1024;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        &text[..end]
+    } else {
+        text
+    };
+    SPELLCHECK_COMMENT.captures(head).map(|cap| cap[1].trim().to_string())
+}
+
+/// Checks prose in the document against `dictionary`.
+///
+/// Each diagnostic carries its replacement suggestions in `data`, which
+/// [`suggestion_actions`] turns into quick fixes.
+pub fn check_spelling(root: &SyntaxNode, line_index: &LineIndex, dictionary: &Dictionary) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for (range, word) in prose_words(root) {
+        if dictionary.contains(&word) {
+            continue;
+        }
+        diagnostics.push(Diagnostic {
+            range: line_index.range(range),
+            severity: Some(DiagnosticSeverity::INFORMATION),
+            code: Some(NumberOrString::String(SPELLING_CODE.to_string())),
+            source: Some("ferrotex-spell".to_string()),
+            message: format!("Unknown word: '{}'", word),
+            data: Some(serde_json::json!(dictionary.suggest(&word, 5))),
+            ..Default::default()
+        });
+    }
+
+    diagnostics
+}
+
+/// Builds "replace with" quick fixes for the spelling diagnostics in `diagnostics`.
+pub fn suggestion_actions(uri: &Url, diagnostics: &[Diagnostic]) -> Vec<CodeActionOrCommand> {
+    super::replacement_actions(uri, diagnostics, |diag| {
+        diag.code == Some(NumberOrString::String(SPELLING_CODE.to_string()))
+    })
+}
+
+/// Extracts the words of running text, skipping commands, math, verbatim and identifier arguments.
+fn prose_words(root: &SyntaxNode) -> Vec<(TextRange, String)> {
+    let mut words = Vec::new();
+    let mut in_math = false;
+
+    for token in root.descendants_with_tokens().filter_map(|e| e.into_token()) {
+        match token.kind() {
+            SyntaxKind::Dollar => in_math = !in_math,
+            SyntaxKind::Command => match token.text() {
+                "\\(" | "\\[" => in_math = true,
+                "\\)" | "\\]" => in_math = false,
+                _ => {}
+            },
+            SyntaxKind::Text => {
+                if in_math
+                    || super::in_verbatim(&token)
+                    || super::in_environment(&token, super::MATH_ENVIRONMENTS)
+                    || in_non_prose_argument(&token)
+                    || in_command_option(&token)
+                {
+                    continue;
+                }
+                collect_words(&token, &mut words);
+            }
+            _ => {}
+        }
+    }
+
+    words
+}
+
+fn collect_words(token: &SyntaxToken, words: &mut Vec<(TextRange, String)>) {
+    let text = token.text();
+    let base = token.text_range().start();
+    let mut start: Option<usize> = None;
+
+    let flush = |from: usize, to: usize, words: &mut Vec<(TextRange, String)>| {
+        let word = text[from..to].trim_matches('\'');
+        let offset = from + text[from..to].find(word).unwrap_or(0);
+        let skip = word.chars().count() < 2
+            || word.chars().all(|c| c.is_uppercase())
+            || word.chars().any(|c| c.is_ascii_digit());
+        if !skip {
+            words.push((
+                TextRange::at(base + TextSize::from(offset as u32), TextSize::of(word)),
+                word.to_string(),
+            ));
+        }
+    };
+
+    for (i, c) in text.char_indices() {
+        let in_word = c.is_alphanumeric() || c == '\'';
+        match (in_word, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                flush(s, i, words);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        flush(s, text.len(), words);
+    }
+}
+
+/// Returns `true` if the token sits in a `{...}` argument of an identifier-taking command.
+pub(crate) fn in_non_prose_argument(token: &SyntaxToken) -> bool {
+    token
+        .parent_ancestors()
+        .filter(|n| n.kind() == SyntaxKind::Group)
+        .any(|group| {
+            if group.parent().is_some_and(|p| NON_PROSE_NODES.contains(&p.kind())) {
+                return true;
+            }
+            let mut prev = group.prev_sibling_or_token();
+            let mut groups_before = 0;
+            let mut in_option = false;
+            while let Some(element) = prev {
+                match element.kind() {
+                    SyntaxKind::RBracket if !in_option => in_option = true,
+                    SyntaxKind::LBracket if in_option => in_option = false,
+                    _ if in_option => {}
+                    SyntaxKind::Group => groups_before += 1,
+                    SyntaxKind::Command => {
+                        let text = element.to_string();
+                        if FIRST_ARGUMENT_ONLY.contains(&text.as_str()) {
+                            return groups_before == 0;
+                        }
+                        return NON_PROSE_COMMANDS.contains(&text.as_str());
+                    }
+                    _ => return false,
+                }
+                prev = element.prev_sibling_or_token();
+            }
+            false
+        })
+}
+
+/// Returns `true` if the token sits in a `[...]` option list directly following a command.
+pub(crate) fn in_command_option(token: &SyntaxToken) -> bool {
+    let mut prev = token.prev_sibling_or_token();
+    while let Some(element) = prev {
+        match element.kind() {
+            SyntaxKind::RBracket => return false,
+            SyntaxKind::LBracket => {
+                return element
+                    .prev_sibling_or_token()
+                    .is_some_and(|p| matches!(p.kind(), SyntaxKind::Command | SyntaxKind::Group));
+            }
+            _ => {}
+        }
+        prev = element.prev_sibling_or_token();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferrotex_syntax::parse;
+
+    const DIC: &str = "6\nhello\nworld\nthe\ncat/S\nwalk/DG\nParis\n";
+    const AFF: &str = "SFX S Y 1\nSFX S 0 s .\nSFX D Y 1\nSFX D 0 ed [^e]\nSFX G Y 1\nSFX G 0 ing .\n";
+
+    fn dictionary() -> Dictionary {
+        Dictionary::from_hunspell(DIC, Some(AFF))
+    }
+
+    fn unknown_words(input: &str) -> Vec<String> {
+        let parsed = parse(input);
+        let line_index = LineIndex::new(input);
+        check_spelling(&parsed.syntax(), &line_index, &dictionary())
+            .into_iter()
+            .map(|d| d.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_dictionary_affix_expansion() {
+        let dict = dictionary();
+        assert!(dict.contains("cats"));
+        assert!(dict.contains("walked"));
+        assert!(dict.contains("walking"));
+        assert!(dict.contains("Hello"));
+        assert!(!dict.contains("paris"));
+        assert!(!dict.contains("wrld"));
+    }
+
+    #[test]
+    fn test_dictionary_suggestions() {
+        let dict = dictionary();
+        assert_eq!(dict.suggest("wrld", 5), vec!["world"]);
+        assert!(dict.suggest("hlelo", 5).contains(&"hello".to_string()));
+    }
+
+    #[test]
+    fn test_spelling_skips_markup() {
+        let input = r"\section{Hello} the cat $x + yz$ \label{sec:foo} \begin{verbatim}qwerty\end{verbatim}";
+        assert!(unknown_words(input).is_empty(), "{:?}", unknown_words(input));
+    }
+
+    #[test]
+    fn test_spelling_reports_unknown_words() {
+        let input = r"Hello wrld, \textbf{the catz}";
+        assert_eq!(unknown_words(input), vec!["Unknown word: 'wrld'", "Unknown word: 'catz'"]);
+    }
+
+    #[test]
+    fn test_spelling_skips_command_options() {
+        let input = r"\includegraphics[width=5cm]{figure-one} the cat";
+        assert!(unknown_words(input).is_empty());
+    }
+
+    #[test]
+    fn test_spellcheck_magic_comment() {
+        assert_eq!(spellcheck_language("% !TeX spellcheck = de_DE\nText"), Some("de_DE".to_string()));
+        assert_eq!(spellcheck_language("Text"), None);
+    }
+
+    #[test]
+    fn test_suggestion_actions() {
+        let input = "Hello wrld";
+        let parsed = parse(input);
+        let line_index = LineIndex::new(input);
+        let diags = check_spelling(&parsed.syntax(), &line_index, &dictionary());
+        let uri = Url::parse("file:///test.tex").unwrap();
+
+        let actions = suggestion_actions(&uri, &diags);
+        assert_eq!(actions.len(), 1);
+        match &actions[0] {
+            CodeActionOrCommand::CodeAction(action) => assert_eq!(action.title, "Replace with 'world'"),
+            _ => panic!("Expected a code action"),
+        }
+    }
+}
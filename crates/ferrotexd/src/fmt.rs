@@ -1,15 +1,127 @@
-use ferrotex_syntax::{SyntaxKind, SyntaxNode};
+use crate::diagnostics::{MATH_ENVIRONMENTS, VERBATIM_ENVIRONMENTS, in_environment, in_verbatim};
+use crate::position::LineIndex;
+use crate::settings::FormatSettings;
+use ferrotex_syntax::table::table_rows;
+use ferrotex_syntax::{SyntaxKind, SyntaxNode, TextRange};
 use rowan::NodeOrToken;
-use tower_lsp::lsp_types::{Position, Range, TextEdit};
+use std::collections::HashMap;
+use tower_lsp::lsp_types::{FormattingOptions, Position, Range, TextEdit};
+
+/// Options controlling the output of [`format_document`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Width of one indentation level in spaces (ignored when indenting with tabs).
+    pub tab_size: usize,
+    /// Indent with spaces instead of tabs.
+    pub insert_spaces: bool,
+    /// Remove whitespace at the end of lines.
+    pub trim_trailing_whitespace: bool,
+    /// Ensure the document ends with a newline.
+    pub insert_final_newline: bool,
+    /// Collapse trailing blank lines at the end of the document.
+    pub trim_final_newlines: bool,
+    /// Extra indentation levels for lines inside a multi-line brace group.
+    pub continuation_indent: usize,
+    /// Break prose lines longer than this column. `None` disables wrapping.
+    pub wrap_column: Option<usize>,
+    /// Sort entries by key when formatting `.bib` files.
+    pub sort_bib_entries: bool,
+}
+
+/// Environments whose `&` separators are lined up into columns.
+const ALIGNMENT_ENVIRONMENTS: &[&str] = &[
+    "tabular", "tabular*", "tabularx", "tabulary", "longtable", "array",
+    "align", "align*", "alignat", "alignat*", "flalign", "flalign*", "aligned", "alignedat", "split",
+    "eqnarray", "eqnarray*", "cases",
+    "matrix", "pmatrix", "bmatrix", "Bmatrix", "vmatrix", "Vmatrix", "smallmatrix",
+];
+
+/// Commands whose arguments must stay on one line (URLs, paths, inline code).
+const NO_WRAP_COMMANDS: &[&str] = &[
+    "\\verb", "\\url", "\\href", "\\path", "\\lstinline", "\\mintinline", "\\nolinkurl",
+];
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            tab_size: 4,
+            insert_spaces: true,
+            trim_trailing_whitespace: false,
+            insert_final_newline: false,
+            trim_final_newlines: false,
+            continuation_indent: 0,
+            wrap_column: None,
+            sort_bib_entries: false,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Combines the client's per-request options with the server settings.
+    pub fn new(options: &FormattingOptions, settings: &FormatSettings) -> Self {
+        Self {
+            tab_size: settings.indent_size.unwrap_or(options.tab_size as usize),
+            insert_spaces: options.insert_spaces,
+            trim_trailing_whitespace: options.trim_trailing_whitespace.unwrap_or(false),
+            insert_final_newline: options.insert_final_newline.unwrap_or(false),
+            trim_final_newlines: options.trim_final_newlines.unwrap_or(false),
+            continuation_indent: settings.continuation_indent,
+            wrap_column: settings.wrap_column,
+            sort_bib_entries: settings.bib_sort_entries,
+        }
+    }
+
+    /// Returns the leading whitespace for a line at the given indentation level.
+    pub fn indent(&self, level: usize) -> String {
+        if self.insert_spaces {
+            " ".repeat(level * self.tab_size)
+        } else {
+            "\t".repeat(level)
+        }
+    }
+}
+
+/// Formats `text`, a `.bib` file if `bib` or else a LaTeX document, and returns the
+/// result. For files outside the editor, e.g. `ferrotex fmt`.
+pub fn format_text(text: &str, bib: bool, options: &FormatOptions) -> String {
+    let line_index = LineIndex::new(text);
+    let edits = if bib {
+        crate::bib_fmt::format_bibtex(text, &line_index, options)
+    } else {
+        format_document(&ferrotex_syntax::parse(text).syntax(), &line_index, options)
+    };
+    apply_text_edits(text, &line_index, edits)
+}
+
+/// Applies non-overlapping `edits` to `text`, from the bottom up so earlier offsets stay
+/// valid. Edits outside the text are dropped.
+pub fn apply_text_edits(text: &str, line_index: &LineIndex, mut edits: Vec<TextEdit>) -> String {
+    edits.sort_by_key(|e| std::cmp::Reverse((e.range.start.line, e.range.start.character)));
+    let mut result = text.to_string();
+    for edit in edits {
+        let (Some(start), Some(end)) = (line_index.offset(edit.range.start), line_index.offset(edit.range.end)) else {
+            continue;
+        };
+        let (start, end) = (usize::from(start), usize::from(end).min(result.len()));
+        if start <= end {
+            result.replace_range(start..end, &edit.new_text);
+        }
+    }
+    result
+}
 
 /// Formats the entire document represented by `root`.
 ///
 /// This is a conservative formatter. It primarily focuses on:
 /// 1. Correcting indentation for environment blocks.
-/// 2. Trimming trailing whitespace (optional, effectively side-effect of correct indentation if line is re-emitted).
+/// 2. Indenting continuation lines of multi-line brace groups (see [`FormatOptions::continuation_indent`]).
+/// 3. Trimming trailing whitespace and final newlines, if requested by the client.
+/// 4. Lining up the `&` separators of `tabular`, `align` and `matrix`-like environments.
+/// 5. Breaking long prose lines, if [`FormatOptions::wrap_column`] is set.
 ///
-/// It does NOT aggressively reflow text or change line breaks.
-pub fn format_document(root: &SyntaxNode, line_index: &line_index::LineIndex) -> Vec<TextEdit> {
+/// It does NOT aggressively reflow text or change line breaks, and never edits the body
+/// of a verbatim-like environment (see [`VERBATIM_ENVIRONMENTS`]).
+pub fn format_document(root: &SyntaxNode, line_index: &LineIndex, options: &FormatOptions) -> Vec<TextEdit> {
     let mut edits = Vec::new();
     // let indent_level = 0;
 
@@ -163,14 +275,129 @@ pub fn format_document(root: &SyntaxNode, line_index: &line_index::LineIndex) ->
         }
     }
 
+    let target_indents = target_indents(root, line_index, lines.len(), options);
+    let wrappable = match options.wrap_column {
+        Some(_) => wrappable_lines(root, line_index, lines.len()),
+        None => Vec::new(),
+    };
+
+    let aligned_rows = aligned_rows(root, line_index, &text);
+    let verbatim = verbatim_lines(root, line_index, lines.len());
+
+    // Generate Edits
+    for (i, line_content) in lines.iter().enumerate() {
+        if verbatim[i] {
+            // Code samples keep their whitespace exactly as written.
+            continue;
+        }
+        let trimmed = line_content.trim_start();
+        if let Some(row) = aligned_rows.get(&i) {
+            let new_line = format!("{}{}", options.indent(target_indents[i]), row);
+            if new_line != *line_content {
+                edits.push(TextEdit {
+                    range: line_range(line_index, i, 0, line_content.len()),
+                    new_text: new_line,
+                });
+            }
+            continue;
+        }
+        if let Some(column) = options.wrap_column.filter(|_| wrappable[i]) {
+            let indent = options.indent(target_indents[i]);
+            if let Some(wrapped) = wrap_line(trimmed, &indent, column, options.tab_size) {
+                // The whole line is rewritten, which also fixes its indentation.
+                edits.push(TextEdit {
+                    range: line_range(line_index, i, 0, line_content.len()),
+                    new_text: wrapped,
+                });
+                continue;
+            }
+        }
+        if trimmed.trim_end().is_empty() {
+            // Don't indent empty lines, but drop whitespace-only content if requested.
+            if options.trim_trailing_whitespace && !line_content.is_empty() {
+                edits.push(TextEdit {
+                    range: line_range(line_index, i, 0, line_content.len()),
+                    new_text: String::new(),
+                });
+            }
+            continue;
+        }
+
+        let current_indent_str = &line_content[..(line_content.len() - trimmed.len())];
+        let target_str = options.indent(target_indents[i]);
+        if current_indent_str != target_str {
+            // Replace indentation
+            edits.push(TextEdit {
+                range: line_range(line_index, i, 0, current_indent_str.len()),
+                new_text: target_str,
+            });
+        }
+
+        if options.trim_trailing_whitespace {
+            let content_end = line_content.trim_end().len();
+            if content_end < line_content.len() {
+                edits.push(TextEdit {
+                    range: line_range(line_index, i, content_end, line_content.len()),
+                    new_text: String::new(),
+                });
+            }
+        }
+    }
+
+    edits.extend(final_newline_edit(&text, &lines, line_index, options));
+
+    edits
+}
+
+/// Re-indents the line at `position` after the client typed `ch`.
+///
+/// After a newline the fresh line is indented to the current environment depth, so
+/// pressing Enter after `\begin{itemize}` indents the next line. After a brace, a line
+/// starting with `\end` or `}` is outdented to match its opening line. Other lines are
+/// left alone so typing a brace mid-sentence never moves text around.
+pub fn format_on_type(
+    root: &SyntaxNode,
+    line_index: &LineIndex,
+    position: Position,
+    ch: &str,
+    options: &FormatOptions,
+) -> Vec<TextEdit> {
+    let text = root.to_string();
+    let line = position.line as usize;
+    let Some(line_content) = text.split('\n').nth(line) else {
+        return Vec::new();
+    };
+    let line_content = line_content.trim_end_matches('\r');
+    let trimmed = line_content.trim_start();
+
+    let reindent = match ch {
+        "\n" => true,
+        "{" | "}" => trimmed.starts_with("\\end") || trimmed.starts_with('}'),
+        _ => false,
+    };
+    if !reindent || verbatim_lines(root, line_index, line + 1)[line] {
+        return Vec::new();
+    }
+
+    let target = options.indent(target_indents(root, line_index, line + 1, options)[line]);
+    let current = &line_content[..line_content.len() - trimmed.len()];
+    if current == target {
+        return Vec::new();
+    }
+    vec![TextEdit {
+        range: line_range(line_index, line, 0, current.len()),
+        new_text: target,
+    }]
+}
+
+/// Computes the indentation level of every line in `0..line_count`.
+fn target_indents(root: &SyntaxNode, line_index: &LineIndex, line_count: usize, options: &FormatOptions) -> Vec<usize> {
     // Correct strategy:
     // Walk tokens. Track `current_indent`.
     // When we hit new line, record `current_indent`.
     // Special case: if a line *starts* with `\end` (ignoring whitespace), it should use `current_indent - 1`.
 
-    // Let's do a concrete walk.
-    // let current_indent = 0;
-    let mut target_indents = vec![0; lines.len()];
+    let mut target_indents = vec![0; line_count];
 
     // We need to iterate lines and find the "indentation critical tokens" on them.
     // But lines are not nodes.
@@ -184,136 +411,359 @@ pub fn format_document(root: &SyntaxNode, line_index: &line_index::LineIndex) ->
     // 1. Identify lines that contain `\begin` or `\end` as significant tokens.
     // 2. Determine net effect on indent.
 
-    let mut line_effects = vec![(0isize, 0isize); lines.len()]; // (pre_adjustment, post_adjustment)
+    let mut line_effects = vec![(0isize, 0isize); line_count]; // (pre_adjustment, post_adjustment)
     // pre_adjustment: applied to THIS line (e.g. \end)
     // post_adjustment: applied to NEXT line (e.g. \begin)
+    let mut brace_effects = vec![(0isize, 0isize); line_count];
+    // Same model for braces, except only a `}` that starts its line outdents that line.
+    let mut last_line = None;
+    // Braces and `\begin` inside code samples do not affect the surrounding indentation.
+    let verbatim = verbatim_lines(root, line_index, line_count);
 
     for token in root
         .descendants_with_tokens()
         .filter_map(|e| e.into_token())
     {
-        if token.kind() == SyntaxKind::Command {
-            let txt = token.text();
-            let line = line_index.line_col(token.text_range().start()).line as usize;
-            if line >= lines.len() {
-                continue;
-            }
+        let line = line_index.line_col(token.text_range().start()).line as usize;
+        if line >= line_count || verbatim[line] {
+            continue;
+        }
+        let starts_line = token.kind() != SyntaxKind::Whitespace && last_line != Some(line);
+        if token.kind() != SyntaxKind::Whitespace {
+            last_line = Some(line);
+        }
 
-            if txt == "\\begin" {
-                line_effects[line].1 += 1; // Increment for next line
-            } else if txt == "\\end" {
-                line_effects[line].0 -= 1; // Decrement for this line
-                // If we decrement for this line, we IMPLICITLY decrement for next line too purely by state carry-over?
-                // No, we need to model the state machine.
+        match token.kind() {
+            SyntaxKind::Command => {
+                let txt = token.text();
+                if txt == "\\begin" {
+                    line_effects[line].1 += 1; // Increment for next line
+                } else if txt == "\\end" {
+                    line_effects[line].0 -= 1; // Decrement for this line
+                }
             }
+            SyntaxKind::LBrace => brace_effects[line].1 += 1,
+            SyntaxKind::RBrace if starts_line => brace_effects[line].0 -= 1,
+            SyntaxKind::RBrace => brace_effects[line].1 -= 1,
+            _ => {}
         }
     }
 
     // Now compute state
     let mut depth = 0isize;
-    for (i, (pre, post)) in line_effects.iter().enumerate() {
-        // "pre" affects the current line's visuals (like \end usually outdents itself)
-        // But strictly, formatting state is:
-        // Start of line depth = End of previous line depth.
-        // Then we apply "pre" modifiers?
-
-        // Actually:
+    let mut brace_depth = 0isize;
+    for (i, ((pre, post), (brace_pre, brace_post))) in line_effects.iter().zip(&brace_effects).enumerate() {
         // Depth at start of Line i = Depth at end of Line i-1.
-        // But if Line i contains `\end` at the start, we want to visually render it with -1.
-        // AND calculate end-of-line depth with -1.
+        // A line containing `\end` is rendered one level lower, and so is everything after it:
+        // \begin: pre=0, post=1. Net +1.
+        // \end: pre=-1, post=0. Net -1.
+        // \begin \end: pre=-1, post=1. Net 0.
+        let visual_depth = (depth + pre).max(0) as usize;
+        let visual_braces = (brace_depth + brace_pre).max(0) as usize;
+        target_indents[i] = visual_depth + visual_braces * options.continuation_indent;
 
-        let visual_depth = depth + pre;
-        target_indents[i] = visual_depth.max(0) as usize;
+        depth += pre + post;
+        brace_depth = (brace_depth + brace_pre + brace_post).max(0);
+    }
 
-        // Calculate depth for next line
-        // Net change for this line is (count(\begin) - count(\end))?
-        // My line_effects logic:
-        // \begin: post += 1
-        // \end: pre -= 1
+    target_indents
+}
 
-        // Total delta = pre + post?
-        // \begin: pre=0, post=1. Net +1. Correct.
-        // \end: pre=-1, post=0. Net -1. Correct.
-        // \begin \end: pre=-1, post=1. Net 0. Correct.
+/// Marks the body lines of verbatim-like environments.
+///
+/// The `\begin` and `\end` lines themselves are not part of the body and are indented
+/// like any other line.
+fn verbatim_lines(root: &SyntaxNode, line_index: &LineIndex, line_count: usize) -> Vec<bool> {
+    let mut verbatim = vec![false; line_count];
+    let environments = root.descendants().filter(|node| {
+        node.kind() == SyntaxKind::Environment
+            && crate::workspace::extract_group_text(node).is_some_and(|name| VERBATIM_ENVIRONMENTS.contains(&name.as_str()))
+    });
 
-        depth += pre + post;
+    for env in environments {
+        let first = line_index.line_col(env.text_range().start()).line as usize + 1;
+        let end = env
+            .children_with_tokens()
+            .filter_map(|e| e.into_token())
+            .filter(|t| t.kind() == SyntaxKind::Command && t.text() == "\\end")
+            .last();
+        // An unclosed environment runs to the end of the document.
+        let last = match end {
+            Some(end) => (line_index.line_col(end.text_range().start()).line as usize).checked_sub(1),
+            None => Some(line_index.line_col(env.text_range().end()).line as usize),
+        };
+        let Some(last) = last else { continue };
+        for flag in verbatim.iter_mut().take(last + 1).skip(first) {
+            *flag = true;
+        }
     }
 
-    // Generate Edits
-    for (i, line_content) in lines.iter().enumerate() {
-        let trimmed = line_content.trim_start();
-        if trimmed.is_empty() {
-            // Don't indent empty lines
+    verbatim
+}
+
+/// Lays out the rows of alignment environments so their `&` separators line up.
+///
+/// Returns the new content (without indentation) keyed by line. Only rows whose
+/// separators and terminator all sit on one line are aligned; rows with multi-line
+/// cells are left as they are and do not affect the column widths.
+fn aligned_rows(root: &SyntaxNode, line_index: &LineIndex, text: &str) -> HashMap<usize, String> {
+    let mut result = HashMap::new();
+    let environments = root.descendants().filter(|node| {
+        node.kind() == SyntaxKind::Environment
+            && crate::workspace::extract_group_text(node).is_some_and(|name| ALIGNMENT_ENVIRONMENTS.contains(&name.as_str()))
+            && node.first_token().is_some_and(|t| !in_verbatim(&t))
+    });
+
+    for env in environments {
+        // (line, cells, trailing text after the last cell)
+        let mut rows: Vec<(usize, Vec<&str>, &str)> = Vec::new();
+        for row in table_rows(&env) {
+            if row.cells.len() < 2 {
+                continue;
+            }
+            let line_of = |offset| line_index.line_col(offset).line as usize;
+            let line = line_of(row.cells[1].start());
+            let Some(line_range) = line_index.line(line as u32) else {
+                continue;
+            };
+            let last = row.cells[row.cells.len() - 1];
+            let content_end = last.start() + ferrotex_syntax::TextSize::of(text[last].trim_end());
+            let row_end = row.terminator.map_or(content_end, |t| t.start());
+
+            let first_start = row.cells[0].start().max(line_range.start());
+            let on_one_line = row.cells.iter().skip(1).all(|c| line_of(c.start()) == line)
+                && line_of(row_end) == line
+                && row.terminator.is_none_or(|t| line_of(t.end()) == line);
+            let starts_line = text[TextRange::new(line_range.start(), first_start)].trim().is_empty();
+            // A first cell that continues from an earlier line counts only if part of it is
+            // on this line, so a leading `\hline` does not prevent alignment.
+            let first_cell = text[TextRange::new(first_start, row.cells[0].end())].trim();
+            let first_cell_here = row.cells[0].start() >= line_range.start() || !first_cell.is_empty();
+            if !on_one_line || !starts_line || !first_cell_here {
+                continue;
+            }
+
+            let mut cells: Vec<&str> = row.cells.iter().map(|c| text[*c].trim()).collect();
+            cells[0] = first_cell;
+            let tail = text[TextRange::new(row_end, line_range.end())].trim();
+            rows.push((line, cells, tail));
+        }
+
+        let mut widths: Vec<usize> = Vec::new();
+        for (_, cells, _) in &rows {
+            for (col, cell) in cells[..cells.len() - 1].iter().enumerate() {
+                let width = cell.chars().count();
+                match widths.get_mut(col) {
+                    Some(w) => *w = (*w).max(width),
+                    None => widths.push(width),
+                }
+            }
+        }
+
+        for (line, cells, tail) in rows {
+            let last = cells.len() - 1;
+            let mut content = cells
+                .iter()
+                .enumerate()
+                .map(|(col, cell)| {
+                    if col == last {
+                        cell.to_string()
+                    } else {
+                        format!("{:<width$}", cell, width = widths[col])
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" & ")
+                .trim_end()
+                .to_string();
+            if !tail.is_empty() {
+                content.push(' ');
+                content.push_str(tail);
+            }
+            result.entry(line).or_insert(content);
+        }
+    }
+
+    result
+}
+
+/// Marks the lines whose content is plain prose and may be broken by the wrapper.
+///
+/// A line is excluded if any of its tokens is math, verbatim, a comment or an argument
+/// of one of the [`NO_WRAP_COMMANDS`].
+fn wrappable_lines(root: &SyntaxNode, line_index: &LineIndex, line_count: usize) -> Vec<bool> {
+    let mut wrappable = vec![true; line_count];
+    let mut in_math = false;
+
+    for token in root.descendants_with_tokens().filter_map(|e| e.into_token()) {
+        if token.kind() == SyntaxKind::Whitespace {
+            continue;
+        }
+        let was_in_math = in_math;
+        let blocked = match token.kind() {
+            SyntaxKind::Dollar => {
+                in_math = !in_math;
+                true
+            }
+            SyntaxKind::Comment | SyntaxKind::Error => true,
+            SyntaxKind::Command => match token.text() {
+                "\\(" | "\\[" => {
+                    in_math = true;
+                    true
+                }
+                "\\)" | "\\]" => {
+                    in_math = false;
+                    true
+                }
+                cmd => NO_WRAP_COMMANDS.contains(&cmd),
+            },
+            _ => false,
+        };
+        let blocked = blocked
+            || was_in_math
+            || in_verbatim(&token)
+            || in_environment(&token, MATH_ENVIRONMENTS)
+            || in_no_wrap_argument(&token);
+        if !blocked {
             continue;
         }
 
-        let target_indent_count = target_indents[i] * 4; // 4 spaces
-        let current_indent_str = &line_content[..(line_content.len() - trimmed.len())];
-        let current_indent_count = current_indent_str.len(); // Assuming spaces. If tabs, this is fuzzy.
+        let first = line_index.line_col(token.text_range().start()).line as usize;
+        let last = line_index.line_col(token.text_range().end()).line as usize;
+        for flag in wrappable.iter_mut().take(last + 1).skip(first) {
+            *flag = false;
+        }
+    }
 
-        // If strict spaces:
-        let target_str = " ".repeat(target_indent_count);
-        if current_indent_str != target_str {
-            // Replace indentation
-            edits.push(TextEdit {
-                range: Range {
-                    start: Position {
-                        line: i as u32,
-                        character: 0,
-                    },
-                    end: Position {
-                        line: i as u32,
-                        character: current_indent_count as u32,
-                    },
-                },
-                new_text: target_str,
-            });
+    wrappable
+}
+
+/// Returns `true` if the token sits in a `{...}` argument of one of the [`NO_WRAP_COMMANDS`].
+fn in_no_wrap_argument(token: &ferrotex_syntax::SyntaxToken) -> bool {
+    token
+        .parent_ancestors()
+        .filter(|n| n.kind() == SyntaxKind::Group)
+        .any(|group| {
+            let mut prev = group.prev_sibling_or_token();
+            while let Some(element) = prev {
+                match element.kind() {
+                    SyntaxKind::Group => prev = element.prev_sibling_or_token(),
+                    SyntaxKind::Command => return NO_WRAP_COMMANDS.contains(&element.to_string().as_str()),
+                    _ => return false,
+                }
+            }
+            false
+        })
+}
+
+/// Greedily breaks `content` at spaces so every line fits in `column`.
+///
+/// Returns the replacement text (each line prefixed with `indent`), or `None` if the
+/// line already fits or cannot be broken.
+fn wrap_line(content: &str, indent: &str, column: usize, tab_size: usize) -> Option<String> {
+    let indent_width: usize = indent.chars().map(|c| if c == '\t' { tab_size } else { 1 }).sum();
+    if indent_width + content.trim_end().chars().count() <= column {
+        return None;
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for word in content.split_whitespace() {
+        // Breaking after a control space (`\ `) would leave a lone backslash.
+        let can_break = !current.is_empty() && !current.ends_with('\\');
+        if can_break && indent_width + current.chars().count() + 1 + word.chars().count() > column {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
         }
+        current.push_str(word);
     }
+    lines.push(current);
 
-    edits
+    if lines.len() < 2 {
+        return None;
+    }
+    Some(
+        lines
+            .iter()
+            .map(|line| format!("{}{}", indent, line))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+
+/// Computes the edit that fixes up the newlines at the end of the document, if any.
+fn final_newline_edit(text: &str, lines: &[&str], line_index: &LineIndex, options: &FormatOptions) -> Option<TextEdit> {
+    let content = text.trim_end_matches(['\n', '\r']);
+    if content.is_empty() {
+        return None;
+    }
+    let last_content_line = content.matches('\n').count();
+
+    if !text.ends_with('\n') {
+        if !options.insert_final_newline {
+            return None;
+        }
+        let column = lines[last_content_line].len();
+        let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+        return Some(TextEdit {
+            range: line_range(line_index, last_content_line, column, column),
+            new_text: newline.to_string(),
+        });
+    }
+
+    let last_line = text.matches('\n').count();
+    if options.trim_final_newlines && last_line > last_content_line + 1 {
+        return Some(TextEdit {
+            range: Range {
+                start: Position { line: (last_content_line + 1) as u32, character: 0 },
+                end: Position { line: last_line as u32, character: 0 },
+            },
+            new_text: String::new(),
+        });
+    }
+    None
+}
+
+/// Converts byte columns `start..end` on `line` into an LSP range.
+fn line_range(line_index: &LineIndex, line: usize, start: usize, end: usize) -> Range {
+    Range {
+        start: line_index.line_position(line as u32, start as u32),
+        end: line_index.line_position(line as u32, end as u32),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use ferrotex_syntax::parse;
-    use line_index::LineIndex;
 
     fn check_format(input: &str, expected: &str) {
+        check_format_with(input, expected, &FormatOptions::default());
+    }
+
+    fn check_format_with(input: &str, expected: &str, options: &FormatOptions) {
         let parse = parse(input);
         let root = parse.syntax();
         let line_index = LineIndex::new(input);
 
-        let edits = format_document(&root, &line_index);
-
-        // Apply edits to simulate result
-        // Simplified applier since our edits are line-based replacements
-        // let mut result = input.to_string();
-        // Sort edits reverse by range so indices don't shift?
-        // Our edits replace the indentation at start of lines.
-        // If we process from bottom to top line, it's safe.
-        // Edits are ordered by line ID (0..N).
-        // So reverse iteration works.
-
-        let mut lines: Vec<String> = input.lines().map(|s| s.to_string()).collect();
-        for edit in edits {
-            let line_idx = edit.range.start.line as usize;
-            if line_idx < lines.len() {
-                let line_content = &lines[line_idx];
-                let trimmed = line_content.trim_start();
-                let indent = edit.new_text;
-                lines[line_idx] = format!("{}{}", indent, trimmed);
-            }
-        }
+        let edits = format_document(&root, &line_index, options);
+        assert_eq!(apply_edits(input, edits), expected, "Formatting mismatch");
+    }
 
-        let mut actual = lines.join("\n");
-        // Re-add trailing newline if input had it, split removes it
-        if input.ends_with('\n') {
-            actual.push('\n');
-        }
+    fn apply_edits(input: &str, edits: Vec<TextEdit>) -> String {
+        apply_text_edits(input, &LineIndex::new(input), edits)
+    }
 
-        assert_eq!(actual, expected, "Formatting mismatch");
+    #[test]
+    fn test_format_text() {
+        // Edits are in UTF-16 columns; the text is edited at byte offsets.
+        let input = "\\begin{itemize}\n\\item Caf\u{e9} \u{1d538}   \n\\item B\n\\end{itemize}\n";
+        let options = FormatOptions { trim_trailing_whitespace: true, ..FormatOptions::default() };
+        let expected = "\\begin{itemize}\n    \\item Caf\u{e9} \u{1d538}\n    \\item B\n\\end{itemize}\n";
+        assert_eq!(format_text(input, false, &options), expected);
+        assert_eq!(format_text(expected, false, &options), expected);
     }
 
     #[test]
@@ -352,7 +802,7 @@ Hello World
         let parse = parse(input);
         let root = parse.syntax();
         let line_index = LineIndex::new(input);
-        let edits = format_document(&root, &line_index);
+        let edits = format_document(&root, &line_index, &FormatOptions::default());
         
         // Apply edits (which should be none if already formatted, or minimal)
         // If the input is already well-formatted, formatting it again should yield zero edits?
@@ -378,4 +828,227 @@ Hello World
         
         check_format(input, expected);
     }
+
+    #[test]
+    fn test_format_tabs_and_tab_size() {
+        let input = "\\begin{itemize}\n\\item One\n\\end{itemize}";
+
+        let tabs = FormatOptions { insert_spaces: false, ..Default::default() };
+        check_format_with(input, "\\begin{itemize}\n\t\\item One\n\\end{itemize}", &tabs);
+
+        let two = FormatOptions { tab_size: 2, ..Default::default() };
+        check_format_with(input, "\\begin{itemize}\n  \\item One\n\\end{itemize}", &two);
+    }
+
+    #[test]
+    fn test_format_trims_whitespace_and_newlines() {
+        let options = FormatOptions {
+            trim_trailing_whitespace: true,
+            trim_final_newlines: true,
+            ..Default::default()
+        };
+        check_format_with("Hello   \n  \nWorld\t\n\n\n", "Hello\n\nWorld\n", &options);
+
+        // Without the options, whitespace is left alone.
+        check_format("Hello   \n\n\n", "Hello   \n\n\n");
+    }
+
+    #[test]
+    fn test_format_inserts_final_newline() {
+        let options = FormatOptions { insert_final_newline: true, ..Default::default() };
+        check_format_with("Hello", "Hello\n", &options);
+        check_format_with("Hello\n", "Hello\n", &options);
+        check_format_with("", "", &options);
+    }
+
+    #[test]
+    fn test_format_continuation_indent() {
+        let input = "\\section{A very\nlong title\n}\n\\begin{document}\n\\caption{Wrapped\ncaption}\n\\end{document}";
+        let expected = "\\section{A very\n    long title\n}\n\\begin{document}\n    \\caption{Wrapped\n        caption}\n\\end{document}";
+        let options = FormatOptions { continuation_indent: 1, ..Default::default() };
+        check_format_with(input, expected, &options);
+
+        // The default keeps brace groups flat.
+        check_format(input, "\\section{A very\nlong title\n}\n\\begin{document}\n    \\caption{Wrapped\n    caption}\n\\end{document}");
+    }
+
+    #[test]
+    fn test_format_options_from_client() {
+        let client = FormattingOptions {
+            tab_size: 2,
+            insert_spaces: false,
+            trim_trailing_whitespace: Some(true),
+            ..Default::default()
+        };
+        let options = FormatOptions::new(&client, &FormatSettings::default());
+        assert_eq!(options.tab_size, 2);
+        assert!(!options.insert_spaces);
+        assert!(options.trim_trailing_whitespace);
+        assert!(!options.insert_final_newline);
+
+        let settings = FormatSettings {
+            indent_size: Some(3),
+            continuation_indent: 1,
+            wrap_column: Some(80),
+            bib_sort_entries: true,
+        };
+        let options = FormatOptions::new(&client, &settings);
+        assert_eq!(options.tab_size, 3);
+        assert_eq!(options.continuation_indent, 1);
+        assert_eq!(options.wrap_column, Some(80));
+        assert!(options.sort_bib_entries);
+    }
+
+    fn on_type(input: &str, line: u32, ch: &str) -> Vec<TextEdit> {
+        let parse = parse(input);
+        let line_index = LineIndex::new(input);
+        let position = Position { line, character: 0 };
+        format_on_type(&parse.syntax(), &line_index, position, ch, &FormatOptions::default())
+    }
+
+    #[test]
+    fn test_on_type_newline_after_begin() {
+        let input = "\\begin{document}\n    \\begin{itemize}\n\n    \\end{itemize}\n\\end{document}";
+        let edits = on_type(input, 2, "\n");
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].new_text, " ".repeat(8));
+        assert_eq!(edits[0].range, line_range(&LineIndex::new(input), 2, 0, 0));
+
+        // Enter at the end of the document still yields an indented line.
+        let edits = on_type("\\begin{itemize}\n", 1, "\n");
+        assert_eq!(edits[0].new_text, "    ");
+    }
+
+    #[test]
+    fn test_on_type_outdents_end() {
+        let input = "\\begin{itemize}\n    \\item One\n    \\end{itemize}";
+        assert_eq!(apply_edits(input, on_type(input, 2, "}")), "\\begin{itemize}\n    \\item One\n\\end{itemize}");
+
+        // Incomplete `\end{` while typing is outdented too.
+        let input = "\\begin{itemize}\n    \\item One\n    \\end{";
+        assert_eq!(on_type(input, 2, "{")[0].range, line_range(&LineIndex::new(input), 2, 0, 4));
+    }
+
+    #[test]
+    fn test_on_type_ignores_other_lines() {
+        let input = "\\begin{itemize}\n\\item \\textbf{One}\n\\end{itemize}";
+        assert!(on_type(input, 1, "}").is_empty());
+    }
+
+    fn wrap_options(column: usize) -> FormatOptions {
+        FormatOptions { wrap_column: Some(column), ..Default::default() }
+    }
+
+    /// Formats `input` and checks that formatting the result again changes nothing.
+    fn check_wrap(input: &str, expected: &str, column: usize) {
+        let options = wrap_options(column);
+        check_format_with(input, expected, &options);
+        check_format_with(expected, expected, &options);
+    }
+
+    #[test]
+    fn test_wrap_long_prose() {
+        check_wrap(
+            "The quick brown fox jumps over the lazy dog.",
+            "The quick brown fox\njumps over the lazy\ndog.",
+            20,
+        );
+        check_wrap(
+            "\\begin{document}\nThe quick brown fox jumps over the lazy dog.\n\\end{document}",
+            "\\begin{document}\n    The quick brown\n    fox jumps over\n    the lazy dog.\n\\end{document}",
+            20,
+        );
+    }
+
+    #[test]
+    fn test_wrap_is_off_by_default() {
+        let input = "The quick brown fox jumps over the lazy dog.";
+        check_format(input, input);
+    }
+
+    #[test]
+    fn test_wrap_keeps_short_and_unbreakable_lines() {
+        check_wrap("Short line.", "Short line.", 20);
+        check_wrap("Supercalifragilisticexpialidocious", "Supercalifragilisticexpialidocious", 20);
+    }
+
+    #[test]
+    fn test_wrap_skips_sensitive_lines() {
+        let inputs = [
+            "Inline math $a + b + c + d = e$ stays put here.",
+            "Display \\[ x + y + z \\] stays put as well here.",
+            "A comment line with many words % do not touch",
+            "See \\url{https://example.com/a/very/long/path} for details.",
+            "\\begin{verbatim}\nsome very long code line that must not be wrapped\n\\end{verbatim}",
+            "\\begin{equation}\na + b + c + d + e + f + g + h + i + j\n\\end{equation}",
+            "$a + b +\nc + d + e + f + g + h + i + j + k + l +\nm$",
+        ];
+        for input in inputs {
+            let parse = parse(input);
+            let line_index = LineIndex::new(input);
+            let edits = format_document(&parse.syntax(), &line_index, &wrap_options(20));
+            assert!(
+                edits.iter().all(|e| !e.new_text.contains('\n')),
+                "Unexpected wrap in {:?}: {:?}",
+                input,
+                edits
+            );
+        }
+    }
+
+    #[test]
+    fn test_align_tabular_columns() {
+        let input = "\\begin{tabular}{lll}\n\\hline\nName & Value & Unit \\\\\nx & 1 & m\\\\ \\hline\nlonger name & 42 & kg \\\\\n\\end{tabular}";
+        let expected = "\\begin{tabular}{lll}\n    \\hline\n    Name        & Value & Unit \\\\\n    x           & 1     & m \\\\ \\hline\n    longer name & 42    & kg \\\\\n\\end{tabular}";
+        check_format(input, expected);
+        check_format(expected, expected);
+    }
+
+    #[test]
+    fn test_align_math_environments() {
+        let input = "\\begin{align}\nx &= 1 \\\\\nlong &= 2\n\\end{align}";
+        let expected = "\\begin{align}\n    x    & = 1 \\\\\n    long & = 2\n\\end{align}";
+        check_format(input, expected);
+
+        let input = "\\begin{pmatrix}\n1 & 10\\\\\n100 & 1\n\\end{pmatrix}";
+        let expected = "\\begin{pmatrix}\n    1   & 10 \\\\\n    100 & 1\n\\end{pmatrix}";
+        check_format(input, expected);
+    }
+
+    #[test]
+    fn test_align_skips_multi_line_cells() {
+        // The second row spans two lines and is left alone; it does not widen the column.
+        let input = "\\begin{tabular}{ll}\n    a & b \\\\\n    a very long cell\n    & b \\\\\n    ccc & d\n\\end{tabular}";
+        let expected = "\\begin{tabular}{ll}\n    a   & b \\\\\n    a very long cell\n    & b \\\\\n    ccc & d\n\\end{tabular}";
+        check_format(input, expected);
+    }
+
+    #[test]
+    fn test_align_ignores_other_environments() {
+        let input = "\\begin{itemize}\n    \\item A & B \\\\\n    \\item Longer & C\n\\end{itemize}";
+        check_format(input, input);
+    }
+
+    #[test]
+    fn test_format_leaves_verbatim_untouched() {
+        let input = "\\begin{document}\n\\begin{verbatim}\nfn main() {\n      println!(\"hi\");   \n}\n\\end{verbatim}\nText\n\\end{document}";
+        let expected = "\\begin{document}\n    \\begin{verbatim}\nfn main() {\n      println!(\"hi\");   \n}\n    \\end{verbatim}\n    Text\n\\end{document}";
+        let options = FormatOptions { trim_trailing_whitespace: true, ..Default::default() };
+        check_format_with(input, expected, &options);
+    }
+
+    #[test]
+    fn test_format_leaves_minted_and_lstlisting_untouched() {
+        // The unbalanced brace inside the listing must not shift the following lines.
+        let input = "\\begin{minted}{python}\n  def f():\n\treturn {\n\\end{minted}\n\\begin{lstlisting}\n    int x;\n\\end{lstlisting}\n\\begin{itemize}\n\\item A\n\\end{itemize}";
+        let expected = "\\begin{minted}{python}\n  def f():\n\treturn {\n\\end{minted}\n\\begin{lstlisting}\n    int x;\n\\end{lstlisting}\n\\begin{itemize}\n    \\item A\n\\end{itemize}";
+        let options = FormatOptions { continuation_indent: 1, ..Default::default() };
+        check_format_with(input, expected, &options);
+    }
+
+    #[test]
+    fn test_on_type_ignores_verbatim() {
+        let input = "\\begin{itemize}\n\\begin{verbatim}\n\n\\end{verbatim}\n\\end{itemize}";
+        assert!(on_type(input, 2, "\n").is_empty());
+    }
 }
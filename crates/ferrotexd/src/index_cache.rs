@@ -0,0 +1,189 @@
+use crate::workspace::{FileIndex, Workspace};
+use ferrotex_syntax::bibtex::BibFile;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::Url;
+
+/// Bumped whenever the layout of [`FileIndex`] or [`BibFile`] changes, so that stale
+/// caches written by older servers are discarded instead of misread.
+const CACHE_FORMAT: u32 = 3;
+
+/// The index entry of one file, as stored on disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CachedIndex {
+    Tex {
+        index: Box<FileIndex>,
+        /// Root named by `%!TEX root` or the `subfiles` class.
+        explicit_root: Option<String>,
+    },
+    Bib(BibFile),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedFile {
+    /// Hash of the file content the entry was built from.
+    pub hash: u64,
+    pub index: CachedIndex,
+}
+
+/// Workspace index persisted between sessions, keyed by file URI.
+///
+/// Each project gets its own file in the cache directory, next to the package index.
+/// An entry is only reused if the content hash of the file still matches.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IndexCache {
+    format: u32,
+    files: HashMap<Url, CachedFile>,
+}
+
+impl Default for IndexCache {
+    fn default() -> Self {
+        Self {
+            format: CACHE_FORMAT,
+            files: HashMap::new(),
+        }
+    }
+}
+
+impl IndexCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the cache file for a project: ~/.cache/ferrotex/workspaces/<hash>.json
+    pub fn cache_path(root: &Path) -> Option<PathBuf> {
+        let name = format!("{:016x}.json", content_hash(root.to_string_lossy().as_bytes()));
+        dirs::cache_dir().map(|p| p.join("ferrotex").join("workspaces").join(name))
+    }
+
+    /// Loads the cache of a project. Returns an empty cache if there is none or it is unreadable.
+    pub fn load(root: &Path) -> Self {
+        Self::cache_path(root)
+            .and_then(|path| Self::load_from_path(&path))
+            .unwrap_or_default()
+    }
+
+    pub fn load_from_path(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        match serde_json::from_str::<IndexCache>(&content) {
+            Ok(cache) if cache.format == CACHE_FORMAT => {
+                log::info!("Loaded {} cached workspace entries.", cache.files.len());
+                Some(cache)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                log::warn!("Failed to parse workspace cache: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Saves the cache of a project.
+    pub fn save(&self, root: &Path) -> std::io::Result<()> {
+        match Self::cache_path(root) {
+            Some(path) => self.save_to_path(&path),
+            None => Ok(()),
+        }
+    }
+
+    pub fn save_to_path(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn insert(&mut self, uri: Url, file: CachedFile) {
+        self.files.insert(uri, file);
+    }
+
+    /// Indexes a file from disk, reusing the cached entry if the content is unchanged.
+    ///
+    /// Returns the entry to keep for the next session, or `None` if the file could not be read.
+    pub fn index_file(&self, workspace: &Workspace, uri: &Url, path: &Path) -> Option<CachedFile> {
+        let text = std::fs::read_to_string(path).ok()?;
+        let hash = content_hash(text.as_bytes());
+
+        if let Some(cached) = self.files.get(uri).filter(|cached| cached.hash == hash) {
+            workspace.restore(uri, cached.index.clone());
+            return Some(cached.clone());
+        }
+
+        if path.extension().is_some_and(|ext| ext == "bib") {
+            workspace.update_bib(uri, &text);
+        } else {
+            workspace.update(uri, &text);
+        }
+        let index = workspace.snapshot(uri)?;
+        Some(CachedFile { hash, index })
+    }
+}
+
+/// FNV-1a, which unlike `DefaultHasher` is guaranteed to be stable across Rust releases.
+fn content_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_cache_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let tex = dir.path().join("main.tex");
+        let bib = dir.path().join("refs.bib");
+        std::fs::write(&tex, "% !TEX root = book.tex\n\\label{sec:a}").unwrap();
+        std::fs::write(&bib, "@book{knuth, title={TeX}}").unwrap();
+        let tex_uri = Url::from_file_path(&tex).unwrap();
+        let bib_uri = Url::from_file_path(&bib).unwrap();
+
+        let mut cache = IndexCache::new();
+        let workspace = Workspace::new();
+        for (uri, path) in [(&tex_uri, &tex), (&bib_uri, &bib)] {
+            let entry = IndexCache::new().index_file(&workspace, uri, path).unwrap();
+            cache.insert(uri.clone(), entry);
+        }
+        let cache_file = dir.path().join("cache.json");
+        cache.save_to_path(&cache_file).unwrap();
+
+        let loaded = IndexCache::load_from_path(&cache_file).unwrap();
+        assert_eq!(loaded.len(), 2);
+
+        // Entries are restored without re-parsing while the content is unchanged.
+        let restored = Workspace::new();
+        loaded.index_file(&restored, &tex_uri, &tex).unwrap();
+        loaded.index_file(&restored, &bib_uri, &bib).unwrap();
+        assert_eq!(restored.find_definitions("sec:a").len(), 1);
+        assert_eq!(restored.get_explicit_root(&tex_uri), Some("book.tex".to_string()));
+        assert!(restored.has_citation_key("knuth"));
+
+        // A changed file is re-indexed.
+        std::fs::write(&tex, r"\label{sec:b}").unwrap();
+        let entry = loaded.index_file(&restored, &tex_uri, &tex).unwrap();
+        assert_ne!(entry.hash, loaded.files[&tex_uri].hash);
+        assert!(restored.find_definitions("sec:a").is_empty());
+        assert_eq!(restored.find_definitions("sec:b").len(), 1);
+    }
+
+    #[test]
+    fn test_index_cache_rejects_other_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.json");
+        std::fs::write(&path, r#"{"format": 0, "files": {}}"#).unwrap();
+        assert!(IndexCache::load_from_path(&path).is_none());
+        assert!(IndexCache::load_from_path(&dir.path().join("missing.json")).is_none());
+    }
+}
@@ -0,0 +1,145 @@
+//! Inverse search for PDF viewers that call an external command.
+//!
+//! Viewers such as Zathura or SumatraPDF run a command with the source file and line
+//! under the cursor. When enabled, the server listens on a local TCP port or a Unix
+//! socket for `file:line` requests, one per line, and opens each location in the editor
+//! through `window/showDocument`. Every request is answered with `ok` or `error: <reason>`.
+//!
+//! Zathura, for example:
+//! `zathura -x 'sh -c "echo %{input}:%{line} | nc -q1 localhost 28100"' main.pdf`
+
+use crate::settings::InverseSearchSettings;
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tower_lsp::Client;
+use tower_lsp::lsp_types::{Position, Range, ShowDocumentParams, Url};
+
+/// A running listener; dropping it stops accepting requests and removes the socket file.
+#[derive(Debug)]
+pub struct Listener {
+    settings: InverseSearchSettings,
+    task: tokio::task::JoinHandle<()>,
+    socket_path: Option<PathBuf>,
+}
+
+impl Listener {
+    /// The settings the listener was started with.
+    pub fn settings(&self) -> &InverseSearchSettings {
+        &self.settings
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.task.abort();
+        if let Some(path) = &self.socket_path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Binds the socket configured in `settings` and serves requests on behalf of `client`.
+///
+/// A Unix socket is used when `socket_path` is set, otherwise `127.0.0.1:<port>`.
+pub async fn start(settings: &InverseSearchSettings, client: Client) -> std::io::Result<Listener> {
+    let (task, socket_path) = match &settings.socket_path {
+        #[cfg(unix)]
+        Some(path) => {
+            let path = PathBuf::from(path);
+            // A socket left behind by a previous server refuses the bind.
+            if std::fs::symlink_metadata(&path).is_ok_and(|meta| {
+                use std::os::unix::fs::FileTypeExt;
+                meta.file_type().is_socket()
+            }) {
+                std::fs::remove_file(&path)?;
+            }
+            let listener = tokio::net::UnixListener::bind(&path)?;
+            let task = tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(serve(stream, client.clone()));
+                }
+            });
+            (task, Some(path))
+        }
+        #[cfg(not(unix))]
+        Some(_) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "Unix sockets are not available on this platform",
+            ));
+        }
+        None => {
+            let listener = tokio::net::TcpListener::bind(("127.0.0.1", settings.port)).await?;
+            let task = tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(serve(stream, client.clone()));
+                }
+            });
+            (task, None)
+        }
+    };
+    Ok(Listener { settings: settings.clone(), task, socket_path })
+}
+
+/// Answers the requests of one connection until the viewer closes it.
+async fn serve<S: AsyncRead + AsyncWrite>(stream: S, client: Client) {
+    let (read, mut write) = tokio::io::split(stream);
+    let mut lines = BufReader::new(read).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match show(&client, &line).await {
+            Ok(()) => "ok\n".to_string(),
+            Err(e) => format!("error: {}\n", e),
+        };
+        if write.write_all(reply.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn show(client: &Client, request: &str) -> Result<(), String> {
+    let (path, line) = parse_request(request).ok_or_else(|| format!("expected `file:line`, got `{}`", request.trim()))?;
+    let uri = Url::from_file_path(&path).map_err(|_| format!("not an absolute path: {}", path.display()))?;
+    let position = Position { line, character: 0 };
+    let params = ShowDocumentParams {
+        uri,
+        external: None,
+        take_focus: Some(true),
+        selection: Some(Range { start: position, end: position }),
+    };
+    match client.show_document(params).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err("the editor could not open the document".to_string()),
+        Err(e) => Err(e.message.into_owned()),
+    }
+}
+
+/// Parses `file:line` into the path and the 0-based line.
+///
+/// The line is 1-based, as viewers report it. The path may contain colons itself,
+/// e.g. `C:\thesis\main.tex:12`.
+pub fn parse_request(request: &str) -> Option<(PathBuf, u32)> {
+    let (path, line) = request.trim().rsplit_once(':')?;
+    let line: u32 = line.trim().parse().ok()?;
+    if path.is_empty() {
+        return None;
+    }
+    Some((PathBuf::from(path), line.saturating_sub(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        assert_eq!(parse_request("/home/me/thesis/main.tex:12\n"), Some((PathBuf::from("/home/me/thesis/main.tex"), 11)));
+        assert_eq!(parse_request("C:\\thesis\\main.tex:1"), Some((PathBuf::from("C:\\thesis\\main.tex"), 0)));
+        assert_eq!(parse_request("/main.tex:0"), Some((PathBuf::from("/main.tex"), 0)));
+        assert_eq!(parse_request("/main.tex"), None);
+        assert_eq!(parse_request("/main.tex:twelve"), None);
+        assert_eq!(parse_request(":12"), None);
+    }
+}
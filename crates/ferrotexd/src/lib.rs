@@ -1,22 +1,35 @@
+pub mod bib_fmt;
 pub mod build;
 pub mod completer;
+pub mod dependency_graph;
 pub mod diagnostics;
 pub mod fmt;
 pub mod hover;
+pub mod index_cache;
+pub mod inverse_search;
+pub mod links;
+pub mod parse_cache;
+pub mod position;
+pub mod protocol;
+pub mod refactor;
+pub mod semantic_tokens;
+pub mod settings;
+pub mod symbols;
 pub mod workspace;
 pub mod synctex;
+pub mod word_count;
 
-use build::{BuildEngine, BuildRequest, latexmk::LatexmkAdapter};
+use build::{BuildRequest, BuildStatus, OutputFormat, engine_for};
 use dashmap::DashMap;
 use ferrotex_core::package_manager;
 use ferrotex_package::{PackageIndex, scanner::PackageScanner};
-use ferrotex_syntax::SyntaxKind;
-use line_index::LineIndex;
 use notify::{Watcher, RecursiveMode, Config};
+use settings::Settings;
 use std::sync::{Arc, Mutex};
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
-use tower_lsp::{Client, LanguageServer};
+use tower_lsp::lsp_types::request::Request as _;
+use tower_lsp::{Client, LanguageServer, LspServiceBuilder};
 use workspace::Workspace;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -63,22 +76,15 @@ pub const ENVIRONMENTS: &[&str] = &[
     "center",
 ];
 
-pub const SEMANTIC_TOKEN_TYPES: &[SemanticTokenType] = &[
-    SemanticTokenType::MACRO,     // 0: Commands (\foo)
-    SemanticTokenType::KEYWORD,   // 1: Environment markers (\begin, \end)
-    SemanticTokenType::STRING,    // 2: Arguments
-    SemanticTokenType::COMMENT,   // 3: Comments
-    SemanticTokenType::PARAMETER, // 4: Optional arguments
-    SemanticTokenType::VARIABLE,  // 5: Labels, citations
-];
+pub use semantic_tokens::{SEMANTIC_TOKEN_MODIFIERS, SEMANTIC_TOKEN_TYPES};
 
-pub const SEMANTIC_TOKEN_MODIFIERS: &[SemanticTokenModifier] = &[
-    SemanticTokenModifier::DECLARATION,
-    SemanticTokenModifier::DEFINITION,
-    SemanticTokenModifier::READONLY,
-];
+/// Number of files indexed per blocking task during background workspace indexing.
+const WORKSPACE_INDEX_BATCH: usize = 50;
+
+/// Diagnostic code of a citation key defined more than once (see `docs/spec/diagnostic-codes.md`).
+const DUPLICATE_KEY_CODE: &str = "FTX0302";
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Backend {
     pub client: Client,
     pub documents: Arc<DashMap<Url, String>>,
@@ -87,6 +93,18 @@ pub struct Backend {
     pub syntax_diagnostics: Arc<DashMap<Url, Vec<Diagnostic>>>,
     pub package_manager: Arc<Mutex<package_manager::PackageManager>>,
     pub package_index: Arc<Mutex<Option<PackageIndex>>>,
+    pub settings: Arc<Mutex<Settings>>,
+    pub parse_cache: Arc<parse_cache::ParseCache>,
+    /// Pending or running validation per document; a new edit aborts the previous one.
+    pub validations: Arc<DashMap<Url, tokio::task::JoinHandle<()>>>,
+    /// LanguageTool client and the grammar diagnostics of each checked document version.
+    pub grammar: Arc<diagnostics::grammar::GrammarChecker>,
+    /// Errors and warnings from the last build, by source file.
+    pub build_diagnostics: Arc<DashMap<Url, Vec<Diagnostic>>>,
+    /// Cancellation handle of the running build, by root document.
+    pub builds: Arc<DashMap<Url, Arc<tokio::sync::Notify>>>,
+    /// Listener for inverse search from external PDF viewers, while enabled.
+    pub inverse_search: Arc<tokio::sync::Mutex<Option<inverse_search::Listener>>>,
 }
 
 #[tower_lsp::async_trait]
@@ -96,6 +114,13 @@ impl LanguageServer for Backend {
             let mut root = self.root_uri.lock().unwrap();
             *root = params.root_uri.clone();
         }
+
+        let encoding = position::PositionEncoding::negotiate(&params.capabilities);
+        self.parse_cache.set_encoding(encoding);
+
+        if let Some(settings) = params.initialization_options.and_then(Settings::from_value) {
+            *self.settings.lock().unwrap() = settings;
+        }
         
         let detected_pm = package_manager::PackageManager::new();
         {
@@ -165,13 +190,20 @@ impl LanguageServer for Backend {
 
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
+                position_encoding: Some(encoding.kind()),
                 text_document_sync: Some(TextDocumentSyncCapability::Kind(
                     TextDocumentSyncKind::FULL,
                 )),
                 document_symbol_provider: Some(OneOf::Left(true)),
+                folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
                 execute_command_provider: Some(ExecuteCommandOptions {
                     commands: vec![
                         "ferrotex.internal.build".to_string(),
+                        "ferrotex.cancelBuild".to_string(),
+                        "ferrotex.clean".to_string(),
+                        "ferrotex.dependencyGraph".to_string(),
+                        "ferrotex.buildPlan".to_string(),
+                        refactor::EXTRACT_TO_FILE_COMMAND.to_string(),
                         "ferrotex.synctex_forward".to_string(),
                         "ferrotex.synctex_inverse".to_string(),
                         "ferrotex.installPackage".to_string(),
@@ -189,6 +221,15 @@ impl LanguageServer for Backend {
                     ..Default::default()
                 }),
                 document_formatting_provider: Some(OneOf::Left(true)),
+                document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+                    first_trigger_character: "\n".to_string(),
+                    more_trigger_character: Some(vec!["}".to_string(), "{".to_string()]),
+                }),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                document_link_provider: Some(DocumentLinkOptions {
+                    resolve_provider: Some(false),
+                    work_done_progress_options: Default::default(),
+                }),
                 semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
                     SemanticTokensOptions {
                         legend: SemanticTokensLegend {
@@ -210,6 +251,7 @@ impl LanguageServer for Backend {
         self.client
             .log_message(MessageType::INFO, "FerroTeX Daemon Initialized")
             .await;
+        self.configure_inverse_search().await;
 
         let root_uri = {
             let guard = self.root_uri.lock().unwrap();
@@ -218,9 +260,14 @@ impl LanguageServer for Backend {
 
         if let Some(root) = root_uri {
             if let Ok(path) = root.to_file_path() {
-                let client = self.client.clone();
+                let backend = self.clone();
+                let index_root = path.clone();
+                tokio::spawn(async move {
+                    backend.index_workspace(index_root).await;
+                });
+
                 let documents = self.documents.clone();
-                let workspace = self.workspace.clone();
+                let backend = self.clone();
 
                 tokio::spawn(async move {
                     let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
@@ -232,32 +279,22 @@ impl LanguageServer for Backend {
                     while let Some(res) = rx.recv().await {
                         match res {
                             Ok(event) => {
+                                let changed: Vec<_> = event
+                                    .paths
+                                    .iter()
+                                    .filter(|p| workspace::is_project_file(&path, p))
+                                    .cloned()
+                                    .collect();
+                                if !changed.is_empty() && !event.kind.is_access() {
+                                    backend.refresh_files(&changed).await;
+                                }
+
+                                // A build rewrote the log; revalidate so its warnings replace the old ones.
                                 for path in event.paths {
                                     if path.extension().and_then(|s| s.to_str()) == Some("log") {
-                                        let tex_path = path.with_extension("tex");
-                                        let uri = Url::from_file_path(tex_path).unwrap();
-                                        
+                                        let Ok(uri) = Url::from_file_path(path.with_extension("tex")) else { continue };
                                         if documents.contains_key(&uri) {
-                                           if let Some(text) = documents.get(&uri) {
-                                               workspace.update(&uri, &text);
-                                               let mut diagnostics = Vec::new();
-                                               
-                                               if let Ok(log_content) = std::fs::read_to_string(&path) {
-                                                   let parser = ferrotex_log::LogParser::new();
-                                                   let events = parser.parse(&log_content);
-                                                   for event in events {
-                                                       if let ferrotex_log::ir::EventPayload::Warning { message } = event.payload {
-                                                           diagnostics.push(Diagnostic {
-                                                               range: Range::default(),
-                                                               severity: Some(DiagnosticSeverity::WARNING),
-                                                               message,
-                                                               ..Default::default()
-                                                           });
-                                                       }
-                                                   }
-                                                   let _ = client.publish_diagnostics(uri, diagnostics, None).await;
-                                               }
-                                           }
+                                            backend.validate_document(uri).await;
                                         }
                                     }
                                 }
@@ -279,27 +316,165 @@ impl LanguageServer for Backend {
             params.text_document.uri.clone(),
             params.text_document.text.clone(),
         );
+        self.parse_cache.set_version(&params.text_document.uri, params.text_document.version);
         self.validate_document(params.text_document.uri).await;
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
         if let Some(change) = params.content_changes.into_iter().next() {
-            self.documents
-                .insert(params.text_document.uri.clone(), change.text);
-            self.validate_document(params.text_document.uri).await;
+            let uri = params.text_document.uri;
+            self.documents.insert(uri.clone(), change.text.clone());
+            self.parse_cache.set_version(&uri, params.text_document.version);
+            self.schedule_validation(uri);
+        }
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        if let Some((_, handle)) = self.validations.remove(&uri) {
+            handle.abort();
+        }
+        self.documents.remove(&uri);
+        self.parse_cache.remove(&uri);
+        self.grammar.remove(&uri);
+        self.syntax_diagnostics.remove(&uri);
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        let Some(settings) = Settings::from_value(params.settings) else {
+            return;
+        };
+        *self.settings.lock().unwrap() = settings;
+        self.grammar.clear();
+        self.configure_inverse_search().await;
+
+        let uris: Vec<Url> = self.documents.iter().map(|e| e.key().clone()).collect();
+        for uri in uris {
+            self.validate_document(uri).await;
         }
     }
 
     async fn execute_command(&self, params: ExecuteCommandParams) -> Result<Option<serde_json::Value>> {
         match params.command.as_str() {
             "ferrotex.internal.build" => {
-                let uri_str = params.arguments.get(0).and_then(|v| v.as_str()).unwrap_or("");
+                let uri_str = params.arguments.first().and_then(|v| v.as_str()).unwrap_or("");
                 let uri = Url::parse(uri_str).map_err(|_| tower_lsp::jsonrpc::Error::invalid_params("Invalid URI"))?;
                 self.run_build(uri).await;
                 Ok(None)
             }
+            "ferrotex.synctex_forward" => {
+                // Arguments: [uri, line, character, pdfUri?]
+                let uri_str = params.arguments.first().and_then(|v| v.as_str()).unwrap_or("");
+                let uri = Url::parse(uri_str).map_err(|_| tower_lsp::jsonrpc::Error::invalid_params("Invalid URI"))?;
+                let line = params
+                    .arguments
+                    .get(1)
+                    .and_then(|v| v.as_u64())
+                    .ok_or_else(|| tower_lsp::jsonrpc::Error::invalid_params("Missing line"))?;
+                let pdf = params
+                    .arguments
+                    .get(3)
+                    .and_then(|v| v.as_str())
+                    .and_then(|s| Url::parse(s).ok())
+                    .and_then(|u| u.to_file_path().ok());
+                Ok(self.synctex_forward(&uri, line as u32, pdf).await)
+            }
+            "ferrotex.synctex_inverse" => {
+                // Arguments: [pdfUri, page, x, y]
+                let uri_str = params.arguments.first().and_then(|v| v.as_str()).unwrap_or("");
+                let pdf = Url::parse(uri_str)
+                    .ok()
+                    .and_then(|u| u.to_file_path().ok())
+                    .ok_or_else(|| tower_lsp::jsonrpc::Error::invalid_params("Invalid URI"))?;
+                let number = |i: usize| params.arguments.get(i).and_then(|v| v.as_f64());
+                let (Some(page), Some(x), Some(y)) = (number(1), number(2), number(3)) else {
+                    return Err(tower_lsp::jsonrpc::Error::invalid_params("Expected page, x and y"));
+                };
+                Ok(self.synctex_inverse(pdf, page as u32, x, y).await)
+            }
+            "ferrotex.clean" => {
+                let uri_str = params.arguments.first().and_then(|v| v.as_str()).unwrap_or("");
+                let uri = Url::parse(uri_str).map_err(|_| tower_lsp::jsonrpc::Error::invalid_params("Invalid URI"))?;
+                Ok(self.clean(&uri).await)
+            }
+            refactor::EXTRACT_TO_FILE_COMMAND => {
+                // Arguments: [uri, range]
+                let uri_str = params.arguments.first().and_then(|v| v.as_str()).unwrap_or("");
+                let uri = Url::parse(uri_str).map_err(|_| tower_lsp::jsonrpc::Error::invalid_params("Invalid URI"))?;
+                let range: Range = params
+                    .arguments
+                    .get(1)
+                    .and_then(|v| serde_json::from_value(v.clone()).ok())
+                    .ok_or_else(|| tower_lsp::jsonrpc::Error::invalid_params("Missing range"))?;
+                self.extract_to_file(&uri, range).await;
+                Ok(None)
+            }
+            "ferrotex.dependencyGraph" => {
+                // Arguments: [uri?, "json" | "dot", outputPath?]
+                let root = params
+                    .arguments
+                    .first()
+                    .and_then(|v| v.as_str())
+                    .and_then(|s| Url::parse(s).ok())
+                    .map(|uri| self.root_document(&uri));
+                let graph = self.workspace.dependency_graph(root.as_ref());
+                let content = match params.arguments.get(1).and_then(|v| v.as_str()).unwrap_or("json") {
+                    "json" => serde_json::to_value(&graph).map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?,
+                    "dot" => serde_json::Value::String(graph.to_dot()),
+                    _ => return Err(tower_lsp::jsonrpc::Error::invalid_params("Expected format \"json\" or \"dot\"")),
+                };
+                let Some(output) = params.arguments.get(2).and_then(|v| v.as_str()) else {
+                    return Ok(Some(content));
+                };
+                let text = match &content {
+                    serde_json::Value::String(dot) => dot.clone(),
+                    json => serde_json::to_string_pretty(json).unwrap_or_default(),
+                };
+                if let Err(e) = tokio::fs::write(output, text).await {
+                    self.client
+                        .show_message(MessageType::ERROR, format!("Failed to write dependency graph: {}", e))
+                        .await;
+                    return Ok(None);
+                }
+                Ok(Some(serde_json::Value::String(output.to_string())))
+            }
+            "ferrotex.buildPlan" => {
+                // Arguments: [uri]
+                let uri_str = params.arguments.first().and_then(|v| v.as_str()).unwrap_or("");
+                let uri = Url::parse(uri_str).map_err(|_| tower_lsp::jsonrpc::Error::invalid_params("Invalid URI"))?;
+                let path = self
+                    .root_document(&uri)
+                    .to_file_path()
+                    .map_err(|_| tower_lsp::jsonrpc::Error::invalid_params("Expected a file URI"))?;
+                let settings = self.settings.lock().unwrap().build.clone();
+                // The output directory used by the latexmk adapter.
+                let mut compiler = ferrotex_build::Compiler::new("pdflatex", std::path::PathBuf::from("build"))
+                    .with_profile(settings.profile)
+                    .with_synctex(settings.synctex);
+                if let Some(container) = settings.container.container() {
+                    compiler = compiler.with_container(container);
+                }
+                let sources = ferrotex_build::ProjectSources::discover(&path).map_err(|e| tower_lsp::jsonrpc::Error {
+                    code: tower_lsp::jsonrpc::ErrorCode::InternalError,
+                    message: e.to_string().into(),
+                    data: None,
+                })?;
+                let spec = ferrotex_build::Planner::new(compiler).plan_sources(&sources).spec();
+                Ok(Some(serde_json::to_value(spec).map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?))
+            }
+            "ferrotex.cancelBuild" => {
+                // Without a document argument, every running build is cancelled.
+                let uri = match params.arguments.first().and_then(|v| v.as_str()) {
+                    Some(uri_str) => Some(
+                        Url::parse(uri_str).map_err(|_| tower_lsp::jsonrpc::Error::invalid_params("Invalid URI"))?,
+                    ),
+                    None => None,
+                };
+                let cancelled = self.cancel_build(uri.as_ref());
+                Ok(Some(serde_json::Value::Bool(cancelled)))
+            }
             "ferrotex.installPackage" => {
-                 let pkg_name = params.arguments.get(0).and_then(|v| v.as_str()).unwrap_or("");
+                 let pkg_name = params.arguments.first().and_then(|v| v.as_str()).unwrap_or("");
                  if pkg_name.is_empty() {
                      return Err(tower_lsp::jsonrpc::Error::invalid_params("Missing package name"));
                  }
@@ -331,41 +506,22 @@ impl LanguageServer for Backend {
 
     async fn document_symbol(&self, params: DocumentSymbolParams) -> Result<Option<DocumentSymbolResponse>> {
         let uri = params.text_document.uri;
-        let symbols = self.workspace.query_symbols("");
-        let lsp_symbols: Vec<DocumentSymbol> = symbols.into_iter()
-            .filter(|(_, _, u, _)| u == &uri)
-            .map(|(name, kind, _, range)| {
-                let start_lc = {
-                    let text = self.documents.get(&uri).map(|v| v.clone()).unwrap_or_default();
-                    let li = LineIndex::new(&text);
-                    li.line_col(range.start())
-                };
-                let end_lc = {
-                    let text = self.documents.get(&uri).map(|v| v.clone()).unwrap_or_default();
-                    let li = LineIndex::new(&text);
-                    li.line_col(range.end())
-                };
+        let Some(parsed) = self.parse_cache.get(&uri, &self.documents) else {
+            return Ok(None);
+        };
+        let structural = self.settings.lock().unwrap().outline.structural_commands.clone();
+        let symbols = symbols::document_symbols(&parsed.syntax(), &parsed.line_index, &structural);
+        Ok(Some(DocumentSymbolResponse::Nested(symbols)))
+    }
 
-                #[allow(deprecated)]
-                DocumentSymbol {
-                    name,
-                    detail: None,
-                    kind,
-                    tags: None,
-                    deprecated: None,
-                    range: Range {
-                        start: Position { line: start_lc.line, character: start_lc.col },
-                        end: Position { line: end_lc.line, character: end_lc.col },
-                    },
-                    selection_range: Range {
-                        start: Position { line: start_lc.line, character: start_lc.col },
-                        end: Position { line: end_lc.line, character: end_lc.col },
-                    },
-                    children: None,
-                }
-            })
-            .collect();
-        Ok(Some(DocumentSymbolResponse::Nested(lsp_symbols)))
+    async fn folding_range(&self, params: FoldingRangeParams) -> Result<Option<Vec<FoldingRange>>> {
+        let uri = params.text_document.uri;
+        let Some(parsed) = self.parse_cache.get(&uri, &self.documents) else {
+            return Ok(None);
+        };
+        let structural = self.settings.lock().unwrap().outline.structural_commands.clone();
+        let symbols = symbols::document_symbols(&parsed.syntax(), &parsed.line_index, &structural);
+        Ok(Some(symbols::folding_ranges(&symbols)))
     }
 
     async fn goto_definition(&self, params: GotoDefinitionParams) -> Result<Option<GotoDefinitionResponse>> {
@@ -383,39 +539,109 @@ impl LanguageServer for Backend {
     async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
         let uri = params.text_document_position_params.text_document.uri;
         let pos = params.text_document_position_params.position;
-        if let Some(text) = self.documents.get(&uri) {
-            let offset = {
-                let line_index = LineIndex::new(&text);
-                line_index.offset(line_index::LineCol { line: pos.line, col: pos.character })
-            };
-            
+        if let Some(parsed) = self.parse_cache.get(&uri, &self.documents) {
+            let offset = parsed.line_index.offset(pos);
             if let Some(off) = offset {
-                let parse_res = ferrotex_syntax::parse(&text);
-                let root = ferrotex_syntax::SyntaxNode::new_root(parse_res.green_node());
-                let h = hover::find_hover(&root, off, &self.workspace);
+                let h = hover::find_hover(&parsed.syntax(), off, &self.workspace);
                 return Ok(h);
             }
         }
         Ok(None)
     }
 
+    async fn document_link(&self, params: DocumentLinkParams) -> Result<Option<Vec<DocumentLink>>> {
+        let uri = params.text_document.uri;
+        let base = self.root_document(&uri);
+        if let Some(parsed) = self.parse_cache.get(&uri, &self.documents) {
+            return Ok(Some(links::find_links(&parsed.syntax(), &parsed.line_index, &base)));
+        }
+        Ok(None)
+    }
+
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
         let uri = params.text_document_position.text_document.uri;
-        let packages = self.workspace.get_packages(&uri);
-        let index_guard = self.package_index.lock().unwrap();
-        let (cmds, envs) = completer::get_package_completions(&packages, index_guard.as_ref());
-        let mut items = cmds;
-        items.extend(envs);
-        Ok(Some(CompletionResponse::Array(items)))
+        let position = params.text_document_position.position;
+        let prefix = self
+            .parse_cache
+            .get(&uri, &self.documents)
+            .and_then(|parsed| {
+                let line_start = parsed.line_index.offset(Position { line: position.line, character: 0 })?;
+                let end = parsed.line_index.offset(position)?;
+                let text = self.documents.get(&uri)?;
+                let line = text.get(usize::from(line_start)..usize::from(end))?;
+                Some(completer::completion_prefix(line).to_string())
+            })
+            .unwrap_or_default();
+
+        let root = self.root_document(&uri);
+        let packages = self.workspace.get_packages(&uri, &root);
+        let mut items = Vec::new();
+        let mut add = |source, (cmds, envs): (Vec<CompletionItem>, Vec<CompletionItem>)| {
+            items.extend(cmds.into_iter().chain(envs).map(|item| (source, item)));
+        };
+        add(
+            completer::CompletionSource::Document,
+            (
+                completer::get_user_completions(
+                    &self.workspace.get_command_definitions(),
+                    &self.workspace.get_environment_definitions(),
+                ),
+                Vec::new(),
+            ),
+        );
+        {
+            let index_guard = self.package_index.lock().unwrap();
+            add(
+                completer::CompletionSource::LoadedPackage,
+                completer::get_package_completions(&packages, index_guard.as_ref()),
+            );
+            add(completer::CompletionSource::Standard, completer::get_standard_completions());
+            // Commands of every installed package are only worth offering once the user typed something.
+            if let Some(index) = index_guard.as_ref().filter(|_| prefix.trim_start_matches('\\').len() >= 2) {
+                add(
+                    completer::CompletionSource::OtherPackage,
+                    completer::get_other_package_completions(&packages, index),
+                );
+            }
+        }
+
+        let (items, is_incomplete) = completer::rank_completions(items, &prefix);
+        Ok(Some(CompletionResponse::List(CompletionList { is_incomplete, items })))
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = &params.text_document.uri;
+        let mut actions = diagnostics::spell::suggestion_actions(uri, &params.context.diagnostics);
+        actions.extend(diagnostics::grammar::suggestion_actions(uri, &params.context.diagnostics));
+        actions.extend(build::log_diagnostics::install_actions(&params.context.diagnostics));
+        if !is_bib(uri) {
+            actions.extend(refactor::extract_action(uri, params.range));
+        }
+        Ok(Some(actions))
     }
 
     async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
         let uri = params.text_document.uri;
-        if let Some(text) = self.documents.get(&uri) {
-            let parse_res = ferrotex_syntax::parse(&text);
-            let root = ferrotex_syntax::SyntaxNode::new_root(parse_res.green_node());
-            let line_index = LineIndex::new(&text);
-            let edits = fmt::format_document(&root, &line_index);
+        let options = fmt::FormatOptions::new(&params.options, &self.settings.lock().unwrap().format);
+        if is_bib(&uri) {
+            return Ok(self.documents.get(&uri).map(|text| {
+                let line_index = position::LineIndex::with_encoding(&text, self.parse_cache.encoding());
+                bib_fmt::format_bibtex(&text, &line_index, &options)
+            }));
+        }
+        if let Some(parsed) = self.parse_cache.get(&uri, &self.documents) {
+            let edits = fmt::format_document(&parsed.syntax(), &parsed.line_index, &options);
+            Ok(Some(edits))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn on_type_formatting(&self, params: DocumentOnTypeFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let position = params.text_document_position;
+        if let Some(parsed) = self.parse_cache.get(&position.text_document.uri, &self.documents) {
+            let options = fmt::FormatOptions::new(&params.options, &self.settings.lock().unwrap().format);
+            let edits = fmt::format_on_type(&parsed.syntax(), &parsed.line_index, position.position, &params.ch, &options);
             Ok(Some(edits))
         } else {
             Ok(None)
@@ -424,8 +650,8 @@ impl LanguageServer for Backend {
 
     async fn semantic_tokens_full(&self, params: SemanticTokensParams) -> Result<Option<SemanticTokensResult>> {
         let uri = params.text_document.uri;
-        if let Some(text) = self.documents.get(&uri) {
-            let tokens = self.compute_semantic_tokens(&text);
+        if let Some(parsed) = self.parse_cache.get(&uri, &self.documents) {
+            let tokens = semantic_tokens::compute_semantic_tokens(&parsed.syntax(), &parsed.line_index);
             Ok(Some(SemanticTokensResult::Tokens(SemanticTokens {
                 result_id: None,
                 data: tokens,
@@ -436,34 +662,332 @@ impl LanguageServer for Backend {
     }
 }
 
+/// Returns `true` if the document is a BibTeX bibliography.
+fn is_bib(uri: &Url) -> bool {
+    uri.path().ends_with(".bib")
+}
+
+/// Forwards log events of a running build to the client as `ferrotex/buildLog`.
+async fn send_log(client: &Client, uri: &Url, pass: u32, events: &[ferrotex_log::ir::LogEvent]) {
+    let params = protocol::BuildLogParams { uri: uri.clone(), pass, events: events.to_vec() };
+    client.send_notification::<protocol::BuildLog>(params).await;
+}
+
 impl Backend {
+    /// Registers the FerroTeX protocol extensions (see [`protocol`]) on the service builder.
+    pub fn custom_methods(builder: LspServiceBuilder<Self>) -> LspServiceBuilder<Self> {
+        builder.custom_method(protocol::IncludeTree::METHOD, Backend::include_tree)
+    }
+
+    /// Moves the text in `range` of `uri` into a new file next to it and `\input`s that file instead.
+    ///
+    /// The file is created through `workspace/applyEdit`, so the client can undo the whole
+    /// refactoring; failures are shown to the user.
+    pub async fn extract_to_file(&self, uri: &Url, range: Range) {
+        let Some(parsed) = self.parse_cache.get(uri, &self.documents) else {
+            return;
+        };
+        let (Some(start), Some(end)) = (parsed.line_index.offset(range.start), parsed.line_index.offset(range.end)) else {
+            return;
+        };
+        let Some(selected) = self
+            .documents
+            .get(uri)
+            .and_then(|text| text.get(usize::from(start)..usize::from(end)).map(str::to_string))
+        else {
+            return;
+        };
+        let root_dir = self.root_document(uri).to_file_path().ok().and_then(|p| p.parent().map(|p| p.to_path_buf()));
+        let (Some(dir), Some(root_dir)) = (uri.to_file_path().ok().and_then(|p| p.parent().map(|p| p.to_path_buf())), root_dir)
+        else {
+            self.client.show_message(MessageType::ERROR, "Extract to file needs a document on disk").await;
+            return;
+        };
+
+        let target = refactor::unique_target(&dir, &refactor::file_stem(&selected), |path| {
+            path.exists() || Url::from_file_path(path).is_ok_and(|u| self.documents.contains_key(&u))
+        });
+        let Ok(target_uri) = Url::from_file_path(&target) else {
+            return;
+        };
+        let input = refactor::input_path(&root_dir, &target);
+        let edit = refactor::extract_edit(uri, Some(parsed.version), range, &target_uri, &selected, &input);
+        drop(parsed);
+
+        match self.client.apply_edit(edit).await {
+            Ok(response) if response.applied => {}
+            Ok(response) => {
+                let reason = response.failure_reason.unwrap_or_else(|| "the client rejected the edit".to_string());
+                self.client.show_message(MessageType::ERROR, format!("Could not extract to file: {}", reason)).await;
+            }
+            Err(e) => {
+                self.client.show_message(MessageType::ERROR, format!("Could not extract to file: {}", e)).await;
+            }
+        }
+    }
+
+    /// Sends a `$/ferrotex/status` notification.
+    pub async fn send_status(&self, status: protocol::ServerStatus) {
+        self.client.send_notification::<protocol::Status>(status).await;
+    }
+
+    /// Handles `ferrotex/includeTree`: the include tree of the project `params` belongs to.
+    pub async fn include_tree(&self, params: protocol::IncludeTreeParams) -> Result<protocol::IncludeTreeNode> {
+        let root = self.root_document(&params.text_document.uri);
+        Ok(self.workspace.include_tree(&root))
+    }
+
+    /// Indexes every `.tex` and `.bib` file below `root` so that labels and citations
+    /// from files that were never opened resolve, then re-validates the open documents.
+    ///
+    /// Open documents are skipped: their in-memory text is more recent than the disk copy.
+    /// Entries of files unchanged since the last session are taken from the on-disk
+    /// [`index_cache::IndexCache`], which is rewritten afterwards.
+    pub async fn index_workspace(&self, root: std::path::PathBuf) {
+        let cache_root = root.clone();
+        let (files, previous) = tokio::task::spawn_blocking(move || {
+            (workspace::collect_project_files(&root), index_cache::IndexCache::load(&root))
+        })
+        .await
+        .unwrap_or_default();
+        let previous = Arc::new(previous);
+        if files.is_empty() {
+            return;
+        }
+
+        let token = NumberOrString::String("ferrotex-workspace-index".to_string());
+        let created = self
+            .client
+            .send_request::<request::WorkDoneProgressCreate>(WorkDoneProgressCreateParams { token: token.clone() })
+            .await
+            .is_ok();
+        let report = |progress: WorkDoneProgress| {
+            let client = self.client.clone();
+            let token = token.clone();
+            async move {
+                if created {
+                    client
+                        .send_notification::<notification::Progress>(ProgressParams {
+                            token,
+                            value: ProgressParamsValue::WorkDone(progress),
+                        })
+                        .await;
+                }
+            }
+        };
+
+        let total = files.len();
+        self.send_status(protocol::ServerStatus::Indexing { done: 0, total }).await;
+        report(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+            title: "Indexing workspace".to_string(),
+            cancellable: Some(false),
+            message: Some(format!("0/{} files", total)),
+            percentage: Some(0),
+        }))
+        .await;
+
+        let mut done = 0;
+        let mut cache = index_cache::IndexCache::new();
+        for batch in files.chunks(WORKSPACE_INDEX_BATCH) {
+            let batch = batch.to_vec();
+            let workspace = self.workspace.clone();
+            let documents = self.documents.clone();
+            let previous = previous.clone();
+            let entries = tokio::task::spawn_blocking(move || {
+                let mut entries = Vec::new();
+                for path in batch {
+                    let Ok(uri) = Url::from_file_path(&path) else {
+                        continue;
+                    };
+                    if documents.contains_key(&uri) {
+                        continue;
+                    }
+                    if let Some(entry) = previous.index_file(&workspace, &uri, &path) {
+                        entries.push((uri, entry));
+                    }
+                }
+                entries
+            })
+            .await
+            .unwrap_or_default();
+            for (uri, entry) in entries {
+                cache.insert(uri, entry);
+            }
+
+            done = (done + WORKSPACE_INDEX_BATCH).min(total);
+            self.send_status(protocol::ServerStatus::Indexing { done, total }).await;
+            report(WorkDoneProgress::Report(WorkDoneProgressReport {
+                cancellable: Some(false),
+                message: Some(format!("{}/{} files", done, total)),
+                percentage: Some((done * 100 / total) as u32),
+            }))
+            .await;
+        }
+
+        report(WorkDoneProgress::End(WorkDoneProgressEnd {
+            message: Some(format!("Indexed {} files", total)),
+        }))
+        .await;
+        log::info!("Indexed {} workspace files.", total);
+        if self.builds.is_empty() {
+            self.send_status(protocol::ServerStatus::Idle).await;
+        }
+
+        let _ = tokio::task::spawn_blocking(move || {
+            if let Err(e) = cache.save(&cache_root) {
+                log::warn!("Failed to save workspace cache: {}", e);
+            }
+        })
+        .await;
+
+        let open: Vec<Url> = self.documents.iter().map(|entry| entry.key().clone()).collect();
+        for uri in open {
+            self.validate_document(uri).await;
+        }
+    }
+
+    /// Re-reads project files changed on disk and re-validates the open documents.
+    ///
+    /// Files open in the editor are skipped, since their buffer is authoritative.
+    /// Files that can no longer be read are dropped from the index.
+    pub async fn refresh_files(&self, paths: &[std::path::PathBuf]) {
+        let mut changed = false;
+        for path in paths {
+            let Ok(uri) = Url::from_file_path(path) else {
+                continue;
+            };
+            if self.documents.contains_key(&uri) {
+                continue;
+            }
+            if !self.workspace.index_file(&uri, path) {
+                self.workspace.remove(&uri);
+            }
+            changed = true;
+        }
+        if !changed {
+            return;
+        }
+
+        let open: Vec<Url> = self.documents.iter().map(|entry| entry.key().clone()).collect();
+        for uri in open {
+            self.validate_document(uri).await;
+        }
+    }
+
+    /// Validates `uri` in a background task after the configured `diagnostics.delay`.
+    ///
+    /// A validation still waiting or running for the same document is aborted first,
+    /// so a burst of edits only validates the latest text.
+    pub fn schedule_validation(&self, uri: Url) {
+        let delay = self.settings.lock().unwrap().diagnostics.delay;
+        let backend = self.clone();
+        let task_uri = uri.clone();
+        let handle = tokio::spawn(async move {
+            if delay > 0 {
+                tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+            }
+            backend.validate_document(task_uri).await;
+        });
+        if let Some(previous) = self.validations.insert(uri, handle) {
+            previous.abort();
+        }
+    }
+
+    /// Publishes duplicate-key errors on every open `.bib` document.
+    ///
+    /// Duplicates span files, so an edit to one bibliography can add or clear errors in another;
+    /// so can a `.tex` edit that changes which bibliographies are referenced.
+    async fn publish_bib_diagnostics(&self) {
+        let duplicates = self.workspace.validate_bib_duplicates();
+        let open: Vec<(Url, String)> = self
+            .documents
+            .iter()
+            .filter(|entry| is_bib(entry.key()))
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+        for (uri, text) in open {
+            let line_index = position::LineIndex::with_encoding(&text, self.parse_cache.encoding());
+            let diagnostics = duplicates
+                .iter()
+                .filter(|(u, _, _)| u == &uri)
+                .map(|(_, range, message)| {
+                    Diagnostic {
+                        range: line_index.range(*range),
+                        severity: Some(DiagnosticSeverity::ERROR),
+                        code: Some(NumberOrString::String(DUPLICATE_KEY_CODE.to_string())),
+                        source: Some("ferrotex".to_string()),
+                        message: message.clone(),
+                        ..Default::default()
+                    }
+                })
+                .collect();
+            self.client.publish_diagnostics(uri, diagnostics, None).await;
+        }
+    }
+
     pub async fn validate_document(&self, uri: Url) {
-        if let Some(text) = self.documents.get(&uri) {
-            self.workspace.update(&uri, &text);
-            
+        if is_bib(&uri) {
+            // Bibliographies are indexed for citations but not checked as LaTeX.
+            if let Some(text) = self.documents.get(&uri).map(|text| text.clone()) {
+                self.workspace.update_bib(&uri, &text);
+            }
+            self.publish_bib_diagnostics().await;
+            return;
+        }
+        let text = self.documents.get(&uri).map(|text| text.clone());
+        if let (Some(text), Some(parsed)) = (text, self.parse_cache.get(&uri, &self.documents)) {
+            let settings = self.settings.lock().unwrap().clone();
+
             let mut diagnostics = Vec::new();
+            let mut grammar_input = None;
+            let bib_paths = |uri: &Url| -> Vec<String> {
+                self.workspace.get_bibliographies(uri).into_iter().map(|bib| bib.path).collect()
+            };
+            let bibliographies = bib_paths(&uri);
+            let language = diagnostics::spell::spellcheck_language(&text)
+                .or(settings.spellcheck.language.clone())
+                .unwrap_or_else(|| diagnostics::spell::DEFAULT_LANGUAGE.to_string());
+            let dictionary = diagnostics::spell::dictionary(&language).await;
             
             {
-                let parse_res = ferrotex_syntax::parse(&text);
-                let line_index = LineIndex::new(&text);
-                let root = ferrotex_syntax::SyntaxNode::new_root(parse_res.green_node());
+                let root = parsed.syntax();
+                let line_index = &parsed.line_index;
+                self.workspace.update_syntax(&uri, &text, &root);
 
-                for err in parse_res.errors {
-                    let start = line_index.line_col(err.range.start());
-                    let end = line_index.line_col(err.range.end());
+                for err in parsed.parse.errors.iter().cloned() {
                     diagnostics.push(Diagnostic {
-                        range: Range {
-                            start: Position { line: start.line, character: start.col },
-                            end: Position { line: end.line, character: end.col },
-                        },
+                        range: line_index.range(err.range),
                         severity: Some(DiagnosticSeverity::ERROR),
                         message: err.message,
                         ..Default::default()
                     });
                 }
                 
-                let math_diags = diagnostics::math::check_math(&root, &line_index);
+                let math_diags = diagnostics::math::check_math(&root, line_index);
                 diagnostics.extend(math_diags);
+
+                if settings.lint.enabled {
+                    let lint_diags = diagnostics::lint::check_lint(&root, line_index, &settings.lint.lint_config());
+                    diagnostics.extend(lint_diags);
+                }
+
+                if let Some(dictionary) = &dictionary {
+                    diagnostics.extend(diagnostics::spell::check_spelling(&root, line_index, dictionary));
+                }
+
+                if settings.grammar.enabled {
+                    let language = settings
+                        .grammar
+                        .language
+                        .clone()
+                        .unwrap_or_else(|| diagnostics::grammar::languagetool_language(&language));
+                    grammar_input = Some((diagnostics::grammar::extract_prose(&root), language));
+                }
+            }
+
+            // Duplicate keys are checked across the referenced bibliographies only.
+            if bib_paths(&uri) != bibliographies {
+                self.publish_bib_diagnostics().await;
             }
 
             let labels = self.workspace.validate_labels();
@@ -478,104 +1002,480 @@ impl Backend {
                 }
             }
 
-            self.client.publish_diagnostics(uri.clone(), diagnostics.clone(), None).await;
+            let line_index = &parsed.line_index;
+            for (u, r, m) in self.workspace.validate_deprecated(|marker| settings.lint.reports_deprecation(marker)) {
+                if u == uri {
+                    diagnostics.push(Diagnostic {
+                        range: line_index.range(r),
+                        severity: Some(DiagnosticSeverity::WARNING),
+                        code: Some(NumberOrString::String("deprecated".to_string())),
+                        source: Some("ferrotex".to_string()),
+                        message: m,
+                        tags: Some(vec![DiagnosticTag::DEPRECATED]),
+                        ..Default::default()
+                    });
+                }
+            }
 
-            // Log diagnostic logic
-            if let Ok(path) = uri.to_file_path() {
+            // Log events of the last build, placed at their file and line.
+            if let Some(build) = self.build_diagnostics.get(&uri) {
+                diagnostics.extend(build.iter().cloned());
+            }
+
+            // Without a build of the server, show the warnings of a log an external build wrote;
+            // the log belongs to the root document, not the buffer.
+            let root = self.root_document(&uri);
+            let built_files: Vec<Url> = self.build_diagnostics.iter().map(|entry| entry.key().clone()).collect();
+            let built = built_files.iter().any(|file| self.root_document(file) == root);
+            if let (false, Ok(path)) = (built, root.to_file_path()) {
                 let log_path = path.with_extension("log");
-                if log_path.exists() {
-                   if let Ok(log_content) = std::fs::read_to_string(&log_path) {
-                       let parser = ferrotex_log::LogParser::new();
-                       let events = parser.parse(&log_content);
-                       
-                       let mut log_diags = Vec::new();
-                       for event in events {
-                           if let ferrotex_log::ir::EventPayload::Warning { message } = event.payload {
-                               log_diags.push(Diagnostic {
-                                   range: Range::default(),
-                                   severity: Some(DiagnosticSeverity::WARNING),
-                                   message,
-                                   ..Default::default()
-                               });
-                           }
-                       }
-                       // Combine if needed or publish separately
-                       if !log_diags.is_empty() {
-                           diagnostics.extend(log_diags);
-                           self.client.publish_diagnostics(uri, diagnostics, None).await;
-                       }
-                   }
+                if let Ok(log_content) = tokio::fs::read_to_string(&log_path).await {
+                    let parser = ferrotex_log::LogParser::new();
+                    let mut events = parser.parse(&log_content);
+                    let suppressed = self.settings.lock().unwrap().diagnostics.suppressed_warnings.clone();
+                    build::log_diagnostics::drop_suppressed(&mut events, &suppressed);
+
+                    let mut log_diags = Vec::new();
+                    for event in events {
+                        if let Some(message) = event.payload.warning_message() {
+                            log_diags.push(Diagnostic {
+                                range: Range::default(),
+                                severity: Some(DiagnosticSeverity::WARNING),
+                                message: message.to_string(),
+                                ..Default::default()
+                            });
+                        }
+                    }
+                    diagnostics.extend(log_diags);
                 }
             }
+
+            // The syntax tree is not `Send`, so the LanguageTool request happens after it is dropped.
+            match grammar_input {
+                Some((prose, language)) => {
+                    self.publish_with_grammar(uri, parsed, diagnostics, prose, language, settings.grammar).await;
+                }
+                None => self.client.publish_diagnostics(uri, diagnostics, None).await,
+            }
         }
     }
 
-    pub async fn run_build(&self, uri: Url) {
-        let client = self.client.clone();
-        
-        tokio::spawn(async move {
-            let adapter = LatexmkAdapter;
-            let request = BuildRequest {
-                document_uri: uri,
-                workspace_root: None,
-            };
-            
-            let _ = client.log_message(MessageType::INFO, "Building...").await;
-            match adapter.build(&request, None).await {
-                Ok(_) => {
-                    let _ = client.log_message(MessageType::INFO, "Build successful").await;
-                }
+    /// Publishes `diagnostics` of the `parsed` version of `uri` together with its grammar
+    /// diagnostics.
+    ///
+    /// LanguageTool may take seconds to answer, so unless this version was checked before,
+    /// `diagnostics` are published right away and the merged set once a background check
+    /// completes, if the document has not changed meanwhile.
+    async fn publish_with_grammar(
+        &self,
+        uri: Url,
+        parsed: Arc<parse_cache::ParsedDocument>,
+        mut diagnostics: Vec<Diagnostic>,
+        prose: diagnostics::grammar::Prose,
+        language: String,
+        settings: settings::GrammarSettings,
+    ) {
+        if let Some(grammar) = self.grammar.cached(&uri, parsed.version) {
+            diagnostics.extend(grammar);
+            self.client.publish_diagnostics(uri, diagnostics, None).await;
+            return;
+        }
+        self.client.publish_diagnostics(uri.clone(), diagnostics.clone(), None).await;
+
+        let backend = self.clone();
+        let task_uri = uri.clone();
+        let check = tokio::spawn(async move {
+            let uri = task_uri;
+            let grammar = match backend.grammar.check(&settings, &language, &prose).await {
+                Ok(matches) => diagnostics::grammar::to_diagnostics(&prose, &matches, &parsed.line_index),
                 Err(e) => {
-                    let _ = client.log_message(MessageType::ERROR, format!("Build failed: {}", e)).await;
+                    log::warn!("Grammar check failed: {}", e);
+                    return;
                 }
+            };
+            backend.grammar.store(uri.clone(), parsed.version, grammar.clone());
+            if backend.documents.contains_key(&uri) && backend.parse_cache.version(&uri) == parsed.version {
+                diagnostics.extend(grammar);
+                backend.client.publish_diagnostics(uri, diagnostics, None).await;
             }
         });
+        self.grammar.track(uri, check);
     }
 
-    fn compute_semantic_tokens(&self, text: &str) -> Vec<SemanticToken> {
-        let mut tokens = Vec::new();
-        let mut last_line = 0;
-        let mut last_char = 0;
+    /// Returns the main document `uri` belongs to.
+    ///
+    /// The `rootDocument` setting takes precedence; otherwise the root is resolved from
+    /// `%!TEX root` comments, the `subfiles` class and the include graph.
+    pub fn root_document(&self, uri: &Url) -> Url {
+        let root_document = self.settings.lock().unwrap().root_document.clone();
+        let workspace_root = self.root_uri.lock().unwrap().as_ref().and_then(|u| u.to_file_path().ok());
+        if let (Some(root), Some(dir)) = (root_document, workspace_root) {
+            if let Ok(root_uri) = Url::from_file_path(dir.join(root)) {
+                return root_uri;
+            }
+        }
+        self.workspace.resolve_root(uri)
+    }
 
-        let parse_res = ferrotex_syntax::parse(text);
-        let line_index = LineIndex::new(text);
-        
-        for node in parse_res.syntax().descendants() {
-            let kind = node.kind();
-            let token_type = match kind {
-                SyntaxKind::Command => 0, // MACRO
-                SyntaxKind::Environment => 1, // KEYWORD
-                SyntaxKind::Group => 2, // STRING
-                SyntaxKind::Comment => 3, // COMMENT
-                _ => continue,
-            };
+    pub async fn run_build(&self, uri: Url) {
+        let backend = self.clone();
+        tokio::spawn(async move {
+            backend.build_document(uri).await;
+        });
+    }
 
-            let range = node.text_range();
-            let start = line_index.line_col(range.start());
-            let end = line_index.line_col(range.end());
+    /// Returns the PDF built from the root document of `uri` that has SyncTeX data, and
+    /// the directory the engine ran in.
+    ///
+    /// `pdf` is tried first; otherwise the build output directory, then the document's own.
+    fn synctex_pdf(&self, uri: &Url, pdf: Option<std::path::PathBuf>) -> Option<(std::path::PathBuf, std::path::PathBuf)> {
+        let root = self.root_document(uri).to_file_path().ok()?;
+        let base_dir = root.parent()?.to_path_buf();
+        let stem = root.file_stem()?;
+        let mut candidates: Vec<std::path::PathBuf> = pdf.into_iter().collect();
+        candidates.push(base_dir.join("build").join(stem).with_extension("pdf"));
+        candidates.push(base_dir.join(stem).with_extension("pdf"));
+        let pdf = candidates.into_iter().find(|pdf| synctex::find_synctex_file(pdf).is_some())?;
+        Some((pdf, base_dir))
+    }
 
-            if start.line != end.line { continue; }
+    /// Resolves a source line to a PDF page and position, as `{ page, x, y }` in PDF points.
+    pub async fn synctex_forward(&self, uri: &Url, line: u32, pdf: Option<std::path::PathBuf>) -> Option<serde_json::Value> {
+        let tex_path = uri.to_file_path().ok()?;
+        let Some((pdf, base_dir)) = self.synctex_pdf(uri, pdf) else {
+            self.client
+                .show_message(MessageType::WARNING, "No SyncTeX data found. Build the document first.")
+                .await;
+            return None;
+        };
+        let result = tokio::task::spawn_blocking(move || synctex::forward_search(&tex_path, &pdf, &base_dir, line))
+            .await
+            .ok()
+            .flatten()?;
+        serde_json::to_value(result).ok()
+    }
 
-            let delta_line = start.line - last_line;
-            let delta_char = if delta_line == 0 {
-                start.col - last_char
-            } else {
-                start.col
+    /// Resolves a PDF location to the source line typeset there, as a [`Location`].
+    pub async fn synctex_inverse(&self, pdf: std::path::PathBuf, page: u32, x: f64, y: f64) -> Option<serde_json::Value> {
+        if synctex::find_synctex_file(&pdf).is_none() {
+            self.client
+                .show_message(MessageType::WARNING, "No SyncTeX data found. Build the document first.")
+                .await;
+            return None;
+        }
+        // Builds write their output to `build/` next to the root document, where they run.
+        let pdf_dir = pdf.parent()?.to_path_buf();
+        let base_dir = if pdf_dir.file_name().is_some_and(|name| name == "build") {
+            pdf_dir.parent().map(|p| p.to_path_buf()).unwrap_or(pdf_dir)
+        } else {
+            pdf_dir
+        };
+        let result = tokio::task::spawn_blocking(move || synctex::inverse_search(&pdf, &base_dir, page, x, y))
+            .await
+            .ok()
+            .flatten()?;
+        let position = Position { line: result.line, character: 0 };
+        let location = Location {
+            uri: Url::from_file_path(&result.file).ok()?,
+            range: Range { start: position, end: position },
+        };
+        serde_json::to_value(location).ok()
+    }
+
+    /// Removes the auxiliary files of the root document of `uri` and returns their paths.
+    pub async fn clean(&self, uri: &Url) -> Option<serde_json::Value> {
+        let root = self.root_document(uri).to_file_path().ok()?;
+        let extensions = self.settings.lock().unwrap().build.clean_extensions.clone();
+        let result = tokio::task::spawn_blocking(move || build::clean::clean(&root, &extensions, false)).await.ok()?;
+        match result {
+            Ok(removed) => {
+                let names: Vec<String> = removed.iter().map(|p| p.to_string_lossy().into_owned()).collect();
+                let message = format!("Removed {} auxiliary file(s)", names.len());
+                self.client.log_message(MessageType::INFO, format!("{}: {}", message, names.join(", "))).await;
+                self.client.show_message(MessageType::INFO, message).await;
+                Some(serde_json::Value::from(names))
+            }
+            Err(e) => {
+                self.client
+                    .show_message(MessageType::ERROR, format!("Failed to clean auxiliary files: {}", e))
+                    .await;
+                None
+            }
+        }
+    }
+
+    /// Starts, restarts or stops the inverse search listener to match the settings.
+    pub async fn configure_inverse_search(&self) {
+        let settings = self.settings.lock().unwrap().inverse_search.clone();
+        let mut listener = self.inverse_search.lock().await;
+        if listener.as_ref().is_some_and(|l| *l.settings() == settings) {
+            return;
+        }
+        // Release the old socket before binding it again.
+        *listener = None;
+        if !settings.enabled {
+            return;
+        }
+        match inverse_search::start(&settings, self.client.clone()).await {
+            Ok(started) => *listener = Some(started),
+            Err(e) => {
+                self.client
+                    .show_message(MessageType::WARNING, format!("Inverse search listener failed to start: {}", e))
+                    .await;
+            }
+        }
+    }
+
+    /// Cancels the running build of the root document of `uri`, or all builds if `None`.
+    ///
+    /// Returns whether a build was running.
+    pub fn cancel_build(&self, uri: Option<&Url>) -> bool {
+        match uri {
+            Some(uri) => match self.builds.get(&self.root_document(uri)) {
+                Some(cancel) => {
+                    cancel.notify_one();
+                    true
+                }
+                None => false,
+            },
+            None => {
+                self.builds.iter().for_each(|cancel| cancel.notify_one());
+                !self.builds.is_empty()
+            }
+        }
+    }
+
+    /// Builds the root document of `uri`, reporting each engine pass as work-done progress.
+    ///
+    /// Engine output is fed through [`ferrotex_log::LogParser`] while the build runs;
+    /// once it finishes, the errors and warnings found are published on their source files,
+    /// replacing those of the previous build.
+    pub async fn build_document(&self, uri: Url) {
+        let build_settings = self.settings.lock().unwrap().build.clone();
+        let workspace_root = self.root_uri.lock().unwrap().as_ref().and_then(|u| u.to_file_path().ok());
+        let document_uri = self.root_document(&uri);
+        let base_dir = document_uri
+            .to_file_path()
+            .ok()
+            .and_then(|p| p.parent().map(|p| p.to_path_buf()))
+            .unwrap_or_default();
+
+        let adapter = engine_for(&build_settings);
+        let format = adapter.output_format();
+        let cancel = Arc::new(tokio::sync::Notify::new());
+        self.builds.insert(document_uri.clone(), cancel.clone());
+        // A timeout cancels the build like `ferrotex.cancelBuild`, but is reported as a failure.
+        let timed_out = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let timer = build_settings.timeout.map(|seconds| {
+            let (cancel, timed_out) = (cancel.clone(), timed_out.clone());
+            tokio::spawn(async move {
+                tokio::time::sleep(std::time::Duration::from_secs(seconds)).await;
+                timed_out.store(true, std::sync::atomic::Ordering::SeqCst);
+                cancel.notify_one();
+            })
+        });
+        let request = BuildRequest {
+            document_uri: document_uri.clone(),
+            workspace_root,
+            cancel: Some(cancel.clone()),
+            profile: build_settings.profile,
+            synctex: build_settings.synctex,
+        };
+
+        let token = NumberOrString::String("ferrotex-build".to_string());
+        let created = self
+            .client
+            .send_request::<request::WorkDoneProgressCreate>(WorkDoneProgressCreateParams { token: token.clone() })
+            .await
+            .is_ok();
+        let progress_client = self.client.clone();
+        let report = move |progress: WorkDoneProgress| {
+            let client = progress_client.clone();
+            let token = token.clone();
+            async move {
+                if created {
+                    client
+                        .send_notification::<notification::Progress>(ProgressParams {
+                            token,
+                            value: ProgressParamsValue::WorkDone(progress),
+                        })
+                        .await;
+                }
+            }
+        };
+
+        report(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+            title: format!("Building with {}", adapter.name()),
+            cancellable: Some(false),
+            message: None,
+            percentage: None,
+        }))
+        .await;
+        self.client
+            .log_message(MessageType::INFO, format!("Building with {}...", adapter.name()))
+            .await;
+        self.send_status(protocol::ServerStatus::Building { uri: document_uri.clone(), pass: 0, step: None })
+            .await;
+
+        // Output lines arrive on the engine's reader tasks; parse them here as they come.
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        let callback: Box<dyn Fn(String) + Send + Sync> = Box::new(move |line| {
+            let _ = tx.send(line);
+        });
+        let client = self.client.clone();
+        let progress = report.clone();
+        let consumer_root = document_uri.clone();
+        let consumer = tokio::spawn(async move {
+            let mut passes = 0;
+            let mut step: Option<String> = None;
+            let mut parser = match format {
+                OutputFormat::TexLog => ferrotex_log::LogParser::new(),
+                OutputFormat::Tectonic => ferrotex_log::LogParser::new().with_format(ferrotex_log::LogFormat::Tectonic),
             };
+            let mut events = Vec::new();
+            while let Some(line) = rx.recv().await {
+                let line = line
+                    .strip_prefix("[stdout] ")
+                    .or_else(|| line.strip_prefix("[stderr] "))
+                    .unwrap_or(&line);
+                let pass = match format {
+                    OutputFormat::TexLog => build::log_diagnostics::pass_name(line),
+                    OutputFormat::Tectonic => build::log_diagnostics::tectonic_pass_name(line),
+                };
+                if let Some(pass) = pass {
+                    passes += 1;
+                    client.log_message(MessageType::INFO, format!("Running {}", pass)).await;
+                    client
+                        .send_notification::<protocol::Status>(protocol::ServerStatus::Building {
+                            uri: consumer_root.clone(),
+                            pass: passes,
+                            step: Some(pass.clone()),
+                        })
+                        .await;
+                    progress(WorkDoneProgress::Report(WorkDoneProgressReport {
+                        cancellable: Some(false),
+                        message: Some(format!("Running {}", pass)),
+                        percentage: None,
+                    }))
+                    .await;
+                    step = Some(pass);
+                }
+                let parsed = parser.update(line);
+                let page = parsed.iter().rev().find_map(|event| match event.payload {
+                    ferrotex_log::ir::EventPayload::PageShipout { number } => Some(number),
+                    _ => None,
+                });
+                if let (Some(page), Some(step)) = (page, &step) {
+                    progress(WorkDoneProgress::Report(WorkDoneProgressReport {
+                        cancellable: Some(false),
+                        message: Some(format!("Running {}: page {}", step, page)),
+                        percentage: None,
+                    }))
+                    .await;
+                }
+                if !parsed.is_empty() {
+                    send_log(&client, &consumer_root, passes, &parsed).await;
+                }
+                events.extend(parsed);
+            }
+            let rest = parser.finish();
+            if !rest.is_empty() {
+                send_log(&client, &consumer_root, passes, &rest).await;
+            }
+            events.extend(rest);
+            events
+        });
 
-            tokens.push(SemanticToken {
-                delta_line,
-                delta_start: delta_char,
-                length: (range.end() - range.start()).into(),
-                token_type,
-                token_modifiers_bitset: 0,
-            });
+        let result = adapter.build(&request, Some(callback)).await;
+        let mut events = consumer.await.unwrap_or_default();
+        self.builds.remove_if(&document_uri, |_, running| Arc::ptr_eq(running, &cancel));
+        if let Some(timer) = timer {
+            timer.abort();
+        }
 
-            last_line = start.line;
-            last_char = start.col;
+        if matches!(result, Ok(BuildStatus::Cancelled)) && timed_out.load(std::sync::atomic::Ordering::SeqCst) {
+            let message = format!("Build timed out after {}s", build_settings.timeout.unwrap_or_default());
+            self.client.log_message(MessageType::ERROR, &message).await;
+            self.send_status(protocol::ServerStatus::Failed { uri: document_uri.clone(), errors: 0, message: message.clone() })
+                .await;
+            report(WorkDoneProgress::End(WorkDoneProgressEnd { message: Some(message) })).await;
+            return;
+        }
+        if matches!(result, Ok(BuildStatus::Cancelled)) {
+            // The log of an aborted run is incomplete; keep the previous build's diagnostics.
+            self.client.log_message(MessageType::INFO, "Build cancelled").await;
+            report(WorkDoneProgress::End(WorkDoneProgressEnd { message: Some("Build cancelled".to_string()) })).await;
+            self.send_status(protocol::ServerStatus::Idle).await;
+            return;
         }
 
-        tokens
+        let suppressed = self.settings.lock().unwrap().diagnostics.suppressed_warnings.clone();
+        build::log_diagnostics::drop_suppressed(&mut events, &suppressed);
+        let mut found = build::log_diagnostics::diagnostics_from_events(&events, &base_dir, &document_uri);
+        // makeindex and xindy report index problems in their own transcript.
+        let index_log = document_uri.to_file_path().ok().and_then(|root| build::log_diagnostics::index_log_path(&root));
+        if let Some(path) = index_log {
+            if let Ok(bytes) = tokio::fs::read(path).await {
+                let mut index_events = ferrotex_log::LogParser::new().parse(&String::from_utf8_lossy(&bytes));
+                build::log_diagnostics::drop_suppressed(&mut index_events, &suppressed);
+                for (file, diagnostics) in
+                    build::log_diagnostics::diagnostics_from_events(&index_events, &base_dir, &document_uri)
+                {
+                    found.entry(file).or_default().extend(diagnostics);
+                }
+            }
+        }
+        let labels: std::collections::HashSet<String> = self.workspace.get_all_labels().into_iter().collect();
+        build::log_diagnostics::drop_resolved_keys(&mut found, |code, key| match code {
+            build::log_diagnostics::UNDEFINED_REFERENCE_CODE => labels.contains(key),
+            _ => self.workspace.has_citation_key(key),
+        });
+        let errors = found
+            .values()
+            .flatten()
+            .filter(|d| d.severity == Some(DiagnosticSeverity::ERROR))
+            .count();
+        let message = match &result {
+            Ok(BuildStatus::Success(_)) => "Build successful".to_string(),
+            Ok(BuildStatus::Failure(events)) => {
+                let first = ferrotex_log::correlate(events).into_iter().find_map(|event| match event.payload {
+                    ferrotex_log::ir::EventPayload::Error { message, .. } => Some(message),
+                    _ => None,
+                });
+                match first {
+                    Some(first) => format!("Build failed with {} error(s): {}", errors, first),
+                    None => format!("Build failed with {} error(s)", errors),
+                }
+            }
+            Ok(BuildStatus::Cancelled) => unreachable!("handled above"),
+            Err(e) => format!("Build failed: {}", e),
+        };
+        let level = if matches!(result, Ok(BuildStatus::Success(_))) { MessageType::INFO } else { MessageType::ERROR };
+        self.client.log_message(level, &message).await;
+        let status = match result {
+            Ok(BuildStatus::Success(_)) => protocol::ServerStatus::Idle,
+            _ => protocol::ServerStatus::Failed { uri: document_uri.clone(), errors, message: message.clone() },
+        };
+        self.send_status(status).await;
+        report(WorkDoneProgress::End(WorkDoneProgressEnd { message: Some(message) })).await;
+
+        // Replace the previous build's diagnostics, clearing files that are clean now.
+        let mut affected: Vec<Url> = self.build_diagnostics.iter().map(|e| e.key().clone()).collect();
+        self.build_diagnostics.clear();
+        for (file, diagnostics) in found {
+            affected.push(file.clone());
+            self.build_diagnostics.insert(file, diagnostics);
+        }
+        affected.sort();
+        affected.dedup();
+        for file in affected {
+            if self.documents.contains_key(&file) {
+                self.validate_document(file).await;
+            } else {
+                let diagnostics = self.build_diagnostics.get(&file).map(|d| d.clone()).unwrap_or_default();
+                self.client.publish_diagnostics(file, diagnostics, None).await;
+            }
+        }
     }
 }
 
@@ -593,6 +1493,13 @@ mod tests {
             syntax_diagnostics: Arc::new(DashMap::new()),
             package_manager: Arc::new(Mutex::new(ferrotex_core::package_manager::PackageManager::new())),
             package_index: Arc::new(Mutex::new(None)),
+            settings: Arc::new(Mutex::new(Settings::default())),
+            parse_cache: Arc::new(parse_cache::ParseCache::new()),
+            validations: Arc::new(DashMap::new()),
+            grammar: Arc::new(diagnostics::grammar::GrammarChecker::new()),
+            build_diagnostics: Arc::new(DashMap::new()),
+            builds: Arc::new(DashMap::new()),
+            inverse_search: Arc::new(tokio::sync::Mutex::new(None)),
         });
         
         service
@@ -611,6 +1518,142 @@ mod tests {
         assert!(result.capabilities.text_document_sync.is_some());
     }
 
+    #[tokio::test]
+    async fn test_backend_configuration() {
+        let service = setup().await;
+        let backend = service.inner();
+
+        let params = InitializeParams {
+            initialization_options: Some(serde_json::json!({ "format": { "indentSize": 2 } })),
+            ..Default::default()
+        };
+        backend.initialize(params).await.unwrap();
+        assert_eq!(backend.settings.lock().unwrap().format.indent_size, Some(2));
+
+        backend.did_change_configuration(DidChangeConfigurationParams {
+            settings: serde_json::json!({ "ferrotex": { "lint": { "enabled": false } } }),
+        }).await;
+        let settings = backend.settings.lock().unwrap().clone();
+        assert!(!settings.lint.enabled);
+        assert_eq!(settings.format.indent_size, None, "A new configuration replaces the previous one");
+    }
+
+    #[tokio::test]
+    async fn test_refresh_files() {
+        let service = setup().await;
+        let backend = service.inner();
+
+        let dir = tempfile::tempdir().unwrap();
+        let bib = dir.path().join("refs.bib");
+        std::fs::write(&bib, "@book{knuth, title={TeX}}").unwrap();
+        backend.refresh_files(std::slice::from_ref(&bib)).await;
+        assert!(backend.workspace.has_citation_key("knuth"));
+
+        std::fs::write(&bib, "@book{lamport, title={LaTeX}}").unwrap();
+        backend.refresh_files(std::slice::from_ref(&bib)).await;
+        assert!(!backend.workspace.has_citation_key("knuth"));
+        assert!(backend.workspace.has_citation_key("lamport"));
+
+        std::fs::remove_file(&bib).unwrap();
+        backend.refresh_files(std::slice::from_ref(&bib)).await;
+        assert!(!backend.workspace.has_citation_key("lamport"));
+    }
+
+    #[tokio::test]
+    async fn test_validation_reuses_line_index() {
+        let service = setup().await;
+        let backend = service.inner();
+        let uri = Url::parse("file:///large.tex").unwrap();
+        let text: String = (0..2000).map(|i| format!("\\section{{S{}}}\\label{{l{}}} {{\\bf bold}}\n", i, i)).collect();
+        backend.documents.insert(uri.clone(), text);
+
+        let before = position::CONSTRUCTED.with(|count| count.get());
+        backend.validate_document(uri.clone()).await;
+        let _ = backend.document_symbol(DocumentSymbolParams {
+            text_document: TextDocumentIdentifier { uri },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        }).await;
+        // The parse cache's index, built once for validation and the outline.
+        assert_eq!(position::CONSTRUCTED.with(|count| count.get()) - before, 1);
+    }
+
+    #[tokio::test]
+    async fn test_grammar_check_does_not_delay_validation() {
+        let service = setup().await;
+        let backend = service.inner();
+        // A LanguageTool server that accepts connections but never answers.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let accepted = requests.clone();
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                accepted.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                connections.push(socket);
+            }
+        });
+        {
+            let mut settings = backend.settings.lock().unwrap();
+            settings.grammar.enabled = true;
+            settings.grammar.url = url;
+        }
+
+        let uri = Url::parse("file:///grammar.tex").unwrap();
+        backend.documents.insert(uri.clone(), "We has a cat.".to_string());
+        let validation = backend.validate_document(uri.clone());
+        assert!(tokio::time::timeout(std::time::Duration::from_secs(5), validation).await.is_ok());
+        assert!(backend.grammar.cached(&uri, 0).is_none());
+        while requests.load(std::sync::atomic::Ordering::SeqCst) == 0 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        // A checked version is not submitted again.
+        backend.grammar.store(uri.clone(), 0, Vec::new());
+        backend.validate_document(uri.clone()).await;
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_schedule_validation_latest_wins() {
+        let service = setup().await;
+        let backend = service.inner();
+        backend.settings.lock().unwrap().diagnostics.delay = 50;
+
+        let uri = Url::parse("file:///debounce.tex").unwrap();
+        backend.documents.insert(uri.clone(), r"\label{first}".to_string());
+        backend.schedule_validation(uri.clone());
+        let first = backend.validations.get(&uri).unwrap().abort_handle();
+
+        backend.documents.insert(uri.clone(), r"\label{second}".to_string());
+        backend.parse_cache.set_version(&uri, 2);
+        backend.schedule_validation(uri.clone());
+
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert!(first.is_finished(), "Superseded validation must be aborted");
+        assert!(backend.workspace.find_definitions("second").is_empty(), "Validation must wait for the delay");
+
+        let (_, latest) = backend.validations.remove(&uri).unwrap();
+        latest.await.unwrap();
+        assert_eq!(backend.workspace.find_definitions("second").len(), 1);
+        assert!(backend.workspace.find_definitions("first").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_index_workspace() {
+        let service = setup().await;
+        let backend = service.inner();
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.tex"), r"See \ref{sec:other}").unwrap();
+        std::fs::write(dir.path().join("other.tex"), r"\section{Other}\label{sec:other}").unwrap();
+
+        backend.index_workspace(dir.path().to_path_buf()).await;
+        assert_eq!(backend.workspace.find_definitions("sec:other").len(), 1);
+    }
+
     #[tokio::test]
     async fn test_backend_lifecycle() {
         let service = setup().await;
@@ -644,6 +1687,15 @@ mod tests {
         }).await;
         
         assert_eq!(backend.documents.get(&uri).unwrap().as_str(), "\\section{Changed}");
+
+        // Close
+        backend.did_close(DidCloseTextDocumentParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+        }).await;
+
+        assert!(!backend.documents.contains_key(&uri));
+        assert!(!backend.validations.contains_key(&uri));
+        assert!(backend.parse_cache.get(&uri, &backend.documents).is_none());
         
         // Shutdown
         backend.shutdown().await.unwrap();
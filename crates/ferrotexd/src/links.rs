@@ -0,0 +1,192 @@
+use ferrotex_syntax::{SyntaxKind, SyntaxNode, TextRange, TextSize};
+use crate::position::LineIndex;
+use rowan::NodeOrToken;
+use std::path::Path;
+use tower_lsp::lsp_types::{DocumentLink, Url};
+
+/// Extensions tried, in order, for `\includegraphics` paths written without one.
+const GRAPHICS_EXTENSIONS: &[&str] = &["pdf", "png", "jpg", "jpeg", "eps"];
+
+/// Finds links to the files referenced by a document.
+///
+/// Covers `\input`, `\include`, `\bibliography` (one link per comma-separated file),
+/// `\addbibresource` and `\includegraphics`. Paths are resolved against `base`, the
+/// root document, since LaTeX resolves them relative to the directory it runs in.
+/// Graphics paths without an extension only link if a matching file exists.
+pub fn find_links(root: &SyntaxNode, line_index: &LineIndex, base: &Url) -> Vec<DocumentLink> {
+    let mut links = Vec::new();
+    let mut push = |range: TextRange, target: Url| {
+        links.push(DocumentLink {
+            range: line_index.range(range),
+            target: Some(target),
+            tooltip: None,
+            data: None,
+        });
+    };
+
+    for element in root.descendants_with_tokens() {
+        match element {
+            NodeOrToken::Node(node) if node.kind() == SyntaxKind::Include => {
+                let Some((path, range)) = node.children().find(|n| n.kind() == SyntaxKind::Group).and_then(|g| group_content(&g)) else {
+                    continue;
+                };
+                if let Some(target) = resolve(base, &path, "tex") {
+                    push(range, target);
+                }
+            }
+            NodeOrToken::Node(node) if node.kind() == SyntaxKind::Bibliography => {
+                let is_addbibresource = node
+                    .first_token()
+                    .is_some_and(|t| t.text() == "\\addbibresource");
+                let Some((paths, range)) = node.children().find(|n| n.kind() == SyntaxKind::Group).and_then(|g| group_content(&g)) else {
+                    continue;
+                };
+                if is_addbibresource {
+                    if let Some(target) = resolve(base, &paths, "bib") {
+                        push(range, target);
+                    }
+                    continue;
+                }
+                let mut offset = range.start();
+                for path in paths.split(',') {
+                    let trimmed = path.trim();
+                    let start = offset + TextSize::of(&path[..path.len() - path.trim_start().len()]);
+                    offset += TextSize::of(path) + TextSize::from(1);
+                    if let Some(target) = resolve(base, trimmed, "bib") {
+                        push(TextRange::at(start, TextSize::of(trimmed)), target);
+                    }
+                }
+            }
+            NodeOrToken::Token(token) if token.kind() == SyntaxKind::Command && token.text() == "\\includegraphics" => {
+                let Some((path, range)) = argument_group(&token).and_then(|g| group_content(&g)) else {
+                    continue;
+                };
+                if let Some(target) = resolve_graphics(base, &path) {
+                    push(range, target);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    links
+}
+
+/// Returns the `{...}` argument following a command, skipping a `*` and one `[...]` option.
+pub(crate) fn argument_group(command: &ferrotex_syntax::SyntaxToken) -> Option<SyntaxNode> {
+    nth_argument_group(command, 0)
+}
+
+/// Returns the `index`-th (0-based) `{...}` argument of a command, skipping a `*` and
+/// `[...]` options between the arguments.
+pub(crate) fn nth_argument_group(command: &ferrotex_syntax::SyntaxToken, mut index: usize) -> Option<SyntaxNode> {
+    let mut next = command.next_sibling_or_token();
+    let mut in_option = false;
+    while let Some(element) = next {
+        match element.kind() {
+            SyntaxKind::Group if !in_option && index == 0 => return element.into_node(),
+            SyntaxKind::Group if !in_option => index -= 1,
+            SyntaxKind::LBracket if !in_option => in_option = true,
+            SyntaxKind::RBracket if in_option => in_option = false,
+            _ if in_option => {}
+            SyntaxKind::Text if element.as_token().is_some_and(|t| t.text() == "*") => {}
+            _ => return None,
+        }
+        next = element.next_sibling_or_token();
+    }
+    None
+}
+
+/// Returns the trimmed text between the braces of a group and its range.
+pub(crate) fn group_content(group: &SyntaxNode) -> Option<(String, TextRange)> {
+    let text = group.text().to_string();
+    let inner = text.strip_prefix('{')?;
+    let inner = inner.strip_suffix('}').unwrap_or(inner);
+    let trimmed = inner.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    let leading = inner.len() - inner.trim_start().len();
+    let start = group.text_range().start() + TextSize::from((1 + leading) as u32);
+    Some((trimmed.to_string(), TextRange::at(start, TextSize::of(trimmed))))
+}
+
+/// Resolves `path` against `base`, appending `extension` if the path has none.
+fn resolve(base: &Url, path: &str, extension: &str) -> Option<Url> {
+    let path = path.trim().trim_matches('"');
+    if path.is_empty() {
+        return None;
+    }
+    if Path::new(path).extension().is_some() {
+        base.join(path).ok()
+    } else {
+        base.join(&format!("{}.{}", path, extension)).ok()
+    }
+}
+
+/// Resolves a graphics path against `base`, trying [`GRAPHICS_EXTENSIONS`] if it has none.
+pub(crate) fn resolve_graphics(base: &Url, path: &str) -> Option<Url> {
+    let path = path.trim().trim_matches('"');
+    if path.is_empty() {
+        return None;
+    }
+    if Path::new(path).extension().is_some() {
+        return base.join(path).ok();
+    }
+    GRAPHICS_EXTENSIONS
+        .iter()
+        .filter_map(|ext| base.join(&format!("{}.{}", path, ext)).ok())
+        .find(|uri| uri.to_file_path().is_ok_and(|p| p.exists()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links(text: &str, base: &Url) -> Vec<(String, String)> {
+        let root = ferrotex_syntax::parse(text).syntax();
+        let line_index = LineIndex::new(text);
+        let lines: Vec<&str> = text.lines().collect();
+        find_links(&root, &line_index, base)
+            .into_iter()
+            .map(|link| {
+                let r = link.range;
+                assert_eq!(r.start.line, r.end.line);
+                let line = lines[r.start.line as usize];
+                let source = line[r.start.character as usize..r.end.character as usize].to_string();
+                (source, link.target.unwrap().to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_links_for_includes_and_bibliographies() {
+        let base = Url::parse("file:///project/main.tex").unwrap();
+        let text = "\\input{chapters/intro}\n\\include{ appendix.tex }\n\\bibliography{refs, more}\n\\addbibresource[label=x]{lib.bib}";
+        assert_eq!(
+            links(text, &base),
+            vec![
+                ("chapters/intro".to_string(), "file:///project/chapters/intro.tex".to_string()),
+                ("appendix.tex".to_string(), "file:///project/appendix.tex".to_string()),
+                ("refs".to_string(), "file:///project/refs.bib".to_string()),
+                ("more".to_string(), "file:///project/more.bib".to_string()),
+                ("lib.bib".to_string(), "file:///project/lib.bib".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_links_for_graphics() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("plot.png"), "").unwrap();
+        let base = Url::from_file_path(dir.path().join("main.tex")).unwrap();
+
+        let text = "\\includegraphics[width=5cm]{plot}\n\\includegraphics{missing}\n\\includegraphics*{logo.pdf}";
+        let found = links(text, &base);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].0, "plot");
+        assert!(found[0].1.ends_with("/plot.png"));
+        assert_eq!(found[1].0, "logo.pdf");
+        assert!(found[1].1.ends_with("/logo.pdf"));
+    }
+}
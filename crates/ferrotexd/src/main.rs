@@ -10,7 +10,7 @@ async fn main() {
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, socket) = LspService::new(|client| Backend {
+    let (service, socket) = Backend::custom_methods(LspService::build(|client| Backend {
         client,
         documents: Arc::new(DashMap::new()),
         workspace: Arc::new(Workspace::new()),
@@ -18,7 +18,15 @@ async fn main() {
         syntax_diagnostics: Arc::new(DashMap::new()),
         package_manager: Arc::new(Mutex::new(ferrotex_core::package_manager::PackageManager::new())),
         package_index: Arc::new(Mutex::new(None)),
-    });
+        settings: Arc::new(Mutex::new(ferrotexd::settings::Settings::default())),
+        parse_cache: Arc::new(ferrotexd::parse_cache::ParseCache::new()),
+        validations: Arc::new(DashMap::new()),
+        grammar: Arc::new(ferrotexd::diagnostics::grammar::GrammarChecker::new()),
+        build_diagnostics: Arc::new(DashMap::new()),
+        builds: Arc::new(DashMap::new()),
+        inverse_search: Arc::new(tokio::sync::Mutex::new(None)),
+    }))
+    .finish();
 
     Server::new(stdin, stdout, socket).serve(service).await;
 }
@@ -0,0 +1,119 @@
+use dashmap::DashMap;
+use ferrotex_syntax::parser::ParseResult;
+use ferrotex_syntax::SyntaxNode;
+use crate::position::{LineIndex, PositionEncoding};
+use std::sync::{Arc, Mutex};
+use tower_lsp::lsp_types::Url;
+
+/// A parsed document together with its line index.
+#[derive(Debug)]
+pub struct ParsedDocument {
+    /// The document version this tree was built from.
+    pub version: i32,
+    pub parse: ParseResult,
+    pub line_index: LineIndex,
+}
+
+impl ParsedDocument {
+    /// Returns the root of the syntax tree.
+    ///
+    /// Syntax nodes are not `Send`; drop the returned node before awaiting.
+    pub fn syntax(&self) -> SyntaxNode {
+        self.parse.syntax()
+    }
+}
+
+/// Parse trees of open documents, reused across requests until the document changes.
+///
+/// Writers must store the new text before calling [`ParseCache::set_version`]: a reader
+/// that sees the new version is then guaranteed to read the new text, and a tree built
+/// from older text is stored under the older version, where it is never returned again.
+#[derive(Debug, Default)]
+pub struct ParseCache {
+    versions: DashMap<Url, i32>,
+    entries: DashMap<Url, Arc<ParsedDocument>>,
+    encoding: Mutex<PositionEncoding>,
+}
+
+impl ParseCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The position encoding line indices are built with.
+    pub fn encoding(&self) -> PositionEncoding {
+        *self.encoding.lock().unwrap()
+    }
+
+    /// Sets the negotiated position encoding and drops trees indexed with another one.
+    pub fn set_encoding(&self, encoding: PositionEncoding) {
+        *self.encoding.lock().unwrap() = encoding;
+        self.entries.retain(|_, entry| entry.line_index.encoding() == encoding);
+    }
+
+    /// Records the current version of a document and drops its stale tree.
+    pub fn set_version(&self, uri: &Url, version: i32) {
+        self.versions.insert(uri.clone(), version);
+        self.entries.remove_if(uri, |_, entry| entry.version != version);
+    }
+
+    /// The current version of `uri`; 0 until one is recorded.
+    pub fn version(&self, uri: &Url) -> i32 {
+        self.versions.get(uri).map_or(0, |v| *v)
+    }
+
+    /// Forgets a document, e.g. when it is closed.
+    pub fn remove(&self, uri: &Url) {
+        self.versions.remove(uri);
+        self.entries.remove(uri);
+    }
+
+    /// Returns the tree for the current text of `uri`, parsing it on a cache miss.
+    ///
+    /// Returns `None` if the document is not open.
+    pub fn get(&self, uri: &Url, documents: &DashMap<Url, String>) -> Option<Arc<ParsedDocument>> {
+        let version = self.version(uri);
+        if let Some(entry) = self.entries.get(uri).filter(|entry| entry.version == version) {
+            return Some(entry.clone());
+        }
+
+        let parsed = {
+            let text = documents.get(uri)?;
+            Arc::new(ParsedDocument {
+                version,
+                parse: ferrotex_syntax::parse(&text),
+                line_index: LineIndex::with_encoding(&text, self.encoding()),
+            })
+        };
+        self.entries.insert(uri.clone(), parsed.clone());
+        Some(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cache_reuses_until_version_changes() {
+        let cache = ParseCache::new();
+        let documents = DashMap::new();
+        let uri = Url::parse("file:///main.tex").unwrap();
+
+        documents.insert(uri.clone(), r"\section{A}".to_string());
+        cache.set_version(&uri, 1);
+        let first = cache.get(&uri, &documents).unwrap();
+        let again = cache.get(&uri, &documents).unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+
+        documents.insert(uri.clone(), r"\section{B}".to_string());
+        cache.set_version(&uri, 2);
+        let second = cache.get(&uri, &documents).unwrap();
+        assert_eq!(second.version, 2);
+        assert_eq!(second.syntax().to_string(), r"\section{B}");
+
+        cache.remove(&uri);
+        documents.remove(&uri);
+        assert!(cache.get(&uri, &documents).is_none());
+    }
+}
@@ -0,0 +1,171 @@
+//! Conversion between byte offsets and LSP positions.
+//!
+//! The syntax tree and the workspace index work with byte offsets, while an LSP
+//! `Position` counts columns in the encoding negotiated at `initialize`
+//! (UTF-16 unless the client offers something else). Every conversion goes
+//! through [`LineIndex`], which knows that encoding.
+
+use ferrotex_syntax::{TextRange, TextSize};
+use line_index::{LineCol, WideEncoding, WideLineCol};
+use tower_lsp::lsp_types::{ClientCapabilities, Position, PositionEncodingKind, Range};
+
+/// The unit of `Position::character`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PositionEncoding {
+    Utf8,
+    /// The LSP default, used when the client does not advertise `positionEncodings`.
+    #[default]
+    Utf16,
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// Picks the encoding for a session from the client's `general.positionEncodings`.
+    ///
+    /// UTF-8 is preferred because it needs no conversion, then UTF-32; UTF-16 is the fallback
+    /// every client must support.
+    pub fn negotiate(capabilities: &ClientCapabilities) -> Self {
+        let offered = capabilities
+            .general
+            .as_ref()
+            .and_then(|general| general.position_encodings.as_deref())
+            .unwrap_or_default();
+        if offered.contains(&PositionEncodingKind::UTF8) {
+            PositionEncoding::Utf8
+        } else if offered.contains(&PositionEncodingKind::UTF32) {
+            PositionEncoding::Utf32
+        } else {
+            PositionEncoding::Utf16
+        }
+    }
+
+    /// The value announced in `ServerCapabilities::position_encoding`.
+    pub fn kind(self) -> PositionEncodingKind {
+        match self {
+            PositionEncoding::Utf8 => PositionEncodingKind::UTF8,
+            PositionEncoding::Utf16 => PositionEncodingKind::UTF16,
+            PositionEncoding::Utf32 => PositionEncodingKind::UTF32,
+        }
+    }
+
+    fn wide(self) -> Option<WideEncoding> {
+        match self {
+            PositionEncoding::Utf8 => None,
+            PositionEncoding::Utf16 => Some(WideEncoding::Utf16),
+            PositionEncoding::Utf32 => Some(WideEncoding::Utf32),
+        }
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Line indices built on this thread, so tests can check that requests reuse the cached one.
+    pub(crate) static CONSTRUCTED: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Line index of a document that converts offsets to positions in the negotiated encoding.
+#[derive(Debug)]
+pub struct LineIndex {
+    index: line_index::LineIndex,
+    encoding: PositionEncoding,
+}
+
+impl LineIndex {
+    /// Builds an index that reports UTF-16 positions.
+    pub fn new(text: &str) -> Self {
+        Self::with_encoding(text, PositionEncoding::default())
+    }
+
+    pub fn with_encoding(text: &str, encoding: PositionEncoding) -> Self {
+        #[cfg(test)]
+        CONSTRUCTED.with(|count| count.set(count.get() + 1));
+        Self { index: line_index::LineIndex::new(text), encoding }
+    }
+
+    pub fn encoding(&self) -> PositionEncoding {
+        self.encoding
+    }
+
+    /// Returns the line and byte column of `offset`.
+    pub fn line_col(&self, offset: TextSize) -> LineCol {
+        self.index.line_col(offset)
+    }
+
+    /// Returns the byte range of `line`, including its line break.
+    pub fn line(&self, line: u32) -> Option<TextRange> {
+        self.index.line(line)
+    }
+
+    /// Converts a byte offset into an LSP position.
+    pub fn position(&self, offset: TextSize) -> Position {
+        let line_col = self.index.line_col(offset);
+        self.line_position(line_col.line, line_col.col)
+    }
+
+    /// Converts a byte column on `line` into an LSP position.
+    pub fn line_position(&self, line: u32, col: u32) -> Position {
+        let line_col = LineCol { line, col };
+        let col = match self.encoding.wide() {
+            Some(encoding) => self.index.to_wide(encoding, line_col).map_or(col, |wide| wide.col),
+            None => col,
+        };
+        Position { line, character: col }
+    }
+
+    /// Converts a byte range into an LSP range.
+    pub fn range(&self, range: TextRange) -> Range {
+        Range { start: self.position(range.start()), end: self.position(range.end()) }
+    }
+
+    /// Converts an LSP position into a byte offset, or `None` if it lies outside the document.
+    pub fn offset(&self, position: Position) -> Option<TextSize> {
+        let line_col = match self.encoding.wide() {
+            Some(encoding) => self
+                .index
+                .to_utf8(encoding, WideLineCol { line: position.line, col: position.character })?,
+            None => LineCol { line: position.line, col: position.character },
+        };
+        self.index.offset(line_col)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::GeneralClientCapabilities;
+
+    #[test]
+    fn test_positions_per_encoding() {
+        // 'é' is 2 bytes / 1 UTF-16 unit, '𝔸' is 4 bytes / 2 UTF-16 units.
+        let text = "x\nCaf\u{e9} \u{1d538} \\ref{a}";
+        let offset = TextSize::from(text.find("\\ref").unwrap() as u32);
+
+        let cases = [(PositionEncoding::Utf8, 11), (PositionEncoding::Utf16, 8), (PositionEncoding::Utf32, 7)];
+        for (encoding, character) in cases {
+            let index = LineIndex::with_encoding(text, encoding);
+            let position = index.position(offset);
+            assert_eq!(position, Position { line: 1, character }, "{:?}", encoding);
+            assert_eq!(index.offset(position), Some(offset), "{:?}", encoding);
+        }
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let with = |encodings: Vec<PositionEncodingKind>| ClientCapabilities {
+            general: Some(GeneralClientCapabilities {
+                position_encodings: Some(encodings),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(PositionEncoding::negotiate(&ClientCapabilities::default()), PositionEncoding::Utf16);
+        assert_eq!(
+            PositionEncoding::negotiate(&with(vec![PositionEncodingKind::UTF16, PositionEncodingKind::UTF8])),
+            PositionEncoding::Utf8
+        );
+        assert_eq!(
+            PositionEncoding::negotiate(&with(vec![PositionEncodingKind::UTF32, PositionEncodingKind::UTF16])),
+            PositionEncoding::Utf32
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! FerroTeX extensions to the Language Server Protocol.
+//!
+//! Clients opt into these by sending the custom methods defined here; standard
+//! clients never see them.
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::notification::Notification;
+use tower_lsp::lsp_types::request::Request;
+use tower_lsp::lsp_types::{TextDocumentIdentifier, Url};
+
+/// `ferrotex/includeTree`: the `\input`/`\include` tree of the project a document belongs to.
+#[derive(Debug)]
+pub enum IncludeTree {}
+
+impl Request for IncludeTree {
+    type Params = IncludeTreeParams;
+    type Result = IncludeTreeNode;
+    const METHOD: &'static str = "ferrotex/includeTree";
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncludeTreeParams {
+    /// Any document of the project; the tree starts at its root document.
+    pub text_document: TextDocumentIdentifier,
+}
+
+/// A file in the include tree.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncludeTreeNode {
+    pub uri: Url,
+    /// The path as written in the including file; `None` for the root document.
+    pub path: Option<String>,
+    /// Whether the file is part of the workspace index.
+    pub exists: bool,
+    /// The file is already one of its own ancestors; its children are omitted.
+    pub cycle: bool,
+    pub children: Vec<IncludeTreeNode>,
+}
+
+/// `$/ferrotex/status`: what the server is busy with, for a client status-bar item.
+#[derive(Debug)]
+pub enum Status {}
+
+impl Notification for Status {
+    type Params = ServerStatus;
+    const METHOD: &'static str = "$/ferrotex/status";
+}
+
+/// Server state sent with [`Status`], tagged by `state`, e.g.
+/// `{ "state": "indexing", "done": 50, "total": 120 }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub enum ServerStatus {
+    /// Nothing is running.
+    Idle,
+    /// The workspace is being indexed; `done` of `total` files are finished.
+    Indexing { done: usize, total: usize },
+    /// The root document `uri` is being built. `pass` counts the engine passes started
+    /// so far and `step` names the current one (e.g. `pdflatex (run 2)`).
+    Building { uri: Url, pass: u32, step: Option<String> },
+    /// The last build of `uri` failed.
+    Failed { uri: Url, errors: usize, message: String },
+}
+
+/// `ferrotex/buildLog`: log events parsed from the engine output while a build runs.
+///
+/// Sent in batches as output arrives, so a client can fill a live output or problem panel
+/// instead of waiting for the diagnostics published at the end of the build.
+#[derive(Debug)]
+pub enum BuildLog {}
+
+impl Notification for BuildLog {
+    type Params = BuildLogParams;
+    const METHOD: &'static str = "ferrotex/buildLog";
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildLogParams {
+    /// The root document being built.
+    pub uri: Url,
+    /// The engine pass the events belong to, counted as in [`ServerStatus::Building`].
+    pub pass: u32,
+    /// Events in log order. Spans are byte offsets into the output of the whole build.
+    pub events: Vec<ferrotex_log::ir::LogEvent>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_status_serialization() {
+        let indexing = ServerStatus::Indexing { done: 50, total: 120 };
+        assert_eq!(serde_json::to_value(&indexing).unwrap(), json!({ "state": "indexing", "done": 50, "total": 120 }));
+        assert_eq!(serde_json::to_value(ServerStatus::Idle).unwrap(), json!({ "state": "idle" }));
+
+        let uri = Url::parse("file:///main.tex").unwrap();
+        let building = ServerStatus::Building { uri, pass: 2, step: Some("pdflatex (run 2)".to_string()) };
+        let value = serde_json::to_value(&building).unwrap();
+        assert_eq!(value["state"], "building");
+        assert_eq!(value["step"], "pdflatex (run 2)");
+        assert_eq!(serde_json::from_value::<ServerStatus>(value).unwrap(), building);
+    }
+
+    #[test]
+    fn test_build_log_serialization() {
+        let mut parser = ferrotex_log::LogParser::new();
+        let mut events = parser.update("(./main.tex\n");
+        events.extend(parser.update("! Undefined control sequence.\nl.3 \\foo\n        \n"));
+        let params = BuildLogParams { uri: Url::parse("file:///main.tex").unwrap(), pass: 1, events };
+        let value = serde_json::to_value(&params).unwrap();
+
+        assert_eq!(value["pass"], 1);
+        let events = value["events"].as_array().unwrap();
+        assert_eq!(events[0]["kind"], "FileEnter");
+        assert_eq!(events[0]["data"]["path"], "./main.tex");
+        assert!(events.iter().any(|e| e["kind"] == "ErrorStart" && e["data"]["message"] == "Undefined control sequence."));
+        assert!(events.iter().any(|e| e["kind"] == "ErrorStart" && e["data"]["context"][0] == "l.3 \\foo"));
+        assert_eq!(serde_json::from_value::<BuildLogParams>(value).unwrap(), params);
+    }
+}
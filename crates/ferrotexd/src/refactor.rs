@@ -0,0 +1,158 @@
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Command, CreateFile, CreateFileOptions, DocumentChangeOperation,
+    DocumentChanges, OneOf, OptionalVersionedTextDocumentIdentifier, Position, Range, ResourceOp, TextDocumentEdit,
+    TextEdit, Url, WorkspaceEdit,
+};
+
+/// Command run by the "Extract to file" code action; arguments are `[uri, range]`.
+pub const EXTRACT_TO_FILE_COMMAND: &str = "ferrotex.extractToFile";
+
+/// Name of the extracted file when the selection has no heading to name it after.
+const DEFAULT_STEM: &str = "extracted";
+
+lazy_static::lazy_static! {
+    /// A sectioning command with its title as the first capture.
+    static ref HEADING: Regex =
+        Regex::new(r"\\(?:part|chapter|section|subsection|subsubsection)\*?\s*(?:\[[^\]]*\])?\{([^}]*)\}").unwrap();
+}
+
+/// Offers "Extract to file" for a non-empty selection.
+///
+/// The edit is computed when the command runs, so the file name is checked against
+/// the disk at that point rather than when the menu was opened.
+pub fn extract_action(uri: &Url, range: Range) -> Option<CodeActionOrCommand> {
+    if range.start == range.end {
+        return None;
+    }
+    let title = "Extract to file".to_string();
+    Some(CodeActionOrCommand::CodeAction(CodeAction {
+        title: title.clone(),
+        kind: Some(CodeActionKind::REFACTOR_EXTRACT),
+        command: Some(Command {
+            title,
+            command: EXTRACT_TO_FILE_COMMAND.to_string(),
+            arguments: Some(vec![serde_json::json!(uri), serde_json::json!(range)]),
+        }),
+        ..Default::default()
+    }))
+}
+
+/// Derives a file name from the first sectioning command in `selected`, e.g.
+/// `\section{Related Work}` gives `related-work`.
+pub fn file_stem(selected: &str) -> String {
+    let Some(title) = HEADING.captures(selected).map(|cap| cap[1].to_lowercase()) else {
+        return DEFAULT_STEM.to_string();
+    };
+    let slug = title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() { DEFAULT_STEM.to_string() } else { slug }
+}
+
+/// Returns `<dir>/<stem>.tex`, or `<dir>/<stem>-<n>.tex` with the first `n` not taken.
+pub fn unique_target(dir: &Path, stem: &str, exists: impl Fn(&Path) -> bool) -> PathBuf {
+    let mut candidate = dir.join(format!("{}.tex", stem));
+    let mut n = 2;
+    while exists(&candidate) {
+        candidate = dir.join(format!("{}-{}.tex", stem, n));
+        n += 1;
+    }
+    candidate
+}
+
+/// The argument of `\input` that loads `target` from a root document in `root_dir`.
+///
+/// TeX resolves `\input` against the directory of the root document and adds `.tex`
+/// itself; files outside that directory are referenced by absolute path.
+pub fn input_path(root_dir: &Path, target: &Path) -> String {
+    let target = target.with_extension("");
+    match target.strip_prefix(root_dir) {
+        // TeX wants forward slashes on every platform.
+        Ok(relative) => relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        Err(_) => target.to_string_lossy().into_owned(),
+    }
+}
+
+/// Builds the workspace edit that creates `target` holding `content` and replaces
+/// `range` of the document `uri` (at `version`) with `\input{<input>}`.
+pub fn extract_edit(uri: &Url, version: Option<i32>, range: Range, target: &Url, content: &str, input: &str) -> WorkspaceEdit {
+    // A selection of whole lines keeps its line break after the `\input`.
+    let newline = if content.ends_with('\n') { "\n" } else { "" };
+    let mut content = content.to_string();
+    if newline.is_empty() {
+        content.push('\n');
+    }
+    let start = Position { line: 0, character: 0 };
+    WorkspaceEdit {
+        document_changes: Some(DocumentChanges::Operations(vec![
+            DocumentChangeOperation::Op(ResourceOp::Create(CreateFile {
+                uri: target.clone(),
+                options: Some(CreateFileOptions { overwrite: Some(false), ignore_if_exists: Some(false) }),
+                annotation_id: None,
+            })),
+            DocumentChangeOperation::Edit(TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier { uri: target.clone(), version: None },
+                edits: vec![OneOf::Left(TextEdit { range: Range { start, end: start }, new_text: content })],
+            }),
+            DocumentChangeOperation::Edit(TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier { uri: uri.clone(), version },
+                edits: vec![OneOf::Left(TextEdit { range, new_text: format!("\\input{{{}}}{}", input, newline) })],
+            }),
+        ])),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_stem() {
+        assert_eq!(file_stem("\\section{Related Work}\nSome text."), "related-work");
+        assert_eq!(file_stem("\\chapter*[Short]{Über uns!}"), "über-uns");
+        assert_eq!(file_stem("Just text."), "extracted");
+        assert_eq!(file_stem("\\section{$x$}"), "x");
+    }
+
+    #[test]
+    fn test_unique_target_and_input_path() {
+        let dir = Path::new("/project/chapters");
+        let taken = [dir.join("intro.tex"), dir.join("intro-2.tex")];
+        let target = unique_target(dir, "intro", |p| taken.iter().any(|t| t == p));
+        assert_eq!(target, dir.join("intro-3.tex"));
+
+        assert_eq!(input_path(Path::new("/project"), &target), "chapters/intro-3");
+        assert_eq!(input_path(Path::new("/elsewhere"), &target), "/project/chapters/intro-3");
+    }
+
+    #[test]
+    fn test_extract_edit() {
+        let uri = Url::parse("file:///project/main.tex").unwrap();
+        let target = Url::parse("file:///project/intro.tex").unwrap();
+        let range = Range { start: Position { line: 2, character: 0 }, end: Position { line: 4, character: 0 } };
+        let edit = extract_edit(&uri, Some(3), range, &target, "\\section{Intro}\nText.", "intro");
+
+        let Some(DocumentChanges::Operations(ops)) = edit.document_changes else {
+            panic!("expected resource operations");
+        };
+        assert_eq!(ops.len(), 3);
+        assert!(matches!(&ops[0], DocumentChangeOperation::Op(ResourceOp::Create(create)) if create.uri == target));
+        let DocumentChangeOperation::Edit(fill) = &ops[1] else { panic!("expected text edit") };
+        assert_eq!(fill.edits, vec![OneOf::Left(TextEdit {
+            range: Range::default(),
+            new_text: "\\section{Intro}\nText.\n".to_string(),
+        })]);
+        let DocumentChangeOperation::Edit(replace) = &ops[2] else { panic!("expected text edit") };
+        assert_eq!(replace.text_document.version, Some(3));
+        assert_eq!(replace.edits, vec![OneOf::Left(TextEdit { range, new_text: "\\input{intro}".to_string() })]);
+    }
+}
@@ -0,0 +1,282 @@
+use ferrotex_syntax::{SyntaxKind, SyntaxNode, SyntaxToken, TextSize};
+use crate::position::LineIndex;
+use rowan::NodeOrToken;
+use tower_lsp::lsp_types::{SemanticToken, SemanticTokenModifier, SemanticTokenType};
+
+pub const SEMANTIC_TOKEN_TYPES: &[SemanticTokenType] = &[
+    SemanticTokenType::MACRO,     // 0: Commands (\foo)
+    SemanticTokenType::KEYWORD,   // 1: Environment markers (\begin, \end)
+    SemanticTokenType::STRING,    // 2: Paths, section titles
+    SemanticTokenType::COMMENT,   // 3: Comments
+    SemanticTokenType::PARAMETER, // 4: Optional arguments
+    SemanticTokenType::VARIABLE,  // 5: Labels, references, citation keys
+    SemanticTokenType::TYPE,      // 6: Environment names
+];
+
+pub const SEMANTIC_TOKEN_MODIFIERS: &[SemanticTokenModifier] = &[
+    SemanticTokenModifier::DECLARATION, // 1 << 0: Label definitions
+    SemanticTokenModifier::DEFINITION,  // 1 << 1: Section titles
+    SemanticTokenModifier::READONLY,    // 1 << 2: Citation keys
+];
+
+const MACRO: u32 = 0;
+const KEYWORD: u32 = 1;
+const STRING: u32 = 2;
+const COMMENT: u32 = 3;
+const PARAMETER: u32 = 4;
+const VARIABLE: u32 = 5;
+const TYPE: u32 = 6;
+
+const DECLARATION: u32 = 1 << 0;
+const DEFINITION: u32 = 1 << 1;
+const READONLY: u32 = 1 << 2;
+
+/// Sectioning commands whose argument is a title. `\section` itself has its own node.
+const SECTION_COMMANDS: &[&str] = &[
+    "\\part", "\\chapter", "\\subsection", "\\subsubsection", "\\paragraph", "\\subparagraph",
+];
+
+/// Reference commands beyond `\ref`, which has its own node.
+const REFERENCE_COMMANDS: &[&str] = &["\\eqref", "\\pageref", "\\autoref", "\\cref", "\\Cref", "\\nameref"];
+
+/// Citation commands beyond `\cite`, which has its own node.
+const CITATION_COMMANDS: &[&str] = &[
+    "\\citep", "\\citet", "\\parencite", "\\textcite", "\\autocite", "\\footcite", "\\nocite",
+];
+
+/// What the content of a `{...}` argument denotes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArgumentRole {
+    EnvironmentName,
+    Label,
+    Reference,
+    Citation,
+    Title,
+    Path,
+}
+
+impl ArgumentRole {
+    fn classify(self) -> (u32, u32) {
+        match self {
+            ArgumentRole::EnvironmentName => (TYPE, 0),
+            ArgumentRole::Label => (VARIABLE, DECLARATION),
+            ArgumentRole::Reference => (VARIABLE, 0),
+            ArgumentRole::Citation => (VARIABLE, READONLY),
+            ArgumentRole::Title => (STRING, DEFINITION),
+            ArgumentRole::Path => (STRING, 0),
+        }
+    }
+}
+
+/// Computes the full semantic token list for a document.
+///
+/// Tokens are classified individually: commands, comments and the text inside arguments
+/// whose role is known (environment names, labels, references, citation keys, section
+/// titles, paths) and inside `[...]` options. Plain prose is left to the client grammar.
+pub fn compute_semantic_tokens(root: &SyntaxNode, line_index: &LineIndex) -> Vec<SemanticToken> {
+    // (line, start column, length, token type, modifiers)
+    let mut spans = Vec::new();
+    for token in root.descendants_with_tokens().filter_map(|e| e.into_token()) {
+        let Some((token_type, modifiers)) = classify(&token) else {
+            continue;
+        };
+
+        // Clients cannot render multi-line tokens, so split at line breaks.
+        let mut offset = token.text_range().start();
+        for segment in token.text().split('\n') {
+            let content = segment.trim_end_matches('\r');
+            if !content.is_empty() {
+                let start = line_index.position(offset);
+                let end = line_index.position(offset + TextSize::of(content));
+                spans.push((start.line, start.character, end.character - start.character, token_type, modifiers));
+            }
+            offset += TextSize::of(segment) + TextSize::from(1);
+        }
+    }
+
+    // Emit in document order and drop segments that overlap an earlier one,
+    // which clients do not support.
+    spans.sort_by_key(|&(line, col, ..)| (line, col));
+
+    let mut tokens = Vec::new();
+    let mut last_line = 0;
+    let mut last_char = 0;
+    let mut last_end = None;
+    for (line, col, length, token_type, modifiers) in spans {
+        if last_end.is_some_and(|(end_line, end_col)| end_line == line && col < end_col) {
+            continue;
+        }
+
+        let delta_line = line - last_line;
+        let delta_char = if delta_line == 0 {
+            col - last_char
+        } else {
+            col
+        };
+
+        tokens.push(SemanticToken {
+            delta_line,
+            delta_start: delta_char,
+            length,
+            token_type,
+            token_modifiers_bitset: modifiers,
+        });
+
+        last_line = line;
+        last_char = col;
+        last_end = Some((line, col + length));
+    }
+
+    tokens
+}
+
+/// Returns the token type and modifier bitset for a single token.
+fn classify(token: &SyntaxToken) -> Option<(u32, u32)> {
+    match token.kind() {
+        SyntaxKind::Comment => Some((COMMENT, 0)),
+        SyntaxKind::Command if matches!(token.text(), "\\begin" | "\\end") => Some((KEYWORD, 0)),
+        SyntaxKind::Command => Some((MACRO, 0)),
+        SyntaxKind::Text => {
+            let group = token.parent().filter(|p| p.kind() == SyntaxKind::Group);
+            if let Some(role) = group.as_ref().and_then(argument_role) {
+                Some(role.classify())
+            } else if in_option(token) {
+                Some((PARAMETER, 0))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Determines the role of a `{...}` argument from its parent node or the command before it.
+fn argument_role(group: &SyntaxNode) -> Option<ArgumentRole> {
+    let parent = group.parent()?;
+    match parent.kind() {
+        SyntaxKind::Section => return Some(ArgumentRole::Title),
+        SyntaxKind::LabelDefinition => return Some(ArgumentRole::Label),
+        SyntaxKind::LabelReference => return Some(ArgumentRole::Reference),
+        SyntaxKind::Citation => return Some(ArgumentRole::Citation),
+        SyntaxKind::Include | SyntaxKind::Bibliography => return Some(ArgumentRole::Path),
+        _ => {}
+    }
+
+    // The command this group is an argument of, skipping a `*` and `[...]` options.
+    let mut prev = group.prev_sibling_or_token();
+    let mut in_brackets = false;
+    while let Some(element) = prev {
+        match &element {
+            NodeOrToken::Token(t) if t.kind() == SyntaxKind::RBracket && !in_brackets => in_brackets = true,
+            NodeOrToken::Token(t) if t.kind() == SyntaxKind::LBracket && in_brackets => in_brackets = false,
+            _ if in_brackets => {}
+            NodeOrToken::Token(t) if t.kind() == SyntaxKind::Text && t.text() == "*" => {}
+            NodeOrToken::Token(t) if t.kind() == SyntaxKind::Command => {
+                let name = t.text();
+                return match name {
+                    "\\begin" | "\\end" => Some(ArgumentRole::EnvironmentName),
+                    _ if SECTION_COMMANDS.contains(&name) => Some(ArgumentRole::Title),
+                    _ if REFERENCE_COMMANDS.contains(&name) => Some(ArgumentRole::Reference),
+                    _ if CITATION_COMMANDS.contains(&name) => Some(ArgumentRole::Citation),
+                    _ => None,
+                };
+            }
+            _ => return None,
+        }
+        prev = element.prev_sibling_or_token();
+    }
+    None
+}
+
+/// Returns `true` if the token sits in a `[...]` option list directly following a command.
+fn in_option(token: &SyntaxToken) -> bool {
+    let mut prev = token.prev_sibling_or_token();
+    while let Some(element) = prev {
+        match element.kind() {
+            SyntaxKind::RBracket => return false,
+            SyntaxKind::LBracket => {
+                return element
+                    .prev_sibling_or_token()
+                    .is_some_and(|p| matches!(p.kind(), SyntaxKind::Command | SyntaxKind::Group));
+            }
+            _ => {}
+        }
+        prev = element.prev_sibling_or_token();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes tokens into (line, column, text, type, modifiers).
+    fn decode(text: &str) -> Vec<(u32, u32, String, u32, u32)> {
+        let lines: Vec<&str> = text.lines().collect();
+        let mut line = 0;
+        let mut col = 0;
+        let root = ferrotex_syntax::parse(text).syntax();
+        compute_semantic_tokens(&root, &LineIndex::new(text))
+            .into_iter()
+            .map(|t| {
+                if t.delta_line > 0 {
+                    line += t.delta_line;
+                    col = t.delta_start;
+                } else {
+                    col += t.delta_start;
+                }
+                let content = lines[line as usize][col as usize..(col + t.length) as usize].to_string();
+                (line, col, content, t.token_type, t.token_modifiers_bitset)
+            })
+            .collect()
+    }
+
+    fn classified(text: &str, content: &str) -> Option<(u32, u32)> {
+        decode(text)
+            .into_iter()
+            .find(|(_, _, c, _, _)| c == content)
+            .map(|(_, _, _, ty, modifiers)| (ty, modifiers))
+    }
+
+    #[test]
+    fn test_semantic_tokens_environment() {
+        let text = "\\begin{itemize}\n  \\item A\n\\end{itemize}";
+        let tokens = decode(text);
+        assert_eq!(tokens[0], (0, 0, "\\begin".to_string(), KEYWORD, 0));
+        assert_eq!(tokens[1], (0, 7, "itemize".to_string(), TYPE, 0));
+        assert_eq!(tokens[2], (1, 2, "\\item".to_string(), MACRO, 0));
+        assert_eq!(tokens[3], (2, 0, "\\end".to_string(), KEYWORD, 0));
+        assert_eq!(tokens[4], (2, 5, "itemize".to_string(), TYPE, 0));
+        // Prose is not classified.
+        assert!(tokens.iter().all(|t| t.2 != "A"));
+    }
+
+    #[test]
+    fn test_semantic_tokens_labels_and_references() {
+        let text = "\\label{sec:intro} see \\ref{sec:intro} and \\eqref{eq:1}";
+        assert_eq!(classified(text, "sec:intro"), Some((VARIABLE, DECLARATION)));
+        let refs: Vec<_> = decode(text).into_iter().filter(|t| t.2 == "sec:intro").collect();
+        assert_eq!(refs[1].3, VARIABLE);
+        assert_eq!(refs[1].4, 0);
+        assert_eq!(classified(text, "eq:1"), Some((VARIABLE, 0)));
+    }
+
+    #[test]
+    fn test_semantic_tokens_citations_titles_and_options() {
+        let text = "\\section{Intro}\n\\subsection*{More}\n\\cite[p.~5]{knuth}\n\\citep{lamport}\n\\includegraphics[width=5cm]{fig}";
+        assert_eq!(classified(text, "Intro"), Some((STRING, DEFINITION)));
+        assert_eq!(classified(text, "More"), Some((STRING, DEFINITION)));
+        assert_eq!(classified(text, "knuth"), Some((VARIABLE, READONLY)));
+        assert_eq!(classified(text, "lamport"), Some((VARIABLE, READONLY)));
+        assert_eq!(classified(text, "p.~5"), Some((PARAMETER, 0)));
+        assert_eq!(classified(text, "width=5cm"), Some((PARAMETER, 0)));
+        assert_eq!(classified(text, "fig"), None);
+    }
+
+    #[test]
+    fn test_semantic_tokens_comments_and_paths() {
+        let text = "\\input{chapters/one} % note";
+        assert_eq!(classified(text, "chapters/one"), Some((STRING, 0)));
+        assert_eq!(classified(text, "% note"), Some((COMMENT, 0)));
+    }
+}
@@ -0,0 +1,364 @@
+use ferrotex_build::{BuildProfile, Container, ContainerRuntime};
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostics::lint::LintConfig;
+
+/// Server configuration, received through `initializationOptions` and
+/// `workspace/didChangeConfiguration`.
+///
+/// Field names follow the `ferrotex.*` settings contributed by the VS Code extension.
+/// Missing fields fall back to their defaults, so clients may send partial objects.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub format: FormatSettings,
+    pub lint: LintSettings,
+    pub build: BuildSettings,
+    pub diagnostics: DiagnosticsSettings,
+    pub spellcheck: SpellcheckSettings,
+    pub grammar: GrammarSettings,
+    pub outline: OutlineSettings,
+    pub inverse_search: InverseSearchSettings,
+    /// Path of the root document, relative to the workspace root. Overrides `%!TEX root`.
+    pub root_document: Option<String>,
+}
+
+/// Formatter options.
+///
+/// Tab width, tabs vs. spaces and whitespace trimming come from the client's
+/// `FormattingOptions`; these settings only cover what LSP has no field for.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FormatSettings {
+    /// Number of spaces per indentation level. Overrides the client's `tabSize` when set.
+    pub indent_size: Option<usize>,
+    /// Extra indentation levels for lines inside a brace group spanning several lines.
+    pub continuation_indent: usize,
+    /// Break prose lines longer than this column. Wrapping is off when unset.
+    pub wrap_column: Option<usize>,
+    /// Sort `.bib` entries by citation key when formatting.
+    pub bib_sort_entries: bool,
+}
+
+/// Lint and deprecation diagnostics toggles.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LintSettings {
+    /// Master switch for style lints.
+    pub enabled: bool,
+    /// IDs of lint rules to disable (see [`crate::diagnostics::lint::RULES`]).
+    pub disabled_rules: Vec<String>,
+    /// Report LaTeX 2.09 font switches and other deprecated commands.
+    pub deprecated_commands: bool,
+    /// Report obsolete packages.
+    pub obsolete_packages: bool,
+    /// Report plain TeX `$$ ... $$` display math.
+    pub display_math_delimiters: bool,
+}
+
+impl Default for LintSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            disabled_rules: Vec::new(),
+            deprecated_commands: true,
+            obsolete_packages: true,
+            display_math_delimiters: true,
+        }
+    }
+}
+
+impl LintSettings {
+    /// Converts the settings into the configuration consumed by the lint engine.
+    pub fn lint_config(&self) -> LintConfig {
+        LintConfig {
+            disabled: self.disabled_rules.iter().cloned().collect(),
+        }
+    }
+
+    /// Returns `true` if a deprecation marker recorded by the workspace scanner should be reported.
+    pub fn reports_deprecation(&self, marker: &str) -> bool {
+        if marker.starts_with("package:") {
+            self.obsolete_packages
+        } else if marker == "displaymath" {
+            self.display_math_delimiters
+        } else {
+            self.deprecated_commands
+        }
+    }
+}
+
+/// The engine used by `ferrotex.internal.build`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildEngineKind {
+    Latexmk,
+    Tectonic,
+    /// Pick the first engine found on the system. Unknown engine names map here.
+    #[default]
+    #[serde(other)]
+    Auto,
+}
+
+/// Build options.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BuildSettings {
+    pub engine: BuildEngineKind,
+    /// Path of the `tectonic` executable. Looked up on `PATH` when unset.
+    pub tectonic_path: Option<String>,
+    /// Extensions (without the leading dot) removed by `ferrotex.clean`.
+    pub clean_extensions: Vec<String>,
+    /// `draft` for quick checks, `final` for the full build.
+    pub profile: BuildProfile,
+    pub container: ContainerSettings,
+    /// Seconds after which a running build is stopped. Builds may run indefinitely when unset.
+    pub timeout: Option<u64>,
+    /// Writes SyncTeX data whatever the profile, so that forward and inverse search
+    /// follow the latest build.
+    pub synctex: bool,
+}
+
+impl Default for BuildSettings {
+    fn default() -> Self {
+        Self {
+            engine: BuildEngineKind::default(),
+            tectonic_path: None,
+            clean_extensions: crate::build::clean::DEFAULT_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+            profile: BuildProfile::default(),
+            container: ContainerSettings::default(),
+            timeout: None,
+            synctex: true,
+        }
+    }
+}
+
+/// The program running containerized builds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerRuntimeKind {
+    Docker,
+    Podman,
+    /// Build on the host. Unknown runtime names map here.
+    #[default]
+    #[serde(other)]
+    None,
+}
+
+/// Runs latexmk in a container from a pinned TeX image, for builds that do not depend
+/// on the local TeX installation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ContainerSettings {
+    pub runtime: ContainerRuntimeKind,
+    pub image: String,
+}
+
+impl Default for ContainerSettings {
+    fn default() -> Self {
+        Self {
+            runtime: ContainerRuntimeKind::None,
+            image: ferrotex_build::container::DEFAULT_IMAGE.to_string(),
+        }
+    }
+}
+
+impl ContainerSettings {
+    /// The configured container, or `None` to build on the host.
+    pub fn container(&self) -> Option<Container> {
+        let runtime = match self.runtime {
+            ContainerRuntimeKind::None => return None,
+            ContainerRuntimeKind::Docker => ContainerRuntime::Docker,
+            ContainerRuntimeKind::Podman => ContainerRuntime::Podman,
+        };
+        Some(Container::new(runtime, &self.image))
+    }
+}
+
+/// Diagnostics options.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DiagnosticsSettings {
+    /// Milliseconds to wait after the last edit before re-validating a document.
+    pub delay: u64,
+    /// Build warnings that are not reported: rule ids such as `hyperref/token-not-allowed`
+    /// (see [`ferrotex_log::classify`]), or package names such as `hyperref` for all of
+    /// a package's warnings.
+    pub suppressed_warnings: Vec<String>,
+}
+
+/// Spell checking options.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SpellcheckSettings {
+    /// Dictionary language (e.g. `en_US`). A `% !TeX spellcheck` comment takes precedence.
+    pub language: Option<String>,
+}
+
+/// LanguageTool grammar checking options.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GrammarSettings {
+    /// Submit prose to the LanguageTool server. Off by default.
+    pub enabled: bool,
+    /// Base URL of the LanguageTool server.
+    pub url: String,
+    /// LanguageTool language code (e.g. `en-US`). Falls back to the spell checking language.
+    pub language: Option<String>,
+    /// LanguageTool rule ids that are never reported.
+    pub disabled_rules: Vec<String>,
+}
+
+impl Default for GrammarSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: "http://localhost:8081".to_string(),
+            language: None,
+            disabled_rules: Vec::new(),
+        }
+    }
+}
+
+/// Outline and folding options.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OutlineSettings {
+    /// Sectioning commands defined by classes or packages, in addition to `\part` … `\subparagraph`.
+    pub structural_commands: Vec<StructuralCommand>,
+}
+
+/// A command that opens an outline entry, such as `\lecture{date}{title}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StructuralCommand {
+    /// Command name, with or without the leading backslash.
+    pub name: String,
+    /// Depth in the outline, on the scale of `\part` (0) to `\subparagraph` (6).
+    pub level: u8,
+    /// 1-based index of the `{...}` argument holding the title.
+    pub argument: usize,
+}
+
+impl Default for StructuralCommand {
+    fn default() -> Self {
+        Self { name: String::new(), level: 2, argument: 1 }
+    }
+}
+
+impl StructuralCommand {
+    /// Returns `true` if this entry declares the command token `command` (e.g. `\lecture`).
+    pub fn matches(&self, command: &str) -> bool {
+        command.strip_prefix('\\') == Some(self.name.trim_start_matches('\\'))
+    }
+}
+
+/// Listener for inverse search requests from external PDF viewers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InverseSearchSettings {
+    /// Accept `file:line` requests. Off by default.
+    pub enabled: bool,
+    /// TCP port on `127.0.0.1`.
+    pub port: u16,
+    /// Listen on this Unix socket instead of the TCP port.
+    pub socket_path: Option<String>,
+}
+
+impl Default for InverseSearchSettings {
+    fn default() -> Self {
+        Self { enabled: false, port: 28100, socket_path: None }
+    }
+}
+
+impl Settings {
+    /// Parses settings sent by a client.
+    ///
+    /// Accepts both the bare settings object and one nested under a `ferrotex` key,
+    /// which is what VS Code sends for a synchronized configuration section.
+    pub fn from_value(value: serde_json::Value) -> Option<Self> {
+        let value = match value {
+            serde_json::Value::Object(mut map) if map.contains_key("ferrotex") => map.remove("ferrotex")?,
+            serde_json::Value::Null => return None,
+            other => other,
+        };
+        match serde_json::from_value(value) {
+            Ok(settings) => Some(settings),
+            Err(e) => {
+                log::warn!("Ignoring invalid settings: {}", e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_settings_defaults() {
+        let settings = Settings::from_value(json!({})).unwrap();
+        assert_eq!(settings, Settings::default());
+        assert_eq!(settings.format.indent_size, None);
+        assert!(settings.lint.enabled);
+        assert!(settings.build.clean_extensions.iter().any(|e| e == "synctex.gz"));
+    }
+
+    #[test]
+    fn test_settings_nested_section() {
+        let settings = Settings::from_value(json!({
+            "ferrotex": {
+                "format": { "indentSize": 2, "continuationIndent": 1 },
+                "lint": { "disabledRules": ["ellipsis"], "obsoletePackages": false },
+                "build": { "engine": "tectonic", "tectonicPath": "/opt/tectonic", "profile": "draft", "container": { "runtime": "podman" }, "timeout": 600, "synctex": false },
+                "diagnostics": { "delay": 250, "suppressedWarnings": ["hyperref"] },
+                "grammar": { "enabled": true, "language": "de-DE" },
+                "outline": { "structuralCommands": [{ "name": "\\lecture", "level": 1, "argument": 2 }, { "name": "problem" }] },
+                "rootDocument": "main.tex"
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(settings.format.indent_size, Some(2));
+        assert_eq!(settings.format.continuation_indent, 1);
+        assert!(!settings.lint.lint_config().is_enabled("ellipsis"));
+        assert!(!settings.lint.reports_deprecation("package:times"));
+        assert!(settings.lint.reports_deprecation("\\bf"));
+        assert_eq!(settings.build.engine, BuildEngineKind::Tectonic);
+        assert_eq!(settings.build.tectonic_path.as_deref(), Some("/opt/tectonic"));
+        assert_eq!(settings.build.profile, BuildProfile::Draft);
+        let container = settings.build.container.container().unwrap();
+        assert_eq!((container.runtime, container.image.as_str()), (ContainerRuntime::Podman, ferrotex_build::container::DEFAULT_IMAGE));
+        assert_eq!(settings.build.timeout, Some(600));
+        assert!(!settings.build.synctex);
+        assert_eq!(settings.diagnostics.delay, 250);
+        assert_eq!(settings.diagnostics.suppressed_warnings, ["hyperref"]);
+        assert!(settings.grammar.enabled);
+        assert_eq!(settings.grammar.url, "http://localhost:8081");
+        assert_eq!(settings.grammar.language.as_deref(), Some("de-DE"));
+        let [lecture, problem] = settings.outline.structural_commands.as_slice() else {
+            panic!("expected two structural commands");
+        };
+        assert!(lecture.matches("\\lecture") && !lecture.matches("\\lectures"));
+        assert_eq!((lecture.level, lecture.argument), (1, 2));
+        assert!(problem.matches("\\problem"));
+        assert_eq!((problem.level, problem.argument), (2, 1));
+        assert_eq!(settings.root_document.as_deref(), Some("main.tex"));
+        assert!(!settings.inverse_search.enabled);
+        assert_eq!(settings.inverse_search.port, 28100);
+    }
+
+    #[test]
+    fn test_settings_unknown_engine_falls_back_to_auto() {
+        let settings = Settings::from_value(json!({ "build": { "engine": "xelatex" } })).unwrap();
+        assert_eq!(settings.build.engine, BuildEngineKind::Auto);
+    }
+
+    #[test]
+    fn test_settings_invalid() {
+        assert!(Settings::from_value(json!({ "format": { "indentSize": "wide" } })).is_none());
+        assert!(Settings::from_value(serde_json::Value::Null).is_none());
+    }
+}
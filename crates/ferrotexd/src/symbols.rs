@@ -0,0 +1,259 @@
+use crate::settings::StructuralCommand;
+use crate::workspace::extract_label_data;
+use ferrotex_syntax::{SyntaxKind, SyntaxNode, SyntaxToken, TextRange, TextSize};
+use crate::position::LineIndex;
+use rowan::NodeOrToken;
+use tower_lsp::lsp_types::{DocumentSymbol, FoldingRange, FoldingRangeKind, Range, SymbolKind};
+
+/// Sectioning commands by depth; a section owns everything up to the next one of the
+/// same or a lower depth.
+pub(crate) const SECTION_LEVELS: &[(&str, u8)] = &[
+    ("\\part", 0),
+    ("\\chapter", 1),
+    ("\\section", 2),
+    ("\\subsection", 3),
+    ("\\subsubsection", 4),
+    ("\\paragraph", 5),
+    ("\\subparagraph", 6),
+];
+
+/// A symbol whose children are still being collected.
+struct OpenSymbol {
+    symbol: DocumentSymbol,
+    start: TextSize,
+    /// `Some(depth)` for sections, which end where the next section starts.
+    level: Option<u8>,
+    /// End offset of environments, which end with their `\end`.
+    end: Option<TextSize>,
+}
+
+/// Returns the level and 0-based title argument of a sectioning command token.
+///
+/// User-declared commands take precedence, so a class may move `\chapter` to another level.
+fn section_level(command: &SyntaxToken, structural: &[StructuralCommand]) -> Option<(u8, usize)> {
+    if let Some(custom) = structural.iter().find(|custom| custom.matches(command.text())) {
+        return Some((custom.level, custom.argument.saturating_sub(1)));
+    }
+    SECTION_LEVELS.iter().find(|(name, _)| *name == command.text()).map(|(_, level)| (*level, 0))
+}
+
+/// Builds the outline of a document.
+///
+/// Sections own the subsections, environments and labels that follow them; environments
+/// own what they enclose. `selection_range` covers the name of each symbol. `structural`
+/// adds sectioning commands on top of the standard ones.
+pub fn document_symbols(root: &SyntaxNode, line_index: &LineIndex, structural: &[StructuralCommand]) -> Vec<DocumentSymbol> {
+    let mut top_level = Vec::new();
+    let mut stack: Vec<OpenSymbol> = Vec::new();
+    let to_range = |range: TextRange| line_index.range(range);
+
+    for element in root.descendants_with_tokens() {
+        let offset = element.text_range().start();
+        close_environments(&mut stack, &mut top_level, offset, line_index);
+
+        match element {
+            NodeOrToken::Token(token) if token.kind() == SyntaxKind::Command && token.text() == "\\label" => {
+                // Matched on the command rather than `LabelDefinition` nodes, so a `\label` whose
+                // argument the parser could not attach still gets a symbol.
+                let Some(group) = crate::links::argument_group(&token) else {
+                    continue;
+                };
+                let Some((name, name_range)) = crate::links::group_content(&group) else {
+                    continue;
+                };
+                let range = TextRange::new(offset, group.text_range().end());
+                let label = symbol(name, None, SymbolKind::CONSTANT, to_range(range), to_range(name_range));
+                push_child(&mut stack, &mut top_level, label);
+            }
+            NodeOrToken::Token(token) if token.kind() == SyntaxKind::Command => {
+                let Some((level, argument)) = section_level(&token, structural) else {
+                    continue;
+                };
+                let Some((title, title_range)) = crate::links::nth_argument_group(&token, argument)
+                    .and_then(|group| crate::links::group_content(&group))
+                else {
+                    continue;
+                };
+                // Close sections of the same or a deeper level, but never leave an environment.
+                while stack.last().is_some_and(|open| open.level.is_some_and(|l| l >= level)) {
+                    close(&mut stack, &mut top_level, offset, line_index);
+                }
+                stack.push(OpenSymbol {
+                    symbol: symbol(title, Some(token.text().trim_start_matches('\\').to_string()), SymbolKind::STRING, to_range(token.text_range()), to_range(title_range)),
+                    start: offset,
+                    level: Some(level),
+                    end: None,
+                });
+            }
+            NodeOrToken::Node(node) if node.kind() == SyntaxKind::Environment => {
+                let Some((name, name_range)) = extract_label_data(&node) else {
+                    continue;
+                };
+                stack.push(OpenSymbol {
+                    symbol: symbol(name, None, SymbolKind::NAMESPACE, to_range(node.text_range()), to_range(name_range)),
+                    start: offset,
+                    level: None,
+                    end: Some(node.text_range().end()),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let end = root.text_range().end();
+    while !stack.is_empty() {
+        close(&mut stack, &mut top_level, end, line_index);
+    }
+    top_level
+}
+
+/// Closes the environments that end at or before `offset`, with the sections inside them.
+fn close_environments(stack: &mut Vec<OpenSymbol>, top_level: &mut Vec<DocumentSymbol>, offset: TextSize, line_index: &LineIndex) {
+    while let Some(env_end) = stack.iter().rev().find_map(|open| open.end).filter(|end| *end <= offset) {
+        while let Some(open) = stack.last() {
+            let is_env = open.end.is_some();
+            close(stack, top_level, env_end, line_index);
+            if is_env {
+                break;
+            }
+        }
+    }
+}
+
+/// Pops the innermost open symbol, ending sections at `offset`, and attaches it to its parent.
+fn close(stack: &mut Vec<OpenSymbol>, top_level: &mut Vec<DocumentSymbol>, offset: TextSize, line_index: &LineIndex) {
+    let Some(mut open) = stack.pop() else {
+        return;
+    };
+    if open.level.is_some() {
+        open.symbol.range = line_index.range(TextRange::new(open.start, offset.max(open.start)));
+    }
+    push_child(stack, top_level, open.symbol);
+}
+
+fn push_child(stack: &mut [OpenSymbol], top_level: &mut Vec<DocumentSymbol>, child: DocumentSymbol) {
+    match stack.last_mut() {
+        Some(parent) => parent.symbol.children.get_or_insert_with(Vec::new).push(child),
+        None => top_level.push(child),
+    }
+}
+
+/// Folding ranges for the sections and environments of an outline.
+///
+/// A section ends where the next one starts, so its fold stops on the line before.
+pub fn folding_ranges(symbols: &[DocumentSymbol]) -> Vec<FoldingRange> {
+    let mut ranges = Vec::new();
+    let mut pending: Vec<&DocumentSymbol> = symbols.iter().collect();
+    while let Some(symbol) = pending.pop() {
+        pending.extend(symbol.children.iter().flatten());
+        if symbol.kind == SymbolKind::CONSTANT {
+            continue;
+        }
+        let Range { start, end } = symbol.range;
+        let end_line = if end.character == 0 && end.line > start.line { end.line - 1 } else { end.line };
+        if end_line > start.line {
+            ranges.push(FoldingRange {
+                start_line: start.line,
+                start_character: None,
+                end_line,
+                end_character: None,
+                kind: Some(FoldingRangeKind::Region),
+                collapsed_text: None,
+            });
+        }
+    }
+    ranges.sort_by_key(|range| (range.start_line, std::cmp::Reverse(range.end_line)));
+    ranges
+}
+
+#[allow(deprecated)]
+fn symbol(name: String, detail: Option<String>, kind: SymbolKind, range: Range, selection_range: Range) -> DocumentSymbol {
+    DocumentSymbol {
+        name,
+        detail,
+        kind,
+        tags: None,
+        deprecated: None,
+        range,
+        selection_range,
+        children: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp::lsp_types::Position;
+
+    /// Renders the outline as `name[child, ...]` for compact assertions.
+    fn outline(text: &str) -> String {
+        fn render(symbols: &[DocumentSymbol]) -> String {
+            symbols
+                .iter()
+                .map(|s| match &s.children {
+                    Some(children) => format!("{}[{}]", s.name, render(children)),
+                    None => s.name.clone(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+        let root = ferrotex_syntax::parse(text).syntax();
+        render(&document_symbols(&root, &LineIndex::new(text), &[]))
+    }
+
+    #[test]
+    fn test_sections_nest_by_level() {
+        let text = "\\section{Intro}\\label{sec:intro}\n\\subsection{Scope}\ntext\n\\subsubsection*{Detail}\n\\section{Method}\n\\subsection{Setup}";
+        assert_eq!(outline(text), "Intro[sec:intro, Scope[Detail]], Method[Setup]");
+    }
+
+    #[test]
+    fn test_environments_own_their_content() {
+        let text = "\\begin{document}\n\\section{A}\n\\begin{figure}\\label{fig:a}\\end{figure}\n\\subsection{B}\n\\begin{itemize}\\begin{enumerate}\\end{enumerate}\\end{itemize}\n\\end{document}\n\\label{after}";
+        assert_eq!(outline(text), "document[A[figure[fig:a], B[itemize[enumerate]]]], after");
+    }
+
+    #[test]
+    fn test_ranges() {
+        let text = "\\section{Intro}\nbody\n\\section{Next}";
+        let root = ferrotex_syntax::parse(text).syntax();
+        let symbols = document_symbols(&root, &LineIndex::new(text), &[]);
+
+        let intro = &symbols[0];
+        assert_eq!(intro.detail.as_deref(), Some("section"));
+        assert_eq!(intro.selection_range.start, Position { line: 0, character: 9 });
+        assert_eq!(intro.selection_range.end, Position { line: 0, character: 14 });
+        assert_eq!(intro.range.start, Position { line: 0, character: 0 });
+        assert_eq!(intro.range.end, Position { line: 2, character: 0 });
+        assert_eq!(symbols[1].range.end, Position { line: 2, character: 14 });
+    }
+
+    #[test]
+    fn test_structural_commands() {
+        let text = "\\lecture{2024-01-08}{Limits}\n\\problem{Squeeze}\ntext\n\\section{Aside}\n\\lecture{2024-01-15}{Series}";
+        let root = ferrotex_syntax::parse(text).syntax();
+        let structural = [
+            StructuralCommand { name: "lecture".to_string(), level: 1, argument: 2 },
+            StructuralCommand { name: "\\problem".to_string(), level: 3, argument: 1 },
+        ];
+        let symbols = document_symbols(&root, &LineIndex::new(text), &structural);
+        let names: Vec<_> = symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["Limits", "Series"]);
+        let children: Vec<_> = symbols[0].children.iter().flatten().map(|s| s.name.as_str()).collect();
+        assert_eq!(children, ["Squeeze", "Aside"]);
+        assert_eq!(symbols[0].detail.as_deref(), Some("lecture"));
+
+        let folds: Vec<_> = folding_ranges(&symbols).iter().map(|f| (f.start_line, f.end_line)).collect();
+        assert_eq!(folds, [(0, 3), (1, 2)]);
+    }
+
+    #[test]
+    fn test_large_document() {
+        // One line index for the whole outline: thousands of labels stay linear.
+        let text: String = (0..5000).map(|i| format!("\\section{{S{}}}\\label{{l{}}}\n", i, i)).collect();
+        let root = ferrotex_syntax::parse(&text).syntax();
+        let symbols = document_symbols(&root, &LineIndex::new(&text), &[]);
+        assert_eq!(symbols.len(), 5000);
+        assert_eq!(symbols[4999].children.as_ref().unwrap()[0].selection_range.start.line, 4999);
+    }
+}
@@ -1,6 +1,11 @@
-use std::path::Path;
-use std::process::Command;
+use flate2::read::GzDecoder;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+/// Scaled points (TeX's unit) per PDF big point.
+const SP_PER_BP: f64 = 65781.76;
 
 /// Result of a SyncTeX Forward Search (Source -> PDF).
 #[derive(Debug, Serialize, Deserialize)]
@@ -14,107 +19,298 @@ pub struct ForwardSearchResult {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct InverseSearchResult {
     pub file: String,
+    /// 0-based source line.
     pub line: u32,
 }
 
-/// Runs `synctex view` to find the PDF location corresponding to a source location.
-/// Note: synctex coordinates are in points (72 dpi).
-pub fn forward_search(
-    tex_path: &Path,
-    pdf_path: &Path,
-    line: u32,
-    col: u32,
-) -> Option<ForwardSearchResult> {
-    // synctex view -i "line:col:tex_path" -o "pdf_path"
-    // output format:
-    // This is SyncTeX...
-    // Output:PDF:...
-    // Page:1
-    // x:123.456
-    // y:789.012
-    // ...
-    
-    let input_spec = format!("{}:{}:{}", line + 1, col + 1, tex_path.to_string_lossy());
-    
-    let output = Command::new("synctex")
-        .arg("view")
-        .arg("-i")
-        .arg(&input_spec)
-        .arg("-o")
-        .arg(pdf_path)
-        .output()
-        .ok()?;
+/// A box or point the engine recorded in the `.synctex` file, in PDF points.
+///
+/// `y` is the baseline, measured from the top of the page; boxes extend `height`
+/// above and `depth` below it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub kind: char,
+    pub page: u32,
+    pub input: u32,
+    /// 1-based source line.
+    pub line: u32,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub depth: f64,
+}
 
-    if !output.status.success() {
-        return None;
+/// The parsed content of a `.synctex` or `.synctex.gz` file.
+#[derive(Debug, Default)]
+pub struct SyncTexData {
+    /// Input files by tag, resolved against the directory the engine ran in.
+    pub inputs: HashMap<u32, PathBuf>,
+    pub records: Vec<Record>,
+}
+
+impl SyncTexData {
+    /// Reads a synctex file, decompressing it if it ends in `.gz`.
+    pub fn load(path: &Path, base_dir: &Path) -> std::io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let mut content = String::new();
+        if path.extension().is_some_and(|ext| ext == "gz") {
+            GzDecoder::new(bytes.as_slice()).read_to_string(&mut content)?;
+        } else {
+            content = String::from_utf8_lossy(&bytes).into_owned();
+        }
+        Ok(Self::parse(&content, base_dir))
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    
-    // Parse output
-    let mut page = 0;
-    let mut x = 0.0;
-    let mut y = 0.0;
-    
-    for line in stdout.lines() {
-        if let Some(p) = line.strip_prefix("Page:") {
-            page = p.trim().parse().unwrap_or(0);
-        } else if let Some(val) = line.strip_prefix("x:") {
-            x = val.trim().parse().unwrap_or(0.0);
-        } else if let Some(val) = line.strip_prefix("y:") {
-            y = val.trim().parse().unwrap_or(0.0);
+    /// Parses the text of a synctex file. Unknown or malformed lines are skipped.
+    pub fn parse(content: &str, base_dir: &Path) -> Self {
+        let mut data = SyncTexData::default();
+        let mut unit = 1.0;
+        let mut magnification = 1.0;
+        let (mut x_offset, mut y_offset) = (0.0, 0.0);
+        let mut page = 0;
+
+        for line in content.lines() {
+            if let Some(input) = line.strip_prefix("Input:") {
+                if let Some((tag, path)) = input.split_once(':') {
+                    if let Ok(tag) = tag.parse() {
+                        data.inputs.insert(tag, normalize(&base_dir.join(path)));
+                    }
+                }
+            } else if let Some(value) = line.strip_prefix("Unit:") {
+                unit = value.trim().parse().unwrap_or(1.0);
+            } else if let Some(value) = line.strip_prefix("Magnification:") {
+                magnification = value.trim().parse::<f64>().map_or(1.0, |m| m / 1000.0);
+            } else if let Some(value) = line.strip_prefix("X Offset:") {
+                x_offset = value.trim().parse().unwrap_or(0.0);
+            } else if let Some(value) = line.strip_prefix("Y Offset:") {
+                y_offset = value.trim().parse().unwrap_or(0.0);
+            } else if let Some(value) = line.strip_prefix('{') {
+                page = value.trim().parse().unwrap_or(0);
+            } else if line.starts_with('}') {
+                page = 0;
+            } else if page > 0 {
+                let scale = |v: f64| v * unit * magnification / SP_PER_BP;
+                if let Some(mut record) = parse_record(line, page) {
+                    record.x = scale(record.x) + x_offset / SP_PER_BP;
+                    record.y = scale(record.y) + y_offset / SP_PER_BP;
+                    record.width = scale(record.width);
+                    record.height = scale(record.height);
+                    record.depth = scale(record.depth);
+                    data.records.push(record);
+                }
+            }
         }
+        data
     }
 
-    if page > 0 {
-        Some(ForwardSearchResult { page, x, y })
-    } else {
-        None
+    /// Finds the first place `line` (0-based) of `file` was typeset.
+    ///
+    /// Lines that produced no output (blank lines, most preamble commands) map to the
+    /// closest line that did. Boxes are preferred over glue and kern points.
+    pub fn forward(&self, file: &Path, line: u32) -> Option<ForwardSearchResult> {
+        let file = normalize(file);
+        let tags: Vec<u32> = self
+            .inputs
+            .iter()
+            .filter(|(_, path)| same_file(path, &file))
+            .map(|(tag, _)| *tag)
+            .collect();
+        let target = line + 1;
+        let candidates = self.records.iter().filter(|r| tags.contains(&r.input) && r.line > 0);
+        let best_line = candidates.clone().map(|r| r.line).min_by_key(|l| (l.abs_diff(target), *l < target))?;
+        let record = candidates
+            .clone()
+            .filter(|r| r.line == best_line)
+            .find(|r| matches!(r.kind, '(' | 'h' | '[' | 'v'))
+            .or_else(|| candidates.clone().find(|r| r.line == best_line))?;
+        Some(ForwardSearchResult { page: record.page, x: record.x, y: record.y })
+    }
+
+    /// Finds the source line typeset at a point of a page, in PDF points from the top left.
+    ///
+    /// The innermost box containing the point wins; if there is none, the nearest record.
+    pub fn inverse(&self, page: u32, x: f64, y: f64) -> Option<InverseSearchResult> {
+        let on_page = self.records.iter().filter(|r| r.page == page && r.line > 0);
+        let containing = on_page
+            .clone()
+            .filter(|r| matches!(r.kind, '(' | 'h' | '[' | 'v'))
+            .filter(|r| x >= r.x && x <= r.x + r.width && y >= r.y - r.height && y <= r.y + r.depth)
+            .min_by(|a, b| area(a).total_cmp(&area(b)));
+        let record = containing.or_else(|| {
+            on_page.min_by(|a, b| distance(a, x, y).total_cmp(&distance(b, x, y)))
+        })?;
+        let file = self.inputs.get(&record.input)?;
+        Some(InverseSearchResult {
+            file: file.to_string_lossy().into_owned(),
+            line: record.line - 1,
+        })
     }
 }
 
-/// Runs `synctex edit` to find the source location corresponding to a PDF location.
-pub fn inverse_search(
-    pdf_path: &Path,
-    page: u32,
-    x: f64,
-    y: f64,
-) -> Option<InverseSearchResult> {
-    // synctex edit -o "page:x:y:pdf_path"
-    // format:
-    // Line:10
-    // Column:5
-    // Input:/path/to/file.tex
-    
-    let input_spec = format!("{}:{}:{}:{}", page, x, y, pdf_path.to_string_lossy());
-
-    let output = Command::new("synctex")
-        .arg("edit")
-        .arg("-o")
-        .arg(&input_spec)
-        .output()
-        .ok()?;
-        
-    if !output.status.success() {
+fn area(record: &Record) -> f64 {
+    record.width * (record.height + record.depth)
+}
+
+/// Distance from a point to a record's box (zero-sized for glue, kern and math points).
+fn distance(record: &Record, x: f64, y: f64) -> f64 {
+    let dx = (record.x - x).max(x - (record.x + record.width)).max(0.0);
+    let dy = (record.y - record.height - y).max(y - (record.y + record.depth)).max(0.0);
+    dx.hypot(dy)
+}
+
+/// Parses `(1,10:4736286,6225904:30785863,40655635,0` and the like.
+fn parse_record(line: &str, page: u32) -> Option<Record> {
+    let kind = line.chars().next()?;
+    if !matches!(kind, '[' | '(' | 'v' | 'h' | 'x' | 'k' | 'g' | '$') {
         return None;
     }
+    let mut parts = line[1..].split(':');
+    let mut link = parts.next()?.split(',');
+    let input = link.next()?.parse().ok()?;
+    let line_number = link.next()?.parse().ok()?;
+    let mut point = parts.next()?.split(',');
+    let x: i64 = point.next()?.parse().ok()?;
+    let y: i64 = point.next()?.parse().ok()?;
+    let size: Vec<f64> = parts
+        .next()
+        .map(|s| s.split(',').filter_map(|v| v.parse::<i64>().ok()).map(|v| v as f64).collect())
+        .unwrap_or_default();
+    Some(Record {
+        kind,
+        page,
+        input,
+        line: line_number,
+        x: x as f64,
+        y: y as f64,
+        width: size.first().copied().unwrap_or(0.0),
+        height: size.get(1).copied().unwrap_or(0.0),
+        depth: size.get(2).copied().unwrap_or(0.0),
+    })
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    
-    let mut file = String::new();
-    let mut line_num = 0;
-    
-    for line in stdout.lines() {
-        if let Some(l) = line.strip_prefix("Line:") {
-            line_num = l.trim().parse().unwrap_or(0);
-        } else if let Some(f) = line.strip_prefix("Input:") {
-            file = f.trim().to_string();
-        }
+/// Removes `.` components, which engines write for files in the current directory.
+fn normalize(path: &Path) -> PathBuf {
+    path.components().filter(|c| !matches!(c, Component::CurDir)).collect()
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    if a == b {
+        return true;
     }
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Returns the synctex file for `pdf_path`, if the engine wrote one.
+pub fn find_synctex_file(pdf_path: &Path) -> Option<PathBuf> {
+    ["synctex.gz", "synctex"]
+        .iter()
+        .map(|ext| pdf_path.with_extension(ext))
+        .find(|path| path.exists())
+}
+
+/// Maps a source location to the PDF location it was typeset at.
+///
+/// `line` is 0-based. Relative input paths in the synctex file are resolved against
+/// `base_dir`, the directory the engine ran in.
+pub fn forward_search(tex_path: &Path, pdf_path: &Path, base_dir: &Path, line: u32) -> Option<ForwardSearchResult> {
+    let synctex = find_synctex_file(pdf_path)?;
+    let data = SyncTexData::load(&synctex, base_dir)
+        .map_err(|e| log::warn!("Failed to read {}: {}", synctex.display(), e))
+        .ok()?;
+    data.forward(tex_path, line)
+}
+
+/// Maps a PDF location to the source line typeset there.
+///
+/// `x` and `y` are in PDF points from the top left of `page`. Relative input paths
+/// in the synctex file are resolved against `base_dir`, the directory the engine ran in.
+pub fn inverse_search(pdf_path: &Path, base_dir: &Path, page: u32, x: f64, y: f64) -> Option<InverseSearchResult> {
+    let synctex = find_synctex_file(pdf_path)?;
+    let data = SyncTexData::load(&synctex, base_dir)
+        .map_err(|e| log::warn!("Failed to read {}: {}", synctex.display(), e))
+        .ok()?;
+    data.inverse(page, x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const SYNCTEX: &str = "SyncTeX Version:1
+Input:1:/project/./main.tex
+Input:2:./chapters/intro.tex
+Output:pdf
+Magnification:1000
+Unit:1
+X Offset:0
+Y Offset:0
+Content:
+!256
+{1
+[1,3:4736286,4736286:30785863,40655635,0
+(1,5:4736286,6578176:30785863,655360,0
+g1,5:5000000,6578176
+)
+(2,2:4736286,13156352:30785863,655360,0
+)
+]
+}1
+{2
+(1,9:4736286,9867264:30785863,655360,0
+)
+}2
+Postamble:
+";
+
+    #[test]
+    fn test_forward_search() {
+        let data = SyncTexData::parse(SYNCTEX, Path::new("/project"));
+        assert_eq!(data.inputs[&2], PathBuf::from("/project/chapters/intro.tex"));
+
+        // Line 5 (0-based 4) is an hbox on page 1, 100bp below the top.
+        let result = data.forward(Path::new("/project/main.tex"), 4).unwrap();
+        assert_eq!(result.page, 1);
+        assert!((result.x - 72.0).abs() < 0.01);
+        assert!((result.y - 100.0).abs() < 0.01);
+
+        // Lines without output snap to the closest typeset line, preferring later ones.
+        assert_eq!(data.forward(Path::new("/project/main.tex"), 6).unwrap().page, 2);
+        assert_eq!(data.forward(Path::new("/project/chapters/intro.tex"), 0).unwrap().page, 1);
+        assert!(data.forward(Path::new("/project/other.tex"), 0).is_none());
+    }
+
+    #[test]
+    fn test_inverse_search() {
+        let data = SyncTexData::parse(SYNCTEX, Path::new("/project"));
+
+        // Inside the line box of main.tex:5, which is nested in the vbox of line 3.
+        let result = data.inverse(1, 100.0, 95.0).unwrap();
+        assert_eq!(result.file, "/project/main.tex");
+        assert_eq!(result.line, 4);
+
+        let result = data.inverse(1, 100.0, 195.0).unwrap();
+        assert_eq!(result.file, "/project/chapters/intro.tex");
+        assert_eq!(result.line, 1);
+
+        // Outside any box, the nearest record is used.
+        assert_eq!(data.inverse(2, 600.0, 150.0).unwrap().line, 8);
+        assert!(data.inverse(3, 0.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn test_load_compressed() {
+        let dir = tempfile::tempdir().unwrap();
+        let pdf = dir.path().join("main.pdf");
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(SYNCTEX.as_bytes()).unwrap();
+        std::fs::write(dir.path().join("main.synctex.gz"), encoder.finish().unwrap()).unwrap();
 
-    if !file.is_empty() && line_num > 0 {
-        Some(InverseSearchResult { file, line: line_num - 1 }) // Convert back to 0-indexed
-    } else {
-        None
+        let result = forward_search(Path::new("/project/main.tex"), &pdf, Path::new("/project"), 4).unwrap();
+        assert_eq!(result.page, 1);
     }
 }
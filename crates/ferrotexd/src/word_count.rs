@@ -0,0 +1,156 @@
+//! Word and character counts of the running text of a document.
+
+use crate::diagnostics::{self, spell};
+use ferrotex_syntax::{SyntaxKind, SyntaxNode, SyntaxToken, TextSize};
+use serde::Serialize;
+
+/// Counts of a stretch of running text.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Counts {
+    pub words: usize,
+    /// Letters and digits; punctuation and whitespace are not counted.
+    pub characters: usize,
+}
+
+impl Counts {
+    fn add(&mut self, other: Counts) {
+        self.words += other.words;
+        self.characters += other.characters;
+    }
+}
+
+/// The text from a sectioning command up to the next one, its title included.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SectionCount {
+    /// The sectioning command without the backslash, e.g. `subsection`.
+    pub command: String,
+    pub title: String,
+    /// Offset of the sectioning command.
+    #[serde(skip)]
+    pub offset: TextSize,
+    #[serde(flatten)]
+    pub counts: Counts,
+}
+
+/// The counts of a file, in total and per section.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct WordCount {
+    #[serde(flatten)]
+    pub total: Counts,
+    pub sections: Vec<SectionCount>,
+}
+
+/// Counts the words of the running text of a file.
+///
+/// Commands, comments, math, verbatim text, options and identifier arguments (labels,
+/// references, file names, ...) are not counted; the titles and arguments of text
+/// commands are. In a file with a `document` environment, only its body counts. A word
+/// is a run of letters and digits, possibly joined by punctuation or accent commands,
+/// so `don't` and `na\"ive` are single words.
+pub fn count_words(root: &SyntaxNode) -> WordCount {
+    let body = root
+        .descendants()
+        .find(|node| node.kind() == SyntaxKind::Environment && crate::workspace::extract_group_text(node).as_deref() == Some("document"));
+    let root = body.as_ref().unwrap_or(root);
+
+    let mut result = WordCount::default();
+    let mut current = Counts::default();
+    let mut in_math = false;
+    let mut in_word = false;
+    for token in root.descendants_with_tokens().filter_map(|e| e.into_token()) {
+        match token.kind() {
+            SyntaxKind::Dollar => {
+                in_math = !in_math;
+                in_word = false;
+            }
+            SyntaxKind::Command => match token.text() {
+                "\\(" | "\\[" => {
+                    in_math = true;
+                    in_word = false;
+                }
+                "\\)" | "\\]" => in_math = false,
+                _ if in_math => {}
+                text => {
+                    let Some(command) = crate::symbols::SECTION_LEVELS.iter().find(|(name, _)| *name == text) else {
+                        continue;
+                    };
+                    finish_section(&mut result, &mut current);
+                    let title = crate::links::argument_group(&token)
+                        .and_then(|group| crate::links::group_content(&group))
+                        .map(|(title, _)| title)
+                        .unwrap_or_default();
+                    result.sections.push(SectionCount {
+                        command: command.0.trim_start_matches('\\').to_string(),
+                        title,
+                        offset: token.text_range().start(),
+                        counts: Counts::default(),
+                    });
+                    in_word = false;
+                }
+            },
+            SyntaxKind::Text if !in_math && is_prose(&token) => {
+                for c in token.text().chars() {
+                    if c.is_alphanumeric() {
+                        if !in_word {
+                            current.words += 1;
+                            in_word = true;
+                        }
+                        current.characters += 1;
+                    } else if c.is_whitespace() {
+                        in_word = false;
+                    }
+                }
+            }
+            SyntaxKind::Whitespace | SyntaxKind::Comment => in_word = false,
+            _ => {}
+        }
+    }
+    finish_section(&mut result, &mut current);
+    result
+}
+
+/// Adds the counts since the last section to it and to the total.
+fn finish_section(result: &mut WordCount, current: &mut Counts) {
+    if let Some(section) = result.sections.last_mut() {
+        section.counts.add(*current);
+    }
+    result.total.add(std::mem::take(current));
+}
+
+fn is_prose(token: &SyntaxToken) -> bool {
+    !diagnostics::in_verbatim(token)
+        && !diagnostics::in_environment(token, diagnostics::MATH_ENVIRONMENTS)
+        && !spell::in_non_prose_argument(token)
+        && !spell::in_command_option(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferrotex_syntax::parse;
+
+    fn count(text: &str) -> WordCount {
+        count_words(&parse(text).syntax())
+    }
+
+    #[test]
+    fn test_count_words() {
+        let result = count("Hello, world! Don't count $x + y$ or \\ref{sec:a} % or this\n");
+        assert_eq!(result.total, Counts { words: 5, characters: 21 });
+        assert!(result.sections.is_empty());
+
+        // Accents and markup inside a word do not split it.
+        assert_eq!(count("na\\\"ive \\textbf{bold}ly").total.words, 2);
+        assert_eq!(count("\\begin{verbatim}not counted\\end{verbatim} counted").total.words, 1);
+        assert_eq!(count("\\includegraphics[width=3cm]{figure} A \\[ x \\] B").total.words, 2);
+    }
+
+    #[test]
+    fn test_count_sections() {
+        let text = "\\documentclass{article}\n\\title{Ignored preamble}\n\\begin{document}\nOne two.\n\\section{Intro}\nThree four five.\n\\subsection{Deep Dive}\nSix.\n\\end{document}\n";
+        let result = count(text);
+        assert_eq!(result.total.words, 9);
+        let sections: Vec<_> = result.sections.iter().map(|s| (s.command.as_str(), s.title.as_str(), s.counts.words)).collect();
+        assert_eq!(sections, [("section", "Intro", 4), ("subsection", "Deep Dive", 3)]);
+    }
+}
@@ -1,9 +1,21 @@
+use crate::diagnostics::deprecated::{DeprecationKind, lookup, lookup_marker};
+use crate::dependency_graph::{DependencyGraph, EdgeKind, NodeKind};
+use crate::index_cache::CachedIndex;
+use crate::protocol::IncludeTreeNode;
 use dashmap::DashMap;
-use ferrotex_syntax::{SyntaxKind, TextRange, parse};
+use ferrotex_syntax::{SyntaxKind, SyntaxNode, TextRange, parse};
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use tower_lsp::lsp_types::{SymbolKind, Url};
 
+/// Directories skipped when indexing a project (in addition to hidden ones).
+const IGNORED_DIRECTORIES: &[&str] = &["node_modules", "target", "_minted", "svg-inkscape"];
+
+/// Upper bound on the number of files indexed in the background.
+const MAX_INDEXED_FILES: usize = 10_000;
+
 /// The central workspace manager for the LSP server.
 ///
 /// It maintains an in-memory index of all tracked TeX and BibTeX files.
@@ -13,12 +25,15 @@ pub struct Workspace {
     indices: DashMap<Url, FileIndex>,
     /// Bibliography index containing parsed BibTeX entries.
     bib_indices: DashMap<Url, ferrotex_syntax::bibtex::BibFile>,
-    /// Explicit root overrides from `%!TEX root` comments.
+    /// Explicit root overrides from `%!TEX root` comments and `subfiles` class options.
     explicit_roots: DashMap<Url, String>,
+    /// Files by the include paths they name, normalized with [`include_key`], so the
+    /// documents including a file are found without scanning every index.
+    includers: DashMap<String, HashSet<Url>>,
 }
 
 /// The index data for a single TeX file.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct FileIndex {
     /// List of included files (e.g., `\input{...}`).
@@ -37,12 +52,18 @@ pub struct FileIndex {
     pub packages: Vec<String>,
     /// List of environments (e.g., `\begin{...}`).
     pub environments: Vec<EnvDef>,
+    /// List of included graphics (e.g., `\includegraphics{...}`).
+    pub graphics: Vec<GraphicsRef>,
+    /// List of user-defined commands (e.g., `\newcommand{\foo}[2]{...}`).
+    pub command_definitions: Vec<MacroDef>,
+    /// List of user-defined environments (e.g., `\newenvironment{thm}{...}{...}`).
+    pub environment_definitions: Vec<MacroDef>,
     /// List of deprecated command usages.
     pub deprecated_usages: Vec<(TextRange, String)>,
 }
 
 /// Represents an environment definition.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnvDef {
     /// The environment name.
     pub name: String,
@@ -51,7 +72,7 @@ pub struct EnvDef {
 }
 
 /// Represents an included file reference.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IncludeRef {
     /// The path to the included file (as written in the source).
     pub path: String,
@@ -59,8 +80,30 @@ pub struct IncludeRef {
     pub range: TextRange,
 }
 
+/// Represents an included graphics file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphicsRef {
+    /// The path to the graphics file, possibly without extension.
+    pub path: String,
+    /// The range of the path string in the source file.
+    pub range: TextRange,
+}
+
+/// Represents a user-defined command or environment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MacroDef {
+    /// The command (without backslash) or environment name.
+    pub name: String,
+    /// Number of parameters, including the optional one.
+    pub parameters: u32,
+    /// Default value of the first parameter if it is optional (`\newcommand{\foo}[2][x]{...}`).
+    pub default: Option<String>,
+    /// The range of the name in the source file.
+    pub range: TextRange,
+}
+
 /// Represents a section definition.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SectionDef {
     /// The section title.
     pub name: String,
@@ -69,7 +112,7 @@ pub struct SectionDef {
 }
 
 /// Represents a label definition.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LabelDef {
     /// The label name.
     pub name: String,
@@ -78,7 +121,7 @@ pub struct LabelDef {
 }
 
 /// Represents a reference to a label.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LabelRef {
     /// The referenced label name.
     pub name: String,
@@ -86,8 +129,25 @@ pub struct LabelRef {
     pub range: TextRange,
 }
 
+/// A label with the places it is defined and referenced across the workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelUsage {
+    /// The label name.
+    pub name: String,
+    /// The `\label` commands defining it, sorted by file and position.
+    pub definitions: Vec<(Url, TextRange)>,
+    /// The references to it, sorted by file and position.
+    pub references: Vec<(Url, TextRange)>,
+}
+
+impl LabelUsage {
+    fn new(name: &str) -> Self {
+        Self { name: name.to_string(), definitions: Vec::new(), references: Vec::new() }
+    }
+}
+
 /// Represents a citation.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CitationRef {
     /// The citation key.
     pub key: String,
@@ -96,7 +156,7 @@ pub struct CitationRef {
 }
 
 /// Represents a bibliography file reference.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct BibRef {
     /// The path to the bibliography file.
@@ -115,8 +175,13 @@ impl Workspace {
     ///
     /// Parses the file content and extracts includes, labels, citations, etc.
     pub fn update(&self, uri: &Url, text: &str) {
-        let (includes, definitions, references, citations, bibliographies, sections, packages, magic_root, deprecated_usages, environments) =
-            scan_file(text);
+        self.update_syntax(uri, text, &parse(text).syntax());
+    }
+
+    /// Same as [`Workspace::update`], but reuses a syntax tree already built for `text`.
+    pub fn update_syntax(&self, uri: &Url, text: &str, root: &SyntaxNode) {
+        let (includes, definitions, references, citations, bibliographies, sections, packages, magic_root, deprecated_usages, environments, graphics, (command_definitions, environment_definitions)) =
+            scan_file(text, root);
 
         if let Some(root_path) = magic_root {
             self.explicit_roots.insert(uri.clone(), root_path);
@@ -124,8 +189,8 @@ impl Workspace {
             self.explicit_roots.remove(uri);
         }
 
-        self.indices.insert(
-            uri.clone(),
+        self.set_index(
+            uri,
             FileIndex {
                 includes,
                 definitions,
@@ -135,6 +200,9 @@ impl Workspace {
                 sections,
                 packages,
                 environments,
+                graphics,
+                command_definitions,
+                environment_definitions,
                 deprecated_usages,
             },
         );
@@ -148,12 +216,111 @@ impl Workspace {
         self.bib_indices.insert(uri.clone(), bib_file);
     }
 
+    /// Reads a file from disk and indexes it as TeX or BibTeX depending on its extension.
+    ///
+    /// Returns `false` if the file could not be read.
+    pub fn index_file(&self, uri: &Url, path: &Path) -> bool {
+        let Ok(text) = std::fs::read_to_string(path) else {
+            return false;
+        };
+        if path.extension().is_some_and(|ext| ext == "bib") {
+            self.update_bib(uri, &text);
+        } else {
+            self.update(uri, &text);
+        }
+        true
+    }
+
+    /// Indexes `root` and, transitively, the files it includes and the bibliographies they
+    /// name, reading from disk those not indexed yet.
+    ///
+    /// Returns the TeX files of the tree in the order they were reached. Files that cannot
+    /// be read are left out; [`Workspace::validate_includes`] and
+    /// [`Workspace::validate_bibliographies`] report them.
+    pub fn load_tree(&self, root: &Url) -> Vec<Url> {
+        let mut loaded = Vec::new();
+        let mut pending = vec![root.clone()];
+        while let Some(uri) = pending.pop() {
+            if loaded.contains(&uri) {
+                continue;
+            }
+            if !self.indices.contains_key(&uri) {
+                let Ok(path) = uri.to_file_path() else { continue };
+                if !self.index_file(&uri, &path) {
+                    continue;
+                }
+            }
+            for bib in self.get_bibliographies(&uri) {
+                let Some(bib_uri) = resolve_bib_uri(root, &bib.path) else { continue };
+                if !self.bib_indices.contains_key(&bib_uri) {
+                    if let Ok(path) = bib_uri.to_file_path() {
+                        self.index_file(&bib_uri, &path);
+                    }
+                }
+            }
+            let includes = self.get_includes(&uri);
+            pending.extend(includes.iter().rev().filter_map(|include| resolve_include(root, &include.path)));
+            loaded.push(uri);
+        }
+        loaded
+    }
+
+    /// Returns the index entry of a file in the form stored by the on-disk cache.
+    pub fn snapshot(&self, uri: &Url) -> Option<CachedIndex> {
+        if let Some(bib) = self.bib_indices.get(uri) {
+            return Some(CachedIndex::Bib(bib.clone()));
+        }
+        self.indices.get(uri).map(|index| CachedIndex::Tex {
+            index: Box::new(index.clone()),
+            explicit_root: self.get_explicit_root(uri),
+        })
+    }
+
+    /// Restores an index entry loaded from the on-disk cache.
+    pub fn restore(&self, uri: &Url, entry: CachedIndex) {
+        match entry {
+            CachedIndex::Tex { index, explicit_root } => {
+                if let Some(root_path) = explicit_root {
+                    self.explicit_roots.insert(uri.clone(), root_path);
+                } else {
+                    self.explicit_roots.remove(uri);
+                }
+                self.set_index(uri, *index);
+            }
+            CachedIndex::Bib(bib) => {
+                self.bib_indices.insert(uri.clone(), bib);
+            }
+        }
+    }
+
     /// Removes a file from the workspace index.
     pub fn remove(&self, uri: &Url) {
-        self.indices.remove(uri);
+        if let Some((_, index)) = self.indices.remove(uri) {
+            self.unlink_includes(uri, &index.includes);
+        }
         self.bib_indices.remove(uri);
     }
 
+    /// Stores the index of a TeX file and records the files it includes in `includers`.
+    fn set_index(&self, uri: &Url, index: FileIndex) {
+        let includes = index.includes.clone();
+        if let Some(old) = self.indices.insert(uri.clone(), index) {
+            self.unlink_includes(uri, &old.includes);
+        }
+        for key in includes.iter().filter_map(|include| include_key(&include.path)) {
+            self.includers.entry(key).or_default().insert(uri.clone());
+        }
+    }
+
+    fn unlink_includes(&self, uri: &Url, includes: &[IncludeRef]) {
+        for key in includes.iter().filter_map(|include| include_key(&include.path)) {
+            if let Some(mut files) = self.includers.get_mut(&key) {
+                files.remove(uri);
+            }
+            self.includers.remove_if(&key, |_, files| files.is_empty());
+        }
+    }
+
     /// Retrieves the list of included files for a given document URI.
     pub fn get_includes(&self, uri: &Url) -> Vec<IncludeRef> {
         self.indices
@@ -167,33 +334,75 @@ impl Workspace {
         self.explicit_roots.get(uri).map(|v| v.value().clone())
     }
 
-    /// Retrieves the list of used packages for a given document URI.
+    /// Resolves the main document that `uri` is compiled as part of.
     ///
-    /// If an explicit root is set, it also includes packages from the root.
-    pub fn get_packages(&self, uri: &Url) -> Vec<String> {
-        let mut packages = HashSet::new();
+    /// In order of precedence:
+    /// 1. An explicit root (`%!TEX root = ...` or `\documentclass[main.tex]{subfiles}`).
+    /// 2. The outermost document whose `\input`/`\include` tree contains `uri`.
+    /// 3. The document itself.
+    ///
+    /// Explicit roots are followed transitively, so a chapter may point at another
+    /// sub-document that in turn names the real root.
+    pub fn resolve_root(&self, uri: &Url) -> Url {
+        let mut current = uri.clone();
+        let mut visited = HashSet::new();
+        while visited.insert(current.clone()) {
+            let next = match self.get_explicit_root(&current) {
+                Some(root_path) => resolve_tex_uri(&current, &root_path),
+                None => self.including_root(&current),
+            };
+            match next {
+                Some(next) => current = next,
+                None => break,
+            }
+        }
+        current
+    }
 
-        // 1. Get packages from current file
-        if let Some(idx) = self.indices.get(uri) {
-            packages.extend(idx.packages.clone());
-        }
-
-        // 2. Get packages from explicit root (if any)
-        if let Some(root_path) = self.get_explicit_root(uri) {
-            #[allow(clippy::collapsible_if)]
-            if let Ok(file_path) = uri.to_file_path() {
-                if let Some(parent) = file_path.parent() {
-                    let root_buf = parent.join(&root_path);
-                    if let Ok(root_uri) = Url::from_file_path(root_buf) {
-                        #[allow(clippy::collapsible_if)]
-                        if let Some(idx) = self.indices.get(&root_uri) {
-                            packages.extend(idx.packages.clone());
-                        }
-                    }
+    /// Returns the top-level indexed document whose include tree contains `uri`.
+    ///
+    /// Includes are resolved against the directory of the document LaTeX runs on, so a
+    /// file naming `uri` only includes it if `uri` is that path below the directory of the
+    /// file's own top-level document. Documents naming an explicit root are not top-level,
+    /// and parents are tried in sorted order for a stable choice.
+    fn including_root(&self, uri: &Url) -> Option<Url> {
+        let root = self.top_level(uri, &mut HashSet::new());
+        (&root != uri).then_some(root)
+    }
+
+    fn top_level(&self, uri: &Url, visited: &mut HashSet<Url>) -> Url {
+        visited.insert(uri.clone());
+        // `uri` relative to each of its ancestor directories, nearest first.
+        let path = uri.path();
+        let relative_paths = path.rmatch_indices('/').map(|(slash, _)| &path[slash + 1..]);
+        for relative in relative_paths {
+            let mut parents: Vec<Url> = self.includers.get(relative).map(|files| files.iter().cloned().collect()).unwrap_or_default();
+            parents.sort();
+            for parent in parents {
+                if visited.contains(&parent) {
+                    continue;
+                }
+                let root = self.top_level(&parent, visited);
+                let includes_uri = || self.get_includes(&parent).iter().any(|include| resolve_include(&root, &include.path).as_ref() == Some(uri));
+                if !self.explicit_roots.contains_key(&root) && includes_uri() {
+                    return root;
                 }
             }
         }
+        uri.clone()
+    }
 
+    /// Retrieves the list of used packages for a given document URI.
+    ///
+    /// Packages loaded by `root` (usually [`Workspace::resolve_root`]) are included as well,
+    /// since they are in effect in every file of the project.
+    pub fn get_packages(&self, uri: &Url, root: &Url) -> Vec<String> {
+        let mut packages = HashSet::new();
+        for file in [uri, root] {
+            if let Some(idx) = self.indices.get(file) {
+                packages.extend(idx.packages.clone());
+            }
+        }
         packages.into_iter().collect()
     }
 
@@ -234,13 +443,15 @@ impl Workspace {
         keys
     }
 
+    /// Returns the `.bib` files named by indexed documents, resolved against the root
+    /// document of each, like LaTeX does.
     pub fn get_referenced_bib_uris(&self) -> Vec<Url> {
         let mut uris = HashSet::new();
 
-        for entry in self.indices.iter() {
-            let base_uri = entry.key();
-            for bib in &entry.value().bibliographies {
-                if let Some(uri) = resolve_bib_uri(base_uri, &bib.path) {
+        for (base_uri, bibliographies) in self.bibliography_refs() {
+            let root = self.resolve_root(&base_uri);
+            for bib in &bibliographies {
+                if let Some(uri) = resolve_bib_uri(&root, &bib.path) {
                     uris.insert(uri);
                 }
             }
@@ -249,6 +460,15 @@ impl Workspace {
         uris.into_iter().collect()
     }
 
+    /// The bibliography references of every indexed file that has some.
+    fn bibliography_refs(&self) -> Vec<(Url, Vec<BibRef>)> {
+        self.indices
+            .iter()
+            .filter(|entry| !entry.value().bibliographies.is_empty())
+            .map(|entry| (entry.key().clone(), entry.value().bibliographies.clone()))
+            .collect()
+    }
+
     /// Returns all label names defined in all indexed TeX files.
     pub fn get_all_labels(&self) -> Vec<String> {
         let mut labels = HashSet::new();
@@ -314,6 +534,30 @@ impl Workspace {
         find_in_bibs(&all_uris)
     }
 
+    /// Returns the commands defined with `\newcommand` and friends in all indexed files.
+    ///
+    /// Redefinitions of the same name are reported once, sorted by name.
+    pub fn get_command_definitions(&self) -> Vec<MacroDef> {
+        self.collect_definitions(|index| &index.command_definitions)
+    }
+
+    /// Returns the environments defined with `\newenvironment` in all indexed files.
+    pub fn get_environment_definitions(&self) -> Vec<MacroDef> {
+        self.collect_definitions(|index| &index.environment_definitions)
+    }
+
+    fn collect_definitions(&self, field: impl Fn(&FileIndex) -> &Vec<MacroDef>) -> Vec<MacroDef> {
+        let mut definitions: HashMap<String, MacroDef> = HashMap::new();
+        for entry in self.indices.iter() {
+            for def in field(entry.value()) {
+                definitions.entry(def.name.clone()).or_insert_with(|| def.clone());
+            }
+        }
+        let mut definitions: Vec<MacroDef> = definitions.into_values().collect();
+        definitions.sort_by(|a, b| a.name.cmp(&b.name));
+        definitions
+    }
+
     /// Finds all definitions of a label by name.
     ///
     /// Returns a list of (File URI, Range) pairs.
@@ -415,13 +659,15 @@ impl Workspace {
 
     // --- Diagnostics ---
 
+    /// Reports bibliographies that cannot be resolved against the root document of the
+    /// file naming them, or that are not indexed.
     pub fn validate_bibliographies(&self) -> Vec<(Url, TextRange, String)> {
         let mut diagnostics = Vec::new();
 
-        for entry in self.indices.iter() {
-            let base_uri = entry.key();
-            for bib in &entry.value().bibliographies {
-                let Some(uri) = resolve_bib_uri(base_uri, &bib.path) else {
+        for (base_uri, bibliographies) in self.bibliography_refs() {
+            let root = self.resolve_root(&base_uri);
+            for bib in &bibliographies {
+                let Some(uri) = resolve_bib_uri(&root, &bib.path) else {
                     diagnostics.push((
                         base_uri.clone(),
                         bib.range,
@@ -443,6 +689,125 @@ impl Workspace {
         diagnostics
     }
 
+    /// Finds citation keys defined more than once across the referenced `.bib` files
+    /// (all indexed ones if no document names a bibliography).
+    ///
+    /// Keys are compared case-insensitively, like BibTeX does. Every occurrence is
+    /// reported, so that each copy can be found from its own file.
+    pub fn validate_bib_duplicates(&self) -> Vec<(Url, TextRange, String)> {
+        let mut bibs = self.get_referenced_bib_uris();
+        if bibs.is_empty() {
+            bibs = self.bib_indices.iter().map(|e| e.key().clone()).collect();
+        }
+        bibs.sort();
+
+        let mut occurrences: HashMap<String, Vec<(Url, TextRange, String)>> = HashMap::new();
+        for uri in &bibs {
+            let Some(bib_file) = self.bib_indices.get(uri) else {
+                continue;
+            };
+            for entry in &bib_file.entries {
+                occurrences
+                    .entry(entry.key.to_lowercase())
+                    .or_default()
+                    .push((uri.clone(), entry.range, entry.key.clone()));
+            }
+        }
+
+        let mut diagnostics = Vec::new();
+        for found in occurrences.values().filter(|found| found.len() > 1) {
+            for (uri, range, key) in found {
+                let others: Vec<String> = found
+                    .iter()
+                    .filter(|(u, r, _)| (u, r) != (uri, range))
+                    .map(|(u, _, _)| u.path_segments().and_then(|mut s| s.next_back()).unwrap_or("").to_string())
+                    .collect();
+                diagnostics.push((
+                    uri.clone(),
+                    *range,
+                    format!("Duplicate citation key '{}' (also defined in {})", key, others.join(", ")),
+                ));
+            }
+        }
+        diagnostics.sort_by(|a, b| (&a.0, a.1.start()).cmp(&(&b.0, b.1.start())));
+        diagnostics
+    }
+
+    /// Finds `\input`/`\include` commands naming files that are not indexed, resolving
+    /// paths like [`Workspace::load_tree`] does for the tree below `root`.
+    pub fn validate_includes(&self, root: &Url) -> Vec<(Url, TextRange, String)> {
+        let mut diagnostics = Vec::new();
+        for entry in self.indices.iter() {
+            for include in &entry.value().includes {
+                let Some(target) = resolve_include(root, &include.path) else {
+                    continue;
+                };
+                if !self.indices.contains_key(&target) {
+                    diagnostics.push((
+                        entry.key().clone(),
+                        include.range,
+                        format!("Missing included file: '{}'", include.path),
+                    ));
+                }
+            }
+        }
+        diagnostics.sort_by(|a, b| (&a.0, a.1.start()).cmp(&(&b.0, b.1.start())));
+        diagnostics
+    }
+
+    /// Returns the entries of the referenced `.bib` files (all indexed ones if no document
+    /// names a bibliography) that no document cites, sorted by file and position.
+    ///
+    /// Keys are compared case-insensitively; `\nocite{*}` cites every entry. `@string`,
+    /// `@preamble` and `@comment` blocks are not entries. Entries a cited entry points to
+    /// through [`ENTRY_REFERENCE_FIELDS`] count as cited, since BibTeX and biber need them.
+    pub fn uncited_entries(&self) -> Vec<(Url, ferrotex_syntax::bibtex::BibEntry)> {
+        let mut cited = HashSet::new();
+        for entry in self.indices.iter() {
+            cited.extend(entry.value().citations.iter().map(|cite| cite.key.to_lowercase()));
+        }
+        if cited.contains("*") {
+            return Vec::new();
+        }
+
+        let mut bibs = self.get_referenced_bib_uris();
+        if bibs.is_empty() {
+            bibs = self.bib_indices.iter().map(|e| e.key().clone()).collect();
+        }
+        bibs.sort();
+
+        let mut pending: Vec<String> = cited.iter().cloned().collect();
+        while let Some(key) = pending.pop() {
+            for uri in &bibs {
+                let Some(bib_file) = self.bib_indices.get(uri) else {
+                    continue;
+                };
+                for entry in bib_file.entries.iter().filter(|entry| entry.key.to_lowercase() == key) {
+                    let targets = ENTRY_REFERENCE_FIELDS.iter().filter_map(|field| entry.fields.get(*field));
+                    for target in targets.flat_map(|value| value.split(',')) {
+                        let target = target.trim().trim_matches(['{', '}', '"']).trim().to_lowercase();
+                        if !target.is_empty() && cited.insert(target.clone()) {
+                            pending.push(target);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut uncited = Vec::new();
+        for uri in bibs {
+            let Some(bib_file) = self.bib_indices.get(&uri) else {
+                continue;
+            };
+            for entry in &bib_file.entries {
+                if !NON_ENTRY_TYPES.contains(&entry.entry_type.as_str()) && !cited.contains(&entry.key.to_lowercase()) {
+                    uncited.push((uri.clone(), entry.clone()));
+                }
+            }
+        }
+        uncited
+    }
+
     /// Validates citations across the workspace.
     ///
     /// Returns a list of diagnostics for undefined citations.
@@ -461,7 +826,7 @@ impl Workspace {
         // Check for undefined citations
         for entry in self.indices.iter() {
             for cite in &entry.value().citations {
-                if !self.has_citation_key(&cite.key) {
+                if cite.key != "*" && !self.has_citation_key(&cite.key) {
                     diagnostics.push((
                         entry.key().clone(),
                         cite.range,
@@ -474,68 +839,161 @@ impl Workspace {
         diagnostics
     }
 
-    /// Validates labels across the workspace.
-    ///
-    /// Checks for duplicate label definitions and undefined references.
-    pub fn validate_labels(&self) -> Vec<(Url, TextRange, String)> {
-        let mut diagnostics = Vec::new();
-
-        // 1. Gather all definitions to check for duplicates
-        let mut defs_by_name: HashMap<String, Vec<(Url, TextRange)>> = HashMap::new();
+    /// Returns every label defined or referenced in the indexed TeX files, sorted by name.
+    pub fn label_usages(&self) -> Vec<LabelUsage> {
+        let mut usages: BTreeMap<String, LabelUsage> = BTreeMap::new();
         for entry in self.indices.iter() {
             for def in &entry.value().definitions {
-                defs_by_name
+                usages
                     .entry(def.name.clone())
-                    .or_default()
+                    .or_insert_with(|| LabelUsage::new(&def.name))
+                    .definitions
                     .push((entry.key().clone(), def.range));
             }
+            for r in &entry.value().references {
+                usages
+                    .entry(r.name.clone())
+                    .or_insert_with(|| LabelUsage::new(&r.name))
+                    .references
+                    .push((entry.key().clone(), r.range));
+            }
         }
 
-        // 2. Report duplicates
-        for (name, locs) in &defs_by_name {
-            if locs.len() > 1 {
-                for (uri, range) in locs {
-                    diagnostics.push((
-                        uri.clone(),
-                        *range,
-                        format!("Duplicate label definition: '{}'", name),
-                    ));
-                }
-            }
+        let mut usages: Vec<LabelUsage> = usages.into_values().collect();
+        for usage in &mut usages {
+            usage.definitions.sort_by(|a, b| (&a.0, a.1.start()).cmp(&(&b.0, b.1.start())));
+            usage.references.sort_by(|a, b| (&a.0, a.1.start()).cmp(&(&b.0, b.1.start())));
         }
+        usages
+    }
 
-        // 3. Check for undefined references
-        for entry in self.indices.iter() {
-            for r in &entry.value().references {
-                if !defs_by_name.contains_key(&r.name) {
-                    diagnostics.push((
-                        entry.key().clone(),
-                        r.range,
-                        format!("Undefined reference: '{}'", r.name),
-                    ));
+    /// Validates labels across the workspace.
+    ///
+    /// Checks for duplicate label definitions and undefined references.
+    pub fn validate_labels(&self) -> Vec<(Url, TextRange, String)> {
+        let mut diagnostics = Vec::new();
+        for usage in self.label_usages() {
+            if usage.definitions.len() > 1 {
+                for (uri, range) in usage.definitions {
+                    diagnostics.push((uri, range, format!("Duplicate label definition: '{}'", usage.name)));
+                }
+            } else if usage.definitions.is_empty() {
+                for (uri, range) in usage.references {
+                    diagnostics.push((uri, range, format!("Undefined reference: '{}'", usage.name)));
                 }
             }
         }
-
         diagnostics
     }
 
     /// Validates usage of deprecated commands.
-    pub fn validate_deprecated(&self) -> Vec<(Url, TextRange, String)> {
+    ///
+    /// `include` receives each scanner marker (e.g. `package:times`) and decides whether it is reported.
+    pub fn validate_deprecated(&self, include: impl Fn(&str) -> bool) -> Vec<(Url, TextRange, String)> {
         let mut diagnostics = Vec::new();
 
         for entry in self.indices.iter() {
-            for (range, cmd) in &entry.value().deprecated_usages {
-                diagnostics.push((
-                    entry.key().clone(),
-                    *range,
-                    format!("Command '{}' is deprecated. Use standard LaTeX2e replacements.", cmd),
-                ));
+            for (range, marker) in &entry.value().deprecated_usages {
+                if !include(marker) {
+                    continue;
+                }
+                if let Some(deprecation) = lookup_marker(marker) {
+                    diagnostics.push((entry.key().clone(), *range, deprecation.message()));
+                }
             }
         }
         diagnostics
     }
 
+    /// Builds the graph of files the project depends on.
+    ///
+    /// Starts at `root` and follows `\input`/`\include` edges; with `None`, every indexed
+    /// TeX file is a starting point. Bibliographies and graphics are leaves. Includes,
+    /// bibliographies and graphics are resolved like LaTeX does, against `root` or, without
+    /// one, against the root document of each starting point.
+    pub fn dependency_graph(&self, root: Option<&Url>) -> DependencyGraph {
+        let mut graph = DependencyGraph::default();
+        // Files to visit, with the document their includes are resolved against.
+        let mut queue: Vec<(Url, Url)> = match root {
+            Some(root) => vec![(root.clone(), root.clone())],
+            None => {
+                let mut all: Vec<Url> = self.indices.iter().map(|e| e.key().clone()).collect();
+                all.sort();
+                all.into_iter().map(|uri| (uri.clone(), self.resolve_root(&uri))).collect()
+            }
+        };
+        queue.reverse();
+        let mut visited = HashSet::new();
+
+        while let Some((uri, root)) = queue.pop() {
+            if !visited.insert(uri.clone()) {
+                continue;
+            }
+            graph.add_node(&uri, NodeKind::Tex, self.indices.contains_key(&uri));
+            let Some(index) = self.indices.get(&uri).map(|i| i.clone()) else {
+                continue;
+            };
+
+            let mut children = Vec::new();
+            for include in &index.includes {
+                if let Some(target) = resolve_include(&root, &include.path) {
+                    graph.add_edge(&uri, &target, EdgeKind::Include);
+                    children.push(target);
+                }
+            }
+            for bib in &index.bibliographies {
+                if let Some(target) = resolve_bib_uri(&root, &bib.path) {
+                    graph.add_node(&target, NodeKind::Bibliography, self.bib_indices.contains_key(&target));
+                    graph.add_edge(&uri, &target, EdgeKind::Bibliography);
+                }
+            }
+            for image in &index.graphics {
+                // Unresolved graphics keep the path as written, so the graph still shows them.
+                let target = crate::links::resolve_graphics(&root, &image.path).or_else(|| root.join(image.path.trim()).ok());
+                if let Some(target) = target {
+                    let exists = target.to_file_path().is_ok_and(|p| p.exists());
+                    graph.add_node(&target, NodeKind::Graphics, exists);
+                    graph.add_edge(&uri, &target, EdgeKind::Graphics);
+                }
+            }
+            queue.extend(children.into_iter().rev().map(|child| (child, root.clone())));
+        }
+        graph
+    }
+
+    /// Builds the transitive `\input`/`\include` tree below `root`.
+    ///
+    /// A file included from several places appears under each parent. A file that
+    /// includes one of its ancestors is marked as a cycle and not expanded further.
+    /// Includes are resolved against `root`'s directory, as LaTeX does.
+    pub fn include_tree(&self, root: &Url) -> IncludeTreeNode {
+        let mut ancestors = Vec::new();
+        self.include_tree_node(root, root, None, &mut ancestors)
+    }
+
+    fn include_tree_node(&self, root: &Url, uri: &Url, path: Option<String>, ancestors: &mut Vec<Url>) -> IncludeTreeNode {
+        let index = self.indices.get(uri).map(|i| i.clone());
+        let mut node = IncludeTreeNode {
+            uri: uri.clone(),
+            path,
+            exists: index.is_some(),
+            cycle: ancestors.contains(uri),
+            children: Vec::new(),
+        };
+        let Some(index) = index.filter(|_| !node.cycle) else {
+            return node;
+        };
+
+        ancestors.push(uri.clone());
+        for include in &index.includes {
+            if let Some(target) = resolve_include(root, &include.path) {
+                node.children.push(self.include_tree_node(root, &target, Some(include.path.clone()), ancestors));
+            }
+        }
+        ancestors.pop();
+        node
+    }
+
     /// Detects inclusion cycles in the workspace.
     ///
     /// Performs a DFS on the inclusion graph to find cycles.
@@ -613,6 +1071,41 @@ impl Workspace {
     }
 }
 
+/// Collects the `.tex` and `.bib` files below `root`, skipping hidden and build directories.
+pub fn collect_project_files(root: &Path) -> Vec<PathBuf> {
+    walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !entry.file_type().is_dir() || !is_ignored_dir(entry.file_name()))
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| has_project_extension(path))
+        .take(MAX_INDEXED_FILES)
+        .collect()
+}
+
+/// Returns `true` if `path` is a file [`collect_project_files`] would pick up below `root`.
+///
+/// The file does not need to exist, so this also works for paths reported as deleted.
+pub fn is_project_file(root: &Path, path: &Path) -> bool {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return false;
+    };
+    has_project_extension(path)
+        && relative
+            .parent()
+            .is_none_or(|dir| !dir.components().any(|c| is_ignored_dir(c.as_os_str())))
+}
+
+fn has_project_extension(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "tex" || ext == "bib")
+}
+
+fn is_ignored_dir(name: &std::ffi::OsStr) -> bool {
+    let name = name.to_string_lossy();
+    name.starts_with('.') || IGNORED_DIRECTORIES.contains(&name.as_ref())
+}
+
 type ScanResult = (
     Vec<IncludeRef>,
     Vec<LabelDef>,
@@ -624,9 +1117,30 @@ type ScanResult = (
     Option<String>, // magic_root
     Vec<(TextRange, String)>, // deprecated_usages
     Vec<EnvDef>, // environments
+    Vec<GraphicsRef>, // graphics
+    (Vec<MacroDef>, Vec<MacroDef>), // command and environment definitions
 );
 
-fn scan_file(text: &str) -> ScanResult {
+/// Commands that define a new command, and those that define an environment.
+const COMMAND_DEFINERS: &[&str] = &["\\newcommand", "\\renewcommand", "\\providecommand", "\\DeclareMathOperator"];
+const ENVIRONMENT_DEFINERS: &[&str] = &["\\newenvironment", "\\renewenvironment"];
+
+/// `.bib` blocks that are not bibliography entries.
+pub const NON_ENTRY_TYPES: &[&str] = &["string", "preamble", "comment"];
+
+/// Fields naming other entries an entry inherits from or groups: BibTeX's `crossref`, and
+/// biblatex's `xref`, `xdata` and the `entryset` of `@set` entries.
+const ENTRY_REFERENCE_FIELDS: &[&str] = &["crossref", "xref", "xdata", "entryset"];
+
+/// Citation commands of natbib and biblatex, and `\nocite`. `\cite` itself is parsed as a
+/// [`SyntaxKind::Citation`] node.
+const CITATION_COMMANDS: &[&str] = &[
+    "\\citep", "\\citet", "\\citealp", "\\citealt", "\\citeauthor", "\\citeyear", "\\Citep", "\\Citet",
+    "\\parencite", "\\Parencite", "\\textcite", "\\Textcite", "\\autocite", "\\Autocite", "\\footcite",
+    "\\smartcite", "\\supercite", "\\fullcite", "\\citetitle", "\\nocite",
+];
+
+fn scan_file(text: &str, root: &SyntaxNode) -> ScanResult {
     // Scan for magic comments in the first 1KB
     let head = if text.len() > 1024 {
         &text[..1024]
@@ -639,8 +1153,12 @@ fn scan_file(text: &str) -> ScanResult {
     let re = Regex::new(r"(?mi)^%\s*!TEX\s+root\s*=\s*(.+)$").unwrap();
     let magic_root = re.captures(head).map(|cap| cap[1].trim().to_string());
 
-    let parse = parse(text);
-    let root = parse.syntax();
+    // Pattern: \documentclass[<main>]{subfiles}
+    let magic_root = magic_root.or_else(|| {
+        let re = Regex::new(r"\\documentclass\s*\[([^\]]+)\]\s*\{subfiles\}").unwrap();
+        re.captures(head).map(|cap| cap[1].trim().to_string())
+    });
+
     let mut includes = Vec::new();
     let mut defs = Vec::new();
     let mut refs = Vec::new();
@@ -649,6 +1167,9 @@ fn scan_file(text: &str) -> ScanResult {
     let mut sections = Vec::new();
     let mut deprecated_usages = Vec::new();
     let mut environments = Vec::new();
+    let mut graphics = Vec::new();
+    let mut command_definitions = Vec::new();
+    let mut environment_definitions = Vec::new();
 
     let mut last_was_dollar = false;
     let mut last_dollar_range: Option<TextRange> = None;
@@ -688,8 +1209,34 @@ fn scan_file(text: &str) -> ScanResult {
                 
                 if element.kind() == SyntaxKind::Command {
                     let text = element.to_string();
-                    let deprecated = ["\\bf", "\\it", "\\sc", "\\rm", "\\sf", "\\tt", "\\sl"];
-                    if deprecated.contains(&text.as_str()) {
+                    if text == "\\includegraphics" {
+                        let argument = element
+                            .as_token()
+                            .and_then(crate::links::argument_group)
+                            .and_then(|group| crate::links::group_content(&group));
+                        if let Some((path, range)) = argument {
+                            graphics.push(GraphicsRef { path, range });
+                        }
+                    } else if CITATION_COMMANDS.contains(&text.as_str()) {
+                        let argument = element
+                            .as_token()
+                            .and_then(crate::links::argument_group)
+                            .and_then(|group| crate::links::group_content(&group));
+                        if let Some((keys, range)) = argument {
+                            for key in keys.split(',').map(str::trim).filter(|key| !key.is_empty()) {
+                                citations.push(CitationRef { key: key.to_string(), range });
+                            }
+                        }
+                    } else if COMMAND_DEFINERS.contains(&text.as_str()) {
+                        if let Some(def) = element.as_token().and_then(scan_definition) {
+                            command_definitions.push(def);
+                        }
+                    } else if ENVIRONMENT_DEFINERS.contains(&text.as_str()) {
+                        if let Some(def) = element.as_token().and_then(scan_definition) {
+                            environment_definitions.push(def);
+                        }
+                    }
+                    if lookup(DeprecationKind::Command, &text).is_some() {
                         // Check if this command is inside a group (e.g., {\bf ...})
                         // by looking at parent context
                         let mut in_group = false;
@@ -776,7 +1323,10 @@ fn scan_file(text: &str) -> ScanResult {
                             }
                         }
                         SyntaxKind::Environment => {
-                            if let Some((name, _range)) = extract_label_data(node) {
+                            if let Some((name, name_range)) = extract_label_data(node) {
+                                if lookup(DeprecationKind::Environment, &name).is_some() {
+                                    deprecated_usages.push((name_range, format!("environment:{}", name)));
+                                }
                                 environments.push(EnvDef { name, range: node.text_range() });
                             }
                         }
@@ -801,8 +1351,7 @@ fn scan_file(text: &str) -> ScanResult {
                 if !trimmed.is_empty() {
                     packages.push(trimmed.to_string());
                     
-                    let forbidden = ["a4wide", "times", "epsfig", "psfig"];
-                    if forbidden.contains(&trimmed) {
+                    if lookup(DeprecationKind::Package, trimmed).is_some() {
                         // Calculate exact range of the package name
                         use ferrotex_syntax::TextSize;
                         let relative_start_in_group = pkg_group_match.as_str().find(trimmed).unwrap_or(0);
@@ -820,7 +1369,56 @@ fn scan_file(text: &str) -> ScanResult {
         }
     }
 
-    (includes, defs, refs, citations, bibs, sections, packages, magic_root, deprecated_usages, environments)
+    (includes, defs, refs, citations, bibs, sections, packages, magic_root, deprecated_usages, environments, graphics, (command_definitions, environment_definitions))
+}
+
+/// Reads the name and parameters following a definition command, e.g.
+/// `\newcommand*{\foo}[2][x]`, `\newcommand\foo` or `\newenvironment{thm}[1]`.
+fn scan_definition(command: &ferrotex_syntax::SyntaxToken) -> Option<MacroDef> {
+    let mut elements = std::iter::successors(command.next_sibling_or_token(), |e| e.next_sibling_or_token())
+        .filter(|e| e.kind() != SyntaxKind::Whitespace)
+        .peekable();
+    if elements.peek().and_then(|e| e.as_token()).is_some_and(|t| t.text() == "*") {
+        elements.next();
+    }
+
+    let (name, range) = match elements.next()? {
+        rowan::NodeOrToken::Token(token) if token.kind() == SyntaxKind::Command => {
+            (token.text().trim_start_matches('\\').to_string(), token.text_range())
+        }
+        rowan::NodeOrToken::Node(group) if group.kind() == SyntaxKind::Group => {
+            let (text, range) = crate::links::group_content(&group)?;
+            (text.trim_start_matches('\\').to_string(), range)
+        }
+        _ => return None,
+    };
+    if name.is_empty() {
+        return None;
+    }
+
+    // Up to two bracketed options: the parameter count and the default of the first one.
+    let mut options = Vec::new();
+    while options.len() < 2 && elements.peek().is_some_and(|e| e.kind() == SyntaxKind::LBracket) {
+        elements.next();
+        let mut option = String::new();
+        for element in elements.by_ref() {
+            if element.kind() == SyntaxKind::RBracket {
+                break;
+            }
+            match element {
+                rowan::NodeOrToken::Token(token) => option.push_str(token.text()),
+                rowan::NodeOrToken::Node(node) => option.push_str(&node.text().to_string()),
+            }
+        }
+        options.push(option);
+    }
+
+    Some(MacroDef {
+        name,
+        parameters: options.first().and_then(|n| n.trim().parse().ok()).unwrap_or(0),
+        default: options.get(1).cloned(),
+        range,
+    })
 }
 
 pub fn extract_group_text(node: &ferrotex_syntax::SyntaxNode) -> Option<String> {
@@ -862,6 +1460,35 @@ pub fn extract_label_data(node: &ferrotex_syntax::SyntaxNode) -> Option<(String,
     Some((trimmed.to_string(), TextRange::at(final_start, final_len)))
 }
 
+/// Resolves an `\input`/`\include` path of any file in the tree below `root`.
+///
+/// LaTeX resolves them relative to the directory it runs in, the root document's, not
+/// relative to the file containing them.
+fn resolve_include(root: &Url, raw_path: &str) -> Option<Url> {
+    resolve_tex_uri(root, raw_path)
+}
+
+/// Normalizes an include path into the file path it names relative to the root document's
+/// directory, the key of [`Workspace::includers`].
+fn include_key(raw_path: &str) -> Option<String> {
+    let base = Url::parse("file:///").ok()?;
+    resolve_include(&base, raw_path).map(|uri| uri.path().trim_start_matches('/').to_string())
+}
+
+/// Resolves a TeX file path relative to `base_uri`, adding the `.tex` extension LaTeX implies.
+fn resolve_tex_uri(base_uri: &Url, raw_path: &str) -> Option<Url> {
+    let mut path = raw_path.trim().trim_matches('"').to_string();
+    if path.is_empty() {
+        return None;
+    }
+
+    if Path::new(&path).extension().is_none() {
+        path.push_str(".tex");
+    }
+
+    base_uri.join(&path).ok()
+}
+
 fn resolve_bib_uri(base_uri: &Url, raw_path: &str) -> Option<Url> {
     let mut path = raw_path.trim().trim_matches('"').to_string();
     if path.is_empty() {
@@ -883,7 +1510,7 @@ mod tests {
     #[test]
     fn test_deprecated_command() {
         let text = r#"\section{Test} {\bf bold} text"#;
-        let result = scan_file(text);
+        let result = scan_file(text, &parse(text).syntax());
         let deprecated = result.8; // deprecated_usages
         assert!(!deprecated.is_empty(), "Should detect deprecated command");
         assert_eq!(deprecated[0].1, "\\bf:group");
@@ -898,7 +1525,7 @@ mod tests {
         $$
         End
         "#;
-        let result = scan_file(text);
+        let result = scan_file(text, &parse(text).syntax());
         let deprecated = result.8;
         assert!(deprecated.iter().any(|d| d.1 == "displaymath"), "Should detect display math block");
     }
@@ -906,12 +1533,23 @@ mod tests {
     #[test]
     fn test_obsolete_package_detection() {
         let text = r#"\usepackage{times, geometry}"#;
-        let result = scan_file(text);
+        let result = scan_file(text, &parse(text).syntax());
         let deprecated = result.8;
         assert!(deprecated.iter().any(|d| d.1 == "package:times"), "Should detect 'times' package");
         assert!(!deprecated.iter().any(|d| d.1 == "package:geometry"), "Should NOT detect 'geometry' package");
     }
 
+    #[test]
+    fn test_deprecated_environment_and_package() {
+        let workspace = Workspace::new();
+        let uri = Url::parse("file:///main.tex").unwrap();
+        workspace.update(&uri, "\\usepackage{subfigure}\n\\begin{eqnarray}x\\end{eqnarray}");
+
+        let messages: Vec<String> = workspace.validate_deprecated(|_| true).into_iter().map(|(_, _, m)| m).collect();
+        assert!(messages.iter().any(|m| m.contains("'subfigure'") && m.contains("subcaption")));
+        assert!(messages.iter().any(|m| m.contains("'eqnarray'") && m.contains("align")));
+    }
+
     #[test]
     fn test_workspace_cross_file_labels() {
         let workspace = Workspace::new();
@@ -927,6 +1565,64 @@ mod tests {
         assert!(labels.contains(&"lbl2".to_string()));
     }
 
+    #[test]
+    fn test_label_usages() {
+        let workspace = Workspace::new();
+        let main = Url::parse("file:///main.tex").unwrap();
+        let sub = Url::parse("file:///sub.tex").unwrap();
+        workspace.update(&main, r"\label{fig}\ref{sec}\ref{missing}\label{twice}");
+        workspace.update(&sub, r"\label{sec}\ref{fig}\ref{fig}\label{twice}");
+
+        let usages = workspace.label_usages();
+        let names: Vec<&str> = usages.iter().map(|usage| usage.name.as_str()).collect();
+        assert_eq!(names, vec!["fig", "missing", "sec", "twice"]);
+        assert_eq!(usages[0].definitions.len(), 1);
+        assert_eq!(usages[0].references.len(), 2);
+        assert!(usages[0].references[0].1.start() < usages[0].references[1].1.start());
+        assert!(usages[1].definitions.is_empty());
+        assert_eq!(usages[3].definitions.iter().map(|(uri, _)| uri).collect::<Vec<_>>(), vec![&main, &sub]);
+
+        let mut messages: Vec<String> = workspace.validate_labels().into_iter().map(|(_, _, message)| message).collect();
+        messages.sort();
+        assert_eq!(
+            messages,
+            vec![
+                "Duplicate label definition: 'twice'",
+                "Duplicate label definition: 'twice'",
+                "Undefined reference: 'missing'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_user_definitions() {
+        let workspace = Workspace::new();
+        let uri = Url::parse("file:///macros.tex").unwrap();
+        workspace.update(
+            &uri,
+            "\\newcommand{\\vect}[1]{\\mathbf{#1}}\n\\newcommand*\\R{\\mathbb{R}}\n\\providecommand{\\norm}[2][2]{#2}\n\\DeclareMathOperator{\\Tr}{Tr}\n\\newenvironment{proofsketch}[1]{}{}",
+        );
+
+        let commands: Vec<(String, u32, Option<String>)> = workspace
+            .get_command_definitions()
+            .into_iter()
+            .map(|d| (d.name, d.parameters, d.default))
+            .collect();
+        assert_eq!(
+            commands,
+            [
+                ("R".to_string(), 0, None),
+                ("Tr".to_string(), 0, None),
+                ("norm".to_string(), 2, Some("2".to_string())),
+                ("vect".to_string(), 1, None),
+            ]
+        );
+        let environments = workspace.get_environment_definitions();
+        assert_eq!(environments.len(), 1);
+        assert_eq!(environments[0].name, "proofsketch");
+        assert_eq!(environments[0].parameters, 1);
+    }
+
     #[test]
     fn test_workspace_cycle_detection() {
         let workspace = Workspace::new();
@@ -941,6 +1637,34 @@ mod tests {
         assert!(!cycles.is_empty(), "Cycle should be detected");
     }
 
+    #[test]
+    fn test_include_tree() {
+        let workspace = Workspace::new();
+        let main = Url::parse("file:///main.tex").unwrap();
+        let intro = Url::parse("file:///chapters/intro.tex").unwrap();
+        workspace.update(&main, "\\input{chapters/intro}\n\\include{missing}");
+        // `figures` is next to main.tex, where LaTeX runs, not next to intro.tex.
+        workspace.update(&intro, "\\input{main}\n\\input{figures}");
+        workspace.update(&Url::parse("file:///chapters/figures.tex").unwrap(), "Figures");
+
+        let tree = workspace.include_tree(&main);
+        assert_eq!(tree.uri, main);
+        assert_eq!(tree.path, None);
+        assert!(!tree.cycle);
+        assert_eq!(tree.children.len(), 2);
+
+        let child = &tree.children[0];
+        assert_eq!(child.uri, intro);
+        assert_eq!(child.path.as_deref(), Some("chapters/intro"));
+        assert_eq!(child.children.len(), 2);
+        assert!(child.children[0].cycle);
+        assert!(child.children[0].children.is_empty());
+        assert_eq!(child.children[1].uri.path(), "/figures.tex");
+        assert!(!child.children[1].exists);
+
+        assert!(!tree.children[1].exists);
+    }
+
     #[test]
     fn test_workspace_bib_indexing() {
         let workspace = Workspace::new();
@@ -952,6 +1676,26 @@ mod tests {
         assert!(!workspace.has_citation_key("key2"));
     }
 
+    #[test]
+    fn test_bib_duplicate_keys() {
+        let workspace = Workspace::new();
+        let main = Url::parse("file:///project/main.tex").unwrap();
+        let refs = Url::parse("file:///project/refs.bib").unwrap();
+        let more = Url::parse("file:///project/more.bib").unwrap();
+        let unused = Url::parse("file:///project/old.bib").unwrap();
+        workspace.update(&main, r"\bibliography{refs,more}");
+        workspace.update_bib(&refs, "@book{knuth, title={A}}\n@book{lamport, title={B}}");
+        workspace.update_bib(&more, "@book{Knuth, title={C}}");
+        workspace.update_bib(&unused, "@book{lamport, title={D}}");
+
+        let duplicates = workspace.validate_bib_duplicates();
+        assert_eq!(duplicates.len(), 2);
+        assert_eq!(duplicates[0].0, more);
+        assert_eq!(duplicates[0].2, "Duplicate citation key 'Knuth' (also defined in refs.bib)");
+        assert_eq!(duplicates[1].0, refs);
+        assert_eq!(duplicates[1].1.start(), 0.into());
+    }
+
     #[test]
     fn test_magic_root_detection() {
         let workspace = Workspace::new();
@@ -962,6 +1706,55 @@ mod tests {
         assert_eq!(workspace.get_explicit_root(&uri), Some("main.tex".to_string()));
     }
 
+    #[test]
+    fn test_resolve_root() {
+        let workspace = Workspace::new();
+        let main = Url::parse("file:///project/main.tex").unwrap();
+        let chapter = Url::parse("file:///project/chapters/intro.tex").unwrap();
+        let section = Url::parse("file:///project/chapters/details.tex").unwrap();
+        let sub = Url::parse("file:///project/appendix/a.tex").unwrap();
+        let magic = Url::parse("file:///project/notes.tex").unwrap();
+        let standalone = Url::parse("file:///project/other.tex").unwrap();
+
+        // Includes resolve against main.tex's directory, wherever they are; `\input{stray}`
+        // names stray.tex next to main.tex, which does not exist, not chapters/stray.tex.
+        let stray = Url::parse("file:///project/chapters/stray.tex").unwrap();
+        workspace.update(&main, r"\documentclass{book}\usepackage{amsmath}\include{chapters/intro}");
+        workspace.update(&chapter, "\\input{chapters/details}\n\\input{stray}");
+        workspace.update(&section, "Details");
+        workspace.update(&stray, "Stray");
+        workspace.update(&sub, "\\documentclass[../main.tex]{subfiles}\n\\begin{document}\\end{document}");
+        workspace.update(&magic, "% !TEX root = chapters/intro\nNotes");
+        workspace.update(&standalone, r"\documentclass{article}");
+
+        assert_eq!(workspace.resolve_root(&section), main);
+        assert_eq!(workspace.resolve_root(&stray), stray);
+        assert_eq!(workspace.resolve_root(&chapter), main);
+        assert_eq!(workspace.resolve_root(&sub), main);
+        assert_eq!(workspace.resolve_root(&magic), main);
+        assert_eq!(workspace.resolve_root(&standalone), standalone);
+        assert_eq!(workspace.get_packages(&section, &main), vec!["amsmath".to_string()]);
+
+        // The include map follows edits and removals.
+        workspace.update(&main, r"\documentclass{book}\include{chapters/details}");
+        assert_eq!(workspace.resolve_root(&chapter), chapter);
+        assert_eq!(workspace.resolve_root(&section), main);
+        workspace.remove(&main);
+        assert_eq!(workspace.resolve_root(&section), section);
+        assert!(workspace.includers.get("chapters/intro.tex").is_none());
+    }
+
+    #[test]
+    fn test_resolve_root_cycle() {
+        let workspace = Workspace::new();
+        let a = Url::parse("file:///a.tex").unwrap();
+        let b = Url::parse("file:///b.tex").unwrap();
+        workspace.update(&a, r"\input{b}");
+        workspace.update(&b, r"\input{a}");
+        let root = workspace.resolve_root(&a);
+        assert!(root == a || root == b);
+    }
+
     #[test]
     fn test_workspace_sections() {
         let workspace = Workspace::new();
@@ -973,4 +1766,126 @@ mod tests {
         assert_eq!(index.sections.len(), 1);
         assert_eq!(index.sections[0].name, "Introduction");
     }
+
+    #[test]
+    fn test_commands_inside_environments() {
+        let workspace = Workspace::new();
+        let uri = Url::parse("file:///main.tex").unwrap();
+        workspace.update(
+            &uri,
+            r"\begin{document}\section*{Intro}\begin{figure}\label{fig}\end{figure}\ref{fig}\cite{a,b}\input{ch1}\end{document}",
+        );
+
+        let index = workspace.indices.get(&uri).unwrap();
+        assert_eq!(index.sections.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), vec!["Intro"]);
+        assert_eq!(index.definitions.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(), vec!["fig"]);
+        assert_eq!(index.references.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(), vec!["fig"]);
+        assert_eq!(index.citations.iter().map(|c| c.key.as_str()).collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(index.includes.iter().map(|i| i.path.as_str()).collect::<Vec<_>>(), vec!["ch1"]);
+        assert_eq!(index.environments.len(), 2);
+    }
+
+    #[test]
+    fn test_index_project_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("chapters")).unwrap();
+        std::fs::create_dir_all(dir.path().join(".git")).unwrap();
+        std::fs::write(dir.path().join("main.tex"), r"\input{chapters/intro}").unwrap();
+        std::fs::write(dir.path().join("chapters/intro.tex"), r"\label{sec:intro}").unwrap();
+        std::fs::write(dir.path().join("refs.bib"), "@book{knuth, title={TeX}}").unwrap();
+        std::fs::write(dir.path().join(".git/ignored.tex"), r"\label{hidden}").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not indexed").unwrap();
+
+        let mut files = collect_project_files(dir.path());
+        files.sort();
+        assert_eq!(files.len(), 3);
+
+        let workspace = Workspace::new();
+        for path in &files {
+            let uri = Url::from_file_path(path).unwrap();
+            assert!(workspace.index_file(&uri, path));
+        }
+        assert_eq!(workspace.get_all_labels(), vec!["sec:intro".to_string()]);
+        assert!(workspace.has_citation_key("knuth"));
+    }
+
+    #[test]
+    fn test_load_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("chapters")).unwrap();
+        std::fs::write(dir.path().join("main.tex"), r"\input{chapters/intro}\include{missing}\bibliography{refs}\input{chapters/end}").unwrap();
+        std::fs::write(dir.path().join("chapters/intro.tex"), r"\label{sec:intro}\cite{knuth}\input{chapters/end}").unwrap();
+        std::fs::write(dir.path().join("chapters/end.tex"), r"\ref{sec:intro}").unwrap();
+        std::fs::write(dir.path().join("refs.bib"), "@book{knuth, title={TeX}}").unwrap();
+        std::fs::write(dir.path().join("unrelated.tex"), r"\label{other}").unwrap();
+
+        let workspace = Workspace::new();
+        let uri = |name: &str| Url::from_file_path(dir.path().join(name)).unwrap();
+        let loaded = workspace.load_tree(&uri("main.tex"));
+        assert_eq!(loaded, vec![uri("main.tex"), uri("chapters/intro.tex"), uri("chapters/end.tex")]);
+        assert_eq!(workspace.get_all_labels(), vec!["sec:intro".to_string()]);
+        assert!(workspace.has_citation_key("knuth"));
+        assert!(workspace.validate_labels().is_empty());
+        assert!(workspace.validate_citations().is_empty());
+        let missing = workspace.validate_includes(&uri("main.tex"));
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].0, uri("main.tex"));
+        assert_eq!(missing[0].2, "Missing included file: 'missing'");
+    }
+
+    #[test]
+    fn test_load_tree_bibliography_in_included_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("chapters")).unwrap();
+        std::fs::write(dir.path().join("main.tex"), r"\input{chapters/a}").unwrap();
+        std::fs::write(dir.path().join("chapters/a.tex"), r"\cite{knuth}\bibliography{refs}").unwrap();
+        std::fs::write(dir.path().join("refs.bib"), "@book{knuth, title={TeX}}").unwrap();
+
+        let workspace = Workspace::new();
+        let uri = |name: &str| Url::from_file_path(dir.path().join(name)).unwrap();
+        workspace.load_tree(&uri("main.tex"));
+        assert_eq!(workspace.get_referenced_bib_uris(), vec![uri("refs.bib")]);
+        assert!(workspace.validate_bibliographies().is_empty());
+        assert!(workspace.validate_citations().is_empty());
+        assert!(workspace.uncited_entries().is_empty());
+    }
+
+    #[test]
+    fn test_uncited_entries() {
+        let workspace = Workspace::new();
+        let main = Url::parse("file:///project/main.tex").unwrap();
+        let refs = Url::parse("file:///project/refs.bib").unwrap();
+        workspace.update(&main, "\\cite{knuth} \\citep[p.~3]{lamport, Dijkstra}\\bibliography{refs}");
+        workspace.update_bib(
+            &refs,
+            "@string{tug = {TUG}}\n@book{knuth, title={TeX}}\n@book{lamport, title={LaTeX}}\n@book{Dijkstra, title={EWD}}\n@book{wirth, title={Pascal}}",
+        );
+        let uncited: Vec<String> = workspace.uncited_entries().into_iter().map(|(_, entry)| entry.key).collect();
+        assert_eq!(uncited, vec!["wirth".to_string()]);
+        assert!(workspace.validate_citations().is_empty());
+
+        workspace.update(&main, "\\cite{paper,set}\\bibliography{refs}");
+        workspace.update_bib(
+            &refs,
+            "@inproceedings{paper, crossref={Proc}}\n@proceedings{proc, xdata={pub}}\n@xdata{pub, publisher={ACM}}\n\
+             @set{set, entryset={a, b}}\n@article{a, title={A}}\n@article{b, title={B}}\n@book{unused, title={U}}",
+        );
+        let uncited: Vec<String> = workspace.uncited_entries().into_iter().map(|(_, entry)| entry.key).collect();
+        assert_eq!(uncited, vec!["unused".to_string()]);
+
+        workspace.update(&main, "\\nocite{*}\\bibliography{refs}");
+        assert!(workspace.uncited_entries().is_empty());
+        assert!(workspace.validate_citations().is_empty());
+    }
+
+    #[test]
+    fn test_is_project_file() {
+        let root = Path::new("/project");
+        assert!(is_project_file(root, Path::new("/project/main.tex")));
+        assert!(is_project_file(root, Path::new("/project/chapters/refs.bib")));
+        assert!(!is_project_file(root, Path::new("/project/main.log")));
+        assert!(!is_project_file(root, Path::new("/project/.git/main.tex")));
+        assert!(!is_project_file(root, Path::new("/project/_minted/out.tex")));
+        assert!(!is_project_file(root, Path::new("/elsewhere/main.tex")));
+    }
 }
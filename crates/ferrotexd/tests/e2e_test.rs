@@ -7,7 +7,7 @@ use tower_lsp::{LspService, Server};
 
 async fn setup_server() -> (BufReader<ReadHalf<DuplexStream>>, WriteHalf<DuplexStream>) {
     let (client_side, server_side) = tokio::io::duplex(1024 * 1024);
-    let (service, socket) = LspService::new(|client| ferrotexd::Backend {
+    let (service, socket) = ferrotexd::Backend::custom_methods(LspService::build(|client| ferrotexd::Backend {
         client,
         documents: std::sync::Arc::new(dashmap::DashMap::new()),
         workspace: std::sync::Arc::new(ferrotexd::workspace::Workspace::new()),
@@ -15,7 +15,15 @@ async fn setup_server() -> (BufReader<ReadHalf<DuplexStream>>, WriteHalf<DuplexS
         syntax_diagnostics: std::sync::Arc::new(dashmap::DashMap::new()),
         package_manager: std::sync::Arc::new(std::sync::Mutex::new(ferrotex_core::package_manager::PackageManager::new())),
         package_index: std::sync::Arc::new(std::sync::Mutex::new(None)),
-    });
+        settings: std::sync::Arc::new(std::sync::Mutex::new(ferrotexd::settings::Settings::default())),
+        parse_cache: std::sync::Arc::new(ferrotexd::parse_cache::ParseCache::new()),
+        validations: std::sync::Arc::new(dashmap::DashMap::new()),
+        grammar: std::sync::Arc::new(ferrotexd::diagnostics::grammar::GrammarChecker::new()),
+        build_diagnostics: std::sync::Arc::new(dashmap::DashMap::new()),
+        builds: std::sync::Arc::new(dashmap::DashMap::new()),
+        inverse_search: std::sync::Arc::new(tokio::sync::Mutex::new(None)),
+    }))
+    .finish();
     
     let (server_read, server_write) = tokio::io::split(server_side);
     tokio::spawn(Server::new(server_read, server_write, socket).serve(service));
@@ -87,7 +95,7 @@ async fn test_document_symbol_flow() -> anyhow::Result<()> {
     let doc_uri = Url::from_file_path(temp_path.join("main.tex")).unwrap();
     send_msg(&mut writer, &json!({
         "jsonrpc": "2.0", "method": "textDocument/didOpen",
-        "params": { "textDocument": { "uri": doc_uri.clone(), "languageId": "latex", "version": 1, "text": "\\begin{document} \\end{document}" } }
+        "params": { "textDocument": { "uri": doc_uri.clone(), "languageId": "latex", "version": 1, "text": "\\begin{document}\n\\section{Intro}\n\\label{sec:intro}\n\\end{document}" } }
     })).await?;
 
     send_msg(&mut writer, &json!({
@@ -101,7 +109,62 @@ async fn test_document_symbol_flow() -> anyhow::Result<()> {
             break msg["result"].as_array().unwrap().clone();
         }
     };
-    assert!(!syms.is_empty());
+    assert_eq!(syms.len(), 1);
+    assert_eq!(syms[0]["name"], "document");
+    let section = &syms[0]["children"][0];
+    assert_eq!(section["name"], "Intro");
+    assert_eq!(section["children"][0]["name"], "sec:intro");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_structural_commands_folding_flow() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let temp_path = temp_dir.path().canonicalize()?;
+    let (mut reader, mut writer) = setup_server().await;
+
+    send_msg(&mut writer, &json!({
+        "jsonrpc": "2.0", "id": 1, "method": "initialize",
+        "params": {
+            "capabilities": {},
+            "rootUri": Url::from_directory_path(&temp_path).unwrap(),
+            "initializationOptions": { "outline": { "structuralCommands": [{ "name": "lecture", "level": 1, "argument": 2 }] } }
+        }
+    })).await?;
+    let init = read_msg(&mut reader).await?;
+    assert_eq!(init["result"]["capabilities"]["foldingRangeProvider"], true);
+    send_msg(&mut writer, &json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} })).await?;
+
+    let doc_uri = Url::from_file_path(temp_path.join("notes.tex")).unwrap();
+    send_msg(&mut writer, &json!({
+        "jsonrpc": "2.0", "method": "textDocument/didOpen",
+        "params": { "textDocument": { "uri": doc_uri.clone(), "languageId": "latex", "version": 1, "text": "\\lecture{Mon}{Limits}\n\\section{Definition}\ntext\n\\lecture{Wed}{Series}\n" } }
+    })).await?;
+
+    send_msg(&mut writer, &json!({
+        "jsonrpc": "2.0", "id": 2, "method": "textDocument/foldingRange",
+        "params": { "textDocument": { "uri": doc_uri.clone() } }
+    })).await?;
+    send_msg(&mut writer, &json!({
+        "jsonrpc": "2.0", "id": 3, "method": "textDocument/documentSymbol",
+        "params": { "textDocument": { "uri": doc_uri } }
+    })).await?;
+
+    let (mut folds, mut syms) = (None, None);
+    while folds.is_none() || syms.is_none() {
+        let msg = read_msg(&mut reader).await?;
+        if msg.get("id") == Some(&json!(2)) {
+            folds = Some(msg["result"].as_array().unwrap().clone());
+        } else if msg.get("id") == Some(&json!(3)) {
+            syms = Some(msg["result"].as_array().unwrap().clone());
+        }
+    }
+    let syms = syms.unwrap();
+    assert_eq!(syms.len(), 2);
+    assert_eq!(syms[0]["name"], "Limits");
+    assert_eq!(syms[0]["children"][0]["name"], "Definition");
+    let folds: Vec<_> = folds.unwrap().iter().map(|f| (f["startLine"].clone(), f["endLine"].clone())).collect();
+    assert_eq!(folds, [(json!(0), json!(2)), (json!(1), json!(2))]);
     Ok(())
 }
 
@@ -127,11 +190,11 @@ async fn test_syntax_diagnostics_flow() -> anyhow::Result<()> {
     let mut found = false;
     for _ in 0..10 {
         let msg = read_msg(&mut reader).await?;
-        if msg["method"] == "textDocument/publishDiagnostics" && msg["params"]["uri"] == doc_uri.as_str() {
-            if msg["params"]["diagnostics"].as_array().unwrap().iter().any(|d| d["message"].as_str().unwrap().contains("Expected '}'")) {
-                found = true;
-                break;
-            }
+        if msg["method"] == "textDocument/publishDiagnostics" && msg["params"]["uri"] == doc_uri.as_str()
+            && msg["params"]["diagnostics"].as_array().unwrap().iter().any(|d| d["message"].as_str().unwrap().contains("Expected '}'"))
+        {
+            found = true;
+            break;
         }
     }
     assert!(found);
@@ -172,6 +235,267 @@ async fn test_label_features_flow() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_document_link_flow() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let temp_path = temp_dir.path().canonicalize()?;
+    let (mut reader, mut writer) = setup_server().await;
+
+    send_msg(&mut writer, &json!({
+        "jsonrpc": "2.0", "id": 1, "method": "initialize",
+        "params": { "capabilities": {}, "rootUri": Url::from_directory_path(&temp_path).unwrap() }
+    })).await?;
+    let init = read_msg(&mut reader).await?;
+    assert!(init["result"]["capabilities"]["documentLinkProvider"].is_object());
+    send_msg(&mut writer, &json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} })).await?;
+
+    let doc_uri = Url::from_file_path(temp_path.join("main.tex")).unwrap();
+    send_msg(&mut writer, &json!({
+        "jsonrpc": "2.0", "method": "textDocument/didOpen",
+        "params": { "textDocument": { "uri": doc_uri.clone(), "languageId": "latex", "version": 1, "text": "\\input{intro}\n\\bibliography{refs}" } }
+    })).await?;
+
+    send_msg(&mut writer, &json!({
+        "jsonrpc": "2.0", "id": 2, "method": "textDocument/documentLink",
+        "params": { "textDocument": { "uri": doc_uri } }
+    })).await?;
+
+    let links = loop {
+        let msg = read_msg(&mut reader).await?;
+        if msg.get("id") == Some(&json!(2)) {
+            break msg["result"].as_array().unwrap().clone();
+        }
+    };
+    let intro = Url::from_file_path(temp_path.join("intro.tex")).unwrap();
+    assert_eq!(links.len(), 2);
+    assert_eq!(links[0]["target"].as_str(), Some(intro.as_str()));
+    assert_eq!(links[0]["range"]["start"], json!({ "line": 0, "character": 7 }));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_include_tree_request() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let temp_path = temp_dir.path().canonicalize()?;
+    let (mut reader, mut writer) = setup_server().await;
+
+    send_msg(&mut writer, &json!({
+        "jsonrpc": "2.0", "id": 1, "method": "initialize",
+        "params": { "capabilities": {}, "rootUri": Url::from_directory_path(&temp_path).unwrap() }
+    })).await?;
+    read_msg(&mut reader).await?;
+    send_msg(&mut writer, &json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} })).await?;
+
+    let main_uri = Url::from_file_path(temp_path.join("main.tex")).unwrap();
+    let chapter_uri = Url::from_file_path(temp_path.join("chapter.tex")).unwrap();
+    for (uri, text) in [(&main_uri, "\\documentclass{article}\n\\input{chapter}"), (&chapter_uri, "\\input{main}")] {
+        send_msg(&mut writer, &json!({
+            "jsonrpc": "2.0", "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": uri, "languageId": "latex", "version": 1, "text": text } }
+        })).await?;
+    }
+    sleep(Duration::from_millis(500)).await;
+
+    send_msg(&mut writer, &json!({
+        "jsonrpc": "2.0", "id": 2, "method": "ferrotex/includeTree",
+        "params": { "textDocument": { "uri": main_uri } }
+    })).await?;
+
+    let tree = loop {
+        let msg = read_msg(&mut reader).await?;
+        if msg.get("id") == Some(&json!(2)) {
+            break msg["result"].clone();
+        }
+    };
+    assert_eq!(tree["uri"].as_str(), Some(main_uri.as_str()));
+    let chapter = &tree["children"][0];
+    assert_eq!(chapter["uri"].as_str(), Some(chapter_uri.as_str()));
+    assert_eq!(chapter["path"], json!("chapter"));
+    assert_eq!(chapter["children"][0]["cycle"], json!(true));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_indexing_status_notifications() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let temp_path = temp_dir.path().canonicalize()?;
+    tokio::fs::write(temp_path.join("main.tex"), "\\section{Intro}\\label{sec:intro}").await?;
+    let (mut reader, mut writer) = setup_server().await;
+
+    send_msg(&mut writer, &json!({
+        "jsonrpc": "2.0", "id": 1, "method": "initialize",
+        "params": { "capabilities": {}, "rootUri": Url::from_directory_path(&temp_path).unwrap() }
+    })).await?;
+    read_msg(&mut reader).await?;
+    send_msg(&mut writer, &json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} })).await?;
+
+    let statuses = timeout(Duration::from_secs(10), async {
+        let mut statuses = Vec::new();
+        loop {
+            let msg = read_msg(&mut reader).await?;
+            if msg.get("method").and_then(|m| m.as_str()) == Some("$/ferrotex/status") {
+                let status = msg["params"].clone();
+                let idle = status["state"] == "idle";
+                statuses.push(status);
+                if idle {
+                    return anyhow::Ok(statuses);
+                }
+            } else if let Some(id) = msg.get("id").filter(|_| msg.get("method").is_some()) {
+                // Acknowledge server requests such as window/workDoneProgress/create.
+                send_msg(&mut writer, &json!({ "jsonrpc": "2.0", "id": id, "result": null })).await?;
+            }
+        }
+    })
+    .await??;
+
+    assert_eq!(statuses[0], json!({ "state": "indexing", "done": 0, "total": 1 }));
+    assert!(statuses.contains(&json!({ "state": "indexing", "done": 1, "total": 1 })));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_position_encoding_negotiation() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let temp_path = temp_dir.path().canonicalize()?;
+    let (mut reader, mut writer) = setup_server().await;
+
+    send_msg(&mut writer, &json!({
+        "jsonrpc": "2.0", "id": 1, "method": "initialize",
+        "params": {
+            "capabilities": { "general": { "positionEncodings": ["utf-32", "utf-16"] } },
+            "rootUri": Url::from_directory_path(&temp_path).unwrap()
+        }
+    })).await?;
+    let init = read_msg(&mut reader).await?;
+    assert_eq!(init["result"]["capabilities"]["positionEncoding"], json!("utf-32"));
+    send_msg(&mut writer, &json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} })).await?;
+
+    // '𝔸' is four bytes and two UTF-16 units, but a single UTF-32 unit.
+    let doc_uri = Url::from_file_path(temp_path.join("main.tex")).unwrap();
+    send_msg(&mut writer, &json!({
+        "jsonrpc": "2.0", "method": "textDocument/didOpen",
+        "params": { "textDocument": { "uri": doc_uri.clone(), "languageId": "latex", "version": 1, "text": "\u{1d538} \\input{intro}" } }
+    })).await?;
+    send_msg(&mut writer, &json!({
+        "jsonrpc": "2.0", "id": 2, "method": "textDocument/documentLink",
+        "params": { "textDocument": { "uri": doc_uri } }
+    })).await?;
+
+    let links = loop {
+        let msg = read_msg(&mut reader).await?;
+        if msg.get("id") == Some(&json!(2)) {
+            break msg["result"].as_array().unwrap().clone();
+        }
+    };
+    assert_eq!(links[0]["range"]["start"], json!({ "line": 0, "character": 9 }));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_extract_to_file_flow() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let temp_path = temp_dir.path().canonicalize()?;
+    let (mut reader, mut writer) = setup_server().await;
+
+    send_msg(&mut writer, &json!({
+        "jsonrpc": "2.0", "id": 1, "method": "initialize",
+        "params": { "capabilities": {}, "rootUri": Url::from_directory_path(&temp_path).unwrap() }
+    })).await?;
+    read_msg(&mut reader).await?;
+    send_msg(&mut writer, &json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} })).await?;
+
+    let doc_uri = Url::from_file_path(temp_path.join("main.tex")).unwrap();
+    send_msg(&mut writer, &json!({
+        "jsonrpc": "2.0", "method": "textDocument/didOpen",
+        "params": { "textDocument": { "uri": doc_uri.clone(), "languageId": "latex", "version": 1,
+            "text": "\\begin{document}\n\\section{Related Work}\nText.\n\\end{document}\n" } }
+    })).await?;
+
+    let selection = json!({ "start": { "line": 1, "character": 0 }, "end": { "line": 3, "character": 0 } });
+    send_msg(&mut writer, &json!({
+        "jsonrpc": "2.0", "id": 2, "method": "textDocument/codeAction",
+        "params": { "textDocument": { "uri": doc_uri }, "range": selection, "context": { "diagnostics": [] } }
+    })).await?;
+    let command = loop {
+        let msg = read_msg(&mut reader).await?;
+        if msg.get("id") == Some(&json!(2)) {
+            let actions = msg["result"].as_array().unwrap().clone();
+            let action = actions.iter().find(|a| a["kind"] == "refactor.extract").expect("extract action");
+            break action["command"].clone();
+        }
+    };
+
+    send_msg(&mut writer, &json!({
+        "jsonrpc": "2.0", "id": 3, "method": "workspace/executeCommand",
+        "params": { "command": command["command"], "arguments": command["arguments"] }
+    })).await?;
+    let edit = loop {
+        let msg = read_msg(&mut reader).await?;
+        if msg.get("method").and_then(|m| m.as_str()) == Some("workspace/applyEdit") {
+            send_msg(&mut writer, &json!({ "jsonrpc": "2.0", "id": msg["id"], "result": { "applied": true } })).await?;
+            break msg["params"]["edit"].clone();
+        }
+    };
+
+    let target = Url::from_file_path(temp_path.join("related-work.tex")).unwrap();
+    let changes = edit["documentChanges"].as_array().unwrap();
+    assert_eq!(changes[0]["kind"], "create");
+    assert_eq!(changes[0]["uri"].as_str(), Some(target.as_str()));
+    assert_eq!(changes[1]["edits"][0]["newText"], "\\section{Related Work}\nText.\n");
+    assert_eq!(changes[2]["edits"][0]["newText"], "\\input{related-work}\n");
+    assert_eq!(changes[2]["textDocument"]["version"], 1);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_inverse_search_socket() -> anyhow::Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let temp_path = temp_dir.path().canonicalize()?;
+    let socket_path = temp_path.join("inverse.sock");
+    let (mut reader, mut writer) = setup_server().await;
+
+    send_msg(&mut writer, &json!({
+        "jsonrpc": "2.0", "id": 1, "method": "initialize",
+        "params": {
+            "capabilities": {},
+            "rootUri": Url::from_directory_path(&temp_path).unwrap(),
+            "initializationOptions": { "inverseSearch": { "enabled": true, "socketPath": socket_path } }
+        }
+    })).await?;
+    read_msg(&mut reader).await?;
+    send_msg(&mut writer, &json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} })).await?;
+
+    let stream = timeout(Duration::from_secs(10), async {
+        loop {
+            match tokio::net::UnixStream::connect(&socket_path).await {
+                Ok(stream) => return stream,
+                Err(_) => sleep(Duration::from_millis(50)).await,
+            }
+        }
+    })
+    .await?;
+    let (socket_read, mut socket_write) = tokio::io::split(stream);
+    let tex_path = temp_path.join("chapter.tex");
+    socket_write.write_all(format!("{}:3\nnot a location\n", tex_path.display()).as_bytes()).await?;
+
+    let params = loop {
+        let msg = read_msg(&mut reader).await?;
+        if msg.get("method").and_then(|m| m.as_str()) == Some("window/showDocument") {
+            send_msg(&mut writer, &json!({ "jsonrpc": "2.0", "id": msg["id"], "result": { "success": true } })).await?;
+            break msg["params"].clone();
+        }
+    };
+    assert_eq!(params["uri"].as_str(), Some(Url::from_file_path(&tex_path).unwrap().as_str()));
+    assert_eq!(params["selection"]["start"], json!({ "line": 2, "character": 0 }));
+
+    let mut replies = BufReader::new(socket_read).lines();
+    assert_eq!(replies.next_line().await?.as_deref(), Some("ok"));
+    let error = replies.next_line().await?.unwrap_or_default();
+    assert!(error.starts_with("error: expected `file:line`"), "{}", error);
+    Ok(())
+}
+
 async fn send_msg<W: AsyncWriteExt + Unpin>(writer: &mut W, msg: &serde_json::Value) -> anyhow::Result<()> {
     let s = msg.to_string();
     writer.write_all(format!("Content-Length: {}\r\n\r\n{}", s.len(), s).as_bytes()).await?;
@@ -70,8 +70,11 @@ export function activate(context: vscode.ExtensionContext) {
     documentSelector: [
       { scheme: "file", language: "latex" },
       { scheme: "file", language: "tex" },
+      { scheme: "file", language: "bibtex" },
     ],
+    initializationOptions: vscode.workspace.getConfiguration("ferrotex"),
     synchronize: {
+      configurationSection: "ferrotex",
       fileEvents: vscode.workspace.createFileSystemWatcher("**/.clientrc"),
     },
   };
@@ -307,6 +310,9 @@ export function activate(context: vscode.ExtensionContext) {
   const outputChannel = vscode.window.createOutputChannel("FerroTeX Build");
   context.subscriptions.push(outputChannel);
 
+  const statusBar = vscode.window.createStatusBarItem(vscode.StatusBarAlignment.Left);
+  context.subscriptions.push(statusBar);
+
   // Register notification handler after client creation
   // We need to wait for client to be ready, or just register it.
   // Note: v8+ handling might differ, but onNotification is standard
@@ -319,6 +325,9 @@ export function activate(context: vscode.ExtensionContext) {
       client.onNotification("$/ferrotex/log", (params: any) => {
         outputChannel.append(params.message);
       });
+      client.onNotification("$/ferrotex/status", (status: any) => {
+        updateStatusBar(statusBar, status);
+      });
     })
     .catch((e) => {
       console.error("[FerroTeX] Client start failed:", e);
@@ -326,6 +335,29 @@ export function activate(context: vscode.ExtensionContext) {
     });
 }
 
+/** Renders a `$/ferrotex/status` notification in the status bar. */
+function updateStatusBar(item: vscode.StatusBarItem, status: any) {
+  item.tooltip = undefined;
+  item.backgroundColor = undefined;
+  switch (status.state) {
+    case "indexing":
+      item.text = `$(sync~spin) FerroTeX: indexing ${status.done}/${status.total}`;
+      break;
+    case "building":
+      item.text = `$(sync~spin) FerroTeX: building${status.step ? ` (${status.step})` : ""}`;
+      break;
+    case "failed":
+      item.text = `$(error) FerroTeX: build failed`;
+      item.tooltip = status.message;
+      item.backgroundColor = new vscode.ThemeColor("statusBarItem.errorBackground");
+      break;
+    default:
+      item.hide();
+      return;
+  }
+  item.show();
+}
+
 export function deactivate(): Thenable<void> | undefined {
   if (!client) {
     return undefined;
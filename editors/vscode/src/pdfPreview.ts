@@ -56,10 +56,11 @@ export class PdfPreviewProvider implements vscode.CustomReadonlyEditorProvider {
         })) as any;
 
         if (result) {
-          const fileUri = vscode.Uri.file(result.file);
+          // The server responds with an LSP Location.
+          const fileUri = vscode.Uri.parse(result.uri);
           const doc = await vscode.workspace.openTextDocument(fileUri);
           const editor = await vscode.window.showTextDocument(doc, vscode.ViewColumn.One);
-          const pos = new vscode.Position(result.line, 0);
+          const pos = new vscode.Position(result.range.start.line, result.range.start.character);
           editor.selection = new vscode.Selection(pos, pos);
           editor.revealRange(new vscode.Range(pos, pos), vscode.TextEditorRevealType.InCenter);
         }